@@ -1,45 +1,54 @@
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+// Its SPIR-V reflection is for the shader layout test.
+#[allow(dead_code)]
+#[path = "src/vulkan_app/glsl.rs"]
+mod glsl;
+
+const SHADER_DIR: &str = "src/shaders";
 
 fn main() -> Result<(), Box<dyn Error>> {
-    println!("cargo:rerun-if-changed=src/shaders");
+    println!("cargo:rerun-if-changed={}", SHADER_DIR);
 
     let out_dir = PathBuf::from(std::env::var("OUT_DIR")?);
-    let mut compiler = shaderc::Compiler::new().ok_or("shaderc not found!")?;
-
-    for entry in std::fs::read_dir("src/shaders")? {
-        let entry = entry?;
-        let in_path = entry.path();
-
-        if in_path.is_file() {
-            if let Some(ext) = in_path.extension().and_then(|s| s.to_str()) {
-                if ext == "vert" || ext == "frag" {
-                    let source = std::fs::read_to_string(&in_path)?;
-                    let kind = if ext == "vert" {
-                        shaderc::ShaderKind::Vertex
-                    } else {
-                        shaderc::ShaderKind::Fragment
-                    };
-
-                    let compiled = compiler.compile_into_spirv(
-                        &source,
-                        kind,
-                        in_path.to_str().unwrap(),
-                        "main",
-                        None,
-                    )?;
-
-                    let file_name = in_path.file_name().unwrap().to_str().unwrap();
-                    let out_file_name = format!("{}.spv", file_name);
-                    let out_path = out_dir.join(&out_file_name);
-
-                    std::fs::write(&out_path, compiled.as_binary_u8())?;
-
-                    let env_var_name = format!("{}_SHADER_PATH", ext.to_uppercase());
-                    println!("cargo:rustc-env={}={}", env_var_name, out_path.to_str().unwrap());
-                }
+    let compiler = shaderc::Compiler::new().ok_or("shaderc not found!")?;
+    let shader_dir = Path::new(SHADER_DIR);
+
+    for (in_path, define) in glsl::permutations(shader_dir)? {
+        let file_name = in_path.file_name().unwrap().to_str().unwrap();
+        let stem = in_path.file_stem().unwrap().to_str().unwrap();
+        let ext = in_path.extension().unwrap().to_str().unwrap();
+        let compiled = match glsl::compile(&compiler, shader_dir, &in_path, define) {
+            Ok(compiled) => compiled,
+            Err(diagnostics) => {
+                // Printed as is: the error's Debug form would
+                // escape the newlines.
+                eprintln!("{}", diagnostics);
+                return Err(format!("{} failed to compile", file_name).into());
             }
-        }
+        };
+
+        // shader.vert -> SHADER_VERT_SHADER_PATH, graph.frag -> GRAPH_FRAG_SHADER_PATH,
+        // and shader.vert with DRAW_PUSH_FIELDS=0 ->
+        // SHADER_VERT_DRAW_PUSH_FIELDS_0_SHADER_PATH.
+        let suffix = define
+            .map(|(name, value)| format!("_{}_{}", name, value))
+            .unwrap_or_default();
+        let out_path = out_dir.join(format!("{}{}.spv", file_name, suffix));
+        std::fs::write(&out_path, compiled)?;
+
+        let env_var_name = format!(
+            "{}_{}{}_SHADER_PATH",
+            stem.to_uppercase(),
+            ext.to_uppercase(),
+            suffix
+        );
+        println!(
+            "cargo:rustc-env={}={}",
+            env_var_name,
+            out_path.to_str().unwrap()
+        );
     }
 
     Ok(())
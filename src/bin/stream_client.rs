@@ -0,0 +1,52 @@
+//! Example client for `--serve`: writes every received frame into a
+//! directory and forwards lines typed on stdin to the server as commands.
+//!
+//! Usage: stream_client <host:port> <output dir> [frame count]
+
+#[allow(dead_code)]
+#[path = "../vulkan_app/protocol.rs"]
+mod protocol;
+
+use std::io::{BufRead, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+
+use protocol::{EncodedFrame, FrameFormat};
+
+fn main() -> std::io::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 3 {
+        eprintln!("usage: {} <host:port> <output dir> [frame count]", args[0]);
+        std::process::exit(2);
+    }
+    let output_dir = PathBuf::from(&args[2]);
+    let frame_count: Option<u64> = args.get(3).map(|count| count.parse().unwrap());
+    std::fs::create_dir_all(&output_dir)?;
+
+    let mut stream = TcpStream::connect(&args[1])?;
+    let mut commands = stream.try_clone()?;
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if writeln!(commands, "{}", line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut received = 0u64;
+    while frame_count.is_none_or(|count| received < count) {
+        let frame = EncodedFrame::read_from(&mut stream)?;
+        let extension = match frame.format {
+            FrameFormat::Rgba => "rgba",
+            FrameFormat::Png => "png",
+        };
+        let path = output_dir.join(format!("frame-{:06}.{}", received, extension));
+        std::fs::write(&path, &frame.payload)?;
+        println!("{} ({}x{})", path.display(), frame.width, frame.height);
+        received += 1;
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use gilrs::{Axis, Button, Event, EventType, GamepadId, Gilrs};
+use log::{info, warn};
+use std::time::Instant;
+
+use rust_vulkan::vulkan_app::{VulkanApp, GAMEPAD_DEAD_ZONE, GAMEPAD_MAX_ZOOM, GAMEPAD_TURN_RATE};
+
+/// Steers the cube from a game controller: the left stick turns it and the
+/// right trigger zooms the camera. Controllers can come and go at any time;
+/// the first one connected steers, and the next takes over if it goes away.
+/// Until the stick first moves, the cube keeps spinning on its own.
+pub struct GamepadInput {
+    gilrs: Gilrs,
+    active: Option<GamepadId>,
+    // Accumulated from the stick, in radians.
+    yaw: f32,
+    pitch: f32,
+    steering: bool,
+    last_poll: Instant,
+}
+
+impl GamepadInput {
+    /// `None` if the platform's controller API isn't available.
+    pub fn new() -> Option<Self> {
+        let gilrs = Gilrs::new()
+            .map_err(|error| warn!("Gamepad input is unavailable: {}", error))
+            .ok()?;
+        let active = gilrs.gamepads().map(|(id, _)| id).next();
+        if let Some(id) = active {
+            info!("Steering with {}", gilrs.gamepad(id).name());
+        }
+        Some(Self {
+            gilrs,
+            active,
+            yaw: 0.0,
+            pitch: 0.0,
+            steering: false,
+            last_poll: Instant::now(),
+        })
+    }
+
+    /// With a controller connected the event loop has to keep polling, even
+    /// while the renderer is idle.
+    pub fn is_connected(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Handles connections since the last call, then applies the active
+    /// controller's stick and trigger to `app`. Called once per frame.
+    pub fn poll(&mut self, app: &mut VulkanApp) {
+        while let Some(Event { id, event, .. }) = self.gilrs.next_event() {
+            match event {
+                EventType::Connected if self.active.is_none() => {
+                    info!("Steering with {}", self.gilrs.gamepad(id).name());
+                    self.active = Some(id);
+                }
+                EventType::Disconnected if self.active == Some(id) => {
+                    self.active = self
+                        .gilrs
+                        .gamepads()
+                        .map(|(id, _)| id)
+                        .find(|&other| other != id);
+                    match self.active {
+                        Some(next) => info!("Steering with {}", self.gilrs.gamepad(next).name()),
+                        None => info!("Gamepad disconnected"),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let now = Instant::now();
+        let elapsed = (now - self.last_poll).as_secs_f32();
+        self.last_poll = now;
+        let Some(id) = self.active else {
+            return;
+        };
+        let gamepad = self.gilrs.gamepad(id);
+        let (x, y) = dead_zone(
+            gamepad.value(Axis::LeftStickX),
+            gamepad.value(Axis::LeftStickY),
+        );
+        let trigger = gamepad
+            .button_data(Button::RightTrigger2)
+            .map_or(0.0, |data| data.value());
+
+        if x != 0.0 || y != 0.0 {
+            self.steering = true;
+            self.yaw += x * GAMEPAD_TURN_RATE * elapsed;
+            self.pitch = (self.pitch + y * GAMEPAD_TURN_RATE * elapsed)
+                .clamp(-std::f32::consts::FRAC_PI_2, std::f32::consts::FRAC_PI_2);
+        }
+        if self.steering {
+            app.set_model_rotation(self.yaw, self.pitch);
+        }
+        let (trigger, _) = dead_zone(trigger, 0.0);
+        app.set_camera_zoom(1.0 + trigger * (GAMEPAD_MAX_ZOOM - 1.0));
+    }
+}
+
+/// Zero within GAMEPAD_DEAD_ZONE of centre, measured on the stick's radius
+/// rather than per axis so diagonals aren't favoured, and rescaled beyond it
+/// so the output still starts at 0 and reaches full tilt.
+fn dead_zone(x: f32, y: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude <= GAMEPAD_DEAD_ZONE {
+        return (0.0, 0.0);
+    }
+    let scale = ((magnitude - GAMEPAD_DEAD_ZONE) / (1.0 - GAMEPAD_DEAD_ZONE)).min(1.0) / magnitude;
+    (x * scale, y * scale)
+}
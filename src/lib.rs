@@ -0,0 +1,4 @@
+//! The renderer behind the `RustVulkan` binary, as a library so the
+//! integration tests can drive it too.
+
+pub mod vulkan_app;
@@ -1,38 +1,1469 @@
-mod vulkan_app;
-use vulkan_app::{VulkanApp, HEIGHT, WIDTH};
-use winit::event::{Event, WindowEvent};
+#[cfg(feature = "gamepad")]
+mod input;
+use ash::vk;
+use log::{error, info, warn};
+use rust_vulkan::vulkan_app::{
+    window_icon, AppConfig, BackendPreference, Command, FrameServer, FullscreenMode, JournalEvent,
+    OrbitDrag, RecreateReason, RendererConfig, ResourceClass, ServeConfig, Settings, SettingsFile,
+    StartupDiagnostics, VideoModeRequest, VulkanApp, VulkanError, WindowSettings,
+    FPS_TITLE_INTERVAL_MS, GPU_HANG_EXIT_CODE, HEADLESS_OUTPUT_PATH, HEIGHT, IDLE_HEARTBEAT_MS,
+    MEMORY_BENCHMARK_FRAMES, MEMORY_BENCHMARK_WARMUP_FRAMES, RECORD_BENCHMARK_CUBES,
+    RECORD_BENCHMARK_FRAMES, RENDER_SCALE_LADDER, RESIZE_STORM_SIZES, SELF_TEST_FAILURE_EXIT_CODE,
+    SELF_TEST_SIZE, SHUTDOWN_STRESS_ITERATIONS, USAGE, USAGE_EXIT_CODE, VULKAN_ERROR_EXIT_CODE,
+    WIDTH,
+};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use winit::dpi::{LogicalSize, PhysicalPosition, PhysicalSize};
+use winit::event::{
+    ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+    VirtualKeyCode, WindowEvent,
+};
 use winit::event_loop::{ControlFlow, EventLoop};
-use winit::window::WindowBuilder;
+use winit::window::{Fullscreen, Window, WindowBuilder};
 
-fn main() {
-    let event_loop = EventLoop::new();
+/// State of `--serve`: the socket and when the next frame is due.
+struct Serving {
+    config: ServeConfig,
+    server: FrameServer,
+    next_frame: Instant,
+}
+
+impl Serving {
+    /// Applies client commands, then, while anyone is watching, renders and
+    /// streams one frame.
+    fn frame(&mut self, app: &mut VulkanApp) -> Result<(), VulkanError> {
+        for command in self.server.commands() {
+            match command {
+                Command::Orbit { yaw, pitch } => app.orbit_camera(yaw, pitch),
+                Command::Pause => app.toggle_animation_pause(),
+                Command::Screenshot => app.request_screenshot(),
+            }
+        }
+
+        if self.server.has_clients() {
+            let frame = app.render_to_image(app.animation_time())?;
+            self.server.offer(frame.encode(self.config.format));
+        }
+        // A frame that overran its slot delays the next one instead of
+        // queueing catch-up frames.
+        self.next_frame = (self.next_frame + self.config.frame_interval()).max(Instant::now());
+        Ok(())
+    }
+}
+
+/// `--shutdown-stress`: creates the renderer, draws one frame and destroys
+/// it, over and over, stopping at the first validation error or leak.
+fn shutdown_stress(event_loop: &EventLoop<()>) -> bool {
+    for iteration in 0..SHUTDOWN_STRESS_ITERATIONS {
+        let window = WindowBuilder::new()
+            .with_inner_size(winit::dpi::LogicalSize::new(WIDTH, HEIGHT))
+            .build(event_loop)
+            .unwrap();
+        let mut app = match VulkanApp::new(&window, RendererConfig::default()) {
+            Ok(app) => app,
+            Err(error) => {
+                eprintln!("Iteration {}: {}", iteration, error);
+                return false;
+            }
+        };
+        // Alternate between presenting and dropping the frame unpresented,
+        // which must leave the renderer just as clean.
+        let presented = match app.begin_frame(&window) {
+            Ok(Some(mut frame)) => {
+                frame.uniforms().animation_time = 0.0;
+                if iteration % 2 == 0 {
+                    frame.render_and_present()
+                } else {
+                    Ok(())
+                }
+            }
+            Ok(None) => Ok(()),
+            Err(error) => Err(error),
+        };
+        if let Err(error) = presented {
+            eprintln!("Iteration {}: {}", iteration, error);
+            app.shutdown();
+            return false;
+        }
+        let report = app.shutdown();
+        if !report.passed() {
+            print!("Iteration {}:\n{}", iteration, report);
+            return false;
+        }
+    }
+    println!(
+        "Shutdown stress passed ({} iterations)",
+        SHUTDOWN_STRESS_ITERATIONS
+    );
+    true
+}
+
+/// `--resize-storm`: reports each of `RESIZE_STORM_SIZES` as a resize,
+/// drawing a frame after each, and checks the journal holds one resize and
+/// one WindowResized recreation per size, in order. The window itself keeps
+/// its size, so nothing else can trigger a recreation in between.
+fn resize_storm(event_loop: &EventLoop<()>) -> bool {
     let window = WindowBuilder::new()
-        .with_title("Vulkan Triangle")
         .with_inner_size(winit::dpi::LogicalSize::new(WIDTH, HEIGHT))
-        .build(&event_loop)
+        .build(event_loop)
         .unwrap();
+    let mut app = match VulkanApp::new(&window, RendererConfig::default()) {
+        Ok(app) => app,
+        Err(error) => {
+            eprintln!("{}", error);
+            return false;
+        }
+    };
+    for &(width, height) in &RESIZE_STORM_SIZES {
+        app.window_resized(width, height);
+        if let Err(error) = app.draw_frame(&window) {
+            eprintln!("Resize to {}x{}: {}", width, height, error);
+            app.shutdown();
+            return false;
+        }
+    }
+
+    let expected: Vec<String> = RESIZE_STORM_SIZES
+        .iter()
+        .flat_map(|&(width, height)| {
+            [
+                format!("resize {}x{}", width, height),
+                format!("{:?}", RecreateReason::WindowResized),
+            ]
+        })
+        .collect();
+    let journal = app.journal();
+    let actual: Vec<String> = journal
+        .entries()
+        .iter()
+        .filter_map(|entry| match entry.event {
+            JournalEvent::Resize { width, height } => Some(format!("resize {}x{}", width, height)),
+            JournalEvent::SwapchainRecreated { reason, .. } => Some(format!("{:?}", reason)),
+            _ => None,
+        })
+        .collect();
+    let passed = actual == expected;
+    if passed {
+        println!("Resize storm passed ({} resizes)", RESIZE_STORM_SIZES.len());
+    } else {
+        print!(
+            "Resize storm failed: expected {:?}\n{}",
+            expected,
+            journal.dump()
+        );
+    }
+    app.shutdown().passed() && passed
+}
 
-    let mut app = VulkanApp::new(&window);
+/// The process exit code for a renderer that failed with `error`.
+fn exit_code(error: &VulkanError) -> i32 {
+    match error {
+        VulkanError::GpuHang(_) => GPU_HANG_EXIT_CODE,
+        _ => VULKAN_ERROR_EXIT_CODE,
+    }
+}
 
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Poll;
-        match event {
+/// `--memory-benchmark`: times MEMORY_BENCHMARK_FRAMES frames with the
+/// default memory types, then again with uniform buffers forced into
+/// host-visible memory that needn't be device local, and prints both. On a
+/// device without DEVICE_LOCAL | HOST_VISIBLE memory the two runs match.
+fn memory_benchmark(event_loop: &EventLoop<()>) -> bool {
+    let window = WindowBuilder::new()
+        .with_inner_size(winit::dpi::LogicalSize::new(WIDTH, HEIGHT))
+        .build(event_loop)
+        .unwrap();
+    let runs = [
+        ("default", Vec::new()),
+        (
+            "host-visible uniforms",
+            vec![(
+                ResourceClass::Uniform,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )],
+        ),
+    ];
+    for (name, memory_types) in runs {
+        let config = RendererConfig {
+            // Unthrottled where the surface allows it, so frame times show
+            // the memory and not the display.
+            present_mode: vk::PresentModeKHR::IMMEDIATE,
+            memory_types,
+            ..RendererConfig::default()
+        };
+        let mut app = match VulkanApp::new(&window, config) {
+            Ok(app) => app,
+            Err(error) => {
+                eprintln!("{}: {}", name, error);
+                return false;
+            }
+        };
+        let mut start = Instant::now();
+        for frame in 0..MEMORY_BENCHMARK_WARMUP_FRAMES + MEMORY_BENCHMARK_FRAMES {
+            if frame == MEMORY_BENCHMARK_WARMUP_FRAMES {
+                start = Instant::now();
+            }
+            if let Err(error) = app.draw_frame(&window) {
+                eprintln!("{}: {}", name, error);
+                app.shutdown();
+                return false;
+            }
+        }
+        let average = start.elapsed() / MEMORY_BENCHMARK_FRAMES;
+        let uniform = app
+            .memory_placements()
+            .into_iter()
+            .find(|placement| placement.class == ResourceClass::Uniform)
+            .unwrap();
+        println!(
+            "{}: {:.3} ms per frame, {}",
+            name,
+            average.as_secs_f64() * 1000.0,
+            uniform
+        );
+        if !app.shutdown().passed() {
+            return false;
+        }
+    }
+    true
+}
+
+/// `--record-benchmark`: draws RECORD_BENCHMARK_CUBES cubes, one draw each,
+/// recording every draw on the render thread and then in parallel, and
+/// prints the frame times of both. With the threads' overhead hidden by the
+/// GPU, or on one core, the two can match.
+fn record_benchmark(event_loop: &EventLoop<()>) -> bool {
+    let window = WindowBuilder::new()
+        .with_inner_size(winit::dpi::LogicalSize::new(WIDTH, HEIGHT))
+        .build(event_loop)
+        .unwrap();
+    for (name, parallel_recording) in [("inline", false), ("parallel", true)] {
+        let config = RendererConfig {
+            present_mode: vk::PresentModeKHR::IMMEDIATE,
+            draw_stress: Some(RECORD_BENCHMARK_CUBES),
+            parallel_recording,
+            ..RendererConfig::default()
+        };
+        let mut app = match VulkanApp::new(&window, config) {
+            Ok(app) => app,
+            Err(error) => {
+                eprintln!("{}: {}", name, error);
+                return false;
+            }
+        };
+        let mut start = Instant::now();
+        for frame in 0..MEMORY_BENCHMARK_WARMUP_FRAMES + RECORD_BENCHMARK_FRAMES {
+            if frame == MEMORY_BENCHMARK_WARMUP_FRAMES {
+                start = Instant::now();
+            }
+            if let Err(error) = app.draw_frame(&window) {
+                eprintln!("{}: {}", name, error);
+                app.shutdown();
+                return false;
+            }
+        }
+        let average = start.elapsed() / RECORD_BENCHMARK_FRAMES;
+        println!(
+            "{}: {:.3} ms per frame, 1% low {:.3} ms",
+            name,
+            average.as_secs_f64() * 1000.0,
+            app.frame_stats().percentile_frame_ms(99.0)
+        );
+        if !app.shutdown().passed() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Logs what the drivers reported at startup with `--verbose`, or always
+/// once the renderer `failed` to start, and writes it to
+/// `--diagnostics-json`'s path if given.
+fn report_startup(args: &[String], failed: bool) {
+    let Some(diagnostics) = StartupDiagnostics::latest() else {
+        return;
+    };
+    if failed {
+        error!("{}", diagnostics);
+    } else if args.iter().any(|arg| arg == "--verbose") {
+        info!("{}", diagnostics);
+    }
+    let path = args
+        .iter()
+        .position(|arg| arg == "--diagnostics-json")
+        .and_then(|index| args.get(index + 1));
+    if let Some(path) = path {
+        match std::fs::write(path, diagnostics.to_json()) {
+            Ok(()) => info!("Saved startup diagnostics to {}", path),
+            Err(error) => error!("Failed to save {}: {}", path, error),
+        }
+    }
+}
+
+/// `--headless`: renders one frame offscreen at the `--width` and
+/// `--height` size, with no window or display server, and writes it to
+/// `output`. Returns the exit code.
+fn headless(config: RendererConfig, app_config: &AppConfig, output: &Path) -> i32 {
+    let mut app = match VulkanApp::new_headless(app_config.width, app_config.height, config) {
+        Ok(app) => app,
+        Err(error) => {
+            error!("Failed to start the renderer: {}", error);
+            return VULKAN_ERROR_EXIT_CODE;
+        }
+    };
+    let frame = match app.render_to_image(0.0) {
+        Ok(frame) => frame,
+        Err(error) => {
+            error!("{}", error);
+            app.shutdown();
+            return exit_code(&error);
+        }
+    };
+    if let Err(error) = app.destroy() {
+        error!("Renderer shutdown failed: {}", error);
+        return VULKAN_ERROR_EXIT_CODE;
+    }
+    match frame.write_image(output) {
+        Ok(()) => {
+            info!("Saved {}", output.display());
+            0
+        }
+        Err(error) => {
+            error!("Failed to save {}: {}", output.display(), error);
+            1
+        }
+    }
+}
+
+/// `--serve`: renders headless at the size `serving` asks for, with no
+/// window or display server, and streams frames to its clients until
+/// Ctrl+C. Returns the exit code.
+fn serve(config: RendererConfig, mut serving: Serving) -> i32 {
+    let (width, height) = (serving.config.width, serving.config.height);
+    let mut app = match VulkanApp::new_headless(width, height, config) {
+        Ok(app) => app,
+        Err(error) => {
+            error!("Failed to start the renderer: {}", error);
+            return VULKAN_ERROR_EXIT_CODE;
+        }
+    };
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let handler = {
+        let interrupted = Arc::clone(&interrupted);
+        ctrlc::set_handler(move || interrupted.store(true, Ordering::Relaxed))
+    };
+    if let Err(error) = handler {
+        warn!(
+            "Ctrl+C will end the process without shutting down: {}",
+            error
+        );
+    }
+    while !interrupted.load(Ordering::Relaxed) {
+        if let Err(error) = serving.frame(&mut app) {
+            error!("{}", error);
+            eprint!("{}", app.journal().dump());
+            app.shutdown();
+            return exit_code(&error);
+        }
+        std::thread::sleep(serving.next_frame.saturating_duration_since(Instant::now()));
+    }
+    match app.destroy() {
+        Ok(()) => 0,
+        Err(error) => {
+            error!("Renderer shutdown failed: {}", error);
+            VULKAN_ERROR_EXIT_CODE
+        }
+    }
+}
+
+/// `--print-info`: starts the renderer headless at the `--width` and
+/// `--height` size, renders a frame and prints the diagnostics report F12
+/// exports. Returns the exit code.
+fn print_info(config: RendererConfig, app_config: &AppConfig) -> i32 {
+    let mut app = match VulkanApp::new_headless(app_config.width, app_config.height, config) {
+        Ok(app) => app,
+        Err(error) => {
+            error!("Failed to start the renderer: {}", error);
+            return VULKAN_ERROR_EXIT_CODE;
+        }
+    };
+    if let Err(error) = app.render_to_image(0.0) {
+        error!("{}", error);
+        app.shutdown();
+        return exit_code(&error);
+    }
+    print!("{}", app.diagnostics_report());
+    match app.destroy() {
+        Ok(()) => 0,
+        Err(error) => {
+            error!("Renderer shutdown failed: {}", error);
+            VULKAN_ERROR_EXIT_CODE
+        }
+    }
+}
+
+/// `--self-test`: renders the canonical scenes headless, with the defaults
+/// rather than the settings file or flags other than `--gpu`, then shuts
+/// down, and prints PASS, FAIL or SKIP for each check.
+fn self_test(config: &RendererConfig) -> i32 {
+    let config = RendererConfig {
+        gpu: config.gpu.clone(),
+        // The canonical cube is the colour one, whatever the build embeds.
+        cube_texture: None,
+        ..RendererConfig::default()
+    };
+    let mut app = match VulkanApp::new_headless(SELF_TEST_SIZE, SELF_TEST_SIZE, config) {
+        Ok(app) => app,
+        Err(error) => {
+            error!("Failed to start the renderer: {}", error);
+            return VULKAN_ERROR_EXIT_CODE;
+        }
+    };
+    let mut report = match app.self_test() {
+        Ok(report) => report,
+        Err(error) => {
+            error!("{}", error);
+            app.shutdown();
+            return exit_code(&error);
+        }
+    };
+    report.append(app.shutdown());
+    print!("{}", report);
+    if report.passed() {
+        0
+    } else {
+        SELF_TEST_FAILURE_EXIT_CODE
+    }
+}
+
+/// Shuts the renderer down from inside the event loop, while the window it
+/// presents to still exists, and ends the loop. Events still to come find
+/// no renderer.
+fn close(running: &mut Option<VulkanApp>, control_flow: &mut ControlFlow) {
+    // An earlier failure's exit code takes precedence.
+    if !matches!(control_flow, ControlFlow::ExitWithCode(_)) {
+        *control_flow = ControlFlow::Exit;
+    }
+    let Some(mut app) = running.take() else {
+        return;
+    };
+    if let Err(error) = app.close() {
+        error!("Renderer shutdown failed: {}", error);
+        *control_flow = ControlFlow::ExitWithCode(VULKAN_ERROR_EXIT_CODE);
+    }
+}
+
+/// Ends the loop after `draw_frame` or a served frame fails.
+fn frame_failed(app: &VulkanApp, error: VulkanError, control_flow: &mut ControlFlow) {
+    error!("{}", error);
+    if let VulkanError::DeviceLost(_) = error {
+        // Teardown still runs when the loop ends, and reports the waits
+        // that fail on a lost device.
+        error!("The GPU can't be used any more; exiting");
+    }
+    eprint!("{}", app.journal().dump());
+    *control_flow = ControlFlow::ExitWithCode(exit_code(&error));
+}
+
+/// What `--fullscreen` asks for, on the window's monitor. Exclusive takes
+/// the video mode closest to `request`, or to the monitor's native
+/// resolution at its highest refresh rate, and falls back to borderless
+/// where there are no video modes.
+fn fullscreen(
+    window: &Window,
+    mode: FullscreenMode,
+    request: Option<VideoModeRequest>,
+) -> Fullscreen {
+    let monitor = window.current_monitor();
+    if mode == FullscreenMode::Exclusive {
+        let video_mode = monitor.as_ref().and_then(|monitor| {
+            let (width, height) = match request {
+                Some(request) => (request.width, request.height),
+                None => (monitor.size().width, monitor.size().height),
+            };
+            let refresh_millihertz = request
+                .and_then(|request| request.refresh_hz)
+                .map(|hz| hz * 1000);
+            monitor.video_modes().min_by_key(|video_mode| {
+                let size = video_mode.size();
+                let refresh = video_mode.refresh_rate_millihertz();
+                (
+                    size.width.abs_diff(width) + size.height.abs_diff(height),
+                    match refresh_millihertz {
+                        Some(wanted) => refresh.abs_diff(wanted),
+                        None => u32::MAX - refresh,
+                    },
+                )
+            })
+        });
+        match video_mode {
+            Some(video_mode) => {
+                info!(
+                    "Exclusive fullscreen at {}x{} @ {:.2} Hz",
+                    video_mode.size().width,
+                    video_mode.size().height,
+                    video_mode.refresh_rate_millihertz() as f32 / 1000.0
+                );
+                return Fullscreen::Exclusive(video_mode);
+            }
+            None => warn!("No video modes for exclusive fullscreen; using borderless"),
+        }
+    }
+    Fullscreen::Borderless(monitor)
+}
+
+/// Where the window was before Alt+Enter made it fullscreen, to put it
+/// back when leaving.
+struct WindowedPlacement {
+    size: PhysicalSize<u32>,
+    // Not every platform can report or set it.
+    position: Option<PhysicalPosition<i32>>,
+}
+
+/// Alt+Enter: into fullscreen in `mode`, remembering the window's size and
+/// position, or back out to them.
+fn toggle_fullscreen(
+    window: &Window,
+    mode: FullscreenMode,
+    request: Option<VideoModeRequest>,
+    windowed: &mut Option<WindowedPlacement>,
+) {
+    if window.fullscreen().is_some() {
+        window.set_fullscreen(None);
+        if let Some(placement) = windowed.take() {
+            window.set_inner_size(placement.size);
+            if let Some(position) = placement.position {
+                window.set_outer_position(position);
+            }
+        }
+        info!("Windowed");
+    } else {
+        *windowed = Some(WindowedPlacement {
+            size: window.inner_size(),
+            position: window.outer_position().ok(),
+        });
+        window.set_fullscreen(Some(fullscreen(window, mode, request)));
+    }
+}
+
+/// Where `window` is now, for the settings file. Fullscreen keeps the
+/// windowed size and position from `last`, and so does being minimised,
+/// where some platforms report a size of zero or a position far off screen.
+fn window_settings(window: &Window, last: WindowSettings) -> WindowSettings {
+    if window.fullscreen().is_some() {
+        return WindowSettings {
+            fullscreen: true,
+            ..last
+        };
+    }
+    let size: LogicalSize<u32> = window.inner_size().to_logical(window.scale_factor());
+    if window.is_minimized() == Some(true) || size.width == 0 || size.height == 0 {
+        return last;
+    }
+    WindowSettings {
+        width: size.width,
+        height: size.height,
+        position: window
+            .outer_position()
+            .ok()
+            .map(|position| (position.x, position.y))
+            .or(last.position),
+        fullscreen: false,
+    }
+}
+
+fn main() {
+    // Info and above unless RUST_LOG says otherwise.
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    // Before any Vulkan call, so a panic in renderer startup still prints
+    // what had been found, ahead of the message and backtrace.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Some(diagnostics) = StartupDiagnostics::latest() {
+            eprint!("{}", diagnostics);
+        }
+        default_hook(info);
+    }));
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        print!("{}", USAGE);
+        return;
+    }
+    let mut app_config = AppConfig::from_args(&args).unwrap_or_else(|error| {
+        eprintln!("{}; see --help", error);
+        std::process::exit(USAGE_EXIT_CODE);
+    });
+    // Flags win over the settings file at startup; later edits to the file
+    // win over both.
+    let mut settings_file = SettingsFile::in_config_dir();
+    if args.iter().any(|arg| arg == "--reset-settings") {
+        match settings_file.reset() {
+            Ok(()) => info!("Reset {}", settings_file.path().display()),
+            Err(error) => warn!("Can't reset {}: {}", settings_file.path().display(), error),
+        }
+    }
+    let mut defaults = RendererConfig::default();
+    let mut window_state = WindowSettings::default();
+    if let Some(settings) = settings_file.load(Settings::from_config(&defaults)) {
+        info!("Loaded {}", settings_file.path().display());
+        settings.apply_to(&mut defaults);
+        window_state = settings.window;
+    }
+    let mut config = RendererConfig::from_args(defaults, &args);
+    app_config.apply_to(&mut config);
+    // Before the event loop, which needs a display server.
+    if args.iter().any(|arg| arg == "--headless") {
+        let output = args
+            .iter()
+            .position(|arg| arg == "--output")
+            .and_then(|index| args.get(index + 1))
+            .map_or(HEADLESS_OUTPUT_PATH, String::as_str);
+        let code = headless(config, &app_config, Path::new(output));
+        report_startup(&args, code == VULKAN_ERROR_EXIT_CODE);
+        std::process::exit(code);
+    }
+    if args.iter().any(|arg| arg == "--print-info") {
+        let code = print_info(config, &app_config);
+        report_startup(&args, code == VULKAN_ERROR_EXIT_CODE);
+        std::process::exit(code);
+    }
+    if args.iter().any(|arg| arg == "--self-test") {
+        let code = self_test(&config);
+        report_startup(&args, code == VULKAN_ERROR_EXIT_CODE);
+        std::process::exit(code);
+    }
+    if let Some(serve_config) = ServeConfig::from_args(&args) {
+        // A busy or privileged port is the user's to pick again.
+        let server = FrameServer::bind(serve_config.port).unwrap_or_else(|error| {
+            eprintln!("Can't serve on port {}: {}", serve_config.port, error);
+            std::process::exit(USAGE_EXIT_CODE);
+        });
+        let serving = Serving {
+            server,
+            config: serve_config,
+            next_frame: Instant::now(),
+        };
+        let code = serve(config, serving);
+        report_startup(&args, code == VULKAN_ERROR_EXIT_CODE);
+        std::process::exit(code);
+    }
+    BackendPreference::from_args(&args).apply();
+    let event_loop = EventLoop::new();
+    if args.iter().any(|arg| arg == "--shutdown-stress") {
+        let code = if shutdown_stress(&event_loop) {
+            0
+        } else {
+            SELF_TEST_FAILURE_EXIT_CODE
+        };
+        std::process::exit(code);
+    }
+    if args.iter().any(|arg| arg == "--memory-benchmark") {
+        let code = if memory_benchmark(&event_loop) {
+            0
+        } else {
+            SELF_TEST_FAILURE_EXIT_CODE
+        };
+        std::process::exit(code);
+    }
+    if args.iter().any(|arg| arg == "--record-benchmark") {
+        let code = if record_benchmark(&event_loop) {
+            0
+        } else {
+            SELF_TEST_FAILURE_EXIT_CODE
+        };
+        std::process::exit(code);
+    }
+    if args.iter().any(|arg| arg == "--resize-storm") {
+        let code = if resize_storm(&event_loop) {
+            0
+        } else {
+            SELF_TEST_FAILURE_EXIT_CODE
+        };
+        std::process::exit(code);
+    }
+    // A saved position is only used while it's still on a monitor.
+    let position = window_state.position.filter(|&(x, y)| {
+        event_loop.available_monitors().any(|monitor| {
+            let (corner, size) = (monitor.position(), monitor.size());
+            (corner.x..corner.x + size.width as i32).contains(&x)
+                && (corner.y..corner.y + size.height as i32).contains(&y)
+        })
+    });
+    app_config.apply_window_settings(&window_state, &args);
+    let builder =
+        WindowBuilder::new().with_inner_size(LogicalSize::new(app_config.width, app_config.height));
+    let window = match position {
+        Some((x, y)) => builder.with_position(PhysicalPosition::new(x, y)),
+        None => builder,
+    }
+    .with_title(&config.app_name)
+    .with_window_icon(window_icon())
+    .build(&event_loop)
+    .unwrap();
+    if let Some(mode) = app_config.fullscreen {
+        window.set_fullscreen(Some(fullscreen(&window, mode, app_config.video_mode)));
+    }
+    // Alt+Enter goes back to the flags' fullscreen mode, or borderless.
+    let fullscreen_mode = app_config.fullscreen.unwrap_or(FullscreenMode::Borderless);
+    let video_mode = app_config.video_mode;
+    // Started fullscreen, there is no windowed placement to go back to.
+    let mut windowed = None;
+    let mut modifiers = ModifiersState::empty();
+
+    let title = config.app_name.clone();
+    let mut app = VulkanApp::new(&window, config).unwrap_or_else(|error| {
+        error!("Failed to start the renderer: {}", error);
+        report_startup(&args, true);
+        std::process::exit(VULKAN_ERROR_EXIT_CODE);
+    });
+    report_startup(&args, false);
+    if args.iter().any(|arg| arg == "--dump-config") {
+        print!("{}", app.effective_settings().to_toml());
+        app.shutdown();
+        return;
+    }
+    let journal = app.journal();
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        eprint!("{}", journal.dump());
+        default_hook(info);
+    }));
+
+    // Ctrl+C shuts down like closing the window. The wakeup gets a loop
+    // that is waiting for events to look at the flag.
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let proxy = event_loop.create_proxy();
+    let handler = {
+        let interrupted = Arc::clone(&interrupted);
+        ctrlc::set_handler(move || {
+            interrupted.store(true, Ordering::Relaxed);
+            let _ = proxy.send_event(());
+        })
+    };
+    if let Err(error) = handler {
+        warn!(
+            "Ctrl+C will end the process without shutting down: {}",
+            error
+        );
+    }
+
+    // Taken out to be closed, then destroyed when the loop ends.
+    let mut running = Some(app);
+    let mut next_title_update = Instant::now();
+    // Where the last left click picks an object, with `--picking`.
+    let mut cursor = PhysicalPosition::new(0.0, 0.0);
+    #[cfg(feature = "gamepad")]
+    let mut gamepad = input::GamepadInput::new();
+    event_loop.run(move |event, window_target, control_flow| {
+        if let Event::LoopDestroyed = event {
+            if let Some(app) = running.take() {
+                if let Err(error) = app.destroy() {
+                    error!("Renderer shutdown failed: {}", error);
+                    // An earlier failure's exit code takes precedence.
+                    if *control_flow == ControlFlow::Exit {
+                        std::process::exit(VULKAN_ERROR_EXIT_CODE);
+                    }
+                }
+            }
+            return;
+        }
+        // The windows Ctrl+N opens only show the scene: closing one closes
+        // just that window, and they take no other input.
+        if let (Event::WindowEvent { window_id, event }, Some(app)) = (&event, running.as_mut()) {
+            if app.is_extra_window(*window_id) {
+                match event {
+                    WindowEvent::CloseRequested => app.close_window(*window_id),
+                    WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                        app.extra_window_resized(*window_id)
+                    }
+                    _ => {}
+                }
+                return;
+            }
+        }
+        let closing = matches!(
+            event,
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
-            } => {
-                *control_flow = ControlFlow::Exit;
+            } | Event::WindowEvent {
+                event: WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Escape),
+                        ..
+                    },
+                    ..
+                },
+                ..
+            }
+        );
+        if closing || interrupted.load(Ordering::Relaxed) {
+            if let Some(app) = &running {
+                settings_file.flush(Settings {
+                    window: window_settings(&window, window_state),
+                    ..app.settings()
+                });
+            }
+            close(&mut running, control_flow);
+            return;
+        }
+        let Some(app) = running.as_mut() else {
+            return;
+        };
+        // A connected controller is read every frame, so the loop can't sleep.
+        #[cfg(feature = "gamepad")]
+        let steering = gamepad
+            .as_ref()
+            .is_some_and(input::GamepadInput::is_connected);
+        #[cfg(not(feature = "gamepad"))]
+        let steering = false;
+        *control_flow = if app.is_suspended() {
+            ControlFlow::Wait
+        } else if !app.needs_redraw() && !steering {
+            // Only to poll the settings file; nothing is drawn.
+            ControlFlow::WaitUntil(Instant::now() + Duration::from_millis(IDLE_HEARTBEAT_MS))
+        } else if let Some(due) = app.throttled_until() {
+            ControlFlow::WaitUntil(due)
+        } else {
+            ControlFlow::Poll
+        };
+        if let Event::WindowEvent { event, .. } = &event {
+            match *event {
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                } => app.journal().record(JournalEvent::Key(key)),
+                WindowEvent::MouseInput { state, button, .. } => {
+                    app.journal().record(JournalEvent::Mouse(button, state))
+                }
+                _ => {}
             }
+        }
+        match event {
             Event::WindowEvent {
                 event: WindowEvent::Resized(new_size),
                 ..
             } => {
-                if new_size.width > 0 && new_size.height > 0 {
-                    app.framebuffer_resized = true;
+                app.window_resized(new_size.width, new_size.height);
+            }
+            // Moving to a monitor with another scale factor resizes the
+            // window without a Resized event.
+            Event::WindowEvent {
+                event: WindowEvent::ScaleFactorChanged { new_inner_size, .. },
+                ..
+            } => {
+                app.window_resized(new_inner_size.width, new_inner_size.height);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(state),
+                ..
+            } => {
+                modifiers = state;
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Focused(focused),
+                ..
+            } => {
+                app.window_focus_changed(focused);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Occluded(occluded),
+                ..
+            } => {
+                app.window_occluded(occluded);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Return),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } if modifiers.alt() => {
+                toggle_fullscreen(&window, fullscreen_mode, video_mode, &mut windowed);
+                // Not every platform sends Resized for the switch.
+                let size = window.inner_size();
+                app.window_resized(size.width, size.height);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::G),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.toggle_interpolation_split();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Space),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.toggle_animation_pause();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::B),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.toggle_selected_double_sided();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::F12),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                print!("{}", app.export_diagnostics());
+            }
+            #[cfg(feature = "overlay")]
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::F3),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.toggle_frame_graph();
+            }
+            #[cfg(feature = "overlay")]
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::H),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.toggle_hud();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::L),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.toggle_face_labels();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::T),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.toggle_sun_pause();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::P),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.request_screenshot();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::E),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.toggle_error_banner();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::K),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.cycle_background();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::F2),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.cycle_present_mode();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Tab),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.toggle_wireframe();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::F4),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.toggle_depth_view();
+            }
+            // Ctrl+C copies what the overlay summarises, in full.
+            #[cfg(feature = "overlay")]
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::C),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } if modifiers.ctrl() && app.overlay_visible() => {
+                print!("{}", app.export_diagnostics());
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::C),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.cycle_clear_color();
+            }
+            // Plain M holds the magnifier.
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::M),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } if modifiers.shift() => {
+                app.toggle_breathing();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state,
+                                virtual_keycode: Some(VirtualKeyCode::M),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.set_magnifier_held(state == ElementState::Pressed);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state,
+                        button: MouseButton::Left,
+                        ..
+                    },
+                ..
+            } => {
+                app.set_split_dragging(state == ElementState::Pressed);
+                app.set_orbit_dragging(OrbitDrag::Orbit, state == ElementState::Pressed);
+                if state == ElementState::Pressed {
+                    app.pick(cursor.x as u32, cursor.y as u32);
+                }
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state,
+                        button: MouseButton::Middle,
+                        ..
+                    },
+                ..
+            } => {
+                app.set_orbit_dragging(OrbitDrag::Pan, state == ElementState::Pressed);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::MouseWheel { delta, .. },
+                ..
+            } => {
+                app.dolly_camera(match delta {
+                    MouseScrollDelta::LineDelta(_, lines) => lines,
+                    // Touchpads scroll in pixels; call a line 40 of them.
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 40.0,
+                });
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::O),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.toggle_orbit_camera();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::V),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.toggle_plasma();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::A),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                app.toggle_letterbox();
+            }
+            // Ctrl+1 to Ctrl+5 pin the render scale at a step of the ladder,
+            // for the HUD to compare them; with Shift they render the right
+            // half of the window at that step instead, and Ctrl+Shift+0
+            // ends the split.
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode:
+                                    Some(
+                                        key @ (VirtualKeyCode::Key0
+                                        | VirtualKeyCode::Key1
+                                        | VirtualKeyCode::Key2
+                                        | VirtualKeyCode::Key3
+                                        | VirtualKeyCode::Key4
+                                        | VirtualKeyCode::Key5),
+                                    ),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } if modifiers.ctrl() && (modifiers.shift() || key != VirtualKeyCode::Key0) => {
+                let step = match key {
+                    VirtualKeyCode::Key1 => Some(0),
+                    VirtualKeyCode::Key2 => Some(1),
+                    VirtualKeyCode::Key3 => Some(2),
+                    VirtualKeyCode::Key4 => Some(3),
+                    VirtualKeyCode::Key5 => Some(4),
+                    _ => None,
+                };
+                let percent = step.map(|step| RENDER_SCALE_LADDER[step]);
+                match (modifiers.shift(), percent) {
+                    (true, percent) => app.set_split_scale(percent),
+                    (false, Some(percent)) => app.set_render_scale(percent),
+                    (false, None) => {}
+                }
+            }
+            // Pins the render scale a step up or down; 0 hands it back to
+            // --adaptive-resolution.
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode:
+                                    Some(
+                                        key @ (VirtualKeyCode::Plus
+                                        | VirtualKeyCode::Equals
+                                        | VirtualKeyCode::NumpadAdd
+                                        | VirtualKeyCode::Minus
+                                        | VirtualKeyCode::NumpadSubtract
+                                        | VirtualKeyCode::Key0),
+                                    ),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => match key {
+                VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => app.step_render_scale(-1),
+                VirtualKeyCode::Key0 => app.release_render_scale(),
+                _ => app.step_render_scale(1),
+            },
+            // Ctrl+N opens another window onto the scene, from another
+            // camera.
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::N),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } if modifiers.ctrl() => {
+                let opened = WindowBuilder::new()
+                    .with_inner_size(window.inner_size())
+                    .with_title(format!("{} — view {}", title, app.extra_window_count() + 2))
+                    .with_window_icon(window_icon())
+                    .build(window_target)
+                    .map_err(|error| error.to_string())
+                    .and_then(|extra| app.open_window(extra).map_err(|error| error.to_string()));
+                if let Err(error) = opened {
+                    warn!("Couldn't open another window: {}", error);
+                }
+            }
+            // With --post-process: N steps the vignette round, [ and ] step
+            // the gamma down and up.
+            #[cfg(feature = "post-processing")]
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode:
+                                    Some(
+                                        key @ (VirtualKeyCode::N
+                                        | VirtualKeyCode::LBracket
+                                        | VirtualKeyCode::RBracket),
+                                    ),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => match key {
+                VirtualKeyCode::N => app.cycle_vignette(),
+                VirtualKeyCode::LBracket => app.step_gamma(-1),
+                _ => app.step_gamma(1),
+            },
+            Event::WindowEvent {
+                event: WindowEvent::CursorLeft { .. } | WindowEvent::CursorEntered { .. },
+                ..
+            } => {
+                app.cursor_crossed_window_edge();
+            }
+            #[cfg(feature = "tools")]
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state,
+                        button: MouseButton::Right,
+                        ..
+                    },
+                ..
+            } => {
+                app.set_gizmo_dragging(state == ElementState::Pressed);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { position, .. },
+                ..
+            } => {
+                cursor = position;
+                app.cursor_moved(position.x, position.y);
+            }
+            // Extra windows are drawn with the main window's frames.
+            Event::RedrawRequested(id) if id == window.id() => {
+                if let Err(error) = app.draw_frame(&window) {
+                    frame_failed(app, error, control_flow);
                 }
             }
             Event::MainEventsCleared => {
-                app.draw_frame(&window);
+                if let Some(settings) = settings_file.poll(app.settings()) {
+                    app.apply_settings(&settings);
+                }
+                // The window's size and position are saved as they are, so
+                // a `[window]` edit made while running is overwritten.
+                window_state = window_settings(&window, window_state);
+                settings_file.save(Settings {
+                    window: window_state,
+                    ..app.settings()
+                });
+                #[cfg(feature = "gamepad")]
+                if let Some(gamepad) = &mut gamepad {
+                    gamepad.poll(app);
+                }
+                if app.needs_redraw() {
+                    window.request_redraw();
+                }
+                if Instant::now() >= next_title_update {
+                    next_title_update =
+                        Instant::now() + Duration::from_millis(FPS_TITLE_INTERVAL_MS);
+                    let stats = app.frame_stats();
+                    let gpu = match stats.avg_gpu_ms() {
+                        Some(ms) => format!(", GPU {:.2} ms", ms),
+                        None => String::new(),
+                    };
+                    let throttled = if stats.is_throttled() {
+                        ", throttled"
+                    } else {
+                        ""
+                    };
+                    let scale = match app.render_scale_percent() {
+                        100 => String::new(),
+                        percent => format!(", scale {}%", percent),
+                    };
+                    window.set_title(&format!(
+                        "{} — {:.0} FPS ({:.2} ms{}{}{})",
+                        title,
+                        stats.fps(),
+                        stats.avg_frame_ms(),
+                        gpu,
+                        throttled,
+                        scale
+                    ));
+                    #[cfg(feature = "overlay")]
+                    {
+                        const MIB: f64 = (1 << 20) as f64;
+                        let memory = app.memory_stats();
+                        app.hud_print(
+                            0,
+                            &format!(
+                                "GPU memory {:.1} of {:.1} MiB",
+                                memory.allocated_bytes as f64 / MIB,
+                                memory.block_bytes as f64 / MIB
+                            ),
+                        );
+                        for (line, text) in app.render_scale_lines().iter().enumerate() {
+                            app.hud_print(1 + line, text);
+                        }
+                    }
+                }
             }
             _ => {}
         }
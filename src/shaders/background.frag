@@ -0,0 +1,130 @@
+#version 450
+
+#include "common.glsl"
+
+// background::Background: 1 gradient, 2 starfield, 3 clouds.
+layout(constant_id = 0) const int BACKGROUND = 1;
+layout(constant_id = 1) const bool MANUAL_SRGB_ENCODE = false;
+
+layout(set = 0, binding = 0) uniform PerFrame {
+    PER_FRAME_UBO_FIELDS
+} frame;
+
+layout(set = 1, binding = 0) uniform PerView {
+    PER_VIEW_UBO_FIELDS
+} camera;
+
+layout(location = 0) in vec2 fragNdc;
+
+layout(location = 0) out vec4 outColor;
+
+// Linear colours; the world is Z up.
+const vec3 ZENITH = vec3(0.05, 0.15, 0.45);
+const vec3 HORIZON = vec3(0.55, 0.65, 0.8);
+const vec3 GROUND = vec3(0.04, 0.035, 0.03);
+// Stars are drawn in cells of a grid this many cells from the eye.
+const float STAR_CELLS = 120.0;
+const float STAR_DENSITY = 0.015;
+// The cloud layer, in units above the eye along unit rays.
+const float CLOUD_BOTTOM = 2.0;
+const float CLOUD_TOP = 3.5;
+const int CLOUD_STEPS = 16;
+
+float hash(vec3 p) {
+    p = fract(p * 0.3183099 + 0.1);
+    p *= 17.0;
+    return fract(p.x * p.y * p.z * (p.x + p.y + p.z));
+}
+
+float noise(vec3 p) {
+    vec3 i = floor(p);
+    vec3 f = fract(p);
+    f = f * f * (3.0 - 2.0 * f);
+    return mix(mix(mix(hash(i), hash(i + vec3(1, 0, 0)), f.x),
+                   mix(hash(i + vec3(0, 1, 0)), hash(i + vec3(1, 1, 0)), f.x), f.y),
+               mix(mix(hash(i + vec3(0, 0, 1)), hash(i + vec3(1, 0, 1)), f.x),
+                   mix(hash(i + vec3(0, 1, 1)), hash(i + vec3(1, 1, 1)), f.x), f.y),
+               f.z);
+}
+
+float fbm(vec3 p) {
+    float sum = 0.0;
+    float amplitude = 0.5;
+    for (int i = 0; i < 4; i++) {
+        sum += amplitude * noise(p);
+        p *= 2.03;
+        amplitude *= 0.5;
+    }
+    return sum;
+}
+
+// The world-space direction through this pixel, undoing the projection and
+// the view's rotation; the eye's position doesn't matter for a background.
+vec3 viewRay() {
+    vec4 target = inverse(camera.proj) * vec4(fragNdc, 1.0, 1.0);
+    return normalize(transpose(mat3(camera.view)) * (target.xyz / target.w));
+}
+
+vec3 gradient(vec3 dir) {
+    if (dir.z < 0.0) {
+        return mix(HORIZON * 0.4, GROUND, smoothstep(0.0, 0.15, -dir.z));
+    }
+    return mix(HORIZON, ZENITH, sqrt(dir.z));
+}
+
+vec3 starfield(vec3 dir) {
+    vec3 p = dir * STAR_CELLS;
+    vec3 cell = floor(p);
+    float seed = hash(cell);
+    if (seed > STAR_DENSITY) {
+        return vec3(0.0);
+    }
+    vec3 star = cell + 0.5 + 0.6 * (vec3(hash(cell + 11.0), hash(cell + 23.0), hash(cell + 37.0)) - 0.5);
+    float glow = smoothstep(0.25, 0.0, length(p - star));
+    float twinkle = 0.65 + 0.35 * sin(frame.time * (2.0 + 40.0 * seed) + 400.0 * seed);
+    vec3 tint = mix(vec3(0.7, 0.8, 1.0), vec3(1.0, 0.85, 0.7), hash(cell + 51.0));
+    return tint * glow * twinkle;
+}
+
+// Marches the ray through the slab between CLOUD_BOTTOM and CLOUD_TOP,
+// compositing front to back over the gradient.
+vec3 clouds(vec3 dir) {
+    vec3 sky = gradient(dir);
+    if (dir.z < 0.02) {
+        return sky;
+    }
+    float near = CLOUD_BOTTOM / dir.z;
+    float far = CLOUD_TOP / dir.z;
+    float stepLength = (far - near) / float(CLOUD_STEPS);
+    vec3 wind = vec3(frame.time * 0.08, frame.time * 0.03, 0.0);
+    vec3 color = vec3(0.0);
+    float transmittance = 1.0;
+    for (int i = 0; i < CLOUD_STEPS; i++) {
+        vec3 p = dir * (near + (float(i) + 0.5) * stepLength);
+        float height = (p.z - CLOUD_BOTTOM) / (CLOUD_TOP - CLOUD_BOTTOM);
+        float density = max(fbm(p * 0.6 + wind) - 0.45, 0.0) * 4.0 * (1.0 - abs(2.0 * height - 1.0));
+        float absorbed = 1.0 - exp(-density * stepLength);
+        // Lighter towards the top of the layer.
+        color += transmittance * absorbed * mix(vec3(0.6, 0.62, 0.68), vec3(1.0), height);
+        transmittance *= 1.0 - absorbed;
+        if (transmittance < 0.01) {
+            break;
+        }
+    }
+    // Thin the layer out towards the horizon, where it is far away.
+    float fade = exp(-near * 0.08);
+    return mix(sky, sky * transmittance + color, fade);
+}
+
+void main() {
+    vec3 dir = viewRay();
+    vec3 color;
+    if (BACKGROUND == 2) {
+        color = starfield(dir);
+    } else if (BACKGROUND == 3) {
+        color = clouds(dir);
+    } else {
+        color = gradient(dir);
+    }
+    outColor = vec4(MANUAL_SRGB_ENCODE ? linearToSrgb(color) : color, 1.0);
+}
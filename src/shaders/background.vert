@@ -0,0 +1,11 @@
+#version 450
+
+layout(location = 0) out vec2 fragNdc;
+
+// One triangle covering the screen, made from the vertex index alone: no
+// vertex buffer is bound and three vertices are drawn.
+void main() {
+    vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    fragNdc = uv * 2.0 - 1.0;
+    gl_Position = vec4(fragNdc, 1.0, 1.0);
+}
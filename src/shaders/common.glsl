@@ -0,0 +1,58 @@
+#ifndef COMMON_GLSL
+#define COMMON_GLSL
+
+// Layouts shared with the Rust side. Each must stay in step with the
+// #[repr(C)] struct it mirrors; the Rust structs assert their sizes.
+
+// ubo::MAX_LIGHTS.
+#define MAX_LIGHTS 4
+
+// ubo::Light, under std140.
+struct Light {
+    vec4 direction;
+    vec4 color;
+};
+
+// ubo::PerFrameUbo, under std140.
+#define PER_FRAME_UBO_FIELDS \
+    vec4 cameraPosition;     \
+    float time;              \
+    uint lightCount;         \
+    float ambient;           \
+    Light lights[MAX_LIGHTS];
+
+// ubo::PerViewUbo, under std140.
+#define PER_VIEW_UBO_FIELDS \
+    mat4 view;              \
+    mat4 proj;              \
+    vec4 viewport;
+
+// draw_constants::DrawConstants, under std140.
+#define DRAW_CONSTANT_FIELDS \
+    vec4 tint;               \
+    mat4 model;
+
+// How many of the draw constants, in the order above, are push constants;
+// the rest are read from the draw's dynamic uniform slot. Each stage reads
+// one field, so it only sees two cases: the default, and 0 from build.rs.
+#ifndef DRAW_PUSH_FIELDS
+#define DRAW_PUSH_FIELDS 2
+#endif
+
+// Fragment shaders that write the swapchain declare
+//     layout(constant_id = 1) const bool MANUAL_SRGB_ENCODE = false;
+// which is true when the swapchain format is UNORM: the hardware then stores
+// what they write as is, so they encode it themselves.
+vec3 linearToSrgb(vec3 c) {
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, c));
+}
+
+// animation::AnimationParams, under std430.
+struct AnimationParams {
+    vec3 axis;
+    float speed;
+    float phase;
+    mat4 base;
+};
+
+#endif
@@ -0,0 +1,41 @@
+#version 450
+
+// The depth buffer in grey: black at the near plane, white at the far plane,
+// linear in distance between them. Drawn over the frame by a second pass.
+
+#include "common.glsl"
+
+layout(constant_id = 1) const bool MANUAL_SRGB_ENCODE = false;
+// The projection's depth runs from 1 at the near plane to 0 at the far one.
+layout(constant_id = 2) const bool REVERSED_Z = false;
+
+#ifndef MULTISAMPLED
+#define MULTISAMPLED 0
+#endif
+
+#if MULTISAMPLED
+layout(set = 0, binding = 0) uniform sampler2DMS depthImage;
+#else
+layout(set = 0, binding = 0) uniform sampler2D depthImage;
+#endif
+
+layout(push_constant) uniform Planes {
+    float nearPlane;
+    float farPlane;
+} planes;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    // Sample 0 of a multisampled image, or mip 0 of a single-sampled one.
+    float depth = texelFetch(depthImage, ivec2(gl_FragCoord.xy), 0).r;
+    // The projection maps distance to an OpenGL-style NDC z in [-1, 1], of
+    // which the viewport keeps [0, 1] as is. Reversed-Z remaps all of it to
+    // [1, 0] instead.
+    float z = REVERSED_Z ? 1.0 - 2.0 * depth : depth;
+    float n = planes.nearPlane;
+    float f = planes.farPlane;
+    float distance = 2.0 * n * f / (f + n - z * (f - n));
+    vec3 grey = vec3((distance - n) / (f - n));
+    outColor = vec4(MANUAL_SRGB_ENCODE ? linearToSrgb(grey) : grey, 1.0);
+}
@@ -0,0 +1,13 @@
+#version 450
+
+#include "common.glsl"
+
+layout(constant_id = 1) const bool MANUAL_SRGB_ENCODE = false;
+
+layout(location = 0) in vec3 fragColor;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    outColor = vec4(MANUAL_SRGB_ENCODE ? linearToSrgb(fragColor) : fragColor, 1.0);
+}
@@ -0,0 +1,20 @@
+#version 450
+
+#include "common.glsl"
+
+layout(constant_id = 1) const bool MANUAL_SRGB_ENCODE = false;
+
+layout(set = 0, binding = 0) uniform sampler2D fontAtlas;
+
+layout(location = 0) in vec2 fragTexel;
+layout(location = 1) in vec4 fragColor;
+
+layout(location = 0) out vec4 outColor;
+
+// Glyphs are drawn a texel to a pixel, so the atlas is fetched rather than
+// filtered.
+void main() {
+    float coverage = texelFetch(fontAtlas, ivec2(fragTexel), 0).a;
+    vec3 color = MANUAL_SRGB_ENCODE ? linearToSrgb(fragColor.rgb) : fragColor.rgb;
+    outColor = vec4(color, fragColor.a * coverage);
+}
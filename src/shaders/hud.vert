@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) in vec2 inPosition;
+layout(location = 1) in vec2 inTexel;
+layout(location = 2) in vec4 inColor;
+
+layout(location = 0) out vec2 fragTexel;
+layout(location = 1) out vec4 fragColor;
+
+// Positions are already in normalized device coordinates, y down.
+void main() {
+    gl_Position = vec4(inPosition, 0.0, 1.0);
+    fragTexel = inTexel;
+    fragColor = inColor;
+}
@@ -0,0 +1,30 @@
+#version 450
+
+// The ID pass: the scene's cubes again, with each one's object ID in place
+// of its shading. Reads the main view and the transforms the frame drew.
+
+#include "common.glsl"
+
+layout(set = 0, binding = 0) uniform PerView {
+    PER_VIEW_UBO_FIELDS
+} camera;
+
+layout(std430, set = 1, binding = 0) readonly buffer ObjectTransforms {
+    mat4 models[];
+} transforms;
+
+// pick::PickPush.
+layout(push_constant) uniform PickPush {
+    mat4 model;
+    uint firstId;
+} draw;
+
+layout(location = 0) in vec3 inPosition;
+
+layout(location = 0) flat out uint fragId;
+
+void main() {
+    mat4 model = draw.model * transforms.models[gl_InstanceIndex];
+    gl_Position = camera.proj * camera.view * model * vec4(inPosition, 1.0);
+    fragId = draw.firstId + uint(gl_InstanceIndex);
+}
@@ -0,0 +1,29 @@
+#version 450
+
+// The scene, read back from the subpass before as an input attachment, with
+// its corners darkened and a gamma curve over it. Drawn over the whole
+// target by the render pass's last subpass, with `--post-process`.
+//
+// The scene's target has the swapchain's format, so with an sRGB format
+// this works on linear colour, and with a UNORM one on the colour the scene
+// already encoded, which it writes back as is.
+
+layout(input_attachment_index = 0, set = 0, binding = 0) uniform subpassInput scene;
+
+// post_process::PostParams.
+layout(push_constant) uniform Params {
+    float vignette;
+    float gamma;
+} params;
+
+layout(location = 0) in vec2 fragNdc;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    vec3 color = subpassLoad(scene).rgb;
+    // 0 at the centre to 1 in the corners.
+    float falloff = dot(fragNdc, fragNdc) / 2.0;
+    color *= 1.0 - params.vignette * falloff;
+    outColor = vec4(pow(color, vec3(1.0 / params.gamma)), 1.0);
+}
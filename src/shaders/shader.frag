@@ -1,9 +1,63 @@
 #version 450
 
+#include "common.glsl"
+
+layout(constant_id = 0) const bool NAIVE_SRGB_INTERPOLATION = false;
+layout(constant_id = 1) const bool MANUAL_SRGB_ENCODE = false;
+
 layout(location = 0) in vec3 fragColor;
+layout(location = 1) in vec2 fragUV;
+layout(location = 2) in vec3 fragWorldPosition;
+layout(location = 3) in vec3 fragNormal;
+
+layout(set = 0, binding = 0) uniform PerFrame {
+    PER_FRAME_UBO_FIELDS
+} frame;
+
+#if DRAW_PUSH_FIELDS >= 1
+layout(push_constant) uniform DrawPush {
+    vec4 tint;
+} draw;
+#else
+layout(set = 0, binding = 1) uniform DrawSlot {
+    DRAW_CONSTANT_FIELDS
+} draw;
+#endif
+
+const float SHININESS = 32.0;
+
+// sRGB, so samples come back linear and can multiply the vertex colour.
+layout(set = 3, binding = 0) uniform sampler2D faceLabels;
 
 layout(location = 0) out vec4 outColor;
 
+vec3 srgbToLinear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+}
+
 void main() {
-    outColor = vec4(fragColor, 1.0);
+    vec3 color = NAIVE_SRGB_INTERPOLATION ? srgbToLinear(fragColor) : fragColor;
+    // Blinn-Phong: ambient and diffuse tint the surface, each highlight is
+    // its light's colour scaled by the colour's w.
+    // Double-sided objects show their back faces too, lit from that side.
+    vec3 normal = normalize(gl_FrontFacing ? fragNormal : -fragNormal);
+    vec3 toCamera = normalize(frame.cameraPosition.xyz - fragWorldPosition);
+    vec3 diffuse = vec3(frame.ambient);
+    vec3 specular = vec3(0.0);
+    for (uint i = 0u; i < min(frame.lightCount, uint(MAX_LIGHTS)); i++) {
+        vec4 direction = frame.lights[i].direction;
+        vec4 lightColor = frame.lights[i].color;
+        float lambert = max(dot(normal, direction.xyz), 0.0);
+        vec3 halfway = normalize(direction.xyz + toCamera);
+        diffuse += lambert * lightColor.rgb;
+        if (lambert > 0.0) {
+            float highlight = pow(max(dot(normal, halfway), 0.0), SHININESS);
+            specular += highlight * lightColor.w * lightColor.rgb;
+        }
+    }
+    vec3 surface = color * diffuse * texture(faceLabels, fragUV).rgb + specular;
+    outColor = vec4(surface, 1.0) * draw.tint;
+    if (MANUAL_SRGB_ENCODE) {
+        outColor.rgb = linearToSrgb(outColor.rgb);
+    }
 }
@@ -1,17 +1,55 @@
 #version 450
 
-layout(binding = 0) uniform UniformBufferObject {
-    mat4 model;
-    mat4 view;
-    mat4 proj;
-} ubo;
+#include "common.glsl"
+
+layout(constant_id = 0) const bool NAIVE_SRGB_INTERPOLATION = false;
+
+layout(set = 0, binding = 0) uniform PerFrame {
+    PER_FRAME_UBO_FIELDS
+} frame;
+
+layout(set = 1, binding = 0) uniform PerView {
+    PER_VIEW_UBO_FIELDS
+} camera;
+
+#if DRAW_PUSH_FIELDS >= 2
+layout(push_constant) uniform DrawPush {
+    layout(offset = 16) mat4 model;
+} draw;
+#else
+layout(set = 0, binding = 1) uniform DrawSlot {
+    DRAW_CONSTANT_FIELDS
+} draw;
+#endif
+
+layout(std430, set = 2, binding = 0) readonly buffer ObjectTransforms {
+    mat4 models[];
+} transforms;
 
 layout(location = 0) in vec3 inPosition;
 layout(location = 1) in vec3 inColor;
+layout(location = 2) in vec2 inUV;
+layout(location = 3) in vec3 inNormal;
 
 layout(location = 0) out vec3 fragColor;
+layout(location = 1) out vec2 fragUV;
+layout(location = 2) out vec3 fragWorldPosition;
+layout(location = 3) out vec3 fragNormal;
+
+// The depth pre-pass runs this shader too and the main pass tests for EQUAL.
+invariant gl_Position;
+
+vec3 srgbToLinear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+}
 
 void main() {
-    gl_Position = ubo.proj * ubo.view * ubo.model * vec4(inPosition, 1.0);
-    fragColor = inColor;
+    mat4 model = draw.model * transforms.models[gl_InstanceIndex];
+    vec4 worldPosition = model * vec4(inPosition, 1.0);
+    gl_Position = camera.proj * camera.view * worldPosition;
+    fragWorldPosition = worldPosition.xyz;
+    fragColor = NAIVE_SRGB_INTERPOLATION ? inColor : srgbToLinear(inColor);
+    fragUV = inUV;
+    // The inverse transpose keeps normals perpendicular under non-uniform scale.
+    fragNormal = transpose(inverse(mat3(model))) * inNormal;
 }
@@ -0,0 +1,17 @@
+#version 450
+
+#include "common.glsl"
+
+layout(constant_id = 1) const bool MANUAL_SRGB_ENCODE = false;
+
+layout(set = 3, binding = 0) uniform samplerCube sky;
+
+layout(location = 0) in vec3 fragDirection;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    // The world is Z up; cube maps are Y up.
+    vec3 color = texture(sky, vec3(fragDirection.x, fragDirection.z, -fragDirection.y)).rgb;
+    outColor = vec4(MANUAL_SRGB_ENCODE ? linearToSrgb(color) : color, 1.0);
+}
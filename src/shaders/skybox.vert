@@ -0,0 +1,24 @@
+#version 450
+
+#include "common.glsl"
+
+// With reversed-Z the far plane is at depth 0.
+layout(constant_id = 2) const bool REVERSED_Z = false;
+
+layout(set = 1, binding = 0) uniform PerView {
+    PER_VIEW_UBO_FIELDS
+} camera;
+
+layout(location = 0) in vec3 inPosition;
+
+layout(location = 0) out vec3 fragDirection;
+
+// A unit cube around the eye. Only the view's rotation is applied, so the
+// sky stays put as the eye moves, and every vertex lands on the far plane:
+// the cleared depth, which the pipeline's or-equal test passes against and
+// the scene then draws in front of.
+void main() {
+    fragDirection = inPosition;
+    vec4 position = camera.proj * mat4(mat3(camera.view)) * vec4(inPosition, 1.0);
+    gl_Position = REVERSED_Z ? vec4(position.xy, 0.0, position.w) : position.xyww;
+}
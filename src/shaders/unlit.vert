@@ -0,0 +1,31 @@
+#version 450
+
+#include "common.glsl"
+
+// The shaders windows on another GPU draw with: the scene's meshes in their
+// vertex colours, without the main device's lights, textures or draw slots.
+
+layout(set = 0, binding = 0) uniform PerView {
+    PER_VIEW_UBO_FIELDS
+} camera;
+
+layout(push_constant) uniform UnlitPush {
+    mat4 model;
+} draw;
+
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inColor;
+// The object's transform, per instance, as shader.vert reads it from
+// ObjectTransforms.
+layout(location = 4) in mat4 instanceModel;
+
+layout(location = 0) out vec3 fragColor;
+
+vec3 srgbToLinear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+}
+
+void main() {
+    gl_Position = camera.proj * camera.view * draw.model * instanceModel * vec4(inPosition, 1.0);
+    fragColor = srgbToLinear(inColor);
+}
@@ -0,0 +1,396 @@
+use ash::vk;
+use log::{info, warn};
+use std::ffi::c_void;
+use std::fmt;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use super::ubo::align_up;
+use super::MEMORY_BLOCK_SIZE;
+
+/// A range of a device memory block, bound to one buffer or image. Freed
+/// with `Allocator::free`, never with `vkFreeMemory`.
+#[derive(Clone, Copy, Debug)]
+pub struct Allocation {
+    pub memory: vk::DeviceMemory,
+    pub offset: vk::DeviceSize,
+    pub size: vk::DeviceSize,
+    // Null unless the block is host visible.
+    mapped: *mut u8,
+}
+
+impl Allocation {
+    /// The allocation's first byte. Host-visible blocks stay mapped for as
+    /// long as they exist, so this never needs unmapping.
+    pub fn mapped_ptr(&self) -> *mut c_void {
+        assert!(
+            !self.mapped.is_null(),
+            "allocation in memory that isn't host visible"
+        );
+        self.mapped as *mut c_void
+    }
+}
+
+/// First-fit free list over one block's bytes, like the mesh pool's
+/// `RangeAllocator` but honouring each allocation's alignment. Free ranges
+/// are kept sorted and coalesced with their neighbours on release.
+pub struct FreeList {
+    size: vk::DeviceSize,
+    free: Vec<Range<vk::DeviceSize>>,
+}
+
+impl FreeList {
+    pub fn new(size: vk::DeviceSize) -> Self {
+        Self {
+            size,
+            free: vec![0..size],
+        }
+    }
+
+    /// `size` bytes from the first free range with room for them at an
+    /// `alignment` multiple. The padding skipped to align them stays free.
+    pub fn allocate(
+        &mut self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+    ) -> Option<Range<vk::DeviceSize>> {
+        let (index, start) = self.free.iter().enumerate().find_map(|(index, range)| {
+            let start = align_up(range.start, alignment);
+            (start + size <= range.end).then_some((index, start))
+        })?;
+        let range = self.free[index].clone();
+        let allocated = start..start + size;
+        let before = range.start..allocated.start;
+        let after = allocated.end..range.end;
+        self.free.splice(
+            index..=index,
+            [before, after]
+                .into_iter()
+                .filter(|range| !range.is_empty()),
+        );
+        Some(allocated)
+    }
+
+    pub fn release(&mut self, range: Range<vk::DeviceSize>) {
+        if range.is_empty() {
+            return;
+        }
+        let index = self.free.partition_point(|free| free.start < range.start);
+        self.free.insert(index, range);
+        if index + 1 < self.free.len() && self.free[index].end == self.free[index + 1].start {
+            self.free[index].end = self.free.remove(index + 1).end;
+        }
+        if index > 0 && self.free[index - 1].end == self.free[index].start {
+            self.free[index - 1].end = self.free.remove(index).end;
+        }
+    }
+
+    pub fn used(&self) -> vk::DeviceSize {
+        self.size
+            - self
+                .free
+                .iter()
+                .map(|range| range.end - range.start)
+                .sum::<vk::DeviceSize>()
+    }
+
+    /// The free ranges, in order.
+    pub fn free_ranges(&self) -> &[Range<vk::DeviceSize>] {
+        &self.free
+    }
+}
+
+/// MEMORY_BLOCK_SIZE, or an eighth of a smaller heap.
+pub fn standard_block_size(heap_size: vk::DeviceSize) -> vk::DeviceSize {
+    MEMORY_BLOCK_SIZE.min(heap_size / 8)
+}
+
+/// The block to allocate when no existing one has room for `size` bytes:
+/// a `standard` one, or one of its own for more than half of that.
+pub fn new_block_size(size: vk::DeviceSize, standard: vk::DeviceSize) -> vk::DeviceSize {
+    if size > standard / 2 {
+        size
+    } else {
+        standard
+    }
+}
+
+/// One `vkAllocateMemory`. Buffers and linear images never share a block
+/// with optimal images, so `bufferImageGranularity` can't apply.
+struct Block {
+    memory: vk::DeviceMemory,
+    memory_type: u32,
+    linear: bool,
+    mapped: *mut u8,
+    free: FreeList,
+    allocations: usize,
+}
+
+// The mapping is only written through the allocations handed out of it.
+unsafe impl Send for Block {}
+
+/// Memory in use on one heap, from `Allocator::stats`.
+#[derive(Clone, Copy, Debug)]
+pub struct HeapUsage {
+    pub heap_index: u32,
+    pub heap_size: vk::DeviceSize,
+    /// Allocated from the device in blocks.
+    pub block_bytes: vk::DeviceSize,
+    /// Handed out of those blocks.
+    pub allocated_bytes: vk::DeviceSize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AllocatorStats {
+    pub block_count: usize,
+    pub allocation_count: usize,
+    pub block_bytes: vk::DeviceSize,
+    pub allocated_bytes: vk::DeviceSize,
+    /// Only heaps with blocks.
+    pub heaps: Vec<HeapUsage>,
+}
+
+impl fmt::Display for AllocatorStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MIB: f64 = (1 << 20) as f64;
+        write!(
+            f,
+            "{} allocations, {:.1} of {:.1} MiB in {} blocks",
+            self.allocation_count,
+            self.allocated_bytes as f64 / MIB,
+            self.block_bytes as f64 / MIB,
+            self.block_count
+        )?;
+        for heap in &self.heaps {
+            write!(
+                f,
+                "; heap {}: {:.1} of {:.1} MiB in blocks, {:.0} MiB heap",
+                heap.heap_index,
+                heap.allocated_bytes as f64 / MIB,
+                heap.block_bytes as f64 / MIB,
+                heap.heap_size as f64 / MIB
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Hands out ranges of large device memory blocks, one set of blocks per
+/// memory type, instead of allocating memory for every buffer and image;
+/// drivers may allow as few as 4096 allocations. Resources bigger than half
+/// a block get a block of their own. Empty blocks are freed, except the
+/// last of their memory type, which staging buffers come and go in.
+pub struct Allocator {
+    device: ash::Device,
+    properties: vk::PhysicalDeviceMemoryProperties,
+    blocks: Mutex<Vec<Block>>,
+    live: AtomicUsize,
+}
+
+impl Allocator {
+    pub fn new(device: ash::Device, properties: vk::PhysicalDeviceMemoryProperties) -> Self {
+        Self {
+            device,
+            properties,
+            blocks: Mutex::new(Vec::new()),
+            live: AtomicUsize::new(0),
+        }
+    }
+
+    fn block_size(&self, memory_type: u32) -> vk::DeviceSize {
+        let heap = self.properties.memory_types[memory_type as usize].heap_index;
+        standard_block_size(self.properties.memory_heaps[heap as usize].size)
+    }
+
+    /// Memory of `memory_type` meeting `requirements`. `linear` is true for
+    /// buffers and linear images.
+    pub fn allocate(
+        &self,
+        requirements: vk::MemoryRequirements,
+        memory_type: u32,
+        linear: bool,
+    ) -> Result<Allocation, vk::Result> {
+        let mut blocks = self.blocks.lock().unwrap();
+        let found = blocks
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, block)| block.memory_type == memory_type && block.linear == linear)
+            .find_map(|(index, block)| {
+                let range = block
+                    .free
+                    .allocate(requirements.size, requirements.alignment)?;
+                Some((index, range.start))
+            });
+        let (index, offset) = match found {
+            Some(found) => found,
+            None => {
+                let size = new_block_size(requirements.size, self.block_size(memory_type));
+                let mut block = self.create_block(memory_type, linear, size)?;
+                let range = block
+                    .free
+                    .allocate(requirements.size, requirements.alignment)
+                    .unwrap();
+                blocks.push(block);
+                (blocks.len() - 1, range.start)
+            }
+        };
+        let block = &mut blocks[index];
+        block.allocations += 1;
+        self.live.fetch_add(1, Ordering::Relaxed);
+        Ok(Allocation {
+            memory: block.memory,
+            offset,
+            size: requirements.size,
+            mapped: if block.mapped.is_null() {
+                std::ptr::null_mut()
+            } else {
+                unsafe { block.mapped.add(offset as usize) }
+            },
+        })
+    }
+
+    fn create_block(
+        &self,
+        memory_type: u32,
+        linear: bool,
+        size: vk::DeviceSize,
+    ) -> Result<Block, vk::Result> {
+        let alloc_info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(size)
+            .memory_type_index(memory_type);
+        let memory = unsafe { self.device.allocate_memory(&alloc_info, None)? };
+        let host_visible = self.properties.memory_types[memory_type as usize]
+            .property_flags
+            .contains(vk::MemoryPropertyFlags::HOST_VISIBLE);
+        let mapped = if host_visible {
+            let mapped = unsafe {
+                self.device
+                    .map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())
+            };
+            match mapped {
+                Ok(mapped) => mapped as *mut u8,
+                Err(error) => {
+                    unsafe { self.device.free_memory(memory, None) };
+                    return Err(error);
+                }
+            }
+        } else {
+            std::ptr::null_mut()
+        };
+        info!(
+            "Allocated a {:.1} MiB block of memory type {}",
+            size as f64 / (1 << 20) as f64,
+            memory_type
+        );
+        Ok(Block {
+            memory,
+            memory_type,
+            linear,
+            mapped,
+            free: FreeList::new(size),
+            allocations: 0,
+        })
+    }
+
+    /// Returns `allocation`'s range to its block. The buffer or image bound
+    /// to it must be destroyed first.
+    pub fn free(&self, allocation: Allocation) {
+        let mut blocks = self.blocks.lock().unwrap();
+        let Some(index) = blocks
+            .iter()
+            .position(|block| block.memory == allocation.memory)
+        else {
+            warn!(
+                "Freeing an allocation from no block: {:?}",
+                allocation.memory
+            );
+            return;
+        };
+        let block = &mut blocks[index];
+        block
+            .free
+            .release(allocation.offset..allocation.offset + allocation.size);
+        block.allocations -= 1;
+        self.live.fetch_sub(1, Ordering::Relaxed);
+        if block.allocations > 0 {
+            return;
+        }
+        let (memory_type, linear) = (block.memory_type, block.linear);
+        let siblings = blocks
+            .iter()
+            .filter(|block| block.memory_type == memory_type && block.linear == linear)
+            .count();
+        if siblings > 1 || blocks[index].free.size != self.block_size(memory_type) {
+            let block = blocks.swap_remove(index);
+            self.destroy_block(&block);
+        }
+    }
+
+    fn destroy_block(&self, block: &Block) {
+        unsafe {
+            if !block.mapped.is_null() {
+                self.device.unmap_memory(block.memory);
+            }
+            self.device.free_memory(block.memory, None);
+        }
+    }
+
+    pub fn stats(&self) -> AllocatorStats {
+        let blocks = self.blocks.lock().unwrap();
+        let mut stats = AllocatorStats {
+            block_count: blocks.len(),
+            allocation_count: self.live.load(Ordering::Relaxed),
+            ..AllocatorStats::default()
+        };
+        for block in blocks.iter() {
+            let used = block.free.used();
+            stats.block_bytes += block.free.size;
+            stats.allocated_bytes += used;
+            let heap_index = self.properties.memory_types[block.memory_type as usize].heap_index;
+            let heap = match stats
+                .heaps
+                .iter_mut()
+                .position(|heap| heap.heap_index == heap_index)
+            {
+                Some(position) => &mut stats.heaps[position],
+                None => {
+                    stats.heaps.push(HeapUsage {
+                        heap_index,
+                        heap_size: self.properties.memory_heaps[heap_index as usize].size,
+                        block_bytes: 0,
+                        allocated_bytes: 0,
+                    });
+                    stats.heaps.last_mut().unwrap()
+                }
+            };
+            heap.block_bytes += block.free.size;
+            heap.allocated_bytes += used;
+        }
+        stats.heaps.sort_by_key(|heap| heap.heap_index);
+        stats
+    }
+
+    /// Frees every block, allocated from or not. Only before the device is
+    /// destroyed.
+    pub fn destroy(&self) {
+        for block in self.blocks.lock().unwrap().drain(..) {
+            self.destroy_block(&block);
+        }
+    }
+}
+
+impl Drop for Allocator {
+    fn drop(&mut self) {
+        let live = *self.live.get_mut();
+        // Blocks are left when `destroy` never ran: startup failed, and the
+        // rest of the device was abandoned along with them. Panicking while
+        // already unwinding would abort.
+        let abandoned = !self.blocks.get_mut().unwrap().is_empty();
+        debug_assert!(
+            live == 0 || abandoned || std::thread::panicking(),
+            "{} allocations were never freed",
+            live
+        );
+    }
+}
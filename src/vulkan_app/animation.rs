@@ -0,0 +1,355 @@
+use ash::vk;
+use cgmath::{InnerSpace, Matrix4, Rad, SquareMatrix, Vector3};
+use std::time::{Duration, Instant};
+
+use super::allocator::{Allocation, Allocator};
+use super::frame::{FrameIndex, FrameResources};
+use super::math::{self, Columns};
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::MAX_FRAMES_IN_FLIGHT;
+
+/// Workgroup size of `animate.comp`.
+pub const WORKGROUP_SIZE: u32 = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimationMode {
+    /// Model matrices are evaluated on the CPU and written into the mapped
+    /// transform buffer every frame.
+    Cpu,
+    /// `animate.comp` evaluates the matrices from the static parameter buffer.
+    Gpu,
+}
+
+/// Per-object animation, laid out to match `AnimationParams` in `animate.comp`
+/// under std430 (the mat4 starts on a 16-byte boundary).
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct AnimationParams {
+    pub axis: [f32; 3],
+    pub speed: f32,
+    pub phase: f32,
+    _padding: [f32; 3],
+    pub base: Matrix4<f32>,
+}
+
+// 32 bytes of header, then the mat4, as in common.glsl.
+const _: () = assert!(std::mem::size_of::<AnimationParams>() == 96);
+
+impl AnimationParams {
+    pub fn evaluate(&self, time: f32) -> Matrix4<f32> {
+        let axis = Vector3::from(self.axis);
+        self.base * Matrix4::from_axis_angle(axis, Rad(self.phase + self.speed * time))
+    }
+
+    /// `evaluate` on the configured math backend, for the bulk CPU path.
+    pub fn evaluate_columns(&self, time: f32) -> [[f32; 4]; 4] {
+        let base = math::Mat4::from_columns(self.base.into());
+        let axis = math::Vec3::from(self.axis);
+        (base * math::axis_angle(axis, self.phase + self.speed * time)).to_columns()
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct AnimationPushConstants {
+    pub time: f32,
+    pub count: u32,
+}
+
+/// One object spinning around Z at 90°/s reproduces the original cube; larger
+/// counts fill a grid of smaller cubes with varied axes and phases.
+pub fn animation_scene(count: u32) -> Vec<AnimationParams> {
+    let quarter_turn = std::f32::consts::FRAC_PI_2;
+    if count <= 1 {
+        return vec![AnimationParams {
+            axis: [0.0, 0.0, 1.0],
+            speed: quarter_turn,
+            phase: 0.0,
+            _padding: [0.0; 3],
+            base: Matrix4::identity(),
+        }];
+    }
+
+    let side = (count as f32).sqrt().ceil() as u32;
+    let spacing = 2.0 / side as f32;
+    (0..count)
+        .map(|i| {
+            let (x, y) = ((i % side) as f32, (i / side) as f32);
+            let offset = Vector3::new(-1.0 + spacing * (x + 0.5), -1.0 + spacing * (y + 0.5), 0.0);
+            let axis = Vector3::new(x + 1.0, y + 1.0, side as f32).normalize();
+            AnimationParams {
+                axis: axis.into(),
+                speed: quarter_turn * (1.0 + (i % 3) as f32 * 0.5),
+                phase: i as f32 * 0.37,
+                _padding: [0.0; 3],
+                base: Matrix4::from_translation(offset) * Matrix4::from_scale(spacing * 0.4),
+            }
+        })
+        .collect()
+}
+
+/// Objects that stay where `bases` put them, such as the instances of a
+/// generated primitive.
+pub fn still_scene(bases: impl Iterator<Item = Matrix4<f32>>) -> Vec<AnimationParams> {
+    bases
+        .map(|base| AnimationParams {
+            axis: [0.0, 0.0, 1.0],
+            speed: 0.0,
+            phase: 0.0,
+            _padding: [0.0; 3],
+            base,
+        })
+        .collect()
+}
+
+/// Animation time in seconds since startup, excluding time spent paused and
+/// scaled by the speed.
+pub struct AnimationClock {
+    start: Instant,
+    paused_at: Option<Instant>,
+    paused_total: Duration,
+    speed: f32,
+    // Animation time at `start`, carried over from before the last speed change.
+    offset: f32,
+}
+
+impl AnimationClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            paused_at: None,
+            paused_total: Duration::ZERO,
+            speed: 1.0,
+            offset: 0.0,
+        }
+    }
+
+    pub fn time(&self) -> f32 {
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        self.offset + self.speed * (now - self.start - self.paused_total).as_secs_f32()
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Changes the speed from now on without making the time jump.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.offset = self.time();
+        self.start = self.paused_at.unwrap_or_else(Instant::now);
+        self.paused_total = Duration::ZERO;
+        self.speed = speed;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    pub fn toggle_pause(&mut self) {
+        match self.paused_at.take() {
+            Some(paused_at) => self.paused_total += paused_at.elapsed(),
+            None => self.paused_at = Some(Instant::now()),
+        }
+    }
+}
+
+pub struct FrameTransforms {
+    pub buffer: vk::Buffer,
+    pub allocation: Allocation,
+    /// Set 1 of the graphics pipeline layout: the transforms, read by the vertex shader.
+    pub graphics_set: vk::DescriptorSet,
+    /// Parameters and transforms for `animate.comp`.
+    pub compute_set: vk::DescriptorSet,
+}
+
+/// Evaluates per-object model matrices into a per-frame transform buffer,
+/// either on the CPU or with a compute dispatch ahead of the render pass.
+pub struct Animator {
+    pub mode: AnimationMode,
+    pub objects: Vec<AnimationParams>,
+    pub params_buffer: vk::Buffer,
+    pub params_allocation: Allocation,
+    pub frames: FrameResources<FrameTransforms, MAX_FRAMES_IN_FLIGHT>,
+    pub transform_set_layout: vk::DescriptorSetLayout,
+    pub compute_set_layout: vk::DescriptorSetLayout,
+    pub descriptor_pool: vk::DescriptorPool,
+    pub compute_layout: vk::PipelineLayout,
+    pub compute_pipeline: vk::Pipeline,
+}
+
+impl Animator {
+    pub fn object_count(&self) -> u32 {
+        self.objects.len() as u32
+    }
+
+    pub fn transforms_size(&self) -> vk::DeviceSize {
+        (std::mem::size_of::<Matrix4<f32>>() * self.objects.len()) as vk::DeviceSize
+    }
+
+    /// Replaces the base transform of object `index` and re-uploads the
+    /// parameter buffer. The caller must make sure no frame is still reading it.
+    pub fn set_base(&mut self, index: usize, base: Matrix4<f32>) {
+        self.objects[index].base = base;
+        let params_size = std::mem::size_of_val(self.objects.as_slice()) as vk::DeviceSize;
+        let mut align = unsafe {
+            ash::util::Align::new(
+                self.params_allocation.mapped_ptr(),
+                std::mem::align_of::<AnimationParams>() as _,
+                params_size,
+            )
+        };
+        align.copy_from_slice(&self.objects);
+    }
+
+    pub fn graphics_set(&self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>) -> vk::DescriptorSet {
+        self.frames[frame].graphics_set
+    }
+
+    /// CPU path: evaluates every object and writes the results into `frame`'s
+    /// transform buffer, returning how long the evaluation took. Does nothing
+    /// in GPU mode.
+    pub fn write_transforms(
+        &self,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        time: f32,
+    ) -> Option<Duration> {
+        if self.mode != AnimationMode::Cpu {
+            return None;
+        }
+
+        let start = Instant::now();
+        let models: Vec<[[f32; 4]; 4]> = self
+            .objects
+            .iter()
+            .map(|object| object.evaluate_columns(time))
+            .collect();
+        let elapsed = start.elapsed();
+        let mut align = unsafe {
+            ash::util::Align::new(
+                self.frames[frame].allocation.mapped_ptr(),
+                std::mem::align_of::<[[f32; 4]; 4]>() as _,
+                self.transforms_size(),
+            )
+        };
+        align.copy_from_slice(&models);
+        Some(elapsed)
+    }
+
+    /// GPU path: records the dispatch that fills `frame`'s transform buffer and
+    /// the barrier that makes it visible to vertex shading. Must be recorded
+    /// outside the render pass. Does nothing in CPU mode.
+    pub fn record_dispatch(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        time: f32,
+    ) {
+        if self.mode != AnimationMode::Gpu {
+            return;
+        }
+
+        let push_constants = AnimationPushConstants {
+            time,
+            count: self.object_count(),
+        };
+        let barrier = vk::BufferMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(self.frames[frame].buffer)
+            .offset(0)
+            .size(vk::WHOLE_SIZE);
+
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.compute_pipeline,
+            );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.compute_layout,
+                0,
+                &[self.frames[frame].compute_set],
+                &[],
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.compute_layout,
+                vk::ShaderStageFlags::COMPUTE,
+                0,
+                std::slice::from_raw_parts(
+                    &push_constants as *const AnimationPushConstants as *const u8,
+                    std::mem::size_of::<AnimationPushConstants>(),
+                ),
+            );
+            device.cmd_dispatch(
+                command_buffer,
+                self.object_count().div_ceil(WORKGROUP_SIZE),
+                1,
+                1,
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::VERTEX_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                std::slice::from_ref(&barrier),
+                &[],
+            );
+        }
+    }
+
+    /// Reads `frame`'s transforms back and returns the largest element-wise
+    /// difference from the CPU evaluation at `time`. The frame's work must
+    /// have completed.
+    pub fn max_error(&self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>, time: f32) -> f32 {
+        let gpu_models = unsafe {
+            std::slice::from_raw_parts(
+                self.frames[frame].allocation.mapped_ptr() as *const Matrix4<f32>,
+                self.objects.len(),
+            )
+            .to_vec()
+        };
+
+        let mut max_error = 0.0f32;
+        for (object, gpu) in self.objects.iter().zip(gpu_models.iter()) {
+            let cpu = object.evaluate(time);
+            let cpu: &[f32; 16] = cpu.as_ref();
+            let gpu: &[f32; 16] = gpu.as_ref();
+            for (a, b) in cpu.iter().zip(gpu.iter()) {
+                max_error = max_error.max((a - b).abs());
+            }
+        }
+        max_error
+    }
+
+    pub fn destroy(
+        &mut self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        resources: &mut ResourceRegistry,
+    ) {
+        unsafe {
+            resources.unregister(ResourceKind::Pipeline, self.compute_pipeline);
+            device.destroy_pipeline(self.compute_pipeline, None);
+            device.destroy_pipeline_layout(self.compute_layout, None);
+            resources.unregister(ResourceKind::DescriptorPool, self.descriptor_pool);
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_descriptor_set_layout(self.compute_set_layout, None);
+            device.destroy_descriptor_set_layout(self.transform_set_layout, None);
+            for frame in self.frames.iter() {
+                resources.unregister(ResourceKind::Buffer, frame.buffer);
+                device.destroy_buffer(frame.buffer, None);
+                allocator.free(frame.allocation);
+            }
+            resources.unregister(ResourceKind::Buffer, self.params_buffer);
+            device.destroy_buffer(self.params_buffer, None);
+            allocator.free(self.params_allocation);
+        }
+    }
+}
@@ -1,562 +1,1334 @@
 use ash::{vk, Entry};
 use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 use std::ffi::{CStr, CString};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Rad, SquareMatrix, Vector3};
+use log::{error, info, warn};
+use std::time::{Duration, Instant};
+
+use super::allocator::{Allocation, Allocator, AllocatorStats};
+use super::animation::{
+    animation_scene, still_scene, AnimationClock, AnimationMode, AnimationParams,
+    AnimationPushConstants, Animator, FrameTransforms, WORKGROUP_SIZE,
+};
+use super::aspect::{self, AspectPolicy};
+use super::backend::{self, WindowBackend};
+use super::background::Background;
+use super::camera::{OrbitCamera, OrbitDrag};
+use super::capabilities::DeviceCapabilities;
+use super::capture::{CaptureTarget, CapturedFrame, ReadbackBuffer, ScreenshotWriter};
+use super::clear_color::{hue_cycle, parse_hex_color, CLEAR_COLOR_PRESETS};
+use super::config::{GpuSelector, RendererConfig};
+use super::config_resolver::{ConfigResolver, Downgrade, EffectiveConfig};
+use super::context::{self, SurfaceBundle, VkContext};
+use super::cube_shaders::CubeShaders;
+use super::debug::ValidationLog;
+use super::depth::DepthDirection;
+use super::diagnostics::{DeviceSummary, DiagnosticsReport, StartupDiagnostics};
+use super::draw_constants::{DrawConstantPacking, DrawConstants};
+use super::draw_list::{DrawList, DrawStats};
+use super::error::{Stage, VulkanError};
+use super::error_banner::ErrorBanner;
+use super::extra_window::ExtraWindow;
+use super::frame::{FrameIndex, FrameResources, FrameSync};
+use super::frame_stats::{FrameStats, GpuTimer};
+#[cfg(feature = "overlay")]
+use super::hud::{self, Hud, HudBuffer, HudVertex};
+use super::interpolation::{ColorInterpolation, SplitView};
+use super::journal::{Journal, JournalEvent, RecreateReason};
+use super::magnifier::Inset;
+use super::mapped_buffer::MappedBuffer;
+use super::math;
+use super::memory::{MemoryPlacement, MemoryTypeSelector, ResourceClass};
+use super::mesh_pool::{IndexData, MeshAllocation, MeshPool, RangeAllocator};
+use super::meshopt::{self, MeshIndex};
+use super::morph::{self, MorphedVertices};
+use super::parallel::{DrawBatch, SecondaryTarget, ThreadLocalPools};
+#[cfg(feature = "overlay")]
+use super::perf_graph::{FrameTimeHistory, GraphBuffer, GraphVertex, GRAPH_VERTICES};
+use super::pick::{self, PickDraw, PickPass, PickPush, PICK_FORMAT};
+use super::pipeline_cache::{Faces, PipelineKey, PipelineVariants, RenderMode};
+use super::plasma::{self, PlasmaPass};
+#[cfg(feature = "asset-loaders")]
+use super::png;
+#[cfg(feature = "post-processing")]
+use super::post_process::{PostParams, PostPass};
+use super::present_mode::PresentModePreference;
+use super::primitives;
+use super::queue::TransferQueue;
+use super::render_context::RenderContext;
+use super::render_scale::{self, RenderScaler, ScaledTarget};
+use super::rendering::{DynamicRenderer, PassTarget};
+use super::resources::{ResourceKind, ResourceRecord, ResourceRegistry};
+use super::scale_compare::{ComparisonTarget, ScaleTimes};
+use super::scene::{self, SceneObject};
+use super::selftest::{CanonicalScene, CheckOutcome, SelfTestReport};
+use super::settings::Settings;
+use super::skybox::{self, Skybox};
+use super::startup::StartupProfiler;
+use super::sun::{linear_to_srgb, SunState};
+use super::ubo::{
+    draw_slot_offset, per_view_offset, uniform_buffer_size, Light, PerFrameUbo, PerViewUbo,
+    MAGNIFIER_VIEW, MAIN_VIEW, MAX_LIGHTS, PER_FRAME_OFFSET, VIEW_COUNT,
+};
+use super::vertex::{self, Vertex, INDICES, VERTICES};
+use super::watchdog::{Checkpoint, MarkerBackend, MarkerExtension, Watchdog};
+
+use super::swapchain::{self, Swapchain};
+use super::swapchain_support;
+use super::texgen::Pattern;
+use super::texture::{self, OwnershipTransfer, Texture};
+#[cfg(feature = "tools")]
+use super::tools::{self, AxisDrag, Ray};
+#[cfg(feature = "tools")]
+use super::GIZMO_SIZE_PIXELS;
+#[cfg(feature = "overlay")]
+use super::HUD_VERTICES;
+use super::{
+    ANIMATED_OBJECT_COUNT, ANIMATION_TOLERANCE, DEPTH_PREPASS, ENABLE_VALIDATION,
+    EXTRA_WINDOW_YAW_DEGREES, GPU_ANIMATION, GROUND_TEXTURE_SIZE, INSTANCE_MEMORY_FRACTION,
+    LETTERBOX_ASPECT, LIGHT_AMBIENT, LIGHT_ELEVATION_DEGREES, LIGHT_ORBIT_SECONDS, MAGNIFIER_ZOOM,
+    MAX_FRAMES_IN_FLIGHT, MAX_FRAME_DELTA_SECONDS, MESH_POOL_INDICES, MESH_POOL_VERTICES,
+    MIN_RENDER_EXTENT, OPTIMIZE_MESHES, ORBIT_DEMO_CUBES, ORBIT_DEMO_RADIUS, ORBIT_DEMO_SCALE,
+    ORBIT_DEMO_SPEED, PANIC_ON_LEAK, PARALLEL_RECORD_CHUNK, PARALLEL_RECORD_MIN_DRAWS,
+    PREWARM_DEBUG_PIPELINES, QUANTIZE_MESH_POSITIONS, RENDER_SCALE_LADDER,
+    RENDER_SCALE_MAX_PERCENT, RENDER_SCALE_MIN_PERCENT, SCREENSHOT_QOI, SHUTDOWN_FENCE_TIMEOUT_MS,
+    SKYBOX_SIZE, SPECULAR_STRENGTH, UNFOCUSED_FRAME_INTERVAL_MS, WATCHDOG_TIMEOUT_MS,
+};
+
+const CAMERA_EYE: Point3<f32> = Point3::new(2.0, 2.0, 2.0);
+const CAMERA_FOV_Y: cgmath::Deg<f32> = cgmath::Deg(45.0);
+const CAMERA_NEAR: f32 = 0.1;
+const CAMERA_FAR: f32 = 10.0;
+/// Orbiting stops short of the poles, where the Z-up view would degenerate.
+const CAMERA_MAX_ELEVATION: f32 = 1.5;
+const CUBE_PIPELINE: u16 = 0;
+const DOUBLE_SIDED_PIPELINE: u16 = 1;
+// Materials differ only in their texture set.
+const CUBE_MATERIAL: u16 = 0;
+const TEXTURED_MATERIAL: u16 = 1;
+const MATERIAL_COUNT: usize = 2;
+// The skybox's cube map, after the materials' sets.
+const SKYBOX_TEXTURE_SET: u16 = MATERIAL_COUNT as u16;
+// The HUD's font atlas, after the skybox's.
+#[cfg(feature = "overlay")]
+const HUD_TEXTURE_SET: u16 = SKYBOX_TEXTURE_SET + 1;
+const TEXTURE_SET_COUNT: usize = MATERIAL_COUNT + 2;
+// Every mesh shares the one pool, so there is a mesh binding per index
+// type: `pooled_meshes` picks one.
+const POOLED_MESHES: u16 = 0;
+const POOLED_MESHES_U32: u16 = 1;
+// Indices into `meshes`.
+const COLOR_CUBE: usize = 0;
+const LABELED_CUBE: usize = 1;
+const TEXTURED_CUBE: usize = 2;
+const OPEN_BOX: usize = 3;
+const STATISTICS_LOG_INTERVAL: u64 = 300;
+
+/// What `VulkanApp::create` renders into.
+#[derive(Clone, Copy)]
+enum RenderTarget<'a> {
+    Window(&'a winit::window::Window),
+    /// An offscreen image of this size, for `render_to_image`.
+    Offscreen(vk::Extent2D),
+}
+
+/// The scene's orientation while something other than the clock steers it.
+#[derive(Clone, Copy, PartialEq)]
+struct ModelRotation {
+    yaw: f32,
+    pitch: f32,
+    // Animation time the spin stopped at.
+    spin_time: f32,
+}
 
-use cgmath::{Matrix4, Point3, Vector3};
-use std::time::Instant;
+/// The depth buffer shown in grey over the frame. A second render pass after
+/// the scene's samples the depth image, which the scene pass stores only
+/// while this exists.
+struct DepthView {
+    // `render_pass`, but storing depth. Null with dynamic rendering.
+    scene_pass: vk::RenderPass,
+    render_pass: vk::RenderPass,
+    // One per swapchain image, with only that image attached.
+    framebuffers: Vec<vk::Framebuffer>,
+    // The depth aspect of the depth image, or of `resolved`.
+    image_view: vk::ImageView,
+    // With MSAA on the dynamic rendering path of devices with depth stencil
+    // resolve, a single-sample image the scene resolves sample 0 of its
+    // depth into, for `image_view` to read, and its view as an attachment.
+    resolved: Option<(vk::Image, Allocation, vk::ImageView)>,
+    // The swapchain generation whose depth image `descriptor_set` reads.
+    generation: u64,
+    // Every aspect of the depth image, which its layout transitions need.
+    aspect_mask: vk::ImageAspectFlags,
+    sampler: vk::Sampler,
+    set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+}
 
-use super::debug::vulkan_debug_callback;
-use super::queue::QueueFamilyIndices;
-use super::ubo::UniformBufferObject;
-use super::vertex::{Vertex, INDICES, VERTICES};
+impl ModelRotation {
+    fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_angle_z(cgmath::Rad(self.yaw))
+            * Matrix4::from_angle_x(cgmath::Rad(self.pitch))
+    }
+}
 
-use super::swapchain_support::SwapchainSupportDetails;
 pub struct VulkanApp {
-    entry: Entry,
-    instance: ash::Instance,
-    debug_utils_loader: ash::extensions::ext::DebugUtils,
-    debug_messenger: vk::DebugUtilsMessengerEXT,
-    surface: vk::SurfaceKHR,
-    surface_loader: ash::extensions::khr::Surface,
-    physical_device: vk::PhysicalDevice,
-    device: ash::Device,
-    graphics_queue: vk::Queue,
-    present_queue: vk::Queue,
-    swapchain_loader: ash::extensions::khr::Swapchain,
-    swapchain: vk::SwapchainKHR,
-    swapchain_images: Vec<vk::Image>,
-    swapchain_format: vk::Format,
-    swapchain_extent: vk::Extent2D,
-    swapchain_image_views: Vec<vk::ImageView>,
+    context: VkContext,
+    validation_log: Box<ValidationLog>,
+    journal: Arc<Journal>,
+    window_backend: WindowBackend,
+    surface_extensions: Vec<String>,
+    capabilities: DeviceCapabilities,
+    draw_packing: DrawConstantPacking,
+    // Draw slots in each frame's uniform buffer; grown by
+    // `ensure_draw_slots` when a frame has more draws.
+    draw_slot_capacity: usize,
+    config: RendererConfig,
+    effective: EffectiveConfig,
+    downgrades: Vec<Downgrade>,
+    // `None` without a dedicated transfer family; uploads then go through
+    // the graphics queue.
+    transfer: Option<TransferQueue>,
+    swapchain: Swapchain,
+    // The swapchain format is UNORM, so the fragment shaders encode sRGB.
+    needs_manual_srgb: bool,
+    // The top-left part of each swapchain image the scene is shown in: all
+    // of it unless the surface is larger than the device's framebuffer
+    // limits.
+    display_extent: vk::Extent2D,
+    // What the scene is rendered at: `display_extent`, or away from full
+    // render scale that scaled, rendered into `scaled_targets` and stretched
+    // or shrunk over it.
+    render_extent: vk::Extent2D,
+    // One per swapchain image away from full render scale, empty at full
+    // scale.
+    scaled_targets: Vec<ScaledTarget>,
+    render_scaler: RenderScaler,
+    scale_times: ScaleTimes,
+    // The right half's scale with the A/B split on, and what it's drawn
+    // into, made with the other attachments.
+    split_percent: Option<u32>,
+    comparison: Option<ComparisonTarget>,
+    // Stepped by hand; applied before the next frame.
+    render_scale_changed: bool,
+    // From `can_upscale` for the current swapchain.
+    can_upscale: bool,
+    // Null with `dynamic_rendering`, and the swapchain's framebuffers
+    // empty.
     render_pass: vk::RenderPass,
+    // Begins the scene without a render pass, when the device can and
+    // DEPTH_PREPASS doesn't need subpasses.
+    dynamic_rendering: Option<DynamicRenderer>,
     pipeline_layout: vk::PipelineLayout,
-    graphics_pipeline: vk::Pipeline,
-    framebuffers: Vec<vk::Framebuffer>,
+    pipelines: PipelineVariants,
     command_pool: vk::CommandPool,
-    command_buffers: Vec<vk::CommandBuffer>,
-    image_available_semaphore: vk::Semaphore,
-    render_finished_semaphore: vk::Semaphore,
-    in_flight_fence: vk::Fence,
-    pub framebuffer_resized: bool,
-    queue_family_indices: QueueFamilyIndices,
-    vertex_buffer: vk::Buffer,
-    vertex_buffer_memory: vk::DeviceMemory,
-    index_buffer: vk::Buffer,
-    index_buffer_memory: vk::DeviceMemory,
-    uniform_buffers: Vec<vk::Buffer>,
-    uniform_buffers_memory: Vec<vk::DeviceMemory>,
+    frames: FrameResources<FrameSync, MAX_FRAMES_IN_FLIGHT>,
+    current_frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+    framebuffer_resized: bool,
+    // The window is minimised (zero-sized); nothing is drawn until it has a
+    // size again.
+    suspended: bool,
+    // Fully hidden behind other windows, on platforms that say so. Nothing
+    // is drawn, but resizes are still tracked for when it shows again.
+    occluded: bool,
+    // Out of focus, frames are drawn every UNFOCUSED_FRAME_INTERVAL_MS, the
+    // next one at `next_throttled_frame`.
+    unfocused: bool,
+    next_throttled_frame: Instant,
+    // Reloaded settings changed the effective present mode; recreate the
+    // swapchain after the next present.
+    settings_rebuild: bool,
+    mesh_pool: MeshPool,
+    meshes: Vec<MeshAllocation>,
+    // Created by the first `update_vertices`; until then pooled meshes are
+    // drawn straight from the pool.
+    morphed_vertices: Option<MorphedVertices>,
+    // The breathing demo rewrites the vertices every frame.
+    breathing: bool,
+    // Created the first time the plasma is shown, over `morphed_vertices`.
+    plasma: Option<PlasmaPass>,
+    // Whether `plasma` recolours the vertices every frame.
+    plasma_colors: bool,
+    // Per frame in flight, like the command buffers: a frame's uniforms are
+    // only rewritten once its fence has signalled. Mapped for as long as
+    // they live.
+    uniform_buffers: FrameResources<MappedBuffer, MAX_FRAMES_IN_FLIGHT>,
     descriptor_set_layout: vk::DescriptorSetLayout,
+    view_set_layout: vk::DescriptorSetLayout,
     descriptor_pool: vk::DescriptorPool,
-    descriptor_sets: Vec<vk::DescriptorSet>,
-    start_time: Instant,
-    depth_image: vk::Image,
-    depth_image_memory: vk::DeviceMemory,
-    depth_image_view: vk::ImageView,
+    descriptor_sets: FrameResources<vk::DescriptorSet, MAX_FRAMES_IN_FLIGHT>,
+    // Per frame in flight, one set per view.
+    view_descriptor_sets: FrameResources<Vec<vk::DescriptorSet>, MAX_FRAMES_IN_FLIGHT>,
+    clock: AnimationClock,
+    // Runs the sun, paused separately from the scene's animation.
+    sun_clock: AnimationClock,
+    // Something other than animation changed what the next frame shows.
+    // With the animation paused and nothing dirty, frames are skipped.
+    frame_dirty: bool,
+    depth_format: vk::Format,
+    // From `config.reversed_z`.
+    depth_direction: DepthDirection,
+    // Samples per pixel of the colour and depth attachments, as resolved
+    // from `config.msaa_samples`, which defaults to 4x.
+    msaa_samples: vk::SampleCountFlags,
+    // The multisampled colour attachment, resolved into the swapchain
+    // image; `None` without MSAA.
+    color_image: Option<(vk::Image, Allocation, vk::ImageView)>,
+    watchdog: Watchdog,
+    frame_count: u64,
+    draw_list: DrawList,
+    draw_stats: DrawStats,
+    split_view: SplitView,
+    // What the A key letterboxes to: the last ratio letterboxed at.
+    letterbox_aspect: f32,
+    // Held down: redraw the area under the cursor, zoomed, in a corner inset.
+    magnifier_held: bool,
+    error_banner: ErrorBanner,
+    resources: ResourceRegistry,
+    animator: Animator,
+    animation_check_pending: bool,
+    animation_error: Option<f32>,
+    cursor_position: (f32, f32),
+    // The animated object the gizmo and the double-sided toggle act on: the
+    // last one picked, or the first.
+    selected_object: usize,
+    // Per animated object: drawn without back-face culling.
+    double_sided: Vec<bool>,
+    #[cfg(feature = "tools")]
+    gizmo_drag: Option<AxisDrag>,
+    // Yaw around Z and elevation added to CAMERA_EYE's, in radians.
+    camera_orbit: (f32, f32),
+    // Divides the camera's distance from the origin.
+    camera_zoom: f32,
+    // Replaces the fixed view above while on.
+    orbit_camera: Option<OrbitCamera>,
+    // When `draw_frame` last advanced the orbit camera.
+    last_camera_update: Option<Instant>,
+    // From `--compile-shaders`, in place of the built-in SPIR-V.
+    cube_shaders: Option<Arc<CubeShaders>>,
+    // Set by `set_model_rotation`: replaces the animated spin.
+    model_rotation: Option<ModelRotation>,
+    // Drawn after the main cube, one draw each with its own model matrix.
+    objects: Vec<SceneObject>,
+    depth_view: Option<DepthView>,
+    // The render pass's last subpass, with `--post-process`.
+    #[cfg(feature = "post-processing")]
+    post: Option<PostPass>,
+    #[cfg(feature = "post-processing")]
+    post_params: PostParams,
+    // With `set_picking`, the object ID pass `pick` draws.
+    picking: Option<PickPass>,
+    // The frame last submitted, whose view and transforms `pick` draws the
+    // IDs with. None before the first frame and once the uniform buffers
+    // are rebuilt.
+    last_frame: Option<FrameIndex<MAX_FRAMES_IN_FLIGHT>>,
+    // Opened with `open_window`, each drawn and presented with the main
+    // window's frames.
+    extra_windows: Vec<ExtraWindow>,
+    // The other devices `--gpu-for-window` put windows on, which
+    // `ExtraWindow::device` indexes. Kept until shutdown once created.
+    render_contexts: Vec<RenderContext>,
+    // Linear, cleared to while there is no sun and no hue cycle.
+    clear_color: [f32; 4],
+    // Follow `clear_color::hue_cycle` instead of `clear_color`.
+    clear_color_cycle: bool,
+    // The entry of CLEAR_COLOR_PRESETS that C last picked, if any.
+    clear_preset: Option<usize>,
+    // Queued by `request_capture` and `request_screenshot`; the next frame
+    // copies its image into that frame's readback buffer.
+    capture_requests: Vec<CaptureTarget>,
+    readbacks: FrameResources<Option<ReadbackBuffer>, MAX_FRAMES_IN_FLIGHT>,
+    captured: Option<CapturedFrame>,
+    screenshots: ScreenshotWriter,
+    screenshot_count: u32,
+    // Set once `destroy` has run, so `Drop` after `shutdown` does nothing.
+    destroyed: bool,
+    statistics_queries: Option<FrameResources<vk::QueryPool, MAX_FRAMES_IN_FLIGHT>>,
+    // `None` if the device can't write timestamps on the graphics queue.
+    gpu_timer: Option<GpuTimer>,
+    // Created once a draw list is long enough to record in parallel.
+    record_pools: Option<ThreadLocalPools>,
+    frame_stats: FrameStats,
+    last_frame_start: Option<Instant>,
+    // Opened on the first copy and kept, since on X11 and Wayland the
+    // clipboard only holds the text while its owner is alive.
+    #[cfg(feature = "clipboard")]
+    clipboard: Option<arboard::Clipboard>,
+    // Generated from `config.ground_texture` once the device is up.
+    ground_texture: Option<Texture>,
+    // Set 3: the face label atlas, bound for every draw. The colour cube
+    // samples its blank area, the labeled cube its per-face cells.
+    texture_set_layout: vk::DescriptorSetLayout,
+    texture_pool: vk::DescriptorPool,
+    /// Set 3 for each material, then the skybox's.
+    texture_sets: Vec<vk::DescriptorSet>,
+    face_labels: Option<Texture>,
+    // Loaded from `config.cube_texture`; the cube is drawn with it unless
+    // face labels are shown.
+    cube_texture: Option<Texture>,
+    // Draw LABELED_CUBE instead of COLOR_CUBE.
+    show_face_labels: bool,
+    render_mode: RenderMode,
+    // Fullscreen pass for `config.background`, drawn before the scene.
+    background_pipeline: Option<vk::Pipeline>,
+    // From `config.skybox`, or generated; drawn for `Background::Skybox`.
+    skybox: Option<Skybox>,
+    // Frame-time graph, drawn in the corner after the scene when visible.
+    #[cfg(feature = "overlay")]
+    graph_layout: vk::PipelineLayout,
+    #[cfg(feature = "overlay")]
+    graph_pipeline: vk::Pipeline,
+    #[cfg(feature = "overlay")]
+    graph_buffers: FrameResources<GraphBuffer, MAX_FRAMES_IN_FLIGHT>,
+    #[cfg(feature = "overlay")]
+    graph_visible: bool,
+    #[cfg(feature = "overlay")]
+    frame_times: FrameTimeHistory,
+    // Text in the top-left corner, drawn after the graph.
+    #[cfg(feature = "overlay")]
+    hud: Hud,
+    #[cfg(feature = "overlay")]
+    hud_layout: vk::PipelineLayout,
+    #[cfg(feature = "overlay")]
+    hud_pipeline: vk::Pipeline,
+    #[cfg(feature = "overlay")]
+    hud_buffers: FrameResources<HudBuffer, MAX_FRAMES_IN_FLIGHT>,
+    // Uploaded once the device is up.
+    #[cfg(feature = "overlay")]
+    hud_atlas: Option<Texture>,
 }
 
 impl VulkanApp {
-    pub fn new(window: &winit::window::Window) -> Self {
-        let entry = unsafe { Entry::load().unwrap() };
-        let instance = Self::create_instance(&entry, window);
-        let (debug_utils_loader, debug_messenger) = Self::setup_debug_messenger(&entry, &instance);
-        let surface = unsafe {
-            ash_window::create_surface(
+    /// Objects created before a failure are not destroyed; the caller is
+    /// expected to report the error and exit.
+    pub fn new(
+        window: &winit::window::Window,
+        config: RendererConfig,
+    ) -> Result<Self, VulkanError> {
+        Self::create(RenderTarget::Window(window), config)
+    }
+
+    /// A renderer without a window, surface or swapchain, drawing into a
+    /// `width` x `height` offscreen image that `render_to_image` reads
+    /// back. Any GPU with a graphics queue will do. The render pass leaves
+    /// the image in TRANSFER_SRC_OPTIMAL, whatever `config.final_layout`
+    /// says.
+    pub fn new_headless(
+        width: u32,
+        height: u32,
+        mut config: RendererConfig,
+    ) -> Result<Self, VulkanError> {
+        config.final_layout = vk::ImageLayout::TRANSFER_SRC_OPTIMAL;
+        Self::create(
+            RenderTarget::Offscreen(vk::Extent2D { width, height }),
+            config,
+        )
+    }
+
+    fn create(target: RenderTarget<'_>, mut config: RendererConfig) -> Result<Self, VulkanError> {
+        let mut profiler = StartupProfiler::new();
+        #[cfg(not(feature = "post-processing"))]
+        if config.post_process {
+            warn!("--post-process needs the post-processing feature");
+            config.post_process = false;
+        }
+        let entry = unsafe { Entry::load()? };
+        StartupDiagnostics::begin(entry.try_enumerate_instance_version().ok().flatten());
+        let journal = Arc::new(Journal::default());
+        let validation_log = Box::new(ValidationLog::new(journal.clone()));
+        let display = match target {
+            RenderTarget::Window(window) => Some(window.raw_display_handle()),
+            RenderTarget::Offscreen(_) => None,
+        };
+        let instance = VkContext::create_instance(&entry, display, &mut config, &validation_log)?;
+        #[cfg(feature = "debug-utils")]
+        let debug_messenger = if ENABLE_VALIDATION {
+            Some(VkContext::setup_debug_messenger(
                 &entry,
                 &instance,
-                window.raw_display_handle(),
-                window.raw_window_handle(),
-                None,
-            )
-            .unwrap()
+                &validation_log,
+            )?)
+        } else {
+            None
+        };
+        let (window_backend, surface_extensions, surface) = match target {
+            RenderTarget::Window(window) => {
+                let window_backend =
+                    WindowBackend::from_display_handle(window.raw_display_handle());
+                let surface_extensions = backend::surface_extensions(window.raw_display_handle());
+                let surface = unsafe {
+                    ash_window::create_surface(
+                        &entry,
+                        &instance,
+                        window.raw_display_handle(),
+                        window.raw_window_handle(),
+                        None,
+                    )
+                }
+                .map_err(|error| {
+                    backend::surface_creation_failed(window_backend, &surface_extensions, error);
+                    VulkanError::at(Stage::SurfaceCreation)(error)
+                })?;
+                (window_backend, surface_extensions, Some(surface))
+            }
+            RenderTarget::Offscreen(_) => (WindowBackend::Headless, Vec::new(), None),
         };
+        info!(
+            "Window backend: {} ({})",
+            window_backend,
+            surface_extensions.join(", ")
+        );
         let surface_loader = ash::extensions::khr::Surface::new(&entry, &instance);
-        let (physical_device, queue_family_indices) =
-            Self::pick_physical_device(&instance, &surface_loader, surface);
-        let (device, graphics_queue, present_queue) =
-            Self::create_logical_device(&instance, physical_device, &queue_family_indices);
-
-        let (vertex_buffer, vertex_buffer_memory) = Self::create_vertex_buffer(
+        StartupDiagnostics::record(|diagnostics| {
+            diagnostics.devices = DeviceSummary::enumerate(&instance);
+        });
+        profiler.phase("instance");
+        let (physical_device, queue_family_indices) = VkContext::pick_physical_device(
             &instance,
-            &device,
-            physical_device,
-            &queue_family_indices,
-            &VERTICES,
-        );
-        let (index_buffer, index_buffer_memory) = Self::create_index_buffer(
+            &surface_loader,
+            surface,
+            config.gpu.as_ref(),
+        )?;
+        let capabilities = DeviceCapabilities::query(
             &instance,
-            &device,
             physical_device,
-            &queue_family_indices,
-            &INDICES,
+            VkContext::instance_api_version(&entry),
         );
-
-        let swapchain_loader = ash::extensions::khr::Swapchain::new(&instance, &device);
-        let (swapchain, swapchain_format, swapchain_extent) = Self::create_swapchain(
-            &instance,
-            &device,
-            physical_device,
-            &surface_loader,
+        info!(
+            "Y flip: {}",
+            if capabilities.negative_viewport_height {
+                "negative viewport height"
+            } else {
+                "projection matrix"
+            }
+        );
+        if !capabilities.pipeline_statistics_query {
+            info!("Pipeline statistics queries not supported");
+        }
+        if !capabilities.fill_mode_non_solid {
+            info!("Non-solid fill modes not supported; wireframe disabled");
+        }
+        if !capabilities.timestamps {
+            info!("Timestamp queries not supported; GPU frame times disabled");
+        }
+        let draw_packing =
+            DrawConstantPacking::choose(&capabilities.limits, config.push_constant_budget)
+                .map_err(|message| VulkanError::Config {
+                    stage: Stage::PipelineLayout,
+                    message,
+                })?;
+        info!("Draw constants: {}", draw_packing);
+        let memory_properties =
+            unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let memory = MemoryTypeSelector::new(memory_properties, &config.memory_types).map_err(
+            |message| VulkanError::Config {
+                stage: Stage::MemorySelection,
+                message,
+            },
+        )?;
+        let surface =
+            surface.map(|surface| SurfaceBundle::new(surface_loader, surface, physical_device));
+        if let Some(bundle) = &surface {
+            StartupDiagnostics::record(|diagnostics| {
+                diagnostics.surface_formats = bundle.support.formats.clone();
+                diagnostics.present_modes = bundle.support.present_modes.clone();
+            });
+        }
+        let present_modes = match &surface {
+            Some(bundle) => bundle.support.present_modes.clone(),
+            // Nothing is presented, so the requested mode stands.
+            None => vec![config.present_mode],
+        };
+        let (effective, downgrades) =
+            ConfigResolver::new(&capabilities, &present_modes).resolve(&config);
+        for downgrade in &downgrades {
+            info!("Config downgraded: {}", downgrade);
+        }
+        let (device, graphics_queue, present_queue, transfer_queue, marker_extension) =
+            VkContext::create_logical_device(
+                &instance,
+                physical_device,
+                &queue_family_indices,
+                &capabilities,
+                &effective,
+                surface.is_some(),
+                &mut config,
+            )?;
+        // The depth pre-pass and post-process are subpasses of the render
+        // pass.
+        let dynamic_rendering = capabilities
+            .dynamic_rendering
+            .filter(|_| !DEPTH_PREPASS && !config.post_process)
+            .map(|support| {
+                info!("Render path: dynamic rendering ({})", support);
+                DynamicRenderer::new(&instance, &device, support)
+            });
+        if dynamic_rendering.is_none() {
+            info!("Render path: render pass");
+        }
+        let allocator = Allocator::new(device.clone(), memory_properties);
+        let context = VkContext {
+            entry,
+            instance,
+            #[cfg(feature = "debug-utils")]
+            debug_messenger,
             surface,
-            &queue_family_indices,
-            &swapchain_loader,
-            window,
+            physical_device,
+            device,
+            graphics_queue,
+            present_queue,
+            queue_family_indices,
+            memory,
+            allocator,
+            owns_instance: true,
+        };
+        let mut resources = ResourceRegistry::default();
+        let watchdog = Self::create_watchdog(&context, marker_extension, &mut resources)?;
+        profiler.phase("device");
+        let (vertices, indices) = Self::load_mesh("cube", &VERTICES, &INDICES);
+
+        let mut mesh_pool = Self::create_mesh_pool(&context, &mut resources)?;
+        let cube_mesh = mesh_pool
+            .upload(&vertices, &indices)
+            .expect("mesh pool too small for the cube");
+        let (labeled_vertices, labeled_indices) = vertex::labeled_cube();
+        let (vertices, indices) =
+            Self::load_mesh("labeled cube", &labeled_vertices, &labeled_indices);
+        let labeled_mesh = mesh_pool
+            .upload(&vertices, &indices)
+            .expect("mesh pool too small for the labeled cube");
+        let (textured_vertices, textured_indices) = vertex::textured_cube();
+        let (vertices, indices) =
+            Self::load_mesh("textured cube", &textured_vertices, &textured_indices);
+        let textured_mesh = mesh_pool
+            .upload(&vertices, &indices)
+            .expect("mesh pool too small for the textured cube");
+        let (open_vertices, open_indices) = vertex::open_box();
+        let (vertices, indices) = Self::load_mesh("open box", &open_vertices, &open_indices);
+        let open_box_mesh = mesh_pool
+            .upload(&vertices, &indices)
+            .expect("mesh pool too small for the open box");
+        profiler.phase("mesh upload");
+
+        let mut swapchain = match target {
+            RenderTarget::Window(window) => Swapchain::new(
+                &context,
+                window,
+                effective.present_mode,
+                config.swapchain_usage,
+                &mut resources,
+            )?,
+            RenderTarget::Offscreen(extent) => {
+                Swapchain::offscreen(&context, extent, config.swapchain_usage, &mut resources)?
+            }
+        };
+        let (swapchain_format, swapchain_extent, swapchain_usage) =
+            (swapchain.format, swapchain.extent, swapchain.usage);
+        swapchain_support::check_final_layout(config.final_layout, swapchain_usage).map_err(
+            |message| VulkanError::Config {
+                stage: Stage::SwapchainCreation,
+                message,
+            },
+        )?;
+        let display_extent = Self::display_extent(&capabilities, swapchain_extent);
+        // At full scale until frame times say otherwise.
+        let render_scaler = RenderScaler::new(config.adaptive_resolution);
+        let can_upscale = Self::can_upscale(&context, swapchain_format, swapchain_usage);
+        if config.adaptive_resolution.is_some() && !can_upscale {
+            warn!("--adaptive-resolution needs swapchain images that can be blitted onto");
+        }
+        let render_extent = display_extent;
+        let depth_format = context.find_depth_format();
+        profiler.phase("swapchain");
+        let descriptor_set_layout = Self::create_descriptor_set_layout(
+            &context.device,
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            draw_packing.uniform_stages(),
         );
-        let swapchain_images = unsafe { swapchain_loader.get_swapchain_images(swapchain).unwrap() };
-        let swapchain_image_views =
-            Self::create_image_views(&device, &swapchain_images, swapchain_format);
-        let depth_format = Self::find_depth_format(&instance, physical_device);
-        let descriptor_set_layout = Self::create_descriptor_set_layout(&device);
-        let render_pass = Self::create_render_pass(&device, swapchain_format, depth_format);
-        let (graphics_pipeline, pipeline_layout) = Self::create_graphics_pipeline(
-            &device,
+        // The background's fragment shader reads the view to cast its rays.
+        let view_set_layout = Self::create_descriptor_set_layout(
+            &context.device,
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            vk::ShaderStageFlags::empty(),
+        );
+        let (texture_set_layout, texture_pool, texture_sets) =
+            Self::create_texture_sets(&context.device, &mut resources);
+        let objects = match config.menger_level {
+            Some(level) => Self::menger_scene(&context, level),
+            None => animation_scene(config.draw_stress.unwrap_or(ANIMATED_OBJECT_COUNT)),
+        };
+        let animator = Self::create_animator(&context, objects, &mut resources)?;
+        profiler.phase("animation");
+        // Sample count flags are the counts themselves.
+        let msaa_samples = vk::SampleCountFlags::from_raw(effective.msaa_samples);
+        let render_pass = if dynamic_rendering.is_some() {
+            vk::RenderPass::null()
+        } else {
+            Self::create_render_pass(
+                &context.device,
+                swapchain_format,
+                depth_format,
+                msaa_samples,
+                config.final_layout,
+                !config.background.covers_screen(),
+                false,
+                config.post_process,
+            )
+        };
+        let depth_direction = DepthDirection::new(config.reversed_z);
+        let pass_target = PassTarget {
             render_pass,
-            swapchain_extent,
+            color_format: swapchain_format,
+            depth_format,
+            samples: msaa_samples,
+            depth_direction,
+        };
+        let pipeline_layout = Self::create_pipeline_layout(
+            &context.device,
             descriptor_set_layout,
+            view_set_layout,
+            animator.transform_set_layout,
+            texture_set_layout,
+            &draw_packing.push_constant_ranges(),
         );
-        let (depth_image, depth_image_memory, depth_image_view) =
-            Self::create_depth_resources(&instance, &device, physical_device, swapchain_extent);
-        let framebuffers = Self::create_framebuffers(
-            &device,
-            &swapchain_image_views,
-            depth_image_view,
-            render_pass,
-            swapchain_extent,
+        let needs_manual_srgb = swapchain_support::is_unorm_format(swapchain_format);
+        let background_pipeline = Self::create_background_pipeline(
+            &context.device,
+            pass_target,
+            pipeline_layout,
+            config.background,
+            needs_manual_srgb,
+            &mut resources,
         );
-        let command_pool = Self::create_command_pool(&device, &queue_family_indices);
-        let (vertex_buffer, vertex_buffer_memory) = Self::create_vertex_buffer(
-            &instance,
-            &device,
-            physical_device,
-            &queue_family_indices,
-            &VERTICES,
+        let command_pool = context.create_command_pool();
+        let transfer = context
+            .queue_family_indices
+            .transfer_family
+            .zip(transfer_queue)
+            .map(|(family, queue)| TransferQueue {
+                family,
+                queue,
+                command_pool: context.create_transfer_command_pool(family),
+            });
+        match &transfer {
+            Some(transfer) => info!("Uploads on transfer queue family {}", transfer.family),
+            None => info!("No dedicated transfer queue; uploads on the graphics queue"),
+        }
+        swapchain.create_depth(
+            &context,
+            command_pool,
+            render_extent,
+            msaa_samples,
+            &mut resources,
+        )?;
+        let color_image = Self::create_color_resources(
+            &context,
+            swapchain_format,
+            render_extent,
+            msaa_samples,
+            &mut resources,
+        )?;
+        #[cfg(feature = "post-processing")]
+        let mut post = config
+            .post_process
+            .then(|| Self::create_post_pass(&context.device, render_pass, &mut resources));
+        #[cfg(feature = "post-processing")]
+        if let Some(post) = &mut post {
+            post.create_targets(
+                &context,
+                swapchain_format,
+                render_extent,
+                swapchain.images.len(),
+                0,
+                &mut resources,
+            )?;
+        }
+        #[cfg(feature = "post-processing")]
+        let post_views = post
+            .as_ref()
+            .map(PostPass::target_views)
+            .unwrap_or_default();
+        #[cfg(not(feature = "post-processing"))]
+        let post_views = Vec::new();
+        if dynamic_rendering.is_none() {
+            let image_views = swapchain.image_views.clone();
+            swapchain.create_framebuffers(
+                &context.device,
+                &image_views,
+                color_image.map(|(_, _, view)| view),
+                &post_views,
+                render_pass,
+                render_extent,
+            );
+        }
+        #[cfg(feature = "overlay")]
+        let graph_layout = unsafe {
+            context
+                .device
+                .create_pipeline_layout(&vk::PipelineLayoutCreateInfo::builder(), None)
+                .unwrap()
+        };
+        #[cfg(feature = "overlay")]
+        let graph_pipeline = Self::create_graph_pipeline(
+            &context.device,
+            pass_target,
+            graph_layout,
+            needs_manual_srgb,
+            &mut resources,
         );
-        let (index_buffer, index_buffer_memory) = Self::create_index_buffer(
-            &instance,
-            &device,
-            physical_device,
-            &queue_family_indices,
-            &INDICES,
+        #[cfg(feature = "overlay")]
+        let graph_buffers = Self::create_graph_buffers(&context, &mut resources)?;
+        #[cfg(feature = "overlay")]
+        let hud_layout = unsafe {
+            context
+                .device
+                .create_pipeline_layout(
+                    &vk::PipelineLayoutCreateInfo::builder()
+                        .set_layouts(std::slice::from_ref(&texture_set_layout)),
+                    None,
+                )
+                .unwrap()
+        };
+        #[cfg(feature = "overlay")]
+        let hud_pipeline = Self::create_hud_pipeline(
+            &context.device,
+            pass_target,
+            hud_layout,
+            needs_manual_srgb,
+            &mut resources,
         );
-        let descriptor_set_layout = Self::create_descriptor_set_layout(&device);
-        let (descriptor_pool, descriptor_sets) =
-            Self::create_descriptor_pool(&device, swapchain_images.len(), descriptor_set_layout);
-
-        let (uniform_buffers, uniform_buffers_memory) = Self::create_uniform_buffers(
-            &instance,
-            &device,
-            physical_device,
-            swapchain_images.len(),
+        #[cfg(feature = "overlay")]
+        let hud_buffers = FrameResources::try_from_fn(|_| {
+            HudBuffer::new(&context, HUD_VERTICES, &mut resources)
+        })?;
+        #[cfg(feature = "overlay")]
+        let hud = {
+            let properties = unsafe {
+                context
+                    .instance
+                    .get_physical_device_properties(context.physical_device)
+            };
+            Hud::new(
+                unsafe { CStr::from_ptr(properties.device_name.as_ptr()) }
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        };
+        profiler.phase("framebuffers");
+        let descriptor_pool = Self::create_descriptor_pool(
+            &context.device,
+            draw_packing.uses_uniform_slot(),
+            &mut resources,
         );
-        let command_buffers =
-            Self::create_command_buffers(&device, command_pool, swapchain_images.len());
-        let (image_available_semaphore, render_finished_semaphore, in_flight_fence) =
-            Self::create_sync_objects(&device);
 
-        let descriptor_sets = Self::create_descriptor_sets(
-            &device,
+        let uniform_buffers = Self::create_uniform_buffers(
+            &context,
+            draw_packing.uniform_slots(),
+            draw_packing.slot_stride(),
+            &mut resources,
+        )?;
+        let frames = Self::create_frame_resources(&context.device, command_pool);
+
+        let (descriptor_sets, view_descriptor_sets) = Self::create_descriptor_sets(
+            &context.device,
             descriptor_pool,
             descriptor_set_layout,
+            view_set_layout,
             &uniform_buffers,
-            swapchain_images.len(),
+            draw_packing.uses_uniform_slot(),
         );
+        profiler.phase("descriptors");
 
-        Self {
-            entry,
-            instance,
-            debug_utils_loader,
-            debug_messenger,
-            surface,
-            surface_loader,
-            physical_device,
-            device,
-            graphics_queue,
-            present_queue,
-            swapchain_loader,
+        let counts: Vec<String> = resources
+            .counts_by_kind()
+            .iter()
+            .map(|(kind, count)| format!("{:?} x{}", kind, count))
+            .collect();
+        info!("GPU resources after init: {}", counts.join(", "));
+        info!("GPU memory after init: {}", context.allocator.stats());
+
+        let cube_shaders = if config.compile_shaders {
+            match CubeShaders::compile() {
+                Ok(shaders) => {
+                    info!("Compiled the cube's shaders from source");
+                    Some(Arc::new(shaders))
+                }
+                Err(error) => {
+                    error!("{}", error);
+                    warn!("Using the cube's built-in shaders");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let double_sided = vec![config.open_box; animator.objects.len()];
+        let mut clock = AnimationClock::new();
+        clock.set_speed(config.animation_speed);
+        let camera_orbit = (
+            cgmath::Rad::from(config.camera_orbit.0).0,
+            cgmath::Rad::from(config.camera_orbit.1).0,
+        );
+        #[cfg(feature = "overlay")]
+        let graph_visible = config.show_frame_graph;
+        let letterbox_aspect = match config.aspect_policy {
+            AspectPolicy::Letterbox(ratio) => ratio,
+            AspectPolicy::Stretch => LETTERBOX_ASPECT,
+        };
+        let gpu_timer = capabilities
+            .timestamps
+            .then(|| GpuTimer::new(&context.device, capabilities.timestamp_period));
+        let mut app = Self {
+            context,
+            validation_log,
+            journal,
+            window_backend,
+            surface_extensions,
+            capabilities,
+            draw_packing,
+            draw_slot_capacity: draw_packing.uniform_slots(),
+            config,
+            effective,
+            downgrades,
+            transfer,
             swapchain,
-            swapchain_images,
-            swapchain_format,
-            swapchain_extent,
-            swapchain_image_views,
+            needs_manual_srgb,
+            display_extent,
+            render_extent,
+            scaled_targets: Vec::new(),
+            render_scaler,
+            scale_times: ScaleTimes::default(),
+            split_percent: None,
+            comparison: None,
+            render_scale_changed: false,
+            can_upscale,
             render_pass,
+            dynamic_rendering,
             pipeline_layout,
-            graphics_pipeline,
-            framebuffers,
+            pipelines: PipelineVariants::default(),
             command_pool,
-            command_buffers,
-            image_available_semaphore,
-            render_finished_semaphore,
-            in_flight_fence,
+            frames,
+            current_frame: FrameIndex::first(),
             framebuffer_resized: false,
-            queue_family_indices,
-            vertex_buffer,
-            vertex_buffer_memory,
-            index_buffer,
-            index_buffer_memory,
+            suspended: false,
+            occluded: false,
+            unfocused: false,
+            next_throttled_frame: Instant::now(),
+            settings_rebuild: false,
+            mesh_pool,
+            meshes: vec![cube_mesh, labeled_mesh, textured_mesh, open_box_mesh],
             uniform_buffers,
-            uniform_buffers_memory,
             descriptor_set_layout,
+            view_set_layout,
             descriptor_pool,
             descriptor_sets,
-            start_time: Instant::now(),
-            depth_image,
-            depth_image_memory,
-            depth_image_view,
-        }
-    }
-
-    fn create_instance(entry: &Entry, window: &winit::window::Window) -> ash::Instance {
-        let app_name = CString::new("Vulkan Triangle").unwrap();
-        let engine_name = CString::new("No Engine").unwrap();
-        let app_info = vk::ApplicationInfo::builder()
-            .application_name(&app_name)
-            .application_version(vk::make_api_version(0, 1, 0, 0))
-            .engine_name(&engine_name)
-            .engine_version(vk::make_api_version(0, 1, 0, 0))
-            .api_version(vk::API_VERSION_1_0);
-
-        let mut extension_names =
-            ash_window::enumerate_required_extensions(window.raw_display_handle())
-                .unwrap()
-                .to_vec();
-        extension_names.push(ash::extensions::ext::DebugUtils::name().as_ptr());
-
-        let create_info = vk::InstanceCreateInfo::builder()
-            .application_info(&app_info)
-            .enabled_extension_names(&extension_names);
-
-        unsafe {
-            entry
-                .create_instance(&create_info, None)
-                .expect("Failed to create instance")
-        }
-    }
-
-    fn setup_debug_messenger(
-        entry: &Entry,
-        instance: &ash::Instance,
-    ) -> (ash::extensions::ext::DebugUtils, vk::DebugUtilsMessengerEXT) {
-        let debug_info = vk::DebugUtilsMessengerCreateInfoEXT::builder()
-            .message_severity(
-                vk::DebugUtilsMessageSeverityFlagsEXT::ERROR
-                    | vk::DebugUtilsMessageSeverityFlagsEXT::WARNING,
-            )
-            .message_type(
-                vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
-                    | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
-                    | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
-            )
-            .pfn_user_callback(Some(vulkan_debug_callback));
-
-        let debug_utils_loader = ash::extensions::ext::DebugUtils::new(entry, instance);
-        let debug_messenger = unsafe {
-            debug_utils_loader
-                .create_debug_utils_messenger(&debug_info, None)
-                .unwrap()
+            view_descriptor_sets,
+            clock,
+            sun_clock: AnimationClock::new(),
+            frame_dirty: true,
+            depth_format,
+            depth_direction,
+            msaa_samples,
+            color_image,
+            watchdog,
+            frame_count: 0,
+            draw_list: DrawList::default(),
+            draw_stats: DrawStats::default(),
+            split_view: SplitView::new(),
+            letterbox_aspect,
+            magnifier_held: false,
+            morphed_vertices: None,
+            breathing: false,
+            plasma: None,
+            plasma_colors: false,
+            error_banner: ErrorBanner::default(),
+            resources,
+            animator,
+            animation_check_pending: GPU_ANIMATION,
+            animation_error: None,
+            cursor_position: (0.0, 0.0),
+            selected_object: 0,
+            double_sided,
+            #[cfg(feature = "tools")]
+            gizmo_drag: None,
+            camera_orbit,
+            camera_zoom: 1.0,
+            orbit_camera: None,
+            last_camera_update: None,
+            cube_shaders,
+            model_rotation: None,
+            objects: Vec::new(),
+            depth_view: None,
+            #[cfg(feature = "post-processing")]
+            post,
+            #[cfg(feature = "post-processing")]
+            post_params: PostParams::default(),
+            picking: None,
+            last_frame: None,
+            extra_windows: Vec::new(),
+            render_contexts: Vec::new(),
+            clear_color: {
+                let [r, g, b] = config.clear_color;
+                [r, g, b, 1.0]
+            },
+            clear_color_cycle: config.clear_color_cycle,
+            clear_preset: None,
+            capture_requests: Vec::new(),
+            readbacks: FrameResources::new(std::array::from_fn(|_| None)),
+            captured: None,
+            screenshots: ScreenshotWriter::new(),
+            screenshot_count: 0,
+            destroyed: false,
+            statistics_queries: None,
+            gpu_timer,
+            record_pools: None,
+            frame_stats: FrameStats::default(),
+            last_frame_start: None,
+            #[cfg(feature = "clipboard")]
+            clipboard: None,
+            ground_texture: None,
+            texture_set_layout,
+            texture_pool,
+            texture_sets,
+            face_labels: None,
+            cube_texture: None,
+            show_face_labels: false,
+            render_mode: RenderMode::Fill,
+            background_pipeline,
+            skybox: None,
+            #[cfg(feature = "overlay")]
+            graph_layout,
+            #[cfg(feature = "overlay")]
+            graph_pipeline,
+            #[cfg(feature = "overlay")]
+            graph_buffers,
+            #[cfg(feature = "overlay")]
+            graph_visible,
+            #[cfg(feature = "overlay")]
+            frame_times: FrameTimeHistory::default(),
+            #[cfg(feature = "overlay")]
+            hud,
+            #[cfg(feature = "overlay")]
+            hud_layout,
+            #[cfg(feature = "overlay")]
+            hud_pipeline,
+            #[cfg(feature = "overlay")]
+            hud_buffers,
+            #[cfg(feature = "overlay")]
+            hud_atlas: None,
         };
-
-        (debug_utils_loader, debug_messenger)
-    }
-
-    fn pick_physical_device(
-        instance: &ash::Instance,
-        surface_loader: &ash::extensions::khr::Surface,
-        surface: vk::SurfaceKHR,
-    ) -> (vk::PhysicalDevice, QueueFamilyIndices) {
-        let physical_devices = unsafe { instance.enumerate_physical_devices().unwrap() };
-        let physical_device = physical_devices
-            .into_iter()
-            .find(|pdevice| Self::is_device_suitable(instance, surface_loader, surface, *pdevice))
-            .expect("Failed to find a suitable GPU!");
-
-        let indices = Self::find_queue_families(instance, surface_loader, surface, physical_device);
-        (physical_device, indices)
-    }
-
-    fn is_device_suitable(
-        instance: &ash::Instance,
-        surface_loader: &ash::extensions::khr::Surface,
-        surface: vk::SurfaceKHR,
-        pdevice: vk::PhysicalDevice,
-    ) -> bool {
-        let indices = Self::find_queue_families(instance, surface_loader, surface, pdevice);
-        let extensions_supported = Self::check_device_extension_support(instance, pdevice);
-
-        let mut swapchain_adequate = false;
-        if extensions_supported {
-            let swapchain_support = Self::query_swapchain_support(surface_loader, pdevice, surface);
-            swapchain_adequate = !swapchain_support.formats.is_empty()
-                && !swapchain_support.present_modes.is_empty();
+        let ground_pattern = app.config.ground_texture;
+        app.ground_texture = Some(app.create_texture("ground texture", ground_pattern)?);
+        let face_labels = app.create_texture("face labels", Pattern::FaceLabels)?;
+        app.write_texture_set(CUBE_MATERIAL, &face_labels);
+        app.face_labels = Some(face_labels);
+        if let Some(path) = app.config.cube_texture.clone() {
+            let cube_texture = app.load_texture("cube texture", &path)?;
+            app.write_texture_set(TEXTURED_MATERIAL, &cube_texture);
+            app.cube_texture = Some(cube_texture);
         }
-
-        indices.is_complete() && extensions_supported && swapchain_adequate
-    }
-
-    fn check_device_extension_support(
-        instance: &ash::Instance,
-        pdevice: vk::PhysicalDevice,
-    ) -> bool {
-        let required_extensions = [ash::extensions::khr::Swapchain::name()];
-        let available_extensions = unsafe {
-            instance
-                .enumerate_device_extension_properties(pdevice)
-                .unwrap()
-        };
-
-        for required in required_extensions.iter() {
-            let found = available_extensions.iter().any(|ext| {
-                let name = unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) };
-                required == &name
-            });
-
-            if !found {
-                return false;
+        let skybox = app.create_skybox()?;
+        app.write_texture_set(SKYBOX_TEXTURE_SET, &skybox.cube_map);
+        app.skybox = Some(skybox);
+        #[cfg(feature = "overlay")]
+        {
+            let hud_atlas = app.upload_texture(
+                "HUD font atlas",
+                hud::ATLAS_WIDTH,
+                hud::ATLAS_HEIGHT,
+                &hud::atlas_pixels(),
+            )?;
+            app.write_texture_set(HUD_TEXTURE_SET, &hud_atlas);
+            app.hud_atlas = Some(hud_atlas);
+        }
+        if app.config.orbit_demo {
+            for object in scene::orbiting_cubes(
+                ORBIT_DEMO_CUBES,
+                ORBIT_DEMO_RADIUS,
+                ORBIT_DEMO_SCALE,
+                ORBIT_DEMO_SPEED,
+            ) {
+                app.add_object(object);
             }
         }
-
-        true
+        if app.config.picking {
+            app.set_picking(true)?;
+        }
+        profiler.phase("textures");
+        app.warm_pipelines();
+        profiler.phase("pipelines");
+        if app.config.startup_report {
+            print!("{}", profiler.report(app.resources.records().len()));
+        }
+        Ok(app)
     }
 
-    fn find_queue_families(
-        instance: &ash::Instance,
-        surface_loader: &ash::extensions::khr::Surface,
-        surface: vk::SurfaceKHR,
-        pdevice: vk::PhysicalDevice,
-    ) -> QueueFamilyIndices {
-        let queue_families =
-            unsafe { instance.get_physical_device_queue_family_properties(pdevice) };
-        let mut indices = QueueFamilyIndices::new();
-
-        for (i, queue_family) in queue_families.iter().enumerate() {
-            if queue_family.queue_flags.contains(vk::QueueFlags::GRAPHICS) {
-                indices.graphics_family = Some(i as u32);
-            }
-
-            let present_support = unsafe {
-                surface_loader
-                    .get_physical_device_surface_support(pdevice, i as u32, surface)
-                    .unwrap()
-            };
-            if present_support {
-                indices.present_family = Some(i as u32);
-            }
-
-            if indices.is_complete() {
-                break;
+    /// The mesh optimised if OPTIMIZE_MESHES, with its indices at the
+    /// narrowest type that fits them.
+    fn load_mesh<I: MeshIndex>(
+        name: &str,
+        vertices: &[Vertex],
+        indices: &[I],
+    ) -> (Vec<Vertex>, IndexData) {
+        let mut vertices = vertices.to_vec();
+        let mut indices: Vec<u32> = indices
+            .iter()
+            .map(|&index| index.to_usize() as u32)
+            .collect();
+
+        if OPTIMIZE_MESHES {
+            let stats =
+                meshopt::optimize_mesh(&mut vertices, &mut indices, QUANTIZE_MESH_POSITIONS);
+            info!(
+                "Loaded mesh '{}': ACMR {:.3} -> {:.3}",
+                name, stats.acmr_before, stats.acmr_after
+            );
+            if let Some(error) = stats.quantization_error {
+                info!("Quantized '{}' positions, max error {:e}", name, error);
             }
         }
 
-        indices
+        let indices = IndexData::new(indices, vertices.len());
+        (vertices, indices)
     }
 
-    fn create_logical_device(
-        instance: &ash::Instance,
-        pdevice: vk::PhysicalDevice,
-        indices: &QueueFamilyIndices,
-    ) -> (ash::Device, vk::Queue, vk::Queue) {
-        let mut unique_queue_families = std::collections::HashSet::new();
-        unique_queue_families.insert(indices.graphics_family.unwrap());
-        unique_queue_families.insert(indices.present_family.unwrap());
-
-        let queue_priorities = [1.0];
-        let mut queue_create_infos = vec![];
-        for queue_family in unique_queue_families {
-            let queue_create_info = vk::DeviceQueueCreateInfo::builder()
-                .queue_family_index(queue_family)
-                .queue_priorities(&queue_priorities)
-                .build();
-            queue_create_infos.push(queue_create_info);
+    /// The draw list's mesh binding for `mesh`: the pool, bound with its
+    /// index type.
+    fn pooled_meshes(mesh: MeshAllocation) -> u16 {
+        match mesh.index_type {
+            vk::IndexType::UINT32 => POOLED_MESHES_U32,
+            _ => POOLED_MESHES,
         }
+    }
 
-        let physical_device_features = vk::PhysicalDeviceFeatures::builder();
-        let required_extensions = [ash::extensions::khr::Swapchain::name().as_ptr()];
-
-        let create_info = vk::DeviceCreateInfo::builder()
-            .queue_create_infos(&queue_create_infos)
-            .enabled_features(&physical_device_features)
-            .enabled_extension_names(&required_extensions);
-
-        let device = unsafe { instance.create_device(pdevice, &create_info, None).unwrap() };
-
-        let graphics_queue =
-            unsafe { device.get_device_queue(indices.graphics_family.unwrap(), 0) };
-        let present_queue = unsafe { device.get_device_queue(indices.present_family.unwrap(), 0) };
+    fn create_watchdog(
+        context: &VkContext,
+        marker_extension: MarkerExtension,
+        resources: &mut ResourceRegistry,
+    ) -> Result<Watchdog, VulkanError> {
+        let (instance, device) = (&context.instance, &context.device);
+        let properties =
+            unsafe { instance.get_physical_device_properties(context.physical_device) };
+        let device_name = unsafe { CStr::from_ptr(properties.device_name.as_ptr()) }
+            .to_string_lossy()
+            .into_owned();
+
+        let backend = match marker_extension {
+            MarkerExtension::None => MarkerBackend::None,
+            MarkerExtension::NvCheckpoints => MarkerBackend::NvCheckpoints(
+                ash::extensions::nv::DeviceDiagnosticCheckpoints::new(instance, device),
+            ),
+            MarkerExtension::AmdBufferMarker => {
+                let fp = vk::AmdBufferMarkerFn::load(|name| unsafe {
+                    std::mem::transmute(
+                        instance.get_device_proc_addr(device.handle(), name.as_ptr()),
+                    )
+                });
+                let (buffer, allocation) = context.create_buffer(
+                    ResourceClass::Staging,
+                    std::mem::size_of::<u32>() as vk::DeviceSize,
+                    vk::BufferUsageFlags::TRANSFER_DST,
+                )?;
+                resources.register(
+                    ResourceKind::Buffer,
+                    buffer,
+                    "watchdog marker buffer",
+                    std::mem::size_of::<u32>() as vk::DeviceSize,
+                );
+                MarkerBackend::AmdBufferMarker {
+                    fp,
+                    buffer,
+                    allocation,
+                }
+            }
+        };
+        info!("GPU watchdog markers: {:?}", marker_extension);
 
-        (device, graphics_queue, present_queue)
+        Ok(Watchdog::new(WATCHDOG_TIMEOUT_MS, device_name, backend))
     }
 
-    fn create_swapchain(
-        instance: &ash::Instance,
-        device: &ash::Device,
-        pdevice: vk::PhysicalDevice,
-        surface_loader: &ash::extensions::khr::Surface,
-        surface: vk::SurfaceKHR,
-        indices: &QueueFamilyIndices,
-        swapchain_loader: &ash::extensions::khr::Swapchain,
-        window: &winit::window::Window,
-    ) -> (vk::SwapchainKHR, vk::Format, vk::Extent2D) {
-        let swapchain_support = Self::query_swapchain_support(surface_loader, pdevice, surface);
-        let surface_format = Self::choose_swap_surface_format(&swapchain_support.formats);
-        let present_mode = Self::choose_swap_present_mode(&swapchain_support.present_modes);
-        let extent = Self::choose_swap_extent(&swapchain_support.capabilities, window);
-
-        let mut image_count = swapchain_support.capabilities.min_image_count + 1;
-        if swapchain_support.capabilities.max_image_count > 0
-            && image_count > swapchain_support.capabilities.max_image_count
-        {
-            image_count = swapchain_support.capabilities.max_image_count;
+    /// `swapchain_extent` clamped to the device's limits, saying so when it
+    /// had to be.
+    fn display_extent(
+        capabilities: &DeviceCapabilities,
+        swapchain_extent: vk::Extent2D,
+    ) -> vk::Extent2D {
+        let render_extent = capabilities.limits.render_extent(swapchain_extent);
+        if render_extent != swapchain_extent {
+            info!(
+                "Surface is {}x{}, past the device's limits; rendering {}x{} of it",
+                swapchain_extent.width,
+                swapchain_extent.height,
+                render_extent.width,
+                render_extent.height
+            );
         }
+        render_extent
+    }
 
-        let mut create_info = vk::SwapchainCreateInfoKHR::builder()
-            .surface(surface)
-            .min_image_count(image_count)
-            .image_format(surface_format.format)
-            .image_color_space(surface_format.color_space)
-            .image_extent(extent)
-            .image_array_layers(1)
-            .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT);
-
-        let queue_family_indices = [
-            indices.graphics_family.unwrap(),
-            indices.present_family.unwrap(),
-        ];
-
-        if indices.graphics_family != indices.present_family {
-            create_info = create_info
-                .image_sharing_mode(vk::SharingMode::CONCURRENT)
-                .queue_family_indices(&queue_family_indices);
+    /// `display_extent` at the render scale, within the device's limits, if
+    /// the scene can be rendered away from full scale.
+    fn scaled_render_extent(&self) -> vk::Extent2D {
+        if self.upscaling() {
+            self.capabilities
+                .limits
+                .render_extent(render_scale::scaled_extent(
+                    self.display_extent,
+                    self.render_scaler.percent(),
+                ))
         } else {
-            create_info = create_info.image_sharing_mode(vk::SharingMode::EXCLUSIVE);
+            self.display_extent
         }
+    }
 
-        let create_info = create_info
-            .pre_transform(swapchain_support.capabilities.current_transform)
-            .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
-            .present_mode(present_mode)
-            .clipped(true);
-
-        let swapchain = unsafe {
-            swapchain_loader
-                .create_swapchain(&create_info, None)
-                .unwrap()
-        };
-
-        (swapchain, surface_format.format, extent)
+    /// Away from full render scale, with swapchain images the scene can be
+    /// blitted onto.
+    fn upscaling(&self) -> bool {
+        self.render_scaler.percent() != 100 && self.can_upscale
     }
 
-    fn query_swapchain_support(
-        surface_loader: &ash::extensions::khr::Surface,
-        pdevice: vk::PhysicalDevice,
-        surface: vk::SurfaceKHR,
-    ) -> SwapchainSupportDetails {
-        let capabilities = unsafe {
-            surface_loader
-                .get_physical_device_surface_capabilities(pdevice, surface)
-                .unwrap()
-        };
-        let formats = unsafe {
-            surface_loader
-                .get_physical_device_surface_formats(pdevice, surface)
-                .unwrap()
-        };
-        let present_modes = unsafe {
-            surface_loader
-                .get_physical_device_surface_present_modes(pdevice, surface)
-                .unwrap()
+    /// A window's swapchain images of `format` and `usage` can be blitted
+    /// onto with linear filtering. Headless frames are always rendered at
+    /// full scale.
+    fn can_upscale(context: &VkContext, format: vk::Format, usage: vk::ImageUsageFlags) -> bool {
+        let format_features = unsafe {
+            context
+                .instance
+                .get_physical_device_format_properties(context.physical_device, format)
+                .optimal_tiling_features
         };
-
-        SwapchainSupportDetails {
-            capabilities,
-            formats,
-            present_modes,
-        }
+        context.surface.is_some() && render_scale::can_upscale(format_features, usage)
     }
 
-    fn choose_swap_surface_format(
-        available_formats: &[vk::SurfaceFormatKHR],
-    ) -> vk::SurfaceFormatKHR {
-        *available_formats
-            .iter()
-            .find(|format| {
-                format.format == vk::Format::B8G8R8A8_SRGB
-                    && format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
-            })
-            .unwrap_or(&available_formats[0])
+    /// What the scene pass draws `image_index`'s frame into: the swapchain
+    /// image itself, or away from full scale its scaled target.
+    fn scene_target(&self, image_index: usize) -> (vk::Image, vk::ImageView) {
+        match self.scaled_targets.get(image_index) {
+            Some(target) => (target.image, target.view),
+            None => (
+                self.swapchain.images[image_index],
+                self.swapchain.image_views[image_index],
+            ),
+        }
     }
 
-    fn choose_swap_present_mode(
-        available_present_modes: &[vk::PresentModeKHR],
-    ) -> vk::PresentModeKHR {
-        *available_present_modes
-            .iter()
-            .find(|mode| **mode == vk::PresentModeKHR::MAILBOX)
-            .unwrap_or(&vk::PresentModeKHR::FIFO)
+    /// `scene_target`'s views, one per swapchain image, for framebuffers.
+    fn scene_target_views(&self) -> Vec<vk::ImageView> {
+        (0..self.swapchain.images.len())
+            .map(|image_index| self.scene_target(image_index).1)
+            .collect()
     }
 
-    fn choose_swap_extent(
-        capabilities: &vk::SurfaceCapabilitiesKHR,
-        window: &winit::window::Window,
-    ) -> vk::Extent2D {
-        if capabilities.current_extent.width != u32::MAX {
-            capabilities.current_extent
-        } else {
-            let inner_size = window.inner_size();
-            vk::Extent2D {
-                width: inner_size.width.clamp(
-                    capabilities.min_image_extent.width,
-                    capabilities.max_image_extent.width,
-                ),
-                height: inner_size.height.clamp(
-                    capabilities.min_image_extent.height,
-                    capabilities.max_image_extent.height,
-                ),
-            }
+    /// The swapchain's framebuffers over the scene targets and attachments,
+    /// unless rendering dynamically.
+    fn create_framebuffers(&mut self) {
+        if self.dynamic_rendering.is_some() {
+            return;
         }
+        let scene_views = self.scene_target_views();
+        let post_views = self.post_target_views();
+        self.swapchain.create_framebuffers(
+            &self.context.device,
+            &scene_views,
+            self.color_image.map(|(_, _, view)| view),
+            &post_views,
+            self.render_pass,
+            self.render_extent,
+        );
     }
 
-    fn create_image_views(
-        device: &ash::Device,
-        images: &[vk::Image],
-        format: vk::Format,
-    ) -> Vec<vk::ImageView> {
-        images
-            .iter()
-            .map(|&image| {
-                let create_info = vk::ImageViewCreateInfo::builder()
-                    .image(image)
-                    .view_type(vk::ImageViewType::TYPE_2D)
-                    .format(format)
-                    .components(vk::ComponentMapping {
-                        r: vk::ComponentSwizzle::IDENTITY,
-                        g: vk::ComponentSwizzle::IDENTITY,
-                        b: vk::ComponentSwizzle::IDENTITY,
-                        a: vk::ComponentSwizzle::IDENTITY,
-                    })
-                    .subresource_range(vk::ImageSubresourceRange {
-                        aspect_mask: vk::ImageAspectFlags::COLOR,
-                        base_mip_level: 0,
-                        level_count: 1,
-                        base_array_layer: 0,
-                        layer_count: 1,
-                    });
-                unsafe { device.create_image_view(&create_info, None).unwrap() }
-            })
-            .collect()
+    /// Rebuilds what the render extent sizes for the scaler's percentage:
+    /// the attachments, scaled targets, framebuffers and depth view. The
+    /// swapchain stays as it is. Waits for the device.
+    fn apply_render_scale(&mut self) -> Result<(), VulkanError> {
+        unsafe { self.context.device.device_wait_idle() }
+            .map_err(VulkanError::at(Stage::SwapchainRecreation))?;
+        let depth_view = self.depth_view.take();
+        let show_depth = depth_view.is_some();
+        if let Some(view) = depth_view {
+            self.destroy_depth_view(view);
+        }
+        self.swapchain.destroy_framebuffers(&self.context.device);
+        self.cleanup_attachments();
+        self.render_extent = self.scaled_render_extent();
+        self.create_attachments()?;
+        self.create_framebuffers();
+        if show_depth {
+            self.depth_view = Some(self.create_depth_view());
+        }
+        self.frame_dirty = true;
+        info!(
+            "Render scale {}%{}: {}x{} shown at {}x{}",
+            self.render_scaler.percent(),
+            if self.render_scaler.is_pinned() {
+                " (pinned)"
+            } else {
+                ""
+            },
+            self.render_extent.width,
+            self.render_extent.height,
+            self.display_extent.width,
+            self.display_extent.height
+        );
+        Ok(())
     }
 
+    /// Without `clear_color` the colour attachment's old contents are
+    /// discarded instead, for when a background pass writes every pixel.
+    /// Render passes differing only in that stay compatible.
+    /// With more than one sample, attachment 0 is the multisampled colour
+    /// image and the subpass resolves it into the swapchain image,
+    /// attachment 2; only the resolved image is kept. Depth is kept too with
+    /// `store_depth`, for the depth view to read; store ops don't affect
+    /// compatibility either.
+    /// With `post_process` the scene is drawn, or resolved, into a target of
+    /// its own instead, which a last subpass reads as an input attachment
+    /// and draws over the swapchain image, then the last attachment. Render
+    /// passes with it are only compatible with each other.
+    #[allow(clippy::too_many_arguments)]
     fn create_render_pass(
         device: &ash::Device,
         format: vk::Format,
         depth_format: vk::Format,
+        samples: vk::SampleCountFlags,
+        final_layout: vk::ImageLayout,
+        clear_color: bool,
+        store_depth: bool,
+        post_process: bool,
     ) -> vk::RenderPass {
+        let multisampled = samples != vk::SampleCountFlags::TYPE_1;
+        // Where the post-process subpass reads the scene from.
+        let target_layout = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
         let color_attachment = vk::AttachmentDescription::builder()
             .format(format)
-            .samples(vk::SampleCountFlags::TYPE_1)
-            .load_op(vk::AttachmentLoadOp::CLEAR)
-            .store_op(vk::AttachmentStoreOp::STORE)
+            .samples(samples)
+            .load_op(if clear_color {
+                vk::AttachmentLoadOp::CLEAR
+            } else {
+                vk::AttachmentLoadOp::DONT_CARE
+            })
+            .store_op(if multisampled || post_process {
+                vk::AttachmentStoreOp::DONT_CARE
+            } else {
+                vk::AttachmentStoreOp::STORE
+            })
             .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
             .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
             .initial_layout(vk::ImageLayout::UNDEFINED)
-            .final_layout(vk::ImageLayout::PRESENT_SRC_KHR);
+            .final_layout(if multisampled {
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+            } else if post_process {
+                target_layout
+            } else {
+                final_layout
+            });
 
         let color_attachment_ref = vk::AttachmentReference::builder()
             .attachment(0)
@@ -564,9 +1336,13 @@ impl VulkanApp {
 
         let depth_attachment = vk::AttachmentDescription::builder()
             .format(depth_format)
-            .samples(vk::SampleCountFlags::TYPE_1)
+            .samples(samples)
             .load_op(vk::AttachmentLoadOp::CLEAR)
-            .store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .store_op(if store_depth {
+                vk::AttachmentStoreOp::STORE
+            } else {
+                vk::AttachmentStoreOp::DONT_CARE
+            })
             .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
             .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
             .initial_layout(vk::ImageLayout::UNDEFINED)
@@ -576,10 +1352,35 @@ impl VulkanApp {
             .attachment(1)
             .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
 
-        let subpass = vk::SubpassDescription::builder()
+        let resolve_attachment = vk::AttachmentDescription::builder()
+            .format(format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .store_op(if post_process {
+                vk::AttachmentStoreOp::DONT_CARE
+            } else {
+                vk::AttachmentStoreOp::STORE
+            })
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(if post_process {
+                target_layout
+            } else {
+                final_layout
+            });
+
+        let resolve_attachment_ref = vk::AttachmentReference::builder()
+            .attachment(2)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+
+        let mut subpass = vk::SubpassDescription::builder()
             .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
             .color_attachments(std::slice::from_ref(&color_attachment_ref))
             .depth_stencil_attachment(&depth_attachment_ref);
+        if multisampled {
+            subpass = subpass.resolve_attachments(std::slice::from_ref(&resolve_attachment_ref));
+        }
 
         let dependency = vk::SubpassDependency::builder()
             .src_subpass(vk::SUBPASS_EXTERNAL)
@@ -598,107 +1399,560 @@ impl VulkanApp {
                     | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
             );
 
-        let attachments = [color_attachment.build(), depth_attachment.build()];
-        let render_pass_info = vk::RenderPassCreateInfo::builder()
-            .attachments(&attachments)
-            .subpasses(std::slice::from_ref(&subpass))
-            .dependencies(std::slice::from_ref(&dependency));
-
-        unsafe { device.create_render_pass(&render_pass_info, None).unwrap() }
-    }
+        // With the pre-pass, subpass 0 only writes depth and subpass 1 shades
+        // against it; the main subpass waits for the pre-pass depth writes.
+        let prepass_subpass = vk::SubpassDescription::builder()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .depth_stencil_attachment(&depth_attachment_ref);
+        let prepass_dependencies = [
+            vk::SubpassDependency::builder()
+                .src_subpass(vk::SUBPASS_EXTERNAL)
+                .dst_subpass(0)
+                .src_stage_mask(vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS)
+                .src_access_mask(vk::AccessFlags::empty())
+                .dst_stage_mask(vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS)
+                .dst_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+                .build(),
+            vk::SubpassDependency::builder()
+                .src_subpass(vk::SUBPASS_EXTERNAL)
+                .dst_subpass(1)
+                .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .src_access_mask(vk::AccessFlags::empty())
+                .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .build(),
+            vk::SubpassDependency::builder()
+                .src_subpass(0)
+                .dst_subpass(1)
+                .src_stage_mask(
+                    vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                        | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                )
+                .src_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS)
+                .dst_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ)
+                .dependency_flags(vk::DependencyFlags::BY_REGION)
+                .build(),
+        ];
 
-    fn create_graphics_pipeline(
+        // The post-process subpass draws over the whole swapchain image, so
+        // its old contents go.
+        let output_attachment = vk::AttachmentDescription::builder()
+            .format(format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(final_layout);
+        let input_attachment_ref = vk::AttachmentReference::builder()
+            .attachment(if multisampled { 2 } else { 0 })
+            .layout(target_layout);
+        let output_attachment_ref = vk::AttachmentReference::builder()
+            .attachment(if multisampled { 3 } else { 2 })
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+        let post_subpass = vk::SubpassDescription::builder()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .input_attachments(std::slice::from_ref(&input_attachment_ref))
+            .color_attachments(std::slice::from_ref(&output_attachment_ref));
+        // The swapchain image's transition waits for the acquire, as it
+        // would in the scene subpass; the read waits for the scene's writes,
+        // pixel by pixel.
+        let scene_subpass = DEPTH_PREPASS as u32;
+        let post_dependencies = [
+            vk::SubpassDependency::builder()
+                .src_subpass(vk::SUBPASS_EXTERNAL)
+                .dst_subpass(scene_subpass + 1)
+                .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .src_access_mask(vk::AccessFlags::empty())
+                .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .build(),
+            vk::SubpassDependency::builder()
+                .src_subpass(scene_subpass)
+                .dst_subpass(scene_subpass + 1)
+                .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+                .dst_access_mask(vk::AccessFlags::INPUT_ATTACHMENT_READ)
+                .dependency_flags(vk::DependencyFlags::BY_REGION)
+                .build(),
+        ];
+
+        let (mut subpasses, mut dependencies) = if DEPTH_PREPASS {
+            (
+                vec![prepass_subpass.build(), subpass.build()],
+                prepass_dependencies.to_vec(),
+            )
+        } else {
+            (vec![subpass.build()], vec![dependency.build()])
+        };
+
+        let mut attachments = vec![color_attachment.build(), depth_attachment.build()];
+        if multisampled {
+            attachments.push(resolve_attachment.build());
+        }
+        if post_process {
+            attachments.push(output_attachment.build());
+            subpasses.push(post_subpass.build());
+            dependencies.extend(post_dependencies);
+        }
+        let render_pass_info = vk::RenderPassCreateInfo::builder()
+            .attachments(&attachments)
+            .subpasses(&subpasses)
+            .dependencies(&dependencies);
+
+        unsafe { device.create_render_pass(&render_pass_info, None).unwrap() }
+    }
+
+    /// Set 0 per-frame uniforms and any draw uniform slot, set 1 per-view
+    /// uniforms, set 2 object transforms, set 3 the face label texture,
+    /// plus the pushed draw constants.
+    fn create_pipeline_layout(
         device: &ash::Device,
-        render_pass: vk::RenderPass,
-        extent: vk::Extent2D,
         descriptor_set_layout: vk::DescriptorSetLayout,
-    ) -> (vk::Pipeline, vk::PipelineLayout) {
-        let vert_shader_code = include_bytes!(env!("VERT_SHADER_PATH"));
-        let frag_shader_code = include_bytes!(env!("FRAG_SHADER_PATH"));
-
-        let vert_shader_module = Self::create_shader_module(device, vert_shader_code);
-        let frag_shader_module = Self::create_shader_module(device, frag_shader_code);
+        view_set_layout: vk::DescriptorSetLayout,
+        transform_set_layout: vk::DescriptorSetLayout,
+        texture_set_layout: vk::DescriptorSetLayout,
+        push_constant_ranges: &[vk::PushConstantRange],
+    ) -> vk::PipelineLayout {
+        let set_layouts = [
+            descriptor_set_layout,
+            view_set_layout,
+            transform_set_layout,
+            texture_set_layout,
+        ];
+        let pipeline_layout_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&set_layouts)
+            .push_constant_ranges(push_constant_ranges);
+        unsafe {
+            device
+                .create_pipeline_layout(&pipeline_layout_info, None)
+                .unwrap()
+        }
+    }
 
+    /// Line-strip pipeline for the frame-time graph: positions are already in
+    /// NDC with y down, so it uses an unflipped viewport and no depth test.
+    #[cfg(feature = "overlay")]
+    fn create_graph_pipeline(
+        device: &ash::Device,
+        target: PassTarget,
+        layout: vk::PipelineLayout,
+        manual_srgb: bool,
+        resources: &mut ResourceRegistry,
+    ) -> vk::Pipeline {
+        let vert_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("GRAPH_VERT_SHADER_PATH")));
+        let frag_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("GRAPH_FRAG_SHADER_PATH")));
         let main_function_name = CString::new("main").unwrap();
+        let specialization_data = manual_srgb as vk::Bool32;
+        let specialization_entry = vk::SpecializationMapEntry {
+            constant_id: 1,
+            offset: 0,
+            size: std::mem::size_of::<vk::Bool32>(),
+        };
+        let specialization_info = vk::SpecializationInfo::builder()
+            .map_entries(std::slice::from_ref(&specialization_entry))
+            .data(&specialization_data.to_ne_bytes());
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_shader_module)
+                .name(&main_function_name)
+                .build(),
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_shader_module)
+                .name(&main_function_name)
+                .specialization_info(&specialization_info)
+                .build(),
+        ];
 
-        let vert_shader_stage_info = vk::PipelineShaderStageCreateInfo::builder()
-            .stage(vk::ShaderStageFlags::VERTEX)
-            .module(vert_shader_module)
-            .name(&main_function_name);
+        let binding_description = GraphVertex::get_binding_description();
+        let attribute_descriptions = GraphVertex::get_attribute_descriptions();
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_binding_descriptions(std::slice::from_ref(&binding_description))
+            .vertex_attribute_descriptions(&attribute_descriptions);
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::LINE_STRIP)
+            .primitive_restart_enable(false);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo::builder()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(vk::CullModeFlags::NONE);
+        let multisampling =
+            vk::PipelineMultisampleStateCreateInfo::builder().rasterization_samples(target.samples);
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(false)
+            .depth_write_enable(false);
+        let color_blend_attachment = vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(false);
+        let color_blending = vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(std::slice::from_ref(&color_blend_attachment));
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states);
 
-        let frag_shader_stage_info = vk::PipelineShaderStageCreateInfo::builder()
-            .stage(vk::ShaderStageFlags::FRAGMENT)
-            .module(frag_shader_module)
-            .name(&main_function_name);
+        let mut rendering_info = target.pipeline_rendering_info();
+        let mut pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&shader_stages)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterizer)
+            .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&color_blending)
+            .dynamic_state(&dynamic_state)
+            .layout(layout)
+            .render_pass(target.render_pass)
+            .subpass(if DEPTH_PREPASS { 1 } else { 0 });
+        if target.is_dynamic() {
+            pipeline_info = pipeline_info.push_next(&mut rendering_info);
+        }
+        let pipeline = unsafe {
+            device
+                .create_graphics_pipelines(
+                    vk::PipelineCache::null(),
+                    std::slice::from_ref(&pipeline_info),
+                    None,
+                )
+                .unwrap()[0]
+        };
+        unsafe {
+            device.destroy_shader_module(vert_shader_module, None);
+            device.destroy_shader_module(frag_shader_module, None);
+        }
+        resources.register(ResourceKind::Pipeline, pipeline, "frame graph pipeline", 0);
+        pipeline
+    }
 
+    /// Pipeline for the HUD's text: like the graph's, positions are in NDC
+    /// with y down, but triangles are blended over the scene by the atlas's
+    /// alpha. Set 0 is the font atlas.
+    #[cfg(feature = "overlay")]
+    fn create_hud_pipeline(
+        device: &ash::Device,
+        target: PassTarget,
+        layout: vk::PipelineLayout,
+        manual_srgb: bool,
+        resources: &mut ResourceRegistry,
+    ) -> vk::Pipeline {
+        let vert_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("HUD_VERT_SHADER_PATH")));
+        let frag_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("HUD_FRAG_SHADER_PATH")));
+        let main_function_name = CString::new("main").unwrap();
+        let specialization_data = manual_srgb as vk::Bool32;
+        let specialization_entry = vk::SpecializationMapEntry {
+            constant_id: 1,
+            offset: 0,
+            size: std::mem::size_of::<vk::Bool32>(),
+        };
+        let specialization_info = vk::SpecializationInfo::builder()
+            .map_entries(std::slice::from_ref(&specialization_entry))
+            .data(&specialization_data.to_ne_bytes());
         let shader_stages = [
-            vert_shader_stage_info.build(),
-            frag_shader_stage_info.build(),
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_shader_module)
+                .name(&main_function_name)
+                .build(),
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_shader_module)
+                .name(&main_function_name)
+                .specialization_info(&specialization_info)
+                .build(),
         ];
 
-        let binding_description = Vertex::get_binding_description();
-        let attribute_descriptions = Vertex::get_attribute_descriptions();
+        let binding_description = HudVertex::get_binding_description();
+        let attribute_descriptions = HudVertex::get_attribute_descriptions();
         let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::builder()
             .vertex_binding_descriptions(std::slice::from_ref(&binding_description))
             .vertex_attribute_descriptions(&attribute_descriptions);
-
         let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::builder()
             .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
             .primitive_restart_enable(false);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo::builder()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(vk::CullModeFlags::NONE);
+        let multisampling =
+            vk::PipelineMultisampleStateCreateInfo::builder().rasterization_samples(target.samples);
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(false)
+            .depth_write_enable(false);
+        let color_blend_attachment = vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .alpha_blend_op(vk::BlendOp::ADD);
+        let color_blending = vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(std::slice::from_ref(&color_blend_attachment));
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states);
 
-        let viewport = vk::Viewport::builder()
-            .x(0.0)
-            .y(0.0)
-            .width(extent.width as f32)
-            .height(extent.height as f32)
-            .min_depth(0.0)
-            .max_depth(1.0);
+        let mut rendering_info = target.pipeline_rendering_info();
+        let mut pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&shader_stages)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterizer)
+            .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&color_blending)
+            .dynamic_state(&dynamic_state)
+            .layout(layout)
+            .render_pass(target.render_pass)
+            .subpass(if DEPTH_PREPASS { 1 } else { 0 });
+        if target.is_dynamic() {
+            pipeline_info = pipeline_info.push_next(&mut rendering_info);
+        }
+        let pipeline = unsafe {
+            device
+                .create_graphics_pipelines(
+                    vk::PipelineCache::null(),
+                    std::slice::from_ref(&pipeline_info),
+                    None,
+                )
+                .unwrap()[0]
+        };
+        unsafe {
+            device.destroy_shader_module(vert_shader_module, None);
+            device.destroy_shader_module(frag_shader_module, None);
+        }
+        resources.register(ResourceKind::Pipeline, pipeline, "HUD pipeline", 0);
+        pipeline
+    }
 
-        let scissor = vk::Rect2D::builder()
-            .offset(vk::Offset2D { x: 0, y: 0 })
-            .extent(extent);
+    /// The fullscreen pass behind the scene, or `None` for `Background::None`.
+    /// It has no vertex input: the vertex shader makes one screen-covering
+    /// triangle from the vertex index. Shares the cube's layout and binds only
+    /// the frame and view sets. The skybox has a pipeline of its own.
+    fn create_background_pipeline(
+        device: &ash::Device,
+        target: PassTarget,
+        layout: vk::PipelineLayout,
+        background: Background,
+        manual_srgb: bool,
+        resources: &mut ResourceRegistry,
+    ) -> Option<vk::Pipeline> {
+        match background {
+            Background::None => return None,
+            Background::Skybox => {
+                return Some(Self::create_skybox_pipeline(
+                    device,
+                    target,
+                    layout,
+                    manual_srgb,
+                    resources,
+                ));
+            }
+            _ => {}
+        }
+        let vert_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("BACKGROUND_VERT_SHADER_PATH")));
+        let frag_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("BACKGROUND_FRAG_SHADER_PATH")));
+        let main_function_name = CString::new("main").unwrap();
+        // BACKGROUND, then MANUAL_SRGB_ENCODE.
+        let specialization_data = [background as i32 as u32, manual_srgb as vk::Bool32];
+        let specialization_entries = [
+            vk::SpecializationMapEntry {
+                constant_id: 0,
+                offset: 0,
+                size: std::mem::size_of::<i32>(),
+            },
+            vk::SpecializationMapEntry {
+                constant_id: 1,
+                offset: std::mem::size_of::<i32>() as u32,
+                size: std::mem::size_of::<vk::Bool32>(),
+            },
+        ];
+        let specialization_bytes: Vec<u8> = specialization_data
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        let specialization_info = vk::SpecializationInfo::builder()
+            .map_entries(&specialization_entries)
+            .data(&specialization_bytes);
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_shader_module)
+                .name(&main_function_name)
+                .build(),
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_shader_module)
+                .name(&main_function_name)
+                .specialization_info(&specialization_info)
+                .build(),
+        ];
 
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::builder();
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+            .primitive_restart_enable(false);
         let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
-            .viewports(std::slice::from_ref(&viewport))
-            .scissors(std::slice::from_ref(&scissor));
-
+            .viewport_count(1)
+            .scissor_count(1);
         let rasterizer = vk::PipelineRasterizationStateCreateInfo::builder()
-            .depth_clamp_enable(false)
-            .rasterizer_discard_enable(false)
             .polygon_mode(vk::PolygonMode::FILL)
             .line_width(1.0)
-            .cull_mode(vk::CullModeFlags::BACK)
-            .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
-            .depth_bias_enable(false);
-
-        let multisampling = vk::PipelineMultisampleStateCreateInfo::builder()
-            .sample_shading_enable(false)
-            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
-
+            .cull_mode(vk::CullModeFlags::NONE);
+        let multisampling =
+            vk::PipelineMultisampleStateCreateInfo::builder().rasterization_samples(target.samples);
+        // Drawn first, so nothing needs testing against; the scene then
+        // draws over it.
         let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder()
-            .depth_test_enable(true)
-            .depth_write_enable(true)
-            .depth_compare_op(vk::CompareOp::LESS)
-            .depth_bounds_test_enable(false)
-            .stencil_test_enable(false);
-
+            .depth_test_enable(false)
+            .depth_write_enable(false);
         let color_blend_attachment = vk::PipelineColorBlendAttachmentState::builder()
             .color_write_mask(vk::ColorComponentFlags::RGBA)
             .blend_enable(false);
-
         let color_blending = vk::PipelineColorBlendStateCreateInfo::builder()
-            .logic_op_enable(false)
             .attachments(std::slice::from_ref(&color_blend_attachment));
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states);
 
-        let pipeline_layout_info = vk::PipelineLayoutCreateInfo::builder()
-            .set_layouts(std::slice::from_ref(&descriptor_set_layout));
-        let pipeline_layout = unsafe {
+        let mut rendering_info = target.pipeline_rendering_info();
+        let mut pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&shader_stages)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterizer)
+            .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&color_blending)
+            .dynamic_state(&dynamic_state)
+            .layout(layout)
+            .render_pass(target.render_pass)
+            .subpass(if DEPTH_PREPASS { 1 } else { 0 });
+        if target.is_dynamic() {
+            pipeline_info = pipeline_info.push_next(&mut rendering_info);
+        }
+        let pipeline = unsafe {
             device
-                .create_pipeline_layout(&pipeline_layout_info, None)
-                .unwrap()
+                .create_graphics_pipelines(
+                    vk::PipelineCache::null(),
+                    std::slice::from_ref(&pipeline_info),
+                    None,
+                )
+                .unwrap()[0]
         };
+        unsafe {
+            device.destroy_shader_module(vert_shader_module, None);
+            device.destroy_shader_module(frag_shader_module, None);
+        }
+        resources.register(
+            ResourceKind::Pipeline,
+            pipeline,
+            &format!("{} background pipeline", background.name()),
+            0,
+        );
+        Some(pipeline)
+    }
 
-        let pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+    /// The skybox's pipeline: the unit cube's positions as its only vertex
+    /// input, on the far plane, tested with `compare_or_equal_op` against
+    /// the depth but never writing it. Shares the cube's layout for the
+    /// view set and the cube map in set 3.
+    fn create_skybox_pipeline(
+        device: &ash::Device,
+        target: PassTarget,
+        layout: vk::PipelineLayout,
+        manual_srgb: bool,
+        resources: &mut ResourceRegistry,
+    ) -> vk::Pipeline {
+        let vert_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("SKYBOX_VERT_SHADER_PATH")));
+        let frag_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("SKYBOX_FRAG_SHADER_PATH")));
+        let main_function_name = CString::new("main").unwrap();
+        let (specialization_entries, specialization_bytes) = Self::bool_specialization(&[
+            manual_srgb,
+            target.depth_direction == DepthDirection::Reversed,
+        ]);
+        let specialization_info = vk::SpecializationInfo::builder()
+            .map_entries(&specialization_entries)
+            .data(&specialization_bytes);
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_shader_module)
+                .name(&main_function_name)
+                .specialization_info(&specialization_info)
+                .build(),
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_shader_module)
+                .name(&main_function_name)
+                .specialization_info(&specialization_info)
+                .build(),
+        ];
+
+        let binding_description = vk::VertexInputBindingDescription::builder()
+            .binding(0)
+            .stride(std::mem::size_of::<[f32; 3]>() as u32)
+            .input_rate(vk::VertexInputRate::VERTEX);
+        let attribute_description = vk::VertexInputAttributeDescription::builder()
+            .binding(0)
+            .location(0)
+            .format(vk::Format::R32G32B32_SFLOAT)
+            .offset(0);
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_binding_descriptions(std::slice::from_ref(&binding_description))
+            .vertex_attribute_descriptions(std::slice::from_ref(&attribute_description));
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+            .primitive_restart_enable(false);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+        // Seen from inside.
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo::builder()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(vk::CullModeFlags::NONE);
+        let multisampling =
+            vk::PipelineMultisampleStateCreateInfo::builder().rasterization_samples(target.samples);
+        // Drawn first at the far depth, where it passes against the clear
+        // and, with the pre-pass, fails wherever the scene will be.
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(true)
+            .depth_write_enable(false)
+            .depth_compare_op(target.depth_direction.compare_or_equal_op());
+        let color_blend_attachment = vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(false);
+        let color_blending = vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(std::slice::from_ref(&color_blend_attachment));
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states);
+
+        let mut rendering_info = target.pipeline_rendering_info();
+        let mut pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
             .stages(&shader_stages)
             .vertex_input_state(&vertex_input_info)
             .input_assembly_state(&input_assembly)
@@ -707,11 +1961,14 @@ impl VulkanApp {
             .multisample_state(&multisampling)
             .depth_stencil_state(&depth_stencil)
             .color_blend_state(&color_blending)
-            .layout(pipeline_layout)
-            .render_pass(render_pass)
-            .subpass(0);
-
-        let graphics_pipeline = unsafe {
+            .dynamic_state(&dynamic_state)
+            .layout(layout)
+            .render_pass(target.render_pass)
+            .subpass(if DEPTH_PREPASS { 1 } else { 0 });
+        if target.is_dynamic() {
+            pipeline_info = pipeline_info.push_next(&mut rendering_info);
+        }
+        let pipeline = unsafe {
             device
                 .create_graphics_pipelines(
                     vk::PipelineCache::null(),
@@ -720,772 +1977,7231 @@ impl VulkanApp {
                 )
                 .unwrap()[0]
         };
-
         unsafe {
             device.destroy_shader_module(vert_shader_module, None);
             device.destroy_shader_module(frag_shader_module, None);
         }
-
-        (graphics_pipeline, pipeline_layout)
+        resources.register(ResourceKind::Pipeline, pipeline, "skybox pipeline", 0);
+        pipeline
     }
 
-    fn create_shader_module(device: &ash::Device, code: &[u8]) -> vk::ShaderModule {
-        let create_info = vk::ShaderModuleCreateInfo::builder().code(unsafe {
-            std::slice::from_raw_parts(code.as_ptr() as *const u32, code.len() / 4)
+    /// The pipelines windows on another device draw with, indexed by
+    /// CUBE_PIPELINE and DOUBLE_SIDED_PIPELINE: pooled meshes' positions
+    /// and colours, and each instance's transform from binding 1, unlit.
+    fn create_unlit_pipelines(
+        device: &ash::Device,
+        target: PassTarget,
+        layout: vk::PipelineLayout,
+        manual_srgb: bool,
+        resources: &mut ResourceRegistry,
+    ) -> [vk::Pipeline; 2] {
+        let vert_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("UNLIT_VERT_SHADER_PATH")));
+        let frag_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("UNLIT_FRAG_SHADER_PATH")));
+        let main_function_name = CString::new("main").unwrap();
+        let (specialization_entries, specialization_bytes) =
+            Self::bool_specialization(&[manual_srgb]);
+        let specialization_info = vk::SpecializationInfo::builder()
+            .map_entries(&specialization_entries)
+            .data(&specialization_bytes);
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_shader_module)
+                .name(&main_function_name)
+                .build(),
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_shader_module)
+                .name(&main_function_name)
+                .specialization_info(&specialization_info)
+                .build(),
+        ];
+
+        let binding_descriptions = [
+            Vertex::get_binding_description(),
+            vk::VertexInputBindingDescription::builder()
+                .binding(1)
+                .stride(std::mem::size_of::<[[f32; 4]; 4]>() as u32)
+                .input_rate(vk::VertexInputRate::INSTANCE)
+                .build(),
+        ];
+        // Position and colour, then the transform's columns at 4 to 7.
+        let mut attribute_descriptions = Vertex::get_attribute_descriptions()[..2].to_vec();
+        attribute_descriptions.extend((0..4).map(|column| {
+            vk::VertexInputAttributeDescription::builder()
+                .binding(1)
+                .location(4 + column)
+                .format(vk::Format::R32G32B32A32_SFLOAT)
+                .offset(column * std::mem::size_of::<[f32; 4]>() as u32)
+                .build()
+        }));
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_binding_descriptions(&binding_descriptions)
+            .vertex_attribute_descriptions(&attribute_descriptions);
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+            .primitive_restart_enable(false);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+        let multisampling =
+            vk::PipelineMultisampleStateCreateInfo::builder().rasterization_samples(target.samples);
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(true)
+            .depth_write_enable(true)
+            .depth_compare_op(target.depth_direction.compare_op());
+        let color_blend_attachment = vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(false);
+        let color_blending = vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(std::slice::from_ref(&color_blend_attachment));
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states);
+
+        let pipelines = [Faces::Front, Faces::Both].map(|faces| {
+            let rasterizer = vk::PipelineRasterizationStateCreateInfo::builder()
+                .polygon_mode(vk::PolygonMode::FILL)
+                .line_width(1.0)
+                .cull_mode(faces.cull_mode())
+                .front_face(vk::FrontFace::COUNTER_CLOCKWISE);
+            let pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+                .stages(&shader_stages)
+                .vertex_input_state(&vertex_input_info)
+                .input_assembly_state(&input_assembly)
+                .viewport_state(&viewport_state)
+                .rasterization_state(&rasterizer)
+                .multisample_state(&multisampling)
+                .depth_stencil_state(&depth_stencil)
+                .color_blend_state(&color_blending)
+                .dynamic_state(&dynamic_state)
+                .layout(layout)
+                .render_pass(target.render_pass)
+                .subpass(0);
+            let pipeline = unsafe {
+                device
+                    .create_graphics_pipelines(
+                        vk::PipelineCache::null(),
+                        std::slice::from_ref(&pipeline_info),
+                        None,
+                    )
+                    .unwrap()[0]
+            };
+            resources.register(ResourceKind::Pipeline, pipeline, "unlit pipeline", 0);
+            pipeline
         });
-        unsafe { device.create_shader_module(&create_info, None).unwrap() }
+        unsafe {
+            device.destroy_shader_module(vert_shader_module, None);
+            device.destroy_shader_module(frag_shader_module, None);
+        }
+        pipelines
     }
 
-    fn create_framebuffers(
-        device: &ash::Device,
-        image_views: &[vk::ImageView],
-        depth_image_view: vk::ImageView,
-        render_pass: vk::RenderPass,
-        extent: vk::Extent2D,
-    ) -> Vec<vk::Framebuffer> {
-        image_views
+    /// The depth view's resources for the current depth image, swapchain and
+    /// sample count. The scene pass it brings stores depth, and is
+    /// compatible with `render_pass`, so the scene's framebuffers and
+    /// pipelines work with it unchanged. Dynamic rendering resolves
+    /// multisampled depth into an image of the view's own where it can.
+    fn create_depth_view(&mut self) -> DepthView {
+        let resolved = self.create_depth_resolve_image();
+        let device = &self.context.device;
+        let depth_format = self.depth_format;
+        // Dynamic rendering picks the depth store op per frame instead.
+        let scene_pass = if self.dynamic_rendering.is_some() {
+            vk::RenderPass::null()
+        } else {
+            Self::create_render_pass(
+                device,
+                self.swapchain.format,
+                depth_format,
+                self.msaa_samples,
+                self.config.final_layout,
+                !self.config.background.covers_screen(),
+                true,
+                self.post_processing(),
+            )
+        };
+
+        // Covers every pixel, so the scene's colour isn't loaded; waits for
+        // the scene's colour writes, which it overwrites.
+        let target_attachment = vk::AttachmentDescription::builder()
+            .format(self.swapchain.format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(self.config.final_layout);
+        let target_ref = vk::AttachmentReference::builder()
+            .attachment(0)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+        let subpass = vk::SubpassDescription::builder()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(std::slice::from_ref(&target_ref));
+        let dependency = vk::SubpassDependency::builder()
+            .src_subpass(vk::SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE);
+        let render_pass_info = vk::RenderPassCreateInfo::builder()
+            .attachments(std::slice::from_ref(&target_attachment))
+            .subpasses(std::slice::from_ref(&subpass))
+            .dependencies(std::slice::from_ref(&dependency));
+        let render_pass = unsafe { device.create_render_pass(&render_pass_info, None).unwrap() };
+        let framebuffers = self
+            .scene_target_views()
             .iter()
-            .map(|&view| {
-                let attachments = [view, depth_image_view];
+            .map(|view| {
                 let framebuffer_info = vk::FramebufferCreateInfo::builder()
                     .render_pass(render_pass)
-                    .attachments(&attachments)
-                    .width(extent.width)
-                    .height(extent.height)
+                    .attachments(std::slice::from_ref(view))
+                    .width(self.render_extent.width)
+                    .height(self.render_extent.height)
                     .layers(1);
                 unsafe { device.create_framebuffer(&framebuffer_info, None).unwrap() }
             })
-            .collect()
-    }
-
-    fn create_command_pool(device: &ash::Device, indices: &QueueFamilyIndices) -> vk::CommandPool {
-        let pool_info = vk::CommandPoolCreateInfo::builder()
-            .queue_family_index(indices.graphics_family.unwrap())
-            .flags(vk::CommandPoolCreateFlags::empty());
-        unsafe { device.create_command_pool(&pool_info, None).unwrap() }
-    }
-
-    fn create_command_buffers(
-        device: &ash::Device,
-        command_pool: vk::CommandPool,
-        framebuffer_count: usize,
-    ) -> Vec<vk::CommandBuffer> {
-        let alloc_info = vk::CommandBufferAllocateInfo::builder()
-            .command_pool(command_pool)
-            .level(vk::CommandBufferLevel::PRIMARY)
-            .command_buffer_count(framebuffer_count as u32);
-        unsafe { device.allocate_command_buffers(&alloc_info).unwrap() }
-    }
+            .collect();
 
-    fn record_command_buffer(&self, command_buffer: vk::CommandBuffer, image_index: usize) {
-        let begin_info = vk::CommandBufferBeginInfo::builder();
-        unsafe {
-            self.device
-                .begin_command_buffer(command_buffer, &begin_info)
-                .unwrap();
-        }
+        // Sampled views can only have one aspect.
+        let image_view = self.context.create_image_view(
+            resolved.map_or(self.swapchain.depth_image, |(image, _, _)| image),
+            depth_format,
+            vk::ImageAspectFlags::DEPTH,
+        );
+        let sampler_info = vk::SamplerCreateInfo::builder()
+            .mag_filter(vk::Filter::NEAREST)
+            .min_filter(vk::Filter::NEAREST)
+            .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .max_lod(0.0);
+        let sampler = unsafe { device.create_sampler(&sampler_info, None).unwrap() };
+
+        let binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+            .build();
+        let layout_info =
+            vk::DescriptorSetLayoutCreateInfo::builder().bindings(std::slice::from_ref(&binding));
+        let set_layout = unsafe {
+            device
+                .create_descriptor_set_layout(&layout_info, None)
+                .unwrap()
+        };
+        let pool_size = vk::DescriptorPoolSize::builder()
+            .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .build();
+        let pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(std::slice::from_ref(&pool_size))
+            .max_sets(1);
+        let descriptor_pool = unsafe { device.create_descriptor_pool(&pool_info, None).unwrap() };
+        let alloc_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(std::slice::from_ref(&set_layout));
+        let descriptor_set = unsafe { device.allocate_descriptor_sets(&alloc_info).unwrap()[0] };
+        let image_info = vk::DescriptorImageInfo::builder()
+            .image_layout(vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL)
+            .image_view(image_view)
+            .sampler(sampler)
+            .build();
+        let write = vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(std::slice::from_ref(&image_info))
+            .build();
+        unsafe { device.update_descriptor_sets(std::slice::from_ref(&write), &[]) };
 
-        let clear_color = vk::ClearValue {
-            color: vk::ClearColorValue {
-                float32: [0.0, 0.0, 0.0, 1.0],
-            },
+        // The near and far planes, to linearize depth with.
+        let push_constant_range = vk::PushConstantRange::builder()
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+            .offset(0)
+            .size(2 * std::mem::size_of::<f32>() as u32)
+            .build();
+        let pipeline_layout_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(std::slice::from_ref(&set_layout))
+            .push_constant_ranges(std::slice::from_ref(&push_constant_range));
+        let pipeline_layout = unsafe {
+            device
+                .create_pipeline_layout(&pipeline_layout_info, None)
+                .unwrap()
         };
-        let depth_clear = vk::ClearValue {
-            depth_stencil: vk::ClearDepthStencilValue {
-                depth: 1.0,
-                stencil: 0,
+
+        // Background's vertex shader draws the screen-covering triangle.
+        let vert_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("BACKGROUND_VERT_SHADER_PATH")));
+        let frag_shader_module = Self::create_shader_module(
+            device,
+            if self.msaa_samples == vk::SampleCountFlags::TYPE_1 || resolved.is_some() {
+                include_bytes!(env!("DEPTH_VIEW_FRAG_SHADER_PATH"))
+            } else {
+                include_bytes!(env!("DEPTH_VIEW_FRAG_MULTISAMPLED_1_SHADER_PATH"))
             },
+        );
+        let main_function_name = CString::new("main").unwrap();
+        let (specialization_entries, specialization_bytes) = Self::bool_specialization(&[
+            self.needs_manual_srgb,
+            self.depth_direction == DepthDirection::Reversed,
+        ]);
+        let specialization_info = vk::SpecializationInfo::builder()
+            .map_entries(&specialization_entries)
+            .data(&specialization_bytes);
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_shader_module)
+                .name(&main_function_name)
+                .build(),
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_shader_module)
+                .name(&main_function_name)
+                .specialization_info(&specialization_info)
+                .build(),
+        ];
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::builder();
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo::builder()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(vk::CullModeFlags::NONE);
+        let multisampling = vk::PipelineMultisampleStateCreateInfo::builder()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder();
+        let color_blend_attachment = vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(false);
+        let color_blending = vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(std::slice::from_ref(&color_blend_attachment));
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states);
+        let pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&shader_stages)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterizer)
+            .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&color_blending)
+            .dynamic_state(&dynamic_state)
+            .layout(pipeline_layout)
+            .render_pass(render_pass)
+            .subpass(0);
+        let pipeline = unsafe {
+            device
+                .create_graphics_pipelines(
+                    vk::PipelineCache::null(),
+                    std::slice::from_ref(&pipeline_info),
+                    None,
+                )
+                .unwrap()[0]
         };
-        let clear_values = [clear_color, depth_clear];
-        let render_pass_info = vk::RenderPassBeginInfo::builder()
-            .render_pass(self.render_pass)
-            .framebuffer(self.framebuffers[image_index])
-            .render_area(vk::Rect2D {
-                offset: vk::Offset2D { x: 0, y: 0 },
-                extent: self.swapchain_extent,
-            })
-            .clear_values(&clear_values);
-
         unsafe {
-            self.device.cmd_begin_render_pass(
-                command_buffer,
-                &render_pass_info,
-                vk::SubpassContents::INLINE,
-            );
-            self.device.cmd_bind_pipeline(
-                command_buffer,
-                vk::PipelineBindPoint::GRAPHICS,
-                self.graphics_pipeline,
-            );
-            let vertex_buffers = [self.vertex_buffer];
-            let offsets = [0];
-            self.device
-                .cmd_bind_vertex_buffers(command_buffer, 0, &vertex_buffers, &offsets);
-            self.device.cmd_bind_index_buffer(
-                command_buffer,
-                self.index_buffer,
-                0,
-                vk::IndexType::UINT16,
-            );
-            self.device.cmd_bind_descriptor_sets(
-                command_buffer,
-                vk::PipelineBindPoint::GRAPHICS,
-                self.pipeline_layout,
-                0,
-                &[self.descriptor_sets[image_index]],
-                &[],
-            );
-            self.device
-                .cmd_draw_indexed(command_buffer, INDICES.len() as u32, 1, 0, 0, 0);
-            self.device.cmd_end_render_pass(command_buffer);
-            self.device.end_command_buffer(command_buffer).unwrap();
+            device.destroy_shader_module(vert_shader_module, None);
+            device.destroy_shader_module(frag_shader_module, None);
         }
-    }
-
-    fn create_sync_objects(device: &ash::Device) -> (vk::Semaphore, vk::Semaphore, vk::Fence) {
-        let semaphore_info = vk::SemaphoreCreateInfo::builder();
-        let fence_info = vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED);
-
-        let image_available_semaphore =
-            unsafe { device.create_semaphore(&semaphore_info, None).unwrap() };
-        let render_finished_semaphore =
-            unsafe { device.create_semaphore(&semaphore_info, None).unwrap() };
-        let in_flight_fence = unsafe { device.create_fence(&fence_info, None).unwrap() };
-
-        (
-            image_available_semaphore,
-            render_finished_semaphore,
-            in_flight_fence,
-        )
-    }
 
-    fn cleanup_swapchain(&mut self) {
-        unsafe {
-            for i in 0..self.uniform_buffers.len() {
-                self.device.destroy_buffer(self.uniform_buffers[i], None);
-                self.device
-                    .free_memory(self.uniform_buffers_memory[i], None);
-            }
-            for framebuffer in self.framebuffers.iter() {
-                self.device.destroy_framebuffer(*framebuffer, None);
-            }
-            self.device.destroy_pipeline(self.graphics_pipeline, None);
-            self.device
-                .destroy_descriptor_pool(self.descriptor_pool, None);
-            self.device
-                .destroy_pipeline_layout(self.pipeline_layout, None);
-            self.device.destroy_render_pass(self.render_pass, None);
-            for image_view in self.swapchain_image_views.iter() {
-                self.device.destroy_image_view(*image_view, None);
-            }
-            self.device.destroy_image_view(self.depth_image_view, None);
-            self.device.destroy_image(self.depth_image, None);
-            self.device.free_memory(self.depth_image_memory, None);
-            self.swapchain_loader
-                .destroy_swapchain(self.swapchain, None);
+        self.resources.register(
+            ResourceKind::ImageView,
+            image_view,
+            "depth view image view",
+            0,
+        );
+        self.resources
+            .register(ResourceKind::Sampler, sampler, "depth view sampler", 0);
+        self.resources.register(
+            ResourceKind::DescriptorPool,
+            descriptor_pool,
+            "depth view descriptor pool",
+            0,
+        );
+        self.resources
+            .register(ResourceKind::Pipeline, pipeline, "depth view pipeline", 0);
+        DepthView {
+            scene_pass,
+            render_pass,
+            framebuffers,
+            image_view,
+            resolved,
+            generation: self.swapchain.generation,
+            aspect_mask: context::depth_aspect_flags(depth_format),
+            sampler,
+            set_layout,
+            descriptor_pool,
+            descriptor_set,
+            pipeline_layout,
+            pipeline,
         }
     }
 
-    fn recreate_swapchain(&mut self, window: &winit::window::Window) {
-        unsafe {
-            self.device.device_wait_idle().unwrap();
-        }
-        self.cleanup_swapchain();
-
-        let depth_format = Self::find_depth_format(&self.instance, self.physical_device);
-        self.render_pass =
-            Self::create_render_pass(&self.device, self.swapchain_format, depth_format);
-
-        let (swapchain, swapchain_format, swapchain_extent) = Self::create_swapchain(
-            &self.instance,
-            &self.device,
-            self.physical_device,
-            &self.surface_loader,
-            self.surface,
-            &self.queue_family_indices,
-            &self.swapchain_loader,
-            window,
+    /// The image the dynamic rendering path resolves multisampled depth
+    /// into for the depth view, on devices with depth stencil resolve.
+    /// Short of memory for it, the view reads the multisampled depth image
+    /// as the render pass path does.
+    fn create_depth_resolve_image(&mut self) -> Option<(vk::Image, Allocation, vk::ImageView)> {
+        if self.dynamic_rendering.is_none()
+            || !self.capabilities.depth_stencil_resolve
+            || self.msaa_samples == vk::SampleCountFlags::TYPE_1
+        {
+            return None;
+        }
+        let extent = self.render_extent;
+        let image = self.context.create_image(
+            extent.width,
+            extent.height,
+            vk::SampleCountFlags::TYPE_1,
+            self.depth_format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
         );
-        self.swapchain = swapchain;
-        self.swapchain_images = unsafe {
-            self.swapchain_loader
-                .get_swapchain_images(swapchain)
-                .unwrap()
+        let (image, allocation) = match image {
+            Ok(image) => image,
+            Err(error) => {
+                warn!("{}; the depth view reads sample 0 in place", error);
+                return None;
+            }
         };
-        self.swapchain_format = swapchain_format;
-        self.swapchain_extent = swapchain_extent;
-        self.swapchain_image_views =
-            Self::create_image_views(&self.device, &self.swapchain_images, self.swapchain_format);
-        let depth_format = Self::find_depth_format(&self.instance, self.physical_device);
-        self.render_pass =
-            Self::create_render_pass(&self.device, self.swapchain_format, depth_format);
-        let (graphics_pipeline, pipeline_layout) = Self::create_graphics_pipeline(
-            &self.device,
-            self.render_pass,
-            self.swapchain_extent,
-            self.descriptor_set_layout,
+        let attachment_view = self.context.create_image_view(
+            image,
+            self.depth_format,
+            context::depth_aspect_flags(self.depth_format),
         );
-        self.graphics_pipeline = graphics_pipeline;
-        self.pipeline_layout = pipeline_layout;
-        let (depth_image, depth_image_memory, depth_image_view) = Self::create_depth_resources(
-            &self.instance,
-            &self.device,
-            self.physical_device,
-            self.swapchain_extent,
-        );
-        self.depth_image = depth_image;
-        self.depth_image_memory = depth_image_memory;
-        self.depth_image_view = depth_image_view;
-        self.framebuffers = Self::create_framebuffers(
-            &self.device,
-            &self.swapchain_image_views,
-            self.depth_image_view,
-            self.render_pass,
-            self.swapchain_extent,
-        );
-        let (uniform_buffers, uniform_buffers_memory) = Self::create_uniform_buffers(
-            &self.instance,
-            &self.device,
-            self.physical_device,
-            self.swapchain_images.len(),
-        );
-        self.uniform_buffers = uniform_buffers;
-        self.uniform_buffers_memory = uniform_buffers_memory;
-        let (descriptor_pool, descriptor_sets) = Self::create_descriptor_pool(
-            &self.device,
-            self.swapchain_images.len(),
-            self.descriptor_set_layout,
-        );
-        self.descriptor_pool = descriptor_pool;
-        self.descriptor_sets = descriptor_sets;
-        self.descriptor_sets = Self::create_descriptor_sets(
-            &self.device,
-            self.descriptor_pool,
-            self.descriptor_set_layout,
-            &self.uniform_buffers,
-            self.swapchain_images.len(),
+        let size = unsafe {
+            self.context
+                .device
+                .get_image_memory_requirements(image)
+                .size
+        };
+        self.resources
+            .register(ResourceKind::Image, image, "resolved depth image", size);
+        self.resources.register(
+            ResourceKind::ImageView,
+            attachment_view,
+            "resolved depth image view",
+            0,
         );
+        Some((image, allocation, attachment_view))
     }
 
-    pub fn draw_frame(&mut self, window: &winit::window::Window) {
+    fn destroy_depth_view(&mut self, view: DepthView) {
+        let device = &self.context.device;
+        self.resources
+            .unregister(ResourceKind::Pipeline, view.pipeline);
+        self.resources
+            .unregister(ResourceKind::DescriptorPool, view.descriptor_pool);
+        self.resources
+            .unregister(ResourceKind::Sampler, view.sampler);
+        self.resources
+            .unregister(ResourceKind::ImageView, view.image_view);
         unsafe {
-            self.device
-                .wait_for_fences(std::slice::from_ref(&self.in_flight_fence), true, u64::MAX)
-                .unwrap();
+            device.destroy_pipeline(view.pipeline, None);
+            device.destroy_pipeline_layout(view.pipeline_layout, None);
+            device.destroy_descriptor_pool(view.descriptor_pool, None);
+            device.destroy_descriptor_set_layout(view.set_layout, None);
+            device.destroy_sampler(view.sampler, None);
+            device.destroy_image_view(view.image_view, None);
+            if let Some((image, allocation, attachment_view)) = view.resolved {
+                self.resources
+                    .unregister(ResourceKind::ImageView, attachment_view);
+                device.destroy_image_view(attachment_view, None);
+                self.resources.unregister(ResourceKind::Image, image);
+                device.destroy_image(image, None);
+                self.context.allocator.free(allocation);
+            }
+            for framebuffer in view.framebuffers {
+                device.destroy_framebuffer(framebuffer, None);
+            }
+            device.destroy_render_pass(view.render_pass, None);
+            device.destroy_render_pass(view.scene_pass, None);
+        }
+    }
 
-            let result = self.swapchain_loader.acquire_next_image(
-                self.swapchain,
-                u64::MAX,
-                self.image_available_semaphore,
-                vk::Fence::null(),
+    /// Rebuilds the depth view, if shown, to match the render pass. The GPU
+    /// must be idle.
+    fn refresh_depth_view(&mut self) {
+        if let Some(view) = self.depth_view.take() {
+            self.destroy_depth_view(view);
+            self.depth_view = Some(self.create_depth_view());
+        }
+    }
+
+    /// One persistently mapped vertex buffer per frame in flight, so a frame
+    /// never rewrites vertices the GPU may still be reading.
+    #[cfg(feature = "overlay")]
+    fn create_graph_buffers(
+        context: &VkContext,
+        resources: &mut ResourceRegistry,
+    ) -> Result<FrameResources<GraphBuffer, MAX_FRAMES_IN_FLIGHT>, VulkanError> {
+        let size = (GRAPH_VERTICES * std::mem::size_of::<GraphVertex>()) as vk::DeviceSize;
+        FrameResources::try_from_fn(|i| {
+            let (buffer, allocation) = context.create_buffer(
+                ResourceClass::Vertex,
+                size,
+                vk::BufferUsageFlags::VERTEX_BUFFER,
+            )?;
+            resources.register(
+                ResourceKind::Buffer,
+                buffer,
+                &format!("frame graph vertices {}", i),
+                size,
             );
+            Ok(GraphBuffer {
+                buffer,
+                allocation,
+                mapped: allocation.mapped_ptr() as *mut GraphVertex,
+                series_vertices: 0,
+            })
+        })
+    }
+
+    /// Creates one pipeline per key in a single `create_graphics_pipelines` call.
+    /// Touches only its arguments, so it can run on a worker thread; the
+    /// caller registers the results.
+    fn create_graphics_pipeline(
+        device: &ash::Device,
+        target: PassTarget,
+        pipeline_layout: vk::PipelineLayout,
+        draw_packing: DrawConstantPacking,
+        manual_srgb: bool,
+        cube_shaders: Option<&CubeShaders>,
+        keys: &[PipelineKey],
+    ) -> Vec<vk::Pipeline> {
+        // The vertex stage reads only `model`, so for it 1 is the same as 0.
+        let vert_shader_code: &[u8] = match (cube_shaders, draw_packing.push_fields()) {
+            (Some(shaders), push_fields) => shaders.vertex(push_fields),
+            (None, 0 | 1) => include_bytes!(env!("SHADER_VERT_DRAW_PUSH_FIELDS_0_SHADER_PATH")),
+            (None, _) => include_bytes!(env!("SHADER_VERT_SHADER_PATH")),
+        };
+        let frag_shader_code: &[u8] = match (cube_shaders, draw_packing.push_fields()) {
+            (Some(shaders), push_fields) => shaders.fragment(push_fields),
+            (None, 0) => include_bytes!(env!("SHADER_FRAG_DRAW_PUSH_FIELDS_0_SHADER_PATH")),
+            (None, _) => include_bytes!(env!("SHADER_FRAG_SHADER_PATH")),
+        };
+
+        let vert_shader_module = Self::create_shader_module(device, vert_shader_code);
+        let frag_shader_module = Self::create_shader_module(device, frag_shader_code);
+
+        let main_function_name = CString::new("main").unwrap();
 
-            let image_index = match result {
-                Ok((image_index, is_suboptimal)) => {
-                    if is_suboptimal {
-                        self.framebuffer_resized = true;
+        // NAIVE_SRGB_INTERPOLATION, then MANUAL_SRGB_ENCODE.
+        let specialization_data: Vec<[vk::Bool32; 2]> = keys
+            .iter()
+            .map(|key| {
+                let interpolation = match key {
+                    PipelineKey::Cube(variant, _) => variant.specialization_value(),
+                    PipelineKey::DepthPrepass(_) | PipelineKey::Wireframe(_) => {
+                        ColorInterpolation::Linear.specialization_value()
                     }
-                    image_index
-                }
-                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
-                    self.recreate_swapchain(window);
-                    return;
+                };
+                [interpolation, manual_srgb as vk::Bool32]
+            })
+            .collect();
+        let specialization_entries = [0, 1].map(|constant_id| vk::SpecializationMapEntry {
+            constant_id,
+            offset: constant_id * std::mem::size_of::<vk::Bool32>() as u32,
+            size: std::mem::size_of::<vk::Bool32>(),
+        });
+        let specialization_infos: Vec<vk::SpecializationInfo> = specialization_data
+            .iter()
+            .map(|data| {
+                vk::SpecializationInfo::builder()
+                    .map_entries(&specialization_entries)
+                    .data(unsafe {
+                        std::slice::from_raw_parts(
+                            data.as_ptr() as *const u8,
+                            std::mem::size_of::<[vk::Bool32; 2]>(),
+                        )
+                    })
+                    .build()
+            })
+            .collect();
+
+        let shader_stages: Vec<Vec<vk::PipelineShaderStageCreateInfo>> = keys
+            .iter()
+            .zip(specialization_infos.iter())
+            .map(|(key, specialization_info)| {
+                let vert_shader_stage_info = vk::PipelineShaderStageCreateInfo::builder()
+                    .stage(vk::ShaderStageFlags::VERTEX)
+                    .module(vert_shader_module)
+                    .name(&main_function_name)
+                    .specialization_info(specialization_info);
+
+                let frag_shader_stage_info = vk::PipelineShaderStageCreateInfo::builder()
+                    .stage(vk::ShaderStageFlags::FRAGMENT)
+                    .module(frag_shader_module)
+                    .name(&main_function_name)
+                    .specialization_info(specialization_info);
+
+                match key {
+                    PipelineKey::Cube(..) | PipelineKey::Wireframe(_) => vec![
+                        vert_shader_stage_info.build(),
+                        frag_shader_stage_info.build(),
+                    ],
+                    // Same vertex stage as the main pass so the depths match
+                    // exactly under EQUAL; no fragment stage.
+                    PipelineKey::DepthPrepass(_) => vec![vert_shader_stage_info.build()],
                 }
-                Err(error) => panic!("Error acquiring swapchain image: {}", error),
-            };
+            })
+            .collect();
+
+        let binding_description = Vertex::get_binding_description();
+        let attribute_descriptions = Vertex::get_attribute_descriptions();
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_binding_descriptions(std::slice::from_ref(&binding_description))
+            .vertex_attribute_descriptions(&attribute_descriptions);
 
-            self.update_uniform_buffer(image_index as usize);
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+            .primitive_restart_enable(false);
 
-            self.device
-                .reset_fences(std::slice::from_ref(&self.in_flight_fence))
-                .unwrap();
+        // Set per frame, so a resize doesn't rebuild any pipeline.
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
 
-            self.device
-                .reset_command_buffer(
-                    self.command_buffers[image_index as usize],
-                    vk::CommandBufferResetFlags::empty(),
-                )
-                .unwrap();
-            self.record_command_buffer(
-                self.command_buffers[image_index as usize],
-                image_index as usize,
-            );
+        let rasterizers: Vec<vk::PipelineRasterizationStateCreateInfo> = keys
+            .iter()
+            .map(|key| {
+                let (PipelineKey::Cube(_, faces)
+                | PipelineKey::DepthPrepass(faces)
+                | PipelineKey::Wireframe(faces)) = *key;
+                let render_mode = match key {
+                    PipelineKey::Wireframe(_) => RenderMode::Wireframe,
+                    _ => RenderMode::Fill,
+                };
+                vk::PipelineRasterizationStateCreateInfo::builder()
+                    .depth_clamp_enable(false)
+                    .rasterizer_discard_enable(false)
+                    .polygon_mode(render_mode.polygon_mode())
+                    .line_width(1.0)
+                    .cull_mode(faces.cull_mode())
+                    .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+                    .depth_bias_enable(false)
+                    .build()
+            })
+            .collect();
 
-            let wait_semaphores = [self.image_available_semaphore];
-            let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-            let signal_semaphores = [self.render_finished_semaphore];
-            let submit_info = vk::SubmitInfo::builder()
-                .wait_semaphores(&wait_semaphores)
-                .wait_dst_stage_mask(&wait_stages)
-                .command_buffers(std::slice::from_ref(
-                    &self.command_buffers[image_index as usize],
-                ))
-                .signal_semaphores(&signal_semaphores);
+        let multisampling = vk::PipelineMultisampleStateCreateInfo::builder()
+            .sample_shading_enable(false)
+            .rasterization_samples(target.samples);
 
-            self.device
-                .queue_submit(
-                    self.graphics_queue,
-                    std::slice::from_ref(&submit_info),
-                    self.in_flight_fence,
-                )
-                .unwrap();
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(true)
+            .depth_write_enable(!DEPTH_PREPASS)
+            .depth_compare_op(if DEPTH_PREPASS {
+                vk::CompareOp::EQUAL
+            } else {
+                target.depth_direction.compare_op()
+            })
+            .depth_bounds_test_enable(false)
+            .stencil_test_enable(false);
 
-            let swapchains = [self.swapchain];
-            let present_info = vk::PresentInfoKHR::builder()
-                .wait_semaphores(&signal_semaphores)
-                .swapchains(&swapchains)
-                .image_indices(std::slice::from_ref(&image_index));
+        let prepass_depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(true)
+            .depth_write_enable(true)
+            .depth_compare_op(target.depth_direction.compare_op())
+            .depth_bounds_test_enable(false)
+            .stencil_test_enable(false);
 
-            let result = self
-                .swapchain_loader
-                .queue_present(self.present_queue, &present_info);
+        let color_blend_attachment = vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(false);
 
-            let mut recreate_swapchain = false;
-            match result {
-                Ok(is_suboptimal) => {
-                    if is_suboptimal {
-                        recreate_swapchain = true;
+        let color_blending = vk::PipelineColorBlendStateCreateInfo::builder()
+            .logic_op_enable(false)
+            .attachments(std::slice::from_ref(&color_blend_attachment));
+
+        // The pre-pass subpass has no color attachments.
+        let prepass_color_blending =
+            vk::PipelineColorBlendStateCreateInfo::builder().logic_op_enable(false);
+
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states);
+
+        // One per pipeline, as each is chained into its own create info.
+        let mut rendering_infos: Vec<vk::PipelineRenderingCreateInfo> = keys
+            .iter()
+            .map(|_| target.pipeline_rendering_info().build())
+            .collect();
+        let pipeline_infos: Vec<vk::GraphicsPipelineCreateInfo> = keys
+            .iter()
+            .zip(shader_stages.iter())
+            .zip(rasterizers.iter())
+            .zip(rendering_infos.iter_mut())
+            .map(|(((key, stages), rasterizer), rendering_info)| {
+                let (depth_stencil, color_blending, subpass) = match key {
+                    PipelineKey::Cube(..) | PipelineKey::Wireframe(_) => (
+                        &depth_stencil,
+                        &color_blending,
+                        if DEPTH_PREPASS { 1 } else { 0 },
+                    ),
+                    PipelineKey::DepthPrepass(_) => {
+                        (&prepass_depth_stencil, &prepass_color_blending, 0)
                     }
+                };
+                let pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+                    .stages(stages)
+                    .vertex_input_state(&vertex_input_info)
+                    .input_assembly_state(&input_assembly)
+                    .viewport_state(&viewport_state)
+                    .rasterization_state(rasterizer)
+                    .multisample_state(&multisampling)
+                    .depth_stencil_state(depth_stencil)
+                    .color_blend_state(color_blending)
+                    .dynamic_state(&dynamic_state)
+                    .layout(pipeline_layout)
+                    .render_pass(target.render_pass)
+                    .subpass(subpass);
+                if target.is_dynamic() {
+                    pipeline_info.push_next(rendering_info).build()
+                } else {
+                    pipeline_info.build()
                 }
-                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) | Err(vk::Result::SUBOPTIMAL_KHR) => {
-                    recreate_swapchain = true;
-                }
-                Err(error) => panic!("Failed to present swapchain image: {}", error),
-            }
+            })
+            .collect();
 
-            if self.framebuffer_resized || recreate_swapchain {
-                self.framebuffer_resized = false;
-                self.recreate_swapchain(window);
-            }
+        let pipelines = unsafe {
+            device
+                .create_graphics_pipelines(vk::PipelineCache::null(), &pipeline_infos, None)
+                .unwrap()
+        };
+        unsafe {
+            device.destroy_shader_module(vert_shader_module, None);
+            device.destroy_shader_module(frag_shader_module, None);
         }
+
+        pipelines
     }
 
-    fn update_uniform_buffer(&self, current_image: usize) {
-        let time = self.start_time.elapsed().as_secs_f32();
+    /// Viewport over `rect`. With a negative height (y = bottom) the
+    /// rasterizer does the Y flip and the projection is left conventional; both
+    /// flips mirror identically in framebuffer space, so front-face winding is
+    /// the same under either strategy.
+    fn viewport(rect: vk::Rect2D, capabilities: &DeviceCapabilities) -> vk::Viewport {
+        let (top, extent) = (rect.offset.y as f32, rect.extent);
+        let (y, height) = if capabilities.negative_viewport_height {
+            (top + extent.height as f32, -(extent.height as f32))
+        } else {
+            (top, extent.height as f32)
+        };
+        vk::Viewport {
+            x: rect.offset.x as f32,
+            y,
+            width: extent.width as f32,
+            height,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }
+    }
 
-        let model = Matrix4::from_angle_z(cgmath::Deg(time * 90.0));
-        let view = Matrix4::look_at_rh(
-            Point3::new(2.0, 2.0, 2.0),
-            Point3::new(0.0, 0.0, 0.0),
-            Vector3::new(0.0, 0.0, 1.0),
-        );
-        let mut proj = cgmath::perspective(
-            cgmath::Deg(45.0),
-            self.swapchain_extent.width as f32 / self.swapchain_extent.height as f32,
-            0.1,
-            10.0,
-        );
-        proj[1][1] *= -1.0;
+    fn create_shader_module(device: &ash::Device, code: &[u8]) -> vk::ShaderModule {
+        let create_info = vk::ShaderModuleCreateInfo::builder().code(unsafe {
+            std::slice::from_raw_parts(code.as_ptr() as *const u32, code.len() / 4)
+        });
+        unsafe { device.create_shader_module(&create_info, None).unwrap() }
+    }
 
-        let ubo = UniformBufferObject { model, view, proj };
+    /// Bool specialization constants 1, 2 and on, set to `values`: in the
+    /// skybox and depth view shaders, MANUAL_SRGB_ENCODE then REVERSED_Z.
+    fn bool_specialization(values: &[bool]) -> (Vec<vk::SpecializationMapEntry>, Vec<u8>) {
+        let size = std::mem::size_of::<vk::Bool32>();
+        let entries = (0..values.len())
+            .map(|i| vk::SpecializationMapEntry {
+                constant_id: i as u32 + 1,
+                offset: (i * size) as u32,
+                size,
+            })
+            .collect();
+        let bytes = values
+            .iter()
+            .flat_map(|&value| (value as vk::Bool32).to_ne_bytes())
+            .collect();
+        (entries, bytes)
+    }
 
-        unsafe {
-            let data_ptr = self
-                .device
-                .map_memory(
-                    self.uniform_buffers_memory[current_image],
-                    0,
-                    std::mem::size_of::<UniformBufferObject>() as vk::DeviceSize,
-                    vk::MemoryMapFlags::empty(),
-                )
-                .unwrap();
-            let mut align = ash::util::Align::new(
-                data_ptr,
-                std::mem::align_of::<UniformBufferObject>() as _,
-                std::mem::size_of::<UniformBufferObject>() as vk::DeviceSize,
+    fn create_command_buffers(
+        device: &ash::Device,
+        command_pool: vk::CommandPool,
+        count: usize,
+    ) -> Vec<vk::CommandBuffer> {
+        let alloc_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(count as u32);
+        unsafe { device.allocate_command_buffers(&alloc_info).unwrap() }
+    }
+
+    /// One instanced draw per run of objects sharing a cull mode, or with
+    /// `draw_stress` one draw per object.
+    fn build_draw_list(&mut self, animation_time: f32) {
+        self.draw_list.clear();
+        let (cube_mesh, material) = if self.config.open_box {
+            (self.meshes[OPEN_BOX], CUBE_MATERIAL)
+        } else if self.show_face_labels {
+            (self.meshes[LABELED_CUBE], CUBE_MATERIAL)
+        } else if self.cube_texture.is_some() {
+            (self.meshes[TEXTURED_CUBE], TEXTURED_MATERIAL)
+        } else {
+            (self.meshes[COLOR_CUBE], CUBE_MATERIAL)
+        };
+        let cube_depth = self.camera_eye().to_vec().magnitude();
+        let constants = DrawConstants {
+            model: self
+                .model_rotation
+                .map_or(Matrix4::identity(), |rotation| rotation.matrix()),
+            ..DrawConstants::default()
+        };
+        let mut first = 0;
+        let runs: Vec<&[bool]> = if self.config.draw_stress.is_some() {
+            self.double_sided.chunks(1).collect()
+        } else {
+            self.double_sided.chunk_by(|a, b| a == b).collect()
+        };
+        for run in runs {
+            let pipeline = if run[0] {
+                DOUBLE_SIDED_PIPELINE
+            } else {
+                CUBE_PIPELINE
+            };
+            let end = first + run.len() as u32;
+            self.draw_list.push_opaque(
+                pipeline,
+                material,
+                Self::pooled_meshes(cube_mesh),
+                cube_mesh,
+                first..end,
+                cube_depth,
+                constants,
             );
-            align.copy_from_slice(&[ubo]);
-            self.device
-                .unmap_memory(self.uniform_buffers_memory[current_image]);
+            first = end;
+        }
+        // Instance 0's transform spins them along with the main cube.
+        for object in &self.objects {
+            let model = object.model(animation_time);
+            self.draw_list.push_opaque(
+                CUBE_PIPELINE,
+                material,
+                Self::pooled_meshes(cube_mesh),
+                cube_mesh,
+                0..1,
+                (self.camera_eye() - Point3::from_vec(model.w.truncate())).magnitude(),
+                DrawConstants {
+                    model,
+                    ..DrawConstants::default()
+                },
+            );
+        }
+        self.draw_list.sort();
+        if let Some(picking) = &mut self.picking {
+            let animated = self.animator.object_count();
+            picking.draws.clear();
+            picking.draws.push(PickDraw {
+                mesh: cube_mesh,
+                model: constants.model,
+                instances: 0..animated,
+                first_id: pick::object_id(0),
+            });
+            for (index, object) in self.objects.iter().enumerate() {
+                picking.draws.push(PickDraw {
+                    mesh: cube_mesh,
+                    model: object.model(animation_time),
+                    instances: 0..1,
+                    first_id: pick::object_id(animated + index as u32),
+                });
+            }
         }
     }
 
-    fn create_index_buffer(
-        instance: &ash::Instance,
-        device: &ash::Device,
-        pdevice: vk::PhysicalDevice,
-        _indices: &QueueFamilyIndices,
-        data: &[u16],
-    ) -> (vk::Buffer, vk::DeviceMemory) {
-        let buffer_size = (std::mem::size_of::<u16>() * INDICES.len()) as vk::DeviceSize;
-        let (buffer, buffer_memory) = Self::create_buffer(
-            instance,
-            device,
-            pdevice,
-            buffer_size,
-            vk::BufferUsageFlags::INDEX_BUFFER,
-            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-        );
+    /// Moves a swapchain image from `config.final_layout` to PRESENT_SRC_KHR
+    /// once the frame's commands are done with it.
+    /// The layout each frame leaves its image in: PRESENT_SRC_KHR for the
+    /// swapchain, and the render pass's final layout when headless, where
+    /// nothing is presented.
+    fn release_layout(&self) -> vk::ImageLayout {
+        match self.context.surface {
+            Some(_) => vk::ImageLayout::PRESENT_SRC_KHR,
+            None => self.config.final_layout,
+        }
+    }
 
+    fn record_present_transition(&self, command_buffer: vk::CommandBuffer, image: vk::Image) {
+        let barrier = vk::ImageMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(vk::AccessFlags::empty())
+            .old_layout(self.config.final_layout)
+            .new_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            });
         unsafe {
-            let data_ptr = device
-                .map_memory(buffer_memory, 0, buffer_size, vk::MemoryMapFlags::empty())
-                .unwrap();
-            let mut align =
-                ash::util::Align::new(data_ptr, std::mem::align_of::<u16>() as _, buffer_size);
-            align.copy_from_slice(data);
-            device.unmap_memory(buffer_memory);
+            self.context.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                std::slice::from_ref(&barrier),
+            );
         }
-
-        (buffer, buffer_memory)
     }
 
-    fn create_vertex_buffer(
-        instance: &ash::Instance,
-        device: &ash::Device,
-        pdevice: vk::PhysicalDevice,
-        _indices: &QueueFamilyIndices,
-        data: &[Vertex],
-    ) -> (vk::Buffer, vk::DeviceMemory) {
-        let buffer_size = (std::mem::size_of::<Vertex>() * VERTICES.len()) as vk::DeviceSize;
-        let (buffer, buffer_memory) = Self::create_buffer(
-            instance,
-            device,
-            pdevice,
-            buffer_size,
-            vk::BufferUsageFlags::VERTEX_BUFFER,
-            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-        );
-
+    fn record_command_buffer(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        animation_time: f32,
+        clear_color: [f32; 4],
+    ) -> DrawStats {
+        let begin_info = vk::CommandBufferBeginInfo::builder();
         unsafe {
-            let data_ptr = device
-                .map_memory(buffer_memory, 0, buffer_size, vk::MemoryMapFlags::empty())
+            self.context
+                .device
+                .begin_command_buffer(command_buffer, &begin_info)
                 .unwrap();
-            let mut align =
-                ash::util::Align::new(data_ptr, std::mem::align_of::<Vertex>() as _, buffer_size);
-            align.copy_from_slice(data);
-            device.unmap_memory(buffer_memory);
+        }
+        self.watchdog
+            .checkpoint(command_buffer, Checkpoint::BeginCommandBuffer);
+        self.animator
+            .record_dispatch(&self.context.device, command_buffer, frame, animation_time);
+        if let (true, Some(plasma), Some(morphed)) =
+            (self.plasma_colors, &self.plasma, &self.morphed_vertices)
+        {
+            plasma.record_dispatch(
+                &self.context.device,
+                command_buffer,
+                frame,
+                morphed.buffer(frame),
+                animation_time,
+            );
+        }
+        if let Some(queries) = &self.statistics_queries {
+            unsafe {
+                self.context
+                    .device
+                    .cmd_reset_query_pool(command_buffer, queries[frame], 0, 1);
+                self.context.device.cmd_begin_query(
+                    command_buffer,
+                    queries[frame],
+                    0,
+                    vk::QueryControlFlags::empty(),
+                );
+            }
         }
 
-        (buffer, buffer_memory)
-    }
+        // Extra windows' swapchains encode, or don't, for themselves.
+        let linear_clear_color = clear_color;
+        // Clears aren't encoded by a UNORM swapchain either.
+        let clear_color = if self.needs_manual_srgb {
+            let [r, g, b, a] = clear_color;
+            [linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), a]
+        } else {
+            clear_color
+        };
+        let clear_color = vk::ClearValue {
+            color: vk::ClearColorValue {
+                float32: clear_color,
+            },
+        };
+        let depth_clear = vk::ClearValue {
+            depth_stencil: vk::ClearDepthStencilValue {
+                depth: self.depth_direction.clear_depth(),
+                stencil: 0,
+            },
+        };
+        let clear_values = [clear_color, depth_clear];
 
-    fn create_buffer(
-        instance: &ash::Instance,
-        device: &ash::Device,
-        pdevice: vk::PhysicalDevice,
-        size: vk::DeviceSize,
-        usage: vk::BufferUsageFlags,
-        properties: vk::MemoryPropertyFlags,
-    ) -> (vk::Buffer, vk::DeviceMemory) {
-        let buffer_info = vk::BufferCreateInfo::builder()
-            .size(size)
-            .usage(usage)
-            .sharing_mode(vk::SharingMode::EXCLUSIVE);
-
-        let buffer = unsafe { device.create_buffer(&buffer_info, None).unwrap() };
-        let mem_requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
-        let mem_type_index = Self::find_memory_type(
-            instance,
-            pdevice,
-            mem_requirements.memory_type_bits,
-            properties,
+        if let Some(timer) = &self.gpu_timer {
+            timer.record_start(&self.context.device, command_buffer, frame);
+        }
+        // With enough draws the scene's subpass is recorded into secondary
+        // buffers on the rayon workers, and the primary only executes them.
+        let parallel = self.parallel_draws(frame);
+        let scene_contents = if parallel.is_some() {
+            vk::SubpassContents::SECONDARY_COMMAND_BUFFERS
+        } else {
+            vk::SubpassContents::INLINE
+        };
+        let scene_rect = self.scene_rect();
+        let viewport = Self::viewport(scene_rect, &self.capabilities);
+        let full_scissor = vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: self.render_extent,
+        };
+        self.begin_scene(
+            command_buffer,
+            image_index,
+            &clear_values,
+            if DEPTH_PREPASS {
+                vk::SubpassContents::INLINE
+            } else {
+                scene_contents
+            },
         );
-
-        let alloc_info = vk::MemoryAllocateInfo::builder()
-            .allocation_size(mem_requirements.size)
-            .memory_type_index(mem_type_index);
-
-        let buffer_memory = unsafe { device.allocate_memory(&alloc_info, None).unwrap() };
-        unsafe {
-            device.bind_buffer_memory(buffer, buffer_memory, 0).unwrap();
+        if DEPTH_PREPASS || parallel.is_none() {
+            self.watchdog
+                .checkpoint(command_buffer, Checkpoint::BeginRenderPass);
+            unsafe {
+                self.context.device.cmd_set_viewport(
+                    command_buffer,
+                    0,
+                    std::slice::from_ref(&viewport),
+                );
+            }
         }
 
-        (buffer, buffer_memory)
-    }
-
-    fn find_memory_type(
-        instance: &ash::Instance,
-        pdevice: vk::PhysicalDevice,
-        type_filter: u32,
-        properties: vk::MemoryPropertyFlags,
-    ) -> u32 {
-        let mem_properties = unsafe { instance.get_physical_device_memory_properties(pdevice) };
-        for i in 0..mem_properties.memory_type_count {
-            if (type_filter & (1 << i)) != 0
-                && (mem_properties.memory_types[i as usize]
-                    .property_flags
-                    .contains(properties))
-            {
-                return i;
+        let mut stats = DrawStats::default();
+        if DEPTH_PREPASS {
+            let pipelines = [
+                self.pipeline(PipelineKey::DepthPrepass(Faces::Front)),
+                self.pipeline(PipelineKey::DepthPrepass(Faces::Both)),
+            ];
+            unsafe {
+                self.context.device.cmd_set_scissor(
+                    command_buffer,
+                    0,
+                    std::slice::from_ref(&scene_rect),
+                );
+            }
+            stats += self.record_draws(command_buffer, frame, MAIN_VIEW, pipelines, true);
+            unsafe {
+                self.context
+                    .device
+                    .cmd_next_subpass(command_buffer, scene_contents);
+            }
+        }
+        match parallel {
+            Some(batches) => {
+                stats += self.record_scene_parallel(
+                    command_buffer,
+                    image_index,
+                    frame,
+                    &batches,
+                    full_scissor,
+                    clear_color,
+                );
+            }
+            None => {
+                stats += self.record_scene(command_buffer, frame, scene_rect, clear_color);
+                stats += self.record_overlays(command_buffer, frame, clear_color, full_scissor);
             }
         }
-        panic!("Failed to find suitable memory type!");
-    }
-
-    fn create_depth_resources(
-        instance: &ash::Instance,
-        device: &ash::Device,
-        pdevice: vk::PhysicalDevice,
-        extent: vk::Extent2D,
-    ) -> (vk::Image, vk::DeviceMemory, vk::ImageView) {
-        let depth_format = Self::find_depth_format(instance, pdevice);
-        let (depth_image, depth_image_memory) = Self::create_image(
-            instance,
-            device,
-            pdevice,
-            extent.width,
-            extent.height,
-            depth_format,
-            vk::ImageTiling::OPTIMAL,
-            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
-            vk::MemoryPropertyFlags::DEVICE_LOCAL,
-        );
-        let depth_image_view = Self::create_image_view(
-            device,
-            depth_image,
-            depth_format,
-            vk::ImageAspectFlags::DEPTH,
-        );
-
-        (depth_image, depth_image_memory, depth_image_view)
-    }
-
-    fn find_depth_format(instance: &ash::Instance, pdevice: vk::PhysicalDevice) -> vk::Format {
-        Self::find_supported_format(
-            instance,
-            pdevice,
-            &[
-                vk::Format::D32_SFLOAT,
-                vk::Format::D32_SFLOAT_S8_UINT,
-                vk::Format::D24_UNORM_S8_UINT,
-            ],
-            vk::ImageTiling::OPTIMAL,
-            vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
-        )
-    }
-
-    fn find_supported_format(
-        instance: &ash::Instance,
-        pdevice: vk::PhysicalDevice,
-        candidates: &[vk::Format],
-        tiling: vk::ImageTiling,
-        features: vk::FormatFeatureFlags,
-    ) -> vk::Format {
-        for &format in candidates {
-            let props = unsafe { instance.get_physical_device_format_properties(pdevice, format) };
 
-            if tiling == vk::ImageTiling::LINEAR && props.linear_tiling_features.contains(features)
-            {
-                return format;
-            } else if tiling == vk::ImageTiling::OPTIMAL
-                && props.optimal_tiling_features.contains(features)
-            {
-                return format;
+        #[cfg(feature = "post-processing")]
+        if let Some(post) = &self.post {
+            debug_assert_eq!(
+                post.generation, self.swapchain.generation,
+                "post-process sets written for an older swapchain"
+            );
+            unsafe {
+                self.context
+                    .device
+                    .cmd_next_subpass(command_buffer, vk::SubpassContents::INLINE);
+            }
+            post.record(
+                &self.context.device,
+                command_buffer,
+                image_index,
+                self.render_extent,
+                self.post_params,
+            );
+        }
+        self.end_scene(command_buffer, image_index);
+        self.watchdog
+            .checkpoint(command_buffer, Checkpoint::EndRenderPass);
+        if let Some(view) = &self.depth_view {
+            debug_assert_eq!(
+                view.generation, self.swapchain.generation,
+                "depth view set written for an older swapchain"
+            );
+            self.record_depth_view(command_buffer, image_index, view);
+        }
+        if let Some(target) = self.scaled_targets.get(image_index) {
+            target.record_upscale(
+                &self.context.device,
+                command_buffer,
+                self.swapchain.images[image_index],
+                self.render_extent,
+                self.display_extent,
+                self.config.final_layout,
+            );
+        }
+        if let Some(comparison) = &self.comparison {
+            self.record_comparison(
+                command_buffer,
+                image_index,
+                frame,
+                &clear_values,
+                comparison,
+            );
+        }
+        if let Some(timer) = &self.gpu_timer {
+            timer.record_end(&self.context.device, command_buffer, frame);
+        }
+        if let Some(readback) = self.readbacks[frame]
+            .as_ref()
+            .filter(|readback| !readback.pending.is_empty())
+        {
+            readback.record_copy(
+                &self.context.device,
+                command_buffer,
+                self.swapchain.images[image_index],
+                self.config.final_layout,
+                self.release_layout(),
+            );
+        } else if self.config.final_layout != self.release_layout() {
+            self.record_present_transition(command_buffer, self.swapchain.images[image_index]);
+        }
+        for extra in self
+            .extra_windows
+            .iter()
+            .filter(|extra| extra.device.is_none())
+        {
+            self.record_extra_window(command_buffer, frame, linear_clear_color, extra);
+        }
+        unsafe {
+            if let Some(queries) = &self.statistics_queries {
+                self.context
+                    .device
+                    .cmd_end_query(command_buffer, queries[frame], 0);
             }
+            self.context
+                .device
+                .end_command_buffer(command_buffer)
+                .unwrap();
         }
 
-        panic!("Failed to find supported format!");
+        stats
     }
 
-    fn create_image(
-        instance: &ash::Instance,
-        device: &ash::Device,
-        pdevice: vk::PhysicalDevice,
-        width: u32,
-        height: u32,
-        format: vk::Format,
-        tiling: vk::ImageTiling,
-        usage: vk::ImageUsageFlags,
-        properties: vk::MemoryPropertyFlags,
-    ) -> (vk::Image, vk::DeviceMemory) {
-        let image_info = vk::ImageCreateInfo::builder()
-            .image_type(vk::ImageType::TYPE_2D)
-            .extent(vk::Extent3D {
-                width,
-                height,
-                depth: 1,
-            })
-            .mip_levels(1)
-            .array_layers(1)
-            .format(format)
-            .tiling(tiling)
-            .initial_layout(vk::ImageLayout::UNDEFINED)
-            .usage(usage)
-            .sharing_mode(vk::SharingMode::EXCLUSIVE)
-            .samples(vk::SampleCountFlags::TYPE_1);
-
-        let image = unsafe { device.create_image(&image_info, None).unwrap() };
-
-        let mem_requirements = unsafe { device.get_image_memory_requirements(image) };
-        let mem_type_index = Self::find_memory_type(
-            instance,
-            pdevice,
-            mem_requirements.memory_type_bits,
-            properties,
-        );
-
-        let alloc_info = vk::MemoryAllocateInfo::builder()
-            .allocation_size(mem_requirements.size)
-            .memory_type_index(mem_type_index);
+    /// Begins the scene pass, or on the dynamic rendering path moves the
+    /// attachments to their attachment layouts and begins rendering to them
+    /// with the load and store ops the scene pass would have.
+    fn begin_scene(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+        clear_values: &[vk::ClearValue; 2],
+        contents: vk::SubpassContents,
+    ) {
+        let device = &self.context.device;
+        let render_area = vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: self.render_extent,
+        };
+        let Some(renderer) = &self.dynamic_rendering else {
+            let render_pass_info = vk::RenderPassBeginInfo::builder()
+                .render_pass(self.scene_pass())
+                .framebuffer(self.swapchain.framebuffers[image_index])
+                .render_area(render_area)
+                .clear_values(clear_values);
+            unsafe { device.cmd_begin_render_pass(command_buffer, &render_pass_info, contents) };
+            return;
+        };
 
-        let image_memory = unsafe { device.allocate_memory(&alloc_info, None).unwrap() };
+        // Every attachment is cleared or fully overwritten, so earlier
+        // contents are discarded; the barriers only order this frame's
+        // writes after the last frame's.
+        let layout_barrier = |image, aspect_mask, new_layout, access_mask| {
+            vk::ImageMemoryBarrier::builder()
+                .src_access_mask(access_mask)
+                .dst_access_mask(access_mask)
+                .old_layout(vk::ImageLayout::UNDEFINED)
+                .new_layout(new_layout)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .build()
+        };
+        let (target_image, target_view) = self.scene_target(image_index);
+        let mut barriers = vec![layout_barrier(
+            target_image,
+            vk::ImageAspectFlags::COLOR,
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+        )];
+        if let Some((image, _, _)) = self.color_image {
+            barriers.push(layout_barrier(
+                image,
+                vk::ImageAspectFlags::COLOR,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            ));
+        }
+        barriers.push(layout_barrier(
+            self.swapchain.depth_image,
+            context::depth_aspect_flags(self.depth_format),
+            vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+        ));
+        let resolved_depth = self.depth_view.as_ref().and_then(|view| {
+            view.resolved
+                .map(|(image, _, attachment)| (image, attachment))
+        });
+        if let Some((image, _)) = resolved_depth {
+            barriers.push(layout_barrier(
+                image,
+                context::depth_aspect_flags(self.depth_format),
+                vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            ));
+        }
         unsafe {
-            device.bind_image_memory(image, image_memory, 0).unwrap();
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &barriers,
+            );
         }
 
-        (image, image_memory)
+        let color_load_op = if self.config.background.covers_screen() {
+            vk::AttachmentLoadOp::DONT_CARE
+        } else {
+            vk::AttachmentLoadOp::CLEAR
+        };
+        let color_attachment = vk::RenderingAttachmentInfo::builder()
+            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(color_load_op)
+            .clear_value(clear_values[0]);
+        // With MSAA only the resolved swapchain image is kept.
+        let color_attachment = match self.color_image {
+            Some((_, _, view)) => color_attachment
+                .image_view(view)
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .resolve_mode(vk::ResolveModeFlags::AVERAGE)
+                .resolve_image_view(target_view)
+                .resolve_image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            None => color_attachment
+                .image_view(target_view)
+                .store_op(vk::AttachmentStoreOp::STORE),
+        };
+        let depth_attachment = vk::RenderingAttachmentInfo::builder()
+            .image_view(self.swapchain.depth_image_view)
+            .image_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .clear_value(clear_values[1]);
+        // The depth view reads the resolved sample 0 if it can, or else the
+        // stored depth image.
+        let depth_attachment = match resolved_depth {
+            Some((_, view)) => depth_attachment
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .resolve_mode(vk::ResolveModeFlags::SAMPLE_ZERO)
+                .resolve_image_view(view)
+                .resolve_image_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+            None if self.depth_view.is_some() => {
+                depth_attachment.store_op(vk::AttachmentStoreOp::STORE)
+            }
+            None => depth_attachment.store_op(vk::AttachmentStoreOp::DONT_CARE),
+        };
+        let rendering_info = vk::RenderingInfo::builder()
+            .flags(
+                if contents == vk::SubpassContents::SECONDARY_COMMAND_BUFFERS {
+                    vk::RenderingFlags::CONTENTS_SECONDARY_COMMAND_BUFFERS
+                } else {
+                    vk::RenderingFlags::empty()
+                },
+            )
+            .render_area(render_area)
+            .layer_count(1)
+            .color_attachments(std::slice::from_ref(&color_attachment))
+            .depth_attachment(&depth_attachment);
+        renderer.begin(device, command_buffer, &rendering_info);
     }
 
-    fn create_image_view(
-        device: &ash::Device,
-        image: vk::Image,
-        format: vk::Format,
-        aspect_flags: vk::ImageAspectFlags,
-    ) -> vk::ImageView {
-        let view_info = vk::ImageViewCreateInfo::builder()
-            .image(image)
-            .view_type(vk::ImageViewType::TYPE_2D)
-            .format(format)
+    /// Ends the scene pass, or on the dynamic rendering path ends rendering
+    /// and moves the swapchain image to `config.final_layout`, as the render
+    /// pass would.
+    fn end_scene(&self, command_buffer: vk::CommandBuffer, image_index: usize) {
+        let device = &self.context.device;
+        let Some(renderer) = &self.dynamic_rendering else {
+            unsafe { device.cmd_end_render_pass(command_buffer) };
+            return;
+        };
+        renderer.end(device, command_buffer);
+        let barrier = vk::ImageMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(vk::AccessFlags::empty())
+            .old_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .new_layout(self.config.final_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.scene_target(image_index).0)
             .subresource_range(vk::ImageSubresourceRange {
-                aspect_mask: aspect_flags,
+                aspect_mask: vk::ImageAspectFlags::COLOR,
                 base_mip_level: 0,
                 level_count: 1,
                 base_array_layer: 0,
                 layer_count: 1,
             });
-
-        unsafe { device.create_image_view(&view_info, None).unwrap() }
+        // Later passes and copies of the image wait on colour attachment
+        // output too, which chains them after the transition.
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                std::slice::from_ref(&barrier),
+            );
+        }
     }
 
-    fn create_uniform_buffers(
-        instance: &ash::Instance,
-        device: &ash::Device,
-        pdevice: vk::PhysicalDevice,
-        num_images: usize,
-    ) -> (Vec<vk::Buffer>, Vec<vk::DeviceMemory>) {
-        let buffer_size = std::mem::size_of::<UniformBufferObject>();
-        let mut uniform_buffers = Vec::with_capacity(num_images);
-        let mut uniform_buffers_memory = Vec::with_capacity(num_images);
-
-        for _ in 0..num_images {
-            let (buffer, memory) = Self::create_buffer(
-                instance,
-                device,
-                pdevice,
-                buffer_size as vk::DeviceSize,
-                vk::BufferUsageFlags::UNIFORM_BUFFER,
-                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+    /// Replaces the frame with its depth: moves the depth image, or the
+    /// image it was resolved into, to a read-only layout once the scene's
+    /// depth writes are done, draws it in grey over the whole target, and
+    /// moves it back for the next frame's scene pass.
+    fn record_depth_view(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+        view: &DepthView,
+    ) {
+        let device = &self.context.device;
+        let depth_image = view
+            .resolved
+            .map_or(self.swapchain.depth_image, |(image, _, _)| image);
+        let depth_barrier = |old_layout, new_layout, src_access_mask, dst_access_mask| {
+            vk::ImageMemoryBarrier::builder()
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_access_mask(src_access_mask)
+                .dst_access_mask(dst_access_mask)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(depth_image)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: view.aspect_mask,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .build()
+        };
+        let depth_tests = vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+            | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS;
+        // Depth resolves are written as colour attachment output.
+        let (written_in, written_by) = if view.resolved.is_some() {
+            (
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            )
+        } else {
+            (depth_tests, vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+        };
+        let render_pass_info = vk::RenderPassBeginInfo::builder()
+            .render_pass(view.render_pass)
+            .framebuffer(view.framebuffers[image_index])
+            .render_area(vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.render_extent,
+            });
+        // Drawn in framebuffer coordinates, which never need flipping.
+        let viewport = vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: self.render_extent.width as f32,
+            height: self.render_extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        };
+        let scissor = vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: self.render_extent,
+        };
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                written_in,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[depth_barrier(
+                    vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                    vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+                    written_by,
+                    vk::AccessFlags::SHADER_READ,
+                )],
+            );
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &render_pass_info,
+                vk::SubpassContents::INLINE,
+            );
+            device.cmd_set_viewport(command_buffer, 0, std::slice::from_ref(&viewport));
+            device.cmd_set_scissor(command_buffer, 0, std::slice::from_ref(&scissor));
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                view.pipeline,
+            );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                view.pipeline_layout,
+                0,
+                std::slice::from_ref(&view.descriptor_set),
+                &[],
+            );
+            let planes = [CAMERA_NEAR, CAMERA_FAR];
+            device.cmd_push_constants(
+                command_buffer,
+                view.pipeline_layout,
+                vk::ShaderStageFlags::FRAGMENT,
+                0,
+                std::slice::from_raw_parts(
+                    planes.as_ptr() as *const u8,
+                    std::mem::size_of_val(&planes),
+                ),
+            );
+            device.cmd_draw(command_buffer, 3, 1, 0, 0);
+            device.cmd_end_render_pass(command_buffer);
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                depth_tests,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[depth_barrier(
+                    vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+                    vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                    vk::AccessFlags::empty(),
+                    vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                        | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                )],
             );
-            uniform_buffers.push(buffer);
-            uniform_buffers_memory.push(memory);
         }
-
-        (uniform_buffers, uniform_buffers_memory)
     }
 
-    fn create_descriptor_set_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
-        let ubo_layout_binding = vk::DescriptorSetLayoutBinding::builder()
-            .binding(0)
-            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
-            .descriptor_count(1)
+    /// The ID pass over the scene rect, from `last_frame`'s view, transforms
+    /// and draws, then the copy of the ID at `pixel` into the pass's buffer.
+    /// The split view and magnifier don't take part: each object gets one
+    /// ID wherever it is drawn.
+    fn record_pick(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        picking: &PickPass,
+        framebuffer: vk::Framebuffer,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        pixel: vk::Offset2D,
+    ) {
+        let device = &self.context.device;
+        let clear_values = [
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    uint32: [pick::BACKGROUND_ID; 4],
+                },
+            },
+            vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: self.depth_direction.clear_depth(),
+                    stencil: 0,
+                },
+            },
+        ];
+        let render_pass_info = vk::RenderPassBeginInfo::builder()
+            .render_pass(picking.render_pass)
+            .framebuffer(framebuffer)
+            .render_area(vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.render_extent,
+            })
+            .clear_values(&clear_values);
+        let scene_rect = self.scene_rect();
+        let viewport = Self::viewport(scene_rect, &self.capabilities);
+        unsafe {
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &render_pass_info,
+                vk::SubpassContents::INLINE,
+            );
+            device.cmd_set_viewport(command_buffer, 0, std::slice::from_ref(&viewport));
+            device.cmd_set_scissor(command_buffer, 0, std::slice::from_ref(&scene_rect));
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                picking.pipeline,
+            );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                picking.layout,
+                0,
+                &[
+                    self.view_descriptor_sets[frame][MAIN_VIEW],
+                    self.animator.graphics_set(frame),
+                ],
+                &[],
+            );
+            device.cmd_bind_vertex_buffers(
+                command_buffer,
+                0,
+                &[self.pooled_vertex_buffer(frame)],
+                &[0],
+            );
+            for draw in picking
+                .draws
+                .iter()
+                .filter(|draw| !draw.instances.is_empty())
+            {
+                let push = PickPush {
+                    model: draw.model.into(),
+                    first_id: draw.first_id,
+                };
+                device.cmd_bind_index_buffer(
+                    command_buffer,
+                    self.mesh_pool.index_buffer,
+                    0,
+                    draw.mesh.index_type,
+                );
+                device.cmd_push_constants(
+                    command_buffer,
+                    picking.layout,
+                    vk::ShaderStageFlags::VERTEX,
+                    0,
+                    std::slice::from_raw_parts(
+                        &push as *const PickPush as *const u8,
+                        std::mem::size_of::<PickPush>(),
+                    ),
+                );
+                device.cmd_draw_indexed(
+                    command_buffer,
+                    draw.mesh.index_count,
+                    draw.instances.len() as u32,
+                    draw.mesh.first_index,
+                    draw.mesh.vertex_offset(),
+                    draw.instances.start,
+                );
+            }
+            device.cmd_end_render_pass(command_buffer);
+        }
+        picking.record_copy(device, command_buffer, pixel);
+    }
+
+    /// The scene's subpass recorded inline: any letterbox bars, the
+    /// background, then the draws, halved if the split view is on.
+    fn record_scene(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        scene_rect: vk::Rect2D,
+        clear_color: vk::ClearValue,
+    ) -> DrawStats {
+        self.record_bars(command_buffer, scene_rect, clear_color);
+        self.record_background(command_buffer, frame, MAIN_VIEW, scene_rect);
+
+        if self.split_view.enabled {
+            let mut stats = DrawStats::default();
+            let halves = self.split_view.scissors(scene_rect);
+            let variants = [
+                self.cube_pipelines(ColorInterpolation::Linear),
+                self.cube_pipelines(ColorInterpolation::NaiveSrgb),
+            ];
+            for (scissor, pipelines) in halves.into_iter().zip(variants) {
+                if let Some(scissor) = scissor {
+                    unsafe {
+                        self.context.device.cmd_set_scissor(
+                            command_buffer,
+                            0,
+                            std::slice::from_ref(&scissor),
+                        );
+                    }
+                    stats += self.record_draws(command_buffer, frame, MAIN_VIEW, pipelines, false);
+                }
+            }
+
+            let divider = vk::ClearAttachment {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                color_attachment: 0,
+                clear_value: vk::ClearValue {
+                    color: vk::ClearColorValue {
+                        float32: [1.0, 1.0, 1.0, 1.0],
+                    },
+                },
+            };
+            let divider_rect = vk::ClearRect {
+                rect: self.split_view.divider_rect(scene_rect),
+                base_array_layer: 0,
+                layer_count: 1,
+            };
+            unsafe {
+                self.context.device.cmd_clear_attachments(
+                    command_buffer,
+                    std::slice::from_ref(&divider),
+                    std::slice::from_ref(&divider_rect),
+                );
+            }
+            stats
+        } else {
+            unsafe {
+                self.context.device.cmd_set_scissor(
+                    command_buffer,
+                    0,
+                    std::slice::from_ref(&scene_rect),
+                );
+            }
+            self.record_draws(
+                command_buffer,
+                frame,
+                MAIN_VIEW,
+                self.cube_pipelines(ColorInterpolation::Linear),
+                false,
+            )
+        }
+    }
+
+    /// What is drawn over the scene: the magnifier, the frame-time graph,
+    /// the HUD and the error banner.
+    fn record_overlays(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        clear_color: vk::ClearValue,
+        full_scissor: vk::Rect2D,
+    ) -> DrawStats {
+        let mut stats = DrawStats::default();
+        if let Some(inset) = self.magnifier_inset() {
+            stats += self.record_magnifier(command_buffer, frame, inset, clear_color);
+        }
+        self.watchdog
+            .checkpoint(command_buffer, Checkpoint::DrawCube);
+        #[cfg(feature = "overlay")]
+        if self.graph_visible {
+            // The graph's vertices are already y down, so never flipped.
+            let graph_viewport = vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: self.render_extent.width as f32,
+                height: self.render_extent.height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            };
+            unsafe {
+                self.context.device.cmd_set_viewport(
+                    command_buffer,
+                    0,
+                    std::slice::from_ref(&graph_viewport),
+                );
+                self.context.device.cmd_set_scissor(
+                    command_buffer,
+                    0,
+                    std::slice::from_ref(&full_scissor),
+                );
+                self.context.device.cmd_bind_pipeline(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.graph_pipeline,
+                );
+            }
+            self.graph_buffers[frame].record(&self.context.device, command_buffer);
+        }
+        #[cfg(feature = "overlay")]
+        if self.hud.is_visible() {
+            // Also y down, with its quads on whole pixels.
+            let hud_viewport = vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: self.render_extent.width as f32,
+                height: self.render_extent.height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            };
+            unsafe {
+                self.context.device.cmd_set_viewport(
+                    command_buffer,
+                    0,
+                    std::slice::from_ref(&hud_viewport),
+                );
+                self.context.device.cmd_set_scissor(
+                    command_buffer,
+                    0,
+                    std::slice::from_ref(&full_scissor),
+                );
+                self.context.device.cmd_bind_pipeline(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.hud_pipeline,
+                );
+                self.context.device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.hud_layout,
+                    0,
+                    &[self.texture_sets[HUD_TEXTURE_SET as usize]],
+                    &[],
+                );
+            }
+            self.hud_buffers[frame].record(&self.context.device, command_buffer);
+        }
+        let banner = self
+            .error_banner
+            .layout(self.render_extent, self.validation_log.error_count());
+        for (color, rects) in &banner {
+            let attachment = vk::ClearAttachment {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                color_attachment: 0,
+                clear_value: vk::ClearValue {
+                    color: vk::ClearColorValue { float32: *color },
+                },
+            };
+            let rects: Vec<_> = rects
+                .iter()
+                .map(|&rect| vk::ClearRect {
+                    rect,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .collect();
+            unsafe {
+                self.context.device.cmd_clear_attachments(
+                    command_buffer,
+                    std::slice::from_ref(&attachment),
+                    &rects,
+                );
+            }
+        }
+        stats
+    }
+
+    /// The scene's subpass as secondary buffers: the background and the
+    /// overlays on this thread, the draws in chunks of PARALLEL_RECORD_CHUNK
+    /// on the rayon workers.
+    fn record_scene_parallel(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        batches: &[DrawBatch],
+        full_scissor: vk::Rect2D,
+        clear_color: vk::ClearValue,
+    ) -> DrawStats {
+        let device = &self.context.device;
+        let pools = self
+            .record_pools
+            .as_ref()
+            .expect("parallel draws without record pools");
+        // The frame's fence has signalled, so its last secondaries are done.
+        pools.reset(device, frame);
+        let scene_rect = self.scene_rect();
+        let target = SecondaryTarget {
+            pass: PassTarget {
+                render_pass: self.scene_pass(),
+                ..self.pass_target()
+            },
+            subpass: DEPTH_PREPASS as u32,
+            framebuffer: if self.dynamic_rendering.is_some() {
+                vk::Framebuffer::null()
+            } else {
+                self.swapchain.framebuffers[image_index]
+            },
+            pipeline_statistics: if self.statistics_queries.is_some() {
+                vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS
+            } else {
+                vk::QueryPipelineStatisticFlags::empty()
+            },
+            viewport: Self::viewport(scene_rect, &self.capabilities),
+            scissor: scene_rect,
+        };
+
+        let background = pools.begin(device, frame, target);
+        if !DEPTH_PREPASS {
+            self.watchdog
+                .checkpoint(background, Checkpoint::BeginRenderPass);
+        }
+        self.record_bars(background, scene_rect, clear_color);
+        self.record_background(background, frame, MAIN_VIEW, scene_rect);
+        unsafe { device.end_command_buffer(background).unwrap() };
+
+        let draws = pools.record_batches(
+            device,
+            frame,
+            target,
+            batches,
+            PARALLEL_RECORD_CHUNK,
+            self.pipeline_layout,
+            &self.draw_packing,
+        );
+
+        let overlays = pools.begin(device, frame, target);
+        let mut stats = self.record_overlays(overlays, frame, clear_color, full_scissor);
+        unsafe { device.end_command_buffer(overlays).unwrap() };
+
+        let mut secondaries = vec![background];
+        for (draw_buffer, draw_stats) in draws {
+            secondaries.push(draw_buffer);
+            stats += draw_stats;
+        }
+        secondaries.push(overlays);
+        unsafe { device.cmd_execute_commands(command_buffer, &secondaries) };
+        stats
+    }
+
+    /// The main view's draws resolved for `ThreadLocalPools`, once there are
+    /// PARALLEL_RECORD_MIN_DRAWS of them. The split view still records
+    /// inline, as does the depth pre-pass.
+    fn parallel_draws(&self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>) -> Option<Vec<DrawBatch>> {
+        let records = self.draw_list.records();
+        if self.record_pools.is_none()
+            || self.split_view.enabled
+            || records.len() < PARALLEL_RECORD_MIN_DRAWS
+        {
+            return None;
+        }
+        // Indexed like `record_draws`' tables.
+        let pipelines = self.cube_pipelines(ColorInterpolation::Linear);
+        let frame_set = self.descriptor_sets[frame];
+        let view_set = self.view_descriptor_sets[frame][MAIN_VIEW];
+        let transform_set = self.animator.graphics_set(frame);
+        let batches = records
+            .iter()
+            .enumerate()
+            .map(|(slot, record)| DrawBatch {
+                pipeline: pipelines[record.pipeline as usize],
+                vertex_buffer: self.pooled_vertex_buffer(frame),
+                index_buffer: self.mesh_pool.index_buffer,
+                index_type: match record.mesh {
+                    POOLED_MESHES_U32 => vk::IndexType::UINT32,
+                    _ => vk::IndexType::UINT16,
+                },
+                descriptor_sets: [
+                    frame_set,
+                    view_set,
+                    transform_set,
+                    self.texture_sets[record.material as usize],
+                ],
+                slot_offset: self
+                    .draw_packing
+                    .uses_uniform_slot()
+                    .then(|| (slot as vk::DeviceSize * self.draw_packing.slot_stride()) as u32),
+                index_count: record.index_count,
+                first_index: record.first_index,
+                vertex_offset: record.vertex_offset,
+                instance_count: record.instance_count,
+                first_instance: record.first_instance,
+                constants: record.constants,
+            })
+            .collect();
+        Some(batches)
+    }
+
+    /// The render pass the scene records into: `render_pass`, or the depth
+    /// view's copy that stores depth. Null on the dynamic rendering path.
+    fn scene_pass(&self) -> vk::RenderPass {
+        self.depth_view
+            .as_ref()
+            .map_or(self.render_pass, |view| view.scene_pass)
+    }
+
+    /// What the scene's pipelines are built against.
+    fn pass_target(&self) -> PassTarget {
+        PassTarget {
+            render_pass: self.render_pass,
+            color_format: self.swapchain.format,
+            depth_format: self.depth_format,
+            samples: self.msaa_samples,
+            depth_direction: self.depth_direction,
+        }
+    }
+
+    /// Draws the scene again into `inset` through the magnifier view, inside
+    /// the main render pass. Clearing the inset's depth lets it use the
+    /// scene's pipelines as they are; the scissor keeps it in the corner.
+    fn record_magnifier(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        inset: Inset,
+        clear_color: vk::ClearValue,
+    ) -> DrawStats {
+        let clear_rect = |rect: vk::Rect2D| vk::ClearRect {
+            rect,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let border = vk::ClearAttachment {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            color_attachment: 0,
+            clear_value: vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [1.0, 1.0, 1.0, 1.0],
+                },
+            },
+        };
+        let inner = [
+            vk::ClearAttachment {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                color_attachment: 0,
+                clear_value: clear_color,
+            },
+            vk::ClearAttachment {
+                aspect_mask: vk::ImageAspectFlags::DEPTH,
+                color_attachment: 0,
+                clear_value: vk::ClearValue {
+                    depth_stencil: vk::ClearDepthStencilValue {
+                        depth: self.depth_direction.clear_depth(),
+                        stencil: 0,
+                    },
+                },
+            },
+        ];
+        unsafe {
+            self.context.device.cmd_clear_attachments(
+                command_buffer,
+                std::slice::from_ref(&border),
+                &[clear_rect(inset.border)],
+            );
+            self.context.device.cmd_clear_attachments(
+                command_buffer,
+                &inner,
+                &[clear_rect(inset.inner)],
+            );
+            self.context.device.cmd_set_scissor(
+                command_buffer,
+                0,
+                std::slice::from_ref(&inset.inner),
+            );
+        }
+        self.record_background(command_buffer, frame, MAGNIFIER_VIEW, inset.inner);
+        self.record_draws(
+            command_buffer,
+            frame,
+            MAGNIFIER_VIEW,
+            self.cube_pipelines(ColorInterpolation::Linear),
+            false,
+        )
+    }
+
+    /// Clears the bars letterboxing leaves around `scene_rect` to
+    /// `clear_color`. The render pass doesn't clear them when a background
+    /// covers the screen, and the background only fills the scene.
+    fn record_bars(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        scene_rect: vk::Rect2D,
+        clear_color: vk::ClearValue,
+    ) {
+        let bars: Vec<vk::ClearRect> = aspect::bars(self.render_extent, scene_rect)
+            .into_iter()
+            .map(|rect| vk::ClearRect {
+                rect,
+                base_array_layer: 0,
+                layer_count: 1,
+            })
+            .collect();
+        if bars.is_empty() {
+            return;
+        }
+        let attachment = vk::ClearAttachment {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            color_attachment: 0,
+            clear_value: clear_color,
+        };
+        unsafe {
+            self.context.device.cmd_clear_attachments(
+                command_buffer,
+                std::slice::from_ref(&attachment),
+                &bars,
+            );
+        }
+    }
+
+    /// Fills `scissor` with the background as seen through `view`, if there
+    /// is one. Three vertices and no buffers, or the skybox's cube; the rays
+    /// come from the view's matrices, so the magnifier's inset zooms the
+    /// background too.
+    fn record_background(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        view: usize,
+        scissor: vk::Rect2D,
+    ) {
+        let Some(pipeline) = self.background_pipeline else {
+            return;
+        };
+        // Binding 1 of set 0 is dynamic when draw constants use it; the
+        // background doesn't read it, so any valid slot will do.
+        let draw_slot_offsets: &[u32] = if self.draw_packing.uses_uniform_slot() {
+            &[0]
+        } else {
+            &[]
+        };
+        unsafe {
+            self.context
+                .device
+                .cmd_set_scissor(command_buffer, 0, std::slice::from_ref(&scissor));
+            self.context.device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline,
+            );
+            self.context.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout,
+                0,
+                &[
+                    self.descriptor_sets[frame],
+                    self.view_descriptor_sets[frame][view],
+                ],
+                draw_slot_offsets,
+            );
+            match (self.config.background, &self.skybox) {
+                (Background::Skybox, Some(skybox)) => {
+                    self.context.device.cmd_bind_descriptor_sets(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        self.pipeline_layout,
+                        3,
+                        &[self.texture_sets[SKYBOX_TEXTURE_SET as usize]],
+                        &[],
+                    );
+                    self.context.device.cmd_bind_vertex_buffers(
+                        command_buffer,
+                        0,
+                        &[skybox.vertex_buffer],
+                        &[0],
+                    );
+                    self.context.device.cmd_draw(command_buffer, 36, 1, 0, 0);
+                }
+                _ => self.context.device.cmd_draw(command_buffer, 3, 1, 0, 0),
+            }
+        }
+    }
+
+    /// Where the magnifier draws this frame, if it's held. Not with
+    /// DEPTH_PREPASS, whose pipelines test for EQUAL against the pre-pass
+    /// depth that the inset clears.
+    fn magnifier_inset(&self) -> Option<Inset> {
+        if !self.magnifier_held || DEPTH_PREPASS {
+            return None;
+        }
+        Inset::place(self.scene_rect())
+    }
+
+    /// Where the scene is drawn in the render extent, as `AspectPolicy`
+    /// places it.
+    fn scene_rect(&self) -> vk::Rect2D {
+        self.config.aspect_policy.scene_rect(self.render_extent)
+    }
+
+    /// Draws the frame's draw list again into the A/B split's target, at its
+    /// scale in the scene's place, and puts its right half over the frame.
+    fn record_comparison(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        clear_values: &[vk::ClearValue],
+        comparison: &ComparisonTarget,
+    ) {
+        let area = vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: comparison.extent,
+        };
+        let render_pass_info = vk::RenderPassBeginInfo::builder()
+            .render_pass(comparison.render_pass)
+            .framebuffer(comparison.framebuffer)
+            .render_area(area)
+            .clear_values(clear_values);
+        // Letterboxed like the scene, so the halves line up.
+        let scene_rect = self.config.aspect_policy.scene_rect(comparison.extent);
+        let viewport = Self::viewport(scene_rect, &self.capabilities);
+        let device = &self.context.device;
+        unsafe {
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &render_pass_info,
+                vk::SubpassContents::INLINE,
+            );
+            device.cmd_set_viewport(command_buffer, 0, std::slice::from_ref(&viewport));
+            device.cmd_set_scissor(command_buffer, 0, std::slice::from_ref(&scene_rect));
+        }
+        self.record_draws(
+            command_buffer,
+            image_index,
+            frame,
+            MAIN_VIEW,
+            comparison.pipelines,
+            false,
+        );
+        unsafe { device.cmd_end_render_pass(command_buffer) };
+        comparison.record_composite(
+            device,
+            command_buffer,
+            self.swapchain.images[image_index],
+            self.display_extent,
+            self.config.final_layout,
+        );
+    }
+
+    /// Draws the frame's draw list again into `extra`'s acquired image, in
+    /// a render pass of its own, through its view and with its pipelines.
+    /// Nothing if it sits the frame out.
+    fn record_extra_window(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        clear_color: [f32; 4],
+        extra: &ExtraWindow,
+    ) {
+        let device = &self.context.device;
+        let begun = Self::begin_extra_window(
+            device,
+            command_buffer,
+            clear_color,
+            self.depth_direction,
+            extra,
+            |area| Self::viewport(area, &self.capabilities),
+        );
+        if !begun {
+            return;
+        }
+        self.record_draws_through(
+            command_buffer,
+            frame,
+            extra.view_set(frame),
+            extra.pipelines,
+            false,
+        );
+        unsafe { device.cmd_end_render_pass(command_buffer) };
+    }
+
+    /// The same for a window on another device, from `render_context`'s
+    /// copies of the meshes and transforms, unlit. The viewport is never
+    /// flipped; `extra_view_data` flips the projection instead.
+    fn record_device_window(
+        &self,
+        render_context: &RenderContext,
+        command_buffer: vk::CommandBuffer,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        clear_color: [f32; 4],
+        extra: &ExtraWindow,
+    ) {
+        let device = &render_context.context.device;
+        let begun = Self::begin_extra_window(
+            device,
+            command_buffer,
+            clear_color,
+            self.depth_direction,
+            extra,
+            |area| vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: area.extent.width as f32,
+                height: area.extent.height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            },
+        );
+        if !begun {
+            return;
+        }
+        // Without objects there are no transforms, and nothing to draw.
+        if let Some(vertex_buffers) = render_context.vertex_buffers(frame) {
+            let layout = render_context.pipeline_layout;
+            unsafe {
+                device.cmd_bind_vertex_buffers(command_buffer, 0, &vertex_buffers, &[0, 0]);
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    layout,
+                    0,
+                    &[extra.view_set(frame)],
+                    &[],
+                );
+            }
+            self.draw_list.record(
+                false,
+                |pipeline| unsafe {
+                    device.cmd_bind_pipeline(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        extra.pipelines[pipeline as usize],
+                    );
+                },
+                |_| {},
+                |mesh| unsafe {
+                    let index_type = if mesh == POOLED_MESHES_U32 {
+                        vk::IndexType::UINT32
+                    } else {
+                        vk::IndexType::UINT16
+                    };
+                    device.cmd_bind_index_buffer(
+                        command_buffer,
+                        render_context.index_buffer(),
+                        0,
+                        index_type,
+                    );
+                },
+                |_, draw| unsafe {
+                    device.cmd_push_constants(
+                        command_buffer,
+                        layout,
+                        vk::ShaderStageFlags::VERTEX,
+                        0,
+                        std::slice::from_raw_parts(
+                            &draw.constants.model as *const Matrix4<f32> as *const u8,
+                            std::mem::size_of::<Matrix4<f32>>(),
+                        ),
+                    );
+                    device.cmd_draw_indexed(
+                        command_buffer,
+                        draw.index_count,
+                        draw.instance_count,
+                        draw.first_index,
+                        draw.vertex_offset,
+                        draw.first_instance,
+                    );
+                },
+            );
+        }
+        unsafe { device.cmd_end_render_pass(command_buffer) };
+    }
+
+    /// Begins `extra`'s render pass, cleared to `clear_color`, encoded if
+    /// its swapchain is UNORM, with `viewport` and a scissor over the whole
+    /// window. False, recording nothing, if it sits the frame out.
+    fn begin_extra_window(
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        clear_color: [f32; 4],
+        depth_direction: DepthDirection,
+        extra: &ExtraWindow,
+        viewport: impl FnOnce(vk::Rect2D) -> vk::Viewport,
+    ) -> bool {
+        let Some(framebuffer) = extra.framebuffer() else {
+            return false;
+        };
+        let clear_color = if extra.manual_srgb {
+            let [r, g, b, a] = clear_color;
+            [linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), a]
+        } else {
+            clear_color
+        };
+        let clear_values = [
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: clear_color,
+                },
+            },
+            vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: depth_direction.clear_depth(),
+                    stencil: 0,
+                },
+            },
+        ];
+        let area = vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: extra.extent,
+        };
+        let render_pass_info = vk::RenderPassBeginInfo::builder()
+            .render_pass(extra.render_pass)
+            .framebuffer(framebuffer)
+            .render_area(area)
+            .clear_values(&clear_values);
+        let viewport = viewport(area);
+        unsafe {
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &render_pass_info,
+                vk::SubpassContents::INLINE,
+            );
+            device.cmd_set_viewport(command_buffer, 0, std::slice::from_ref(&viewport));
+            device.cmd_set_scissor(command_buffer, 0, std::slice::from_ref(&area));
+        }
+        true
+    }
+
+    fn record_draws(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        view: usize,
+        pipelines: [vk::Pipeline; 2],
+        opaque_only: bool,
+    ) -> DrawStats {
+        let view_set = self.view_descriptor_sets[frame][view];
+        self.record_draws_through(command_buffer, frame, view_set, pipelines, opaque_only)
+    }
+
+    /// The same through any per-view set, such as an extra window's.
+    fn record_draws_through(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        view_set: vk::DescriptorSet,
+        pipelines: [vk::Pipeline; 2],
+        opaque_only: bool,
+    ) -> DrawStats {
+        // Ids index these tables: pipelines by CUBE_PIPELINE and
+        // DOUBLE_SIDED_PIPELINE, materials by CUBE_MATERIAL and
+        // TEXTURED_MATERIAL, and meshes by POOLED_MESHES and
+        // POOLED_MESHES_U32.
+        let frame_set = self.descriptor_sets[frame];
+        let meshes = [vk::IndexType::UINT16, vk::IndexType::UINT32].map(|index_type| {
+            (
+                self.pooled_vertex_buffer(frame),
+                self.mesh_pool.index_buffer,
+                index_type,
+            )
+        });
+        let transform_set = self.animator.graphics_set(frame);
+        // Relative to the first slot, which binding 1 of set 0 points at.
+        let draw_slot_offsets = |slot: usize| -> Vec<u32> {
+            if self.draw_packing.uses_uniform_slot() {
+                vec![(slot as vk::DeviceSize * self.draw_packing.slot_stride()) as u32]
+            } else {
+                Vec::new()
+            }
+        };
+        self.draw_list.record(
+            opaque_only,
+            |pipeline| unsafe {
+                self.context.device.cmd_bind_pipeline(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    pipelines[pipeline as usize],
+                );
+            },
+            |material| unsafe {
+                self.context.device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.pipeline_layout,
+                    0,
+                    &[
+                        frame_set,
+                        view_set,
+                        transform_set,
+                        self.texture_sets[material as usize],
+                    ],
+                    &draw_slot_offsets(0),
+                );
+            },
+            |mesh| unsafe {
+                let (vertex_buffer, index_buffer, index_type) = meshes[mesh as usize];
+                self.context.device.cmd_bind_vertex_buffers(
+                    command_buffer,
+                    0,
+                    &[vertex_buffer],
+                    &[0],
+                );
+                self.context.device.cmd_bind_index_buffer(
+                    command_buffer,
+                    index_buffer,
+                    0,
+                    index_type,
+                );
+            },
+            |slot, draw| unsafe {
+                if self.draw_packing.uses_uniform_slot() {
+                    self.context.device.cmd_bind_descriptor_sets(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        self.pipeline_layout,
+                        0,
+                        &[frame_set],
+                        &draw_slot_offsets(slot),
+                    );
+                }
+                self.draw_packing.push(
+                    &self.context.device,
+                    command_buffer,
+                    self.pipeline_layout,
+                    &draw.constants,
+                );
+                self.context.device.cmd_draw_indexed(
+                    command_buffer,
+                    draw.index_count,
+                    draw.instance_count,
+                    draw.first_index,
+                    draw.vertex_offset,
+                    draw.first_instance,
+                );
+            },
+        )
+    }
+
+    /// Where `frame` draws pooled meshes' vertices from: the pool, or its
+    /// own copy once they have been morphed.
+    fn pooled_vertex_buffer(&self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>) -> vk::Buffer {
+        self.morphed_vertices
+            .as_ref()
+            .map_or(self.mesh_pool.vertex_buffer, |morphed| {
+                morphed.buffer(frame)
+            })
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    /// Error-severity validation messages since startup; 0 for a clean run,
+    /// or without validation.
+    pub fn validation_error_count(&self) -> u32 {
+        self.validation_log.error_count()
+    }
+
+    /// The checks `--self-test` runs before shutting down: renders each
+    /// `CanonicalScene` and compares it with its reference hash, then the
+    /// GPU animation with the CPU's. Only for a renderer from
+    /// `new_headless`, started with the defaults at SELF_TEST_SIZE;
+    /// `shutdown` reports the rest.
+    pub fn self_test(&mut self) -> Result<SelfTestReport, VulkanError> {
+        let mut report = SelfTestReport::default();
+        for scene in CanonicalScene::ALL {
+            let outcome =
+                if scene == CanonicalScene::Wireframe && !self.capabilities.fill_mode_non_solid {
+                    CheckOutcome::Skipped("the device has no fillModeNonSolid".to_string())
+                } else {
+                    self.show_canonical_scene(scene);
+                    scene.check(&self.render_to_image(0.0)?)
+                };
+            report.add(scene.name(), outcome);
+        }
+        self.show_canonical_scene(CanonicalScene::DefaultCube);
+        report.add(
+            "gpu animation",
+            match self.animation_error {
+                Some(error) if error <= ANIMATION_TOLERANCE => CheckOutcome::Pass(format!(
+                    "compute matches CPU for {} objects (max error {:e})",
+                    self.animator.object_count(),
+                    error
+                )),
+                Some(error) => CheckOutcome::Fail(format!(
+                    "compute differs from CPU by {:e} (tolerance {:e})",
+                    error, ANIMATION_TOLERANCE
+                )),
+                None => CheckOutcome::Skipped("animation runs on the CPU".to_string()),
+            },
+        );
+        Ok(report)
+    }
+
+    fn show_canonical_scene(&mut self, scene: CanonicalScene) {
+        self.render_mode = match scene {
+            CanonicalScene::Wireframe => RenderMode::Wireframe,
+            _ => RenderMode::Fill,
+        };
+        self.show_face_labels = scene == CanonicalScene::Textured;
+        self.frame_dirty = true;
+    }
+
+    /// Destroys the renderer, reporting the first problem: in-flight frames
+    /// that didn't finish, a failed vkDeviceWaitIdle, or GPU objects left
+    /// alive. Everything is destroyed either way, and dropping the renderer
+    /// afterwards does nothing.
+    pub fn destroy(mut self) -> Result<(), VulkanError> {
+        self.close()
+    }
+
+    /// `destroy` for a renderer still held elsewhere, such as by the event
+    /// loop. Nothing may be drawn afterwards. Calling it again, or dropping
+    /// the renderer, does nothing and reports nothing.
+    pub fn close(&mut self) -> Result<(), VulkanError> {
+        if self.destroyed {
+            return Ok(());
+        }
+        unsafe { self.teardown() }?;
+        let leaks = self.resource_report();
+        if leaks.is_empty() {
+            Ok(())
+        } else {
+            Err(VulkanError::Leaked(leaks.len()))
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.destroyed
+    }
+
+    /// Destroys the renderer and reports validation errors and leaked GPU
+    /// objects from its whole lifetime, shutdown included.
+    pub fn shutdown(mut self) -> SelfTestReport {
+        let torn_down = unsafe { self.teardown() };
+        let mut report = SelfTestReport::default();
+        report.add(
+            "teardown",
+            match torn_down {
+                Ok(()) => CheckOutcome::Pass("device idle before destruction".to_string()),
+                Err(error) => CheckOutcome::Fail(error.to_string()),
+            },
+        );
+        let errors = self.validation_log.error_count();
+        report.add(
+            "validation",
+            if errors == 0 {
+                CheckOutcome::Pass(format!("no errors in {} frames", self.frame_count))
+            } else {
+                CheckOutcome::Fail(format!("{} validation errors", errors))
+            },
+        );
+        let leaks = self.resource_report();
+        report.add(
+            "resources",
+            if leaks.is_empty() {
+                CheckOutcome::Pass("every tracked object destroyed".to_string())
+            } else {
+                CheckOutcome::Fail(format!("{} GPU resources leaked", leaks.len()))
+            },
+        );
+        report
+    }
+
+    /// Every tracked GPU object that has been created and not yet destroyed.
+    pub fn resource_report(&self) -> Vec<ResourceRecord> {
+        self.resources.records()
+    }
+
+    /// The memory type each resource class prefers and last landed in.
+    pub fn memory_placements(&self) -> Vec<MemoryPlacement> {
+        self.context.memory.placements()
+    }
+
+    /// How much device memory the allocator holds, and how much of it is in
+    /// use, per heap.
+    #[cfg_attr(not(feature = "overlay"), allow(dead_code))]
+    pub fn memory_stats(&self) -> AllocatorStats {
+        self.context.allocator.stats()
+    }
+
+    pub fn diagnostics_report(&self) -> DiagnosticsReport {
+        let properties = unsafe {
+            self.context
+                .instance
+                .get_physical_device_properties(self.context.physical_device)
+        };
+        DiagnosticsReport {
+            app_name: self.config.app_name.clone(),
+            app_version: self.config.app_version,
+            engine_name: self.config.engine_name.clone(),
+            engine_version: self.config.engine_version,
+            window_backend: self.window_backend,
+            effective: self.effective,
+            downgrades: self.downgrades.clone(),
+            surface_extensions: self.surface_extensions.clone(),
+            device_name: unsafe { CStr::from_ptr(properties.device_name.as_ptr()) }
+                .to_string_lossy()
+                .into_owned(),
+            device_type: properties.device_type,
+            api_version: properties.api_version,
+            driver_version: properties.driver_version,
+            capabilities: self.capabilities,
+            draw_packing: self.draw_packing,
+            memory_placements: self.context.memory.placements(),
+            swapchain_extent: self.swapchain.extent,
+            render_extent: self.render_extent,
+            swapchain_format: self.swapchain.format,
+            swapchain_usage: self.swapchain.usage,
+            scene_objects: self.animator.objects.len(),
+            final_layout: self.config.final_layout,
+            frame_count: self.frame_count,
+            avg_frame_ms: self.frame_stats.avg_frame_ms(),
+            slow_frame_ms: self.frame_stats.percentile_frame_ms(99.0),
+            avg_gpu_ms: self.frame_stats.avg_gpu_ms(),
+            draw_stats: self.draw_stats,
+            mesh_pool: self.mesh_pool.stats(),
+            ground_texture: self.ground_texture.as_ref().map(|texture| {
+                (
+                    self.config.ground_texture,
+                    texture.width,
+                    texture.mip_levels,
+                )
+            }),
+            live_resources: self.resources.counts_by_kind(),
+            validation_messages: self.validation_log.recent(),
+        }
+    }
+
+    /// Copies the diagnostics report to the clipboard for pasting into bug
+    /// reports. Without a clipboard, or built without the `clipboard`
+    /// feature, saves a copy in the working directory instead. Returns the
+    /// report's text for the caller to show.
+    pub fn export_diagnostics(&mut self) -> String {
+        let report = self.diagnostics_report();
+        let text = report.to_string();
+        #[cfg(feature = "clipboard")]
+        match self.copy_to_clipboard(text.clone()) {
+            Ok(()) => {
+                info!("Diagnostics copied to the clipboard");
+                return text;
+            }
+            Err(error) => warn!("Can't copy diagnostics to the clipboard: {}", error),
+        }
+        match report.write_to_file() {
+            Ok(path) => info!("Diagnostics written to {}", path.display()),
+            Err(error) => warn!("Failed to write diagnostics: {}", error),
+        }
+        text
+    }
+
+    #[cfg(feature = "clipboard")]
+    fn copy_to_clipboard(&mut self, text: String) -> Result<(), arboard::Error> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(arboard::Clipboard::new()?);
+        }
+        self.clipboard.as_mut().unwrap().set_text(text)
+    }
+
+    /// The event journal, for dumping from a panic hook or recording input.
+    pub fn journal(&self) -> Arc<Journal> {
+        self.journal.clone()
+    }
+
+    /// A resize event from the window. A zero-sized (minimised) window
+    /// suspends rendering; the next real size resumes it, recreating the
+    /// swapchain only if the size differs from the swapchain's.
+    pub fn window_resized(&mut self, width: u32, height: u32) {
+        self.journal.record(JournalEvent::Resize { width, height });
+        if width == 0 || height == 0 {
+            self.suspended = true;
+            return;
+        }
+        let resumed = std::mem::replace(&mut self.suspended, false);
+        if !resumed
+            || width != self.swapchain.extent.width
+            || height != self.swapchain.extent.height
+        {
+            self.framebuffer_resized = true;
+        }
+    }
+
+    /// The window is minimised or hidden. `draw_frame` does nothing until
+    /// it's restored, so the event loop can wait for events instead of
+    /// polling.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended || self.occluded
+    }
+
+    /// An occlusion event from the window, on platforms that send them.
+    /// Resizes while hidden still go through `window_resized`, so the
+    /// swapchain is recreated for the right size on the first frame back.
+    pub fn window_occluded(&mut self, occluded: bool) {
+        if occluded == self.occluded {
+            return;
+        }
+        self.occluded = occluded;
+        if !occluded {
+            // The compositor may not have kept the last frame.
+            self.frame_dirty = true;
+            self.last_frame_start = None;
+        }
+    }
+
+    /// A focus event from the window. Out of focus, `draw_frame` draws one
+    /// frame every UNFOCUSED_FRAME_INTERVAL_MS and skips the calls between.
+    pub fn window_focus_changed(&mut self, focused: bool) {
+        if focused != self.unfocused {
+            return;
+        }
+        self.unfocused = !focused;
+        self.next_throttled_frame = Instant::now();
+        // The gap belongs to neither rate.
+        self.last_frame_start = None;
+        info!(
+            "Window {}",
+            if focused {
+                "focused; rendering at full rate"
+            } else {
+                "out of focus; throttling frames"
+            }
+        );
+    }
+
+    /// When the next throttled frame is due, for the event loop to wait
+    /// until; `None` while the window has focus.
+    pub fn throttled_until(&self) -> Option<Instant> {
+        self.unfocused.then_some(self.next_throttled_frame)
+    }
+
+    pub fn toggle_animation_pause(&mut self) {
+        self.clock.toggle_pause();
+        info!(
+            "Animation {}",
+            if self.clock.is_paused() {
+                "paused"
+            } else {
+                "resumed"
+            }
+        );
+    }
+
+    /// Nothing would change on screen: the event loop can sleep until the
+    /// next event instead of redrawing the same frame. A pipeline still
+    /// building counts as a change, since frames are drawing its fallback, and
+    /// so does a capture waiting for its frame to be drawn or collected.
+    pub fn is_idle(&self) -> bool {
+        self.clock.is_paused()
+            && (self.config.sun.is_none() || self.sun_clock.is_paused())
+            && !self.frame_dirty
+            && !self.framebuffer_resized
+            && !self.settings_rebuild
+            && !self.pipelines.is_building()
+            && !self.captures_pending()
+            && !self.error_banner.is_active(&self.validation_log)
+            && !self
+                .orbit_camera
+                .as_ref()
+                .is_some_and(OrbitCamera::is_coasting)
+    }
+
+    /// Whether `draw_frame` would draw: unless idle, or always with
+    /// `RenderMode::Continuous`. The event loop only asks for a redraw, and
+    /// otherwise sleeps, when this holds.
+    pub fn needs_redraw(&self) -> bool {
+        self.config.render_mode == super::config::RenderMode::Continuous || !self.is_idle()
+    }
+
+    /// Samples per pixel the scene is rendered with. Follows
+    /// `effective_settings` once the swapchain has been recreated.
+    pub fn msaa_samples(&self) -> vk::SampleCountFlags {
+        self.msaa_samples
+    }
+
+    /// The settings a settings file can change, as requested, with the
+    /// camera and overlay where input has since left them. The window's are
+    /// left at their defaults; only `main` knows where the window is.
+    pub fn settings(&self) -> Settings {
+        let (yaw, elevation) = self.camera_orbit;
+        Settings {
+            animation_speed: self.clock.speed(),
+            camera_yaw: cgmath::Rad(yaw).into(),
+            camera_elevation: cgmath::Rad(elevation).into(),
+            #[cfg(feature = "overlay")]
+            show_frame_graph: self.graph_visible,
+            ..Settings::from_config(&self.config)
+        }
+    }
+
+    /// `settings`, with MSAA and the present mode as the device actually
+    /// runs them.
+    pub fn effective_settings(&self) -> Settings {
+        Settings {
+            msaa_samples: self.effective.msaa_samples,
+            present_mode: self.effective.present_mode,
+            ..self.settings()
+        }
+    }
+
+    /// Applies whatever differs from the current settings. The clear
+    /// colour, animation speed, camera and overlay change on the next frame,
+    /// the background once the device is idle.
+    /// MSAA and the present mode go back through `ConfigResolver`, and if
+    /// the outcome differs the swapchain is recreated after the next
+    /// present, once the device is idle.
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        let current = Settings {
+            window: settings.window,
+            ..self.settings()
+        };
+        if settings == &current {
+            return;
+        }
+        info!("Applying settings:\n{}", settings.to_toml());
+        if settings.animation_speed != current.animation_speed {
+            self.clock.set_speed(settings.animation_speed);
+        }
+        if (settings.camera_yaw, settings.camera_elevation)
+            != (current.camera_yaw, current.camera_elevation)
+        {
+            self.camera_orbit = (
+                cgmath::Rad::from(settings.camera_yaw).0,
+                cgmath::Rad::from(settings.camera_elevation).0,
+            );
+        }
+        #[cfg(feature = "overlay")]
+        if settings.show_frame_graph != current.show_frame_graph {
+            self.graph_visible = settings.show_frame_graph;
+        }
+        if settings.background != current.background {
+            self.set_background(settings.background);
+        }
+        if settings.clear_color != current.clear_color {
+            let [r, g, b] = settings.clear_color;
+            self.set_clear_color([r, g, b, 1.0]);
+        }
+        settings.apply_to(&mut self.config);
+        self.frame_dirty = true;
+
+        if (settings.msaa_samples, settings.present_mode)
+            != (current.msaa_samples, current.present_mode)
+        {
+            let present_modes = match &self.context.surface {
+                Some(bundle) => bundle.support.present_modes.clone(),
+                None => vec![settings.present_mode],
+            };
+            let (effective, downgrades) =
+                ConfigResolver::new(&self.capabilities, &present_modes).resolve(&self.config);
+            for downgrade in &downgrades {
+                info!("Config downgraded: {}", downgrade);
+            }
+            self.downgrades = downgrades;
+            if effective != self.effective {
+                self.effective = effective;
+                self.settings_rebuild = true;
+            }
+        }
+    }
+
+    /// Stops or restarts the sun without affecting the scene's animation.
+    pub fn toggle_sun_pause(&mut self) {
+        if self.config.sun.is_none() {
+            info!("No sun to pause; start with --sun");
+            return;
+        }
+        self.sun_clock.toggle_pause();
+        info!(
+            "Sun {}",
+            if self.sun_clock.is_paused() {
+                "paused"
+            } else {
+                "resumed"
+            }
+        );
+    }
+
+    pub fn toggle_interpolation_split(&mut self) {
+        self.frame_dirty = true;
+        self.split_view.enabled = !self.split_view.enabled;
+        info!(
+            "Interpolation comparison: {}",
+            if self.split_view.enabled {
+                "linear (left) vs naive sRGB (right)"
+            } else {
+                "off"
+            }
+        );
+    }
+
+    pub fn set_split_dragging(&mut self, dragging: bool) {
+        self.split_view.dragging = dragging;
+    }
+
+    /// While held, the area under the cursor is drawn again, zoomed
+    /// MAGNIFIER_ZOOM times, in an inset in the top-right corner. Costs a
+    /// second pass over the scene only while held.
+    pub fn set_magnifier_held(&mut self, held: bool) {
+        if held != self.magnifier_held {
+            self.magnifier_held = held;
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Shows or hides the banner with the newest validation error.
+    pub fn toggle_error_banner(&mut self) {
+        let visible = self.error_banner.toggle();
+        self.frame_dirty = true;
+        info!(
+            "Validation error banner {}",
+            if visible { "shown" } else { "hidden" }
+        );
+    }
+
+    /// Switches the background pass. Turning it on or off swaps the render
+    /// pass for one that does or doesn't clear colour; only the load op
+    /// differs, so the framebuffers and pipelines stay compatible with it.
+    pub fn set_background(&mut self, background: Background) {
+        let current = self.config.background;
+        if background == current {
+            return;
+        }
+        unsafe { self.context.device.device_wait_idle().unwrap() };
+        if let Some(pipeline) = self.background_pipeline.take() {
+            self.resources.unregister(ResourceKind::Pipeline, pipeline);
+            unsafe { self.context.device.destroy_pipeline(pipeline, None) };
+        }
+        // Dynamic rendering picks the load op per frame instead.
+        if background.covers_screen() != current.covers_screen() && self.dynamic_rendering.is_none()
+        {
+            // An in-flight build reads the render pass; the next frame
+            // schedules whatever it dropped again.
+            for pipeline in self.pipelines.cancel_build() {
+                unsafe { self.context.device.destroy_pipeline(pipeline, None) };
+            }
+            unsafe {
+                self.context
+                    .device
+                    .destroy_render_pass(self.render_pass, None)
+            };
+            self.render_pass = Self::create_render_pass(
+                &self.context.device,
+                self.swapchain.format,
+                self.depth_format,
+                self.msaa_samples,
+                self.config.final_layout,
+                !background.covers_screen(),
+                false,
+                self.post_processing(),
+            );
+        }
+        self.background_pipeline = Self::create_background_pipeline(
+            &self.context.device,
+            self.pass_target(),
+            self.pipeline_layout,
+            background,
+            self.needs_manual_srgb,
+            &mut self.resources,
+        );
+        self.config.background = background;
+        // Its scene pass clears colour as `render_pass` does.
+        self.refresh_depth_view();
+        self.frame_dirty = true;
+    }
+
+    /// Whether the scene fills the window or keeps an aspect ratio inside
+    /// it, from the next frame on.
+    pub fn set_aspect_policy(&mut self, policy: AspectPolicy) {
+        if let AspectPolicy::Letterbox(ratio) = policy {
+            self.letterbox_aspect = ratio;
+        }
+        self.config.aspect_policy = policy;
+        self.frame_dirty = true;
+    }
+
+    /// Letterboxes to the last ratio used, LETTERBOX_ASPECT at first, or
+    /// back to stretching.
+    pub fn toggle_letterbox(&mut self) {
+        let policy = match self.config.aspect_policy {
+            AspectPolicy::Stretch => AspectPolicy::Letterbox(self.letterbox_aspect),
+            AspectPolicy::Letterbox(_) => AspectPolicy::Stretch,
+        };
+        self.set_aspect_policy(policy);
+        match policy {
+            AspectPolicy::Letterbox(ratio) => info!("Letterboxed to {:.3}:1", ratio),
+            AspectPolicy::Stretch => info!("Filling the window"),
+        }
+    }
+
+    /// Keeps an object ID target alongside the attachments for `pick`, or
+    /// frees it. The IDs are only drawn when something is picked, so
+    /// picking costs the target's memory and nothing per frame.
+    pub fn set_picking(&mut self, enabled: bool) -> Result<(), VulkanError> {
+        self.config.picking = enabled;
+        if enabled == self.picking.is_some() {
+            return Ok(());
+        }
+        // `pick` waits for its own work, so no frame is using the pass.
+        if let Some(mut picking) = self.picking.take() {
+            picking.destroy_target(
+                &self.context.device,
+                &self.context.allocator,
+                &mut self.resources,
+            );
+            picking.destroy(
+                &self.context.device,
+                &self.context.allocator,
+                &mut self.resources,
+            );
+            return Ok(());
+        }
+        let mut picking = self.create_pick_pass()?;
+        let target = picking.create_target(
+            &self.context,
+            self.depth_format,
+            self.render_extent,
+            &mut self.resources,
+        );
+        if let Err(error) = target {
+            picking.destroy(
+                &self.context.device,
+                &self.context.allocator,
+                &mut self.resources,
+            );
+            return Err(error);
+        }
+        self.picking = Some(picking);
+        // Its draws come with the next frame's draw list.
+        self.frame_dirty = true;
+        Ok(())
+    }
+
+    /// The object drawn at window position `(x, y)`: an index into the
+    /// animated objects, or past them into `objects()`. None over the
+    /// background or letterbox bars, before the first frame, or without
+    /// `set_picking`. Draws the IDs the way the last frame drew the scene
+    /// and waits for the one under `(x, y)` to be read back, stalling the
+    /// GPU, so it is for clicks rather than every frame. A picked animated
+    /// object becomes the selection.
+    pub fn pick(&mut self, x: u32, y: u32) -> Option<u32> {
+        let last_frame = self.last_frame?;
+        let picking = self.picking.as_ref()?;
+        let framebuffer = picking.framebuffer()?;
+        let pixel = pick::render_pixel(
+            x,
+            y,
+            self.display_extent,
+            self.render_extent,
+            self.scene_rect(),
+        )?;
+        self.context.submit_once_on(
+            self.command_pool,
+            self.context.graphics_queue,
+            |_, command_buffer| {
+                self.record_pick(command_buffer, picking, framebuffer, last_frame, pixel)
+            },
+        );
+        let index = pick::object_index(picking.read())?;
+        if index < self.animator.object_count() {
+            self.selected_object = index as usize;
+        }
+        info!("Picked object {}", index);
+        Some(index)
+    }
+
+    /// Shows the scene in `window` too, drawn and presented with every
+    /// frame from a camera EXTRA_WINDOW_YAW_DEGREES further round than the
+    /// last window's, on the GPU `--gpu-for-window` maps it to, else the
+    /// main one. Fails, closing `window`, if that device can't present to
+    /// it. Not for a headless renderer.
+    pub fn open_window(&mut self, window: winit::window::Window) -> Result<(), VulkanError> {
+        assert!(
+            self.context.surface.is_some(),
+            "headless renderers have no windows"
+        );
+        let number = self.extra_windows.len() + 1;
+        let (surface, device) = match self.config.window_gpus.for_window(number).cloned() {
+            Some(selector) => self.window_device(&selector, &window)?,
+            None => (self.context.create_window_surface(&window)?, None),
+        };
+        let yaw = EXTRA_WINDOW_YAW_DEGREES * number as f32;
+        let (context, view_set_layout, resources) = match device {
+            Some(index) => {
+                let render_context = &mut self.render_contexts[index];
+                (
+                    &render_context.context,
+                    render_context.view_set_layout,
+                    &mut render_context.resources,
+                )
+            }
+            None => (&self.context, self.view_set_layout, &mut self.resources),
+        };
+        let mut extra = ExtraWindow::new(
+            context,
+            window,
+            surface,
+            yaw.to_radians(),
+            device,
+            view_set_layout,
+            resources,
+        )?;
+        if let Err(error) = self.create_extra_targets(&mut extra) {
+            self.destroy_extra_window(extra);
+            return Err(error);
+        }
+        self.extra_windows.push(extra);
+        info!(
+            "Opened window {} onto the scene",
+            self.extra_windows.len() + 1
+        );
+        self.frame_dirty = true;
+        Ok(())
+    }
+
+    /// A surface on `window`, and the render context that draws it: None
+    /// for the main device, else one for the device `selector` names,
+    /// created the first time a window is put on it. Fails, saying why, if
+    /// that device can't render to the window or present to it from the
+    /// queue its context already has.
+    fn window_device(
+        &mut self,
+        selector: &GpuSelector,
+        window: &winit::window::Window,
+    ) -> Result<(SurfaceBundle, Option<usize>), VulkanError> {
+        let (physical_device, indices, surface) =
+            self.context.find_window_device(selector, window)?;
+        let existing = self
+            .render_contexts
+            .iter()
+            .position(|render_context| render_context.context.physical_device == physical_device);
+        let checked = if physical_device == self.context.physical_device {
+            self.context.check_presentable(&surface).map(|()| None)
+        } else if let Some(index) = existing {
+            self.render_contexts[index]
+                .context
+                .check_presentable(&surface)
+                .map(|()| Some(index))
+        } else {
+            self.context
+                .for_device(physical_device, indices)
+                .and_then(|context| RenderContext::new(context, &self.mesh_pool))
+                .map(|render_context| {
+                    self.render_contexts.push(render_context);
+                    Some(self.render_contexts.len() - 1)
+                })
+        };
+        match checked {
+            Ok(device) => Ok((surface, device)),
+            Err(error) => {
+                unsafe { surface.destroy() };
+                Err(error)
+            }
+        }
+    }
+
+    /// Whether `id` is a window `open_window` opened.
+    pub fn is_extra_window(&self, id: winit::window::WindowId) -> bool {
+        self.extra_windows
+            .iter()
+            .any(|extra| extra.window.id() == id)
+    }
+
+    pub fn extra_window_count(&self) -> usize {
+        self.extra_windows.len()
+    }
+
+    /// Closes a window `open_window` opened. Any other `id` is ignored.
+    pub fn close_window(&mut self, id: winit::window::WindowId) {
+        let Some(index) = self
+            .extra_windows
+            .iter()
+            .position(|extra| extra.window.id() == id)
+        else {
+            return;
+        };
+        let extra = self.extra_windows.remove(index);
+        self.destroy_extra_window(extra);
+    }
+
+    /// Has a window `open_window` opened rebuild its swapchain for its new
+    /// size before the next frame.
+    pub fn extra_window_resized(&mut self, id: winit::window::WindowId) {
+        if let Some(extra) = self
+            .extra_windows
+            .iter_mut()
+            .find(|extra| extra.window.id() == id)
+        {
+            extra.stale = true;
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Rebuilds stale extra windows, then acquires each one's image for the
+    /// frame about to be submitted. Runs after the main window's image is
+    /// acquired, so it never fails: a window that can't be rebuilt or
+    /// acquired is closed, and a minimized one, or a stale one while the
+    /// device can't be waited on, sits the frame out.
+    fn acquire_extra_windows(&mut self) {
+        let rebuilding = self
+            .extra_windows
+            .iter()
+            .any(|extra| extra.stale && extra.has_area());
+        let devices = std::iter::once(&self.context.device).chain(
+            self.render_contexts
+                .iter()
+                .map(|render_context| &render_context.context.device),
+        );
+        let idle = !rebuilding
+            || devices
+                .map(|device| unsafe { device.device_wait_idle() })
+                .collect::<Result<(), _>>()
+                .map_or_else(
+                    |error| {
+                        warn!("Not rebuilding windows this frame: {}", error);
+                        false
+                    },
+                    |()| true,
+                );
+        // Other devices' windows reuse semaphores their last submission
+        // for this frame waited on.
+        let mut waited = vec![true; self.render_contexts.len()];
+        for (index, render_context) in self.render_contexts.iter().enumerate() {
+            if let Err(error) = render_context.wait(self.current_frame) {
+                warn!("Windows on another GPU sit this frame out: {}", error);
+                waited[index] = false;
+            }
+        }
+        for mut extra in std::mem::take(&mut self.extra_windows) {
+            if extra.device.is_some_and(|index| !waited[index]) {
+                extra.acquired = None;
+                self.extra_windows.push(extra);
+                continue;
+            }
+            if !extra.has_area() || (extra.stale && !idle) {
+                extra.acquired = None;
+                self.extra_windows.push(extra);
+                continue;
+            }
+            if extra.stale {
+                extra.stale = false;
+                let (context, _, resources) = self.window_device_objects(extra.device);
+                extra.destroy_targets(&context.device, &context.allocator, resources);
+                if let Err(error) = self.create_extra_targets(&mut extra) {
+                    warn!("Closing a window that couldn't be rebuilt: {}", error);
+                    self.destroy_extra_window(extra);
+                    continue;
+                }
+            }
+            let (_, swapchain_loader, _) = self.window_device_objects(extra.device);
+            if let Err(error) = extra.acquire(swapchain_loader, self.current_frame) {
+                warn!("Closing a window that couldn't be drawn: {}", error);
+                self.destroy_extra_window(extra);
+                continue;
+            }
+            self.extra_windows.push(extra);
+        }
+    }
+
+    /// `extra`'s swapchain, handing over any old one, and the render pass,
+    /// pipelines and targets that go with it. Always filled, whatever the
+    /// render mode, and single-sampled.
+    fn create_extra_targets(&mut self, extra: &mut ExtraWindow) -> Result<(), VulkanError> {
+        let old_swapchain = Some(extra.swapchain).filter(|&old| old != vk::SwapchainKHR::null());
+        let main_present_mode = self.effective.present_mode;
+        let depth_direction = self.depth_direction;
+        let depth_format = match extra.device {
+            Some(index) => self.render_contexts[index].depth_format,
+            None => self.depth_format,
+        };
+        let (context, swapchain_loader, _) = self.window_device_objects(extra.device);
+        // The main window's present mode, if this surface has it too.
+        let support = context.surface_support(&extra.surface);
+        let present_mode = if support.present_modes.contains(&main_present_mode) {
+            main_present_mode
+        } else {
+            vk::PresentModeKHR::FIFO
+        };
+        let (swapchain, format, extent, _) = swapchain::create_swapchain(
+            context,
+            &extra.surface,
+            old_swapchain,
+            swapchain_loader,
+            present_mode,
+            vk::ImageUsageFlags::empty(),
+            &extra.window,
+            Stage::SwapchainCreation,
+        )?;
+        if let Some(old_swapchain) = old_swapchain {
+            unsafe { swapchain_loader.destroy_swapchain(old_swapchain, None) };
+        }
+        extra.swapchain = swapchain;
+        extra.extent = extent;
+        extra.render_pass = Self::create_extra_render_pass(
+            &context.device,
+            format,
+            depth_format,
+            vk::ImageLayout::PRESENT_SRC_KHR,
+        );
+        let target = PassTarget {
+            render_pass: extra.render_pass,
+            color_format: format,
+            depth_format,
+            samples: vk::SampleCountFlags::TYPE_1,
+            depth_direction,
+        };
+        extra.manual_srgb = swapchain_support::is_unorm_format(format);
+        extra.pipelines = match extra.device {
+            Some(index) => {
+                let render_context = &mut self.render_contexts[index];
+                Self::create_unlit_pipelines(
+                    &render_context.context.device,
+                    target,
+                    render_context.pipeline_layout,
+                    extra.manual_srgb,
+                    &mut render_context.resources,
+                )
+            }
+            None => {
+                let pipelines = Self::create_graphics_pipeline(
+                    &self.context.device,
+                    target,
+                    self.pipeline_layout,
+                    self.draw_packing,
+                    extra.manual_srgb,
+                    self.cube_shaders.as_deref(),
+                    &[Faces::Front, Faces::Both]
+                        .map(|faces| PipelineKey::Cube(ColorInterpolation::Linear, faces)),
+                );
+                for &pipeline in &pipelines {
+                    self.resources.register(
+                        ResourceKind::Pipeline,
+                        pipeline,
+                        "extra window pipeline",
+                        0,
+                    );
+                }
+                [pipelines[0], pipelines[1]]
+            }
+        };
+        let (context, swapchain_loader, resources) = self.window_device_objects(extra.device);
+        extra.create_targets(context, swapchain_loader, format, depth_format, resources)
+    }
+
+    /// The context, swapchain loader and resource registry of the device a
+    /// window with `device` is on.
+    fn window_device_objects(
+        &mut self,
+        device: Option<usize>,
+    ) -> (
+        &VkContext,
+        &ash::extensions::khr::Swapchain,
+        &mut ResourceRegistry,
+    ) {
+        match device {
+            Some(index) => {
+                let render_context = &mut self.render_contexts[index];
+                (
+                    &render_context.context,
+                    &render_context.swapchain_loader,
+                    &mut render_context.resources,
+                )
+            }
+            None => (&self.context, &self.swapchain.loader, &mut self.resources),
+        }
+    }
+
+    /// Waits for the device, as frames in flight may still draw into
+    /// `extra`, then destroys it and closes its window.
+    fn destroy_extra_window(&mut self, mut extra: ExtraWindow) {
+        let (context, swapchain_loader, resources) = self.window_device_objects(extra.device);
+        if let Err(error) = unsafe { context.device.device_wait_idle() } {
+            warn!("Closing a window without waiting for the device: {}", error);
+        }
+        extra.destroy_targets(&context.device, &context.allocator, resources);
+        extra.destroy(
+            &context.device,
+            &context.allocator,
+            swapchain_loader,
+            resources,
+        );
+    }
+
+    pub fn cycle_background(&mut self) {
+        let background = self.config.background.next();
+        self.set_background(background);
+        info!("Background: {}", background.name());
+    }
+
+    /// Asks for `preference` like a settings change: if the mode the
+    /// surface ends up with differs, the swapchain is recreated after the
+    /// next present, once the device is idle.
+    pub fn set_present_mode(&mut self, preference: PresentModePreference) {
+        let settings = Settings {
+            present_mode: preference.mode(),
+            ..self.settings()
+        };
+        self.apply_settings(&settings);
+    }
+
+    pub fn cycle_present_mode(&mut self) {
+        let preference = PresentModePreference::from_mode(self.config.present_mode).next();
+        self.set_present_mode(preference);
+        info!("Present mode preference: {}", preference.name());
+    }
+
+    pub fn cursor_moved(&mut self, x: f64, y: f64) {
+        // In the scene's pixels, which below full render scale are larger
+        // than the window's.
+        let (display, render) = (self.display_extent, self.render_extent);
+        self.cursor_position = (
+            x as f32 * render.width as f32 / display.width.max(1) as f32,
+            y as f32 * render.height as f32 / display.height.max(1) as f32,
+        );
+        if self.magnifier_held {
+            self.frame_dirty = true;
+        }
+        if let Some(camera) = &mut self.orbit_camera {
+            if camera.cursor_moved(x as f32, y as f32, CAMERA_FOV_Y.into(), display.height) {
+                self.frame_dirty = true;
+            }
+        }
+        #[cfg(feature = "tools")]
+        if let Some(position) = self
+            .gizmo_drag
+            .and_then(|drag| drag.update(&self.cursor_ray()?))
+        {
+            let mut transform = self.animator.objects[self.selected_object].base;
+            transform.w = position.to_homogeneous();
+            self.set_transform(self.selected_object, transform);
+        }
+        if self.split_view.dragging {
+            self.frame_dirty = true;
+            let scene = self.scene_rect();
+            self.split_view.drag_to(
+                (self.cursor_position.0 - scene.offset.x as f32) / scene.extent.width as f32,
+            );
+        }
+    }
+
+    /// Starts an axis drag if the cursor is over a handle of the selected
+    /// object's translate gizmo, or ends the current one.
+    #[cfg(feature = "tools")]
+    pub fn set_gizmo_dragging(&mut self, dragging: bool) {
+        if !dragging {
+            self.gizmo_drag = None;
+            return;
+        }
+        let Some(ray) = self.cursor_ray() else {
+            return;
+        };
+
+        let position = tools::translation_of(&self.animator.objects[self.selected_object].base);
+        let scale = tools::gizmo_scale(
+            (self.camera_eye() - position).magnitude(),
+            cgmath::Rad::from(CAMERA_FOV_Y).0,
+            self.scene_rect().extent.height,
+            GIZMO_SIZE_PIXELS,
+        );
+        self.gizmo_drag = tools::pick_axis(&ray, position, scale)
+            .and_then(|axis| AxisDrag::begin(axis, position, self.camera_eye(), &ray));
+        if let Some(drag) = self.gizmo_drag {
+            info!(
+                "Dragging object {} along {:?}",
+                self.selected_object, drag.axis
+            );
+        }
+    }
+
+    /// Sets the base transform of animated object `index`; animation is applied on top.
+    #[cfg_attr(not(feature = "tools"), allow(dead_code))]
+    pub fn set_transform(&mut self, index: usize, transform: Matrix4<f32>) {
+        unsafe {
+            // Both frames in flight read the parameter buffer.
+            self.context.device.device_wait_idle().unwrap();
+        }
+        self.animator.set_base(index, transform);
+        self.frame_dirty = true;
+    }
+
+    /// Draws animated object `index` without back-face culling, for open or
+    /// thin geometry that must be visible from both sides.
+    pub fn set_double_sided(&mut self, index: usize, double_sided: bool) {
+        self.double_sided[index] = double_sided;
+        self.frame_dirty = true;
+    }
+
+    /// Shows or hides the frame-time graph in the bottom-left corner.
+    #[cfg(feature = "overlay")]
+    pub fn toggle_frame_graph(&mut self) {
+        self.graph_visible = !self.graph_visible;
+        self.frame_dirty = true;
+    }
+
+    /// The frame-time graph or the HUD is showing.
+    #[cfg(feature = "overlay")]
+    pub fn overlay_visible(&self) -> bool {
+        self.graph_visible || self.hud.is_visible()
+    }
+
+    /// Shows or hides the HUD in the top-left corner.
+    #[cfg(feature = "overlay")]
+    pub fn toggle_hud(&mut self) {
+        let visible = self.hud.toggle();
+        info!("HUD {}", if visible { "shown" } else { "hidden" });
+        self.frame_dirty = true;
+    }
+
+    /// Sets HUD line `line`, counted from the first line under the frame
+    /// statistics, to `text`. Characters outside printable ASCII show as
+    /// '?'. Only a change of text needs a new frame.
+    #[cfg(feature = "overlay")]
+    pub fn hud_print(&mut self, line: usize, text: &str) {
+        if self.hud.print(line, text) && self.hud.is_visible() {
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Swaps the colour cube for one with each face labelled by its axis, to
+    /// check winding, texture coordinates and handedness at a glance.
+    pub fn toggle_face_labels(&mut self) {
+        self.show_face_labels = !self.show_face_labels;
+        self.frame_dirty = true;
+        info!(
+            "Drawing the {} cube",
+            if self.show_face_labels {
+                "labeled"
+            } else if self.cube_texture.is_some() {
+                "textured"
+            } else {
+                "colour"
+            }
+        );
+    }
+
+    /// Shows the depth buffer in grey in place of the frame, or goes back to
+    /// the shaded scene.
+    pub fn toggle_depth_view(&mut self) {
+        unsafe {
+            // Recorded frames still use the current scene pass.
+            self.context.device.device_wait_idle().unwrap();
+        }
+        match self.depth_view.take() {
+            Some(view) => self.destroy_depth_view(view),
+            None => self.depth_view = Some(self.create_depth_view()),
+        }
+        self.frame_dirty = true;
+        info!(
+            "Depth view {}",
+            if self.depth_view.is_some() {
+                "on"
+            } else {
+                "off"
+            }
+        );
+    }
+
+    /// Switches between filled and wireframe rendering. Does nothing on
+    /// devices without `fillModeNonSolid`.
+    pub fn toggle_wireframe(&mut self) {
+        if !self.capabilities.fill_mode_non_solid {
+            info!("Wireframe needs fillModeNonSolid, which this device lacks");
+            return;
+        }
+        self.render_mode = match self.render_mode {
+            RenderMode::Fill => RenderMode::Wireframe,
+            RenderMode::Wireframe => RenderMode::Fill,
+        };
+        self.frame_dirty = true;
+        info!("Render mode: {:?}", self.render_mode);
+    }
+
+    /// Renders the scene `steps` RENDER_SCALE_STEP_PERCENT steps above or
+    /// below the current scale, then stretched over the window, and keeps
+    /// it there until `release_render_scale`.
+    pub fn step_render_scale(&mut self, steps: i32) {
+        if !self.can_upscale {
+            info!("The swapchain images can't be blitted onto; rendering at full scale");
+            return;
+        }
+        if self.render_scaler.nudge(steps).is_some() {
+            self.render_scale_changed = true;
+            self.frame_dirty = true;
+        }
+        info!("Render scale pinned at {}%", self.render_scaler.percent());
+    }
+
+    /// Lets `--adaptive-resolution` pick the render scale again.
+    pub fn release_render_scale(&mut self) {
+        self.render_scaler.unpin();
+        info!("Render scale released");
+    }
+
+    /// Renders the scene at `percent` of the window's size, up to
+    /// RENDER_SCALE_MAX_PERCENT, then stretched or shrunk over the window,
+    /// and keeps it there until `release_render_scale`. With the A/B split
+    /// on, this is the left half's scale.
+    pub fn set_render_scale(&mut self, percent: u32) {
+        if !self.can_upscale {
+            info!("The swapchain images can't be blitted onto; rendering at full scale");
+            return;
+        }
+        if self.render_scaler.jump(percent).is_some() {
+            self.render_scale_changed = true;
+            self.frame_dirty = true;
+        }
+        info!("Render scale pinned at {}%", self.render_scaler.percent());
+    }
+
+    /// Shows the right half of the window rendered at `percent` of its
+    /// size next to the left half at the render scale, or with `None`
+    /// the whole window at the render scale again. Waits for the device.
+    pub fn set_split_scale(&mut self, percent: Option<u32>) {
+        if !self.can_upscale {
+            info!("The swapchain images can't be blitted onto; no A/B split");
+            return;
+        }
+        if let Err(error) = unsafe { self.context.device.device_wait_idle() } {
+            warn!("Not changing the A/B split: {}", error);
+            return;
+        }
+        self.destroy_comparison_target();
+        self.split_percent = percent
+            .map(|percent| percent.clamp(RENDER_SCALE_MIN_PERCENT, RENDER_SCALE_MAX_PERCENT));
+        if let Err(error) = self.create_comparison_target() {
+            warn!("No A/B split: {}", error);
+            self.split_percent = None;
+        }
+        self.frame_dirty = true;
+        match self.split_percent {
+            Some(right) => info!(
+                "A/B split: left at {}%, right at {}%",
+                self.render_scale_percent(),
+                right
+            ),
+            None => info!("A/B split off"),
+        }
+    }
+
+    /// The HUD's render scale comparison: a line per step of
+    /// RENDER_SCALE_LADDER with its pixels and GPU time, then the split's
+    /// scales. Always as many lines, left empty while the scale is left to
+    /// `--adaptive-resolution`, so printing them clears the old ones.
+    pub fn render_scale_lines(&self) -> Vec<String> {
+        if !self.render_scaler.is_pinned() && self.split_percent.is_none() {
+            return vec![String::new(); RENDER_SCALE_LADDER.len() + 1];
+        }
+        let mut lines = self.scale_times.lines(
+            self.display_extent,
+            &self.capabilities.limits,
+            self.render_scale_percent(),
+        );
+        lines.push(match self.split_percent {
+            Some(right) => format!(
+                "A/B split: left {}%, right {}%",
+                self.render_scale_percent(),
+                right
+            ),
+            None => String::new(),
+        });
+        lines
+    }
+
+    /// The percentage of the window's size the scene is rendered at.
+    pub fn render_scale_percent(&self) -> u32 {
+        if self.upscaling() {
+            self.render_scaler.percent()
+        } else {
+            100
+        }
+    }
+
+    /// Rewrites the pooled meshes' vertices: `morph` gets the animation time
+    /// and every vertex as first uploaded, and changes them in place. Frames
+    /// in flight keep the vertices they were recorded with; each later frame
+    /// copies the newest into a vertex buffer of its own once its fence has
+    /// signalled, so this never waits for the GPU.
+    pub fn update_vertices(&mut self, morph: impl Fn(f32, &mut [Vertex])) {
+        self.morph_vertices(self.clock.time(), morph);
+        self.frame_dirty = true;
+    }
+
+    fn morph_vertices(&mut self, time: f32, morph: impl Fn(f32, &mut [Vertex])) {
+        if self.morphed_vertices.is_none() {
+            let count = self
+                .meshes
+                .iter()
+                .map(|mesh| mesh.first_vertex + mesh.vertex_count)
+                .max()
+                .unwrap_or(0);
+            let rest = self.mesh_pool.read_vertices(count);
+            match MorphedVertices::new(&self.context, rest, &mut self.resources) {
+                Ok(morphed) => {
+                    info!(
+                        "Drawing {} pooled vertices from a buffer per frame in flight",
+                        count
+                    );
+                    self.morphed_vertices = Some(morphed);
+                }
+                Err(error) => {
+                    warn!("Vertices can't be morphed: {}", error);
+                    return;
+                }
+            }
+        }
+        if let Some(morphed) = &mut self.morphed_vertices {
+            morphed.rewrite(time, morph);
+        }
+    }
+
+    /// Starts or stops the cubes breathing, scaled by a sine of the
+    /// animation time through `update_vertices`.
+    pub fn toggle_breathing(&mut self) {
+        self.breathing = !self.breathing;
+        self.frame_dirty = true;
+        if !self.breathing {
+            self.update_vertices(|_, _| {});
+        }
+        info!(
+            "Breathing demo {}",
+            if self.breathing { "on" } else { "off" }
+        );
+    }
+
+    /// Steps the post-process vignette round; see `PostParams`. Only with
+    /// `--post-process`.
+    #[cfg(feature = "post-processing")]
+    pub fn cycle_vignette(&mut self) {
+        if self.post.is_none() {
+            info!("Post-processing is off; start with --post-process");
+            return;
+        }
+        self.post_params.cycle_vignette();
+        self.frame_dirty = true;
+        info!("Vignette {:.2}", self.post_params.vignette);
+    }
+
+    /// Steps the post-process gamma up for positive `steps`, down for
+    /// negative. Only with `--post-process`.
+    #[cfg(feature = "post-processing")]
+    pub fn step_gamma(&mut self, steps: i32) {
+        if self.post.is_none() {
+            info!("Post-processing is off; start with --post-process");
+            return;
+        }
+        self.post_params.step_gamma(steps);
+        self.frame_dirty = true;
+        info!("Gamma {:.1}", self.post_params.gamma);
+    }
+
+    /// Starts or stops `plasma.comp` recolouring the pooled vertices every
+    /// frame. Without compute on the graphics queue they keep their own
+    /// colours.
+    pub fn toggle_plasma(&mut self) {
+        if !self.plasma_colors && self.plasma.is_none() {
+            if !Self::graphics_queue_computes(&self.context) {
+                warn!("The graphics queue can't run compute shaders; keeping the vertex colours");
+                return;
+            }
+            if self.morphed_vertices.is_none() {
+                self.update_vertices(|_, _| {});
+            }
+            let Some(morphed) = &self.morphed_vertices else {
+                return;
+            };
+            self.plasma = Some(Self::create_plasma_pass(
+                &self.context,
+                morphed,
+                &mut self.resources,
+            ));
+        }
+        self.plasma_colors = !self.plasma_colors;
+        self.frame_dirty = true;
+        // Each frame's buffer gets the vertices' own colours back with its
+        // next upload.
+        if !self.plasma_colors && !self.breathing {
+            self.update_vertices(|_, _| {});
+        }
+        info!(
+            "Plasma vertex colours {}",
+            if self.plasma_colors { "on" } else { "off" }
+        );
+    }
+
+    fn graphics_queue_computes(context: &VkContext) -> bool {
+        let families = unsafe {
+            context
+                .instance
+                .get_physical_device_queue_family_properties(context.physical_device)
+        };
+        context
+            .queue_family_indices
+            .graphics_family
+            .and_then(|family| families.get(family as usize))
+            .is_some_and(|family| family.queue_flags.contains(vk::QueueFlags::COMPUTE))
+    }
+
+    pub fn toggle_selected_double_sided(&mut self) {
+        let index = self.selected_object;
+        self.set_double_sided(index, !self.double_sided[index]);
+        info!(
+            "Object {} is {}",
+            index,
+            if self.double_sided[index] {
+                "double-sided"
+            } else {
+                "back-face culled"
+            }
+        );
+    }
+
+    #[cfg(feature = "tools")]
+    fn cursor_ray(&self) -> Option<Ray> {
+        let scene = self.scene_rect();
+        let (x, y) = (
+            self.cursor_position.0 - scene.offset.x as f32,
+            self.cursor_position.1 - scene.offset.y as f32,
+        );
+        let ndc_x = 2.0 * x / scene.extent.width as f32 - 1.0;
+        let ndc_y = 1.0 - 2.0 * y / scene.extent.height as f32;
+        let (view, proj) = self.camera_matrices();
+        Ray::from_cursor(ndc_x, ndc_y, proj * view)
+    }
+
+    /// Adds a cube drawn with its own model matrix, from the next frame on.
+    /// Returns its index for `remove_object`.
+    pub fn add_object(&mut self, object: SceneObject) -> usize {
+        self.objects.push(object);
+        self.frame_dirty = true;
+        self.objects.len() - 1
+    }
+
+    /// Removes the object at `index`; the ones after it move down one.
+    pub fn remove_object(&mut self, index: usize) -> SceneObject {
+        self.frame_dirty = true;
+        self.objects.remove(index)
+    }
+
+    pub fn objects(&self) -> &[SceneObject] {
+        &self.objects
+    }
+
+    /// Turns the scene by `yaw` around Z, then `pitch` around X, in radians,
+    /// in place of the animated spin. The spin stops where it is on the
+    /// first call, so taking over doesn't make the objects jump.
+    pub fn set_model_rotation(&mut self, yaw: f32, pitch: f32) {
+        let spin_time = match self.model_rotation {
+            Some(rotation) => rotation.spin_time,
+            None => self.clock.time(),
+        };
+        let rotation = ModelRotation {
+            yaw,
+            pitch,
+            spin_time,
+        };
+        if self.model_rotation != Some(rotation) {
+            self.model_rotation = Some(rotation);
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Clears to `color`, linear RGBA, from the next frame, and stops the
+    /// hue cycle. The sun's sky still replaces it while there is one.
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        self.clear_color = color;
+        self.clear_color_cycle = false;
+        self.config.clear_color = [color[0], color[1], color[2]];
+        self.frame_dirty = true;
+    }
+
+    /// Steps through CLEAR_COLOR_PRESETS, then the hue cycle, then back to
+    /// the first preset.
+    pub fn cycle_clear_color(&mut self) {
+        let next = match self.clear_preset {
+            _ if self.clear_color_cycle => Some(0),
+            Some(index) if index + 1 < CLEAR_COLOR_PRESETS.len() => Some(index + 1),
+            Some(_) => None,
+            None => Some(0),
+        };
+        match next {
+            Some(index) => {
+                let [r, g, b] = parse_hex_color(CLEAR_COLOR_PRESETS[index]).unwrap();
+                self.set_clear_color([r, g, b, 1.0]);
+                info!("Clear colour #{}", CLEAR_COLOR_PRESETS[index]);
+            }
+            None => {
+                self.clear_color_cycle = true;
+                self.config.clear_color_cycle = true;
+                self.frame_dirty = true;
+                info!("Clear colour cycling");
+            }
+        }
+        self.clear_preset = next;
+        if self.config.sun.is_some() {
+            info!("The sun's sky hides the clear colour until it's turned off");
+        }
+    }
+
+    /// Moves the camera towards the origin by `zoom` times; 1 is the
+    /// default distance.
+    pub fn set_camera_zoom(&mut self, zoom: f32) {
+        if self.camera_zoom != zoom {
+            self.camera_zoom = zoom;
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Turns the camera around the origin, keeping its distance.
+    pub fn orbit_camera(&mut self, yaw: f32, elevation: f32) {
+        self.camera_orbit.0 += yaw;
+        self.camera_orbit.1 += elevation;
+        self.frame_dirty = true;
+    }
+
+    /// Switches between the fixed view and an orbit camera steered with
+    /// the mouse, which starts from wherever the fixed view is.
+    pub fn toggle_orbit_camera(&mut self) {
+        self.orbit_camera = match self.orbit_camera {
+            Some(_) => None,
+            None => Some(OrbitCamera::new(
+                self.camera_eye(),
+                Point3::new(0.0, 0.0, 0.0),
+            )),
+        };
+        self.frame_dirty = true;
+        info!(
+            "{}",
+            if self.orbit_camera.is_some() {
+                "Orbit camera: left-drag to orbit, middle-drag to pan, scroll to dolly"
+            } else {
+                "Fixed camera"
+            }
+        );
+    }
+
+    /// Starts or ends a mouse drag of the orbit camera, if it is on. Left
+    /// drags move the split instead while the split view is shown.
+    pub fn set_orbit_dragging(&mut self, drag: OrbitDrag, dragging: bool) {
+        if drag == OrbitDrag::Orbit && self.split_view.enabled {
+            return;
+        }
+        if let Some(camera) = &mut self.orbit_camera {
+            camera.set_dragging(drag, dragging);
+        }
+    }
+
+    /// Moves the orbit camera `steps` scroll steps towards its target.
+    pub fn dolly_camera(&mut self, steps: f32) {
+        if let Some(camera) = &mut self.orbit_camera {
+            camera.dolly(steps);
+            self.frame_dirty = true;
+        }
+    }
+
+    /// The cursor left or came back into the window; the jump to its next
+    /// position inside isn't a drag.
+    pub fn cursor_crossed_window_edge(&mut self) {
+        if let Some(camera) = &mut self.orbit_camera {
+            camera.forget_cursor();
+        }
+    }
+
+    fn camera_eye(&self) -> Point3<f32> {
+        if let Some(camera) = &self.orbit_camera {
+            return camera.eye();
+        }
+        let offset = CAMERA_EYE.to_vec();
+        let yaw = offset.y.atan2(offset.x) + self.camera_orbit.0;
+        let elevation = ((offset.z / offset.magnitude()).asin() + self.camera_orbit.1)
+            .clamp(-CAMERA_MAX_ELEVATION, CAMERA_MAX_ELEVATION);
+        let distance = offset.magnitude() / self.camera_zoom;
+        Point3::new(
+            distance * elevation.cos() * yaw.cos(),
+            distance * elevation.cos() * yaw.sin(),
+            distance * elevation.sin(),
+        )
+    }
+
+    fn can_read_back(&self) -> bool {
+        self.swapchain
+            .usage
+            .contains(vk::ImageUsageFlags::TRANSFER_SRC)
+    }
+
+    /// Has the next frame read back for `take_capture`. Returns false if the
+    /// swapchain images weren't created with TRANSFER_SRC usage.
+    pub fn request_capture(&mut self) -> bool {
+        if !self.can_read_back() {
+            return false;
+        }
+        if !self.capture_requests.contains(&CaptureTarget::Stream) {
+            self.capture_requests.push(CaptureTarget::Stream);
+        }
+        self.frame_dirty = true;
+        true
+    }
+
+    /// The most recent frame read back for `request_capture`, once the GPU
+    /// has finished it (a frame or two after the request).
+    pub fn take_capture(&mut self) -> Option<CapturedFrame> {
+        self.captured.take()
+    }
+
+    /// Saves the next frame to `screenshot-<n>.png` (or `.qoi`). Encoding
+    /// and writing happen on a background thread.
+    pub fn request_screenshot(&mut self) {
+        if !self.can_read_back() {
+            warn!("The surface doesn't support reading back frames");
+            return;
+        }
+        let extension = if SCREENSHOT_QOI { "qoi" } else { "png" };
+        let path = format!("screenshot-{:04}.{}", self.screenshot_count, extension);
+        self.screenshot_count += 1;
+        self.capture_requests
+            .push(CaptureTarget::Screenshot(PathBuf::from(path)));
+        self.frame_dirty = true;
+    }
+
+    /// Moves queued capture requests onto `frame`, whose command buffer is
+    /// about to be recorded, making sure its readback buffer fits the swapchain.
+    fn assign_capture_requests(&mut self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>) {
+        if self.capture_requests.is_empty() {
+            return;
+        }
+        let (extent, format) = (self.display_extent, self.swapchain.format);
+        if !self.readbacks[frame]
+            .as_ref()
+            .is_some_and(|readback| readback.extent == extent && readback.format == format)
+        {
+            self.destroy_readback_buffer(frame);
+            let size = ReadbackBuffer::size(extent);
+            let created = self.context.create_buffer(
+                ResourceClass::Staging,
+                size,
+                vk::BufferUsageFlags::TRANSFER_DST,
+            );
+            let (buffer, allocation) = match created {
+                Ok(created) => created,
+                Err(error) => {
+                    error!(
+                        "Dropping {} capture requests, no readback buffer: {}",
+                        self.capture_requests.len(),
+                        error
+                    );
+                    self.capture_requests.clear();
+                    return;
+                }
+            };
+            self.resources
+                .register(ResourceKind::Buffer, buffer, "readback buffer", size);
+            self.readbacks[frame] = Some(ReadbackBuffer {
+                buffer,
+                allocation,
+                extent,
+                format,
+                pending: Vec::new(),
+            });
+        }
+        if let Some(readback) = &mut self.readbacks[frame] {
+            readback.pending = std::mem::take(&mut self.capture_requests);
+        }
+    }
+
+    /// Hands out the captures `frame` recorded the last time it was used.
+    /// Its fence must have signalled.
+    fn collect_captures(&mut self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>) {
+        let Some(readback) = &mut self.readbacks[frame] else {
+            return;
+        };
+        if readback.pending.is_empty() {
+            return;
+        }
+        let data = readback.read();
+        for target in std::mem::take(&mut readback.pending) {
+            match target {
+                CaptureTarget::Stream => {
+                    self.captured = Some(CapturedFrame::from_readback(
+                        data.clone(),
+                        readback.extent,
+                        readback.format,
+                    ));
+                }
+                CaptureTarget::Screenshot(path) => {
+                    self.screenshots
+                        .write(path, data.clone(), readback.extent, readback.format);
+                }
+            }
+        }
+    }
+
+    fn captures_pending(&self) -> bool {
+        !self.capture_requests.is_empty()
+            || self.readbacks.iter().any(|readback| {
+                readback
+                    .as_ref()
+                    .is_some_and(|readback| !readback.pending.is_empty())
+            })
+    }
+
+    fn destroy_readback_buffer(&mut self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>) {
+        if let Some(readback) = self.readbacks[frame].take() {
+            self.resources
+                .unregister(ResourceKind::Buffer, readback.buffer);
+            unsafe { self.context.device.destroy_buffer(readback.buffer, None) };
+            self.context.allocator.free(readback.allocation);
+        }
+    }
+
+    /// View and projection in conventional (y up) form; see `gpu_projection`.
+    fn camera_matrices(&self) -> (Matrix4<f32>, Matrix4<f32>) {
+        let view = match &self.orbit_camera {
+            Some(camera) => camera.view(),
+            None => Matrix4::look_at_rh(
+                self.camera_eye(),
+                Point3::new(0.0, 0.0, 0.0),
+                Vector3::new(0.0, 0.0, 1.0),
+            ),
+        };
+        // The scene is never empty, but a 1xN window still makes an extreme
+        // aspect; keep it finite so the matrix does too.
+        let aspect = aspect::projection_aspect(self.scene_rect().extent);
+        let proj = cgmath::perspective(CAMERA_FOV_Y, aspect, CAMERA_NEAR, CAMERA_FAR);
+        (view, proj)
+    }
+
+    /// The projection as uploaded: with depth running `depth_direction`'s
+    /// way, and flipped only when the viewport can't do it.
+    fn gpu_projection(&self, proj: Matrix4<f32>) -> Matrix4<f32> {
+        let proj = self.depth_direction.projection(proj);
+        self.depth_direction
+            .debug_check(proj, CAMERA_NEAR, CAMERA_FAR);
+        if self.capabilities.negative_viewport_height {
+            return proj;
+        }
+        // Negate clip-space y, which off-center projections shift as well as scale.
+        Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0) * proj
+    }
+
+    /// Permutations created up front: everything the scene draws, plus the
+    /// debug views' variants so toggling them doesn't hitch.
+    fn warmup_keys(&self) -> Vec<PipelineKey> {
+        let mut keys = self.required_pipeline_keys();
+        if PREWARM_DEBUG_PIPELINES && !self.split_view.enabled {
+            for faces in self.used_faces() {
+                keys.push(PipelineKey::Cube(ColorInterpolation::NaiveSrgb, faces));
+                if self.capabilities.fill_mode_non_solid {
+                    keys.push(PipelineKey::Wireframe(faces));
+                }
+            }
+        }
+        keys
+    }
+
+    /// Permutations the next frame records with.
+    fn required_pipeline_keys(&self) -> Vec<PipelineKey> {
+        let mut keys = Vec::new();
+        for faces in self.used_faces() {
+            keys.push(PipelineKey::Cube(ColorInterpolation::Linear, faces));
+            if self.split_view.enabled {
+                keys.push(PipelineKey::Cube(ColorInterpolation::NaiveSrgb, faces));
+            }
+            if self.render_mode == RenderMode::Wireframe {
+                keys.push(PipelineKey::Wireframe(faces));
+            }
+            if DEPTH_PREPASS {
+                keys.push(PipelineKey::DepthPrepass(faces));
+            }
+        }
+        keys
+    }
+
+    /// Cull modes the scene draws with. Back-culled permutations are always
+    /// included, as they end every fallback chain.
+    fn used_faces(&self) -> Vec<Faces> {
+        let mut faces = vec![Faces::Front];
+        if self.double_sided.contains(&true) {
+            faces.push(Faces::Both);
+        }
+        faces
+    }
+
+    /// Pipelines for `interpolation`, indexed by `CUBE_PIPELINE` and
+    /// `DOUBLE_SIDED_PIPELINE`. In wireframe every view draws edges, whatever
+    /// its interpolation.
+    fn cube_pipelines(&self, interpolation: ColorInterpolation) -> [vk::Pipeline; 2] {
+        let key = |faces| match self.render_mode {
+            RenderMode::Fill => PipelineKey::Cube(interpolation, faces),
+            RenderMode::Wireframe => PipelineKey::Wireframe(faces),
+        };
+        [
+            self.pipeline(key(Faces::Front)),
+            self.pipeline(key(Faces::Both)),
+        ]
+    }
+
+    fn warm_pipelines(&mut self) {
+        let start = Instant::now();
+        let keys = self.warmup_keys();
+        self.create_missing_pipelines(&keys);
+        self.pipelines.finish_warmup(start.elapsed());
+    }
+
+    fn create_missing_pipelines(&mut self, keys: &[PipelineKey]) {
+        let missing = self.pipelines.missing(keys);
+        if missing.is_empty() {
+            return;
+        }
+        let pipelines = Self::create_graphics_pipeline(
+            &self.context.device,
+            self.pass_target(),
+            self.pipeline_layout,
+            self.draw_packing,
+            self.needs_manual_srgb,
+            self.cube_shaders.as_deref(),
+            &missing,
+        );
+        self.add_pipelines(&missing, &pipelines);
+    }
+
+    fn add_pipelines(&mut self, keys: &[PipelineKey], pipelines: &[vk::Pipeline]) {
+        for (&pipeline, key) in pipelines.iter().zip(keys) {
+            self.resources.register(
+                ResourceKind::Pipeline,
+                pipeline,
+                &format!("{:?} pipeline", key),
+                0,
+            );
+        }
+        self.pipelines.insert(keys, pipelines);
+    }
+
+    /// Schedules a worker-thread build for any of `keys` not yet available.
+    /// Only one batch is in flight at a time; later requests are queued.
+    fn request_pipelines(&mut self, keys: &[PipelineKey]) {
+        let unscheduled = self.pipelines.unscheduled(keys);
+        if unscheduled.is_empty() {
+            return;
+        }
+        if self.pipelines.is_building() {
+            self.pipelines.queue(&unscheduled);
+            return;
+        }
+
+        let device = self.context.device.clone();
+        let target = self.pass_target();
+        let pipeline_layout = self.pipeline_layout;
+        let draw_packing = self.draw_packing;
+        let manual_srgb = self.needs_manual_srgb;
+        let cube_shaders = self.cube_shaders.clone();
+        let build_keys = unscheduled.clone();
+        let handle = std::thread::spawn(move || {
+            let start = Instant::now();
+            let pipelines = Self::create_graphics_pipeline(
+                &device,
+                target,
+                pipeline_layout,
+                draw_packing,
+                manual_srgb,
+                cube_shaders.as_deref(),
+                &build_keys,
+            );
+            (pipelines, start.elapsed())
+        });
+        self.pipelines.start_build(unscheduled, handle);
+    }
+
+    /// Swaps in a finished worker build, then starts the next queued batch.
+    /// Called at the frame boundary, before the command buffer is recorded.
+    fn poll_pipeline_builds(&mut self) {
+        let Some((keys, (pipelines, elapsed))) = self.pipelines.finished_build() else {
+            return;
+        };
+        info!(
+            "Built {:?} on a worker thread in {:.1} ms",
+            keys,
+            elapsed.as_secs_f64() * 1000.0
+        );
+        self.add_pipelines(&keys, &pipelines);
+        let queued = self.pipelines.take_queued();
+        self.request_pipelines(&queued);
+    }
+
+    /// The pipeline for `key`, or the first available fallback while `key`
+    /// is still building.
+    fn pipeline(&self, key: PipelineKey) -> vk::Pipeline {
+        let mut candidate = Some(key);
+        while let Some(current) = candidate {
+            if let Some(pipeline) = self.pipelines.get(current) {
+                return pipeline;
+            }
+            candidate = current.fallback();
+        }
+        panic!("{:?} pipeline was not created before recording", key)
+    }
+
+    /// One fragment-invocation query per frame in flight.
+    fn create_statistics_queries(
+        device: &ash::Device,
+    ) -> FrameResources<vk::QueryPool, MAX_FRAMES_IN_FLIGHT> {
+        let query_pool_info = vk::QueryPoolCreateInfo::builder()
+            .query_type(vk::QueryType::PIPELINE_STATISTICS)
+            .query_count(1)
+            .pipeline_statistics(vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS);
+        FrameResources::new(std::array::from_fn(|_| unsafe {
+            device.create_query_pool(&query_pool_info, None).unwrap()
+        }))
+    }
+
+    /// The query pools are only read once frames are being recorded, so they
+    /// are created on first use instead of during startup.
+    fn ensure_statistics_queries(&mut self) {
+        if self.statistics_queries.is_none() && self.capabilities.pipeline_statistics_query {
+            self.statistics_queries = Some(Self::create_statistics_queries(&self.context.device));
+        }
+    }
+
+    /// The rayon workers and their command pools start with the first draw
+    /// list long enough to use them, so scenes of a few draws never spawn
+    /// threads.
+    fn ensure_record_pools(&mut self) {
+        if self.record_pools.is_none()
+            && self.config.parallel_recording
+            && self.draw_list.records().len() >= PARALLEL_RECORD_MIN_DRAWS
+        {
+            self.record_pools = Some(ThreadLocalPools::new(
+                &self.context.device,
+                self.context.queue_family_indices.graphics_family.unwrap(),
+            ));
+        }
+    }
+
+    fn log_fragment_invocations(&self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>) {
+        let Some(queries) = &self.statistics_queries else {
+            return;
+        };
+        let mut invocations = [0u64];
+        // NOT_READY before the frame's first submission; nothing to report yet.
+        let result = unsafe {
+            self.context.device.get_query_pool_results(
+                queries[frame],
+                0,
+                1,
+                &mut invocations,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        };
+        if result.is_ok() {
+            info!(
+                "Fragment shader invocations: {} (depth pre-pass {})",
+                invocations[0],
+                if DEPTH_PREPASS { "on" } else { "off" }
+            );
+        }
+    }
+
+    fn create_frame_resources(
+        device: &ash::Device,
+        command_pool: vk::CommandPool,
+    ) -> FrameResources<FrameSync, MAX_FRAMES_IN_FLIGHT> {
+        let command_buffers =
+            Self::create_command_buffers(device, command_pool, MAX_FRAMES_IN_FLIGHT);
+        let semaphore_info = vk::SemaphoreCreateInfo::builder();
+        let fence_info = vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED);
+
+        FrameResources::new(std::array::from_fn(|i| unsafe {
+            FrameSync {
+                image_available_semaphore: device.create_semaphore(&semaphore_info, None).unwrap(),
+                render_finished_semaphore: device.create_semaphore(&semaphore_info, None).unwrap(),
+                in_flight_fence: device.create_fence(&fence_info, None).unwrap(),
+                command_buffer: command_buffers[i],
+            }
+        }))
+    }
+
+    /// Destroys the swapchain and what is sized by its extent or tied to
+    /// its images. The render pass and pipelines outlive it, since viewport
+    /// and scissor are dynamic, and so do the uniform buffers and their
+    /// pool, which are per frame in flight; `destroy_uniform_buffers` has
+    /// those.
+    fn cleanup_swapchain(&mut self) {
+        self.swapchain.destroy_framebuffers(&self.context.device);
+        self.cleanup_attachments();
+        self.swapchain.destroy(&self.context, &mut self.resources);
+    }
+
+    /// One scaled target per swapchain image, if `upscaling`.
+    fn create_scaled_targets(&mut self) -> Result<(), VulkanError> {
+        if !self.upscaling() {
+            return Ok(());
+        }
+        for _ in 0..self.swapchain.images.len() {
+            let target = ScaledTarget::new(
+                &self.context,
+                self.swapchain.format,
+                self.render_extent,
+                self.swapchain.usage,
+                &mut self.resources,
+            )?;
+            self.scaled_targets.push(target);
+        }
+        Ok(())
+    }
+
+    /// The A/B split's right half at its scale of the window, with
+    /// `set_split_scale`. Without the memory for it the split is turned off
+    /// rather than the attachments shrunk.
+    fn create_comparison_target(&mut self) {
+        let Some(percent) = self.split_percent.filter(|_| self.can_upscale) else {
+            return;
+        };
+        let extent = self
+            .capabilities
+            .limits
+            .render_extent(render_scale::scaled_extent(self.display_extent, percent));
+        let render_pass = Self::create_extra_render_pass(
+            &self.context.device,
+            self.swapchain.format,
+            self.depth_format,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        );
+        let comparison = ComparisonTarget::new(
+            &self.context,
+            percent,
+            extent,
+            self.swapchain.format,
+            self.depth_format,
+            render_pass,
+            &mut self.resources,
+        );
+        let mut comparison = match comparison {
+            Ok(comparison) => comparison,
+            Err(error) => {
+                unsafe { self.context.device.destroy_render_pass(render_pass, None) };
+                warn!("No A/B split: {}", error);
+                self.split_percent = None;
+                return;
+            }
+        };
+        let target = PassTarget {
+            render_pass,
+            color_format: self.swapchain.format,
+            depth_format: self.depth_format,
+            samples: vk::SampleCountFlags::TYPE_1,
+            depth_direction: self.depth_direction,
+        };
+        let pipelines = Self::create_graphics_pipeline(
+            &self.context.device,
+            target,
+            self.pipeline_layout,
+            self.draw_packing,
+            self.needs_manual_srgb,
+            self.cube_shaders.as_deref(),
+            &[Faces::Front, Faces::Both]
+                .map(|faces| PipelineKey::Cube(ColorInterpolation::Linear, faces)),
+        );
+        for &pipeline in &pipelines {
+            self.resources
+                .register(ResourceKind::Pipeline, pipeline, "comparison pipeline", 0);
+        }
+        comparison.pipelines = [pipelines[0], pipelines[1]];
+        self.comparison = Some(comparison);
+    }
+
+    fn destroy_comparison_target(&mut self) {
+        if let Some(comparison) = self.comparison.take() {
+            comparison.destroy(
+                &self.context.device,
+                &self.context.allocator,
+                &mut self.resources,
+            );
+        }
+    }
+
+    /// The post-process pass's targets for the render extent, one per
+    /// swapchain image, if it has one.
+    #[cfg(feature = "post-processing")]
+    fn create_post_targets(&mut self) -> Result<(), VulkanError> {
+        let Some(post) = &mut self.post else {
+            return Ok(());
+        };
+        post.create_targets(
+            &self.context,
+            self.swapchain.format,
+            self.render_extent,
+            self.swapchain.images.len(),
+            self.swapchain.generation,
+            &mut self.resources,
+        )
+    }
+
+    #[cfg(not(feature = "post-processing"))]
+    fn create_post_targets(&mut self) -> Result<(), VulkanError> {
+        Ok(())
+    }
+
+    /// The ID pass's target for the render extent, with `set_picking`.
+    fn create_pick_target(&mut self) -> Result<(), VulkanError> {
+        let Some(picking) = &mut self.picking else {
+            return Ok(());
+        };
+        picking.create_target(
+            &self.context,
+            self.depth_format,
+            self.render_extent,
+            &mut self.resources,
+        )
+    }
+
+    /// Whether the render pass ends in the post-process subpass.
+    #[cfg(feature = "post-processing")]
+    fn post_processing(&self) -> bool {
+        self.post.is_some()
+    }
+
+    #[cfg(not(feature = "post-processing"))]
+    fn post_processing(&self) -> bool {
+        false
+    }
+
+    /// Empty without post-processing.
+    #[cfg(feature = "post-processing")]
+    fn post_target_views(&self) -> Vec<vk::ImageView> {
+        self.post
+            .as_ref()
+            .map(PostPass::target_views)
+            .unwrap_or_default()
+    }
+
+    #[cfg(not(feature = "post-processing"))]
+    fn post_target_views(&self) -> Vec<vk::ImageView> {
+        Vec::new()
+    }
+
+    /// The depth and colour attachments, and any scaled, post-process and
+    /// comparison targets.
+    fn cleanup_attachments(&mut self) {
+        self.destroy_comparison_target();
+        for target in self.scaled_targets.drain(..) {
+            target.destroy(
+                &self.context.device,
+                &self.context.allocator,
+                &mut self.resources,
+            );
+        }
+        #[cfg(feature = "post-processing")]
+        if let Some(post) = &mut self.post {
+            post.destroy_targets(
+                &self.context.device,
+                &self.context.allocator,
+                &mut self.resources,
+            );
+        }
+        if let Some(picking) = &mut self.picking {
+            picking.destroy_target(
+                &self.context.device,
+                &self.context.allocator,
+                &mut self.resources,
+            );
+        }
+        self.swapchain
+            .destroy_depth(&self.context, &mut self.resources);
+        unsafe {
+            if let Some((image, allocation, view)) = self.color_image.take() {
+                self.resources.unregister(ResourceKind::ImageView, view);
+                self.context.device.destroy_image_view(view, None);
+                self.resources.unregister(ResourceKind::Image, image);
+                self.context.device.destroy_image(image, None);
+                self.context.allocator.free(allocation);
+            }
+        }
+    }
+
+    /// Every pipeline built against the render pass. An in-flight build
+    /// targets it too; its results are discarded.
+    fn destroy_pipelines(&mut self) {
+        unsafe {
+            for pipeline in self.pipelines.cancel_build() {
+                self.context.device.destroy_pipeline(pipeline, None);
+            }
+            for (_, pipeline) in self.pipelines.drain() {
+                self.resources.unregister(ResourceKind::Pipeline, pipeline);
+                self.context.device.destroy_pipeline(pipeline, None);
+            }
+            if let Some(pipeline) = self.background_pipeline.take() {
+                self.resources.unregister(ResourceKind::Pipeline, pipeline);
+                self.context.device.destroy_pipeline(pipeline, None);
+            }
+            #[cfg(feature = "overlay")]
+            {
+                self.resources
+                    .unregister(ResourceKind::Pipeline, self.graph_pipeline);
+                self.context
+                    .device
+                    .destroy_pipeline(self.graph_pipeline, None);
+                self.resources
+                    .unregister(ResourceKind::Pipeline, self.hud_pipeline);
+                self.context
+                    .device
+                    .destroy_pipeline(self.hud_pipeline, None);
+            }
+        }
+    }
+
+    /// A new render pass for `format` and `msaa_samples`, and the pipelines
+    /// built against it. Only needed when the surface format or the sample
+    /// count changes; resizes keep both. On the dynamic rendering path only
+    /// the pipelines are rebuilt.
+    fn recreate_render_pass(&mut self, format: vk::Format) {
+        self.destroy_pipelines();
+        self.swapchain.format = format;
+        if self.dynamic_rendering.is_none() {
+            unsafe {
+                self.context
+                    .device
+                    .destroy_render_pass(self.render_pass, None)
+            };
+            self.render_pass = Self::create_render_pass(
+                &self.context.device,
+                format,
+                self.depth_format,
+                self.msaa_samples,
+                self.config.final_layout,
+                !self.config.background.covers_screen(),
+                false,
+                self.post_processing(),
+            );
+        }
+        #[cfg(feature = "post-processing")]
+        if let Some(post) = &mut self.post {
+            self.resources
+                .unregister(ResourceKind::Pipeline, post.pipeline);
+            unsafe { self.context.device.destroy_pipeline(post.pipeline, None) };
+            post.pipeline = Self::create_post_pipeline(
+                &self.context.device,
+                self.render_pass,
+                post.layout,
+                &mut self.resources,
+            );
+        }
+        self.warm_pipelines();
+        self.background_pipeline = Self::create_background_pipeline(
+            &self.context.device,
+            self.pass_target(),
+            self.pipeline_layout,
+            self.config.background,
+            self.needs_manual_srgb,
+            &mut self.resources,
+        );
+        #[cfg(feature = "overlay")]
+        {
+            self.graph_pipeline = Self::create_graph_pipeline(
+                &self.context.device,
+                self.pass_target(),
+                self.graph_layout,
+                self.needs_manual_srgb,
+                &mut self.resources,
+            );
+            self.hud_pipeline = Self::create_hud_pipeline(
+                &self.context.device,
+                self.pass_target(),
+                self.hud_layout,
+                self.needs_manual_srgb,
+                &mut self.resources,
+            );
+        }
+    }
+
+    fn recreate_swapchain(
+        &mut self,
+        window: &winit::window::Window,
+        reason: RecreateReason,
+    ) -> Result<(), VulkanError> {
+        let size = window.inner_size();
+        if size.width == 0 || size.height == 0 {
+            // Minimized: a swapchain can't be empty. Keep the old one until
+            // the window has a size again.
+            self.framebuffer_resized = true;
+            self.suspended = true;
+            return Ok(());
+        }
+        let started = Instant::now();
+        unsafe { self.context.device.device_wait_idle() }
+            .map_err(VulkanError::at(Stage::SwapchainRecreation))?;
+        // Their pipelines follow the settings too.
+        for extra in &mut self.extra_windows {
+            extra.stale = true;
+        }
+        // Rebuilt below for the new depth image, before anything can draw.
+        let depth_view = self.depth_view.take();
+        let show_depth = depth_view.is_some();
+        if let Some(view) = depth_view {
+            self.destroy_depth_view(view);
+        }
+        self.swapchain.destroy_framebuffers(&self.context.device);
+        self.cleanup_attachments();
+        self.frame_dirty = true;
+        let old_format = self.swapchain.format;
+        self.swapchain.recreate(
+            &mut self.context,
+            window,
+            self.effective.present_mode,
+            self.config.swapchain_usage,
+            reason,
+            &mut self.resources,
+        )?;
+        let (swapchain_format, swapchain_extent) = (self.swapchain.format, self.swapchain.extent);
+        let msaa_samples = vk::SampleCountFlags::from_raw(self.effective.msaa_samples);
+        // Before the render pass and its pipelines are rebuilt for the format.
+        self.needs_manual_srgb = swapchain_support::is_unorm_format(swapchain_format);
+        if swapchain_format != old_format || msaa_samples != self.msaa_samples {
+            self.msaa_samples = msaa_samples;
+            self.recreate_render_pass(swapchain_format);
+        }
+        self.can_upscale = Self::can_upscale(&self.context, swapchain_format, self.swapchain.usage);
+        self.display_extent = Self::display_extent(&self.capabilities, swapchain_extent);
+        self.render_extent = self.scaled_render_extent();
+        self.journal.record(JournalEvent::SwapchainRecreated {
+            reason,
+            extent: swapchain_extent,
+        });
+        self.create_attachments()?;
+        self.create_framebuffers();
+        if show_depth {
+            self.depth_view = Some(self.create_depth_view());
+        }
+        // The uniform buffers and their descriptor sets are per frame in
+        // flight, not per image or sized by the extent, so recreation keeps
+        // them.
+        info!(
+            "Swapchain {} recreated at {}x{} ({:?}) in {:.1} ms",
+            self.swapchain.generation,
+            swapchain_extent.width,
+            swapchain_extent.height,
+            reason,
+            started.elapsed().as_secs_f64() * 1000.0,
+        );
+        Ok(())
+    }
+
+    /// The depth and colour attachments for the render extent, below full
+    /// render scale the scaled targets, and any post-process targets. Short
+    /// of memory for them, the render extent is halved until they fit, down
+    /// to MIN_RENDER_EXTENT a side, so a resize doesn't end the program.
+    fn create_attachments(&mut self) -> Result<(), VulkanError> {
+        loop {
+            let depth = self.swapchain.create_depth(
+                &self.context,
+                self.command_pool,
+                self.render_extent,
+                self.msaa_samples,
+                &mut self.resources,
+            );
+            let error = match depth {
+                Ok(()) => {
+                    let color_image = Self::create_color_resources(
+                        &self.context,
+                        self.swapchain.format,
+                        self.render_extent,
+                        self.msaa_samples,
+                        &mut self.resources,
+                    );
+                    let targets = color_image
+                        .and_then(|color_image| {
+                            self.color_image = color_image;
+                            self.create_scaled_targets()
+                        })
+                        .and_then(|()| self.create_post_targets())
+                        .and_then(|()| self.create_pick_target());
+                    match targets {
+                        Ok(()) => {
+                            self.create_comparison_target();
+                            return Ok(());
+                        }
+                        Err(error) => {
+                            self.cleanup_attachments();
+                            error
+                        }
+                    }
+                }
+                Err(error) => error,
+            };
+            let extent = self.render_extent;
+            if !matches!(error, VulkanError::OutOfMemory { .. })
+                || extent.width.min(extent.height) / 2 < MIN_RENDER_EXTENT
+            {
+                return Err(error);
+            }
+            self.render_extent = vk::Extent2D {
+                width: extent.width / 2,
+                height: extent.height / 2,
+            };
+            warn!(
+                "{} for the {}x{} attachments; rendering at {}x{}",
+                error,
+                extent.width,
+                extent.height,
+                self.render_extent.width,
+                self.render_extent.height
+            );
+        }
+    }
+
+    /// The uniform buffers, their descriptor pool and their sets, again for
+    /// the current draw slot capacity. Only while the device is idle. If
+    /// the new buffers can't be allocated, the old ones are kept.
+    fn recreate_uniform_buffers(&mut self) -> Result<(), VulkanError> {
+        let uniform_buffers = Self::create_uniform_buffers(
+            &self.context,
+            self.draw_slot_capacity,
+            self.draw_packing.slot_stride(),
+            &mut self.resources,
+        )?;
+        self.destroy_uniform_buffers();
+        self.uniform_buffers = uniform_buffers;
+        self.descriptor_pool = Self::create_descriptor_pool(
+            &self.context.device,
+            self.draw_packing.uses_uniform_slot(),
+            &mut self.resources,
+        );
+        (self.descriptor_sets, self.view_descriptor_sets) = Self::create_descriptor_sets(
+            &self.context.device,
+            self.descriptor_pool,
+            self.descriptor_set_layout,
+            self.view_set_layout,
+            &self.uniform_buffers,
+            self.draw_packing.uses_uniform_slot(),
+        );
+        // The last frame's view went with them.
+        self.last_frame = None;
+        Ok(())
+    }
+
+    /// The descriptor pool, which frees the sets with it, and the uniform
+    /// buffers. Only while the device is idle.
+    fn destroy_uniform_buffers(&mut self) {
+        self.resources
+            .unregister(ResourceKind::DescriptorPool, self.descriptor_pool);
+        unsafe {
+            self.context
+                .device
+                .destroy_descriptor_pool(self.descriptor_pool, None)
+        };
+        for uniform_buffer in self.uniform_buffers.iter() {
+            self.resources
+                .unregister(ResourceKind::Buffer, uniform_buffer.buffer);
+            uniform_buffer.destroy(&self.context.device, &self.context.allocator);
+        }
+    }
+
+    /// Grows the draw slots to the next power of two once the draw list
+    /// has more draws than slots, so every draw keeps its own. Frames in
+    /// flight still read the old buffers, so this waits for the device.
+    fn ensure_draw_slots(&mut self) -> Result<(), VulkanError> {
+        let draws = self.draw_list.records().len();
+        if !self.draw_packing.uses_uniform_slot() || draws <= self.draw_slot_capacity {
+            return Ok(());
+        }
+        unsafe { self.context.device.device_wait_idle() }
+            .map_err(VulkanError::at(Stage::Submit))?;
+        let capacity = self.draw_slot_capacity;
+        self.draw_slot_capacity = draws.next_power_of_two();
+        if let Err(error) = self.recreate_uniform_buffers() {
+            self.draw_slot_capacity = capacity;
+            return Err(error);
+        }
+        info!("Draw slots grown to {} per frame", self.draw_slot_capacity);
+        Ok(())
+    }
+
+    /// Waits for `fence`, writing the watchdog's report each time the wait
+    /// times out. Fails with `GpuHang` once the retry has timed out too, and
+    /// with `DeviceLost` if the device is lost while waiting.
+    fn wait_for_in_flight_fence(&self, fence: vk::Fence) -> Result<(), VulkanError> {
+        let mut attempt = 1;
+        loop {
+            let result = unsafe {
+                self.context.device.wait_for_fences(
+                    std::slice::from_ref(&fence),
+                    true,
+                    self.watchdog.timeout_ns(),
+                )
+            };
+
+            match result {
+                Ok(()) => return Ok(()),
+                Err(vk::Result::TIMEOUT) => {
+                    let report = self.watchdog.dump_report(
+                        self.context.graphics_queue,
+                        &self.validation_log,
+                        &self.journal,
+                        attempt,
+                    );
+                    eprint!("{}", report);
+                    if attempt > 1 {
+                        return Err(VulkanError::GpuHang(Stage::Submit));
+                    }
+                    attempt += 1;
+                }
+                Err(error) => return Err(VulkanError::at(Stage::Submit)(error)),
+            }
+        }
+    }
+
+    /// Draws a frame from the renderer's own state. Built on `begin_frame`.
+    pub fn draw_frame(&mut self, window: &winit::window::Window) -> Result<(), VulkanError> {
+        let now = Instant::now();
+        // On demand, the last frame may have been minutes ago.
+        let elapsed = self
+            .last_camera_update
+            .replace(now)
+            .map_or(0.0, |last| (now - last).as_secs_f32())
+            .min(MAX_FRAME_DELTA_SECONDS);
+        if let Some(camera) = &mut self.orbit_camera {
+            if camera.update(elapsed) {
+                self.frame_dirty = true;
+            }
+        }
+        if self.is_suspended() || !self.needs_redraw() {
+            // The gap until the next frame drawn belongs to no frame rate,
+            // and would read as one long frame to the render scaler.
+            self.last_frame_start = None;
+            return Ok(());
+        }
+        // Skipped before `begin_frame`, so no fence is waited on or image
+        // acquired for a frame that isn't drawn.
+        if self.unfocused {
+            let now = Instant::now();
+            if now < self.next_throttled_frame {
+                return Ok(());
+            }
+            self.next_throttled_frame = (self.next_throttled_frame
+                + Duration::from_millis(UNFOCUSED_FRAME_INTERVAL_MS))
+            .max(now);
+        }
+        match self.begin_frame(window)? {
+            Some(frame) => frame.render_and_present(),
+            None => Ok(()),
+        }
+    }
+
+    /// Waits until the next frame in flight is free and acquires a swapchain
+    /// image for it. The returned context holds this frame's uniforms, filled
+    /// in from the renderer's state, for the caller to change before
+    /// `FrameCtx::render_and_present`. Returns `None` when the swapchain was
+    /// out of date and had to be recreated; try again next frame.
+    pub fn begin_frame<'a>(
+        &'a mut self,
+        window: &'a winit::window::Window,
+    ) -> Result<Option<FrameCtx<'a>>, VulkanError> {
+        let frame = self.frames[self.current_frame];
+        let frame_start = Instant::now();
+        let frame_ms = self.last_frame_start.replace(frame_start).map(|last| {
+            self.frame_stats.push(frame_start - last, self.unfocused);
+            #[cfg(feature = "overlay")]
+            self.frame_times.push(frame_start - last);
+            (frame_start - last).as_secs_f32() * 1000.0
+        });
+        self.wait_for_in_flight_fence(frame.in_flight_fence)?;
+        let gpu_ms = self
+            .gpu_timer
+            .as_mut()
+            .and_then(|timer| timer.read(&self.context.device, self.current_frame));
+        if let Some(gpu_ms) = gpu_ms {
+            self.frame_stats.push_gpu_ms(gpu_ms);
+            self.scale_times.set_scale(
+                self.split_percent
+                    .is_none()
+                    .then(|| self.render_scale_percent()),
+            );
+            self.scale_times.push(gpu_ms);
+        }
+        // The GPU's time where it has timestamps, which vsync doesn't pad.
+        // Throttled frames say nothing about the renderer's speed.
+        let measured_ms = if self.gpu_timer.is_some() {
+            gpu_ms
+        } else {
+            frame_ms
+        };
+        if let Some(ms) = measured_ms.filter(|_| self.can_upscale && !self.unfocused) {
+            self.render_scale_changed |= self.render_scaler.push(ms).is_some();
+        }
+        if std::mem::take(&mut self.render_scale_changed) {
+            self.apply_render_scale()?;
+        }
+        self.collect_captures(self.current_frame);
+        if self.frame_count.is_multiple_of(STATISTICS_LOG_INTERVAL) {
+            self.log_fragment_invocations(self.current_frame);
+        }
+        let result = unsafe {
+            self.swapchain.loader.acquire_next_image(
+                self.swapchain.handle,
+                u64::MAX,
+                frame.image_available_semaphore,
+                vk::Fence::null(),
+            )
+        };
+
+        let image_index = match result {
+            Ok((image_index, is_suboptimal)) => {
+                if is_suboptimal {
+                    self.journal
+                        .record(JournalEvent::Acquire(vk::Result::SUBOPTIMAL_KHR));
+                    self.framebuffer_resized = true;
+                }
+                image_index
+            }
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                self.journal
+                    .record(JournalEvent::Acquire(vk::Result::ERROR_OUT_OF_DATE_KHR));
+                self.recreate_swapchain(window, RecreateReason::OutOfDate)?;
+                return Ok(None);
+            }
+            Err(vk::Result::ERROR_SURFACE_LOST_KHR) => {
+                self.journal
+                    .record(JournalEvent::Acquire(vk::Result::ERROR_SURFACE_LOST_KHR));
+                self.recreate_swapchain(window, RecreateReason::SurfaceLost)?;
+                return Ok(None);
+            }
+            Err(error) => {
+                self.journal.record(JournalEvent::Acquire(error));
+                return Err(VulkanError::at(Stage::Acquire)(error));
+            }
+        };
+
+        let uniforms = self.frame_uniforms();
+        Ok(Some(FrameCtx {
+            app: self,
+            window,
+            image_index,
+            uniforms,
+            presented: false,
+        }))
+    }
+
+    /// Where the scene's animation is now, for a headless renderer kept
+    /// running in real time to pass to `render_to_image`. Pausing stops it.
+    pub fn animation_time(&self) -> f32 {
+        self.frame_uniforms().animation_time
+    }
+
+    /// This frame's uniforms as the renderer would set them.
+    fn frame_uniforms(&self) -> FrameUniforms {
+        FrameUniforms {
+            frame: self.frame_data(),
+            views: std::array::from_fn(|view_index| self.view_data(view_index)),
+            animation_time: self
+                .model_rotation
+                .map_or_else(|| self.clock.time(), |rotation| rotation.spin_time),
+            clear_color: match self.sun_state() {
+                Some(sun) => [sun.sky[0], sun.sky[1], sun.sky[2], 1.0],
+                None if self.clear_color_cycle => {
+                    let [r, g, b] = hue_cycle(self.clock.time());
+                    [r, g, b, 1.0]
+                }
+                None => self.clear_color,
+            },
+        }
+    }
+
+    /// Draws the windows on other devices and submits them on each
+    /// device's queue, once the scene's meshes, if changed, and this
+    /// frame's transforms are copied over. A device that fails has its
+    /// windows closed rather than failing the frame.
+    fn submit_device_windows(&mut self, uniforms: &FrameUniforms) {
+        if self.render_contexts.is_empty() {
+            return;
+        }
+        let frame = self.current_frame;
+        let transforms: Vec<_> = self
+            .animator
+            .objects
+            .iter()
+            .map(|object| object.evaluate_columns(uniforms.animation_time))
+            .collect();
+        let mut failed = Vec::new();
+        for index in 0..self.render_contexts.len() {
+            let render_context = &mut self.render_contexts[index];
+            let copied = render_context
+                .mirror_meshes(&self.mesh_pool)
+                .and_then(|()| render_context.write_transforms(frame, &transforms));
+            let render_context = &self.render_contexts[index];
+            let windows: Vec<&ExtraWindow> = self
+                .extra_windows
+                .iter()
+                .filter(|extra| extra.device == Some(index))
+                .collect();
+            let submitted = copied.and_then(|()| {
+                render_context.submit(frame, &windows, |command_buffer| {
+                    for extra in &windows {
+                        self.record_device_window(
+                            render_context,
+                            command_buffer,
+                            frame,
+                            uniforms.clear_color,
+                            extra,
+                        );
+                    }
+                })
+            });
+            if let Err(error) = submitted {
+                warn!(
+                    "Closing the windows on a GPU that couldn't draw them: {}",
+                    error
+                );
+                failed.push(index);
+            }
+        }
+        for index in failed {
+            let (closing, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.extra_windows)
+                .into_iter()
+                .partition(|extra| extra.device == Some(index));
+            self.extra_windows = kept;
+            for extra in closing {
+                self.destroy_extra_window(extra);
+            }
+        }
+    }
+
+    fn sun_state(&self) -> Option<SunState> {
+        self.config.sun.map(|sun| sun.at(self.sun_clock.time()))
+    }
+
+    /// Submits, then presents, the frame `begin_frame` acquired
+    /// `image_index` for.
+    fn finish_frame(
+        &mut self,
+        window: &winit::window::Window,
+        image_index: u32,
+        uniforms: &FrameUniforms,
+    ) -> Result<(), VulkanError> {
+        let frame_index = self.current_frame;
+        let frame = self.frames[frame_index];
+        self.acquire_extra_windows();
+        self.submit_frame(image_index, uniforms, true)?;
+        self.submit_device_windows(uniforms);
+        unsafe {
+            let swapchains = [self.swapchain.handle];
+            let present_info = vk::PresentInfoKHR::builder()
+                .wait_semaphores(std::slice::from_ref(&frame.render_finished_semaphore))
+                .swapchains(&swapchains)
+                .image_indices(std::slice::from_ref(&image_index));
+
+            let result = self
+                .swapchain_loader
+                .queue_present(self.context.present_queue, &present_info);
+            // Every window is presented before the main result is handled,
+            // and one that fails is closed, so the main window's recreation
+            // is never skipped on its account.
+            let mut failed = Vec::new();
+            for (index, extra) in self.extra_windows.iter_mut().enumerate() {
+                let (swapchain_loader, present_queue) = match extra.device {
+                    Some(device) => {
+                        let render_context = &self.render_contexts[device];
+                        (
+                            &render_context.swapchain_loader,
+                            render_context.context.present_queue,
+                        )
+                    }
+                    None => (&self.swapchain.loader, self.context.present_queue),
+                };
+                if let Err(error) = extra.present(swapchain_loader, present_queue, frame_index) {
+                    warn!("Closing a window that couldn't be presented: {}", error);
+                    failed.push(index);
+                }
+            }
+            for index in failed.into_iter().rev() {
+                let extra = self.extra_windows.remove(index);
+                self.destroy_extra_window(extra);
+            }
+
+            let mut recreate_reason = None;
+            match result {
+                Ok(false) => {}
+                Ok(true) | Err(vk::Result::SUBOPTIMAL_KHR) => {
+                    self.journal
+                        .record(JournalEvent::Present(vk::Result::SUBOPTIMAL_KHR));
+                    recreate_reason = Some(RecreateReason::Suboptimal);
+                }
+                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                    self.journal
+                        .record(JournalEvent::Present(vk::Result::ERROR_OUT_OF_DATE_KHR));
+                    recreate_reason = Some(RecreateReason::OutOfDate);
+                }
+                Err(vk::Result::ERROR_SURFACE_LOST_KHR) => {
+                    self.journal
+                        .record(JournalEvent::Present(vk::Result::ERROR_SURFACE_LOST_KHR));
+                    recreate_reason = Some(RecreateReason::SurfaceLost);
+                }
+                Err(error) => {
+                    self.journal.record(JournalEvent::Present(error));
+                    return Err(VulkanError::at(Stage::Present)(error));
+                }
+            }
+
+            if self.framebuffer_resized && recreate_reason.is_none() {
+                recreate_reason = Some(RecreateReason::WindowResized);
+            }
+            if self.settings_rebuild && recreate_reason.is_none() {
+                recreate_reason = Some(RecreateReason::SettingsChanged);
+            }
+            if let Some(reason) = recreate_reason {
+                self.framebuffer_resized = false;
+                self.settings_rebuild = false;
+                self.recreate_swapchain(window, reason)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders one frame into the offscreen image with the animation at
+    /// `time` seconds, and waits for the GPU to read it back. Only for a
+    /// renderer from `new_headless`.
+    pub fn render_to_image(&mut self, time: f32) -> Result<CapturedFrame, VulkanError> {
+        assert!(
+            self.context.surface.is_none(),
+            "render_to_image needs a renderer from new_headless"
+        );
+        let frame = self.current_frame;
+        self.wait_for_in_flight_fence(self.frames[frame].in_flight_fence)?;
+        self.capture_requests.push(CaptureTarget::Stream);
+        let mut uniforms = self.frame_uniforms();
+        uniforms.animation_time = time;
+        self.submit_frame(0, &uniforms, false)?;
+        self.wait_for_in_flight_fence(self.frames[frame].in_flight_fence)?;
+        self.collect_captures(frame);
+        Ok(self
+            .captured
+            .take()
+            .expect("the frame was read back once its fence signalled"))
+    }
+
+    /// Writes `uniforms`, then records and submits a frame into
+    /// `image_index`. With `presenting` the submission waits for the image
+    /// to be acquired and signals the semaphore `queue_present` waits on.
+    fn submit_frame(
+        &mut self,
+        image_index: u32,
+        uniforms: &FrameUniforms,
+        presenting: bool,
+    ) -> Result<(), VulkanError> {
+        let frame = self.frames[self.current_frame];
+        // This frame's own fence was waited on before the image was acquired.
+        let image_fence = self.swapchain.images_in_flight[image_index as usize];
+        if image_fence != vk::Fence::null() && image_fence != frame.in_flight_fence {
+            self.wait_for_in_flight_fence(image_fence)?;
+        }
+        self.swapchain.images_in_flight[image_index as usize] = frame.in_flight_fence;
+        let animation_time = uniforms.animation_time;
+        // Before anything is written, since more draws than slots means new
+        // uniform buffers.
+        self.build_draw_list(animation_time);
+        self.ensure_draw_slots()?;
+        self.write_uniform_block(self.current_frame, PER_FRAME_OFFSET, uniforms.frame);
+        for (view_index, view) in uniforms.views.iter().enumerate() {
+            self.write_uniform_block(self.current_frame, per_view_offset(view_index), *view);
+        }
+        for extra in &self.extra_windows {
+            extra.write_view(self.current_frame, self.extra_view_data(extra));
+        }
+        #[cfg(feature = "overlay")]
+        if self.graph_visible {
+            self.graph_buffers[self.current_frame].update(&self.frame_times, self.display_extent);
+        }
+        #[cfg(feature = "overlay")]
+        if self.hud.is_visible() {
+            let vertices = self.hud.layout(
+                &self.frame_stats,
+                self.swapchain.extent,
+                self.display_extent,
+                self.render_extent,
+            );
+            self.hud_buffers[self.current_frame].update(
+                &self.context,
+                vertices,
+                &mut self.resources,
+            );
+        }
+        self.error_banner
+            .update(&self.validation_log, Instant::now());
+        if self.breathing {
+            self.morph_vertices(animation_time, morph::breathe);
+        }
+        if let Some(morphed) = &mut self.morphed_vertices {
+            morphed.upload(self.current_frame);
+        }
+        unsafe {
+            let transform_time = self
+                .animator
+                .write_transforms(self.current_frame, animation_time)
+                .filter(|_| self.frame_count.is_multiple_of(STATISTICS_LOG_INTERVAL));
+            if let Some(elapsed) = transform_time {
+                info!(
+                    "CPU transforms ({}): {} objects in {:.3} ms",
+                    math::NAME,
+                    self.animator.object_count(),
+                    elapsed.as_secs_f64() * 1000.0
+                );
+            }
+
+            self.context
+                .device
+                .reset_fences(std::slice::from_ref(&frame.in_flight_fence))
+                .map_err(VulkanError::at(Stage::Submit))?;
+
+            self.context
+                .device
+                .reset_command_buffer(frame.command_buffer, vk::CommandBufferResetFlags::empty())
+                .map_err(VulkanError::at(Stage::Submit))?;
+            self.write_draw_slots(self.current_frame);
+            self.poll_pipeline_builds();
+            let required_keys = self.required_pipeline_keys();
+            self.request_pipelines(&required_keys);
+            self.ensure_statistics_queries();
+            self.ensure_record_pools();
+            self.assign_capture_requests(self.current_frame);
+            let draw_stats = self.record_command_buffer(
+                frame.command_buffer,
+                image_index as usize,
+                self.current_frame,
+                animation_time,
+                uniforms.clear_color,
+            );
+            if draw_stats != self.draw_stats {
+                info!(
+                    "Draws: {}, binds: {} pipeline, {} material, {} mesh",
+                    draw_stats.draws,
+                    draw_stats.pipeline_binds,
+                    draw_stats.material_binds,
+                    draw_stats.mesh_binds
+                );
+                self.draw_stats = draw_stats;
+            }
+
+            // Extra windows' images are drawn in the same submission, bar
+            // those on other devices.
+            let mut wait_semaphores = vec![frame.image_available_semaphore];
+            let mut signal_semaphores = vec![frame.render_finished_semaphore];
+            for extra in self
+                .extra_windows
+                .iter()
+                .filter(|extra| extra.acquired.is_some() && extra.device.is_none())
+            {
+                let (image_available, render_finished) = extra.semaphores(self.current_frame);
+                wait_semaphores.push(image_available);
+                signal_semaphores.push(render_finished);
+            }
+            let wait_stages =
+                vec![vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT; wait_semaphores.len()];
+            let mut submit_info = vk::SubmitInfo::builder()
+                .command_buffers(std::slice::from_ref(&frame.command_buffer));
+            if presenting {
+                submit_info = submit_info
+                    .wait_semaphores(&wait_semaphores)
+                    .wait_dst_stage_mask(&wait_stages)
+                    .signal_semaphores(&signal_semaphores);
+            }
+
+            self.context
+                .device
+                .queue_submit(
+                    self.context.graphics_queue,
+                    std::slice::from_ref(&submit_info),
+                    frame.in_flight_fence,
+                )
+                .map_err(VulkanError::at(Stage::Submit))?;
+            self.watchdog.mark_submitted(self.frame_count);
+            if let Some(timer) = &mut self.gpu_timer {
+                timer.mark_submitted(self.current_frame);
+            }
+            if self.animation_check_pending {
+                self.check_gpu_animation(animation_time);
+            }
+            self.frame_dirty = false;
+            self.frame_count += 1;
+            self.resources.set_frame(self.frame_count);
+            self.journal.set_frame(self.frame_count);
+            self.last_frame = Some(self.current_frame);
+            self.current_frame = self.current_frame.next();
+        }
+        Ok(())
+    }
+
+    /// Compares the frame just submitted against the CPU evaluation, once, so a
+    /// layout mismatch between `AnimationParams` and `animate.comp` shows up at
+    /// startup. Stalls the queue.
+    fn check_gpu_animation(&mut self, animation_time: f32) {
+        self.animation_check_pending = false;
+        unsafe {
+            self.context
+                .device
+                .queue_wait_idle(self.context.graphics_queue)
+                .unwrap();
+        }
+        let max_error = self.animator.max_error(self.current_frame, animation_time);
+        self.animation_error = Some(max_error);
+        if max_error <= ANIMATION_TOLERANCE {
+            info!(
+                "GPU animation matches CPU for {} objects (max error {:e})",
+                self.animator.object_count(),
+                max_error
+            );
+        } else {
+            warn!(
+                "GPU animation differs from CPU by {:e} (tolerance {:e})",
+                max_error, ANIMATION_TOLERANCE
+            );
+        }
+    }
+
+    fn frame_data(&self) -> PerFrameUbo {
+        let (light, ambient) = match self.sun_state() {
+            Some(sun) => (
+                Light {
+                    direction: sun.direction.extend(0.0).into(),
+                    color: [sun.color[0], sun.color[1], sun.color[2], SPECULAR_STRENGTH],
+                },
+                sun.ambient,
+            ),
+            None => {
+                let azimuth = std::f32::consts::TAU * self.clock.time() / LIGHT_ORBIT_SECONDS;
+                let elevation = LIGHT_ELEVATION_DEGREES.to_radians();
+                (
+                    Light {
+                        direction: [
+                            elevation.cos() * azimuth.cos(),
+                            elevation.cos() * azimuth.sin(),
+                            elevation.sin(),
+                            0.0,
+                        ],
+                        color: [1.0, 1.0, 1.0, SPECULAR_STRENGTH],
+                    },
+                    LIGHT_AMBIENT,
+                )
+            }
+        };
+        let mut lights = [Light::default(); MAX_LIGHTS];
+        lights[0] = light;
+        PerFrameUbo {
+            camera_position: self.camera_eye().to_homogeneous().into(),
+            time: self.clock.time(),
+            light_count: 1,
+            ambient,
+            _padding: 0.0,
+            lights,
+        }
+    }
+
+    /// The main view turned `extra.yaw` about the scene's vertical axis,
+    /// at `extra`'s aspect.
+    fn extra_view_data(&self, extra: &ExtraWindow) -> PerViewUbo {
+        let (view, _) = self.camera_matrices();
+        let aspect = aspect::projection_aspect(extra.extent);
+        let proj = cgmath::perspective(CAMERA_FOV_Y, aspect, CAMERA_NEAR, CAMERA_FAR);
+        // Other devices' windows have positive viewports, whatever the
+        // main device's capabilities.
+        let proj = match extra.device {
+            Some(_) => {
+                Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0)
+                    * self.depth_direction.projection(proj)
+            }
+            None => self.gpu_projection(proj),
+        };
+        PerViewUbo {
+            view: view * Matrix4::from_angle_z(Rad(extra.yaw)),
+            proj,
+            viewport: [
+                0.0,
+                0.0,
+                extra.extent.width as f32,
+                extra.extent.height as f32,
+            ],
+        }
+    }
+
+    fn view_data(&self, view_index: usize) -> PerViewUbo {
+        // Per-object transforms live in the animator's transform buffer.
+        let (view, mut proj) = self.camera_matrices();
+        let scene = self.scene_rect();
+        if view_index == MAGNIFIER_VIEW {
+            if let Some(inset) = self.magnifier_inset() {
+                proj = inset.projection(proj, scene, self.cursor_position, MAGNIFIER_ZOOM);
+            }
+        }
+        PerViewUbo {
+            view,
+            proj: self.gpu_projection(proj),
+            viewport: [
+                scene.offset.x as f32,
+                scene.offset.y as f32,
+                scene.extent.width as f32,
+                scene.extent.height as f32,
+            ],
+        }
+    }
+
+    /// Copies each draw's constants to its uniform slot, when the packing
+    /// puts any there.
+    fn write_draw_slots(&self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>) {
+        if !self.draw_packing.uses_uniform_slot() {
+            return;
+        }
+        let stride = self.draw_packing.slot_stride();
+        for (slot, record) in self.draw_list.records().iter().enumerate() {
+            self.write_uniform_block(frame, draw_slot_offset(slot, stride), record.constants);
+        }
+    }
+
+    fn write_uniform_block<T: Copy>(
+        &self,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        offset: vk::DeviceSize,
+        block: T,
+    ) {
+        self.uniform_buffers[frame].write(offset, &block);
+    }
+
+    /// Vertex and index buffers every mesh is sub-allocated from.
+    fn create_mesh_pool(
+        context: &VkContext,
+        resources: &mut ResourceRegistry,
+    ) -> Result<MeshPool, VulkanError> {
+        let vertex_size =
+            (std::mem::size_of::<Vertex>() * MESH_POOL_VERTICES as usize) as vk::DeviceSize;
+        let (vertex_buffer, vertex_allocation) = context.create_buffer(
+            ResourceClass::Vertex,
+            vertex_size,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+        )?;
+        resources.register(
+            ResourceKind::Buffer,
+            vertex_buffer,
+            "mesh pool vertex buffer",
+            vertex_size,
+        );
+
+        let index_size =
+            (std::mem::size_of::<u16>() * MESH_POOL_INDICES as usize) as vk::DeviceSize;
+        let (index_buffer, index_allocation) = context.create_buffer(
+            ResourceClass::Index,
+            index_size,
+            vk::BufferUsageFlags::INDEX_BUFFER,
+        )?;
+        resources.register(
+            ResourceKind::Buffer,
+            index_buffer,
+            "mesh pool index buffer",
+            index_size,
+        );
+
+        Ok(MeshPool {
+            vertex_buffer,
+            vertex_allocation,
+            index_buffer,
+            index_allocation,
+            vertex_ranges: RangeAllocator::new(MESH_POOL_VERTICES),
+            index_ranges: RangeAllocator::new(MESH_POOL_INDICES),
+            meshes: 0,
+            revision: 0,
+        })
+    }
+
+    /// The multisampled colour attachment, or `None` for a single sample,
+    /// where the subpass draws straight into the swapchain image.
+    fn create_color_resources(
+        context: &VkContext,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlags,
+        resources: &mut ResourceRegistry,
+    ) -> Result<Option<(vk::Image, Allocation, vk::ImageView)>, VulkanError> {
+        if samples == vk::SampleCountFlags::TYPE_1 {
+            return Ok(None);
+        }
+        let (color_image, color_image_allocation) = context.create_image(
+            extent.width,
+            extent.height,
+            samples,
+            format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+        )?;
+        let color_image_view =
+            context.create_image_view(color_image, format, vk::ImageAspectFlags::COLOR);
+        let color_size = unsafe {
+            context
+                .device
+                .get_image_memory_requirements(color_image)
+                .size
+        };
+        resources.register(
+            ResourceKind::Image,
+            color_image,
+            "multisampled colour image",
+            color_size,
+        );
+        resources.register(
+            ResourceKind::ImageView,
+            color_image_view,
+            "multisampled colour image view",
+            0,
+        );
+        Ok(Some((
+            color_image,
+            color_image_allocation,
+            color_image_view,
+        )))
+    }
+
+    /// Generates `pattern` into a staging buffer and uploads it through
+    /// `Texture::record_upload`, with a full mip chain when the format can be
+    /// blitted with linear filtering.
+    fn create_texture(&mut self, name: &str, pattern: Pattern) -> Result<Texture, VulkanError> {
+        let size = GROUND_TEXTURE_SIZE;
+        let texture = self.upload_texture(name, size, size, &pattern.generate(size))?;
+        info!(
+            "Generated {} '{}' ({}x{}, {} mip levels)",
+            name,
+            pattern.name(),
+            size,
+            size,
+            texture.mip_levels
+        );
+        Ok(texture)
+    }
+
+    /// Decodes the PNG at `path`, falling back to a generated checkerboard
+    /// when it is missing or can't be read, or without the asset-loaders
+    /// feature.
+    fn load_texture(&mut self, name: &str, path: &Path) -> Result<Texture, VulkanError> {
+        #[cfg(feature = "asset-loaders")]
+        let decoded = std::fs::read(path)
+            .map_err(|error| error.to_string())
+            .and_then(|bytes| png::decode_rgba(&bytes));
+        #[cfg(not(feature = "asset-loaders"))]
+        let decoded: Result<(u32, u32, Vec<u8>), String> =
+            Err("--cube-texture needs the asset-loaders feature".to_string());
+        match decoded {
+            Ok((width, height, pixels)) => {
+                let texture = self.upload_texture(name, width, height, &pixels)?;
+                info!(
+                    "Loaded {} from {} ({}x{}, {} mip levels)",
+                    name,
+                    path.display(),
+                    width,
+                    height,
+                    texture.mip_levels
+                );
+                Ok(texture)
+            }
+            Err(error) => {
+                warn!(
+                    "Failed to load {} from {}: {}; using a checkerboard",
+                    name,
+                    path.display(),
+                    error
+                );
+                self.create_texture(name, Pattern::checker())
+            }
+        }
+    }
+
+    /// A sampled image holding `pixels`, tightly packed sRGB RGBA8, with a
+    /// full mip chain where the format can be blitted linearly.
+    fn upload_texture(
+        &mut self,
+        name: &str,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<Texture, VulkanError> {
+        let format = vk::Format::R8G8B8A8_SRGB;
+        let features = unsafe {
+            self.context
+                .instance
+                .get_physical_device_format_properties(self.context.physical_device, format)
+                .optimal_tiling_features
+        };
+        let mip_levels = if features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR) {
+            texture::mip_levels(width.max(height))
+        } else {
+            1
+        };
+
+        let staging_size = pixels.len() as vk::DeviceSize;
+        let (staging, staging_allocation) = self.context.create_buffer(
+            ResourceClass::Staging,
+            staging_size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+        )?;
+        unsafe {
+            let data_ptr = staging_allocation.mapped_ptr() as *mut u8;
+            data_ptr.copy_from_nonoverlapping(pixels.as_ptr(), pixels.len());
+        }
+
+        let image_info = vk::ImageCreateInfo::builder()
+            .image_type(vk::ImageType::TYPE_2D)
+            .extent(vk::Extent3D {
+                width,
+                height,
+                depth: 1,
+            })
+            .mip_levels(mip_levels)
+            .array_layers(1)
+            .format(format)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(
+                vk::ImageUsageFlags::TRANSFER_SRC
+                    | vk::ImageUsageFlags::TRANSFER_DST
+                    | vk::ImageUsageFlags::SAMPLED,
+            )
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .samples(vk::SampleCountFlags::TYPE_1);
+        let image = unsafe { self.context.device.create_image(&image_info, None).unwrap() };
+        let allocation = match self
+            .context
+            .bind_image_memory(image, vk::ImageTiling::OPTIMAL)
+        {
+            Ok(allocation) => allocation,
+            Err(error) => {
+                unsafe { self.context.device.destroy_buffer(staging, None) };
+                self.context.allocator.free(staging_allocation);
+                return Err(error);
+            }
+        };
+
+        let view_info = vk::ImageViewCreateInfo::builder()
+            .image(image)
+            .view_type(vk::ImageViewType::TYPE_2D)
+            .format(format)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: mip_levels,
+                base_array_layer: 0,
+                layer_count: 1,
+            });
+        let view = unsafe {
+            self.context
+                .device
+                .create_image_view(&view_info, None)
+                .unwrap()
+        };
+        let sampler_info = vk::SamplerCreateInfo::builder()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::REPEAT)
+            .address_mode_v(vk::SamplerAddressMode::REPEAT)
+            .address_mode_w(vk::SamplerAddressMode::REPEAT)
+            .anisotropy_enable(self.effective.anisotropy.is_some())
+            .max_anisotropy(self.effective.anisotropy.unwrap_or(1.0))
+            .max_lod(mip_levels as f32);
+        let sampler = unsafe {
+            self.context
+                .device
+                .create_sampler(&sampler_info, None)
+                .unwrap()
+        };
+
+        let texture = Texture {
+            image,
+            allocation,
+            view,
+            sampler,
+            width,
+            height,
+            mip_levels,
+        };
+        match self.transfer {
+            Some(transfer) => {
+                // submit_once waits for the queue, so the release on the
+                // transfer queue completes before the graphics queue acquires.
+                let ownership = OwnershipTransfer {
+                    src_family: transfer.family,
+                    dst_family: self.context.queue_family_indices.graphics_family.unwrap(),
+                };
+                self.context.submit_once_on(
+                    transfer.command_pool,
+                    transfer.queue,
+                    |device, command_buffer| {
+                        texture.record_copy(device, command_buffer, staging, Some(ownership))
+                    },
+                );
+                self.submit_once(|device, command_buffer| {
+                    texture.record_mips(device, command_buffer, Some(ownership))
+                });
+            }
+            None => self.submit_once(|device, command_buffer| {
+                texture.record_upload(device, command_buffer, staging)
+            }),
+        }
+        unsafe { self.context.device.destroy_buffer(staging, None) };
+        self.context.allocator.free(staging_allocation);
+
+        self.resources
+            .register(ResourceKind::Image, image, name, allocation.size);
+        self.resources
+            .register(ResourceKind::ImageView, view, &format!("{} view", name), 0);
+        self.resources.register(
+            ResourceKind::Sampler,
+            sampler,
+            &format!("{} sampler", name),
+            0,
+        );
+        Ok(texture)
+    }
+
+    /// The skybox's cube map, from the faces or equirectangular PNG at
+    /// `config.skybox` or else the generated gradient, and its unit cube.
+    /// Files that can't be loaded fall back to the gradient too. Uploaded
+    /// on the graphics queue, without mips.
+    fn create_skybox(&mut self) -> Result<Skybox, VulkanError> {
+        let loaded = self.config.skybox.clone().and_then(|path| {
+            skybox::load_faces(&path)
+                .inspect(|(size, _)| {
+                    info!(
+                        "Loaded skybox from {} ({}x{} faces)",
+                        path.display(),
+                        size,
+                        size
+                    )
+                })
+                .map_err(|error| warn!("Failed to load skybox: {}; using a gradient", error))
+                .ok()
+        });
+        let (size, pixels) = loaded.unwrap_or_else(|| (SKYBOX_SIZE, skybox::gradient(SKYBOX_SIZE)));
+        let format = vk::Format::R8G8B8A8_SRGB;
+
+        let staging_size = pixels.len() as vk::DeviceSize;
+        let (staging, staging_allocation) = self.context.create_buffer(
+            ResourceClass::Staging,
+            staging_size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+        )?;
+        unsafe {
+            let data_ptr = staging_allocation.mapped_ptr() as *mut u8;
+            data_ptr.copy_from_nonoverlapping(pixels.as_ptr(), pixels.len());
+        }
+
+        let image_info = vk::ImageCreateInfo::builder()
+            .flags(vk::ImageCreateFlags::CUBE_COMPATIBLE)
+            .image_type(vk::ImageType::TYPE_2D)
+            .extent(vk::Extent3D {
+                width: size,
+                height: size,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(6)
+            .format(format)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .samples(vk::SampleCountFlags::TYPE_1);
+        let image = unsafe { self.context.device.create_image(&image_info, None).unwrap() };
+        let allocation = match self
+            .context
+            .bind_image_memory(image, vk::ImageTiling::OPTIMAL)
+        {
+            Ok(allocation) => allocation,
+            Err(error) => {
+                unsafe { self.context.device.destroy_buffer(staging, None) };
+                self.context.allocator.free(staging_allocation);
+                return Err(error);
+            }
+        };
+        self.submit_once(|device, command_buffer| {
+            skybox::record_upload(device, command_buffer, image, staging, size)
+        });
+        unsafe { self.context.device.destroy_buffer(staging, None) };
+        self.context.allocator.free(staging_allocation);
+
+        let view_info = vk::ImageViewCreateInfo::builder()
+            .image(image)
+            .view_type(vk::ImageViewType::CUBE)
+            .format(format)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 6,
+            });
+        let view = unsafe {
+            self.context
+                .device
+                .create_image_view(&view_info, None)
+                .unwrap()
+        };
+        // Clamped, so the faces' edges don't bleed into each other.
+        let sampler_info = vk::SamplerCreateInfo::builder()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE);
+        let sampler = unsafe {
+            self.context
+                .device
+                .create_sampler(&sampler_info, None)
+                .unwrap()
+        };
+        self.resources
+            .register(ResourceKind::Image, image, "skybox", allocation.size);
+        self.resources
+            .register(ResourceKind::ImageView, view, "skybox view", 0);
+        self.resources
+            .register(ResourceKind::Sampler, sampler, "skybox sampler", 0);
+
+        let cube_map = Texture {
+            image,
+            allocation,
+            view,
+            sampler,
+            width: size,
+            height: size,
+            mip_levels: 1,
+        };
+
+        let vertices = skybox::unit_cube();
+        let vertices_size = std::mem::size_of_val(vertices.as_slice()) as vk::DeviceSize;
+        let (vertex_buffer, vertex_allocation) = match self.context.create_buffer(
+            ResourceClass::Vertex,
+            vertices_size,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+        ) {
+            Ok(created) => created,
+            Err(error) => {
+                cube_map.destroy(
+                    &self.context.device,
+                    &self.context.allocator,
+                    &mut self.resources,
+                );
+                return Err(error);
+            }
+        };
+        unsafe {
+            let data_ptr = vertex_allocation.mapped_ptr() as *mut [f32; 3];
+            data_ptr.copy_from_nonoverlapping(vertices.as_ptr(), vertices.len());
+        }
+        self.resources.register(
+            ResourceKind::Buffer,
+            vertex_buffer,
+            "skybox vertices",
+            vertices_size,
+        );
+
+        Ok(Skybox {
+            cube_map,
+            vertex_buffer,
+            vertex_allocation,
+        })
+    }
+
+    /// Records a command buffer with `record`, submits it and waits for the
+    /// queue to drain. Only for setup work outside the frame loop.
+    fn submit_once(&self, record: impl FnOnce(&ash::Device, vk::CommandBuffer)) {
+        self.context
+            .submit_once_on(self.command_pool, self.context.graphics_queue, record);
+    }
+
+    fn create_uniform_buffers(
+        context: &VkContext,
+        draw_slots: usize,
+        slot_stride: vk::DeviceSize,
+        resources: &mut ResourceRegistry,
+    ) -> Result<FrameResources<MappedBuffer, MAX_FRAMES_IN_FLIGHT>, VulkanError> {
+        let buffer_size = uniform_buffer_size(draw_slots, slot_stride);
+        let mut uniform_buffers = Vec::with_capacity(MAX_FRAMES_IN_FLIGHT);
+
+        for i in 0..MAX_FRAMES_IN_FLIGHT {
+            let created = context.create_buffer(
+                ResourceClass::Uniform,
+                buffer_size as vk::DeviceSize,
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+            );
+            let (buffer, allocation) = match created {
+                Ok(created) => created,
+                Err(error) => {
+                    // The caller may carry on with the buffers it had.
+                    for uniform_buffer in uniform_buffers {
+                        resources.unregister(ResourceKind::Buffer, uniform_buffer.buffer);
+                        uniform_buffer.destroy(&context.device, &context.allocator);
+                    }
+                    return Err(error);
+                }
+            };
+            resources.register(
+                ResourceKind::Buffer,
+                buffer,
+                &format!("uniform buffer {}", i),
+                buffer_size as vk::DeviceSize,
+            );
+            uniform_buffers.push(MappedBuffer::new(
+                buffer,
+                allocation,
+                buffer_size as vk::DeviceSize,
+            ));
+        }
+
+        match uniform_buffers.try_into() {
+            Ok(uniform_buffers) => Ok(FrameResources::new(uniform_buffers)),
+            Err(_) => unreachable!("one per frame in flight"),
+        }
+    }
+
+    /// A set with a uniform block at binding 0 and, unless `dynamic_stages`
+    /// is empty, a dynamic uniform block at binding 1 for draw slots.
+    fn create_descriptor_set_layout(
+        device: &ash::Device,
+        stage_flags: vk::ShaderStageFlags,
+        dynamic_stages: vk::ShaderStageFlags,
+    ) -> vk::DescriptorSetLayout {
+        let mut bindings = vec![vk::DescriptorSetLayoutBinding::builder()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(stage_flags)
+            .build()];
+        if !dynamic_stages.is_empty() {
+            bindings.push(
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(1)
+                    .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+                    .descriptor_count(1)
+                    .stage_flags(dynamic_stages)
+                    .build(),
+            );
+        }
+
+        let layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings);
+
+        unsafe {
+            device
+                .create_descriptor_set_layout(&layout_info, None)
+                .unwrap()
+        }
+    }
+
+    /// The set 3 layout, a single combined image sampler read by the fragment
+    /// shader, and one set per material plus the skybox's from its own pool.
+    /// Each set is written once its texture exists, by `write_texture_set`.
+    fn create_texture_sets(
+        device: &ash::Device,
+        resources: &mut ResourceRegistry,
+    ) -> (
+        vk::DescriptorSetLayout,
+        vk::DescriptorPool,
+        Vec<vk::DescriptorSet>,
+    ) {
+        let binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+            .build();
+        let layout_info =
+            vk::DescriptorSetLayoutCreateInfo::builder().bindings(std::slice::from_ref(&binding));
+        let layout = unsafe {
+            device
+                .create_descriptor_set_layout(&layout_info, None)
+                .unwrap()
+        };
+
+        let pool_size = vk::DescriptorPoolSize::builder()
+            .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(TEXTURE_SET_COUNT as u32)
+            .build();
+        let pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(std::slice::from_ref(&pool_size))
+            .max_sets(TEXTURE_SET_COUNT as u32);
+        let pool = unsafe { device.create_descriptor_pool(&pool_info, None).unwrap() };
+        resources.register(
+            ResourceKind::DescriptorPool,
+            pool,
+            "texture descriptor pool",
+            0,
+        );
+
+        let layouts = [layout; TEXTURE_SET_COUNT];
+        let alloc_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(pool)
+            .set_layouts(&layouts);
+        let sets = unsafe { device.allocate_descriptor_sets(&alloc_info).unwrap() };
+        (layout, pool, sets)
+    }
+
+    fn write_texture_set(&self, material: u16, texture: &Texture) {
+        let image_info = vk::DescriptorImageInfo::builder()
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image_view(texture.view)
+            .sampler(texture.sampler)
+            .build();
+        let write = vk::WriteDescriptorSet::builder()
+            .dst_set(self.texture_sets[material as usize])
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(std::slice::from_ref(&image_info))
+            .build();
+        unsafe {
+            self.context
+                .device
+                .update_descriptor_sets(std::slice::from_ref(&write), &[])
+        };
+    }
+
+    /// Empty; `create_descriptor_sets` allocates from it. Exactly big
+    /// enough for `create_descriptor_sets` with the same `draw_slots`: a
+    /// per-frame set and VIEW_COUNT per-view sets for each frame in flight.
+    fn create_descriptor_pool(
+        device: &ash::Device,
+        draw_slots: bool,
+        resources: &mut ResourceRegistry,
+    ) -> vk::DescriptorPool {
+        let set_count = (MAX_FRAMES_IN_FLIGHT * (1 + VIEW_COUNT)) as u32;
+        let mut pool_sizes = vec![vk::DescriptorPoolSize::builder()
+            .ty(vk::DescriptorType::UNIFORM_BUFFER)
+            .descriptor_count(set_count)
+            .build()];
+        if draw_slots {
+            pool_sizes.push(
+                vk::DescriptorPoolSize::builder()
+                    .ty(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+                    .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32)
+                    .build(),
+            );
+        }
+
+        let pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(&pool_sizes)
+            .max_sets(set_count);
+
+        let descriptor_pool = unsafe { device.create_descriptor_pool(&pool_info, None).unwrap() };
+        resources.register(
+            ResourceKind::DescriptorPool,
+            descriptor_pool,
+            "descriptor pool",
+            0,
+        );
+        descriptor_pool
+    }
+
+    /// Per-frame sets (set 0) and, for each frame in flight, one per-view set
+    /// (set 1) per view, all pointing into that frame's uniform buffer. With
+    /// `draw_slots` set 0 also gets the first draw slot at binding 1, moved
+    /// to each draw's slot by a dynamic offset.
+    fn create_descriptor_sets(
+        device: &ash::Device,
+        descriptor_pool: vk::DescriptorPool,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        view_set_layout: vk::DescriptorSetLayout,
+        uniform_buffers: &FrameResources<MappedBuffer, MAX_FRAMES_IN_FLIGHT>,
+        draw_slots: bool,
+    ) -> (
+        FrameResources<vk::DescriptorSet, MAX_FRAMES_IN_FLIGHT>,
+        FrameResources<Vec<vk::DescriptorSet>, MAX_FRAMES_IN_FLIGHT>,
+    ) {
+        let allocate = |layout: vk::DescriptorSetLayout, count: usize| {
+            let layouts = vec![layout; count];
+            let alloc_info = vk::DescriptorSetAllocateInfo::builder()
+                .descriptor_pool(descriptor_pool)
+                .set_layouts(&layouts);
+            unsafe { device.allocate_descriptor_sets(&alloc_info).unwrap() }
+        };
+        let write = |set: vk::DescriptorSet, binding, buffer: vk::Buffer, offset, range| {
+            let buffer_info = vk::DescriptorBufferInfo::builder()
+                .buffer(buffer)
+                .offset(offset)
+                .range(range)
+                .build();
+
+            let descriptor_type = if binding == 0 {
+                vk::DescriptorType::UNIFORM_BUFFER
+            } else {
+                vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC
+            };
+            let descriptor_write = vk::WriteDescriptorSet::builder()
+                .dst_set(set)
+                .dst_binding(binding)
+                .dst_array_element(0)
+                .descriptor_type(descriptor_type)
+                .buffer_info(std::slice::from_ref(&buffer_info))
+                .build();
+
+            unsafe { device.update_descriptor_sets(std::slice::from_ref(&descriptor_write), &[]) };
+        };
+
+        let buffers: Vec<vk::Buffer> = uniform_buffers
+            .iter()
+            .map(|uniform| uniform.buffer)
+            .collect();
+        let descriptor_sets = allocate(descriptor_set_layout, MAX_FRAMES_IN_FLIGHT);
+        let view_sets = allocate(view_set_layout, MAX_FRAMES_IN_FLIGHT * VIEW_COUNT);
+        for (i, &descriptor_set) in descriptor_sets.iter().enumerate() {
+            write(
+                descriptor_set,
+                0,
+                buffers[i],
+                PER_FRAME_OFFSET,
+                std::mem::size_of::<PerFrameUbo>() as vk::DeviceSize,
+            );
+            if draw_slots {
+                write(
+                    descriptor_set,
+                    1,
+                    buffers[i],
+                    draw_slot_offset(0, 0),
+                    std::mem::size_of::<DrawConstants>() as vk::DeviceSize,
+                );
+            }
+        }
+        for (i, &view_set) in view_sets.iter().enumerate() {
+            write(
+                view_set,
+                0,
+                buffers[i / VIEW_COUNT],
+                per_view_offset(i % VIEW_COUNT),
+                std::mem::size_of::<PerViewUbo>() as vk::DeviceSize,
+            );
+        }
+
+        (
+            FrameResources::new(std::array::from_fn(|i| descriptor_sets[i])),
+            FrameResources::new(std::array::from_fn(|i| {
+                view_sets[i * VIEW_COUNT..(i + 1) * VIEW_COUNT].to_vec()
+            })),
+        )
+    }
+
+    /// A still level `level` Menger sponge, lowered until its instances fit
+    /// the storage buffer and dispatch limits and 1/INSTANCE_MEMORY_FRACTION
+    /// of the largest host-visible heap, which holds the per-instance buffers.
+    fn menger_scene(context: &VkContext, level: u32) -> Vec<AnimationParams> {
+        let (instance, pdevice) = (&context.instance, context.physical_device);
+        let memory = unsafe { instance.get_physical_device_memory_properties(pdevice) };
+        let limits = unsafe { instance.get_physical_device_properties(pdevice) }.limits;
+        let host_heap = memory.memory_types[..memory.memory_type_count as usize]
+            .iter()
+            .filter(|memory_type| {
+                memory_type
+                    .property_flags
+                    .contains(vk::MemoryPropertyFlags::HOST_VISIBLE)
+            })
+            .map(|memory_type| memory.memory_heaps[memory_type.heap_index as usize].size)
+            .max()
+            .unwrap_or(0);
+        let params_size = std::mem::size_of::<AnimationParams>() as u64;
+        let per_instance =
+            params_size + (MAX_FRAMES_IN_FLIGHT * std::mem::size_of::<Matrix4<f32>>()) as u64;
+        let budget = (host_heap / INSTANCE_MEMORY_FRACTION / per_instance)
+            .min(limits.max_storage_buffer_range as u64 / params_size)
+            .min(limits.max_compute_work_group_count[0] as u64 * WORKGROUP_SIZE as u64);
+        let applied = primitives::menger_level_within(level, budget);
+        if applied != level {
+            info!(
+                "Menger sponge level {} needs {} instances but the budget is {}; using level {}",
+                level,
+                primitives::menger_instance_count(level),
+                budget,
+                applied
+            );
+        }
+        info!(
+            "Menger sponge level {}: {} instances",
+            applied,
+            primitives::menger_instance_count(applied)
+        );
+        let to_unit = primitives::cube_mesh_to_unit();
+        still_scene(
+            primitives::menger_sponge(applied)
+                .into_iter()
+                .map(|transform| transform * to_unit),
+        )
+    }
+
+    fn create_animator(
+        context: &VkContext,
+        objects: Vec<AnimationParams>,
+        resources: &mut ResourceRegistry,
+    ) -> Result<Animator, VulkanError> {
+        let device = &context.device;
+        let params_size = std::mem::size_of_val(objects.as_slice()) as vk::DeviceSize;
+        let transforms_size =
+            (std::mem::size_of::<Matrix4<f32>>() * objects.len()) as vk::DeviceSize;
+
+        let (params_buffer, params_allocation) = context.create_buffer(
+            ResourceClass::Storage,
+            params_size,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+        )?;
+        let mut align = unsafe {
+            ash::util::Align::new(
+                params_allocation.mapped_ptr(),
+                std::mem::align_of::<AnimationParams>() as _,
+                params_size,
+            )
+        };
+        align.copy_from_slice(&objects);
+        resources.register(
+            ResourceKind::Buffer,
+            params_buffer,
+            "animation params",
+            params_size,
+        );
+
+        let transform_binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(1)
             .stage_flags(vk::ShaderStageFlags::VERTEX)
             .build();
+        let transform_layout_info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(std::slice::from_ref(&transform_binding));
+        let compute_bindings = [0, 1].map(|binding| {
+            vk::DescriptorSetLayoutBinding::builder()
+                .binding(binding)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE)
+                .build()
+        });
+        let compute_layout_info =
+            vk::DescriptorSetLayoutCreateInfo::builder().bindings(&compute_bindings);
+        let (transform_set_layout, compute_set_layout) = unsafe {
+            (
+                device
+                    .create_descriptor_set_layout(&transform_layout_info, None)
+                    .unwrap(),
+                device
+                    .create_descriptor_set_layout(&compute_layout_info, None)
+                    .unwrap(),
+            )
+        };
+
+        let pool_size = vk::DescriptorPoolSize::builder()
+            .ty(vk::DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(3 * MAX_FRAMES_IN_FLIGHT as u32)
+            .build();
+        let pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(std::slice::from_ref(&pool_size))
+            .max_sets(2 * MAX_FRAMES_IN_FLIGHT as u32);
+        let descriptor_pool = unsafe { device.create_descriptor_pool(&pool_info, None).unwrap() };
+        resources.register(
+            ResourceKind::DescriptorPool,
+            descriptor_pool,
+            "animation descriptor pool",
+            0,
+        );
+
+        let frames = FrameResources::try_from_fn(|i| {
+            // The CPU path writes through a mapping, so the buffer stays host visible.
+            let (buffer, allocation) = context.create_buffer(
+                ResourceClass::Storage,
+                transforms_size,
+                vk::BufferUsageFlags::STORAGE_BUFFER,
+            )?;
+            resources.register(
+                ResourceKind::Buffer,
+                buffer,
+                &format!("object transforms {}", i),
+                transforms_size,
+            );
+
+            let layouts = [transform_set_layout, compute_set_layout];
+            let alloc_info = vk::DescriptorSetAllocateInfo::builder()
+                .descriptor_pool(descriptor_pool)
+                .set_layouts(&layouts);
+            let sets = unsafe { device.allocate_descriptor_sets(&alloc_info).unwrap() };
+
+            let params_info = vk::DescriptorBufferInfo::builder()
+                .buffer(params_buffer)
+                .offset(0)
+                .range(vk::WHOLE_SIZE)
+                .build();
+            let transforms_info = vk::DescriptorBufferInfo::builder()
+                .buffer(buffer)
+                .offset(0)
+                .range(vk::WHOLE_SIZE)
+                .build();
+            let writes = [
+                (sets[0], 0, &transforms_info),
+                (sets[1], 0, &params_info),
+                (sets[1], 1, &transforms_info),
+            ]
+            .map(|(set, binding, info)| {
+                vk::WriteDescriptorSet::builder()
+                    .dst_set(set)
+                    .dst_binding(binding)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(std::slice::from_ref(info))
+                    .build()
+            });
+            unsafe { device.update_descriptor_sets(&writes, &[]) };
+
+            Ok(FrameTransforms {
+                buffer,
+                allocation,
+                graphics_set: sets[0],
+                compute_set: sets[1],
+            })
+        })?;
+
+        let push_constant_range = vk::PushConstantRange::builder()
+            .stage_flags(vk::ShaderStageFlags::COMPUTE)
+            .offset(0)
+            .size(std::mem::size_of::<AnimationPushConstants>() as u32)
+            .build();
+        let layout_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(std::slice::from_ref(&compute_set_layout))
+            .push_constant_ranges(std::slice::from_ref(&push_constant_range));
+        let compute_layout = unsafe { device.create_pipeline_layout(&layout_info, None).unwrap() };
+
+        let comp_shader_code = include_bytes!(env!("ANIMATE_COMP_SHADER_PATH"));
+        let comp_shader_module = Self::create_shader_module(device, comp_shader_code);
+        let main_function_name = CString::new("main").unwrap();
+        let stage = vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(comp_shader_module)
+            .name(&main_function_name);
+        let pipeline_info = vk::ComputePipelineCreateInfo::builder()
+            .stage(stage.build())
+            .layout(compute_layout);
+        let compute_pipeline = unsafe {
+            let pipelines = device
+                .create_compute_pipelines(
+                    vk::PipelineCache::null(),
+                    std::slice::from_ref(&pipeline_info),
+                    None,
+                )
+                .unwrap();
+            device.destroy_shader_module(comp_shader_module, None);
+            pipelines[0]
+        };
+        resources.register(
+            ResourceKind::Pipeline,
+            compute_pipeline,
+            "animation compute pipeline",
+            0,
+        );
+
+        let mode = if GPU_ANIMATION {
+            AnimationMode::Gpu
+        } else {
+            AnimationMode::Cpu
+        };
+        info!("Animating {} objects on the {:?}", objects.len(), mode);
+
+        Ok(Animator {
+            mode,
+            objects,
+            params_buffer,
+            params_allocation,
+            frames,
+            transform_set_layout,
+            compute_set_layout,
+            descriptor_pool,
+            compute_layout,
+            compute_pipeline,
+        })
+    }
+
+    /// `plasma.comp` over each frame's buffer of `morphed` vertices.
+    fn create_plasma_pass(
+        context: &VkContext,
+        morphed: &MorphedVertices,
+        resources: &mut ResourceRegistry,
+    ) -> PlasmaPass {
+        let device = &context.device;
+        let binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::COMPUTE)
+            .build();
+        let layout_info =
+            vk::DescriptorSetLayoutCreateInfo::builder().bindings(std::slice::from_ref(&binding));
+        let set_layout = unsafe {
+            device
+                .create_descriptor_set_layout(&layout_info, None)
+                .unwrap()
+        };
+
+        let pool_size = vk::DescriptorPoolSize::builder()
+            .ty(vk::DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32)
+            .build();
+        let pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(std::slice::from_ref(&pool_size))
+            .max_sets(MAX_FRAMES_IN_FLIGHT as u32);
+        let descriptor_pool = unsafe { device.create_descriptor_pool(&pool_info, None).unwrap() };
+        resources.register(
+            ResourceKind::DescriptorPool,
+            descriptor_pool,
+            "plasma descriptor pool",
+            0,
+        );
+        let layouts = [set_layout; MAX_FRAMES_IN_FLIGHT];
+        let alloc_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&layouts);
+        let sets: [vk::DescriptorSet; MAX_FRAMES_IN_FLIGHT] =
+            unsafe { device.allocate_descriptor_sets(&alloc_info).unwrap() }
+                .try_into()
+                .unwrap();
+        for (&set, buffer) in sets.iter().zip(morphed.buffers()) {
+            let vertices_info = vk::DescriptorBufferInfo::builder()
+                .buffer(buffer)
+                .offset(0)
+                .range(vk::WHOLE_SIZE)
+                .build();
+            let write = vk::WriteDescriptorSet::builder()
+                .dst_set(set)
+                .dst_binding(0)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .buffer_info(std::slice::from_ref(&vertices_info));
+            unsafe { device.update_descriptor_sets(std::slice::from_ref(&write), &[]) };
+        }
+
+        let push_constant_range = vk::PushConstantRange::builder()
+            .stage_flags(vk::ShaderStageFlags::COMPUTE)
+            .offset(0)
+            .size(std::mem::size_of::<AnimationPushConstants>() as u32)
+            .build();
+        let layout_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(std::slice::from_ref(&set_layout))
+            .push_constant_ranges(std::slice::from_ref(&push_constant_range));
+        let layout = unsafe { device.create_pipeline_layout(&layout_info, None).unwrap() };
 
-        let layout_info = vk::DescriptorSetLayoutCreateInfo::builder()
-            .bindings(std::slice::from_ref(&ubo_layout_binding));
+        let comp_shader_code = include_bytes!(env!("PLASMA_COMP_SHADER_PATH"));
+        let comp_shader_module = Self::create_shader_module(device, comp_shader_code);
+        let main_function_name = CString::new("main").unwrap();
+        let stage = vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(comp_shader_module)
+            .name(&main_function_name);
+        let pipeline_info = vk::ComputePipelineCreateInfo::builder()
+            .stage(stage.build())
+            .layout(layout);
+        let pipeline = unsafe {
+            let pipelines = device
+                .create_compute_pipelines(
+                    vk::PipelineCache::null(),
+                    std::slice::from_ref(&pipeline_info),
+                    None,
+                )
+                .unwrap();
+            device.destroy_shader_module(comp_shader_module, None);
+            pipelines[0]
+        };
+        resources.register(
+            ResourceKind::Pipeline,
+            pipeline,
+            "plasma compute pipeline",
+            0,
+        );
+        info!(
+            "Recolouring {} vertices in workgroups of {}",
+            morphed.vertex_count(),
+            plasma::WORKGROUP_SIZE
+        );
 
-        unsafe {
+        PlasmaPass {
+            set_layout,
+            descriptor_pool,
+            sets: FrameResources::new(sets),
+            layout,
+            pipeline,
+            vertex_count: morphed.vertex_count(),
+        }
+    }
+
+    /// The post-process subpass's layouts and pipeline, in `render_pass`.
+    /// Its targets come with the other attachments.
+    #[cfg(feature = "post-processing")]
+    fn create_post_pass(
+        device: &ash::Device,
+        render_pass: vk::RenderPass,
+        resources: &mut ResourceRegistry,
+    ) -> PostPass {
+        let binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::INPUT_ATTACHMENT)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+            .build();
+        let layout_info =
+            vk::DescriptorSetLayoutCreateInfo::builder().bindings(std::slice::from_ref(&binding));
+        let set_layout = unsafe {
             device
                 .create_descriptor_set_layout(&layout_info, None)
                 .unwrap()
-        }
+        };
+        let push_constant_range = vk::PushConstantRange::builder()
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+            .offset(0)
+            .size(std::mem::size_of::<PostParams>() as u32)
+            .build();
+        let layout_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(std::slice::from_ref(&set_layout))
+            .push_constant_ranges(std::slice::from_ref(&push_constant_range));
+        let layout = unsafe { device.create_pipeline_layout(&layout_info, None).unwrap() };
+        let pipeline = Self::create_post_pipeline(device, render_pass, layout, resources);
+        PostPass::new(set_layout, layout, pipeline)
     }
 
-    fn create_descriptor_pool(
+    /// `post.frag` over a screen-covering triangle, in the subpass after
+    /// the scene's.
+    #[cfg(feature = "post-processing")]
+    fn create_post_pipeline(
         device: &ash::Device,
-        num_images: usize,
-        descriptor_set_layout: vk::DescriptorSetLayout,
-    ) -> (vk::DescriptorPool, Vec<vk::DescriptorSet>) {
-        let pool_size = vk::DescriptorPoolSize::builder()
-            .ty(vk::DescriptorType::UNIFORM_BUFFER)
-            .descriptor_count(100)
-            .build();
+        render_pass: vk::RenderPass,
+        layout: vk::PipelineLayout,
+        resources: &mut ResourceRegistry,
+    ) -> vk::Pipeline {
+        // Background's vertex shader draws the screen-covering triangle.
+        let vert_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("BACKGROUND_VERT_SHADER_PATH")));
+        let frag_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("POST_FRAG_SHADER_PATH")));
+        let main_function_name = CString::new("main").unwrap();
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_shader_module)
+                .name(&main_function_name)
+                .build(),
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_shader_module)
+                .name(&main_function_name)
+                .build(),
+        ];
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::builder();
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo::builder()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(vk::CullModeFlags::NONE);
+        let multisampling = vk::PipelineMultisampleStateCreateInfo::builder()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder();
+        let color_blend_attachment = vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(false);
+        let color_blending = vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(std::slice::from_ref(&color_blend_attachment));
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states);
+        let pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&shader_stages)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterizer)
+            .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&color_blending)
+            .dynamic_state(&dynamic_state)
+            .layout(layout)
+            .render_pass(render_pass)
+            .subpass(DEPTH_PREPASS as u32 + 1);
+        let pipeline = unsafe {
+            device
+                .create_graphics_pipelines(
+                    vk::PipelineCache::null(),
+                    std::slice::from_ref(&pipeline_info),
+                    None,
+                )
+                .unwrap()[0]
+        };
+        unsafe {
+            device.destroy_shader_module(vert_shader_module, None);
+            device.destroy_shader_module(frag_shader_module, None);
+        }
+        resources.register(ResourceKind::Pipeline, pipeline, "post-process pipeline", 0);
+        pipeline
+    }
 
-        let pool_info = vk::DescriptorPoolCreateInfo::builder()
-            .pool_sizes(std::slice::from_ref(&pool_size))
-            .max_sets(100);
+    /// An extra window's render pass: its swapchain image of `format`,
+    /// cleared and left in `final_layout`, ready to present or for the A/B
+    /// split to copy from, and a depth image of `depth_format`.
+    fn create_extra_render_pass(
+        device: &ash::Device,
+        format: vk::Format,
+        depth_format: vk::Format,
+        final_layout: vk::ImageLayout,
+    ) -> vk::RenderPass {
+        let attachments = [
+            vk::AttachmentDescription::builder()
+                .format(format)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::STORE)
+                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .final_layout(final_layout)
+                .build(),
+            vk::AttachmentDescription::builder()
+                .format(depth_format)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                .build(),
+        ];
+        let color_ref = vk::AttachmentReference::builder()
+            .attachment(0)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+        let depth_ref = vk::AttachmentReference::builder()
+            .attachment(1)
+            .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
+        let subpass = vk::SubpassDescription::builder()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(std::slice::from_ref(&color_ref))
+            .depth_stencil_attachment(&depth_ref);
+        // After the acquire, the frame's compute writes, and the last
+        // frame's use of the one depth image.
+        let dependency = vk::SubpassDependency::builder()
+            .src_subpass(vk::SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(
+                vk::PipelineStageFlags::COMPUTE_SHADER
+                    | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            )
+            .src_access_mask(
+                vk::AccessFlags::SHADER_WRITE | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            )
+            .dst_stage_mask(
+                vk::PipelineStageFlags::VERTEX_INPUT
+                    | vk::PipelineStageFlags::VERTEX_SHADER
+                    | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            )
+            .dst_access_mask(
+                vk::AccessFlags::VERTEX_ATTRIBUTE_READ
+                    | vk::AccessFlags::SHADER_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
+                    | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            );
+        let render_pass_info = vk::RenderPassCreateInfo::builder()
+            .attachments(&attachments)
+            .subpasses(std::slice::from_ref(&subpass))
+            .dependencies(std::slice::from_ref(&dependency));
+        unsafe { device.create_render_pass(&render_pass_info, None).unwrap() }
+    }
 
-        let descriptor_pool = unsafe { device.create_descriptor_pool(&pool_info, None).unwrap() };
+    /// The ID pass's render pass, layout and pipeline, and its readback
+    /// buffer. Its target comes with the other attachments.
+    fn create_pick_pass(&mut self) -> Result<PickPass, VulkanError> {
+        let device = &self.context.device;
+        // Both are cleared, and only the ID is kept, for the copy after
+        // the pass.
+        let attachments = [
+            vk::AttachmentDescription::builder()
+                .format(PICK_FORMAT)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::STORE)
+                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .final_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .build(),
+            vk::AttachmentDescription::builder()
+                .format(self.depth_format)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                .build(),
+        ];
+        let id_ref = vk::AttachmentReference::builder()
+            .attachment(0)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+        let depth_ref = vk::AttachmentReference::builder()
+            .attachment(1)
+            .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
+        let subpass = vk::SubpassDescription::builder()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(std::slice::from_ref(&id_ref))
+            .depth_stencil_attachment(&depth_ref);
+        // In: after the last frame's compute wrote the transforms, and the
+        // last pick's copy read the target. Out: before this pick's copy.
+        let dependencies = [
+            vk::SubpassDependency::builder()
+                .src_subpass(vk::SUBPASS_EXTERNAL)
+                .dst_subpass(0)
+                .src_stage_mask(
+                    vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::TRANSFER,
+                )
+                .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+                .dst_stage_mask(
+                    vk::PipelineStageFlags::VERTEX_SHADER
+                        | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                        | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                )
+                .dst_access_mask(
+                    vk::AccessFlags::SHADER_READ
+                        | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
+                        | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                )
+                .build(),
+            vk::SubpassDependency::builder()
+                .src_subpass(0)
+                .dst_subpass(vk::SUBPASS_EXTERNAL)
+                .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags::TRANSFER)
+                .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+                .build(),
+        ];
+        let render_pass_info = vk::RenderPassCreateInfo::builder()
+            .attachments(&attachments)
+            .subpasses(std::slice::from_ref(&subpass))
+            .dependencies(&dependencies);
+        let render_pass = unsafe { device.create_render_pass(&render_pass_info, None).unwrap() };
 
-        let layouts = vec![descriptor_set_layout; num_images];
-        let allocate_info = vk::DescriptorSetAllocateInfo::builder()
-            .descriptor_pool(descriptor_pool)
-            .set_layouts(&layouts);
+        let set_layouts = [self.view_set_layout, self.animator.transform_set_layout];
+        let push_constant_range = vk::PushConstantRange::builder()
+            .stage_flags(vk::ShaderStageFlags::VERTEX)
+            .offset(0)
+            .size(std::mem::size_of::<PickPush>() as u32)
+            .build();
+        let layout_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&set_layouts)
+            .push_constant_ranges(std::slice::from_ref(&push_constant_range));
+        let layout = unsafe { device.create_pipeline_layout(&layout_info, None).unwrap() };
+
+        let vert_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("PICK_VERT_SHADER_PATH")));
+        let frag_shader_module =
+            Self::create_shader_module(device, include_bytes!(env!("PICK_FRAG_SHADER_PATH")));
+        let main_function_name = CString::new("main").unwrap();
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vert_shader_module)
+                .name(&main_function_name)
+                .build(),
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_shader_module)
+                .name(&main_function_name)
+                .build(),
+        ];
+        // Only the position is read.
+        let binding_description = Vertex::get_binding_description();
+        let attribute_description = Vertex::get_attribute_descriptions()[0];
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_binding_descriptions(std::slice::from_ref(&binding_description))
+            .vertex_attribute_descriptions(std::slice::from_ref(&attribute_description));
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+        // Unculled, for double-sided objects; the depth test keeps the
+        // nearest face either way.
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo::builder()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .cull_mode(vk::CullModeFlags::NONE);
+        let multisampling = vk::PipelineMultisampleStateCreateInfo::builder()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(true)
+            .depth_write_enable(true)
+            .depth_compare_op(self.depth_direction.compare_op());
+        let color_blend_attachment = vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::R)
+            .blend_enable(false);
+        let color_blending = vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(std::slice::from_ref(&color_blend_attachment));
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states);
+        let pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&shader_stages)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterizer)
+            .multisample_state(&multisampling)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&color_blending)
+            .dynamic_state(&dynamic_state)
+            .layout(layout)
+            .render_pass(render_pass)
+            .subpass(0);
+        let pipeline = unsafe {
+            device
+                .create_graphics_pipelines(
+                    vk::PipelineCache::null(),
+                    std::slice::from_ref(&pipeline_info),
+                    None,
+                )
+                .unwrap()[0]
+        };
+        unsafe {
+            device.destroy_shader_module(vert_shader_module, None);
+            device.destroy_shader_module(frag_shader_module, None);
+        }
+        self.resources
+            .register(ResourceKind::Pipeline, pipeline, "pick pipeline", 0);
+        let picking = PickPass::new(
+            &self.context,
+            render_pass,
+            layout,
+            pipeline,
+            &mut self.resources,
+        );
+        if picking.is_err() {
+            self.resources.unregister(ResourceKind::Pipeline, pipeline);
+            unsafe {
+                device.destroy_pipeline(pipeline, None);
+                device.destroy_pipeline_layout(layout, None);
+                device.destroy_render_pass(render_pass, None);
+            }
+        }
+        picking
+    }
 
-        let descriptor_sets = unsafe { device.allocate_descriptor_sets(&allocate_info).unwrap() };
+    /// Waits for submitted frames, then destroys everything in dependency
+    /// order: swapchain-sized objects and pending pipeline builds, meshes,
+    /// per-frame sync objects and command buffers, per-frame buffers, shared
+    /// buffers and layouts, and finally the device and instance. A failed
+    /// wait is returned after destroying anyway. Does nothing the second
+    /// time.
+    unsafe fn teardown(&mut self) -> Result<(), VulkanError> {
+        if self.destroyed {
+            return Ok(());
+        }
+        let waited = self.wait_for_shutdown();
+        let errors_before = self.validation_log.error_count();
+        for render_context in &self.render_contexts {
+            if let Err(error) = unsafe { render_context.context.device.device_wait_idle() } {
+                warn!(
+                    "Destroying another GPU's windows without waiting: {}",
+                    error
+                );
+            }
+        }
+        // Their surfaces, and the other devices, go before the instance.
+        for mut extra in std::mem::take(&mut self.extra_windows) {
+            let (context, swapchain_loader, resources) = self.window_device_objects(extra.device);
+            extra.destroy_targets(&context.device, &context.allocator, resources);
+            extra.destroy(
+                &context.device,
+                &context.allocator,
+                swapchain_loader,
+                resources,
+            );
+        }
+        for render_context in std::mem::take(&mut self.render_contexts) {
+            render_context.destroy();
+        }
+        if let Some(view) = self.depth_view.take() {
+            self.destroy_depth_view(view);
+        }
+        unsafe {
+            self.cleanup_swapchain();
+            self.destroy_pipelines();
+            self.context
+                .device
+                .destroy_pipeline_layout(self.pipeline_layout, None);
+            self.context
+                .device
+                .destroy_render_pass(self.render_pass, None);
+            // With every mesh released, the free lists must have coalesced
+            // back into one range each.
+            for mesh in self.meshes.drain(..) {
+                self.mesh_pool.remove(mesh);
+            }
+            let pool = self.mesh_pool.stats();
+            if pool.vertices.free_ranges != 1 || pool.indices.free_ranges != 1 {
+                warn!("Mesh pool did not coalesce: {:?}", pool);
+            }
+            if let Some(plasma) = self.plasma.take() {
+                plasma.destroy(&self.context.device, &mut self.resources);
+            }
+            // Its targets went with the swapchain's attachments.
+            #[cfg(feature = "post-processing")]
+            if let Some(post) = self.post.take() {
+                post.destroy(&self.context.device, &mut self.resources);
+            }
+            if let Some(picking) = self.picking.take() {
+                picking.destroy(
+                    &self.context.device,
+                    &self.context.allocator,
+                    &mut self.resources,
+                );
+            }
+            if let Some(morphed) = self.morphed_vertices.take() {
+                morphed.destroy(
+                    &self.context.device,
+                    &self.context.allocator,
+                    &mut self.resources,
+                );
+            }
+            self.mesh_pool.destroy(
+                &self.context.device,
+                &self.context.allocator,
+                &mut self.resources,
+            );
+            if let Some(texture) = self.ground_texture.take() {
+                texture.destroy(
+                    &self.context.device,
+                    &self.context.allocator,
+                    &mut self.resources,
+                );
+            }
+            if let Some(texture) = self.face_labels.take() {
+                texture.destroy(
+                    &self.context.device,
+                    &self.context.allocator,
+                    &mut self.resources,
+                );
+            }
+            if let Some(texture) = self.cube_texture.take() {
+                texture.destroy(
+                    &self.context.device,
+                    &self.context.allocator,
+                    &mut self.resources,
+                );
+            }
+            if let Some(skybox) = self.skybox.take() {
+                skybox.destroy(
+                    &self.context.device,
+                    &self.context.allocator,
+                    &mut self.resources,
+                );
+            }
+            self.resources
+                .unregister(ResourceKind::DescriptorPool, self.texture_pool);
+            self.context
+                .device
+                .destroy_descriptor_pool(self.texture_pool, None);
+            self.context
+                .device
+                .destroy_descriptor_set_layout(self.texture_set_layout, None);
+            #[cfg(feature = "overlay")]
+            {
+                for graph_buffer in self.graph_buffers.iter() {
+                    graph_buffer.destroy(
+                        &self.context.device,
+                        &self.context.allocator,
+                        &mut self.resources,
+                    );
+                }
+                self.context
+                    .device
+                    .destroy_pipeline_layout(self.graph_layout, None);
+                for hud_buffer in self.hud_buffers.iter() {
+                    hud_buffer.destroy(
+                        &self.context.device,
+                        &self.context.allocator,
+                        &mut self.resources,
+                    );
+                }
+                if let Some(texture) = self.hud_atlas.take() {
+                    texture.destroy(
+                        &self.context.device,
+                        &self.context.allocator,
+                        &mut self.resources,
+                    );
+                }
+                self.context
+                    .device
+                    .destroy_pipeline_layout(self.hud_layout, None);
+            }
+            for frame in self.frames.iter() {
+                self.context
+                    .device
+                    .destroy_semaphore(frame.image_available_semaphore, None);
+                self.context
+                    .device
+                    .destroy_semaphore(frame.render_finished_semaphore, None);
+                self.context
+                    .device
+                    .destroy_fence(frame.in_flight_fence, None);
+            }
+            self.context
+                .device
+                .destroy_command_pool(self.command_pool, None);
+            if let Some(transfer) = &self.transfer {
+                self.context
+                    .device
+                    .destroy_command_pool(transfer.command_pool, None);
+            }
+            if let Some(queries) = &self.statistics_queries {
+                for &query_pool in queries.iter() {
+                    self.context.device.destroy_query_pool(query_pool, None);
+                }
+            }
+            if let Some(timer) = &self.gpu_timer {
+                timer.destroy(&self.context.device);
+            }
+            if let Some(pools) = &self.record_pools {
+                pools.destroy(&self.context.device);
+            }
+            self.watchdog.destroy(
+                &self.context.device,
+                &self.context.allocator,
+                &mut self.resources,
+            );
+            for readback in self.readbacks.iter_mut() {
+                if let Some(readback) = readback.take() {
+                    self.resources
+                        .unregister(ResourceKind::Buffer, readback.buffer);
+                    self.context.device.destroy_buffer(readback.buffer, None);
+                    self.context.allocator.free(readback.allocation);
+                }
+            }
+            self.animator.destroy(
+                &self.context.device,
+                &self.context.allocator,
+                &mut self.resources,
+            );
+            self.destroy_uniform_buffers();
+            self.context
+                .device
+                .destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            self.context
+                .device
+                .destroy_descriptor_set_layout(self.view_set_layout, None);
+            self.context.destroy();
+        }
+        self.destroyed = true;
+        // Destroying the device and instance reports every object still
+        // alive as a validation error, so a clean teardown adds none.
+        let teardown_errors = self.validation_log.error_count() - errors_before;
+        debug_assert!(
+            teardown_errors == 0 || std::thread::panicking(),
+            "{} validation errors during teardown, the last: {:?}",
+            teardown_errors,
+            self.validation_log.recent().last()
+        );
+        waited
+    }
 
-        (descriptor_pool, descriptor_sets)
+    /// A fence that never signals or a lost device is logged and returned;
+    /// the caller destroys everything regardless.
+    fn wait_for_shutdown(&self) -> Result<(), VulkanError> {
+        let fences: Vec<vk::Fence> = self
+            .frames
+            .iter()
+            .map(|frame| frame.in_flight_fence)
+            .collect();
+        let fenced = unsafe {
+            self.context.device.wait_for_fences(
+                &fences,
+                true,
+                SHUTDOWN_FENCE_TIMEOUT_MS * 1_000_000,
+            )
+        };
+        if let Err(error) = fenced {
+            error!("In-flight frames did not finish before shutdown: {}", error);
+        }
+        let idle = unsafe { self.context.device.device_wait_idle() };
+        if let Err(error) = idle {
+            error!("vkDeviceWaitIdle failed during shutdown: {}", error);
+        }
+        fenced.and(idle).map_err(VulkanError::at(Stage::Shutdown))
     }
+}
 
-    fn create_descriptor_sets(
-        device: &ash::Device,
-        descriptor_pool: vk::DescriptorPool,
-        descriptor_set_layout: vk::DescriptorSetLayout,
-        uniform_buffers: &[vk::Buffer],
-        num_images: usize,
-    ) -> Vec<vk::DescriptorSet> {
-        let layouts = vec![descriptor_set_layout; num_images];
-        let alloc_info = vk::DescriptorSetAllocateInfo::builder()
-            .descriptor_pool(descriptor_pool)
-            .set_layouts(&layouts);
+/// The fallback for a renderer that wasn't passed to `destroy`, `close` or
+/// `shutdown`: tears down the same way but only logs what went wrong, even
+/// if the device has been lost.
+impl Drop for VulkanApp {
+    fn drop(&mut self) {
+        if self.destroyed {
+            return;
+        }
+        if let Err(error) = unsafe { self.teardown() } {
+            error!("{}", error);
+        }
 
-        let descriptor_sets = unsafe { device.allocate_descriptor_sets(&alloc_info).unwrap() };
+        let leaks = self.resource_report();
+        for leak in leaks.iter() {
+            warn!(
+                "Leaked {:?} '{}' ({} bytes, created at frame {})",
+                leak.kind, leak.name, leak.size, leak.created_frame
+            );
+        }
+        // Panicking while already unwinding would abort.
+        if PANIC_ON_LEAK && !leaks.is_empty() && !std::thread::panicking() {
+            panic!("{} GPU resources were not destroyed", leaks.len());
+        }
+    }
+}
 
-        for (i, &descriptor_set) in descriptor_sets.iter().enumerate() {
-            let buffer_info = vk::DescriptorBufferInfo::builder()
-                .buffer(uniform_buffers[i])
-                .offset(0)
-                .range(std::mem::size_of::<UniformBufferObject>() as vk::DeviceSize)
-                .build();
+/// Uniform data for one frame, staged on the CPU until
+/// `FrameCtx::render_and_present` writes it into the frame's uniform blocks.
+#[derive(Clone, Copy)]
+pub struct FrameUniforms {
+    pub frame: PerFrameUbo,
+    pub views: [PerViewUbo; VIEW_COUNT],
+    /// Time the scene's animation is evaluated at, on the CPU or pushed to
+    /// `animate.comp`.
+    pub animation_time: f32,
+    /// Linear colour the frame is cleared to.
+    pub clear_color: [f32; 4],
+}
 
-            let descriptor_write = vk::WriteDescriptorSet::builder()
-                .dst_set(descriptor_set)
-                .dst_binding(0)
-                .dst_array_element(0)
-                .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
-                .buffer_info(std::slice::from_ref(&buffer_info))
-                .build();
+/// A frame between `VulkanApp::begin_frame` and `render_and_present`. Its
+/// fence has been waited on and its swapchain image acquired, so its uniforms
+/// can be written without racing the GPU.
+///
+/// Borrows of `uniforms` end before `render_and_present` can consume the
+/// context, so nothing can write them while the frame is being recorded.
+/// Dropping the context without presenting discards the caller's changes but
+/// still renders and presents the image from the renderer's own state: an
+/// acquired image can only be given back by presenting it.
+///
+/// ```ignore
+/// let start = Instant::now();
+/// loop {
+///     let Some(mut frame) = app.begin_frame(&window)? else {
+///         continue;
+///     };
+///     let uniforms = frame.uniforms();
+///     uniforms.animation_time = start.elapsed().as_secs_f32() * 0.5;
+///     uniforms.frame.time = uniforms.animation_time;
+///     frame.render_and_present()?;
+/// }
+/// ```
+pub struct FrameCtx<'a> {
+    app: &'a mut VulkanApp,
+    window: &'a winit::window::Window,
+    image_index: u32,
+    uniforms: FrameUniforms,
+    presented: bool,
+}
 
-            unsafe { device.update_descriptor_sets(std::slice::from_ref(&descriptor_write), &[]) };
-        }
+impl FrameCtx<'_> {
+    pub fn uniforms(&mut self) -> &mut FrameUniforms {
+        &mut self.uniforms
+    }
 
-        descriptor_sets
+    pub fn render_and_present(mut self) -> Result<(), VulkanError> {
+        self.presented = true;
+        self.app
+            .finish_frame(self.window, self.image_index, &self.uniforms)
     }
 }
 
-impl Drop for VulkanApp {
+impl Drop for FrameCtx<'_> {
     fn drop(&mut self) {
-        unsafe {
-            self.device.device_wait_idle().unwrap();
-            self.cleanup_swapchain();
-            self.device.destroy_buffer(self.index_buffer, None);
-            self.device.free_memory(self.index_buffer_memory, None);
-            self.device.destroy_buffer(self.vertex_buffer, None);
-            self.device.free_memory(self.vertex_buffer_memory, None);
-            self.device
-                .destroy_semaphore(self.image_available_semaphore, None);
-            self.device
-                .destroy_semaphore(self.render_finished_semaphore, None);
-            self.device.destroy_fence(self.in_flight_fence, None);
-            self.device.destroy_command_pool(self.command_pool, None);
-            self.device.destroy_image_view(self.depth_image_view, None);
-            self.device.destroy_image(self.depth_image, None);
-            self.device.free_memory(self.depth_image_memory, None);
-            self.device
-                .destroy_descriptor_pool(self.descriptor_pool, None);
-            self.device
-                .destroy_descriptor_set_layout(self.descriptor_set_layout, None);
-            for i in 0..self.uniform_buffers.len() {
-                self.device.destroy_buffer(self.uniform_buffers[i], None);
-                self.device
-                    .free_memory(self.uniform_buffers_memory[i], None);
-            }
-            self.device.destroy_device(None);
-            self.surface_loader.destroy_surface(self.surface, None);
-            self.debug_utils_loader
-                .destroy_debug_utils_messenger(self.debug_messenger, None);
-            self.instance.destroy_instance(None);
+        if !self.presented {
+            let uniforms = self.app.frame_uniforms();
+            if let Err(error) = self
+                .app
+                .finish_frame(self.window, self.image_index, &uniforms)
+            {
+                warn!("Presenting a dropped frame: {}", error);
+            }
         }
     }
 }
@@ -0,0 +1,232 @@
+use ash::vk;
+
+use super::config::{GpuSelector, RendererConfig, WindowGpus};
+use super::settings::WindowSettings;
+use super::{GPU_ENV, HEIGHT, WIDTH};
+
+/// Printed by `--help`.
+pub const USAGE: &str = "\
+Usage: RustVulkan [options]
+
+Window:
+  --width <pixels>            Window width (default 800)
+  --height <pixels>           Window height (default 600)
+  --fullscreen [mode]         borderless (default) or exclusive
+  --mode <W>x<H>[@<Hz>]       Exclusive fullscreen in the video mode
+                              closest to this one, instead of the
+                              monitor's native mode
+  --title <text>              Window title and application name
+  --vsync on|off              Present with fifo (on) or immediate (off)
+  --gpu <index|name>          Render on this device, by index or part of
+                              its name; also read from CUBE_GPU
+  --gpu-for-window <w:g,...>  Render window w (0 the main one, then in
+                              the order Ctrl+N opens them) on GPU g
+  --reset-settings            Start from defaults, deleting the saved
+                              settings and window placement
+
+Renderer:
+  --msaa <samples>  --anisotropy <level|off>  --present-mode <mode>
+  --render-mode <continuous|on-demand>
+  --background <none|gradient|starfield|clouds|skybox>
+  --skybox <dir|png>  --ground-texture <pattern>
+  --cube-texture <png>  --menger <level>  --draw-stress <cubes>
+  --orbit-demo  --open-box  --record-inline  --sun  --day-length <seconds>
+  --sun-elevation <degrees>  --sun-kelvin <kelvin>  --memory-type <overrides>
+  --push-constant-budget <bytes>  --swapchain-usage <flags>
+  --final-layout <layout>  --clear-color <RRGGBB|cycle>  --reversed-z
+  --adaptive-resolution <ms>  --post-process  --letterbox [W:H]  --picking
+  --startup-report  --dump-config  --compile-shaders
+
+Modes:
+  --headless [--output <png>]  --serve <port>  --self-test  --print-info
+  --shutdown-stress  --resize-storm  --memory-benchmark  --record-benchmark
+
+Logging:
+  --verbose                   Log the devices, surface formats and
+                              extensions found at startup
+  --diagnostics-json <path>   Also write them to <path> as JSON
+  RUST_LOG=<filter>           Log levels per target (default info);
+                              off,vulkan::validation=error keeps only
+                              validation errors
+";
+
+/// How `--fullscreen` covers the monitor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FullscreenMode {
+    /// A borderless window the size of the monitor.
+    Borderless,
+    /// Takes over the monitor with the video mode closest to the window size.
+    Exclusive,
+}
+
+/// A video mode asked for with `--mode`. Exclusive fullscreen takes the
+/// monitor's mode closest to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VideoModeRequest {
+    pub width: u32,
+    pub height: u32,
+    /// Any refresh rate, the highest first, if `None`.
+    pub refresh_hz: Option<u32>,
+}
+
+impl VideoModeRequest {
+    /// `WxH` or `WxH@Hz`.
+    fn parse(text: &str) -> Option<Self> {
+        let (size, refresh) = match text.split_once('@') {
+            Some((size, refresh)) => (size, Some(refresh)),
+            None => (text, None),
+        };
+        let (width, height) = size.split_once('x')?;
+        let positive = |text: &str| text.parse().ok().filter(|&value: &u32| value > 0);
+        Some(Self {
+            width: positive(width)?,
+            height: positive(height)?,
+            refresh_hz: match refresh {
+                Some(refresh) => Some(positive(refresh)?),
+                None => None,
+            },
+        })
+    }
+}
+
+/// Window options from the command line, and the renderer options they
+/// imply. Malformed values are reported instead of panicking, so they are
+/// caught before any window or device exists.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppConfig {
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: Option<FullscreenMode>,
+    /// Implies exclusive fullscreen.
+    pub video_mode: Option<VideoModeRequest>,
+    pub title: Option<String>,
+    pub vsync: Option<bool>,
+    pub gpu: Option<GpuSelector>,
+    pub window_gpus: WindowGpus,
+}
+
+impl AppConfig {
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let size = |flag: &str, default: u32| match value(args, flag)? {
+            Some(text) => match text.parse() {
+                Ok(0) | Err(_) => Err(format!(
+                    "{} expects a positive number of pixels, found `{}`",
+                    flag, text
+                )),
+                Ok(pixels) => Ok(pixels),
+            },
+            None => Ok(default),
+        };
+        let fullscreen = match args.iter().position(|arg| arg == "--fullscreen") {
+            Some(index) => match args.get(index + 1).map(String::as_str) {
+                Some("exclusive") => Some(FullscreenMode::Exclusive),
+                Some("borderless") => Some(FullscreenMode::Borderless),
+                Some(next) if !next.starts_with("--") => {
+                    return Err(format!(
+                        "--fullscreen expects borderless or exclusive, found `{}`",
+                        next
+                    ));
+                }
+                _ => Some(FullscreenMode::Borderless),
+            },
+            None => None,
+        };
+        let video_mode = match value(args, "--mode")? {
+            Some(text) => Some(
+                VideoModeRequest::parse(text)
+                    .ok_or_else(|| format!("--mode expects WxH or WxH@Hz, found `{}`", text))?,
+            ),
+            None => None,
+        };
+        let borderless = args
+            .windows(2)
+            .any(|pair| pair[0] == "--fullscreen" && pair[1] == "borderless");
+        let fullscreen = match video_mode {
+            Some(_) if borderless => {
+                return Err("--mode needs exclusive fullscreen, not borderless".to_string());
+            }
+            Some(_) => Some(FullscreenMode::Exclusive),
+            None => fullscreen,
+        };
+        let vsync = match value(args, "--vsync")? {
+            Some("on") => Some(true),
+            Some("off") => Some(false),
+            Some(other) => return Err(format!("--vsync expects on or off, found `{}`", other)),
+            None => None,
+        };
+        if vsync.is_some() && value(args, "--present-mode")?.is_some() {
+            return Err("--vsync and --present-mode both choose the present mode".to_string());
+        }
+        let title = value(args, "--title")?.map(str::to_string);
+        if title.as_deref() == Some("") {
+            return Err("--title can't be empty".to_string());
+        }
+        let gpu = value(args, "--gpu")?
+            .map(str::to_string)
+            .or_else(|| std::env::var(GPU_ENV).ok())
+            .map(|gpu| GpuSelector::parse(&gpu));
+        let window_gpus = match value(args, "--gpu-for-window")? {
+            Some(text) => WindowGpus::parse(text)
+                .map_err(|message| format!("--gpu-for-window: {}", message))?,
+            None => WindowGpus::default(),
+        };
+        if window_gpus.for_window(0).is_some() && value(args, "--gpu")?.is_some() {
+            return Err("--gpu and --gpu-for-window 0:... both choose the main GPU".to_string());
+        }
+        let gpu = window_gpus.for_window(0).cloned().or(gpu);
+        Ok(Self {
+            width: size("--width", WIDTH)?,
+            height: size("--height", HEIGHT)?,
+            fullscreen,
+            video_mode,
+            title,
+            vsync,
+            gpu,
+            window_gpus,
+        })
+    }
+
+    /// The size and fullscreen state the window was last closed with,
+    /// where `args` don't choose them.
+    pub fn apply_window_settings(&mut self, window: &WindowSettings, args: &[String]) {
+        if !args.iter().any(|arg| arg == "--width") {
+            self.width = window.width;
+        }
+        if !args.iter().any(|arg| arg == "--height") {
+            self.height = window.height;
+        }
+        if window.fullscreen && self.fullscreen.is_none() {
+            self.fullscreen = Some(FullscreenMode::Borderless);
+        }
+    }
+
+    /// The title, present mode and device choices belong to the renderer.
+    pub fn apply_to(&self, config: &mut RendererConfig) {
+        if let Some(title) = &self.title {
+            config.app_name = title.clone();
+        }
+        if let Some(vsync) = self.vsync {
+            config.present_mode = if vsync {
+                vk::PresentModeKHR::FIFO
+            } else {
+                vk::PresentModeKHR::IMMEDIATE
+            };
+        }
+        if self.gpu.is_some() {
+            config.gpu = self.gpu.clone();
+        }
+        config.window_gpus = self.window_gpus.clone();
+    }
+}
+
+/// The value after `flag`, or an error if `flag` is last or followed by
+/// another flag.
+fn value<'a>(args: &'a [String], flag: &str) -> Result<Option<&'a str>, String> {
+    let Some(position) = args.iter().position(|arg| arg == flag) else {
+        return Ok(None);
+    };
+    match args.get(position + 1) {
+        Some(value) if !value.starts_with("--") => Ok(Some(value)),
+        _ => Err(format!("{} expects a value", flag)),
+    }
+}
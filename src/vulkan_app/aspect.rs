@@ -0,0 +1,87 @@
+use ash::vk;
+
+/// How the scene fits the framebuffer. Stretched, it fills all of it and
+/// the projection follows the window's shape, so extreme shapes see far
+/// more or far less of the scene. Letterboxed, it keeps one aspect ratio,
+/// width over height, in the largest centred rectangle that fits, with bars
+/// of the clear colour either side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AspectPolicy {
+    Stretch,
+    Letterbox(f32),
+}
+
+impl AspectPolicy {
+    /// Where the scene is drawn in a framebuffer of `extent`: all of it, or
+    /// the letterboxed part of it. Never less than a pixel each way, even
+    /// for an empty `extent` or a ratio that isn't positive.
+    pub fn scene_rect(self, extent: vk::Extent2D) -> vk::Rect2D {
+        let (width, height) = (extent.width.max(1), extent.height.max(1));
+        let (scene_width, scene_height) = match self {
+            AspectPolicy::Letterbox(ratio) if ratio.is_finite() && ratio > 0.0 => {
+                let fitted_width = (height as f32 * ratio).round() as u32;
+                if fitted_width < width {
+                    (fitted_width.max(1), height)
+                } else {
+                    let fitted_height = (width as f32 / ratio).round() as u32;
+                    (width, fitted_height.clamp(1, height))
+                }
+            }
+            _ => (width, height),
+        };
+        vk::Rect2D {
+            offset: vk::Offset2D {
+                x: ((width - scene_width) / 2) as i32,
+                y: ((height - scene_height) / 2) as i32,
+            },
+            extent: vk::Extent2D {
+                width: scene_width,
+                height: scene_height,
+            },
+        }
+    }
+
+    /// A ratio given as `W:H`, such as `16:9`, or as a number.
+    pub fn parse_ratio(text: &str) -> Option<f32> {
+        let ratio = match text.split_once(':') {
+            Some((width, height)) => width.parse::<f32>().ok()? / height.parse::<f32>().ok()?,
+            None => text.parse().ok()?,
+        };
+        (ratio.is_finite() && ratio > 0.0).then_some(ratio)
+    }
+}
+
+/// Width over height for a projection onto `extent`, counting empty sides
+/// as one pixel so it stays finite and positive for any extent.
+pub fn projection_aspect(extent: vk::Extent2D) -> f32 {
+    extent.width.max(1) as f32 / extent.height.max(1) as f32
+}
+
+/// The parts of a framebuffer of `extent` outside `scene`: the bars
+/// letterboxing leaves, if any.
+pub fn bars(extent: vk::Extent2D, scene: vk::Rect2D) -> Vec<vk::Rect2D> {
+    let (x, y) = (scene.offset.x as u32, scene.offset.y as u32);
+    let right = x + scene.extent.width;
+    let bottom = y + scene.extent.height;
+    let rect = |x: u32, y: u32, width: u32, height: u32| vk::Rect2D {
+        offset: vk::Offset2D {
+            x: x as i32,
+            y: y as i32,
+        },
+        extent: vk::Extent2D { width, height },
+    };
+    [
+        rect(0, 0, x, extent.height),
+        rect(right, 0, extent.width.saturating_sub(right), extent.height),
+        rect(x, 0, scene.extent.width, y),
+        rect(
+            x,
+            bottom,
+            scene.extent.width,
+            extent.height.saturating_sub(bottom),
+        ),
+    ]
+    .into_iter()
+    .filter(|bar| bar.extent.width > 0 && bar.extent.height > 0)
+    .collect()
+}
@@ -0,0 +1,150 @@
+use ash::vk;
+use log::{error, warn};
+use raw_window_handle::RawDisplayHandle;
+use std::ffi::CStr;
+use std::fmt;
+
+/// Set on a relaunch so a second surface failure isn't retried again.
+const RETRY_ENV: &str = "RUSTVULKAN_BACKEND_RETRY";
+
+/// Which windowing system the surface was created for, from the window's
+/// raw display handle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowBackend {
+    Wayland,
+    Xlib,
+    Xcb,
+    Win32,
+    Metal,
+    Other,
+    /// No window: `VulkanApp::new_headless`.
+    Headless,
+}
+
+impl WindowBackend {
+    pub fn from_display_handle(handle: RawDisplayHandle) -> Self {
+        match handle {
+            RawDisplayHandle::Wayland(_) => WindowBackend::Wayland,
+            RawDisplayHandle::Xlib(_) => WindowBackend::Xlib,
+            RawDisplayHandle::Xcb(_) => WindowBackend::Xcb,
+            RawDisplayHandle::Windows(_) => WindowBackend::Win32,
+            RawDisplayHandle::AppKit(_) | RawDisplayHandle::UiKit(_) => WindowBackend::Metal,
+            _ => WindowBackend::Other,
+        }
+    }
+
+    /// The other Linux backend, if this is one of them.
+    fn alternate(self) -> Option<BackendPreference> {
+        match self {
+            WindowBackend::Wayland => Some(BackendPreference::X11),
+            WindowBackend::Xlib | WindowBackend::Xcb => Some(BackendPreference::Wayland),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for WindowBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            WindowBackend::Wayland => "Wayland",
+            WindowBackend::Xlib => "X11 (Xlib)",
+            WindowBackend::Xcb => "X11 (XCB)",
+            WindowBackend::Win32 => "Win32",
+            WindowBackend::Metal => "Metal",
+            WindowBackend::Other => "other",
+            WindowBackend::Headless => "headless",
+        };
+        f.write_str(name)
+    }
+}
+
+/// `--force-x11` / `--force-wayland`: which backend winit should pick on Linux.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackendPreference {
+    Auto,
+    X11,
+    Wayland,
+}
+
+impl BackendPreference {
+    pub fn from_args(args: &[String]) -> Self {
+        if args.iter().any(|arg| arg == "--force-x11") {
+            BackendPreference::X11
+        } else if args.iter().any(|arg| arg == "--force-wayland") {
+            BackendPreference::Wayland
+        } else {
+            BackendPreference::Auto
+        }
+    }
+
+    fn flag(self) -> Option<&'static str> {
+        match self {
+            BackendPreference::Auto => None,
+            BackendPreference::X11 => Some("--force-x11"),
+            BackendPreference::Wayland => Some("--force-wayland"),
+        }
+    }
+
+    /// Points winit at the chosen backend. Must run before the event loop is
+    /// created, while no other thread can be reading the environment.
+    pub fn apply(self) {
+        let value = match self {
+            BackendPreference::Auto => return,
+            BackendPreference::X11 => "x11",
+            BackendPreference::Wayland => "wayland",
+        };
+        unsafe { std::env::set_var("WINIT_UNIX_BACKEND", value) };
+    }
+}
+
+/// Names of the instance extensions the surface for `handle` needs.
+pub fn surface_extensions(handle: RawDisplayHandle) -> Vec<String> {
+    ash_window::enumerate_required_extensions(handle)
+        .map(|names| {
+            names
+                .iter()
+                .map(|&name| {
+                    unsafe { CStr::from_ptr(name) }
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Explains a failed `vkCreate*SurfaceKHR` and, when the backend was picked
+/// automatically on Linux, relaunches once with the other one forced and
+/// exits with its status. Otherwise returns, for the caller to report the
+/// error.
+pub fn surface_creation_failed(backend: WindowBackend, extensions: &[String], error: vk::Result) {
+    warn!(
+        "Failed to create a {} surface: {} (instance surface extensions: {})",
+        backend,
+        error,
+        extensions.join(", ")
+    );
+    let args: Vec<String> = std::env::args().collect();
+    let alternate = backend
+        .alternate()
+        .filter(|_| BackendPreference::from_args(&args) == BackendPreference::Auto)
+        .filter(|_| std::env::var_os(RETRY_ENV).is_none());
+    let Some(flag) = alternate.and_then(BackendPreference::flag) else {
+        return;
+    };
+    warn!("Retrying with {}", flag);
+    let status = std::env::current_exe().and_then(|exe| {
+        std::process::Command::new(exe)
+            .args(&args[1..])
+            .arg(flag)
+            .env(RETRY_ENV, "1")
+            .status()
+    });
+    match status {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(error) => {
+            error!("Failed to relaunch: {}", error);
+            std::process::exit(1)
+        }
+    }
+}
@@ -0,0 +1,56 @@
+/// What fills the screen behind the scene. Anything but `None` is a pass
+/// drawn before the cube. `Skybox` has its own pipeline; the rest are one
+/// fullscreen pass, whose fragment shader picks the look from its
+/// BACKGROUND specialization constant: the discriminant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Background {
+    /// The clear colour, or the sky colour with a sun.
+    #[default]
+    None = 0,
+    /// Sky blue above the horizon fading to dark ground below.
+    Gradient = 1,
+    /// Twinkling stars on black.
+    Starfield = 2,
+    /// The gradient under a layer of drifting clouds, raymarched.
+    Clouds = 3,
+    /// The cube map from `--skybox`, or a generated gradient.
+    Skybox = 4,
+}
+
+impl Background {
+    const ALL: [Background; 5] = [
+        Background::None,
+        Background::Gradient,
+        Background::Starfield,
+        Background::Clouds,
+        Background::Skybox,
+    ];
+
+    /// Parses a `--background` value.
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|background| background.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Background::None => "none",
+            Background::Gradient => "gradient",
+            Background::Starfield => "starfield",
+            Background::Clouds => "clouds",
+            Background::Skybox => "skybox",
+        }
+    }
+
+    /// The one after this, wrapping back to `None`.
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    /// Whether the pass writes every pixel, so the colour attachment needn't
+    /// be cleared first.
+    pub fn covers_screen(self) -> bool {
+        self != Background::None
+    }
+}
@@ -0,0 +1,169 @@
+use cgmath::{InnerSpace, Matrix4, Point3, Rad, Vector3};
+
+use super::{
+    ORBIT_CAMERA_COAST_SECONDS, ORBIT_CAMERA_DISTANCE_RANGE, ORBIT_CAMERA_DOLLY_STEP,
+    ORBIT_CAMERA_MAX_PITCH, ORBIT_CAMERA_RADIANS_PER_PIXEL,
+};
+
+// Coasting stops below this, in radians per second.
+const MIN_VELOCITY: f32 = 0.01;
+// The drag velocity follows the cursor's with this time constant, in
+// seconds, so one uneven frame doesn't set how the orbit coasts.
+const VELOCITY_SMOOTHING: f32 = 0.05;
+// Longer gaps between updates, like the renderer idling, count as this.
+const MAX_UPDATE_SECONDS: f32 = 0.1;
+
+/// What a mouse drag does to an `OrbitCamera`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrbitDrag {
+    /// Turns the eye around the target.
+    Orbit,
+    /// Moves the target across the view.
+    Pan,
+}
+
+/// A camera on a sphere around `target`, with Z up. Dragging turns or pans
+/// it; letting go of an orbit drag leaves it coasting, slowing to a stop
+/// over ORBIT_CAMERA_COAST_SECONDS.
+pub struct OrbitCamera {
+    pub distance: f32,
+    pub target: Point3<f32>,
+    /// Around Z from +X, in radians.
+    pub yaw: f32,
+    /// Above the XY plane, in radians, short of either pole so the view's
+    /// up vector is never parallel to its direction.
+    pub pitch: f32,
+    // Yaw and pitch per second.
+    velocity: (f32, f32),
+    // Turned by the drag since the last update.
+    dragged: (f32, f32),
+    drag: Option<OrbitDrag>,
+    // Where the cursor last was inside the window; `None` once it leaves or
+    // enters, so coming back in doesn't count as a movement.
+    last_cursor: Option<(f32, f32)>,
+}
+
+impl OrbitCamera {
+    /// A camera at `eye`, looking at `target`.
+    pub fn new(eye: Point3<f32>, target: Point3<f32>) -> Self {
+        let offset = eye - target;
+        let distance = offset.magnitude();
+        let (min, max) = ORBIT_CAMERA_DISTANCE_RANGE;
+        Self {
+            distance: distance.clamp(min, max),
+            target,
+            yaw: offset.y.atan2(offset.x),
+            pitch: (offset.z / distance)
+                .asin()
+                .clamp(-ORBIT_CAMERA_MAX_PITCH, ORBIT_CAMERA_MAX_PITCH),
+            velocity: (0.0, 0.0),
+            dragged: (0.0, 0.0),
+            drag: None,
+            last_cursor: None,
+        }
+    }
+
+    pub fn eye(&self) -> Point3<f32> {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        self.target
+            + self.distance * Vector3::new(cos_pitch * cos_yaw, cos_pitch * sin_yaw, sin_pitch)
+    }
+
+    pub fn view(&self) -> Matrix4<f32> {
+        Matrix4::look_at_rh(self.eye(), self.target, Vector3::unit_z())
+    }
+
+    /// Starts `drag`, or ends it. An orbit drag that ends while the cursor
+    /// is still moving keeps turning the camera until `update` slows it.
+    pub fn set_dragging(&mut self, drag: OrbitDrag, dragging: bool) {
+        if dragging {
+            self.drag = Some(drag);
+            self.velocity = (0.0, 0.0);
+            self.dragged = (0.0, 0.0);
+        } else if self.drag == Some(drag) {
+            self.drag = None;
+        }
+    }
+
+    /// Follows the cursor to `(x, y)`, in pixels, in a view `height` pixels
+    /// high with a vertical field of view of `fov_y`. Returns whether the
+    /// camera moved.
+    pub fn cursor_moved(&mut self, x: f32, y: f32, fov_y: Rad<f32>, height: u32) -> bool {
+        let Some((last_x, last_y)) = self.last_cursor.replace((x, y)) else {
+            return false;
+        };
+        let (dx, dy) = (x - last_x, y - last_y);
+        match self.drag {
+            Some(OrbitDrag::Orbit) => {
+                let (yaw, pitch) = (
+                    -dx * ORBIT_CAMERA_RADIANS_PER_PIXEL,
+                    dy * ORBIT_CAMERA_RADIANS_PER_PIXEL,
+                );
+                self.turn(yaw, pitch);
+                self.dragged.0 += yaw;
+                self.dragged.1 += pitch;
+                true
+            }
+            Some(OrbitDrag::Pan) => {
+                // World units per pixel at the target's depth, so the
+                // target stays under the cursor.
+                let scale = 2.0 * self.distance * (fov_y.0 / 2.0).tan() / height.max(1) as f32;
+                let forward = (self.target - self.eye()).normalize();
+                let right = forward.cross(Vector3::unit_z()).normalize();
+                let up = right.cross(forward);
+                self.target += (up * dy - right * dx) * scale;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The cursor left or entered the window.
+    pub fn forget_cursor(&mut self) {
+        self.last_cursor = None;
+    }
+
+    /// Moves towards the target for positive `steps`, away for negative,
+    /// by the same factor per step whatever the distance.
+    pub fn dolly(&mut self, steps: f32) {
+        let (min, max) = ORBIT_CAMERA_DISTANCE_RANGE;
+        self.distance = (self.distance * ORBIT_CAMERA_DOLLY_STEP.powf(-steps)).clamp(min, max);
+    }
+
+    /// Advances the camera `elapsed` seconds: measures the drag's speed
+    /// while one is held, or coasts and slows down after it. Returns whether
+    /// the camera is still coasting.
+    pub fn update(&mut self, elapsed: f32) -> bool {
+        let elapsed = elapsed.min(MAX_UPDATE_SECONDS);
+        if elapsed <= 0.0 {
+            return self.is_coasting();
+        }
+        if self.drag == Some(OrbitDrag::Orbit) {
+            let blend = 1.0 - (-elapsed / VELOCITY_SMOOTHING).exp();
+            self.velocity.0 += (self.dragged.0 / elapsed - self.velocity.0) * blend;
+            self.velocity.1 += (self.dragged.1 / elapsed - self.velocity.1) * blend;
+            self.dragged = (0.0, 0.0);
+            return false;
+        }
+        if !self.is_coasting() {
+            self.velocity = (0.0, 0.0);
+            return false;
+        }
+        self.turn(self.velocity.0 * elapsed, self.velocity.1 * elapsed);
+        // Down to a hundredth over the coast time.
+        let decay = (-elapsed * 100f32.ln() / ORBIT_CAMERA_COAST_SECONDS).exp();
+        self.velocity = (self.velocity.0 * decay, self.velocity.1 * decay);
+        true
+    }
+
+    /// Still turning after a drag ended.
+    pub fn is_coasting(&self) -> bool {
+        self.drag.is_none() && self.velocity.0.hypot(self.velocity.1) >= MIN_VELOCITY
+    }
+
+    fn turn(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = (self.yaw + yaw) % std::f32::consts::TAU;
+        self.pitch = (self.pitch + pitch).clamp(-ORBIT_CAMERA_MAX_PITCH, ORBIT_CAMERA_MAX_PITCH);
+    }
+}
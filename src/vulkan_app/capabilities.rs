@@ -0,0 +1,174 @@
+use ash::vk;
+use std::ffi::CStr;
+use std::fmt;
+
+/// Optional device features the renderer adapts to, queried once at startup.
+#[derive(Clone, Copy, Debug)]
+pub struct DeviceCapabilities {
+    /// `VK_KHR_maintenance1`: flip Y with a negative viewport height so
+    /// projection matrices stay in conventional (y up) form. Without it the
+    /// projection's Y axis is flipped before upload instead.
+    pub negative_viewport_height: bool,
+    pub pipeline_statistics_query: bool,
+    /// `fillModeNonSolid`: needed for the wireframe render mode.
+    pub fill_mode_non_solid: bool,
+    /// `VK_KHR_depth_stencil_resolve`, core from Vulkan 1.2: a multisampled
+    /// depth attachment can be resolved to a single-sample image. With MSAA
+    /// the dynamic rendering path resolves sample 0 for the depth view to
+    /// read; render passes, which would need `vkCreateRenderPass2`, leave it
+    /// multisampled.
+    pub depth_stencil_resolve: bool,
+    /// Highest sample count both colour and depth framebuffer attachments support.
+    pub max_msaa_samples: u32,
+    pub sampler_anisotropy: bool,
+    pub max_anisotropy: f32,
+    /// `timestampComputeAndGraphics`: every graphics queue can write
+    /// timestamps, with ticks `timestamp_period` nanoseconds apart.
+    pub timestamps: bool,
+    pub timestamp_period: f32,
+    /// `dynamicRendering`: render without render pass or framebuffer
+    /// objects. `None` before Vulkan 1.2, where the extension's
+    /// dependencies aren't core.
+    pub dynamic_rendering: Option<DynamicRenderingSupport>,
+    /// `VK_KHR_portability_subset`: a layered implementation like MoltenVK,
+    /// which must have the extension enabled. Every feature the renderer
+    /// uses is checked above, so nothing else changes.
+    pub portability_subset: bool,
+    pub limits: DeviceLimits,
+}
+
+/// Where a device's `vkCmdBeginRendering` comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DynamicRenderingSupport {
+    /// Vulkan 1.3, on both the device and the instance.
+    Core,
+    /// `VK_KHR_dynamic_rendering`, on a 1.2 device or instance.
+    Extension,
+}
+
+impl fmt::Display for DynamicRenderingSupport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DynamicRenderingSupport::Core => "Vulkan 1.3",
+            DynamicRenderingSupport::Extension => "VK_KHR_dynamic_rendering",
+        })
+    }
+}
+
+/// The `VkPhysicalDeviceLimits` that decide how per-draw data is bound and
+/// how large a frame can be. Minimal drivers sit at or near the spec
+/// minimums.
+#[derive(Clone, Copy, Debug)]
+pub struct DeviceLimits {
+    pub max_push_constants_size: u32,
+    pub max_per_stage_descriptor_uniform_buffers: u32,
+    pub max_descriptor_set_uniform_buffers_dynamic: u32,
+    pub max_bound_descriptor_sets: u32,
+    pub max_framebuffer_width: u32,
+    pub max_framebuffer_height: u32,
+    pub max_image_dimension_2d: u32,
+    pub min_uniform_buffer_offset_alignment: vk::DeviceSize,
+}
+
+impl DeviceLimits {
+    /// The top-left part of a `surface` sized swapchain image the device can
+    /// render to: no larger than a framebuffer or a 2D depth image may be,
+    /// and at least one pixel each way. Smaller than `surface` only on
+    /// surfaces past the device's limits, which are then letterboxed.
+    pub fn render_extent(&self, surface: vk::Extent2D) -> vk::Extent2D {
+        let max_width = self.max_framebuffer_width.min(self.max_image_dimension_2d);
+        let max_height = self.max_framebuffer_height.min(self.max_image_dimension_2d);
+        vk::Extent2D {
+            width: surface.width.clamp(1, max_width.max(1)),
+            height: surface.height.clamp(1, max_height.max(1)),
+        }
+    }
+}
+
+impl DeviceCapabilities {
+    /// `instance_version` is what the instance was created for; the
+    /// device's core features beyond it can't be used.
+    pub fn query(
+        instance: &ash::Instance,
+        pdevice: vk::PhysicalDevice,
+        instance_version: u32,
+    ) -> Self {
+        let available_extensions = unsafe {
+            instance
+                .enumerate_device_extension_properties(pdevice)
+                .unwrap()
+        };
+        let has_extension = |wanted: &CStr| {
+            available_extensions.iter().any(|ext| {
+                let name = unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) };
+                name == wanted
+            })
+        };
+        let features = unsafe { instance.get_physical_device_features(pdevice) };
+        let properties = unsafe { instance.get_physical_device_properties(pdevice) };
+        let limits = properties.limits;
+        let api_version = properties.api_version.min(instance_version);
+        let dynamic_rendering = if api_version >= vk::API_VERSION_1_3 {
+            Some(DynamicRenderingSupport::Core)
+        } else if api_version >= vk::API_VERSION_1_2
+            && has_extension(vk::KhrDynamicRenderingFn::name())
+        {
+            Some(DynamicRenderingSupport::Extension)
+        } else {
+            None
+        };
+        // Only asked for from 1.2 on, so `vkGetPhysicalDeviceFeatures2` is core.
+        let dynamic_rendering = dynamic_rendering.filter(|_| {
+            let mut dynamic_rendering_features =
+                vk::PhysicalDeviceDynamicRenderingFeatures::default();
+            let mut features2 =
+                vk::PhysicalDeviceFeatures2::builder().push_next(&mut dynamic_rendering_features);
+            unsafe { instance.get_physical_device_features2(pdevice, &mut features2) };
+            dynamic_rendering_features.dynamic_rendering == vk::TRUE
+        });
+        let sample_counts =
+            limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts;
+
+        Self {
+            negative_viewport_height: has_extension(vk::KhrMaintenance1Fn::name()),
+            pipeline_statistics_query: features.pipeline_statistics_query == vk::TRUE,
+            fill_mode_non_solid: features.fill_mode_non_solid == vk::TRUE,
+            depth_stencil_resolve: api_version >= vk::API_VERSION_1_2
+                || has_extension(vk::KhrDepthStencilResolveFn::name()),
+            // Sample count flags are the counts themselves, one bit each.
+            max_msaa_samples: 1 << (31 - sample_counts.as_raw().max(1).leading_zeros()),
+            sampler_anisotropy: features.sampler_anisotropy == vk::TRUE,
+            max_anisotropy: limits.max_sampler_anisotropy,
+            timestamps: limits.timestamp_compute_and_graphics == vk::TRUE,
+            timestamp_period: limits.timestamp_period,
+            dynamic_rendering,
+            portability_subset: has_extension(vk::KhrPortabilitySubsetFn::name()),
+            limits: DeviceLimits {
+                max_push_constants_size: limits.max_push_constants_size,
+                max_per_stage_descriptor_uniform_buffers: limits
+                    .max_per_stage_descriptor_uniform_buffers,
+                max_descriptor_set_uniform_buffers_dynamic: limits
+                    .max_descriptor_set_uniform_buffers_dynamic,
+                max_bound_descriptor_sets: limits.max_bound_descriptor_sets,
+                max_framebuffer_width: limits.max_framebuffer_width,
+                max_framebuffer_height: limits.max_framebuffer_height,
+                max_image_dimension_2d: limits.max_image_dimension2_d,
+                min_uniform_buffer_offset_alignment: limits.min_uniform_buffer_offset_alignment,
+            },
+        }
+    }
+
+    pub fn extension_names(&self) -> Vec<&'static CStr> {
+        let mut names = Vec::new();
+        if self.negative_viewport_height {
+            names.push(vk::KhrMaintenance1Fn::name());
+        }
+        if self.dynamic_rendering == Some(DynamicRenderingSupport::Extension) {
+            names.push(vk::KhrDynamicRenderingFn::name());
+        }
+        if self.portability_subset {
+            names.push(vk::KhrPortabilitySubsetFn::name());
+        }
+        names
+    }
+}
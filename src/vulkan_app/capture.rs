@@ -0,0 +1,237 @@
+use ash::vk;
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread::JoinHandle;
+
+use super::allocator::Allocation;
+use super::protocol::{EncodedFrame, FrameFormat};
+use super::{png, qoi};
+
+/// What a frame's readback is for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CaptureTarget {
+    /// Handed out by `VulkanApp::take_capture`.
+    Stream,
+    /// Encoded and written by the `ScreenshotWriter`.
+    Screenshot(PathBuf),
+}
+
+/// Host-visible buffer a swapchain image is copied into for readback. Each
+/// frame in flight has its own, so a capture is collected once that frame's
+/// fence has signalled rather than by stalling the frame that recorded it.
+pub struct ReadbackBuffer {
+    pub buffer: vk::Buffer,
+    pub allocation: Allocation,
+    pub extent: vk::Extent2D,
+    pub format: vk::Format,
+    /// Targets of the copy recorded in this frame's command buffer, if any.
+    pub pending: Vec<CaptureTarget>,
+}
+
+impl ReadbackBuffer {
+    pub fn size(extent: vk::Extent2D) -> vk::DeviceSize {
+        extent.width as vk::DeviceSize * extent.height as vk::DeviceSize * 4
+    }
+
+    /// Copies `image`, which the render pass left in `layout`, into the
+    /// buffer and moves it to `release_layout`: PRESENT_SRC_KHR for
+    /// presentation, or back to TRANSFER_SRC_OPTIMAL when headless. Recorded after the render pass ends. The image must
+    /// have TRANSFER_SRC usage.
+    pub fn record_copy(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        image: vk::Image,
+        layout: vk::ImageLayout,
+        release_layout: vk::ImageLayout,
+    ) {
+        let range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let to_transfer = vk::ImageMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .old_layout(layout)
+            .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(range);
+        let to_present = vk::ImageMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .dst_access_mask(vk::AccessFlags::empty())
+            .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .new_layout(release_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(range);
+        let buffer_barrier = vk::BufferMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(self.buffer)
+            .offset(0)
+            .size(vk::WHOLE_SIZE);
+        let region = vk::BufferImageCopy::builder()
+            .image_subresource(vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            })
+            .image_extent(vk::Extent3D {
+                width: self.extent.width,
+                height: self.extent.height,
+                depth: 1,
+            });
+
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                std::slice::from_ref(&to_transfer),
+            );
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self.buffer,
+                std::slice::from_ref(&region),
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST | vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[],
+                std::slice::from_ref(&buffer_barrier),
+                std::slice::from_ref(&to_present),
+            );
+        }
+    }
+
+    /// Copies the buffer's contents out, in the swapchain's format. The copy
+    /// must have completed.
+    pub fn read(&self) -> Vec<u8> {
+        let size = Self::size(self.extent);
+        unsafe {
+            std::slice::from_raw_parts(self.allocation.mapped_ptr() as *const u8, size as usize)
+                .to_vec()
+        }
+    }
+}
+
+/// A swapchain image read back to the CPU.
+pub struct CapturedFrame {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl CapturedFrame {
+    /// Converts pixels read back from a swapchain image of `format` to RGBA.
+    pub fn from_readback(mut data: Vec<u8>, extent: vk::Extent2D, format: vk::Format) -> Self {
+        if matches!(
+            format,
+            vk::Format::B8G8R8A8_SRGB | vk::Format::B8G8R8A8_UNORM
+        ) {
+            for pixel in data.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        Self {
+            width: extent.width,
+            height: extent.height,
+            rgba: data,
+        }
+    }
+
+    pub fn encode(&self, format: FrameFormat) -> EncodedFrame {
+        let payload = match format {
+            FrameFormat::Rgba => self.rgba.clone(),
+            FrameFormat::Png => png::encode_rgba(self.width, self.height, &self.rgba),
+        };
+        EncodedFrame {
+            format,
+            width: self.width,
+            height: self.height,
+            payload,
+        }
+    }
+
+    /// Writes a QOI file for a `.qoi` path and a PNG otherwise.
+    pub fn write_image(&self, path: &Path) -> std::io::Result<()> {
+        let encoded = if path.extension().is_some_and(|extension| extension == "qoi") {
+            qoi::encode_rgba(self.width, self.height, &self.rgba)
+        } else {
+            png::encode_rgba(self.width, self.height, &self.rgba)
+        };
+        std::fs::write(path, encoded)
+    }
+}
+
+struct ScreenshotJob {
+    path: PathBuf,
+    data: Vec<u8>,
+    extent: vk::Extent2D,
+    format: vk::Format,
+}
+
+/// Converts, encodes and writes screenshots on a background thread, so the
+/// render loop only pays for copying the readback buffer out.
+pub struct ScreenshotWriter {
+    jobs: Option<Sender<ScreenshotJob>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ScreenshotWriter {
+    pub fn new() -> Self {
+        let (jobs, receiver) = mpsc::channel::<ScreenshotJob>();
+        let thread = std::thread::spawn(move || {
+            for job in receiver {
+                let frame = CapturedFrame::from_readback(job.data, job.extent, job.format);
+                match frame.write_image(&job.path) {
+                    Ok(()) => info!("Saved {}", job.path.display()),
+                    Err(error) => warn!("Failed to save {}: {}", job.path.display(), error),
+                }
+            }
+        });
+        Self {
+            jobs: Some(jobs),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn write(&self, path: PathBuf, data: Vec<u8>, extent: vk::Extent2D, format: vk::Format) {
+        let job = ScreenshotJob {
+            path,
+            data,
+            extent,
+            format,
+        };
+        if let Some(jobs) = &self.jobs {
+            jobs.send(job).expect("screenshot writer thread exited");
+        }
+    }
+}
+
+impl Drop for ScreenshotWriter {
+    /// Finishes every queued screenshot.
+    fn drop(&mut self) {
+        self.jobs.take();
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap();
+        }
+    }
+}
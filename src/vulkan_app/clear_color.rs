@@ -0,0 +1,46 @@
+use super::sun::srgb_to_linear;
+use super::CLEAR_HUE_CYCLE_SECONDS;
+
+/// What C steps through, as `--clear-color` writes them. After the last
+/// comes the hue cycle, then the first again.
+pub const CLEAR_COLOR_PRESETS: [&str; 4] = ["000000", "6495ed", "303030", "f0e6d2"];
+
+/// The sRGB colours the hue cycle passes through, a sixth of a cycle apart.
+/// Muted so the cube stays readable in front of them.
+const HUE_STOPS: [[f32; 3]; 6] = [
+    [0.6, 0.2, 0.2],
+    [0.6, 0.6, 0.2],
+    [0.2, 0.6, 0.2],
+    [0.2, 0.6, 0.6],
+    [0.2, 0.2, 0.6],
+    [0.6, 0.2, 0.6],
+];
+
+/// Parses `RRGGBB`, with or without a leading `#`, as an sRGB colour and
+/// returns it linear, as the clear value wants it for an sRGB swapchain.
+pub fn parse_hex_color(text: &str) -> Option<[f32; 3]> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let mut rgb = [0.0; 3];
+    for (channel, value) in rgb.iter_mut().enumerate() {
+        let byte = u8::from_str_radix(&hex[channel * 2..channel * 2 + 2], 16).ok()?;
+        *value = srgb_to_linear(byte as f32 / 255.0);
+    }
+    Some(rgb)
+}
+
+/// The hue cycle's linear colour `time` seconds in. Neighbouring stops are
+/// decoded and blended in linear space, so the swapchain's encoding doesn't
+/// darken the midpoints, and smoothstepped so the hue doesn't visibly kink
+/// at each stop.
+pub fn hue_cycle(time: f32) -> [f32; 3] {
+    let position = (time / CLEAR_HUE_CYCLE_SECONDS).rem_euclid(1.0) * HUE_STOPS.len() as f32;
+    let index = position as usize % HUE_STOPS.len();
+    let from = HUE_STOPS[index].map(srgb_to_linear);
+    let to = HUE_STOPS[(index + 1) % HUE_STOPS.len()].map(srgb_to_linear);
+    let t = position.fract();
+    let t = t * t * (3.0 - 2.0 * t);
+    std::array::from_fn(|i| from[i] + (to[i] - from[i]) * t)
+}
@@ -0,0 +1,478 @@
+use ash::vk;
+use std::path::PathBuf;
+
+use super::aspect::AspectPolicy;
+use super::background::Background;
+use super::clear_color::parse_hex_color;
+use super::memory::{parse_memory_flags, ResourceClass};
+use super::sun::SunConfig;
+use super::texgen::Pattern;
+use super::LETTERBOX_ASPECT;
+
+/// Called with the instance create info just before `vkCreateInstance`. Any
+/// struct chained through `p_next` must outlive the call, so keep it in the
+/// hook's captures rather than on the hook's stack.
+pub type InstanceCreateHook = Box<dyn FnMut(&mut vk::InstanceCreateInfo)>;
+
+/// Called with the device create info just before `vkCreateDevice`. The same
+/// lifetime rule as `InstanceCreateHook` applies to chained structs.
+pub type DeviceCreateHook = Box<dyn FnMut(&mut vk::DeviceCreateInfo)>;
+
+/// How `--gpu` or GPU_ENV names the physical device to render on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GpuSelector {
+    /// Index in the instance's enumeration.
+    Index(usize),
+    /// Case-insensitive part of the device name; the first match wins.
+    Name(String),
+}
+
+impl GpuSelector {
+    pub fn parse(text: &str) -> Self {
+        match text.parse() {
+            Ok(index) => GpuSelector::Index(index),
+            Err(_) => GpuSelector::Name(text.to_string()),
+        }
+    }
+
+    pub fn matches(&self, index: usize, name: &str) -> bool {
+        match self {
+            GpuSelector::Index(wanted) => *wanted == index,
+            GpuSelector::Name(part) => name.to_lowercase().contains(&part.to_lowercase()),
+        }
+    }
+}
+
+/// `--gpu-for-window`: the device each window renders on, by the window's
+/// place in opening order, 0 being the main window. Windows it leaves out
+/// render on the main window's device.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WindowGpus(Vec<(usize, GpuSelector)>);
+
+impl WindowGpus {
+    /// Comma-separated `window:gpu` pairs, such as `0:1,1:0`, each GPU by
+    /// index or part of its name as for `--gpu`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut pairs: Vec<(usize, GpuSelector)> = Vec::new();
+        for pair in text.split(',') {
+            let (window, gpu) = pair
+                .split_once(':')
+                .filter(|(_, gpu)| !gpu.is_empty())
+                .ok_or_else(|| format!("expected window:gpu, found `{}`", pair))?;
+            let window = window
+                .parse()
+                .map_err(|_| format!("`{}` is not a window number", window))?;
+            if pairs.iter().any(|&(seen, _)| seen == window) {
+                return Err(format!("window {} is given a GPU twice", window));
+            }
+            pairs.push((window, GpuSelector::parse(gpu)));
+        }
+        Ok(Self(pairs))
+    }
+
+    pub fn for_window(&self, window: usize) -> Option<&GpuSelector> {
+        self.0
+            .iter()
+            .find(|&&(mapped, _)| mapped == window)
+            .map(|(_, gpu)| gpu)
+    }
+}
+
+/// When the event loop draws. On demand, frames are only drawn while
+/// something on screen changes, as `VulkanApp::is_idle` decides, and the
+/// loop sleeps in between; pausing the animation is what usually lets it.
+/// Continuous draws every frame regardless, for measuring frame rates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    Continuous,
+    #[default]
+    OnDemand,
+}
+
+impl RenderMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "continuous" => Some(RenderMode::Continuous),
+            "on-demand" => Some(RenderMode::OnDemand),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderMode::Continuous => "continuous",
+            RenderMode::OnDemand => "on-demand",
+        }
+    }
+}
+
+/// Application identity reported to the driver through `VkApplicationInfo`,
+/// which profiling tools and vendor drivers key on, plus optional hooks for
+/// extending instance and device creation without patching the renderer.
+pub struct RendererConfig {
+    pub app_name: String,
+    pub app_version: u32,
+    pub engine_name: String,
+    pub engine_version: u32,
+    pub instance_create_hook: Option<InstanceCreateHook>,
+    pub device_create_hook: Option<DeviceCreateHook>,
+    /// Print how long each startup phase took once `VulkanApp::new` returns.
+    pub startup_report: bool,
+    /// Generated for the ground plane at startup.
+    pub ground_texture: Pattern,
+    /// Requested settings. `ConfigResolver` steps each down to what the
+    /// device supports and reports the downgrades.
+    pub msaa_samples: u32,
+    pub anisotropy: Option<f32>,
+    pub present_mode: vk::PresentModeKHR,
+    pub render_mode: RenderMode,
+    /// Swapchain usage on top of COLOR_ATTACHMENT, for integrations that copy,
+    /// sample or write the presented images. Startup fails if the surface
+    /// doesn't support them.
+    pub swapchain_usage: vk::ImageUsageFlags,
+    /// The layout the render pass leaves swapchain images in. Anything other
+    /// than PRESENT_SRC_KHR is transitioned back just before presenting.
+    pub final_layout: vk::ImageLayout,
+    /// Replace the animated scene with a still Menger sponge of this level,
+    /// one instance per cube. Lowered if the instances don't fit the
+    /// memory budget.
+    pub menger_level: Option<u32>,
+    /// Replace the scene with this many small spinning cubes, each its own
+    /// draw instead of one instanced draw, to load command recording.
+    pub draw_stress: Option<u32>,
+    /// Add a few smaller cubes circling the scene, each drawn with its own
+    /// model matrix.
+    pub orbit_demo: bool,
+    /// Draw an open-ended box in place of the cube, double-sided, so its
+    /// inside shows through the open top.
+    pub open_box: bool,
+    /// Compile the cube's shaders from the GLSL source at startup instead
+    /// of using the SPIR-V built in. Needs the compile-shaders feature.
+    pub compile_shaders: bool,
+    /// Record long draw lists into secondary command buffers on a rayon
+    /// thread pool. Off, every draw is recorded on the render thread.
+    pub parallel_recording: bool,
+    /// Light the scene with an animated sun and tint the background to
+    /// match. Without one the scene is unlit.
+    pub sun: Option<SunConfig>,
+    /// Linear clear colour while there is no sun.
+    pub clear_color: [f32; 3],
+    /// Cycle the clear colour around the hue wheel with the animation,
+    /// instead of `clear_color`. The sun still takes precedence.
+    pub clear_color_cycle: bool,
+    /// Drawn behind the scene instead of the clear colour.
+    pub background: Background,
+    /// Animation time runs this many times faster than real time.
+    pub animation_speed: f32,
+    /// Yaw and elevation added to the default eye position.
+    pub camera_orbit: (cgmath::Deg<f32>, cgmath::Deg<f32>),
+    /// Show the frame-time graph from the first frame.
+    pub show_frame_graph: bool,
+    /// PNG drawn on each face of the cube, over its vertex colours. Set at
+    /// build time by the `CUBE_TEXTURE` environment variable or with
+    /// `--cube-texture`; a checkerboard stands in if it can't be loaded.
+    pub cube_texture: Option<PathBuf>,
+    /// Cube map for `Background::Skybox`: a directory of six face PNGs, or
+    /// one equirectangular PNG. Without one, or if it can't be loaded, the
+    /// skybox is a generated gradient.
+    pub skybox: Option<PathBuf>,
+    /// Push at most this many bytes of draw constants, below the device's
+    /// limit, to exercise the uniform slot fallback on any device.
+    pub push_constant_budget: Option<u32>,
+    /// Render on this physical device instead of the best scoring one that
+    /// can present to the window. Startup fails if it doesn't exist or
+    /// can't.
+    pub gpu: Option<GpuSelector>,
+    /// Devices for the windows `VulkanApp::open_window` opens; see
+    /// `WindowGpus`. Window 0's entry is `gpu`.
+    pub window_gpus: WindowGpus,
+    /// Preferred memory properties per resource class, replacing the
+    /// defaults `MemoryTypeSelector` picks. Each must keep what its class
+    /// requires; a device without a matching type falls back to that.
+    pub memory_types: Vec<(ResourceClass, vk::MemoryPropertyFlags)>,
+    /// Depth from 1 at the near plane to 0 at the far one, for precision
+    /// across larger scenes; see `DepthDirection`. To check it, add two
+    /// cubes flattened into large quads a hair apart near the far plane
+    /// with `VulkanApp::add_object`: where they overlap they flicker as the
+    /// camera moves, and stop flickering with this on.
+    pub reversed_z: bool,
+    /// Lower the scene's render scale, down to half the window's size,
+    /// while frames take longer than this many milliseconds, and raise it
+    /// again once they have time to spare. Frames are timed on the GPU
+    /// where timestamps are supported; otherwise the frame interval counts,
+    /// which vsync holds at the refresh period, so the target must be above
+    /// it.
+    pub adaptive_resolution: Option<f32>,
+    /// Draw the scene into a target of its own and finish the render pass
+    /// with a subpass that reads it back, adding a vignette and gamma; see
+    /// `PostParams`. Keeps to the render pass path, as subpasses need it.
+    pub post_process: bool,
+    /// Whether the scene fills the window or keeps an aspect ratio inside
+    /// it; see `AspectPolicy`.
+    pub aspect_policy: AspectPolicy,
+    /// Keep an object ID target alongside the attachments so that
+    /// `VulkanApp::pick` can tell which object is under the cursor; see
+    /// `set_picking`.
+    pub picking: bool,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            app_name: "Vulkan Triangle".to_string(),
+            app_version: vk::make_api_version(0, 1, 0, 0),
+            engine_name: "No Engine".to_string(),
+            engine_version: vk::make_api_version(0, 1, 0, 0),
+            instance_create_hook: None,
+            device_create_hook: None,
+            startup_report: false,
+            ground_texture: Pattern::default(),
+            msaa_samples: 4,
+            anisotropy: Some(16.0),
+            present_mode: vk::PresentModeKHR::MAILBOX,
+            render_mode: RenderMode::default(),
+            swapchain_usage: vk::ImageUsageFlags::empty(),
+            final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+            menger_level: None,
+            draw_stress: None,
+            orbit_demo: false,
+            open_box: false,
+            compile_shaders: false,
+            parallel_recording: true,
+            sun: None,
+            clear_color: [0.0, 0.0, 0.0],
+            clear_color_cycle: false,
+            background: Background::None,
+            animation_speed: 1.0,
+            camera_orbit: (cgmath::Deg(0.0), cgmath::Deg(0.0)),
+            show_frame_graph: false,
+            cube_texture: option_env!("CUBE_TEXTURE").map(PathBuf::from),
+            skybox: None,
+            push_constant_budget: None,
+            gpu: None,
+            window_gpus: WindowGpus::default(),
+            memory_types: Vec::new(),
+            reversed_z: false,
+            adaptive_resolution: None,
+            post_process: false,
+            aspect_policy: AspectPolicy::Stretch,
+            picking: false,
+        }
+    }
+}
+
+impl RendererConfig {
+    /// Defaults with `--startup-report`, `--ground-texture`, `--msaa`,
+    /// `--anisotropy`, `--present-mode`, `--render-mode`, `--swapchain-usage`,
+    /// `--final-layout`, `--menger`, `--draw-stress`, `--orbit-demo`, `--open-box`,
+    /// `--record-inline`, `--cube-texture`, `--skybox`,
+    /// `--push-constant-budget`, `--background`, `--memory-type`,
+    /// `--clear-color`, `--reversed-z`, `--adaptive-resolution`,
+    /// `--letterbox`, `--picking` and the sun
+    /// flags applied. `AppConfig` covers the window flags and `--gpu`. `--sun`, or any of `--day-length`,
+    /// `--sun-elevation` and `--sun-kelvin`, turns the sun on. `--skybox`
+    /// without `--background` shows the skybox. Anything the flags leave
+    /// alone comes from `defaults`, such as settings loaded from a file.
+    pub fn from_args(defaults: Self, args: &[String]) -> Self {
+        Self {
+            startup_report: args.iter().any(|arg| arg == "--startup-report"),
+            ground_texture: arg_value(args, "--ground-texture")
+                .map(|name| {
+                    Pattern::parse(name)
+                        .expect("--ground-texture expects checker, grid, uv, noise or labels")
+                })
+                .unwrap_or(defaults.ground_texture),
+            msaa_samples: arg_value(args, "--msaa")
+                .map(|samples| samples.parse().expect("--msaa expects a sample count"))
+                .unwrap_or(defaults.msaa_samples),
+            anisotropy: arg_value(args, "--anisotropy")
+                .map(|value| match value {
+                    "off" => None,
+                    value => Some(value.parse().expect("--anisotropy expects a number or off")),
+                })
+                .unwrap_or(defaults.anisotropy),
+            present_mode: arg_value(args, "--present-mode")
+                .map(|name| {
+                    parse_present_mode(name)
+                        .expect("--present-mode expects immediate, mailbox, fifo or fifo-relaxed")
+                })
+                .unwrap_or(defaults.present_mode),
+            render_mode: arg_value(args, "--render-mode")
+                .map(|name| {
+                    RenderMode::parse(name).expect("--render-mode expects continuous or on-demand")
+                })
+                .unwrap_or(defaults.render_mode),
+            swapchain_usage: arg_value(args, "--swapchain-usage")
+                .map(|names| {
+                    names
+                        .split(',')
+                        .map(|name| match name {
+                            "transfer-src" => vk::ImageUsageFlags::TRANSFER_SRC,
+                            "transfer-dst" => vk::ImageUsageFlags::TRANSFER_DST,
+                            "sampled" => vk::ImageUsageFlags::SAMPLED,
+                            "storage" => vk::ImageUsageFlags::STORAGE,
+                            _ => panic!(
+                                "--swapchain-usage expects a comma-separated list of \
+                                 transfer-src, transfer-dst, sampled and storage"
+                            ),
+                        })
+                        .fold(vk::ImageUsageFlags::empty(), |usage, flag| usage | flag)
+                })
+                .unwrap_or(defaults.swapchain_usage),
+            final_layout: arg_value(args, "--final-layout")
+                .map(|name| match name {
+                    "present" => vk::ImageLayout::PRESENT_SRC_KHR,
+                    "color-attachment" => vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                    "transfer-src" => vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    "shader-read" => vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    "general" => vk::ImageLayout::GENERAL,
+                    _ => panic!(
+                        "--final-layout expects present, color-attachment, transfer-src, \
+                         shader-read or general"
+                    ),
+                })
+                .unwrap_or(defaults.final_layout),
+            menger_level: arg_value(args, "--menger")
+                .map(|level| level.parse().expect("--menger expects a level"))
+                .or(defaults.menger_level),
+            cube_texture: arg_value(args, "--cube-texture")
+                .map(PathBuf::from)
+                .or(defaults.cube_texture),
+            skybox: arg_value(args, "--skybox")
+                .map(PathBuf::from)
+                .or(defaults.skybox),
+            push_constant_budget: arg_value(args, "--push-constant-budget")
+                .map(|bytes| {
+                    bytes
+                        .parse()
+                        .expect("--push-constant-budget expects a size in bytes")
+                })
+                .or(defaults.push_constant_budget),
+            gpu: defaults.gpu,
+            window_gpus: defaults.window_gpus,
+            background: arg_value(args, "--background")
+                .map(|name| {
+                    Background::parse(name)
+                        .expect("--background expects none, gradient, starfield, clouds or skybox")
+                })
+                .or(arg_value(args, "--skybox").map(|_| Background::Skybox))
+                .unwrap_or(defaults.background),
+            memory_types: arg_value(args, "--memory-type")
+                .map(|overrides| {
+                    overrides
+                        .split(',')
+                        .map(|entry| {
+                            entry
+                                .split_once('=')
+                                .and_then(|(class, flags)| {
+                                    Some((ResourceClass::parse(class)?, parse_memory_flags(flags)?))
+                                })
+                                .expect(
+                                    "--memory-type expects <class>=<flag>+<flag>,... with classes \
+                                     vertex, index, uniform, storage, staging and image and flags \
+                                     device-local, host-visible, host-coherent and host-cached",
+                                )
+                        })
+                        .collect()
+                })
+                .unwrap_or(defaults.memory_types),
+            draw_stress: arg_value(args, "--draw-stress")
+                .map(|cubes| {
+                    cubes
+                        .parse()
+                        .expect("--draw-stress expects a number of cubes")
+                })
+                .or(defaults.draw_stress),
+            orbit_demo: args.iter().any(|arg| arg == "--orbit-demo") || defaults.orbit_demo,
+            open_box: args.iter().any(|arg| arg == "--open-box") || defaults.open_box,
+            compile_shaders: args.iter().any(|arg| arg == "--compile-shaders")
+                || defaults.compile_shaders,
+            parallel_recording: !args.iter().any(|arg| arg == "--record-inline")
+                && defaults.parallel_recording,
+            reversed_z: args.iter().any(|arg| arg == "--reversed-z") || defaults.reversed_z,
+            adaptive_resolution: arg_value(args, "--adaptive-resolution")
+                .map(|ms| {
+                    ms.parse()
+                        .ok()
+                        .filter(|&ms: &f32| ms > 0.0)
+                        .expect("--adaptive-resolution expects a frame time in milliseconds")
+                })
+                .or(defaults.adaptive_resolution),
+            post_process: args.iter().any(|arg| arg == "--post-process") || defaults.post_process,
+            aspect_policy: match args.iter().position(|arg| arg == "--letterbox") {
+                Some(index) => AspectPolicy::Letterbox(
+                    match args.get(index + 1).filter(|next| !next.starts_with("--")) {
+                        Some(ratio) => AspectPolicy::parse_ratio(ratio)
+                            .expect("--letterbox expects a ratio such as 16:9 or 1.5"),
+                        None => LETTERBOX_ASPECT,
+                    },
+                ),
+                None => defaults.aspect_policy,
+            },
+            picking: args.iter().any(|arg| arg == "--picking") || defaults.picking,
+            clear_color_cycle: arg_value(args, "--clear-color") == Some("cycle")
+                || defaults.clear_color_cycle,
+            clear_color: arg_value(args, "--clear-color")
+                .filter(|&color| color != "cycle")
+                .map(|color| parse_hex_color(color).expect("--clear-color expects RRGGBB or cycle"))
+                .unwrap_or(defaults.clear_color),
+            sun: Self::sun_from_args(args).or(defaults.sun),
+            ..defaults
+        }
+    }
+
+    fn sun_from_args(args: &[String]) -> Option<SunConfig> {
+        let flags = ["--sun", "--day-length", "--sun-elevation", "--sun-kelvin"];
+        if !args.iter().any(|arg| flags.contains(&arg.as_str())) {
+            return None;
+        }
+        let mut sun = SunConfig::default();
+        if let Some(seconds) = arg_value(args, "--day-length") {
+            sun.day_length = seconds
+                .parse()
+                .expect("--day-length expects a number of seconds");
+        }
+        if let Some(degrees) = arg_value(args, "--sun-elevation") {
+            sun.max_elevation = cgmath::Deg(
+                degrees
+                    .parse()
+                    .expect("--sun-elevation expects an angle in degrees"),
+            );
+        }
+        if let Some(kelvin) = arg_value(args, "--sun-kelvin") {
+            let parsed: Option<Vec<f32>> = kelvin.split(',').map(|k| k.parse().ok()).collect();
+            let Some(&[horizon, zenith]) = parsed.as_deref() else {
+                panic!("--sun-kelvin expects <horizon>,<zenith> in kelvin");
+            };
+            sun.horizon_kelvin = horizon;
+            sun.zenith_kelvin = zenith;
+        }
+        Some(sun)
+    }
+}
+
+pub fn parse_present_mode(name: &str) -> Option<vk::PresentModeKHR> {
+    match name {
+        "immediate" => Some(vk::PresentModeKHR::IMMEDIATE),
+        "mailbox" => Some(vk::PresentModeKHR::MAILBOX),
+        "fifo" => Some(vk::PresentModeKHR::FIFO),
+        "fifo-relaxed" => Some(vk::PresentModeKHR::FIFO_RELAXED),
+        _ => None,
+    }
+}
+
+/// The name `parse_present_mode` reads back. Modes it doesn't know fall
+/// back to "fifo", which every surface supports.
+pub fn present_mode_name(mode: vk::PresentModeKHR) -> &'static str {
+    match mode {
+        vk::PresentModeKHR::IMMEDIATE => "immediate",
+        vk::PresentModeKHR::MAILBOX => "mailbox",
+        vk::PresentModeKHR::FIFO_RELAXED => "fifo-relaxed",
+        _ => "fifo",
+    }
+}
+
+pub fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let position = args.iter().position(|arg| arg == flag)?;
+    args.get(position + 1).map(String::as_str)
+}
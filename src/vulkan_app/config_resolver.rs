@@ -0,0 +1,149 @@
+use ash::vk;
+use std::fmt;
+
+use super::capabilities::DeviceCapabilities;
+use super::config::RendererConfig;
+
+/// The settings the renderer actually runs with once `RendererConfig` has
+/// been checked against the device.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EffectiveConfig {
+    pub msaa_samples: u32,
+    pub anisotropy: Option<f32>,
+    pub present_mode: vk::PresentModeKHR,
+}
+
+/// A requested setting the device couldn't honour, and what was used instead.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Downgrade {
+    pub field: &'static str,
+    pub requested: String,
+    pub applied: String,
+    pub reason: String,
+}
+
+impl fmt::Display for Downgrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} -> {} ({})",
+            self.field, self.requested, self.applied, self.reason
+        )
+    }
+}
+
+/// Walks each setting down its ladder until the device supports it:
+///
+/// - MSAA: round up to a power of two, then halve the sample count until
+///   the colour and depth attachments both support it, ending at 1x.
+/// - Anisotropy: clamp to `maxSamplerAnisotropy`; off without the
+///   `samplerAnisotropy` feature.
+/// - Present mode: IMMEDIATE -> MAILBOX -> FIFO, MAILBOX -> FIFO and
+///   FIFO_RELAXED -> FIFO. FIFO is always available.
+pub struct ConfigResolver<'a> {
+    capabilities: &'a DeviceCapabilities,
+    present_modes: &'a [vk::PresentModeKHR],
+}
+
+impl<'a> ConfigResolver<'a> {
+    pub fn new(
+        capabilities: &'a DeviceCapabilities,
+        present_modes: &'a [vk::PresentModeKHR],
+    ) -> Self {
+        Self {
+            capabilities,
+            present_modes,
+        }
+    }
+
+    pub fn resolve(&self, config: &RendererConfig) -> (EffectiveConfig, Vec<Downgrade>) {
+        let mut downgrades = Vec::new();
+        let effective = EffectiveConfig {
+            msaa_samples: self.resolve_msaa(config.msaa_samples, &mut downgrades),
+            anisotropy: self.resolve_anisotropy(config.anisotropy, &mut downgrades),
+            present_mode: self.resolve_present_mode(config.present_mode, &mut downgrades),
+        };
+        (effective, downgrades)
+    }
+
+    fn resolve_msaa(&self, requested: u32, downgrades: &mut Vec<Downgrade>) -> u32 {
+        let requested = requested.max(1);
+        let mut samples = requested.next_power_of_two();
+        while samples > 1 && samples > self.capabilities.max_msaa_samples {
+            samples /= 2;
+        }
+        if samples != requested {
+            downgrades.push(Downgrade {
+                field: "msaa_samples",
+                requested: format!("{}x", requested),
+                applied: format!("{}x", samples),
+                reason: format!(
+                    "device supports up to {}x",
+                    self.capabilities.max_msaa_samples
+                ),
+            });
+        }
+        samples
+    }
+
+    fn resolve_anisotropy(
+        &self,
+        requested: Option<f32>,
+        downgrades: &mut Vec<Downgrade>,
+    ) -> Option<f32> {
+        let requested = requested?;
+        let (applied, reason) = if !self.capabilities.sampler_anisotropy {
+            (None, "samplerAnisotropy is not supported".to_string())
+        } else if requested > self.capabilities.max_anisotropy {
+            (
+                Some(self.capabilities.max_anisotropy),
+                format!(
+                    "maxSamplerAnisotropy is {}",
+                    self.capabilities.max_anisotropy
+                ),
+            )
+        } else {
+            return Some(requested);
+        };
+        downgrades.push(Downgrade {
+            field: "anisotropy",
+            requested: format!("{}x", requested),
+            applied: applied.map_or("off".to_string(), |value| format!("{}x", value)),
+            reason,
+        });
+        applied
+    }
+
+    fn resolve_present_mode(
+        &self,
+        requested: vk::PresentModeKHR,
+        downgrades: &mut Vec<Downgrade>,
+    ) -> vk::PresentModeKHR {
+        let ladder: &[vk::PresentModeKHR] = match requested {
+            vk::PresentModeKHR::IMMEDIATE => &[
+                vk::PresentModeKHR::IMMEDIATE,
+                vk::PresentModeKHR::MAILBOX,
+                vk::PresentModeKHR::FIFO,
+            ],
+            vk::PresentModeKHR::MAILBOX => &[vk::PresentModeKHR::MAILBOX, vk::PresentModeKHR::FIFO],
+            vk::PresentModeKHR::FIFO_RELAXED => {
+                &[vk::PresentModeKHR::FIFO_RELAXED, vk::PresentModeKHR::FIFO]
+            }
+            _ => &[vk::PresentModeKHR::FIFO],
+        };
+        let applied = ladder
+            .iter()
+            .copied()
+            .find(|mode| self.present_modes.contains(mode))
+            .unwrap_or(vk::PresentModeKHR::FIFO);
+        if applied != requested {
+            downgrades.push(Downgrade {
+                field: "present_mode",
+                requested: format!("{:?}", requested),
+                applied: format!("{:?}", applied),
+                reason: "not offered by the surface".to_string(),
+            });
+        }
+        applied
+    }
+}
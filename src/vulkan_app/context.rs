@@ -0,0 +1,1130 @@
+use ash::{vk, Entry};
+use log::{info, warn};
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle};
+use std::ffi::{CStr, CString};
+
+use super::allocator::{Allocation, Allocator};
+use super::capabilities::DeviceCapabilities;
+use super::config::{GpuSelector, RendererConfig};
+use super::config_resolver::EffectiveConfig;
+#[cfg(feature = "debug-utils")]
+use super::debug::vulkan_debug_callback;
+use super::debug::ValidationLog;
+use super::diagnostics::StartupDiagnostics;
+use super::error::{Stage, VulkanError};
+use super::memory::{MemoryTypeSelector, ResourceClass};
+use super::queue::QueueFamilyIndices;
+use super::swapchain_support::{SwapchainSupportDetails, PREFERRED_SURFACE_FORMAT};
+use super::watchdog::MarkerExtension;
+use super::ENABLE_VALIDATION;
+
+const VALIDATION_LAYER: &CStr = c"VK_LAYER_KHRONOS_validation";
+
+/// A window's surface and what the device supports on it, replaced as a
+/// unit when the surface is lost.
+pub struct SurfaceBundle {
+    pub loader: ash::extensions::khr::Surface,
+    pub surface: vk::SurfaceKHR,
+    /// As of the surface's creation. Capabilities follow the window's size,
+    /// so swapchains query them again through `VkContext::surface_support`.
+    pub support: SwapchainSupportDetails,
+}
+
+impl SurfaceBundle {
+    pub fn new(
+        loader: ash::extensions::khr::Surface,
+        surface: vk::SurfaceKHR,
+        physical_device: vk::PhysicalDevice,
+    ) -> Self {
+        let support = SwapchainSupportDetails::query(&loader, physical_device, surface);
+        Self {
+            loader,
+            surface,
+            support,
+        }
+    }
+
+    /// Swapchains on the surface must be destroyed first.
+    pub unsafe fn destroy(&self) {
+        unsafe { self.loader.destroy_surface(self.surface, None) };
+    }
+}
+
+/// The instance, the device and what was chosen on the way there: the
+/// objects every other part of the renderer is created from, which live
+/// until shutdown. Buffer, image and one-time command helpers are methods,
+/// so callers don't thread the device, physical device and memory types
+/// through.
+pub struct VkContext {
+    pub entry: Entry,
+    pub instance: ash::Instance,
+    // None unless ENABLE_VALIDATION.
+    #[cfg(feature = "debug-utils")]
+    pub debug_messenger: Option<(ash::extensions::ext::DebugUtils, vk::DebugUtilsMessengerEXT)>,
+    // None when headless.
+    pub surface: Option<SurfaceBundle>,
+    pub physical_device: vk::PhysicalDevice,
+    pub device: ash::Device,
+    pub graphics_queue: vk::Queue,
+    pub present_queue: vk::Queue,
+    pub queue_family_indices: QueueFamilyIndices,
+    pub memory: MemoryTypeSelector,
+    /// Where every buffer and image's memory comes from.
+    pub allocator: Allocator,
+    /// False for a context `for_device` made, which shares the instance
+    /// and leaves it for its owner to destroy.
+    pub owns_instance: bool,
+}
+
+impl VkContext {
+    /// The version instances are created for: the loader's, up to 1.3, so
+    /// that 1.3 devices can use dynamic rendering from the core. 1.0
+    /// loaders can't report their version and get 1.0.
+    pub fn instance_api_version(entry: &Entry) -> u32 {
+        match entry.try_enumerate_instance_version() {
+            Ok(Some(version)) => version.min(vk::API_VERSION_1_3),
+            _ => vk::API_VERSION_1_0,
+        }
+    }
+
+    /// With validation on, also enables the validation layer if it's
+    /// installed and chains a messenger into the create info, so instance
+    /// creation and destruction are reported to `validation_log` too.
+    /// With a `display`, enables the extensions a surface on it needs.
+    /// Where the loader offers `VK_KHR_portability_enumeration`, asks for
+    /// portability drivers like MoltenVK too, which it otherwise hides.
+    pub fn create_instance(
+        entry: &Entry,
+        display: Option<RawDisplayHandle>,
+        config: &mut RendererConfig,
+        validation_log: &ValidationLog,
+    ) -> Result<ash::Instance, VulkanError> {
+        let app_name = CString::new(config.app_name.as_str()).unwrap();
+        let engine_name = CString::new(config.engine_name.as_str()).unwrap();
+        let api_version = Self::instance_api_version(entry);
+        let app_info = vk::ApplicationInfo::builder()
+            .application_name(&app_name)
+            .application_version(config.app_version)
+            .engine_name(&engine_name)
+            .engine_version(config.engine_version)
+            .api_version(api_version);
+
+        let validation = cfg!(feature = "debug-utils") && ENABLE_VALIDATION;
+        let debug_utils = validation.then(|| ash::extensions::ext::DebugUtils::name().as_ptr());
+        let mut layer_names = Vec::new();
+        if validation {
+            let layers = entry
+                .enumerate_instance_layer_properties()
+                .map_err(VulkanError::at(Stage::InstanceCreation))?;
+            let installed = layers.iter().any(|layer| {
+                let name = unsafe { CStr::from_ptr(layer.layer_name.as_ptr()) };
+                name == VALIDATION_LAYER
+            });
+            if installed {
+                layer_names.push(VALIDATION_LAYER.as_ptr());
+            } else {
+                warn!(
+                    "{} is not installed; continuing without validation",
+                    VALIDATION_LAYER.to_string_lossy()
+                );
+            }
+        }
+        let surface_extensions = match display {
+            Some(display) => ash_window::enumerate_required_extensions(display)
+                .map_err(VulkanError::at(Stage::InstanceCreation))?,
+            None => &[],
+        };
+        let mut extension_names: Vec<_> = surface_extensions
+            .iter()
+            .copied()
+            .chain(debug_utils)
+            .collect();
+
+        let available_extensions = entry
+            .enumerate_instance_extension_properties(None)
+            .map_err(VulkanError::at(Stage::InstanceCreation))?;
+        let has_extension = |wanted: &CStr| {
+            available_extensions.iter().any(|ext| {
+                let name = unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) };
+                name == wanted
+            })
+        };
+        let mut flags = vk::InstanceCreateFlags::empty();
+        if has_extension(vk::KhrPortabilityEnumerationFn::name()) {
+            info!("Enumerating portability drivers");
+            extension_names.push(vk::KhrPortabilityEnumerationFn::name().as_ptr());
+            flags |= vk::InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR;
+            // VK_KHR_portability_subset needs it, and it's core from 1.1.
+            if api_version < vk::API_VERSION_1_1
+                && has_extension(vk::KhrGetPhysicalDeviceProperties2Fn::name())
+            {
+                extension_names.push(vk::KhrGetPhysicalDeviceProperties2Fn::name().as_ptr());
+            }
+        }
+
+        #[cfg(feature = "debug-utils")]
+        let mut debug_info = Self::debug_messenger_info(validation_log);
+        #[cfg(not(feature = "debug-utils"))]
+        let _ = validation_log;
+        let mut create_info = vk::InstanceCreateInfo::builder()
+            .flags(flags)
+            .application_info(&app_info)
+            .enabled_layer_names(&layer_names)
+            .enabled_extension_names(&extension_names);
+        #[cfg(feature = "debug-utils")]
+        if validation {
+            create_info = create_info.push_next(&mut debug_info);
+        }
+        if let Some(hook) = config.instance_create_hook.as_mut() {
+            hook(&mut create_info);
+        }
+        StartupDiagnostics::record(|diagnostics| {
+            diagnostics.instance_layers = StartupDiagnostics::names(
+                create_info.enabled_layer_count,
+                create_info.pp_enabled_layer_names,
+            );
+            diagnostics.instance_extensions = StartupDiagnostics::names(
+                create_info.enabled_extension_count,
+                create_info.pp_enabled_extension_names,
+            );
+        });
+
+        unsafe { entry.create_instance(&create_info, None) }
+            .map_err(VulkanError::at(Stage::InstanceCreation))
+    }
+
+    /// Errors and warnings of every type, passed to `vulkan_debug_callback`
+    /// with `validation_log`, which must outlive the messenger.
+    #[cfg(feature = "debug-utils")]
+    fn debug_messenger_info(
+        validation_log: &ValidationLog,
+    ) -> vk::DebugUtilsMessengerCreateInfoEXT {
+        vk::DebugUtilsMessengerCreateInfoEXT::builder()
+            .message_severity(
+                vk::DebugUtilsMessageSeverityFlagsEXT::ERROR
+                    | vk::DebugUtilsMessageSeverityFlagsEXT::WARNING,
+            )
+            .message_type(
+                vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
+                    | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
+                    | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
+            )
+            .pfn_user_callback(Some(vulkan_debug_callback))
+            .user_data(validation_log as *const ValidationLog as *mut std::ffi::c_void)
+            .build()
+    }
+
+    #[cfg(feature = "debug-utils")]
+    pub fn setup_debug_messenger(
+        entry: &Entry,
+        instance: &ash::Instance,
+        validation_log: &ValidationLog,
+    ) -> Result<(ash::extensions::ext::DebugUtils, vk::DebugUtilsMessengerEXT), VulkanError> {
+        let debug_info = Self::debug_messenger_info(validation_log);
+        let debug_utils_loader = ash::extensions::ext::DebugUtils::new(entry, instance);
+        let debug_messenger =
+            unsafe { debug_utils_loader.create_debug_utils_messenger(&debug_info, None) }
+                .map_err(VulkanError::at(Stage::DebugMessenger))?;
+
+        Ok((debug_utils_loader, debug_messenger))
+    }
+
+    /// Device `gpu` if given, which must be able to present to `surface`,
+    /// else the first device that can.
+    pub fn pick_physical_device(
+        instance: &ash::Instance,
+        surface_loader: &ash::extensions::khr::Surface,
+        surface: Option<vk::SurfaceKHR>,
+        gpu: Option<&GpuSelector>,
+    ) -> Result<(vk::PhysicalDevice, QueueFamilyIndices), VulkanError> {
+        let physical_devices = unsafe { instance.enumerate_physical_devices() }
+            .map_err(VulkanError::at(Stage::DevicePick))?;
+        let properties: Vec<_> = physical_devices
+            .iter()
+            .map(|&pdevice| unsafe { instance.get_physical_device_properties(pdevice) })
+            .collect();
+        let name = |index: usize| {
+            unsafe { CStr::from_ptr(properties[index].device_name.as_ptr()) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        let (index, indices) = match gpu {
+            // Reversed so that ties go to the first enumerated device.
+            None => (0..physical_devices.len())
+                .rev()
+                .filter_map(|index| {
+                    Self::check_device(instance, surface_loader, surface, physical_devices[index])
+                        .ok()
+                        .map(|indices| (index, indices))
+                })
+                .max_by_key(|&(index, _)| {
+                    Self::score_device(
+                        surface_loader,
+                        surface,
+                        physical_devices[index],
+                        &properties[index],
+                    )
+                })
+                .ok_or(VulkanError::NoSuitableGpu)?,
+            Some(selector) => {
+                let (index, _, indices) =
+                    Self::select_device(instance, surface_loader, surface, selector)?;
+                (index, indices)
+            }
+        };
+        info!(
+            "GPU {}: {} ({:?}, driver {:#x})",
+            index,
+            name(index),
+            properties[index].device_type,
+            properties[index].driver_version
+        );
+        StartupDiagnostics::record(|diagnostics| {
+            diagnostics.selected_device = Some(index);
+            diagnostics.graphics_family = indices.graphics_family;
+            diagnostics.present_family = indices.present_family;
+            diagnostics.transfer_family = indices.transfer_family;
+        });
+        Ok((physical_devices[index], indices))
+    }
+
+    /// The device `selector` names and its queue families, if it can render
+    /// to, and present to, `surface`; else a Config error listing the
+    /// devices, or saying why that one can't.
+    fn select_device(
+        instance: &ash::Instance,
+        surface_loader: &ash::extensions::khr::Surface,
+        surface: Option<vk::SurfaceKHR>,
+        selector: &GpuSelector,
+    ) -> Result<(usize, vk::PhysicalDevice, QueueFamilyIndices), VulkanError> {
+        let physical_devices = unsafe { instance.enumerate_physical_devices() }
+            .map_err(VulkanError::at(Stage::DevicePick))?;
+        let properties: Vec<_> = physical_devices
+            .iter()
+            .map(|&pdevice| unsafe { instance.get_physical_device_properties(pdevice) })
+            .collect();
+        let name = |index: usize| {
+            unsafe { CStr::from_ptr(properties[index].device_name.as_ptr()) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        let config_error = |message| VulkanError::Config {
+            stage: Stage::DevicePick,
+            message,
+        };
+        let index = (0..physical_devices.len())
+            .find(|&index| selector.matches(index, &name(index)))
+            .ok_or_else(|| {
+                let devices: Vec<_> = (0..physical_devices.len())
+                    .map(|index| format!("{}: {}", index, name(index)))
+                    .collect();
+                config_error(format!(
+                    "no GPU matches {:?}; the instance has [{}]",
+                    selector,
+                    devices.join(", ")
+                ))
+            })?;
+        let indices =
+            Self::check_device(instance, surface_loader, surface, physical_devices[index])
+                .map_err(|reason| {
+                    config_error(format!(
+                        "GPU {} ({}) can't render to this window: {}",
+                        index,
+                        name(index),
+                        reason
+                    ))
+                })?;
+        Ok((index, physical_devices[index], indices))
+    }
+
+    /// Ranks devices that passed `check_device`: discrete over integrated
+    /// over the rest, then larger images, then the preferred surface format.
+    fn score_device(
+        surface_loader: &ash::extensions::khr::Surface,
+        surface: Option<vk::SurfaceKHR>,
+        pdevice: vk::PhysicalDevice,
+        properties: &vk::PhysicalDeviceProperties,
+    ) -> u32 {
+        let type_score = match properties.device_type {
+            vk::PhysicalDeviceType::DISCRETE_GPU => 10_000,
+            vk::PhysicalDeviceType::INTEGRATED_GPU => 5_000,
+            vk::PhysicalDeviceType::VIRTUAL_GPU => 1_000,
+            _ => 0,
+        };
+        // 16 for the common 16384 limit, well below a device type step.
+        let image_score = properties.limits.max_image_dimension2_d / 1024;
+        let format_score = match surface {
+            Some(surface) => {
+                let formats =
+                    unsafe { surface_loader.get_physical_device_surface_formats(pdevice, surface) }
+                        .unwrap_or_default();
+                if formats.contains(&PREFERRED_SURFACE_FORMAT) {
+                    100
+                } else {
+                    0
+                }
+            }
+            None => 0,
+        };
+        type_score + image_score + format_score
+    }
+
+    /// `pdevice`'s queue families if it can render to and present to
+    /// `surface`, else why not. Without a surface, rendering is enough.
+    fn check_device(
+        instance: &ash::Instance,
+        surface_loader: &ash::extensions::khr::Surface,
+        surface: Option<vk::SurfaceKHR>,
+        pdevice: vk::PhysicalDevice,
+    ) -> Result<QueueFamilyIndices, &'static str> {
+        let indices = Self::find_queue_families(instance, surface_loader, surface, pdevice);
+        if indices.graphics_family.is_none() {
+            return Err("no graphics queue");
+        }
+        let Some(surface) = surface else {
+            return Ok(indices);
+        };
+        if indices.present_family.is_none() {
+            return Err("no queue family can present to its surface");
+        }
+        if !Self::check_device_extension_support(instance, pdevice) {
+            return Err("VK_KHR_swapchain is not supported");
+        }
+        let swapchain_support = SwapchainSupportDetails::query(surface_loader, pdevice, surface);
+        if swapchain_support.formats.is_empty() {
+            return Err("the surface has no formats on this device");
+        }
+        if swapchain_support.present_modes.is_empty() {
+            return Err("the surface has no present modes on this device");
+        }
+        Ok(indices)
+    }
+
+    fn check_device_extension_support(
+        instance: &ash::Instance,
+        pdevice: vk::PhysicalDevice,
+    ) -> bool {
+        let required_extensions = [ash::extensions::khr::Swapchain::name()];
+        let available_extensions = unsafe {
+            instance
+                .enumerate_device_extension_properties(pdevice)
+                .unwrap()
+        };
+
+        for required in required_extensions.iter() {
+            let found = available_extensions.iter().any(|ext| {
+                let name = unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) };
+                required == &name
+            });
+
+            if !found {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Without a surface, the graphics family stands in for the present one.
+    fn find_queue_families(
+        instance: &ash::Instance,
+        surface_loader: &ash::extensions::khr::Surface,
+        surface: Option<vk::SurfaceKHR>,
+        pdevice: vk::PhysicalDevice,
+    ) -> QueueFamilyIndices {
+        let queue_families =
+            unsafe { instance.get_physical_device_queue_family_properties(pdevice) };
+        let mut indices = QueueFamilyIndices::new();
+
+        for (i, queue_family) in queue_families.iter().enumerate() {
+            if queue_family.queue_flags.contains(vk::QueueFlags::GRAPHICS) {
+                indices.graphics_family = Some(i as u32);
+            }
+
+            let present_support = match surface {
+                Some(surface) => unsafe {
+                    surface_loader
+                        .get_physical_device_surface_support(pdevice, i as u32, surface)
+                        .unwrap()
+                },
+                None => queue_family.queue_flags.contains(vk::QueueFlags::GRAPHICS),
+            };
+            if present_support {
+                indices.present_family = Some(i as u32);
+            }
+
+            if indices.is_complete() {
+                break;
+            }
+        }
+        indices.transfer_family = queue_families
+            .iter()
+            .position(|queue_family| {
+                queue_family.queue_flags.contains(vk::QueueFlags::TRANSFER)
+                    && !queue_family.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+            })
+            .map(|i| i as u32);
+
+        indices
+    }
+
+    pub fn create_logical_device(
+        instance: &ash::Instance,
+        pdevice: vk::PhysicalDevice,
+        indices: &QueueFamilyIndices,
+        capabilities: &DeviceCapabilities,
+        effective: &EffectiveConfig,
+        swapchain: bool,
+        config: &mut RendererConfig,
+    ) -> Result<
+        (
+            ash::Device,
+            vk::Queue,
+            vk::Queue,
+            Option<vk::Queue>,
+            MarkerExtension,
+        ),
+        VulkanError,
+    > {
+        let mut unique_queue_families = std::collections::HashSet::new();
+        unique_queue_families.insert(indices.graphics_family.unwrap());
+        unique_queue_families.insert(indices.present_family.unwrap());
+        unique_queue_families.extend(indices.transfer_family);
+
+        let queue_priorities = [1.0];
+        let mut queue_create_infos = vec![];
+        for queue_family in unique_queue_families {
+            let queue_create_info = vk::DeviceQueueCreateInfo::builder()
+                .queue_family_index(queue_family)
+                .queue_priorities(&queue_priorities)
+                .build();
+            queue_create_infos.push(queue_create_info);
+        }
+
+        let physical_device_features = vk::PhysicalDeviceFeatures::builder()
+            .pipeline_statistics_query(capabilities.pipeline_statistics_query)
+            .fill_mode_non_solid(capabilities.fill_mode_non_solid)
+            .sampler_anisotropy(effective.anisotropy.is_some());
+        let marker_extension = Self::find_marker_extension(instance, pdevice);
+        let mut required_extensions = Vec::new();
+        if swapchain {
+            required_extensions.push(ash::extensions::khr::Swapchain::name().as_ptr());
+        }
+        if let Some(name) = marker_extension.name() {
+            required_extensions.push(name.as_ptr());
+        }
+        for name in capabilities.extension_names() {
+            required_extensions.push(name.as_ptr());
+        }
+
+        let mut dynamic_rendering =
+            vk::PhysicalDeviceDynamicRenderingFeatures::builder().dynamic_rendering(true);
+        let mut create_info = vk::DeviceCreateInfo::builder()
+            .queue_create_infos(&queue_create_infos)
+            .enabled_features(&physical_device_features)
+            .enabled_extension_names(&required_extensions);
+        if capabilities.dynamic_rendering.is_some() {
+            create_info = create_info.push_next(&mut dynamic_rendering);
+        }
+        if let Some(hook) = config.device_create_hook.as_mut() {
+            hook(&mut create_info);
+        }
+        StartupDiagnostics::record(|diagnostics| {
+            diagnostics.device_extensions = StartupDiagnostics::names(
+                create_info.enabled_extension_count,
+                create_info.pp_enabled_extension_names,
+            );
+        });
+
+        let device = unsafe { instance.create_device(pdevice, &create_info, None) }
+            .map_err(VulkanError::at(Stage::DeviceCreation))?;
+
+        let graphics_queue =
+            unsafe { device.get_device_queue(indices.graphics_family.unwrap(), 0) };
+        let present_queue = unsafe { device.get_device_queue(indices.present_family.unwrap(), 0) };
+        let transfer_queue = indices
+            .transfer_family
+            .map(|family| unsafe { device.get_device_queue(family, 0) });
+
+        Ok((
+            device,
+            graphics_queue,
+            present_queue,
+            transfer_queue,
+            marker_extension,
+        ))
+    }
+
+    fn find_marker_extension(
+        instance: &ash::Instance,
+        pdevice: vk::PhysicalDevice,
+    ) -> MarkerExtension {
+        let available_extensions = unsafe {
+            instance
+                .enumerate_device_extension_properties(pdevice)
+                .unwrap()
+        };
+        let supports = |extension: MarkerExtension| {
+            available_extensions.iter().any(|ext| {
+                let name = unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) };
+                extension.name() == Some(name)
+            })
+        };
+
+        if supports(MarkerExtension::NvCheckpoints) {
+            MarkerExtension::NvCheckpoints
+        } else if supports(MarkerExtension::AmdBufferMarker) {
+            MarkerExtension::AmdBufferMarker
+        } else {
+            MarkerExtension::None
+        }
+    }
+
+    /// `bundle`'s formats, present modes and capabilities on this device.
+    pub fn surface_support(&self, bundle: &SurfaceBundle) -> SwapchainSupportDetails {
+        SwapchainSupportDetails::query(&bundle.loader, self.physical_device, bundle.surface)
+    }
+
+    /// A surface on another window, for the device chosen for the first.
+    /// Fails if the present queue can't present to it, as the device isn't
+    /// chosen again.
+    pub fn create_window_surface(
+        &self,
+        window: &winit::window::Window,
+    ) -> Result<SurfaceBundle, VulkanError> {
+        let loader = ash::extensions::khr::Surface::new(&self.entry, &self.instance);
+        let surface = self.create_surface(window)?;
+        let bundle = SurfaceBundle::new(loader, surface, self.physical_device);
+        if let Err(error) = self.check_presentable(&bundle) {
+            unsafe { bundle.destroy() };
+            return Err(error);
+        }
+        Ok(bundle)
+    }
+
+    /// Fails if the present queue can't present to `bundle`, a surface on
+    /// another window.
+    pub fn check_presentable(&self, bundle: &SurfaceBundle) -> Result<(), VulkanError> {
+        let present_family = self.queue_family_indices.present_family.unwrap();
+        let supported = unsafe {
+            bundle.loader.get_physical_device_surface_support(
+                self.physical_device,
+                present_family,
+                bundle.surface,
+            )
+        }
+        .unwrap_or(false);
+        if !supported {
+            return Err(VulkanError::Config {
+                stage: Stage::SurfaceCreation,
+                message: format!(
+                    "queue family {} can't present to the new window",
+                    present_family
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    fn create_surface(
+        &self,
+        window: &winit::window::Window,
+    ) -> Result<vk::SurfaceKHR, VulkanError> {
+        unsafe {
+            ash_window::create_surface(
+                &self.entry,
+                &self.instance,
+                window.raw_display_handle(),
+                window.raw_window_handle(),
+                None,
+            )
+        }
+        .map_err(VulkanError::at(Stage::SurfaceCreation))
+    }
+
+    /// For `--gpu-for-window`: the device `selector` names and its queue
+    /// families for a surface on `window`, with the surface, queried on that
+    /// device. Fails, naming the device and why, if it can't render and
+    /// present to the window.
+    pub fn find_window_device(
+        &self,
+        selector: &GpuSelector,
+        window: &winit::window::Window,
+    ) -> Result<(vk::PhysicalDevice, QueueFamilyIndices, SurfaceBundle), VulkanError> {
+        let loader = ash::extensions::khr::Surface::new(&self.entry, &self.instance);
+        let surface = self.create_surface(window)?;
+        match Self::select_device(&self.instance, &loader, Some(surface), selector) {
+            Ok((index, physical_device, indices)) => {
+                info!("Window on GPU {}", index);
+                let bundle = SurfaceBundle::new(loader, surface, physical_device);
+                Ok((physical_device, indices, bundle))
+            }
+            Err(error) => {
+                unsafe { loader.destroy_surface(surface, None) };
+                Err(error)
+            }
+        }
+    }
+
+    /// A context for another of the instance's devices, `physical_device`,
+    /// with the graphics and present queues of `indices`: a device with
+    /// only VK_KHR_swapchain enabled, its own memory types and allocator,
+    /// and no surface. It shares this context's instance, and its `destroy`
+    /// leaves the instance alone.
+    pub fn for_device(
+        &self,
+        physical_device: vk::PhysicalDevice,
+        indices: QueueFamilyIndices,
+    ) -> Result<VkContext, VulkanError> {
+        let graphics_family = indices.graphics_family.unwrap();
+        let present_family = indices.present_family.unwrap();
+        let queue_priorities = [1.0];
+        let queue_create_infos: Vec<_> =
+            std::collections::BTreeSet::from([graphics_family, present_family])
+                .into_iter()
+                .map(|family| {
+                    vk::DeviceQueueCreateInfo::builder()
+                        .queue_family_index(family)
+                        .queue_priorities(&queue_priorities)
+                        .build()
+                })
+                .collect();
+        let extensions = [ash::extensions::khr::Swapchain::name().as_ptr()];
+        let create_info = vk::DeviceCreateInfo::builder()
+            .queue_create_infos(&queue_create_infos)
+            .enabled_extension_names(&extensions);
+        let memory_properties = unsafe {
+            self.instance
+                .get_physical_device_memory_properties(physical_device)
+        };
+        let memory = MemoryTypeSelector::new(memory_properties, &[]).map_err(|message| {
+            VulkanError::Config {
+                stage: Stage::MemorySelection,
+                message,
+            }
+        })?;
+        let device = unsafe {
+            self.instance
+                .create_device(physical_device, &create_info, None)
+        }
+        .map_err(VulkanError::at(Stage::DeviceCreation))?;
+        let allocator = Allocator::new(device.clone(), memory_properties);
+        Ok(VkContext {
+            entry: self.entry.clone(),
+            instance: self.instance.clone(),
+            #[cfg(feature = "debug-utils")]
+            debug_messenger: None,
+            surface: None,
+            physical_device,
+            graphics_queue: unsafe { device.get_device_queue(graphics_family, 0) },
+            present_queue: unsafe { device.get_device_queue(present_family, 0) },
+            device,
+            queue_family_indices: QueueFamilyIndices {
+                transfer_family: None,
+                ..indices
+            },
+            memory,
+            allocator,
+            owns_instance: false,
+        })
+    }
+
+    /// Replaces a lost surface with a new one on `window`, on the same
+    /// device, whose present queue must still support it. Swapchains on
+    /// the old surface must be destroyed first.
+    pub fn recreate_surface(&mut self, window: &winit::window::Window) -> Result<(), VulkanError> {
+        let old = self
+            .surface
+            .take()
+            .expect("headless renderers have no surface");
+        unsafe { old.destroy() };
+        let surface = unsafe {
+            ash_window::create_surface(
+                &self.entry,
+                &self.instance,
+                window.raw_display_handle(),
+                window.raw_window_handle(),
+                None,
+            )
+        }
+        .map_err(VulkanError::at(Stage::SurfaceCreation))?;
+        let bundle = SurfaceBundle::new(old.loader, surface, self.physical_device);
+        let present_family = self.queue_family_indices.present_family.unwrap();
+        let supported = unsafe {
+            bundle.loader.get_physical_device_surface_support(
+                self.physical_device,
+                present_family,
+                surface,
+            )
+        }
+        .unwrap_or(false);
+        // Kept even if unsupported, so `destroy` still destroys it.
+        self.surface = Some(bundle);
+        if !supported {
+            return Err(VulkanError::Config {
+                stage: Stage::SurfaceCreation,
+                message: format!(
+                    "queue family {} can't present to the new surface",
+                    present_family
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn create_command_pool(&self) -> vk::CommandPool {
+        let pool_info = vk::CommandPoolCreateInfo::builder()
+            .queue_family_index(self.queue_family_indices.graphics_family.unwrap())
+            .flags(vk::CommandPoolCreateFlags::empty());
+        unsafe { self.device.create_command_pool(&pool_info, None).unwrap() }
+    }
+
+    /// Command buffers from it are recorded once and freed after one
+    /// submission.
+    pub fn create_transfer_command_pool(&self, family: u32) -> vk::CommandPool {
+        let pool_info = vk::CommandPoolCreateInfo::builder()
+            .queue_family_index(family)
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT);
+        unsafe { self.device.create_command_pool(&pool_info, None).unwrap() }
+    }
+
+    pub fn create_buffer(
+        &self,
+        class: ResourceClass,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+    ) -> Result<(vk::Buffer, Allocation), VulkanError> {
+        let buffer_info = vk::BufferCreateInfo::builder()
+            .size(size)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+
+        let buffer = unsafe { self.device.create_buffer(&buffer_info, None) }
+            .map_err(VulkanError::at(Stage::Allocation))?;
+        let mem_requirements = unsafe { self.device.get_buffer_memory_requirements(buffer) };
+        let bound = self
+            .allocate(class, mem_requirements, true)
+            .and_then(|allocation| {
+                unsafe {
+                    self.device
+                        .bind_buffer_memory(buffer, allocation.memory, allocation.offset)
+                }
+                .map(|()| allocation)
+                .map_err(|error| {
+                    self.allocator.free(allocation);
+                    VulkanError::at(Stage::Allocation)(error)
+                })
+            });
+        match bound {
+            Ok(allocation) => Ok((buffer, allocation)),
+            Err(error) => {
+                unsafe { self.device.destroy_buffer(buffer, None) };
+                Err(error)
+            }
+        }
+    }
+
+    pub fn create_image(
+        &self,
+        width: u32,
+        height: u32,
+        samples: vk::SampleCountFlags,
+        format: vk::Format,
+        tiling: vk::ImageTiling,
+        usage: vk::ImageUsageFlags,
+    ) -> Result<(vk::Image, Allocation), VulkanError> {
+        let image_info = vk::ImageCreateInfo::builder()
+            .image_type(vk::ImageType::TYPE_2D)
+            .extent(vk::Extent3D {
+                width,
+                height,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(1)
+            .format(format)
+            .tiling(tiling)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .samples(samples);
+
+        let image = unsafe { self.device.create_image(&image_info, None) }
+            .map_err(VulkanError::at(Stage::Allocation))?;
+        let allocation = self.bind_image_memory(image, tiling)?;
+        Ok((image, allocation))
+    }
+
+    /// Allocates image memory for `image`, created with `tiling`, and binds
+    /// it. On failure the image is destroyed.
+    pub fn bind_image_memory(
+        &self,
+        image: vk::Image,
+        tiling: vk::ImageTiling,
+    ) -> Result<Allocation, VulkanError> {
+        let mem_requirements = unsafe { self.device.get_image_memory_requirements(image) };
+        let bound = self
+            .allocate(
+                ResourceClass::Image,
+                mem_requirements,
+                tiling == vk::ImageTiling::LINEAR,
+            )
+            .and_then(|allocation| {
+                unsafe {
+                    self.device
+                        .bind_image_memory(image, allocation.memory, allocation.offset)
+                }
+                .map(|()| allocation)
+                .map_err(|error| {
+                    self.allocator.free(allocation);
+                    VulkanError::at(Stage::Allocation)(error)
+                })
+            });
+        if bound.is_err() {
+            unsafe { self.device.destroy_image(image, None) };
+        }
+        bound
+    }
+
+    /// Memory for a resource of `class`, from the first of its candidate
+    /// types with room. Types that are out of memory are skipped with a
+    /// warning; other failures end the search.
+    fn allocate(
+        &self,
+        class: ResourceClass,
+        requirements: vk::MemoryRequirements,
+        linear: bool,
+    ) -> Result<Allocation, VulkanError> {
+        let candidates = self.memory.candidates(class, requirements.memory_type_bits);
+        if candidates.is_empty() {
+            return Err(VulkanError::Config {
+                stage: Stage::Allocation,
+                message: format!(
+                    "no memory type the resource can use is {:?}, which {} memory must be",
+                    class.required(),
+                    class.name()
+                ),
+            });
+        }
+        let mut result = vk::Result::ERROR_OUT_OF_DEVICE_MEMORY;
+        for type_index in candidates {
+            match self.allocator.allocate(requirements, type_index, linear) {
+                Ok(allocation) => {
+                    self.memory.record(class, type_index);
+                    return Ok(allocation);
+                }
+                Err(
+                    error @ (vk::Result::ERROR_OUT_OF_DEVICE_MEMORY
+                    | vk::Result::ERROR_OUT_OF_HOST_MEMORY),
+                ) => {
+                    warn!(
+                        "Memory type {} has no room for {} bytes of {} memory ({})",
+                        type_index,
+                        requirements.size,
+                        class.name(),
+                        error
+                    );
+                    result = error;
+                }
+                Err(error) => return Err(VulkanError::at(Stage::Allocation)(error)),
+            }
+        }
+        Err(VulkanError::OutOfMemory {
+            class,
+            size: requirements.size,
+            flags: self.memory.preferred(class),
+            result,
+        })
+    }
+
+    pub fn create_image_view(
+        &self,
+        image: vk::Image,
+        format: vk::Format,
+        aspect_flags: vk::ImageAspectFlags,
+    ) -> vk::ImageView {
+        let view_info = vk::ImageViewCreateInfo::builder()
+            .image(image)
+            .view_type(vk::ImageViewType::TYPE_2D)
+            .format(format)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: aspect_flags,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            });
+
+        unsafe { self.device.create_image_view(&view_info, None).unwrap() }
+    }
+
+    pub fn find_depth_format(&self) -> vk::Format {
+        self.find_supported_format(
+            &[
+                vk::Format::D32_SFLOAT,
+                vk::Format::D32_SFLOAT_S8_UINT,
+                vk::Format::D24_UNORM_S8_UINT,
+            ],
+            vk::ImageTiling::OPTIMAL,
+            vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+        )
+    }
+
+    pub fn find_supported_format(
+        &self,
+        candidates: &[vk::Format],
+        tiling: vk::ImageTiling,
+        features: vk::FormatFeatureFlags,
+    ) -> vk::Format {
+        for &format in candidates {
+            let props = unsafe {
+                self.instance
+                    .get_physical_device_format_properties(self.physical_device, format)
+            };
+
+            if tiling == vk::ImageTiling::LINEAR && props.linear_tiling_features.contains(features)
+            {
+                return format;
+            } else if tiling == vk::ImageTiling::OPTIMAL
+                && props.optimal_tiling_features.contains(features)
+            {
+                return format;
+            }
+        }
+
+        panic!("Failed to find supported format!");
+    }
+
+    /// Moves all of `image` from `old_layout` to `new_layout` in a one-time
+    /// command buffer on the graphics queue, from `command_pool`, and waits
+    /// for it. Panics on a transition it has no stages and access masks for.
+    pub fn transition_image_layout(
+        &self,
+        command_pool: vk::CommandPool,
+        image: vk::Image,
+        format: vk::Format,
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+    ) {
+        let (src_access, dst_access, src_stage, dst_stage, aspect_mask) =
+            match (old_layout, new_layout) {
+                (vk::ImageLayout::UNDEFINED, vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL) => {
+                    (
+                        vk::AccessFlags::empty(),
+                        vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                            | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                        vk::PipelineStageFlags::TOP_OF_PIPE,
+                        vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                        depth_aspect_flags(format),
+                    )
+                }
+                _ => panic!(
+                    "unsupported layout transition from {:?} to {:?}",
+                    old_layout, new_layout
+                ),
+            };
+        let barrier = vk::ImageMemoryBarrier::builder()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            })
+            .src_access_mask(src_access)
+            .dst_access_mask(dst_access);
+        self.submit_once_on(
+            command_pool,
+            self.graphics_queue,
+            |device, command_buffer| unsafe {
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    src_stage,
+                    dst_stage,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    std::slice::from_ref(&barrier),
+                );
+            },
+        );
+    }
+
+    /// Records a command buffer from `command_pool` with `record`, submits
+    /// it to `queue` and waits for the queue to drain. Only for setup work
+    /// outside the frame loop.
+    pub fn submit_once_on(
+        &self,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        record: impl FnOnce(&ash::Device, vk::CommandBuffer),
+    ) {
+        let device = &self.device;
+        let alloc_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        unsafe {
+            let command_buffer = device.allocate_command_buffers(&alloc_info).unwrap()[0];
+            device
+                .begin_command_buffer(command_buffer, &begin_info)
+                .unwrap();
+            record(device, command_buffer);
+            device.end_command_buffer(command_buffer).unwrap();
+            let submit_info =
+                vk::SubmitInfo::builder().command_buffers(std::slice::from_ref(&command_buffer));
+            device
+                .queue_submit(queue, std::slice::from_ref(&submit_info), vk::Fence::null())
+                .unwrap();
+            device.queue_wait_idle(queue).unwrap();
+            device.free_command_buffers(command_pool, std::slice::from_ref(&command_buffer));
+        }
+    }
+
+    /// Frees the allocator's blocks, then destroys the device, surface,
+    /// debug messenger and instance, in that order, or only the device if
+    /// the instance is shared. Everything created from the device must be
+    /// gone.
+    pub unsafe fn destroy(&self) {
+        self.allocator.destroy();
+        unsafe {
+            self.device.destroy_device(None);
+            if self.owns_instance {
+                if let Some(bundle) = &self.surface {
+                    bundle.destroy();
+                }
+                #[cfg(feature = "debug-utils")]
+                if let Some((loader, messenger)) = &self.debug_messenger {
+                    loader.destroy_debug_utils_messenger(*messenger, None);
+                }
+                self.instance.destroy_instance(None);
+            }
+        }
+    }
+}
+
+/// Depth formats `find_depth_format` can pick that also have a stencil
+/// component.
+fn has_stencil_component(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::D32_SFLOAT_S8_UINT | vk::Format::D24_UNORM_S8_UINT
+    )
+}
+
+/// Views and barriers on a combined depth/stencil image must name both
+/// aspects.
+pub fn depth_aspect_flags(format: vk::Format) -> vk::ImageAspectFlags {
+    if has_stencil_component(format) {
+        vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+    } else {
+        vk::ImageAspectFlags::DEPTH
+    }
+}
@@ -0,0 +1,53 @@
+/// The cube's `shader.vert` and `shader.frag`, compiled from the GLSL at
+/// startup for `--compile-shaders` instead of built in, so shader changes
+/// show up without a rebuild. Holds the same variants build.rs makes.
+#[cfg_attr(not(feature = "compile-shaders"), allow(dead_code))]
+pub struct CubeShaders {
+    vertex: Vec<u8>,
+    vertex_push_0: Vec<u8>,
+    fragment: Vec<u8>,
+    fragment_push_0: Vec<u8>,
+}
+
+impl CubeShaders {
+    /// Compiles the shaders in the source tree's shader directory. Errors
+    /// quote the GLSL lines the compiler rejected.
+    #[cfg(feature = "compile-shaders")]
+    pub fn compile() -> Result<Self, String> {
+        use super::glsl;
+        use std::path::Path;
+
+        let shader_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/shaders");
+        let compiler = shaderc::Compiler::new().ok_or("shaderc not found")?;
+        let compile = |file: &str, define| {
+            glsl::compile(&compiler, &shader_dir, &shader_dir.join(file), define)
+        };
+        Ok(Self {
+            vertex: compile("shader.vert", None)?,
+            vertex_push_0: compile("shader.vert", Some(("DRAW_PUSH_FIELDS", 0)))?,
+            fragment: compile("shader.frag", None)?,
+            fragment_push_0: compile("shader.frag", Some(("DRAW_PUSH_FIELDS", 0)))?,
+        })
+    }
+
+    #[cfg(not(feature = "compile-shaders"))]
+    pub fn compile() -> Result<Self, String> {
+        Err("--compile-shaders needs the compile-shaders feature".to_string())
+    }
+
+    /// For the cube pipeline pushing `push_fields` draw constant fields; see
+    /// `create_graphics_pipeline`.
+    pub fn vertex(&self, push_fields: usize) -> &[u8] {
+        match push_fields {
+            0 | 1 => &self.vertex_push_0,
+            _ => &self.vertex,
+        }
+    }
+
+    pub fn fragment(&self, push_fields: usize) -> &[u8] {
+        match push_fields {
+            0 => &self.fragment_push_0,
+            _ => &self.fragment,
+        }
+    }
+}
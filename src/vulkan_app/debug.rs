@@ -1,14 +1,155 @@
 use ash::vk;
+use log::Level;
+use std::collections::VecDeque;
 use std::ffi::CStr;
+use std::sync::atomic::{AtomicPtr, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
 
+use super::journal::{Journal, JournalEvent, JournalText};
+
+const RECENT_MESSAGE_CAPACITY: usize = 20;
+
+/// The newest error message, handed from the debug callback, which may run
+/// on any thread, to the render thread without a lock: the callback swaps a
+/// boxed message in and the render thread swaps it out, so each box has one
+/// owner. A message replaced before the render thread looks is dropped.
+struct LatestMessage(AtomicPtr<String>);
+
+impl LatestMessage {
+    fn put(&self, message: String) {
+        let old = self
+            .0
+            .swap(Box::into_raw(Box::new(message)), Ordering::AcqRel);
+        if !old.is_null() {
+            drop(unsafe { Box::from_raw(old) });
+        }
+    }
+
+    fn take(&self) -> Option<String> {
+        let message = self.0.swap(std::ptr::null_mut(), Ordering::AcqRel);
+        (!message.is_null()).then(|| *unsafe { Box::from_raw(message) })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.load(Ordering::Acquire).is_null()
+    }
+}
+
+impl Drop for LatestMessage {
+    fn drop(&mut self) {
+        self.take();
+    }
+}
+
+/// Recent messenger output, shared with the debug callback through `p_user_data`.
+/// Messages also go into the journal, truncated.
+pub struct ValidationLog {
+    recent: Mutex<VecDeque<String>>,
+    errors: AtomicU32,
+    latest_error: LatestMessage,
+    journal: Arc<Journal>,
+}
+
+impl ValidationLog {
+    pub fn new(journal: Arc<Journal>) -> Self {
+        Self {
+            recent: Mutex::new(VecDeque::new()),
+            errors: AtomicU32::new(0),
+            latest_error: LatestMessage(AtomicPtr::new(std::ptr::null_mut())),
+            journal,
+        }
+    }
+
+    pub fn push(&self, severity: vk::DebugUtilsMessageSeverityFlagsEXT, message: String) {
+        if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+            self.latest_error.put(message.clone());
+        }
+        self.journal.record(JournalEvent::Validation {
+            severity,
+            message: JournalText::new(&message),
+        });
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == RECENT_MESSAGE_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(message);
+    }
+
+    pub fn recent(&self) -> Vec<String> {
+        let recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        recent.iter().cloned().collect()
+    }
+
+    /// Error-severity messages since startup.
+    pub fn error_count(&self) -> u32 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    /// The newest error-severity message since the last call, if any.
+    pub fn take_latest_error(&self) -> Option<String> {
+        self.latest_error.take()
+    }
+
+    pub fn has_new_error(&self) -> bool {
+        !self.latest_error.is_empty()
+    }
+}
+
+/// The log target for a message of `message_type`, so RUST_LOG can pick
+/// them apart: `RUST_LOG=off,vulkan::validation=error` leaves only
+/// validation errors.
+fn message_target(message_type: vk::DebugUtilsMessageTypeFlagsEXT) -> &'static str {
+    if message_type.contains(vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION) {
+        "vulkan::validation"
+    } else if message_type.contains(vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE) {
+        "vulkan::performance"
+    } else {
+        "vulkan::general"
+    }
+}
+
+fn message_level(severity: vk::DebugUtilsMessageSeverityFlagsEXT) -> Level {
+    if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+        Level::Error
+    } else if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+        Level::Warn
+    } else if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::INFO) {
+        Level::Info
+    } else {
+        Level::Trace
+    }
+}
+
+// Without `debug-utils` nothing registers the callback, but the log and the
+// journal's validation events stay so reports keep their shape.
+#[cfg_attr(not(feature = "debug-utils"), allow(dead_code))]
 pub unsafe extern "system" fn vulkan_debug_callback(
     message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
-    _message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    message_type: vk::DebugUtilsMessageTypeFlagsEXT,
     p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
-    _p_user_data: *mut std::ffi::c_void,
+    p_user_data: *mut std::ffi::c_void,
 ) -> vk::Bool32 {
-    let callback_data = *p_callback_data;
-    let message = CStr::from_ptr(callback_data.p_message).to_string_lossy();
-    println!("{:?}: {}", message_severity, message);
+    let callback_data = unsafe { *p_callback_data };
+    let message = unsafe { CStr::from_ptr(callback_data.p_message) }.to_string_lossy();
+    // Null for messages that aren't from a numbered check.
+    let message_id = if callback_data.p_message_id_name.is_null() {
+        "-".into()
+    } else {
+        unsafe { CStr::from_ptr(callback_data.p_message_id_name) }.to_string_lossy()
+    };
+    log::log!(
+        target: message_target(message_type),
+        message_level(message_severity),
+        "[{}] {}",
+        message_id,
+        message
+    );
+    if let Some(log) = unsafe { (p_user_data as *const ValidationLog).as_ref() } {
+        log.push(
+            message_severity,
+            format!("{:?}: {}", message_severity, message),
+        );
+    }
     vk::FALSE
 }
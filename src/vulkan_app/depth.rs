@@ -0,0 +1,100 @@
+use ash::vk;
+use cgmath::{Matrix4, Vector4};
+
+/// Which end of the depth range is near. The uploaded projection, the
+/// pipelines' depth tests and the depth clears all follow it, and have to
+/// agree or nothing passes the test; `debug_check` ties them together.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DepthDirection {
+    /// Far at 1, as cgmath's projections leave it.
+    Standard,
+    /// Near at 1 and far at 0, for `--reversed-z`. Float depth is densest
+    /// near 0, which then offsets the projection's crowding of depth
+    /// towards the near plane, so distant surfaces stop z-fighting.
+    Reversed,
+}
+
+impl DepthDirection {
+    pub fn new(reversed: bool) -> Self {
+        if reversed {
+            DepthDirection::Reversed
+        } else {
+            DepthDirection::Standard
+        }
+    }
+
+    /// The far end of the range, which depth attachments are cleared to.
+    pub fn clear_depth(self) -> f32 {
+        match self {
+            DepthDirection::Standard => 1.0,
+            DepthDirection::Reversed => 0.0,
+        }
+    }
+
+    /// Passes fragments nearer than the depth already there.
+    pub fn compare_op(self) -> vk::CompareOp {
+        match self {
+            DepthDirection::Standard => vk::CompareOp::LESS,
+            DepthDirection::Reversed => vk::CompareOp::GREATER,
+        }
+    }
+
+    /// Also passes fragments at the same depth, like the skybox's on the
+    /// far plane against the clear.
+    pub fn compare_or_equal_op(self) -> vk::CompareOp {
+        match self {
+            DepthDirection::Standard => vk::CompareOp::LESS_OR_EQUAL,
+            DepthDirection::Reversed => vk::CompareOp::GREATER_OR_EQUAL,
+        }
+    }
+
+    /// `proj`, from cgmath with clip z from -w at the near plane to w at
+    /// the far one, with its depth running this way.
+    pub fn projection(self, proj: Matrix4<f32>) -> Matrix4<f32> {
+        match self {
+            DepthDirection::Standard => proj,
+            // z' = (w - z) / 2: the near plane to 1, the far plane to 0.
+            DepthDirection::Reversed => {
+                Matrix4::from_cols(
+                    Vector4::unit_x(),
+                    Vector4::unit_y(),
+                    Vector4::new(0.0, 0.0, -0.5, 0.0),
+                    Vector4::new(0.0, 0.0, 0.5, 1.0),
+                ) * proj
+            }
+        }
+    }
+
+    /// In debug builds, panics unless `proj`, as uploaded, puts the `far`
+    /// plane at `clear_depth` and a point between it and `near` where
+    /// `compare_op` passes against both.
+    pub fn debug_check(self, proj: Matrix4<f32>, near: f32, far: f32) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        let depth = |distance: f32| {
+            let clip = proj * Vector4::new(0.0, 0.0, -distance, 1.0);
+            clip.z / clip.w
+        };
+        let (middle, far) = (depth((near + far) / 2.0), depth(far));
+        let nearer = |depth: f32, than: f32| match self.compare_op() {
+            vk::CompareOp::LESS => depth < than,
+            _ => depth > than,
+        };
+        debug_assert!(
+            (far - self.clear_depth()).abs() < 1e-4,
+            "{:?} depth: the far plane projects to {}, not the clear depth {}",
+            self,
+            far,
+            self.clear_depth()
+        );
+        debug_assert!(
+            nearer(middle, far) && nearer(middle, self.clear_depth()),
+            "{:?} depth: {:?} fails a point in front of the far plane ({} against {})",
+            self,
+            self.compare_op(),
+            middle,
+            far
+        );
+    }
+}
@@ -0,0 +1,556 @@
+use ash::vk;
+use std::ffi::{c_char, CStr};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::backend::WindowBackend;
+use super::capabilities::DeviceCapabilities;
+use super::config_resolver::{Downgrade, EffectiveConfig};
+use super::draw_constants::DrawConstantPacking;
+use super::draw_list::DrawStats;
+use super::memory::MemoryPlacement;
+use super::mesh_pool::MeshPoolStats;
+use super::resources::ResourceKind;
+use super::texgen::Pattern;
+use super::{
+    DEPTH_PREPASS, GPU_ANIMATION, MAX_FRAMES_IN_FLIGHT, OPTIMIZE_MESHES, QUANTIZE_MESH_POSITIONS,
+};
+
+/// Everything worth pasting into a bug report, captured at one point in time.
+pub struct DiagnosticsReport {
+    pub app_name: String,
+    pub app_version: u32,
+    pub engine_name: String,
+    pub engine_version: u32,
+    pub window_backend: WindowBackend,
+    pub effective: EffectiveConfig,
+    pub downgrades: Vec<Downgrade>,
+    pub surface_extensions: Vec<String>,
+    pub device_name: String,
+    pub device_type: vk::PhysicalDeviceType,
+    pub api_version: u32,
+    pub driver_version: u32,
+    pub capabilities: DeviceCapabilities,
+    pub draw_packing: DrawConstantPacking,
+    pub memory_placements: Vec<MemoryPlacement>,
+    pub swapchain_extent: vk::Extent2D,
+    pub render_extent: vk::Extent2D,
+    pub swapchain_format: vk::Format,
+    pub swapchain_usage: vk::ImageUsageFlags,
+    pub scene_objects: usize,
+    pub final_layout: vk::ImageLayout,
+    pub frame_count: u64,
+    /// Over the last FRAME_STATS_SAMPLES frames, start to start.
+    pub avg_frame_ms: f32,
+    /// The 99th percentile frame time, the 1% low.
+    pub slow_frame_ms: f32,
+    /// `None` without GPU timestamps.
+    pub avg_gpu_ms: Option<f32>,
+    pub draw_stats: DrawStats,
+    pub mesh_pool: MeshPoolStats,
+    /// Pattern, size and mip levels.
+    pub ground_texture: Option<(Pattern, u32, u32)>,
+    pub live_resources: Vec<(ResourceKind, usize)>,
+    pub validation_messages: Vec<String>,
+}
+
+impl DiagnosticsReport {
+    /// Writes the report to `diagnostics-<unix seconds>.txt` in the working
+    /// directory and returns the path.
+    pub fn write_to_file(&self) -> std::io::Result<PathBuf> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        let path = PathBuf::from(format!("diagnostics-{}.txt", timestamp));
+        fs::write(&path, self.to_string())?;
+        Ok(path)
+    }
+}
+
+impl fmt::Display for DiagnosticsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "=== Renderer diagnostics ===")?;
+        writeln!(
+            f,
+            "application: {} {}",
+            self.app_name,
+            Version(self.app_version)
+        )?;
+        writeln!(
+            f,
+            "engine: {} {}",
+            self.engine_name,
+            Version(self.engine_version)
+        )?;
+        writeln!(
+            f,
+            "window backend: {} ({})",
+            self.window_backend,
+            self.surface_extensions.join(", ")
+        )?;
+        writeln!(f, "device: {} ({:?})", self.device_name, self.device_type)?;
+        writeln!(f, "api version: {}", Version(self.api_version))?;
+        writeln!(f, "driver version: {:#x}", self.driver_version)?;
+
+        writeln!(f, "capabilities:")?;
+        writeln!(
+            f,
+            "  negative viewport height: {}",
+            self.capabilities.negative_viewport_height
+        )?;
+        writeln!(
+            f,
+            "  pipeline statistics query: {}",
+            self.capabilities.pipeline_statistics_query
+        )?;
+        writeln!(
+            f,
+            "  fill mode non-solid: {}",
+            self.capabilities.fill_mode_non_solid
+        )?;
+        writeln!(
+            f,
+            "  depth stencil resolve: {}",
+            self.capabilities.depth_stencil_resolve
+        )?;
+        match self.capabilities.dynamic_rendering {
+            Some(support) => writeln!(f, "  dynamic rendering: {}", support)?,
+            None => writeln!(f, "  dynamic rendering: false")?,
+        }
+        writeln!(
+            f,
+            "  portability subset: {}",
+            self.capabilities.portability_subset
+        )?;
+
+        writeln!(
+            f,
+            "  max msaa samples: {}x",
+            self.capabilities.max_msaa_samples
+        )?;
+        writeln!(
+            f,
+            "  sampler anisotropy: {} (max {})",
+            self.capabilities.sampler_anisotropy, self.capabilities.max_anisotropy
+        )?;
+        let limits = &self.capabilities.limits;
+        writeln!(
+            f,
+            "  max push constants: {} bytes",
+            limits.max_push_constants_size
+        )?;
+        writeln!(
+            f,
+            "  max uniform buffers: {} per stage, {} dynamic per set",
+            limits.max_per_stage_descriptor_uniform_buffers,
+            limits.max_descriptor_set_uniform_buffers_dynamic
+        )?;
+        writeln!(
+            f,
+            "  max bound descriptor sets: {}",
+            limits.max_bound_descriptor_sets
+        )?;
+        writeln!(
+            f,
+            "  max framebuffer: {}x{}, max 2D image: {}",
+            limits.max_framebuffer_width,
+            limits.max_framebuffer_height,
+            limits.max_image_dimension_2d
+        )?;
+
+        writeln!(f, "config:")?;
+        writeln!(f, "  frames in flight: {}", MAX_FRAMES_IN_FLIGHT)?;
+        writeln!(f, "  optimize meshes: {}", OPTIMIZE_MESHES)?;
+        writeln!(f, "  quantize mesh positions: {}", QUANTIZE_MESH_POSITIONS)?;
+        writeln!(f, "  gpu animation: {}", GPU_ANIMATION)?;
+        writeln!(f, "  scene objects: {}", self.scene_objects)?;
+        writeln!(f, "  depth pre-pass: {}", DEPTH_PREPASS)?;
+
+        writeln!(f, "  msaa: {}x", self.effective.msaa_samples)?;
+        match self.effective.anisotropy {
+            Some(anisotropy) => writeln!(f, "  anisotropy: {}x", anisotropy)?,
+            None => writeln!(f, "  anisotropy: off")?,
+        }
+        writeln!(f, "  present mode: {:?}", self.effective.present_mode)?;
+        writeln!(f, "  draw constants: {}", self.draw_packing)?;
+        for downgrade in &self.downgrades {
+            writeln!(f, "  downgraded {}", downgrade)?;
+        }
+
+        writeln!(f, "memory types:")?;
+        for placement in &self.memory_placements {
+            writeln!(f, "  {}", placement)?;
+        }
+
+        writeln!(f, "swapchain:")?;
+        writeln!(
+            f,
+            "  extent: {}x{}",
+            self.swapchain_extent.width, self.swapchain_extent.height
+        )?;
+        if self.render_extent != self.swapchain_extent {
+            writeln!(
+                f,
+                "  rendered: {}x{}, letterboxed",
+                self.render_extent.width, self.render_extent.height
+            )?;
+        }
+        writeln!(f, "  format: {:?}", self.swapchain_format)?;
+        writeln!(f, "  usage: {:?}", self.swapchain_usage)?;
+        writeln!(f, "  final layout: {:?}", self.final_layout)?;
+
+        writeln!(f, "frames:")?;
+        writeln!(f, "  submitted: {}", self.frame_count)?;
+        writeln!(
+            f,
+            "  frame time: {:.2} ms average, {:.2} ms 1% low",
+            self.avg_frame_ms, self.slow_frame_ms
+        )?;
+        match self.avg_gpu_ms {
+            Some(ms) => writeln!(f, "  gpu time: {:.2} ms average", ms)?,
+            None => writeln!(f, "  gpu time: no timestamps")?,
+        }
+        writeln!(
+            f,
+            "  last frame: {} draws, {} pipeline binds, {} material binds, {} mesh binds",
+            self.draw_stats.draws,
+            self.draw_stats.pipeline_binds,
+            self.draw_stats.material_binds,
+            self.draw_stats.mesh_binds
+        )?;
+
+        writeln!(f, "mesh pool: {} meshes", self.mesh_pool.meshes)?;
+        for (name, stats) in [
+            ("vertices", self.mesh_pool.vertices),
+            ("indices", self.mesh_pool.indices),
+        ] {
+            writeln!(
+                f,
+                "  {}: {}/{} free in {} ranges, largest {}, fragmentation {:.1}%",
+                name,
+                stats.free,
+                stats.capacity,
+                stats.free_ranges,
+                stats.largest_free,
+                stats.fragmentation() * 100.0
+            )?;
+        }
+
+        match self.ground_texture {
+            Some((pattern, size, mip_levels)) => writeln!(
+                f,
+                "ground texture: {:?}, {}x{}, {} mip levels",
+                pattern, size, size, mip_levels
+            )?,
+            None => writeln!(f, "ground texture: none")?,
+        }
+
+        writeln!(f, "live resources:")?;
+        for (kind, count) in &self.live_resources {
+            writeln!(f, "  {:?}: {}", kind, count)?;
+        }
+
+        writeln!(f, "recent validation messages:")?;
+        if self.validation_messages.is_empty() {
+            writeln!(f, "  (none)")?;
+        }
+        for message in &self.validation_messages {
+            writeln!(f, "  {}", message)?;
+        }
+        Ok(())
+    }
+}
+
+/// A `vk::make_api_version` value as `major.minor.patch`.
+struct Version(u32);
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}",
+            vk::api_version_major(self.0),
+            vk::api_version_minor(self.0),
+            vk::api_version_patch(self.0)
+        )
+    }
+}
+
+// Filled in as `VulkanApp::new` brings the renderer up, so a failure or
+// panic partway still reports what was found before it.
+static STARTUP: Mutex<Option<StartupDiagnostics>> = Mutex::new(None);
+
+/// What the loader and drivers reported while the renderer started: the
+/// machine's side of a bug report, next to `DiagnosticsReport`'s view of
+/// the running renderer.
+#[derive(Clone, Debug, Default)]
+pub struct StartupDiagnostics {
+    /// `None` from a 1.0 loader, which can't report its version.
+    pub instance_version: Option<u32>,
+    pub instance_layers: Vec<String>,
+    pub instance_extensions: Vec<String>,
+    pub devices: Vec<DeviceSummary>,
+    /// Index into `devices`.
+    pub selected_device: Option<usize>,
+    pub graphics_family: Option<u32>,
+    pub present_family: Option<u32>,
+    pub transfer_family: Option<u32>,
+    /// Empty when rendering offscreen.
+    pub surface_formats: Vec<vk::SurfaceFormatKHR>,
+    pub present_modes: Vec<vk::PresentModeKHR>,
+    pub device_extensions: Vec<String>,
+}
+
+/// One physical device the instance enumerated.
+#[derive(Clone, Debug)]
+pub struct DeviceSummary {
+    pub name: String,
+    pub device_type: vk::PhysicalDeviceType,
+    pub api_version: u32,
+    /// Encoded as the vendor chooses.
+    pub driver_version: u32,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    /// Size and whether it is device local, per heap.
+    pub heaps: Vec<(vk::DeviceSize, bool)>,
+}
+
+impl DeviceSummary {
+    /// Every device `instance` enumerates, in its order.
+    pub fn enumerate(instance: &ash::Instance) -> Vec<Self> {
+        let devices = unsafe { instance.enumerate_physical_devices() }.unwrap_or_default();
+        devices
+            .into_iter()
+            .map(|pdevice| {
+                let properties = unsafe { instance.get_physical_device_properties(pdevice) };
+                let memory = unsafe { instance.get_physical_device_memory_properties(pdevice) };
+                let heaps = memory.memory_heaps[..memory.memory_heap_count as usize]
+                    .iter()
+                    .map(|heap| {
+                        (
+                            heap.size,
+                            heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL),
+                        )
+                    })
+                    .collect();
+                Self {
+                    name: unsafe { CStr::from_ptr(properties.device_name.as_ptr()) }
+                        .to_string_lossy()
+                        .into_owned(),
+                    device_type: properties.device_type,
+                    api_version: properties.api_version,
+                    driver_version: properties.driver_version,
+                    vendor_id: properties.vendor_id,
+                    device_id: properties.device_id,
+                    heaps,
+                }
+            })
+            .collect()
+    }
+}
+
+impl StartupDiagnostics {
+    /// Starts a new record for a renderer coming up, replacing the last
+    /// one's.
+    pub fn begin(instance_version: Option<u32>) {
+        *STARTUP.lock().unwrap() = Some(Self {
+            instance_version,
+            ..Self::default()
+        });
+    }
+
+    /// Adds to the record `begin` started.
+    pub fn record(update: impl FnOnce(&mut Self)) {
+        if let Some(diagnostics) = STARTUP.lock().unwrap().as_mut() {
+            update(diagnostics);
+        }
+    }
+
+    /// The record so far, if a renderer has started. `None` too while the
+    /// record is being written, which a panic hook can't wait out.
+    pub fn latest() -> Option<Self> {
+        STARTUP.try_lock().ok()?.clone()
+    }
+
+    /// The names behind `count` pointers at `names`, as a create info
+    /// holds them once any hook has run.
+    pub fn names(count: u32, names: *const *const c_char) -> Vec<String> {
+        if names.is_null() {
+            return Vec::new();
+        }
+        unsafe { std::slice::from_raw_parts(names, count as usize) }
+            .iter()
+            .map(|&name| {
+                unsafe { CStr::from_ptr(name) }
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
+    }
+
+    /// For `--diagnostics-json`.
+    pub fn to_json(&self) -> String {
+        let strings = |values: &[String]| {
+            let quoted: Vec<_> = values.iter().map(|value| json_string(value)).collect();
+            format!("[{}]", quoted.join(", "))
+        };
+        let optional = |value: Option<u32>| value.map_or("null".to_string(), |v| v.to_string());
+        let devices: Vec<_> = self
+            .devices
+            .iter()
+            .map(|device| {
+                let heaps: Vec<_> = device
+                    .heaps
+                    .iter()
+                    .map(|&(size, device_local)| {
+                        format!("{{\"size\": {}, \"device_local\": {}}}", size, device_local)
+                    })
+                    .collect();
+                format!(
+                    "    {{\"name\": {}, \"type\": {}, \"api_version\": {}, \
+                     \"driver_version\": {}, \"vendor_id\": {}, \"device_id\": {}, \
+                     \"heaps\": [{}]}}",
+                    json_string(&device.name),
+                    json_string(&format!("{:?}", device.device_type)),
+                    json_string(&Version(device.api_version).to_string()),
+                    device.driver_version,
+                    device.vendor_id,
+                    device.device_id,
+                    heaps.join(", ")
+                )
+            })
+            .collect();
+        let formats: Vec<_> = self
+            .surface_formats
+            .iter()
+            .map(|format| {
+                format!(
+                    "{{\"format\": {}, \"color_space\": {}}}",
+                    json_string(&format!("{:?}", format.format)),
+                    json_string(&format!("{:?}", format.color_space))
+                )
+            })
+            .collect();
+        let present_modes: Vec<_> = self
+            .present_modes
+            .iter()
+            .map(|mode| json_string(&format!("{:?}", mode)))
+            .collect();
+        let mut json = String::from("{\n");
+        let mut field = |name: &str, value: String| {
+            if json.len() > 2 {
+                json.push_str(",\n");
+            }
+            json.push_str(&format!("  {}: {}", json_string(name), value));
+        };
+        field(
+            "instance_version",
+            self.instance_version.map_or("null".to_string(), |version| {
+                json_string(&Version(version).to_string())
+            }),
+        );
+        field("instance_layers", strings(&self.instance_layers));
+        field("instance_extensions", strings(&self.instance_extensions));
+        field("devices", format!("[\n{}\n  ]", devices.join(",\n")));
+        field(
+            "selected_device",
+            optional(self.selected_device.map(|index| index as u32)),
+        );
+        field(
+            "queue_families",
+            format!(
+                "{{\"graphics\": {}, \"present\": {}, \"transfer\": {}}}",
+                optional(self.graphics_family),
+                optional(self.present_family),
+                optional(self.transfer_family)
+            ),
+        );
+        field("surface_formats", format!("[{}]", formats.join(", ")));
+        field("present_modes", format!("[{}]", present_modes.join(", ")));
+        field("device_extensions", strings(&self.device_extensions));
+        json.push_str("\n}\n");
+        json
+    }
+}
+
+impl fmt::Display for StartupDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "=== Startup diagnostics ===")?;
+        match self.instance_version {
+            Some(version) => writeln!(f, "instance version: {}", Version(version))?,
+            None => writeln!(f, "instance version: 1.0 (not reported)")?,
+        }
+        writeln!(f, "instance layers: {}", self.instance_layers.join(", "))?;
+        writeln!(
+            f,
+            "instance extensions: {}",
+            self.instance_extensions.join(", ")
+        )?;
+        writeln!(f, "devices:")?;
+        for (index, device) in self.devices.iter().enumerate() {
+            let selected = if self.selected_device == Some(index) {
+                " (selected)"
+            } else {
+                ""
+            };
+            writeln!(
+                f,
+                "  {}: {} ({:?}){}, api {}, driver {:#x}, vendor {:#06x}, device {:#06x}",
+                index,
+                device.name,
+                device.device_type,
+                selected,
+                Version(device.api_version),
+                device.driver_version,
+                device.vendor_id,
+                device.device_id
+            )?;
+            for (heap, &(size, device_local)) in device.heaps.iter().enumerate() {
+                writeln!(
+                    f,
+                    "    heap {}: {:.0} MiB{}",
+                    heap,
+                    size as f64 / (1 << 20) as f64,
+                    if device_local { ", device local" } else { "" }
+                )?;
+            }
+        }
+        writeln!(
+            f,
+            "queue families: graphics {:?}, present {:?}, transfer {:?}",
+            self.graphics_family, self.present_family, self.transfer_family
+        )?;
+        let formats: Vec<_> = self
+            .surface_formats
+            .iter()
+            .map(|format| format!("{:?}/{:?}", format.format, format.color_space))
+            .collect();
+        writeln!(f, "surface formats: {}", formats.join(", "))?;
+        writeln!(f, "present modes: {:?}", self.present_modes)?;
+        writeln!(
+            f,
+            "device extensions: {}",
+            self.device_extensions.join(", ")
+        )
+    }
+}
+
+/// `value` as a JSON string literal.
+fn json_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,224 @@
+use ash::vk;
+use cgmath::{Matrix4, SquareMatrix};
+use std::fmt;
+
+use super::capabilities::DeviceLimits;
+use super::ubo::align_up;
+use super::DRAW_UNIFORM_SLOTS;
+
+/// Per-draw values, DRAW_CONSTANT_FIELDS in common.glsl under std140. Each
+/// field is either pushed or read from the draw's dynamic uniform slot, as
+/// `DrawConstantPacking` decides.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct DrawConstants {
+    /// Multiplies the fragment colour, linear.
+    pub tint: [f32; 4],
+    /// Applied after the instance's own transform.
+    pub model: Matrix4<f32>,
+}
+
+const _: () = assert!(std::mem::size_of::<DrawConstants>() == 80);
+const _: () = assert!(std::mem::offset_of!(DrawConstants, model) == 16);
+
+impl Default for DrawConstants {
+    fn default() -> Self {
+        Self {
+            tint: [1.0; 4],
+            model: Matrix4::identity(),
+        }
+    }
+}
+
+struct Field {
+    name: &'static str,
+    offset: u32,
+    size: u32,
+    stages: vk::ShaderStageFlags,
+}
+
+/// In packing order: the first fields that fit the push constant budget
+/// are pushed, the rest go to the uniform slot. The shaders are compiled
+/// once per possible split, with DRAW_PUSH_FIELDS set to the pushed count.
+const FIELDS: [Field; 2] = [
+    Field {
+        name: "tint",
+        offset: 0,
+        size: 16,
+        stages: vk::ShaderStageFlags::FRAGMENT,
+    },
+    Field {
+        name: "model",
+        offset: 16,
+        size: 64,
+        stages: vk::ShaderStageFlags::VERTEX,
+    },
+];
+
+/// Uniform buffers the vertex stage already reads: the frame and view blocks.
+const VERTEX_UNIFORM_BUFFERS: u32 = 2;
+
+/// Where each draw constant lives, chosen from the device limits before the
+/// pipeline layout is created.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DrawConstantPacking {
+    push_fields: usize,
+    budget: u32,
+    // Bytes between uniform slots: the constants rounded up to the
+    // device's dynamic offset alignment, which ranges from 1 to 256.
+    slot_stride: vk::DeviceSize,
+}
+
+impl DrawConstantPacking {
+    /// Pushes as many fields as fit `limits.max_push_constants_size`, or
+    /// `budget` if that is lower. Fails if some fields overflow and the
+    /// device can't bind the extra dynamic uniform buffer they need.
+    pub fn choose(limits: &DeviceLimits, budget: Option<u32>) -> Result<Self, String> {
+        let budget = budget.map_or(limits.max_push_constants_size, |budget| {
+            budget.min(limits.max_push_constants_size)
+        });
+        let push_fields = FIELDS
+            .iter()
+            .take_while(|field| field.offset + field.size <= budget)
+            .count();
+        let packing = Self {
+            push_fields,
+            budget,
+            slot_stride: align_up(
+                std::mem::size_of::<DrawConstants>() as vk::DeviceSize,
+                limits.min_uniform_buffer_offset_alignment,
+            ),
+        };
+        if packing.uses_uniform_slot() {
+            if limits.max_descriptor_set_uniform_buffers_dynamic == 0 {
+                return Err(format!(
+                    "{} doesn't fit {} bytes of push constants and the device has no \
+                     dynamic uniform buffers",
+                    packing.overflow_names().join(", "),
+                    budget
+                ));
+            }
+            if limits.max_per_stage_descriptor_uniform_buffers <= VERTEX_UNIFORM_BUFFERS {
+                return Err(format!(
+                    "{} doesn't fit {} bytes of push constants and the vertex stage \
+                     can't bind another uniform buffer (limit {})",
+                    packing.overflow_names().join(", "),
+                    budget,
+                    limits.max_per_stage_descriptor_uniform_buffers
+                ));
+            }
+        }
+        Ok(packing)
+    }
+
+    /// DRAW_PUSH_FIELDS of the shader variant to load.
+    pub fn push_fields(&self) -> usize {
+        self.push_fields
+    }
+
+    pub fn uses_uniform_slot(&self) -> bool {
+        self.push_fields < FIELDS.len()
+    }
+
+    /// Slots first reserved per swapchain image after the view blocks.
+    pub fn uniform_slots(&self) -> usize {
+        if self.uses_uniform_slot() {
+            DRAW_UNIFORM_SLOTS
+        } else {
+            0
+        }
+    }
+
+    pub fn slot_stride(&self) -> vk::DeviceSize {
+        self.slot_stride
+    }
+
+    /// Stages reading the uniform slot, which set 0 binding 1 is visible to.
+    pub fn uniform_stages(&self) -> vk::ShaderStageFlags {
+        FIELDS[self.push_fields..]
+            .iter()
+            .fold(vk::ShaderStageFlags::empty(), |stages, field| {
+                stages | field.stages
+            })
+    }
+
+    pub fn push_constant_ranges(&self) -> Vec<vk::PushConstantRange> {
+        FIELDS[..self.push_fields]
+            .iter()
+            .map(|field| vk::PushConstantRange {
+                stage_flags: field.stages,
+                offset: field.offset,
+                size: field.size,
+            })
+            .collect()
+    }
+
+    /// Pushes this packing's share of `constants`; the rest must already be
+    /// in the draw's uniform slot.
+    pub fn push(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        layout: vk::PipelineLayout,
+        constants: &DrawConstants,
+    ) {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                constants as *const DrawConstants as *const u8,
+                std::mem::size_of::<DrawConstants>(),
+            )
+        };
+        for field in &FIELDS[..self.push_fields] {
+            let range = field.offset as usize..(field.offset + field.size) as usize;
+            unsafe {
+                device.cmd_push_constants(
+                    command_buffer,
+                    layout,
+                    field.stages,
+                    field.offset,
+                    &bytes[range],
+                );
+            }
+        }
+    }
+
+    fn overflow_names(&self) -> Vec<&'static str> {
+        FIELDS[self.push_fields..]
+            .iter()
+            .map(|field| field.name)
+            .collect()
+    }
+}
+
+impl fmt::Display for DrawConstantPacking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pushed: Vec<_> = FIELDS[..self.push_fields]
+            .iter()
+            .map(|field| field.name)
+            .collect();
+        let pushed_size = FIELDS[..self.push_fields]
+            .iter()
+            .map(|field| field.size)
+            .sum::<u32>();
+        if pushed.is_empty() {
+            write!(f, "nothing pushed ({} byte budget)", self.budget)?;
+        } else {
+            write!(
+                f,
+                "{} pushed ({} of {} bytes)",
+                pushed.join(", "),
+                pushed_size,
+                self.budget
+            )?;
+        }
+        if self.uses_uniform_slot() {
+            write!(
+                f,
+                ", {} in a dynamic uniform slot ({} byte stride)",
+                self.overflow_names().join(", "),
+                self.slot_stride
+            )?;
+        }
+        Ok(())
+    }
+}
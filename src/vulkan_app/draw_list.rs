@@ -0,0 +1,154 @@
+use super::draw_constants::DrawConstants;
+use super::mesh_pool::MeshAllocation;
+
+// Opaque sort key layout, most significant first:
+//   [63]     layer (0 = opaque; 1 is reserved for a back-to-front transparent layer)
+//   [47..63) pipeline
+//   [31..47) material
+//   [16..31) mesh buffers
+//   [0..16)  quantized view depth, front to back
+const PIPELINE_SHIFT: u32 = 47;
+const MATERIAL_SHIFT: u32 = 31;
+const MESH_SHIFT: u32 = 16;
+const PIPELINE_MASK: u64 = 0xFFFF;
+const MATERIAL_MASK: u64 = 0xFFFF;
+const MESH_MASK: u64 = 0x7FFF;
+const DEPTH_MASK: u64 = 0xFFFF;
+const TRANSPARENT_LAYER: u64 = 1 << 63;
+
+/// View distance mapped onto the full depth bits; anything further sorts last.
+const DEPTH_SORT_RANGE: f32 = 100.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct DrawRecord {
+    key: u64,
+    pub pipeline: u16,
+    pub material: u16,
+    pub mesh: u16,
+    pub index_count: u32,
+    pub first_index: u32,
+    pub vertex_offset: i32,
+    pub instance_count: u32,
+    pub first_instance: u32,
+    pub constants: DrawConstants,
+}
+
+#[derive(Default)]
+pub struct DrawList {
+    records: Vec<DrawRecord>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrawStats {
+    pub draws: u32,
+    pub pipeline_binds: u32,
+    pub material_binds: u32,
+    pub mesh_binds: u32,
+}
+
+impl std::ops::AddAssign for DrawStats {
+    fn add_assign(&mut self, other: Self) {
+        self.draws += other.draws;
+        self.pipeline_binds += other.pipeline_binds;
+        self.material_binds += other.material_binds;
+        self.mesh_binds += other.mesh_binds;
+    }
+}
+
+impl DrawList {
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    pub fn push_opaque(
+        &mut self,
+        pipeline: u16,
+        material: u16,
+        mesh: u16,
+        allocation: MeshAllocation,
+        instances: std::ops::Range<u32>,
+        view_depth: f32,
+        constants: DrawConstants,
+    ) {
+        let depth = ((view_depth / DEPTH_SORT_RANGE).clamp(0.0, 1.0) * DEPTH_MASK as f32) as u64;
+        let key = ((pipeline as u64 & PIPELINE_MASK) << PIPELINE_SHIFT)
+            | ((material as u64 & MATERIAL_MASK) << MATERIAL_SHIFT)
+            | ((mesh as u64 & MESH_MASK) << MESH_SHIFT)
+            | (depth & DEPTH_MASK);
+
+        self.records.push(DrawRecord {
+            key,
+            pipeline,
+            material,
+            mesh,
+            index_count: allocation.index_count,
+            first_index: allocation.first_index,
+            vertex_offset: allocation.vertex_offset(),
+            instance_count: instances.len() as u32,
+            first_instance: instances.start,
+            constants,
+        });
+    }
+
+    pub fn sort(&mut self) {
+        self.records.sort_unstable_by_key(|record| record.key);
+    }
+
+    /// In draw order once sorted.
+    pub fn records(&self) -> &[DrawRecord] {
+        &self.records
+    }
+
+    /// Walks the sorted records, calling `bind_*` only when that state changes
+    /// from the previous draw, and counts how many binds were issued. With
+    /// `opaque_only` the transparent layer (sorted last) is skipped, as the
+    /// depth pre-pass must not write depth for it. `draw` also gets the
+    /// record's index in `records`, which skipping the last layer leaves
+    /// unchanged.
+    pub fn record(
+        &self,
+        opaque_only: bool,
+        mut bind_pipeline: impl FnMut(u16),
+        mut bind_material: impl FnMut(u16),
+        mut bind_mesh: impl FnMut(u16),
+        mut draw: impl FnMut(usize, &DrawRecord),
+    ) -> DrawStats {
+        let mut stats = DrawStats::default();
+        let mut pipeline = None;
+        let mut material = None;
+        let mut mesh = None;
+
+        let records = if opaque_only {
+            let opaque = self
+                .records
+                .partition_point(|record| record.key & TRANSPARENT_LAYER == 0);
+            &self.records[..opaque]
+        } else {
+            &self.records[..]
+        };
+
+        for (slot, record) in records.iter().enumerate() {
+            if pipeline != Some(record.pipeline) {
+                bind_pipeline(record.pipeline);
+                pipeline = Some(record.pipeline);
+                // A pipeline switch may change the layout, so material state is treated as lost.
+                material = None;
+                stats.pipeline_binds += 1;
+            }
+            if material != Some(record.material) {
+                bind_material(record.material);
+                material = Some(record.material);
+                stats.material_binds += 1;
+            }
+            if mesh != Some(record.mesh) {
+                bind_mesh(record.mesh);
+                mesh = Some(record.mesh);
+                stats.mesh_binds += 1;
+            }
+            draw(slot, record);
+            stats.draws += 1;
+        }
+
+        stats
+    }
+}
@@ -0,0 +1,164 @@
+use ash::vk;
+use std::fmt;
+
+use super::memory::ResourceClass;
+
+/// The step of starting up or rendering that failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Loader,
+    InstanceCreation,
+    DebugMessenger,
+    SurfaceCreation,
+    DevicePick,
+    DeviceCreation,
+    MemorySelection,
+    Allocation,
+    PipelineLayout,
+    SwapchainCreation,
+    SwapchainRecreation,
+    Acquire,
+    Submit,
+    Present,
+    Shutdown,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stage::Loader => "loading Vulkan",
+            Stage::InstanceCreation => "instance creation",
+            Stage::DebugMessenger => "debug messenger setup",
+            Stage::SurfaceCreation => "surface creation",
+            Stage::DevicePick => "device selection",
+            Stage::DeviceCreation => "device creation",
+            Stage::MemorySelection => "memory type selection",
+            Stage::Allocation => "memory allocation",
+            Stage::PipelineLayout => "pipeline layout creation",
+            Stage::SwapchainCreation => "swapchain creation",
+            Stage::SwapchainRecreation => "swapchain recreation",
+            Stage::Acquire => "image acquisition",
+            Stage::Submit => "queue submission",
+            Stage::Present => "presentation",
+            Stage::Shutdown => "shutdown",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum VulkanError {
+    /// `Entry::load` couldn't find or open the Vulkan loader.
+    Loading(ash::LoadingError),
+    Vulkan {
+        stage: Stage,
+        result: vk::Result,
+    },
+    /// ERROR_DEVICE_LOST: the driver reset or crashed, or the GPU was
+    /// removed. Nothing more can be rendered; the renderer can only be
+    /// destroyed.
+    DeviceLost(Stage),
+    /// Every memory type a resource could use was out of memory.
+    OutOfMemory {
+        class: ResourceClass,
+        size: vk::DeviceSize,
+        /// The class's preferred memory properties.
+        flags: vk::MemoryPropertyFlags,
+        /// ERROR_OUT_OF_DEVICE_MEMORY or ERROR_OUT_OF_HOST_MEMORY.
+        result: vk::Result,
+    },
+    /// No device has the queues, extensions and surface formats the renderer
+    /// needs.
+    NoSuitableGpu,
+    /// A frame's fence didn't signal within the watchdog's timeout, twice,
+    /// and the watchdog has written its report. The device may still be
+    /// busy, so teardown may wait or fail.
+    GpuHang(Stage),
+    /// The config asks for something the device or surface can't do.
+    Config {
+        stage: Stage,
+        message: String,
+    },
+    /// This many tracked GPU objects were still alive after teardown.
+    Leaked(usize),
+}
+
+impl VulkanError {
+    /// For `map_err` on a Vulkan call made during `stage`.
+    pub fn at(stage: Stage) -> impl Fn(vk::Result) -> Self {
+        move |result| match result {
+            vk::Result::ERROR_DEVICE_LOST => VulkanError::DeviceLost(stage),
+            _ => VulkanError::Vulkan { stage, result },
+        }
+    }
+
+    pub fn stage(&self) -> Stage {
+        match *self {
+            VulkanError::Loading(_) => Stage::Loader,
+            VulkanError::Vulkan { stage, .. }
+            | VulkanError::Config { stage, .. }
+            | VulkanError::GpuHang(stage)
+            | VulkanError::DeviceLost(stage) => stage,
+            VulkanError::NoSuitableGpu => Stage::DevicePick,
+            VulkanError::OutOfMemory { .. } => Stage::Allocation,
+            VulkanError::Leaked(_) => Stage::Shutdown,
+        }
+    }
+}
+
+impl fmt::Display for VulkanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed: ", self.stage())?;
+        match self {
+            VulkanError::Loading(error) => write!(f, "{}", error),
+            VulkanError::Vulkan { result, .. } => write!(f, "{}", result),
+            VulkanError::DeviceLost(_) => {
+                f.write_str("the GPU was lost: its driver reset or crashed, or it was removed")
+            }
+            VulkanError::OutOfMemory {
+                class,
+                size,
+                flags,
+                result,
+            } => write!(
+                f,
+                "{} for {} bytes of {} memory, preferably {:?}, in any type it can use",
+                result,
+                size,
+                class.name(),
+                flags
+            ),
+            VulkanError::NoSuitableGpu => f.write_str(
+                "no GPU supports graphics and present queues, the swapchain and this surface",
+            ),
+            VulkanError::GpuHang(_) => {
+                f.write_str("the GPU stopped responding: a frame didn't finish in time, twice")
+            }
+            VulkanError::Config { message, .. } => {
+                write!(f, "invalid renderer config: {}", message)
+            }
+            VulkanError::Leaked(count) => write!(f, "{} GPU resources were not destroyed", count),
+        }
+    }
+}
+
+impl std::error::Error for VulkanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VulkanError::Loading(error) => Some(error),
+            VulkanError::Vulkan { result, .. } | VulkanError::OutOfMemory { result, .. } => {
+                Some(result)
+            }
+            VulkanError::DeviceLost(_)
+            | VulkanError::NoSuitableGpu
+            | VulkanError::GpuHang(_)
+            | VulkanError::Config { .. }
+            | VulkanError::Leaked(_) => None,
+        }
+    }
+}
+
+impl From<ash::LoadingError> for VulkanError {
+    fn from(error: ash::LoadingError) -> Self {
+        VulkanError::Loading(error)
+    }
+}
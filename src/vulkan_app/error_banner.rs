@@ -0,0 +1,164 @@
+use ash::vk;
+use std::time::{Duration, Instant};
+
+use super::debug::ValidationLog;
+use super::font::{glyph_rows, GLYPH_HEIGHT, GLYPH_WIDTH};
+use super::{ERROR_BANNER_MIN_MS, ERROR_BANNER_MS, ERROR_BANNER_SCALE};
+
+/// Clear colours, linear.
+const BANNER_COLOR: [f32; 4] = [0.5, 0.02, 0.02, 1.0];
+const BADGE_COLOR: [f32; 4] = [0.15, 0.0, 0.0, 1.0];
+const INK_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+/// Font pixels between the text and the banner's edges.
+const PADDING: u32 = 2;
+
+/// The newest validation error across the top of the window, with a badge
+/// counting every error so far. Drawn with attachment clears rather than a
+/// pipeline, so it needs nothing from the `overlay` feature.
+pub struct ErrorBanner {
+    shown: Option<(String, Instant)>,
+    pending: Option<String>,
+    visible: bool,
+}
+
+impl Default for ErrorBanner {
+    fn default() -> Self {
+        Self {
+            shown: None,
+            pending: None,
+            visible: true,
+        }
+    }
+}
+
+impl ErrorBanner {
+    /// Takes the newest error from `log`. It replaces the one on the banner
+    /// once that has been up `ERROR_BANNER_MIN_MS`, so a storm of messages
+    /// changes the banner at most that often; until then anything newer
+    /// replaces it in turn. A message is taken down after `ERROR_BANNER_MS`.
+    pub fn update(&mut self, log: &ValidationLog, now: Instant) {
+        if let Some(message) = log.take_latest_error() {
+            self.pending = Some(message);
+        }
+        let age = self.shown.as_ref().map(|(_, since)| now - *since);
+        if self.pending.is_some()
+            && age.is_none_or(|age| age >= Duration::from_millis(ERROR_BANNER_MIN_MS))
+        {
+            self.shown = self.pending.take().map(|message| (message, now));
+        } else if age.is_some_and(|age| age >= Duration::from_millis(ERROR_BANNER_MS)) {
+            self.shown = None;
+        }
+    }
+
+    /// Returns whether the banner is now shown. Errors keep arriving while
+    /// it's hidden and the newest is up when it's shown again.
+    pub fn toggle(&mut self) -> bool {
+        self.visible = !self.visible;
+        self.visible
+    }
+
+    /// A message is up, waiting or not yet taken from `log`: frames must
+    /// keep coming to show, replace or take it down.
+    pub fn is_active(&self, log: &ValidationLog) -> bool {
+        self.visible && (self.shown.is_some() || self.pending.is_some() || log.has_new_error())
+    }
+
+    /// The clears that draw the banner in a window of `extent`, in order:
+    /// background, badge, then text, each as a colour and its rects. Empty
+    /// while the banner is hidden or has nothing to show.
+    pub fn layout(
+        &self,
+        extent: vk::Extent2D,
+        error_count: u32,
+    ) -> Vec<([f32; 4], Vec<vk::Rect2D>)> {
+        let Some((message, _)) = self.shown.as_ref().filter(|_| self.visible) else {
+            return Vec::new();
+        };
+        let scale = ERROR_BANNER_SCALE;
+        let padding = PADDING * scale;
+        let height = GLYPH_HEIGHT * scale + 2 * padding;
+        if extent.height < height {
+            return Vec::new();
+        }
+        let mut ink = Vec::new();
+
+        let badge_text = format!(
+            "{} {}",
+            error_count,
+            if error_count == 1 { "ERROR" } else { "ERRORS" }
+        );
+        let badge_width = text_width(badge_text.len() as u32) + 2 * padding;
+        let badge = (badge_width <= extent.width).then(|| {
+            let x = extent.width - badge_width;
+            text_rects(&badge_text, x + padding, padding, &mut ink);
+            rect(x, 0, badge_width, height)
+        });
+
+        let message_right = badge.map_or(extent.width, |badge| badge.offset.x as u32);
+        let fits =
+            (message_right.saturating_sub(2 * padding) + scale) / ((GLYPH_WIDTH + 1) * scale);
+        let chars = message.chars().count() as u32;
+        let text: String = if chars <= fits {
+            message.clone()
+        } else {
+            message
+                .chars()
+                .take(fits.saturating_sub(3) as usize)
+                .chain("...".chars())
+                .collect()
+        };
+        text_rects(&text, padding, padding, &mut ink);
+
+        let mut clears = vec![(BANNER_COLOR, vec![rect(0, 0, extent.width, height)])];
+        clears.extend(badge.map(|badge| (BADGE_COLOR, vec![badge])));
+        if !ink.is_empty() {
+            clears.push((INK_COLOR, ink));
+        }
+        clears
+    }
+}
+
+/// Width in pixels of `chars` glyphs with one font pixel between them.
+fn text_width(chars: u32) -> u32 {
+    (chars * (GLYPH_WIDTH + 1)).saturating_sub(1) * ERROR_BANNER_SCALE
+}
+
+/// Adds a rect per horizontal run of ink in `text`, upper cased, with its
+/// top-left corner at `x`, `y`.
+fn text_rects(text: &str, x: u32, y: u32, rects: &mut Vec<vk::Rect2D>) {
+    let scale = ERROR_BANNER_SCALE;
+    for (index, c) in text.chars().enumerate() {
+        let left = x + index as u32 * (GLYPH_WIDTH + 1) * scale;
+        for (row, bits) in glyph_rows(c.to_ascii_uppercase()).into_iter().enumerate() {
+            let top = y + row as u32 * scale;
+            let mut column = 0;
+            while column < GLYPH_WIDTH {
+                let lit = |column: u32| bits >> (GLYPH_WIDTH - 1 - column) & 1 == 1;
+                if !lit(column) {
+                    column += 1;
+                    continue;
+                }
+                let start = column;
+                while column < GLYPH_WIDTH && lit(column) {
+                    column += 1;
+                }
+                rects.push(rect(
+                    left + start * scale,
+                    top,
+                    (column - start) * scale,
+                    scale,
+                ));
+            }
+        }
+    }
+}
+
+fn rect(x: u32, y: u32, width: u32, height: u32) -> vk::Rect2D {
+    vk::Rect2D {
+        offset: vk::Offset2D {
+            x: x as i32,
+            y: y as i32,
+        },
+        extent: vk::Extent2D { width, height },
+    }
+}
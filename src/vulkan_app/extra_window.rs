@@ -0,0 +1,414 @@
+use ash::vk;
+use winit::window::Window;
+
+use super::allocator::{Allocation, Allocator};
+use super::context::{self, SurfaceBundle, VkContext};
+use super::error::{Stage, VulkanError};
+use super::frame::{FrameIndex, FrameResources};
+use super::mapped_buffer::MappedBuffer;
+use super::memory::ResourceClass;
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::ubo::PerViewUbo;
+use super::MAX_FRAMES_IN_FLIGHT;
+
+/// What each frame in flight needs of its own in an extra window: the
+/// image's semaphores, and the view block and the set reading it.
+struct WindowFrame {
+    image_available: vk::Semaphore,
+    render_finished: vk::Semaphore,
+    uniform_buffer: MappedBuffer,
+    view_set: vk::DescriptorSet,
+}
+
+/// The images an extra window's swapchain has and what is drawn into
+/// them, all sized by its extent.
+struct WindowTargets {
+    views: Vec<vk::ImageView>,
+    depth_image: vk::Image,
+    depth_allocation: Allocation,
+    depth_view: vk::ImageView,
+    framebuffers: Vec<vk::Framebuffer>,
+}
+
+/// Another window onto the scene, with a camera of its own: the main
+/// camera turned `yaw` about the scene's vertical axis. On the main device
+/// it is drawn in the main frame's command buffer, after the main window,
+/// from the same draw list, transforms and per-frame block, and presented
+/// with it, so the main frame's fence covers it too. On another device it
+/// is drawn by that device's `RenderContext`, from its own copies.
+/// Single-sampled and without the main window's post-processing, overlays
+/// or render scale.
+/// The render pass and pipelines come from the app and are rebuilt with
+/// the swapchain; the rest is created here, on the window's device.
+pub struct ExtraWindow {
+    pub window: Window,
+    pub surface: SurfaceBundle,
+    /// Radians from the main camera.
+    pub yaw: f32,
+    /// The app's render context the window is drawn by, for a window
+    /// `--gpu-for-window` put on another device; None on the main one.
+    pub device: Option<usize>,
+    /// Null until the app creates one.
+    pub swapchain: vk::SwapchainKHR,
+    pub extent: vk::Extent2D,
+    pub render_pass: vk::RenderPass,
+    /// Indexed by CUBE_PIPELINE and DOUBLE_SIDED_PIPELINE.
+    pub pipelines: [vk::Pipeline; 2],
+    /// The swapchain is UNORM, so shaders and clears encode sRGB.
+    pub manual_srgb: bool,
+    /// The image acquired for the frame being drawn; None when the window
+    /// sits this frame out.
+    pub acquired: Option<u32>,
+    /// The swapchain no longer matches the window, or the renderer's
+    /// settings, and is rebuilt before the next frame.
+    pub stale: bool,
+    frames: FrameResources<WindowFrame, MAX_FRAMES_IN_FLIGHT>,
+    descriptor_pool: vk::DescriptorPool,
+    // None until `create_targets`, and again after `destroy_targets`.
+    targets: Option<WindowTargets>,
+}
+
+impl ExtraWindow {
+    /// The window's per-frame semaphores and view blocks, with sets of
+    /// `view_set_layout`, on `context`: the main one or `device`'s. The
+    /// swapchain is the app's to create.
+    pub fn new(
+        context: &VkContext,
+        window: Window,
+        surface: SurfaceBundle,
+        yaw: f32,
+        device: Option<usize>,
+        view_set_layout: vk::DescriptorSetLayout,
+        resources: &mut ResourceRegistry,
+    ) -> Result<Self, VulkanError> {
+        let device = &context.device;
+        let pool_size = vk::DescriptorPoolSize::builder()
+            .ty(vk::DescriptorType::UNIFORM_BUFFER)
+            .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32)
+            .build();
+        let pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(std::slice::from_ref(&pool_size))
+            .max_sets(MAX_FRAMES_IN_FLIGHT as u32);
+        let descriptor_pool = unsafe { device.create_descriptor_pool(&pool_info, None).unwrap() };
+        resources.register(
+            ResourceKind::DescriptorPool,
+            descriptor_pool,
+            "extra window descriptor pool",
+            0,
+        );
+        let layouts = [view_set_layout; MAX_FRAMES_IN_FLIGHT];
+        let alloc_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&layouts);
+        let view_sets = unsafe { device.allocate_descriptor_sets(&alloc_info).unwrap() };
+
+        let size = std::mem::size_of::<PerViewUbo>() as vk::DeviceSize;
+        let mut uniform_buffers = Vec::with_capacity(MAX_FRAMES_IN_FLIGHT);
+        for _ in 0..MAX_FRAMES_IN_FLIGHT {
+            let created = context.create_buffer(
+                ResourceClass::Uniform,
+                size,
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+            );
+            let (buffer, allocation) = match created {
+                Ok(created) => created,
+                Err(error) => {
+                    for uniform_buffer in uniform_buffers {
+                        resources.unregister(ResourceKind::Buffer, uniform_buffer.buffer);
+                        uniform_buffer.destroy(device, &context.allocator);
+                    }
+                    resources.unregister(ResourceKind::DescriptorPool, descriptor_pool);
+                    unsafe {
+                        device.destroy_descriptor_pool(descriptor_pool, None);
+                        surface.destroy();
+                    }
+                    return Err(error);
+                }
+            };
+            resources.register(
+                ResourceKind::Buffer,
+                buffer,
+                "extra window view buffer",
+                size,
+            );
+            uniform_buffers.push(MappedBuffer::new(buffer, allocation, size));
+        }
+
+        let semaphore_info = vk::SemaphoreCreateInfo::builder();
+        let mut uniform_buffers = uniform_buffers.into_iter();
+        let frames = FrameResources::new(std::array::from_fn(|i| {
+            let uniform_buffer = uniform_buffers.next().expect("one per frame");
+            let buffer_info = vk::DescriptorBufferInfo::builder()
+                .buffer(uniform_buffer.buffer)
+                .offset(0)
+                .range(size)
+                .build();
+            let write = vk::WriteDescriptorSet::builder()
+                .dst_set(view_sets[i])
+                .dst_binding(0)
+                .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                .buffer_info(std::slice::from_ref(&buffer_info));
+            unsafe {
+                device.update_descriptor_sets(std::slice::from_ref(&write), &[]);
+                WindowFrame {
+                    image_available: device.create_semaphore(&semaphore_info, None).unwrap(),
+                    render_finished: device.create_semaphore(&semaphore_info, None).unwrap(),
+                    uniform_buffer,
+                    view_set: view_sets[i],
+                }
+            }
+        }));
+        Ok(Self {
+            window,
+            surface,
+            yaw,
+            device,
+            swapchain: vk::SwapchainKHR::null(),
+            extent: vk::Extent2D::default(),
+            render_pass: vk::RenderPass::null(),
+            pipelines: [vk::Pipeline::null(); 2],
+            manual_srgb: false,
+            acquired: None,
+            stale: false,
+            frames,
+            descriptor_pool,
+            targets: None,
+        })
+    }
+
+    /// Views of the swapchain's images, of `format`, a depth image of
+    /// `depth_format`, and framebuffers for `render_pass` over them. The
+    /// swapchain, extent and render pass must already be set.
+    pub fn create_targets(
+        &mut self,
+        context: &VkContext,
+        swapchain_loader: &ash::extensions::khr::Swapchain,
+        format: vk::Format,
+        depth_format: vk::Format,
+        resources: &mut ResourceRegistry,
+    ) -> Result<(), VulkanError> {
+        let images = unsafe { swapchain_loader.get_swapchain_images(self.swapchain) }
+            .map_err(VulkanError::at(Stage::SwapchainCreation))?;
+        let (depth_image, depth_allocation) = context.create_image(
+            self.extent.width,
+            self.extent.height,
+            vk::SampleCountFlags::TYPE_1,
+            depth_format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT
+                | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+        )?;
+        let depth_view = context.create_image_view(
+            depth_image,
+            depth_format,
+            context::depth_aspect_flags(depth_format),
+        );
+        resources.register(
+            ResourceKind::Image,
+            depth_image,
+            "extra window depth image",
+            depth_allocation.size,
+        );
+        resources.register(
+            ResourceKind::ImageView,
+            depth_view,
+            "extra window depth view",
+            0,
+        );
+        let views: Vec<vk::ImageView> = images
+            .iter()
+            .map(|&image| {
+                let view = context.create_image_view(image, format, vk::ImageAspectFlags::COLOR);
+                resources.register(ResourceKind::ImageView, view, "extra window image view", 0);
+                view
+            })
+            .collect();
+        let framebuffers = views
+            .iter()
+            .map(|&view| {
+                let attachments = [view, depth_view];
+                let framebuffer_info = vk::FramebufferCreateInfo::builder()
+                    .render_pass(self.render_pass)
+                    .attachments(&attachments)
+                    .width(self.extent.width)
+                    .height(self.extent.height)
+                    .layers(1);
+                unsafe {
+                    context
+                        .device
+                        .create_framebuffer(&framebuffer_info, None)
+                        .unwrap()
+                }
+            })
+            .collect();
+        self.targets = Some(WindowTargets {
+            views,
+            depth_image,
+            depth_allocation,
+            depth_view,
+            framebuffers,
+        });
+        Ok(())
+    }
+
+    /// Acquires the image `frame` draws into, if the window has one to
+    /// give. Out of date, suboptimal or lost, it is marked stale; unless
+    /// suboptimal, it also sits the frame out.
+    pub fn acquire(
+        &mut self,
+        swapchain_loader: &ash::extensions::khr::Swapchain,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+    ) -> Result<(), VulkanError> {
+        self.acquired = None;
+        if self.targets.is_none() {
+            return Ok(());
+        }
+        let result = unsafe {
+            swapchain_loader.acquire_next_image(
+                self.swapchain,
+                u64::MAX,
+                self.frames[frame].image_available,
+                vk::Fence::null(),
+            )
+        };
+        match result {
+            Ok((image_index, is_suboptimal)) => {
+                self.stale |= is_suboptimal;
+                self.acquired = Some(image_index);
+            }
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR | vk::Result::ERROR_SURFACE_LOST_KHR) => {
+                self.stale = true
+            }
+            Err(error) => return Err(VulkanError::at(Stage::Acquire)(error)),
+        }
+        Ok(())
+    }
+
+    /// The semaphores the frame's submission waits on and signals for the
+    /// acquired image.
+    pub fn semaphores(
+        &self,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+    ) -> (vk::Semaphore, vk::Semaphore) {
+        (
+            self.frames[frame].image_available,
+            self.frames[frame].render_finished,
+        )
+    }
+
+    /// Writes the view block `frame` draws with.
+    pub fn write_view(&self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>, view: PerViewUbo) {
+        self.frames[frame].uniform_buffer.write(0, &view);
+    }
+
+    pub fn view_set(&self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>) -> vk::DescriptorSet {
+        self.frames[frame].view_set
+    }
+
+    /// Whether the window has any pixels to draw; minimized, it doesn't.
+    pub fn has_area(&self) -> bool {
+        let size = self.window.inner_size();
+        size.width > 0 && size.height > 0
+    }
+
+    /// The acquired image's framebuffer, if one was acquired.
+    pub fn framebuffer(&self) -> Option<vk::Framebuffer> {
+        let targets = self.targets.as_ref()?;
+        self.acquired
+            .map(|image_index| targets.framebuffers[image_index as usize])
+    }
+
+    /// Presents the acquired image, once `frame`'s submission has drawn it.
+    /// Out of date, suboptimal or lost, the window is marked stale.
+    pub fn present(
+        &mut self,
+        swapchain_loader: &ash::extensions::khr::Swapchain,
+        queue: vk::Queue,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+    ) -> Result<(), VulkanError> {
+        let Some(image_index) = self.acquired.take() else {
+            return Ok(());
+        };
+        let present_info = vk::PresentInfoKHR::builder()
+            .wait_semaphores(std::slice::from_ref(&self.frames[frame].render_finished))
+            .swapchains(std::slice::from_ref(&self.swapchain))
+            .image_indices(std::slice::from_ref(&image_index));
+        match unsafe { swapchain_loader.queue_present(queue, &present_info) } {
+            Ok(false) => {}
+            // A lost surface fails to rebuild, which closes the window.
+            Ok(true)
+            | Err(
+                vk::Result::SUBOPTIMAL_KHR
+                | vk::Result::ERROR_OUT_OF_DATE_KHR
+                | vk::Result::ERROR_SURFACE_LOST_KHR,
+            ) => {
+                self.stale = true;
+            }
+            Err(error) => return Err(VulkanError::at(Stage::Present)(error)),
+        }
+        Ok(())
+    }
+
+    /// The framebuffers, image views and depth image, then the pipelines
+    /// and render pass; the swapchain is left for the next one to replace.
+    /// Safe to call again.
+    pub fn destroy_targets(
+        &mut self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        resources: &mut ResourceRegistry,
+    ) {
+        if let Some(targets) = self.targets.take() {
+            unsafe {
+                for &framebuffer in &targets.framebuffers {
+                    device.destroy_framebuffer(framebuffer, None);
+                }
+                for &view in &targets.views {
+                    resources.unregister(ResourceKind::ImageView, view);
+                    device.destroy_image_view(view, None);
+                }
+                resources.unregister(ResourceKind::ImageView, targets.depth_view);
+                resources.unregister(ResourceKind::Image, targets.depth_image);
+                device.destroy_image_view(targets.depth_view, None);
+                device.destroy_image(targets.depth_image, None);
+            }
+            allocator.free(targets.depth_allocation);
+        }
+        for pipeline in std::mem::take(&mut self.pipelines) {
+            if pipeline != vk::Pipeline::null() {
+                resources.unregister(ResourceKind::Pipeline, pipeline);
+                unsafe { device.destroy_pipeline(pipeline, None) };
+            }
+        }
+        if self.render_pass != vk::RenderPass::null() {
+            unsafe { device.destroy_render_pass(self.render_pass, None) };
+            self.render_pass = vk::RenderPass::null();
+        }
+        self.acquired = None;
+    }
+
+    /// Everything else, swapchain and surface included; the targets must
+    /// already be destroyed and the device idle. Closes the window.
+    pub fn destroy(
+        self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        swapchain_loader: &ash::extensions::khr::Swapchain,
+        resources: &mut ResourceRegistry,
+    ) {
+        unsafe {
+            if self.swapchain != vk::SwapchainKHR::null() {
+                swapchain_loader.destroy_swapchain(self.swapchain, None);
+            }
+            self.surface.destroy();
+            for frame in self.frames.iter() {
+                device.destroy_semaphore(frame.image_available, None);
+                device.destroy_semaphore(frame.render_finished, None);
+                resources.unregister(ResourceKind::Buffer, frame.uniform_buffer.buffer);
+                frame.uniform_buffer.destroy(device, allocator);
+            }
+            resources.unregister(ResourceKind::DescriptorPool, self.descriptor_pool);
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+        }
+    }
+}
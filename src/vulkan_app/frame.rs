@@ -0,0 +1,112 @@
+use ash::vk;
+use std::ops::{Index, IndexMut};
+
+/// Index of a frame in flight. It can only be created in range and only indexes
+/// `FrameResources` with the same `N`, so it can't be mixed up with a swapchain
+/// image index (a plain `usize` into per-image `Vec`s).
+///
+/// ```
+/// use rust_vulkan::vulkan_app::{FrameIndex, FrameResources};
+///
+/// let fences = FrameResources::<u32, 2>::new([10, 11]);
+/// assert_eq!(fences[FrameIndex::<2>::first().next()], 11);
+/// assert_eq!(fences[FrameIndex::<2>::first().next().next()], 10);
+/// ```
+///
+/// A swapchain image index doesn't index per-frame resources:
+///
+/// ```compile_fail
+/// # use rust_vulkan::vulkan_app::FrameResources;
+/// let fences = FrameResources::<u32, 2>::new([10, 11]);
+/// let image_index: usize = 1;
+/// let _ = fences[image_index];
+/// ```
+///
+/// nor does a frame index index per-image ones:
+///
+/// ```compile_fail
+/// # use rust_vulkan::vulkan_app::FrameIndex;
+/// let framebuffers = vec![10u32, 11, 12];
+/// let _ = framebuffers[FrameIndex::<2>::first()];
+/// ```
+///
+/// An index for more frames than the resources have doesn't compile:
+///
+/// ```compile_fail
+/// # use rust_vulkan::vulkan_app::{FrameIndex, FrameResources};
+/// let fences = FrameResources::<u32, 2>::new([10, 11]);
+/// let _ = fences[FrameIndex::<3>::first().next().next()];
+/// ```
+///
+/// and neither does one made from an arbitrary number:
+///
+/// ```compile_fail
+/// # use rust_vulkan::vulkan_app::FrameIndex;
+/// let _ = FrameIndex::<2>(5);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameIndex<const N: usize>(usize);
+
+impl<const N: usize> FrameIndex<N> {
+    pub fn first() -> Self {
+        Self(0)
+    }
+
+    pub fn next(self) -> Self {
+        Self((self.0 + 1) % N)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct FrameSync {
+    pub image_available_semaphore: vk::Semaphore,
+    pub render_finished_semaphore: vk::Semaphore,
+    pub in_flight_fence: vk::Fence,
+    pub command_buffer: vk::CommandBuffer,
+}
+
+pub struct FrameResources<T, const N: usize> {
+    frames: [T; N],
+}
+
+impl<T, const N: usize> FrameResources<T, N> {
+    pub fn new(frames: [T; N]) -> Self {
+        Self { frames }
+    }
+
+    /// One from `create` per frame, or its first error.
+    pub fn try_from_fn<E>(mut create: impl FnMut(usize) -> Result<T, E>) -> Result<Self, E> {
+        let mut frames = Vec::with_capacity(N);
+        for i in 0..N {
+            frames.push(create(i)?);
+        }
+        match frames.try_into() {
+            Ok(frames) => Ok(Self { frames }),
+            Err(_) => unreachable!("one per frame"),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.frames.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.frames.iter_mut()
+    }
+}
+
+impl<T, const N: usize> Index<FrameIndex<N>> for FrameResources<T, N> {
+    type Output = T;
+
+    fn index(&self, index: FrameIndex<N>) -> &T {
+        debug_assert!(index.0 < N, "a FrameIndex<N> is always below N");
+        &self.frames[index.0]
+    }
+}
+
+impl<T, const N: usize> IndexMut<FrameIndex<N>> for FrameResources<T, N> {
+    fn index_mut(&mut self, index: FrameIndex<N>) -> &mut T {
+        debug_assert!(index.0 < N, "a FrameIndex<N> is always below N");
+        &mut self.frames[index.0]
+    }
+}
@@ -0,0 +1,178 @@
+use ash::vk;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::frame::{FrameIndex, FrameResources};
+use super::{FRAME_STATS_SAMPLES, MAX_FRAMES_IN_FLIGHT};
+
+/// CPU frame times, start to start, of the last FRAME_STATS_SAMPLES frames,
+/// and the GPU render pass times of those whose timestamps were read back.
+#[derive(Default)]
+pub struct FrameStats {
+    cpu_ms: VecDeque<f32>,
+    gpu_ms: VecDeque<f32>,
+    throttled: bool,
+}
+
+impl FrameStats {
+    /// `throttled` frames were held back on purpose, as while the window is
+    /// out of focus. Going from throttled frames to unthrottled ones or back
+    /// starts the averages over, so neither rate skews the other's.
+    pub fn push(&mut self, frame_time: Duration, throttled: bool) {
+        if throttled != self.throttled {
+            self.cpu_ms.clear();
+            self.gpu_ms.clear();
+            self.throttled = throttled;
+        }
+        Self::push_sample(&mut self.cpu_ms, frame_time.as_secs_f32() * 1000.0);
+    }
+
+    /// The frames averaged were throttled: `fps` is the throttle's rate,
+    /// not what the renderer can do.
+    pub fn is_throttled(&self) -> bool {
+        self.throttled
+    }
+
+    pub fn push_gpu_ms(&mut self, ms: f32) {
+        Self::push_sample(&mut self.gpu_ms, ms);
+    }
+
+    /// From the average frame time; 0 before the second frame.
+    pub fn fps(&self) -> f32 {
+        let avg = self.avg_frame_ms();
+        if avg > 0.0 {
+            1000.0 / avg
+        } else {
+            0.0
+        }
+    }
+
+    pub fn avg_frame_ms(&self) -> f32 {
+        Self::average(&self.cpu_ms).unwrap_or(0.0)
+    }
+
+    /// The frame time `p` percent of the window's frames are at or below,
+    /// by nearest rank. `percentile_frame_ms(99.0)` is the 1% low.
+    pub fn percentile_frame_ms(&self, p: f32) -> f32 {
+        if self.cpu_ms.is_empty() {
+            return 0.0;
+        }
+        let mut sorted: Vec<f32> = self.cpu_ms.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let rank = (p.clamp(0.0, 100.0) / 100.0 * sorted.len() as f32).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+
+    /// `None` without GPU timestamps.
+    pub fn avg_gpu_ms(&self) -> Option<f32> {
+        Self::average(&self.gpu_ms)
+    }
+
+    fn push_sample(samples: &mut VecDeque<f32>, ms: f32) {
+        if samples.len() == FRAME_STATS_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(ms);
+    }
+
+    fn average(samples: &VecDeque<f32>) -> Option<f32> {
+        (!samples.is_empty()).then(|| samples.iter().sum::<f32>() / samples.len() as f32)
+    }
+}
+
+/// Timestamps at the start and end of each frame's render pass, in one
+/// query pool per frame in flight. Results are read without waiting once
+/// the frame's fence has signalled.
+pub struct GpuTimer {
+    pools: FrameResources<vk::QueryPool, MAX_FRAMES_IN_FLIGHT>,
+    // Submitted since the last read, so a frame that was acquired but never
+    // submitted isn't counted twice.
+    submitted: FrameResources<bool, MAX_FRAMES_IN_FLIGHT>,
+    /// Nanoseconds per tick, `VkPhysicalDeviceLimits::timestampPeriod`.
+    period: f32,
+}
+
+impl GpuTimer {
+    pub fn new(device: &ash::Device, period: f32) -> Self {
+        let query_pool_info = vk::QueryPoolCreateInfo::builder()
+            .query_type(vk::QueryType::TIMESTAMP)
+            .query_count(2);
+        Self {
+            pools: FrameResources::new(std::array::from_fn(|_| unsafe {
+                device.create_query_pool(&query_pool_info, None).unwrap()
+            })),
+            submitted: FrameResources::new([false; MAX_FRAMES_IN_FLIGHT]),
+            period,
+        }
+    }
+
+    /// Before the render pass begins.
+    pub fn record_start(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+    ) {
+        unsafe {
+            device.cmd_reset_query_pool(command_buffer, self.pools[frame], 0, 2);
+            device.cmd_write_timestamp(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                self.pools[frame],
+                0,
+            );
+        }
+    }
+
+    /// After the render pass ends.
+    pub fn record_end(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+    ) {
+        unsafe {
+            device.cmd_write_timestamp(
+                command_buffer,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                self.pools[frame],
+                1,
+            );
+        }
+    }
+
+    pub fn mark_submitted(&mut self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>) {
+        self.submitted[frame] = true;
+    }
+
+    /// The render pass time of `frame`'s last submission, in milliseconds,
+    /// if it hasn't been read yet and the results are available.
+    pub fn read(
+        &mut self,
+        device: &ash::Device,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+    ) -> Option<f32> {
+        if !self.submitted[frame] {
+            return None;
+        }
+        let mut ticks = [0u64; 2];
+        unsafe {
+            device.get_query_pool_results(
+                self.pools[frame],
+                0,
+                2,
+                &mut ticks,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        }
+        .ok()?;
+        self.submitted[frame] = false;
+        Some(ticks[1].wrapping_sub(ticks[0]) as f32 * self.period / 1_000_000.0)
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        for &pool in self.pools.iter() {
+            unsafe { device.destroy_query_pool(pool, None) };
+        }
+    }
+}
@@ -0,0 +1,228 @@
+// GLSL to SPIR-V with shaderc. build.rs compiles every shader with this at
+// build time, `--compile-shaders` the cube's again at startup and the shader
+// layout test all of them, so it can't reach anything else in the crate.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Shaders also compiled with a define set to each of these values, as
+/// `<STEM>_<EXT>_<DEFINE>_<VALUE>_SHADER_PATH`. The plain build leaves the
+/// define to the shader's default.
+pub const VARIANTS: &[(&str, &str, &[u32])] = &[
+    ("shader.vert", "DRAW_PUSH_FIELDS", &[0]),
+    ("shader.frag", "DRAW_PUSH_FIELDS", &[0]),
+    ("depth_view.frag", "MULTISAMPLED", &[1]),
+];
+
+/// Every build of every shader in `shader_dir` the renderer uses: each one
+/// plain, then once per `VARIANTS` value, sorted by file name.
+pub fn permutations(
+    shader_dir: &Path,
+) -> std::io::Result<Vec<(PathBuf, Option<(&'static str, u32)>)>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(shader_dir)? {
+        let path = entry?.path();
+        if path.is_file() && shader_kind(&path).is_some() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut permutations = Vec::new();
+    for path in paths {
+        let file_name = path.file_name().and_then(|name| name.to_str());
+        let defines = VARIANTS
+            .iter()
+            .filter(|&&(variant_file, _, _)| Some(variant_file) == file_name)
+            .flat_map(|&(_, define, values)| values.iter().map(move |&value| (define, value)));
+        let builds = std::iter::once(None).chain(defines.map(Some));
+        permutations.extend(builds.map(|define| (path.clone(), define)));
+    }
+    Ok(permutations)
+}
+
+/// The stage a shader file is for, from its extension: vert, frag or comp.
+pub fn shader_kind(path: &Path) -> Option<shaderc::ShaderKind> {
+    match path.extension()?.to_str()? {
+        "vert" => Some(shaderc::ShaderKind::Vertex),
+        "frag" => Some(shaderc::ShaderKind::Fragment),
+        "comp" => Some(shaderc::ShaderKind::Compute),
+        _ => None,
+    }
+}
+
+/// Compiles the GLSL at `path` to SPIR-V, with `define` set if given.
+/// `#include "x"` is resolved next to the including file and
+/// `#include <x>` in `shader_dir`. Errors quote the offending source lines.
+pub fn compile(
+    compiler: &shaderc::Compiler,
+    shader_dir: &Path,
+    path: &Path,
+    define: Option<(&str, u32)>,
+) -> Result<Vec<u8>, String> {
+    let name = path.to_str().ok_or("non UTF-8 shader path")?;
+    let kind = shader_kind(path).ok_or_else(|| format!("{}: not a shader stage", name))?;
+    let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", name, e))?;
+
+    let parents = RefCell::new(HashMap::new());
+    let mut options = shaderc::CompileOptions::new().ok_or("shaderc options not available")?;
+    options.set_include_callback(|requested, include_type, requesting_source, _depth| {
+        resolve_include(
+            &parents,
+            shader_dir,
+            requested,
+            include_type,
+            requesting_source,
+        )
+    });
+    if let Some((define, value)) = define {
+        options.add_macro_definition(define, Some(&value.to_string()));
+    }
+    compiler
+        .compile_into_spirv(&source, kind, name, "main", Some(&options))
+        .map(|compiled| compiled.as_binary_u8().to_vec())
+        .map_err(|error| explain(&error))
+}
+
+/// `parents` maps each resolved file to the file that included it; since
+/// includes are expanded depth-first, walking it from the includer gives
+/// the current include stack, which is how cycles are caught.
+fn resolve_include(
+    parents: &RefCell<HashMap<String, String>>,
+    shader_dir: &Path,
+    requested: &str,
+    include_type: shaderc::IncludeType,
+    requesting_source: &str,
+) -> shaderc::IncludeCallbackResult {
+    let base = match include_type {
+        shaderc::IncludeType::Relative => {
+            Path::new(requesting_source).parent().unwrap_or(shader_dir)
+        }
+        shaderc::IncludeType::Standard => shader_dir,
+    };
+    let path = base.join(requested);
+    let resolved_name = path.to_str().ok_or("non UTF-8 include path")?.to_string();
+
+    let mut parents = parents.borrow_mut();
+    let mut stack = vec![resolved_name.clone()];
+    let mut current = Some(requesting_source.to_string());
+    while let Some(file) = current {
+        stack.push(file.clone());
+        if file == resolved_name {
+            stack.reverse();
+            return Err(format!("include cycle: {}", stack.join(" -> ")));
+        }
+        current = parents.get(&file).cloned();
+    }
+    parents.insert(resolved_name.clone(), requesting_source.to_string());
+
+    let content = std::fs::read_to_string(&path).map_err(|e| {
+        format!(
+            "{} (included from {}): {}",
+            resolved_name, requesting_source, e
+        )
+    })?;
+    Ok(shaderc::ResolvedInclude {
+        resolved_name,
+        content,
+    })
+}
+
+/// shaderc's diagnostics, each `file:line: message`, followed by that line
+/// of the file where it can be read.
+fn explain(error: &shaderc::Error) -> String {
+    let shaderc::Error::CompilationError(_, log) = error else {
+        return error.to_string();
+    };
+    let mut explained = String::new();
+    for diagnostic in log.lines() {
+        explained.push_str(diagnostic);
+        explained.push('\n');
+        let mut parts = diagnostic.splitn(3, ':');
+        let (Some(file), Some(Ok(line)), Some(_)) = (
+            parts.next(),
+            parts.next().map(|line| line.trim().parse::<usize>()),
+            parts.next(),
+        ) else {
+            continue;
+        };
+        let source = std::fs::read_to_string(file).ok();
+        if let Some(text) = source
+            .as_deref()
+            .and_then(|source| source.lines().nth(line.checked_sub(1)?))
+        {
+            explained.push_str(&format!("{:>5} | {}\n", line, text));
+        }
+    }
+    explained
+}
+
+/// The members of the struct or block type named `name` in a SPIR-V
+/// module, with their byte offsets, in declaration order. `None` if no type
+/// of that name has member offsets, as a struct only copied into locals
+/// doesn't. Names come from the debug names glslang emits unless
+/// optimizing, which `compile` never does.
+pub fn block_offsets(spirv: &[u8], name: &str) -> Option<Vec<(String, u32)>> {
+    const OP_NAME: u32 = 5;
+    const OP_MEMBER_NAME: u32 = 6;
+    const OP_MEMBER_DECORATE: u32 = 72;
+    const DECORATION_OFFSET: u32 = 35;
+    // The magic number, version, generator, bound and schema come first.
+    const HEADER_WORDS: usize = 5;
+
+    let words: Vec<u32> = spirv
+        .chunks_exact(4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect();
+    let mut named = Vec::new();
+    let mut member_names = HashMap::new();
+    let mut offsets = Vec::new();
+    let mut at = HEADER_WORDS;
+    while at < words.len() {
+        let word_count = (words[at] >> 16) as usize;
+        let operands = words.get(at + 1..at + word_count)?;
+        match (words[at] & 0xffff, operands) {
+            (OP_NAME, [id, text @ ..]) if literal_string(text) == name => named.push(*id),
+            (OP_MEMBER_NAME, [id, member, text @ ..]) => {
+                member_names.insert((*id, *member), literal_string(text));
+            }
+            (OP_MEMBER_DECORATE, [id, member, DECORATION_OFFSET, offset]) => {
+                offsets.push((*id, *member, *offset));
+            }
+            _ => {}
+        }
+        at += word_count;
+    }
+
+    // glslang declares a struct again for each layout it's used with.
+    let id = *named
+        .iter()
+        .find(|&&id| offsets.iter().any(|&(owner, _, _)| owner == id))?;
+    let mut members: Vec<(u32, u32)> = offsets
+        .into_iter()
+        .filter(|&(owner, _, _)| owner == id)
+        .map(|(_, member, offset)| (member, offset))
+        .collect();
+    members.sort_unstable();
+    Some(
+        members
+            .into_iter()
+            .map(|(member, offset)| {
+                let name = member_names.remove(&(id, member)).unwrap_or_default();
+                (name, offset)
+            })
+            .collect(),
+    )
+}
+
+/// A nul-terminated UTF-8 string packed four bytes to a word, little end
+/// first.
+fn literal_string(words: &[u32]) -> String {
+    let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+    let end = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
@@ -0,0 +1,428 @@
+use ash::vk;
+use log::{info, warn};
+use std::mem::offset_of;
+
+use super::allocator::{Allocation, Allocator};
+use super::context::VkContext;
+use super::error::VulkanError;
+use super::frame_stats::FrameStats;
+use super::memory::ResourceClass;
+use super::resources::{ResourceKind, ResourceRegistry};
+
+pub const CELL_WIDTH: u32 = 8;
+pub const CELL_HEIGHT: u32 = 16;
+const ATLAS_COLUMNS: u32 = 16;
+/// ' ' to '~', then a solid cell the line backdrops are drawn with.
+const CELL_COUNT: u32 = FONT.len() as u32 / CELL_HEIGHT + 1;
+const SOLID_CELL: u32 = CELL_COUNT - 1;
+pub const ATLAS_WIDTH: u32 = ATLAS_COLUMNS * CELL_WIDTH;
+pub const ATLAS_HEIGHT: u32 = CELL_COUNT.div_ceil(ATLAS_COLUMNS) * CELL_HEIGHT;
+
+/// Pixels between the window's corner and the first line.
+const MARGIN: f32 = 8.0;
+/// Pixels of backdrop either side of a line's text.
+const PADDING: f32 = 2.0;
+/// Linear, with straight alpha.
+const INK_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct HudVertex {
+    /// Normalized device coordinates, y down.
+    pub pos: [f32; 2],
+    /// In atlas pixels.
+    pub texel: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl HudVertex {
+    pub fn get_binding_description() -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription::builder()
+            .binding(0)
+            .stride(std::mem::size_of::<Self>() as u32)
+            .input_rate(vk::VertexInputRate::VERTEX)
+            .build()
+    }
+
+    pub fn get_attribute_descriptions() -> [vk::VertexInputAttributeDescription; 3] {
+        [
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(0)
+                .format(vk::Format::R32G32_SFLOAT)
+                .offset(offset_of!(Self, pos) as u32)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(1)
+                .format(vk::Format::R32G32_SFLOAT)
+                .offset(offset_of!(Self, texel) as u32)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(2)
+                .format(vk::Format::R32G32B32A32_SFLOAT)
+                .offset(offset_of!(Self, color) as u32)
+                .build(),
+        ]
+    }
+}
+
+/// The font atlas as RGBA8: white, with each glyph's coverage in alpha.
+/// Cells are CELL_WIDTH x CELL_HEIGHT, ATLAS_COLUMNS to a row, in ASCII
+/// order from ' '.
+pub fn atlas_pixels() -> Vec<u8> {
+    let mut pixels = vec![0; (ATLAS_WIDTH * ATLAS_HEIGHT * 4) as usize];
+    for cell in 0..CELL_COUNT {
+        let (left, top) = cell_origin(cell);
+        for row in 0..CELL_HEIGHT {
+            // Past the font's last character is the solid cell.
+            let bits = FONT
+                .get((cell * CELL_HEIGHT + row) as usize)
+                .copied()
+                .unwrap_or(u8::MAX);
+            for column in 0..CELL_WIDTH {
+                let lit = bits >> (CELL_WIDTH - 1 - column) & 1 == 1;
+                let start = (((top + row) * ATLAS_WIDTH + left + column) * 4) as usize;
+                pixels[start..start + 4].copy_from_slice(&[255, 255, 255, 255 * lit as u8]);
+            }
+        }
+    }
+    pixels
+}
+
+fn cell_origin(cell: u32) -> (u32, u32) {
+    (
+        cell % ATLAS_COLUMNS * CELL_WIDTH,
+        cell / ATLAS_COLUMNS * CELL_HEIGHT,
+    )
+}
+
+/// The atlas cell for `c`; anything outside printable ASCII shows as '?'.
+fn cell_for(c: char) -> u32 {
+    match c {
+        ' '..='~' => c as u32 - ' ' as u32,
+        _ => '?' as u32 - ' ' as u32,
+    }
+}
+
+/// Lines of text in the top-left corner, each on a translucent backdrop:
+/// frame rate, frame time, swapchain extent and GPU, then whatever was
+/// printed with `print`.
+pub struct Hud {
+    device_name: String,
+    lines: Vec<String>,
+    visible: bool,
+    // Rebuilt every frame; kept to reuse its allocation.
+    vertices: Vec<HudVertex>,
+}
+
+impl Hud {
+    pub fn new(device_name: String) -> Self {
+        Self {
+            device_name,
+            lines: Vec::new(),
+            visible: true,
+            vertices: Vec::new(),
+        }
+    }
+
+    /// Replaces printed line `line`, counted from the first line under the
+    /// statistics. Lines skipped over are left empty. Returns whether the
+    /// text changed.
+    pub fn print(&mut self, line: usize, text: &str) -> bool {
+        if line >= self.lines.len() {
+            self.lines.resize(line + 1, String::new());
+        }
+        if self.lines[line] == text {
+            return false;
+        }
+        self.lines[line].clear();
+        self.lines[line].push_str(text);
+        true
+    }
+
+    /// Returns whether the HUD is now shown.
+    pub fn toggle(&mut self) -> bool {
+        self.visible = !self.visible;
+        self.visible
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// The HUD's triangles for a frame shown at `display_extent` on a
+    /// swapchain of `swapchain_extent`: six vertices per line backdrop and
+    /// per visible character. They're laid out in display pixels, so the
+    /// text keeps its size when the scene renders at a smaller
+    /// `render_extent` and is stretched over the display.
+    pub fn layout(
+        &mut self,
+        stats: &FrameStats,
+        swapchain_extent: vk::Extent2D,
+        display_extent: vk::Extent2D,
+        render_extent: vk::Extent2D,
+    ) -> &[HudVertex] {
+        let statistics = [
+            format!(
+                "{:.0} FPS, {:.2} ms{}",
+                stats.fps(),
+                stats.avg_frame_ms(),
+                if stats.is_throttled() {
+                    " (throttled)"
+                } else {
+                    ""
+                }
+            ),
+            if render_extent == display_extent {
+                format!(
+                    "Swapchain {}x{}",
+                    swapchain_extent.width, swapchain_extent.height
+                )
+            } else {
+                format!(
+                    "Swapchain {}x{}, rendering {}x{} ({}%)",
+                    swapchain_extent.width,
+                    swapchain_extent.height,
+                    render_extent.width,
+                    render_extent.height,
+                    render_extent.width * 100 / display_extent.width.max(1)
+                )
+            },
+            self.device_name.clone(),
+        ];
+        let (width, height) = (display_extent.width as f32, display_extent.height as f32);
+        let ndc = |x: f32, y: f32| [x / width * 2.0 - 1.0, y / height * 2.0 - 1.0];
+        let vertices = &mut self.vertices;
+        vertices.clear();
+        let mut quad = |x: f32, y: f32, w: f32, h: f32, cell: u32, color: [f32; 4]| {
+            let (left, top) = cell_origin(cell);
+            let (u, v) = (left as f32, top as f32);
+            let corner = |dx: f32, dy: f32| HudVertex {
+                pos: ndc(x + dx * w, y + dy * h),
+                texel: [u + dx * CELL_WIDTH as f32, v + dy * CELL_HEIGHT as f32],
+                color,
+            };
+            vertices.extend([
+                corner(0.0, 0.0),
+                corner(1.0, 0.0),
+                corner(0.0, 1.0),
+                corner(0.0, 1.0),
+                corner(1.0, 0.0),
+                corner(1.0, 1.0),
+            ]);
+        };
+        let (cell_width, cell_height) = (CELL_WIDTH as f32, CELL_HEIGHT as f32);
+        for (index, line) in statistics.iter().chain(&self.lines).enumerate() {
+            let y = MARGIN + index as f32 * cell_height;
+            let chars = line.chars().count();
+            if chars == 0 {
+                continue;
+            }
+            // The solid cell, stretched over the whole line.
+            quad(
+                MARGIN - PADDING,
+                y,
+                chars as f32 * cell_width + 2.0 * PADDING,
+                cell_height,
+                SOLID_CELL,
+                BACKDROP_COLOR,
+            );
+            for (column, c) in line.chars().enumerate() {
+                if c != ' ' {
+                    let x = MARGIN + column as f32 * cell_width;
+                    quad(x, y, cell_width, cell_height, cell_for(c), INK_COLOR);
+                }
+            }
+        }
+        &self.vertices
+    }
+}
+
+/// A persistently mapped vertex buffer holding one frame's HUD. It is only
+/// replaced when the text outgrows it.
+pub struct HudBuffer {
+    pub buffer: vk::Buffer,
+    pub allocation: Allocation,
+    mapped: *mut HudVertex,
+    capacity: usize,
+    vertex_count: u32,
+}
+
+impl HudBuffer {
+    /// Room for `capacity` vertices.
+    pub fn new(
+        context: &VkContext,
+        capacity: usize,
+        resources: &mut ResourceRegistry,
+    ) -> Result<Self, VulkanError> {
+        let size = (capacity * std::mem::size_of::<HudVertex>()) as vk::DeviceSize;
+        let (buffer, allocation) = context.create_buffer(
+            ResourceClass::Vertex,
+            size,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+        )?;
+        resources.register(ResourceKind::Buffer, buffer, "HUD vertices", size);
+        Ok(Self {
+            buffer,
+            allocation,
+            mapped: allocation.mapped_ptr() as *mut HudVertex,
+            capacity,
+            vertex_count: 0,
+        })
+    }
+
+    /// Copies in `vertices`, first swapping the buffer for one twice the
+    /// size they need if they don't fit. If that can't be allocated, only
+    /// what fits is drawn. Only once the frame's last use of the buffer has
+    /// finished.
+    pub fn update(
+        &mut self,
+        context: &VkContext,
+        vertices: &[HudVertex],
+        resources: &mut ResourceRegistry,
+    ) {
+        if vertices.len() > self.capacity {
+            match Self::new(context, 2 * vertices.len(), resources) {
+                Ok(grown) => {
+                    info!("HUD buffer grown to {} vertices", grown.capacity);
+                    self.destroy(&context.device, &context.allocator, resources);
+                    *self = grown;
+                }
+                Err(error) => warn!("HUD buffer can't grow, text cut short: {}", error),
+            }
+        }
+        // Whole quads only.
+        let count = vertices.len().min(self.capacity / 6 * 6);
+        unsafe {
+            self.mapped
+                .copy_from_nonoverlapping(vertices.as_ptr(), count);
+        }
+        self.vertex_count = count as u32;
+    }
+
+    /// The caller binds the HUD pipeline and atlas and sets the scissor.
+    pub fn record(&self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        unsafe {
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[self.buffer], &[0]);
+            device.cmd_draw(command_buffer, self.vertex_count, 1, 0, 0);
+        }
+    }
+
+    pub fn destroy(
+        &self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        resources: &mut ResourceRegistry,
+    ) {
+        resources.unregister(ResourceKind::Buffer, self.buffer);
+        unsafe { device.destroy_buffer(self.buffer, None) };
+        allocator.free(self.allocation);
+    }
+}
+
+/// An 8x16 font drawn for the HUD, one byte per row with the leftmost pixel
+/// in the top bit, CELL_HEIGHT rows per character from ' ' to '~'. Capitals
+/// stand on row 11 and descenders reach row 14.
+const FONT: [u8; 95 * CELL_HEIGHT as usize] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x18, 0x3c, 0x3c, 0x3c, 0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x66, 0x66, 0x66, 0x24, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x6c, 0x6c, 0xfe, 0x6c, 0x6c, 0x6c, 0xfe, 0x6c, 0x6c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x18, 0x7c, 0xc6, 0xc2, 0xc0, 0x7c, 0x06, 0x86, 0xc6, 0x7c, 0x18, 0x18, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0xc2, 0xc6, 0x0c, 0x18, 0x30, 0x60, 0xcc, 0x8c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x38, 0x6c, 0x6c, 0x38, 0x76, 0xdc, 0xcc, 0xcc, 0xcc, 0x76, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x30, 0x30, 0x30, 0x60, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x0c, 0x18, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x18, 0x0c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x60, 0x30, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x30, 0x60, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x66, 0x3c, 0xfe, 0x3c, 0x66, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x7e, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x18, 0x30, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfe, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x02, 0x06, 0x0c, 0x18, 0x30, 0x60, 0xc0, 0x80, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x38, 0x6c, 0xc6, 0xc6, 0xd6, 0xd6, 0xc6, 0xc6, 0x6c, 0x38, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x18, 0x38, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x7e, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x7c, 0xc6, 0x06, 0x0c, 0x18, 0x30, 0x60, 0xc0, 0xc6, 0xfe, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x7c, 0xc6, 0x06, 0x06, 0x3c, 0x06, 0x06, 0x06, 0xc6, 0x7c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x0c, 0x1c, 0x3c, 0x6c, 0xcc, 0xfe, 0x0c, 0x0c, 0x0c, 0x1e, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xfe, 0xc0, 0xc0, 0xc0, 0xfc, 0x06, 0x06, 0x06, 0xc6, 0x7c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x38, 0x60, 0xc0, 0xc0, 0xfc, 0xc6, 0xc6, 0xc6, 0xc6, 0x7c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xfe, 0xc6, 0x06, 0x0c, 0x18, 0x30, 0x30, 0x30, 0x30, 0x30, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x7c, 0xc6, 0xc6, 0xc6, 0x7c, 0xc6, 0xc6, 0xc6, 0xc6, 0x7c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x7c, 0xc6, 0xc6, 0xc6, 0x7e, 0x06, 0x06, 0x06, 0x0c, 0x78, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x18, 0x18, 0x30, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x06, 0x0c, 0x18, 0x30, 0x60, 0x30, 0x18, 0x0c, 0x06, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7e, 0x00, 0x00, 0x7e, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x60, 0x30, 0x18, 0x0c, 0x06, 0x0c, 0x18, 0x30, 0x60, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x7c, 0xc6, 0xc6, 0x0c, 0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x7c, 0xc6, 0xc6, 0xde, 0xde, 0xde, 0xdc, 0xc0, 0x7c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x10, 0x38, 0x6c, 0xc6, 0xc6, 0xfe, 0xc6, 0xc6, 0xc6, 0xc6, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xfc, 0x66, 0x66, 0x66, 0x7c, 0x66, 0x66, 0x66, 0x66, 0xfc, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x3c, 0x66, 0xc2, 0xc0, 0xc0, 0xc0, 0xc0, 0xc2, 0x66, 0x3c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xf8, 0x6c, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x6c, 0xf8, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xfe, 0x66, 0x62, 0x68, 0x78, 0x68, 0x60, 0x62, 0x66, 0xfe, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xfe, 0x66, 0x62, 0x68, 0x78, 0x68, 0x60, 0x60, 0x60, 0xf0, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x3c, 0x66, 0xc2, 0xc0, 0xc0, 0xde, 0xc6, 0xc6, 0x66, 0x3a, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xc6, 0xc6, 0xc6, 0xc6, 0xfe, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x3c, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x1e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0xcc, 0xcc, 0xcc, 0x78, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xe6, 0x66, 0x6c, 0x6c, 0x78, 0x78, 0x6c, 0x66, 0x66, 0xe6, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xf0, 0x60, 0x60, 0x60, 0x60, 0x60, 0x60, 0x62, 0x66, 0xfe, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xc6, 0xee, 0xfe, 0xfe, 0xd6, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xc6, 0xe6, 0xf6, 0xfe, 0xde, 0xce, 0xc6, 0xc6, 0xc6, 0xc6, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x7c, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0x7c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xfc, 0x66, 0x66, 0x66, 0x7c, 0x60, 0x60, 0x60, 0x60, 0xf0, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x7c, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0xd6, 0xde, 0x7c, 0x0c, 0x0e, 0x00, 0x00,
+    0x00, 0x00, 0xfc, 0x66, 0x66, 0x66, 0x7c, 0x6c, 0x66, 0x66, 0x66, 0xe6, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x7c, 0xc6, 0xc6, 0x60, 0x38, 0x0c, 0x06, 0xc6, 0xc6, 0x7c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x7e, 0x5a, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0x7c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0x6c, 0x38, 0x10, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xc6, 0xc6, 0xc6, 0xc6, 0xd6, 0xd6, 0xd6, 0xfe, 0x6c, 0x6c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xc6, 0xc6, 0x6c, 0x7c, 0x38, 0x38, 0x7c, 0x6c, 0xc6, 0xc6, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x66, 0x66, 0x66, 0x66, 0x3c, 0x18, 0x18, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xfe, 0xc6, 0x8c, 0x18, 0x30, 0x60, 0xc0, 0xc2, 0xc6, 0xfe, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x3c, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x3c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x80, 0xc0, 0x60, 0x30, 0x18, 0x0c, 0x06, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x78, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x10, 0x38, 0x6c, 0xc6, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x00,
+    0x00, 0x30, 0x18, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x78, 0x0c, 0x7c, 0xcc, 0xcc, 0xcc, 0x76, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xe0, 0x60, 0x60, 0x78, 0x6c, 0x66, 0x66, 0x66, 0x66, 0x7c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x7c, 0xc6, 0xc0, 0xc0, 0xc0, 0xc6, 0x7c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x1c, 0x0c, 0x0c, 0x3c, 0x6c, 0xcc, 0xcc, 0xcc, 0xcc, 0x76, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x7c, 0xc6, 0xfe, 0xc0, 0xc0, 0xc6, 0x7c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x38, 0x6c, 0x64, 0x60, 0xf0, 0x60, 0x60, 0x60, 0x60, 0xf0, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x76, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0x7c, 0x0c, 0xcc, 0x78, 0x00,
+    0x00, 0x00, 0xe0, 0x60, 0x60, 0x6c, 0x76, 0x66, 0x66, 0x66, 0x66, 0xe6, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x18, 0x18, 0x00, 0x38, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x06, 0x06, 0x00, 0x0e, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x66, 0x66, 0x3c, 0x00,
+    0x00, 0x00, 0xe0, 0x60, 0x60, 0x66, 0x6c, 0x78, 0x78, 0x6c, 0x66, 0xe6, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x38, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0xec, 0xfe, 0xd6, 0xd6, 0xd6, 0xd6, 0xc6, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0xdc, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x7c, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0x7c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0xdc, 0x66, 0x66, 0x66, 0x66, 0x66, 0x7c, 0x60, 0x60, 0xf0, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x76, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0x7c, 0x0c, 0x0c, 0x1e, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0xdc, 0x76, 0x66, 0x60, 0x60, 0x60, 0xf0, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x7c, 0xc6, 0x60, 0x38, 0x0c, 0xc6, 0x7c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x10, 0x30, 0xfc, 0x30, 0x30, 0x30, 0x30, 0x36, 0x1c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0x76, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0xc6, 0xc6, 0xc6, 0xc6, 0x6c, 0x38, 0x10, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0xc6, 0xc6, 0xd6, 0xd6, 0xd6, 0xfe, 0x6c, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0xc6, 0x6c, 0x38, 0x38, 0x38, 0x6c, 0xc6, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0xc6, 0x7e, 0x06, 0x0c, 0xf8, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0xfe, 0xcc, 0x18, 0x30, 0x60, 0xc6, 0xfe, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x0e, 0x18, 0x18, 0x18, 0x70, 0x18, 0x18, 0x18, 0x18, 0x0e, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xe0, 0x30, 0x30, 0x30, 0x1c, 0x30, 0x30, 0x30, 0x30, 0xe0, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x76, 0xdc, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
@@ -0,0 +1,19 @@
+use log::warn;
+use winit::window::Icon;
+
+use super::png;
+
+/// The window and taskbar icon: a 32x32 cube, built into the binary.
+/// `None`, with a warning, if it can't be decoded.
+pub fn window_icon() -> Option<Icon> {
+    let icon = png::decode_rgba(include_bytes!("icon.png")).and_then(|(width, height, rgba)| {
+        Icon::from_rgba(rgba, width, height).map_err(|error| error.to_string())
+    });
+    match icon {
+        Ok(icon) => Some(icon),
+        Err(error) => {
+            warn!("No window icon: {}", error);
+            None
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use ash::vk;
+
+/// Where vertex colors (authored in sRGB) get decoded to linear: before
+/// interpolation (correct) or per fragment after interpolating the encoded values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorInterpolation {
+    Linear,
+    NaiveSrgb,
+}
+
+impl ColorInterpolation {
+    /// Value of the `NAIVE_SRGB_INTERPOLATION` specialization constant.
+    pub fn specialization_value(self) -> vk::Bool32 {
+        match self {
+            ColorInterpolation::Linear => vk::FALSE,
+            ColorInterpolation::NaiveSrgb => vk::TRUE,
+        }
+    }
+}
+
+const DIVIDER_WIDTH: u32 = 2;
+
+/// Side-by-side comparison: linear interpolation left of the divider, naive sRGB
+/// interpolation right of it.
+pub struct SplitView {
+    pub enabled: bool,
+    pub dragging: bool,
+    divider: f32,
+}
+
+impl SplitView {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            dragging: false,
+            divider: 0.5,
+        }
+    }
+
+    pub fn drag_to(&mut self, x_fraction: f32) {
+        if self.enabled && self.dragging {
+            self.divider = x_fraction.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Scissors for the linear and naive halves of `area`; a side is `None` when
+    /// the divider has been dragged to its edge.
+    pub fn scissors(&self, area: vk::Rect2D) -> [Option<vk::Rect2D>; 2] {
+        let extent = area.extent;
+        let split = ((extent.width as f32 * self.divider) as u32).min(extent.width);
+        let left = vk::Rect2D {
+            offset: area.offset,
+            extent: vk::Extent2D {
+                width: split,
+                height: extent.height,
+            },
+        };
+        let right = vk::Rect2D {
+            offset: vk::Offset2D {
+                x: area.offset.x + split as i32,
+                y: area.offset.y,
+            },
+            extent: vk::Extent2D {
+                width: extent.width - split,
+                height: extent.height,
+            },
+        };
+
+        [
+            (left.extent.width > 0).then_some(left),
+            (right.extent.width > 0).then_some(right),
+        ]
+    }
+
+    pub fn divider_rect(&self, area: vk::Rect2D) -> vk::Rect2D {
+        let extent = area.extent;
+        let split = (extent.width as f32 * self.divider) as u32;
+        let x = split.saturating_sub(DIVIDER_WIDTH / 2);
+        vk::Rect2D {
+            offset: vk::Offset2D {
+                x: area.offset.x + x as i32,
+                y: area.offset.y,
+            },
+            extent: vk::Extent2D {
+                width: DIVIDER_WIDTH.min(extent.width.saturating_sub(x)),
+                height: extent.height,
+            },
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use ash::vk;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use winit::event::{ElementState, MouseButton, VirtualKeyCode};
+
+use super::JOURNAL_CAPACITY;
+
+/// Bytes of a validation message kept in its journal entry.
+const JOURNAL_TEXT_BYTES: usize = 96;
+
+/// Why the swapchain was recreated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecreateReason {
+    /// A resize event, or an acquire that came back suboptimal, flagged the
+    /// swapchain during the frame.
+    WindowResized,
+    /// Acquire or present returned ERROR_OUT_OF_DATE_KHR.
+    OutOfDate,
+    /// Present returned SUBOPTIMAL_KHR.
+    Suboptimal,
+    /// Reloaded settings changed the present mode.
+    SettingsChanged,
+    /// Acquire or present returned ERROR_SURFACE_LOST_KHR; the surface was
+    /// created again along with the swapchain.
+    SurfaceLost,
+}
+
+/// A string truncated into a fixed buffer, so recording it doesn't allocate.
+#[derive(Clone, Copy)]
+pub struct JournalText {
+    bytes: [u8; JOURNAL_TEXT_BYTES],
+    len: u8,
+}
+
+impl JournalText {
+    pub fn new(text: &str) -> Self {
+        let mut len = text.len().min(JOURNAL_TEXT_BYTES);
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+        let mut bytes = [0; JOURNAL_TEXT_BYTES];
+        bytes[..len].copy_from_slice(&text.as_bytes()[..len]);
+        Self {
+            bytes,
+            len: len as u8,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or("")
+    }
+}
+
+impl fmt::Debug for JournalText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum JournalEvent {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton, ElementState),
+    Resize {
+        width: u32,
+        height: u32,
+    },
+    SwapchainRecreated {
+        reason: RecreateReason,
+        extent: vk::Extent2D,
+    },
+    /// Only results other than plain success are recorded.
+    Acquire(vk::Result),
+    Present(vk::Result),
+    Validation {
+        severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+        message: JournalText,
+    },
+}
+
+impl fmt::Display for JournalEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JournalEvent::Key(key) => write!(f, "key {:?}", key),
+            JournalEvent::Mouse(button, state) => write!(f, "mouse {:?} {:?}", button, state),
+            JournalEvent::Resize { width, height } => write!(f, "resize to {}x{}", width, height),
+            JournalEvent::SwapchainRecreated { reason, extent } => write!(
+                f,
+                "swapchain recreated ({:?}) at {}x{}",
+                reason, extent.width, extent.height
+            ),
+            JournalEvent::Acquire(result) => write!(f, "acquire: {}", result),
+            JournalEvent::Present(result) => write!(f, "present: {}", result),
+            JournalEvent::Validation { severity, message } => {
+                write!(f, "validation {:?}: {}", severity, message.as_str())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct JournalEntry {
+    pub frame: u64,
+    /// Since the journal was created.
+    pub time: Duration,
+    pub event: JournalEvent,
+}
+
+impl fmt::Display for JournalEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[frame {} +{:.3}s] {}",
+            self.frame,
+            self.time.as_secs_f64(),
+            self.event
+        )
+    }
+}
+
+struct Ring {
+    entries: [Option<JournalEntry>; JOURNAL_CAPACITY],
+    next: usize,
+}
+
+/// The last `JOURNAL_CAPACITY` renderer events, tagged with the frame they
+/// happened in. Shared with the debug callback and the panic hook, so it
+/// records through `&self`.
+pub struct Journal {
+    start: Instant,
+    frame: AtomicU64,
+    ring: Mutex<Ring>,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            frame: AtomicU64::new(0),
+            ring: Mutex::new(Ring {
+                entries: [None; JOURNAL_CAPACITY],
+                next: 0,
+            }),
+        }
+    }
+}
+
+impl Journal {
+    /// Frame that later events are tagged with.
+    pub fn set_frame(&self, frame: u64) {
+        self.frame.store(frame, Ordering::Relaxed);
+    }
+
+    pub fn record(&self, event: JournalEvent) {
+        let entry = JournalEntry {
+            frame: self.frame.load(Ordering::Relaxed),
+            time: self.start.elapsed(),
+            event,
+        };
+        let mut ring = self.ring.lock().unwrap_or_else(|e| e.into_inner());
+        let next = ring.next;
+        ring.entries[next] = Some(entry);
+        ring.next = (next + 1) % JOURNAL_CAPACITY;
+    }
+
+    /// Oldest first.
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        let ring = self.ring.lock().unwrap_or_else(|e| e.into_inner());
+        let (newer, older) = ring.entries.split_at(ring.next);
+        older.iter().chain(newer).flatten().copied().collect()
+    }
+
+    pub fn dump(&self) -> String {
+        let mut dump = String::from("recent events:\n");
+        let entries = self.entries();
+        if entries.is_empty() {
+            dump += "  (none)\n";
+        }
+        for entry in entries {
+            dump += &format!("  {}\n", entry);
+        }
+        dump
+    }
+}
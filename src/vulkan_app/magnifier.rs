@@ -0,0 +1,87 @@
+use ash::vk;
+use cgmath::{Matrix4, Vector2, Vector3};
+
+use super::{MAGNIFIER_BORDER_PIXELS, MAGNIFIER_MARGIN_PIXELS, MAGNIFIER_SIZE_PIXELS};
+
+/// Narrows `proj` by `zoom` around `center` and moves that region to
+/// `target`, both in normalized device coordinates: an off-center frustum,
+/// applied after projecting so it works for any projection. A point at
+/// `center` lands on `target`, and distances around it grow by `zoom`.
+pub fn projection_off_center(
+    proj: Matrix4<f32>,
+    center: Vector2<f32>,
+    zoom: f32,
+    target: Vector2<f32>,
+) -> Matrix4<f32> {
+    // In clip space the translation scales with w, so it shifts NDC by
+    // exactly `shift` whatever the depth.
+    let shift = target - center * zoom;
+    Matrix4::from_translation(Vector3::new(shift.x, shift.y, 0.0))
+        * Matrix4::from_nonuniform_scale(zoom, zoom, 1.0)
+        * proj
+}
+
+/// The corner inset a held magnifier draws into: its border and the
+/// area inside it, in pixels.
+#[derive(Clone, Copy, Debug)]
+pub struct Inset {
+    pub border: vk::Rect2D,
+    pub inner: vk::Rect2D,
+}
+
+impl Inset {
+    /// Top-right of `area`, where the scene is drawn, or `None` if that is
+    /// too small to fit it.
+    pub fn place(area: vk::Rect2D) -> Option<Self> {
+        let extent = area.extent;
+        let outer = MAGNIFIER_SIZE_PIXELS + 2 * MAGNIFIER_BORDER_PIXELS;
+        if extent.width < outer + 2 * MAGNIFIER_MARGIN_PIXELS
+            || extent.height < outer + 2 * MAGNIFIER_MARGIN_PIXELS
+        {
+            return None;
+        }
+        let x = area.offset.x as u32 + extent.width - MAGNIFIER_MARGIN_PIXELS - outer;
+        let y = area.offset.y as u32 + MAGNIFIER_MARGIN_PIXELS;
+        let rect = |x: u32, y: u32, size: u32| vk::Rect2D {
+            offset: vk::Offset2D {
+                x: x as i32,
+                y: y as i32,
+            },
+            extent: vk::Extent2D {
+                width: size,
+                height: size,
+            },
+        };
+        Some(Self {
+            border: rect(x, y, outer),
+            inner: rect(
+                x + MAGNIFIER_BORDER_PIXELS,
+                y + MAGNIFIER_BORDER_PIXELS,
+                MAGNIFIER_SIZE_PIXELS,
+            ),
+        })
+    }
+
+    /// `proj`, for a viewport over `area`, redirected so the area around
+    /// `cursor` (pixels, y down) fills the inset, `zoom` times larger.
+    pub fn projection(
+        &self,
+        proj: Matrix4<f32>,
+        area: vk::Rect2D,
+        cursor: (f32, f32),
+        zoom: f32,
+    ) -> Matrix4<f32> {
+        let ndc = |x: f32, y: f32| {
+            Vector2::new(
+                2.0 * (x - area.offset.x as f32) / area.extent.width as f32 - 1.0,
+                1.0 - 2.0 * (y - area.offset.y as f32) / area.extent.height as f32,
+            )
+        };
+        let half = MAGNIFIER_SIZE_PIXELS as f32 / 2.0;
+        let target = ndc(
+            self.inner.offset.x as f32 + half,
+            self.inner.offset.y as f32 + half,
+        );
+        projection_off_center(proj, ndc(cursor.0, cursor.1), zoom, target)
+    }
+}
@@ -0,0 +1,76 @@
+use ash::vk;
+use std::ffi::c_void;
+
+use super::allocator::{Allocation, Allocator};
+
+/// A buffer whose memory stays mapped from creation until `destroy`.
+/// Uniform memory is always HOST_COHERENT, so writes are visible to the
+/// device without a flush; memory that isn't would need the written range
+/// flushed with `flush_mapped_memory_ranges` at the end of `write`.
+pub struct MappedBuffer {
+    pub buffer: vk::Buffer,
+    pub allocation: Allocation,
+    ptr: *mut c_void,
+    size: vk::DeviceSize,
+}
+
+impl MappedBuffer {
+    /// `allocation` must be host visible; its block is already mapped.
+    pub fn new(buffer: vk::Buffer, allocation: Allocation, size: vk::DeviceSize) -> Self {
+        Self {
+            buffer,
+            allocation,
+            ptr: allocation.mapped_ptr(),
+            size,
+        }
+    }
+
+    /// Copies `block` to `offset` bytes into the buffer.
+    pub fn write<T: Copy>(&self, offset: vk::DeviceSize, block: &T) {
+        let len = std::mem::size_of::<T>();
+        assert!(
+            offset + len as vk::DeviceSize <= self.size,
+            "write of {} bytes at {} overruns a {} byte buffer",
+            len,
+            offset,
+            self.size
+        );
+        // Copied as bytes: the mapping is only as aligned as `offset`.
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                block as *const T as *const u8,
+                (self.ptr as *mut u8).add(offset as usize),
+                len,
+            );
+        }
+    }
+
+    /// Copies `data` to `offset` bytes into the buffer.
+    pub fn write_slice<T: Copy>(&self, offset: vk::DeviceSize, data: &[T]) {
+        let len = std::mem::size_of_val(data);
+        assert!(
+            offset + len as vk::DeviceSize <= self.size,
+            "write of {} bytes at {} overruns a {} byte buffer",
+            len,
+            offset,
+            self.size
+        );
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr() as *const u8,
+                (self.ptr as *mut u8).add(offset as usize),
+                len,
+            );
+        }
+    }
+
+    pub fn size(&self) -> vk::DeviceSize {
+        self.size
+    }
+
+    /// Destroys the buffer, then frees its memory.
+    pub fn destroy(&self, device: &ash::Device, allocator: &Allocator) {
+        unsafe { device.destroy_buffer(self.buffer, None) };
+        allocator.free(self.allocation);
+    }
+}
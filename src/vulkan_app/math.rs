@@ -0,0 +1,60 @@
+//! Types for the CPU-side bulk transform path. cgmath by default; with the
+//! `glam` feature the same code runs on glam's SIMD types. The rest of the
+//! renderer stays on cgmath and converts through `Columns` at the boundary.
+
+#[cfg(not(feature = "glam"))]
+mod backend {
+    pub const NAME: &str = "cgmath";
+
+    pub type Mat4 = cgmath::Matrix4<f32>;
+    pub type Vec3 = cgmath::Vector3<f32>;
+
+    pub fn axis_angle(axis: Vec3, radians: f32) -> Mat4 {
+        Mat4::from_axis_angle(axis, cgmath::Rad(radians))
+    }
+}
+
+#[cfg(feature = "glam")]
+mod backend {
+    pub const NAME: &str = "glam";
+
+    pub type Mat4 = glam::Mat4;
+    pub type Vec3 = glam::Vec3;
+
+    pub fn axis_angle(axis: Vec3, radians: f32) -> Mat4 {
+        Mat4::from_axis_angle(axis, radians)
+    }
+}
+
+pub use backend::{axis_angle, Mat4, Vec3, NAME};
+
+/// Column-major conversion, the layout both backends and GLSL's `mat4` share.
+pub trait Columns {
+    fn to_columns(&self) -> [[f32; 4]; 4];
+    fn from_columns(columns: [[f32; 4]; 4]) -> Self;
+}
+
+impl Columns for cgmath::Matrix4<f32> {
+    fn to_columns(&self) -> [[f32; 4]; 4] {
+        (*self).into()
+    }
+
+    fn from_columns(columns: [[f32; 4]; 4]) -> Self {
+        columns.into()
+    }
+}
+
+#[cfg(feature = "glam")]
+impl Columns for glam::Mat4 {
+    fn to_columns(&self) -> [[f32; 4]; 4] {
+        self.to_cols_array_2d()
+    }
+
+    fn from_columns(columns: [[f32; 4]; 4]) -> Self {
+        Self::from_cols_array_2d(&columns)
+    }
+}
+
+// Transforms are copied into storage buffers as `mat4[]` under either backend.
+const _: () = assert!(std::mem::size_of::<Mat4>() == 64);
+const _: () = assert!(std::mem::size_of::<Mat4>() == std::mem::size_of::<[[f32; 4]; 4]>());
@@ -0,0 +1,239 @@
+use ash::vk;
+use log::warn;
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use super::UNIFORM_DEVICE_LOCAL_MIN_HEAP;
+
+/// Mapped, and written without flushing.
+const HOST_MEMORY: vk::MemoryPropertyFlags = vk::MemoryPropertyFlags::from_raw(
+    vk::MemoryPropertyFlags::HOST_VISIBLE.as_raw()
+        | vk::MemoryPropertyFlags::HOST_COHERENT.as_raw(),
+);
+
+/// What an allocation holds. Each class has its own preferred memory
+/// properties, and falls back to the ones it can't do without.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceClass {
+    Vertex,
+    Index,
+    Uniform,
+    /// Storage buffers: animation parameters and instance transforms.
+    Storage,
+    /// Transfer sources and readback targets.
+    Staging,
+    Image,
+}
+
+impl ResourceClass {
+    pub const ALL: [ResourceClass; 6] = [
+        ResourceClass::Vertex,
+        ResourceClass::Index,
+        ResourceClass::Uniform,
+        ResourceClass::Storage,
+        ResourceClass::Staging,
+        ResourceClass::Image,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|class| class.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ResourceClass::Vertex => "vertex",
+            ResourceClass::Index => "index",
+            ResourceClass::Uniform => "uniform",
+            ResourceClass::Storage => "storage",
+            ResourceClass::Staging => "staging",
+            ResourceClass::Image => "image",
+        }
+    }
+
+    /// What every allocation of the class needs. The renderer maps all its
+    /// buffers and writes them directly, so buffers must be host memory.
+    pub fn required(self) -> vk::MemoryPropertyFlags {
+        match self {
+            ResourceClass::Image => vk::MemoryPropertyFlags::empty(),
+            _ => HOST_MEMORY,
+        }
+    }
+}
+
+/// Parses `--memory-type` flag lists such as `device-local+host-visible`.
+pub fn parse_memory_flags(names: &str) -> Option<vk::MemoryPropertyFlags> {
+    names
+        .split('+')
+        .map(|name| match name {
+            "device-local" => Some(vk::MemoryPropertyFlags::DEVICE_LOCAL),
+            "host-visible" => Some(vk::MemoryPropertyFlags::HOST_VISIBLE),
+            "host-coherent" => Some(vk::MemoryPropertyFlags::HOST_COHERENT),
+            "host-cached" => Some(vk::MemoryPropertyFlags::HOST_CACHED),
+            _ => None,
+        })
+        .try_fold(vk::MemoryPropertyFlags::empty(), |flags, flag| {
+            Some(flags | flag?)
+        })
+}
+
+/// Where one class's latest allocation went.
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryPlacement {
+    pub class: ResourceClass,
+    pub preferred: vk::MemoryPropertyFlags,
+    /// Type index, its heap and its properties; `None` until the class's
+    /// first allocation.
+    pub landed: Option<(u32, u32, vk::MemoryPropertyFlags)>,
+}
+
+impl fmt::Display for MemoryPlacement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.class.name())?;
+        match self.landed {
+            Some((type_index, heap_index, flags)) => {
+                write!(
+                    f,
+                    "type {} in heap {} ({:?})",
+                    type_index, heap_index, flags
+                )?;
+                if !flags.contains(self.preferred) {
+                    write!(f, ", fell back from {:?}", self.preferred)?;
+                }
+                Ok(())
+            }
+            None => write!(f, "not allocated, prefers {:?}", self.preferred),
+        }
+    }
+}
+
+/// Picks the memory types for each allocation from its resource class:
+/// those with the class's preferred properties first, then, if none has
+/// room, those with just the properties it requires. Remembers where each
+/// class last landed, for diagnostics.
+pub struct MemoryTypeSelector {
+    properties: vk::PhysicalDeviceMemoryProperties,
+    preferred: [vk::MemoryPropertyFlags; ResourceClass::ALL.len()],
+    landed: [AtomicU32; ResourceClass::ALL.len()],
+}
+
+impl MemoryTypeSelector {
+    /// Defaults to device-local images and host memory buffers. Uniform
+    /// buffers prefer device-local host memory when such a heap holds at
+    /// least UNIFORM_DEVICE_LOCAL_MIN_HEAP bytes. `overrides` replace the
+    /// preference of their class, and must keep what it requires.
+    pub fn new(
+        properties: vk::PhysicalDeviceMemoryProperties,
+        overrides: &[(ResourceClass, vk::MemoryPropertyFlags)],
+    ) -> Result<Self, String> {
+        let mut preferred = ResourceClass::ALL.map(|class| match class {
+            ResourceClass::Image => vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            _ => class.required(),
+        });
+        if Self::largest_heap(
+            &properties,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL | HOST_MEMORY,
+        ) >= UNIFORM_DEVICE_LOCAL_MIN_HEAP
+        {
+            preferred[ResourceClass::Uniform as usize] |= vk::MemoryPropertyFlags::DEVICE_LOCAL;
+        }
+        for &(class, flags) in overrides {
+            if !flags.contains(class.required()) {
+                return Err(format!(
+                    "{} memory must be {:?}, but the override asks for {:?}",
+                    class.name(),
+                    class.required(),
+                    flags
+                ));
+            }
+            preferred[class as usize] = flags;
+        }
+        Ok(Self {
+            properties,
+            preferred,
+            landed: std::array::from_fn(|_| AtomicU32::new(u32::MAX)),
+        })
+    }
+
+    /// The types an allocation of `class` may use, best first, out of
+    /// `type_bits` from `VkMemoryRequirements`: those with the class's
+    /// preferred properties, then those with only the ones it requires.
+    /// Empty if no type has even those.
+    pub fn candidates(&self, class: ResourceClass, type_bits: u32) -> Vec<u32> {
+        let preferred = self.preferred[class as usize];
+        let mut types: Vec<u32> = self.matching(type_bits, preferred).collect();
+        types.extend(
+            self.matching(type_bits, class.required())
+                .filter(|&i| !self.flags(i).contains(preferred)),
+        );
+        types
+    }
+
+    /// Notes that an allocation of `class` landed in `type_index`, and
+    /// warns when that is a fallback from its preferred properties, once
+    /// per change of type.
+    pub fn record(&self, class: ResourceClass, type_index: u32) {
+        let previous = self.landed[class as usize].swap(type_index, Ordering::Relaxed);
+        let preferred = self.preferred[class as usize];
+        let flags = self.flags(type_index);
+        if previous != type_index && !flags.contains(preferred) {
+            warn!(
+                "{} memory fell back to type {} ({:?}) from {:?}",
+                class.name(),
+                type_index,
+                flags,
+                preferred
+            );
+        }
+    }
+
+    pub fn preferred(&self, class: ResourceClass) -> vk::MemoryPropertyFlags {
+        self.preferred[class as usize]
+    }
+
+    pub fn placements(&self) -> Vec<MemoryPlacement> {
+        ResourceClass::ALL
+            .into_iter()
+            .map(|class| {
+                let type_index = self.landed[class as usize].load(Ordering::Relaxed);
+                MemoryPlacement {
+                    class,
+                    preferred: self.preferred[class as usize],
+                    landed: (type_index != u32::MAX).then(|| {
+                        let memory_type = self.properties.memory_types[type_index as usize];
+                        (
+                            type_index,
+                            memory_type.heap_index,
+                            memory_type.property_flags,
+                        )
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    fn matching(
+        &self,
+        type_bits: u32,
+        flags: vk::MemoryPropertyFlags,
+    ) -> impl Iterator<Item = u32> + '_ {
+        (0..self.properties.memory_type_count)
+            .filter(move |&i| type_bits & (1 << i) != 0 && self.flags(i).contains(flags))
+    }
+
+    fn flags(&self, type_index: u32) -> vk::MemoryPropertyFlags {
+        self.properties.memory_types[type_index as usize].property_flags
+    }
+
+    /// Size of the largest heap with a type that has `flags`, or 0.
+    fn largest_heap(
+        properties: &vk::PhysicalDeviceMemoryProperties,
+        flags: vk::MemoryPropertyFlags,
+    ) -> vk::DeviceSize {
+        properties.memory_types[..properties.memory_type_count as usize]
+            .iter()
+            .filter(|memory_type| memory_type.property_flags.contains(flags))
+            .map(|memory_type| properties.memory_heaps[memory_type.heap_index as usize].size)
+            .max()
+            .unwrap_or(0)
+    }
+}
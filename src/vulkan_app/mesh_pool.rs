@@ -0,0 +1,300 @@
+use ash::vk;
+use std::ops::Range;
+
+use super::allocator::{Allocation, Allocator};
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::vertex::Vertex;
+
+/// First-fit free-list allocator over `[0, capacity)`, in elements. Free
+/// ranges are kept sorted and coalesced with their neighbours on release.
+pub struct RangeAllocator {
+    capacity: u32,
+    free: Vec<Range<u32>>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FragmentationStats {
+    pub capacity: u32,
+    pub free: u32,
+    pub largest_free: u32,
+    pub free_ranges: usize,
+}
+
+impl FragmentationStats {
+    /// Share of free space outside the largest free range: 0 when all free
+    /// space is contiguous, approaching 1 as it splinters.
+    pub fn fragmentation(&self) -> f32 {
+        if self.free == 0 {
+            0.0
+        } else {
+            1.0 - self.largest_free as f32 / self.free as f32
+        }
+    }
+}
+
+impl RangeAllocator {
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            capacity,
+            free: vec![0..capacity],
+        }
+    }
+
+    pub fn allocate(&mut self, len: u32) -> Option<Range<u32>> {
+        self.allocate_aligned(len, 1)
+    }
+
+    /// `len` elements starting at a multiple of `alignment`. The elements
+    /// skipped to align them stay free.
+    pub fn allocate_aligned(&mut self, len: u32, alignment: u32) -> Option<Range<u32>> {
+        let (index, start) = self.free.iter().enumerate().find_map(|(index, range)| {
+            let start = range.start.next_multiple_of(alignment);
+            (start + len <= range.end).then_some((index, start))
+        })?;
+        let range = self.free[index].clone();
+        let allocated = start..start + len;
+        let before = range.start..allocated.start;
+        let after = allocated.end..range.end;
+        self.free.splice(
+            index..=index,
+            [before, after]
+                .into_iter()
+                .filter(|range| !range.is_empty()),
+        );
+        Some(allocated)
+    }
+
+    pub fn release(&mut self, range: Range<u32>) {
+        if range.is_empty() {
+            return;
+        }
+        let index = self.free.partition_point(|free| free.start < range.start);
+        self.free.insert(index, range);
+        if index + 1 < self.free.len() && self.free[index].end == self.free[index + 1].start {
+            self.free[index].end = self.free.remove(index + 1).end;
+        }
+        if index > 0 && self.free[index - 1].end == self.free[index].start {
+            self.free[index - 1].end = self.free.remove(index).end;
+        }
+    }
+
+    pub fn stats(&self) -> FragmentationStats {
+        FragmentationStats {
+            capacity: self.capacity,
+            free: self.free.iter().map(|range| range.end - range.start).sum(),
+            largest_free: self
+                .free
+                .iter()
+                .map(|range| range.end - range.start)
+                .max()
+                .unwrap_or(0),
+            free_ranges: self.free.len(),
+        }
+    }
+}
+
+/// The index type for a mesh of `vertex_count` vertices: 16-bit while every
+/// index fits below 0xFFFF, which stays free as the primitive restart
+/// index, and 32-bit from there.
+pub fn index_type_for(vertex_count: usize) -> vk::IndexType {
+    if vertex_count <= u16::MAX as usize {
+        vk::IndexType::UINT16
+    } else {
+        vk::IndexType::UINT32
+    }
+}
+
+/// A mesh's indices, at the narrowest type `index_type_for` allows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IndexData {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl IndexData {
+    /// `indices` into `vertex_count` vertices, narrowed to 16 bits if they
+    /// fit.
+    pub fn new(indices: Vec<u32>, vertex_count: usize) -> Self {
+        match index_type_for(vertex_count) {
+            vk::IndexType::UINT16 => {
+                IndexData::U16(indices.into_iter().map(|index| index as u16).collect())
+            }
+            _ => IndexData::U32(indices),
+        }
+    }
+
+    pub fn index_type(&self) -> vk::IndexType {
+        match self {
+            IndexData::U16(_) => vk::IndexType::UINT16,
+            IndexData::U32(_) => vk::IndexType::UINT32,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            IndexData::U16(indices) => indices.len(),
+            IndexData::U32(indices) => indices.len(),
+        }
+    }
+}
+
+/// The pool's 16-bit index slots one index of `index_type` takes.
+fn index_slots(index_type: vk::IndexType) -> u32 {
+    match index_type {
+        vk::IndexType::UINT32 => 2,
+        _ => 1,
+    }
+}
+
+/// Where a mesh lives in the pool; the draw parameters of `cmd_draw_indexed`,
+/// with the index buffer bound at offset 0 as `index_type`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MeshAllocation {
+    pub first_vertex: u32,
+    pub vertex_count: u32,
+    /// In indices of `index_type`.
+    pub first_index: u32,
+    pub index_count: u32,
+    pub index_type: vk::IndexType,
+}
+
+impl MeshAllocation {
+    pub fn vertex_offset(&self) -> i32 {
+        self.first_vertex as i32
+    }
+
+    /// The pool's index slots the mesh takes.
+    fn index_slots(&self) -> Range<u32> {
+        let slots = index_slots(self.index_type);
+        self.first_index * slots..(self.first_index + self.index_count) * slots
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MeshPoolStats {
+    pub meshes: usize,
+    pub vertices: FragmentationStats,
+    pub indices: FragmentationStats,
+}
+
+/// One host-visible vertex buffer and one index buffer shared by every
+/// `Vertex` mesh, so a single bind serves them all, or one per index type.
+/// Meshes are sub-allocated at element offsets; indices stay relative to
+/// the mesh's first vertex. The index buffer is allocated in 16-bit slots,
+/// of which a 32-bit index takes an aligned pair.
+pub struct MeshPool {
+    pub vertex_buffer: vk::Buffer,
+    pub vertex_allocation: Allocation,
+    pub index_buffer: vk::Buffer,
+    pub index_allocation: Allocation,
+    pub vertex_ranges: RangeAllocator,
+    pub index_ranges: RangeAllocator,
+    pub meshes: usize,
+    /// Bumped by every upload and removal, so copies of the pool on other
+    /// devices know when to copy it again.
+    pub revision: u64,
+}
+
+impl MeshPool {
+    /// Copies a mesh into free ranges of the pool, or returns `None` if
+    /// either buffer has no range large enough.
+    pub fn upload(&mut self, vertices: &[Vertex], indices: &IndexData) -> Option<MeshAllocation> {
+        let index_type = indices.index_type();
+        let slots = index_slots(index_type);
+        let vertex_range = self.vertex_ranges.allocate(vertices.len() as u32)?;
+        let Some(index_range) = self
+            .index_ranges
+            .allocate_aligned(indices.len() as u32 * slots, slots)
+        else {
+            self.vertex_ranges.release(vertex_range);
+            return None;
+        };
+        let first_index = index_range.start / slots;
+        unsafe {
+            write_range(self.vertex_allocation, vertex_range.start, vertices);
+            match indices {
+                IndexData::U16(indices) => write_range(self.index_allocation, first_index, indices),
+                IndexData::U32(indices) => write_range(self.index_allocation, first_index, indices),
+            }
+        }
+        self.meshes += 1;
+        self.revision += 1;
+        Some(MeshAllocation {
+            first_vertex: vertex_range.start,
+            vertex_count: vertices.len() as u32,
+            first_index,
+            index_count: indices.len() as u32,
+            index_type,
+        })
+    }
+
+    /// Returns a mesh's ranges to the free lists. The caller must make sure
+    /// no frame still in flight draws it.
+    pub fn remove(&mut self, mesh: MeshAllocation) {
+        self.vertex_ranges
+            .release(mesh.first_vertex..mesh.first_vertex + mesh.vertex_count);
+        self.index_ranges.release(mesh.index_slots());
+        self.meshes -= 1;
+        self.revision += 1;
+    }
+
+    /// The whole vertex and index buffers, as bytes.
+    pub fn contents(&self) -> (&[u8], &[u8]) {
+        let vertex_len = self.vertex_ranges.capacity as usize * std::mem::size_of::<Vertex>();
+        let index_len = self.index_ranges.capacity as usize * std::mem::size_of::<u16>();
+        unsafe {
+            (
+                std::slice::from_raw_parts(
+                    self.vertex_allocation.mapped_ptr() as *const u8,
+                    vertex_len,
+                ),
+                std::slice::from_raw_parts(
+                    self.index_allocation.mapped_ptr() as *const u8,
+                    index_len,
+                ),
+            )
+        }
+    }
+
+    /// The pool's first `count` vertices, read back from the buffer.
+    pub fn read_vertices(&self, count: u32) -> Vec<Vertex> {
+        let mapped = self.vertex_allocation.mapped_ptr() as *const Vertex;
+        unsafe { std::slice::from_raw_parts(mapped, count as usize) }.to_vec()
+    }
+
+    pub fn stats(&self) -> MeshPoolStats {
+        MeshPoolStats {
+            meshes: self.meshes,
+            vertices: self.vertex_ranges.stats(),
+            indices: self.index_ranges.stats(),
+        }
+    }
+
+    pub fn destroy(
+        &mut self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        resources: &mut ResourceRegistry,
+    ) {
+        unsafe {
+            resources.unregister(ResourceKind::Buffer, self.index_buffer);
+            device.destroy_buffer(self.index_buffer, None);
+            resources.unregister(ResourceKind::Buffer, self.vertex_buffer);
+            device.destroy_buffer(self.vertex_buffer, None);
+        }
+        allocator.free(self.index_allocation);
+        allocator.free(self.vertex_allocation);
+    }
+}
+
+/// Writes `data` at element `first` of a host-visible buffer holding `T`s.
+unsafe fn write_range<T: Copy>(allocation: Allocation, first: u32, data: &[T]) {
+    let offset = first as usize * std::mem::size_of::<T>();
+    let size = std::mem::size_of_val(data) as vk::DeviceSize;
+    unsafe {
+        let data_ptr = (allocation.mapped_ptr() as *mut u8).add(offset);
+        let mut align =
+            ash::util::Align::new(data_ptr as *mut _, std::mem::align_of::<T>() as _, size);
+        align.copy_from_slice(data);
+    }
+}
@@ -0,0 +1,265 @@
+const CACHE_SIZE: usize = 32;
+const CACHE_DECAY_POWER: f32 = 1.5;
+const LAST_TRI_SCORE: f32 = 0.75;
+const VALENCE_BOOST_SCALE: f32 = 2.0;
+const VALENCE_BOOST_POWER: f32 = 0.5;
+
+const ACMR_CACHE_SIZE: usize = 16;
+const QUANTIZE_MAX: f32 = u16::MAX as f32;
+
+pub trait MeshIndex: Copy {
+    fn to_usize(self) -> usize;
+    fn from_usize(value: usize) -> Self;
+}
+
+impl MeshIndex for u16 {
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+
+    fn from_usize(value: usize) -> Self {
+        value as u16
+    }
+}
+
+impl MeshIndex for u32 {
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+
+    fn from_usize(value: usize) -> Self {
+        value as u32
+    }
+}
+
+pub trait HasPosition {
+    fn position_mut(&mut self) -> &mut [f32; 3];
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MeshOptStats {
+    pub acmr_before: f32,
+    pub acmr_after: f32,
+    pub quantization_error: Option<f32>,
+}
+
+pub fn optimize_mesh<V: Copy + HasPosition, I: MeshIndex>(
+    vertices: &mut Vec<V>,
+    indices: &mut [I],
+    quantize: bool,
+) -> MeshOptStats {
+    let acmr_before = estimate_acmr(indices, vertices.len());
+    optimize_vertex_cache(indices, vertices.len());
+    optimize_vertex_fetch(vertices, indices);
+    let acmr_after = estimate_acmr(indices, vertices.len());
+    let quantization_error = if quantize {
+        Some(quantize_positions(vertices))
+    } else {
+        None
+    };
+
+    MeshOptStats {
+        acmr_before,
+        acmr_after,
+        quantization_error,
+    }
+}
+
+/// Average cache miss ratio: transformed vertices per triangle, simulated with a
+/// FIFO post-transform cache. 3.0 is the worst case, 0.5 the theoretical best.
+pub fn estimate_acmr<I: MeshIndex>(indices: &[I], vertex_count: usize) -> f32 {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return 0.0;
+    }
+
+    let mut cache_timestamps = vec![0usize; vertex_count];
+    let mut timestamp = ACMR_CACHE_SIZE + 1;
+    let mut misses = 0;
+
+    for &index in indices {
+        let vertex = index.to_usize();
+        if timestamp - cache_timestamps[vertex] > ACMR_CACHE_SIZE {
+            cache_timestamps[vertex] = timestamp;
+            timestamp += 1;
+            misses += 1;
+        }
+    }
+
+    misses as f32 / triangle_count as f32
+}
+
+/// Reorders triangles for post-transform cache locality using Tom Forsyth's
+/// linear-speed vertex cache optimisation.
+pub fn optimize_vertex_cache<I: MeshIndex>(indices: &mut [I], vertex_count: usize) {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return;
+    }
+
+    let mut vertex_triangles: Vec<Vec<usize>> = vec![Vec::new(); vertex_count];
+    for triangle in 0..triangle_count {
+        for corner in 0..3 {
+            vertex_triangles[indices[triangle * 3 + corner].to_usize()].push(triangle);
+        }
+    }
+
+    let mut remaining_valence: Vec<usize> = vertex_triangles.iter().map(Vec::len).collect();
+    let mut vertex_scores: Vec<f32> = (0..vertex_count)
+        .map(|v| vertex_score(None, remaining_valence[v]))
+        .collect();
+    let mut triangle_emitted = vec![false; triangle_count];
+    let mut triangle_scores: Vec<f32> = (0..triangle_count)
+        .map(|t| {
+            (0..3)
+                .map(|c| vertex_scores[indices[t * 3 + c].to_usize()])
+                .sum()
+        })
+        .collect();
+
+    let mut output = Vec::with_capacity(indices.len());
+    let mut cache: Vec<usize> = Vec::with_capacity(CACHE_SIZE + 3);
+    let mut best_triangle = best_remaining_triangle(&triangle_scores, &triangle_emitted);
+
+    while let Some(triangle) = best_triangle {
+        triangle_emitted[triangle] = true;
+        let corners = [
+            indices[triangle * 3].to_usize(),
+            indices[triangle * 3 + 1].to_usize(),
+            indices[triangle * 3 + 2].to_usize(),
+        ];
+
+        for &vertex in corners.iter() {
+            output.push(I::from_usize(vertex));
+            remaining_valence[vertex] -= 1;
+            let slot = vertex_triangles[vertex].iter().position(|&t| t == triangle);
+            if let Some(slot) = slot {
+                vertex_triangles[vertex].swap_remove(slot);
+            }
+        }
+
+        // Emitted vertices move to the front of the LRU cache, in triangle order.
+        for &vertex in corners.iter().rev() {
+            if let Some(pos) = cache.iter().position(|&v| v == vertex) {
+                cache.remove(pos);
+            }
+            cache.insert(0, vertex);
+        }
+
+        let evicted: Vec<usize> = if cache.len() > CACHE_SIZE {
+            cache.split_off(CACHE_SIZE)
+        } else {
+            Vec::new()
+        };
+        for &vertex in evicted.iter() {
+            vertex_scores[vertex] = vertex_score(None, remaining_valence[vertex]);
+        }
+        for (pos, &vertex) in cache.iter().enumerate() {
+            vertex_scores[vertex] = vertex_score(Some(pos), remaining_valence[vertex]);
+        }
+
+        best_triangle = None;
+        let mut best_score = f32::MIN;
+        for &vertex in cache.iter().chain(evicted.iter()) {
+            for &candidate in vertex_triangles[vertex].iter() {
+                let score = (0..3)
+                    .map(|c| vertex_scores[indices[candidate * 3 + c].to_usize()])
+                    .sum();
+                triangle_scores[candidate] = score;
+                if score > best_score {
+                    best_score = score;
+                    best_triangle = Some(candidate);
+                }
+            }
+        }
+
+        if best_triangle.is_none() {
+            best_triangle = best_remaining_triangle(&triangle_scores, &triangle_emitted);
+        }
+    }
+
+    indices[..output.len()].copy_from_slice(&output);
+}
+
+/// Reorders vertices into first-use order so vertex fetches walk memory linearly.
+/// Unreferenced vertices are kept at the end so the vertex count is unchanged.
+pub fn optimize_vertex_fetch<V: Copy, I: MeshIndex>(vertices: &mut Vec<V>, indices: &mut [I]) {
+    let mut remap: Vec<Option<usize>> = vec![None; vertices.len()];
+    let mut reordered = Vec::with_capacity(vertices.len());
+
+    for index in indices.iter_mut() {
+        let old = index.to_usize();
+        let new = *remap[old].get_or_insert_with(|| {
+            reordered.push(vertices[old]);
+            reordered.len() - 1
+        });
+        *index = I::from_usize(new);
+    }
+
+    for (old, new) in remap.iter().enumerate() {
+        if new.is_none() {
+            reordered.push(vertices[old]);
+        }
+    }
+
+    *vertices = reordered;
+}
+
+/// Snaps positions onto a 16-bit grid spanning the mesh's bounding box, matching
+/// what a UNORM16 position attribute dequantised by the mesh bounds would produce.
+/// Returns the largest per-component error introduced.
+pub fn quantize_positions<V: HasPosition>(vertices: &mut [V]) -> f32 {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for vertex in vertices.iter_mut() {
+        let pos = vertex.position_mut();
+        for axis in 0..3 {
+            min[axis] = min[axis].min(pos[axis]);
+            max[axis] = max[axis].max(pos[axis]);
+        }
+    }
+
+    let mut max_error: f32 = 0.0;
+    for vertex in vertices.iter_mut() {
+        let pos = vertex.position_mut();
+        for axis in 0..3 {
+            let extent = max[axis] - min[axis];
+            if extent <= 0.0 {
+                continue;
+            }
+            let quantized = ((pos[axis] - min[axis]) / extent * QUANTIZE_MAX).round();
+            let restored = min[axis] + quantized / QUANTIZE_MAX * extent;
+            max_error = max_error.max((restored - pos[axis]).abs());
+            pos[axis] = restored;
+        }
+    }
+
+    max_error
+}
+
+fn vertex_score(cache_position: Option<usize>, remaining_valence: usize) -> f32 {
+    if remaining_valence == 0 {
+        return -1.0;
+    }
+
+    let cache_score = match cache_position {
+        None => 0.0,
+        Some(pos) if pos < 3 => LAST_TRI_SCORE,
+        Some(pos) => {
+            let scaler = 1.0 / (CACHE_SIZE - 3) as f32;
+            (1.0 - (pos - 3) as f32 * scaler).powf(CACHE_DECAY_POWER)
+        }
+    };
+    let valence_boost = VALENCE_BOOST_SCALE * (remaining_valence as f32).powf(-VALENCE_BOOST_POWER);
+
+    cache_score + valence_boost
+}
+
+fn best_remaining_triangle(scores: &[f32], emitted: &[bool]) -> Option<usize> {
+    scores
+        .iter()
+        .enumerate()
+        .filter(|(t, _)| !emitted[*t])
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(t, _)| t)
+}
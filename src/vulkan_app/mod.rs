@@ -1,11 +1,389 @@
 pub const WIDTH: u32 = 800;
 pub const HEIGHT: u32 = 600;
+/// The ratio `--letterbox` and the A key keep without one given: the
+/// default window's.
+pub const LETTERBOX_ASPECT: f32 = WIDTH as f32 / HEIGHT as f32;
 
+pub const MAX_FRAMES_IN_FLIGHT: usize = 2;
+
+pub const OPTIMIZE_MESHES: bool = true;
+pub const QUANTIZE_MESH_POSITIONS: bool = false;
+
+/// Capacity of the shared mesh buffers, in vertices and 16-bit indices; a
+/// 32-bit index takes two.
+pub const MESH_POOL_VERTICES: u32 = 262144;
+pub const MESH_POOL_INDICES: u32 = 1048576;
+
+/// How far the breathing demo scales the cubes either way, and how long a
+/// breath takes.
+pub const BREATHING_AMPLITUDE: f32 = 0.15;
+pub const BREATHING_PERIOD_SECONDS: f32 = 2.0;
+
+pub const WATCHDOG_TIMEOUT_MS: u64 = 2000;
+pub const WATCHDOG_LOG_PATH: &str = "gpu_watchdog.log";
+/// Exit code when a frame fails with `VulkanError::GpuHang`.
+pub const GPU_HANG_EXIT_CODE: i32 = 3;
+/// Exit code when the renderer can't start or a frame fails with any other
+/// `VulkanError`.
+pub const VULKAN_ERROR_EXIT_CODE: i32 = 5;
+/// Exit code for malformed or conflicting command-line options.
+pub const USAGE_EXIT_CODE: i32 = 2;
+
+pub const PANIC_ON_LEAK: bool = false;
+
+/// Enable VK_LAYER_KHRONOS_validation and the debug messenger, if built
+/// with the `debug-utils` feature. Off in release builds.
+pub const ENABLE_VALIDATION: bool = cfg!(debug_assertions);
+
+pub const GPU_ANIMATION: bool = true;
+pub const ANIMATED_OBJECT_COUNT: u32 = 1;
+pub const ANIMATION_TOLERANCE: f32 = 1e-4;
+/// Without `--sun`, a white light circles the cube once per
+/// LIGHT_ORBIT_SECONDS of animation time, LIGHT_ELEVATION_DEGREES above the
+/// horizon.
+pub const LIGHT_ORBIT_SECONDS: f32 = 20.0;
+pub const LIGHT_ELEVATION_DEGREES: f32 = 40.0;
+pub const LIGHT_AMBIENT: f32 = 0.2;
+/// The w of each light's colour in the per-frame block, the sun's too.
+pub const SPECULAR_STRENGTH: f32 = 0.5;
+/// Uniform buffers go in DEVICE_LOCAL | HOST_VISIBLE memory by default only
+/// if its heap is at least this big. Without resizable BAR that heap is a
+/// 256 MiB window the driver also allocates from, so it is left alone.
+pub const UNIFORM_DEVICE_LOCAL_MIN_HEAP: u64 = 512 << 20;
+/// Device memory is allocated in blocks of this size, or an eighth of a
+/// smaller heap, and buffers and images are suballocated from them.
+pub const MEMORY_BLOCK_SIZE: u64 = 64 << 20;
+/// Out of memory for the depth and colour attachments after a resize, the
+/// render extent is halved until they fit, but not below this many pixels
+/// a side.
+pub const MIN_RENDER_EXTENT: u32 = 64;
+/// Generated scenes keep their per-instance buffers within this fraction
+/// (1/n) of the largest host-visible heap.
+pub const INSTANCE_MEMORY_FRACTION: u64 = 8;
+
+/// Side of the magnifier inset's zoomed area, and the border and window
+/// margin around it.
+pub const MAGNIFIER_SIZE_PIXELS: u32 = 200;
+pub const MAGNIFIER_BORDER_PIXELS: u32 = 2;
+pub const MAGNIFIER_MARGIN_PIXELS: u32 = 16;
+pub const MAGNIFIER_ZOOM: f32 = 4.0;
+
+/// How much further round the scene, about its vertical axis, each window
+/// Ctrl+N opens looks from than the one before.
+pub const EXTRA_WINDOW_YAW_DEGREES: f32 = 90.0;
+
+#[cfg(feature = "tools")]
+pub const GIZMO_SIZE_PIXELS: f32 = 100.0;
+
+/// Stick and trigger travel, as a fraction of the full range, that reads as
+/// rest, so an idle controller doesn't drift the cube.
+#[cfg(feature = "gamepad")]
+pub const GAMEPAD_DEAD_ZONE: f32 = 0.15;
+/// How fast the left stick turns the cube at full tilt, in radians per second.
+#[cfg(feature = "gamepad")]
+pub const GAMEPAD_TURN_RATE: f32 = 3.0;
+/// Camera zoom with the right trigger pulled all the way.
+#[cfg(feature = "gamepad")]
+pub const GAMEPAD_MAX_ZOOM: f32 = 2.5;
+
+/// How far the orbit camera turns per pixel of left-button drag, in radians.
+pub const ORBIT_CAMERA_RADIANS_PER_PIXEL: f32 = 0.008;
+/// The orbit camera's pitch either side of level, in radians: short of the
+/// poles, where the view's up vector would line up with its direction.
+pub const ORBIT_CAMERA_MAX_PITCH: f32 = 1.5;
+/// How long the orbit camera coasts after a drag, about, in seconds.
+pub const ORBIT_CAMERA_COAST_SECONDS: f32 = 0.5;
+/// The orbit camera's distance shrinks by this factor per scroll step.
+pub const ORBIT_CAMERA_DOLLY_STEP: f32 = 1.15;
+/// The orbit camera's nearest and farthest distance from its target.
+pub const ORBIT_CAMERA_DISTANCE_RANGE: (f32, f32) = (0.5, 8.0);
+
+pub const DEPTH_PREPASS: bool = false;
+
+pub const PREWARM_DEBUG_PIPELINES: bool = true;
+
+/// How often the event loop wakes while idle (animation paused, nothing dirty).
+pub const IDLE_HEARTBEAT_MS: u64 = 1000;
+/// The longest step the orbit camera's coasting takes in one frame, so the
+/// first frame after a long idle doesn't fling it.
+pub const MAX_FRAME_DELTA_SECONDS: f32 = 0.1;
+/// Time between frames while the window is out of focus: about 10 FPS.
+pub const UNFOCUSED_FRAME_INTERVAL_MS: u64 = 100;
+
+/// The scene's render scale, as a percentage of the window's size, moves in
+/// these steps no lower than the minimum.
+pub const RENDER_SCALE_MIN_PERCENT: u32 = 50;
+pub const RENDER_SCALE_STEP_PERCENT: u32 = 10;
+/// Frames `--adaptive-resolution` averages after each change of scale
+/// before deciding on the next.
+pub const RENDER_SCALE_SAMPLES: u32 = 30;
+/// A step up must be predicted to stay this far under the target, so the
+/// scale doesn't go straight back down.
+pub const RENDER_SCALE_HEADROOM: f32 = 0.85;
+/// The scales Ctrl+1 to Ctrl+5 jump to, which go past full scale to
+/// supersample, and the GPU times the HUD compares them by, averaged over
+/// each one's last SCALE_TIME_SAMPLES frames.
+pub const RENDER_SCALE_LADDER: [u32; 5] = [50, 75, 100, 150, 200];
+pub const RENDER_SCALE_MAX_PERCENT: u32 = 200;
+pub const SCALE_TIME_SAMPLES: usize = 60;
+
+/// `--post-process` starts with this much vignette and steps it by
+/// POST_VIGNETTE_STEP, from none to black corners and round again.
+#[cfg(feature = "post-processing")]
+pub const POST_VIGNETTE: f32 = 0.5;
+#[cfg(feature = "post-processing")]
+pub const POST_VIGNETTE_STEP: f32 = 0.25;
+/// Gamma steps, within the range `PostParams::step_gamma` keeps to.
+#[cfg(feature = "post-processing")]
+pub const POST_GAMMA_STEP: f32 = 0.1;
+#[cfg(feature = "post-processing")]
+pub const POST_GAMMA_MIN: f32 = 0.5;
+#[cfg(feature = "post-processing")]
+pub const POST_GAMMA_MAX: f32 = 2.5;
+
+/// Width and height of the frames `--self-test` renders.
+pub const SELF_TEST_SIZE: u32 = 128;
+/// Bits of 64 a `--self-test` frame's hash may differ from its reference
+/// in. Drivers that rasterize edges or filter textures a little differently
+/// flip one or two.
+pub const SELF_TEST_HASH_TOLERANCE: u32 = 6;
+pub const SELF_TEST_FAILURE_EXIT_CODE: i32 = 4;
+
+/// How long shutdown waits for in-flight frames before destroying anyway.
+pub const SHUTDOWN_FENCE_TIMEOUT_MS: u64 = 2000;
+/// Create, render one frame, destroy: repeated this many times by `--shutdown-stress`.
+pub const SHUTDOWN_STRESS_ITERATIONS: u32 = 50;
+/// Window sizes reported one after another by `--resize-storm`, down to a
+/// single pixel and up to 8K.
+pub const RESIZE_STORM_SIZES: [(u32, u32); 10] = [
+    (640, 480),
+    (1024, 768),
+    (320, 200),
+    (1, 1),
+    (1, 600),
+    (800, 1),
+    (7680, 4320),
+    (1280, 720),
+    (800, 800),
+    (800, 600),
+];
+
+/// Frames `--memory-benchmark` times per memory layout, after the warmup
+/// frames it doesn't time.
+pub const MEMORY_BENCHMARK_FRAMES: u32 = 600;
+pub const MEMORY_BENCHMARK_WARMUP_FRAMES: u32 = 60;
+
+pub const SERVE_DEFAULT_FPS: u32 = 30;
+
+/// Picks the GPU like `--gpu`, by index or part of its name, when the flag
+/// isn't given.
+pub const GPU_ENV: &str = "CUBE_GPU";
+
+/// Frames `FrameStats` averages over for the window title's FPS counter.
+pub const FRAME_STATS_SAMPLES: usize = 120;
+/// How often the window title's FPS counter is refreshed.
+pub const FPS_TITLE_INTERVAL_MS: u64 = 1000;
+
+/// Frame times kept for the frame-time graph.
+#[cfg(feature = "overlay")]
+pub const FRAME_GRAPH_SAMPLES: usize = 240;
+/// Vertices each frame's HUD buffer starts with: six per line and six per
+/// character, so about 600 characters. Longer text grows it.
+#[cfg(feature = "overlay")]
+pub const HUD_VERTICES: usize = 4096;
+
+/// Per-draw uniform slots first reserved in each swapchain image's uniform
+/// buffer when draw constants overflow the push constant budget. A frame
+/// with more draws grows them.
+pub const DRAW_UNIFORM_SLOTS: usize = 64;
+
+/// `--orbit-demo`: small cubes, each with its own model matrix, evenly
+/// spaced on a circle round the main cube and orbiting it.
+pub const ORBIT_DEMO_CUBES: usize = 3;
+pub const ORBIT_DEMO_RADIUS: f32 = 1.2;
+pub const ORBIT_DEMO_SCALE: f32 = 0.3;
+/// Radians per second of animation time.
+pub const ORBIT_DEMO_SPEED: f32 = 0.8;
+
+/// How long the newest validation error stays on the banner, and the least
+/// time it stays before a newer one replaces it.
+pub const ERROR_BANNER_MS: u64 = 4000;
+pub const ERROR_BANNER_MIN_MS: u64 = 1000;
+/// Screen pixels per font pixel on the banner.
+pub const ERROR_BANNER_SCALE: u32 = 2;
+
+/// Read at startup from this directory under the platform's config
+/// directory, or from the working directory where there is none, and
+/// watched for changes while running.
+pub const SETTINGS_DIR: &str = "vulkan-cube";
+pub const SETTINGS_FILE: &str = "settings.toml";
+/// Where settings were read from before they moved to SETTINGS_FILE, in
+/// the working directory. Read once if SETTINGS_FILE doesn't exist yet and
+/// copied over to it.
+pub const LEGACY_SETTINGS_FILE: &str = "cube.toml";
+pub const SETTINGS_POLL_MS: u64 = 500;
+/// Changed settings are written back at most this often while running;
+/// shutdown writes the last of them.
+pub const SETTINGS_SAVE_MS: u64 = 1000;
+/// Written into each settings file. Older files have fewer settings, which
+/// keep their defaults; newer ones may have settings this build skips.
+pub const SETTINGS_VERSION: u32 = 3;
+
+/// Events kept by the journal for panic and watchdog reports.
+pub const JOURNAL_CAPACITY: usize = 256;
+
+/// Width and height of generated textures such as the ground grid.
+pub const GROUND_TEXTURE_SIZE: u32 = 512;
+/// Width and height of each face of the generated gradient skybox.
+pub const SKYBOX_SIZE: u32 = 128;
+
+/// Write screenshots as QOI, which encodes much faster than PNG.
+pub const SCREENSHOT_QOI: bool = false;
+/// Where `--headless` writes its frame unless `--output` says otherwise.
+pub const HEADLESS_OUTPUT_PATH: &str = "frame.png";
+
+/// Seconds of animation time for `--clear-color cycle` to go once around
+/// the hue wheel.
+pub const CLEAR_HUE_CYCLE_SECONDS: f32 = 12.0;
+
+/// Draw lists this long are recorded into secondary command buffers on a
+/// rayon thread pool, PARALLEL_RECORD_CHUNK draws to a buffer. Shorter ones
+/// are recorded inline, where the threads would cost more than they save.
+pub const PARALLEL_RECORD_MIN_DRAWS: usize = 256;
+pub const PARALLEL_RECORD_CHUNK: usize = 128;
+/// Cubes `--record-benchmark` draws, one draw each, and the frames it
+/// times after warming up.
+pub const RECORD_BENCHMARK_CUBES: u32 = 4096;
+pub const RECORD_BENCHMARK_FRAMES: u32 = 300;
+
+pub use allocator::{new_block_size, standard_block_size, AllocatorStats, FreeList, HeapUsage};
+pub use animation::AnimationParams;
 pub use app::VulkanApp;
+pub use app_config::{AppConfig, FullscreenMode, VideoModeRequest, USAGE};
+pub use aspect::{bars, projection_aspect, AspectPolicy};
+pub use backend::{BackendPreference, WindowBackend};
+pub use camera::OrbitDrag;
+pub use capabilities::{DeviceCapabilities, DeviceLimits, DynamicRenderingSupport};
+pub use capture::CapturedFrame;
+pub use config::{GpuSelector, RenderMode, RendererConfig, WindowGpus};
+pub use config_resolver::{ConfigResolver, Downgrade, EffectiveConfig};
+pub use debug::{vulkan_debug_callback, ValidationLog};
+pub use diagnostics::{DiagnosticsReport, StartupDiagnostics};
+pub use draw_constants::{DrawConstantPacking, DrawConstants};
+pub use draw_list::DrawStats;
+pub use error::VulkanError;
+pub use frame::{FrameIndex, FrameResources};
+pub use frame_stats::FrameStats;
+#[cfg(feature = "compile-shaders")]
+pub use glsl::{block_offsets, compile as compile_glsl, permutations as shader_permutations};
+pub use icon::window_icon;
+pub use journal::{Journal, JournalEvent, RecreateReason};
+pub use magnifier::{projection_off_center, Inset};
+pub use memory::{MemoryPlacement, MemoryTypeSelector, ResourceClass};
+pub use mesh_pool::{index_type_for, FragmentationStats, IndexData, MeshPoolStats};
+pub use meshopt::{
+    estimate_acmr, optimize_mesh, optimize_vertex_cache, optimize_vertex_fetch, quantize_positions,
+    HasPosition, MeshIndex, MeshOptStats,
+};
+pub use pick::{object_id, object_index, render_pixel, PickPush};
+pub use png::decode_rgba as decode_png;
+#[cfg(feature = "post-processing")]
+pub use post_process::PostParams;
+pub use present_mode::PresentModePreference;
+pub use primitives::{
+    cube_mesh_to_unit, menger_instance_count, menger_level_within, menger_sponge,
+};
+pub use protocol::Command;
+pub use render_scale::{scaled_extent, RenderScaler};
+pub use resources::ResourceKind;
+pub use scale_compare::{right_half_blit, ScaleTimes};
+pub use scene::SceneObject;
+pub use selftest::{hash_distance, perceptual_hash, CanonicalScene, CheckOutcome, SelfTestReport};
+pub use server::{FrameServer, ServeConfig};
+pub use settings::{Settings, SettingsError, SettingsFile, WindowSettings};
+pub use swapchain_support::{
+    choose_surface_format, choose_swap_extent, is_srgb_format, is_unorm_format,
+    PREFERRED_SURFACE_FORMAT,
+};
+pub use texgen::{face_label_uv, Pattern, FACE_LABELS, FACE_LABEL_BLANK_UV};
+pub use ubo::{
+    align_up, draw_slot_offset, per_view_offset, uniform_buffer_size, Light, PerFrameUbo,
+    PerViewUbo, MAX_LIGHTS, PER_FRAME_OFFSET, UNIFORM_BLOCK_ALIGNMENT, VIEW_COUNT,
+};
+pub use vertex::Vertex;
 
+mod allocator;
+mod animation;
 mod app;
+mod app_config;
+mod aspect;
+mod backend;
+mod background;
+mod camera;
+mod capabilities;
+mod capture;
+mod clear_color;
+mod config;
+mod config_resolver;
+mod context;
+mod cube_shaders;
 mod debug;
+mod depth;
+mod diagnostics;
+mod draw_constants;
+mod draw_list;
+mod error;
+mod error_banner;
+mod extra_window;
+mod font;
+mod frame;
+mod frame_stats;
+#[cfg(feature = "compile-shaders")]
+mod glsl;
+#[cfg(feature = "overlay")]
+mod hud;
+mod icon;
+mod interpolation;
+mod journal;
+mod magnifier;
+mod mapped_buffer;
+mod math;
+mod memory;
+mod mesh_pool;
+mod meshopt;
+mod morph;
+mod parallel;
+#[cfg(feature = "overlay")]
+mod perf_graph;
+mod pick;
+mod pipeline_cache;
+mod plasma;
+mod png;
+#[cfg(feature = "post-processing")]
+mod post_process;
+mod present_mode;
+mod primitives;
+mod protocol;
+mod qoi;
 mod queue;
+mod render_context;
+mod render_scale;
+mod rendering;
+mod resources;
+mod scale_compare;
+mod scene;
+mod selftest;
+mod server;
+mod settings;
+mod skybox;
+mod startup;
+mod sun;
+mod swapchain;
 mod swapchain_support;
+mod texgen;
+mod texture;
+#[cfg(feature = "tools")]
+mod tools;
 mod ubo;
 mod vertex;
+mod watchdog;
@@ -0,0 +1,137 @@
+use ash::vk;
+
+use super::allocator::{Allocation, Allocator};
+use super::context::VkContext;
+use super::error::VulkanError;
+use super::frame::{FrameIndex, FrameResources};
+use super::memory::ResourceClass;
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::vertex::Vertex;
+use super::{BREATHING_AMPLITUDE, BREATHING_PERIOD_SECONDS, MAX_FRAMES_IN_FLIGHT};
+
+#[derive(Clone, Copy)]
+struct MorphBuffer {
+    buffer: vk::Buffer,
+    allocation: Allocation,
+    // The `MorphedVertices::generation` copied in.
+    generation: u64,
+}
+
+/// The mesh pool's vertices as `VulkanApp::update_vertices` last rewrote
+/// them, with a vertex buffer per frame in flight to draw them from. A
+/// frame's buffer is only written in `upload`, once its fence has
+/// signalled, so the GPU never reads vertices while they change; the host
+/// writes land before the submit, which makes them visible to the vertex
+/// input stage without a barrier.
+pub struct MorphedVertices {
+    buffers: FrameResources<MorphBuffer, MAX_FRAMES_IN_FLIGHT>,
+    // As uploaded to the pool; every rewrite starts from these.
+    rest: Vec<Vertex>,
+    morphed: Vec<Vertex>,
+    generation: u64,
+}
+
+impl MorphedVertices {
+    /// Buffers for the pool's first `rest.len()` vertices, which meshes
+    /// keep their offsets into.
+    pub fn new(
+        context: &VkContext,
+        rest: Vec<Vertex>,
+        resources: &mut ResourceRegistry,
+    ) -> Result<Self, VulkanError> {
+        let size = std::mem::size_of_val(rest.as_slice()) as vk::DeviceSize;
+        // Freed again if a later frame's buffer can't be created.
+        let mut created: Vec<MorphBuffer> = Vec::new();
+        let buffers = FrameResources::try_from_fn(|_| {
+            let (buffer, allocation) = context
+                .create_buffer(
+                    ResourceClass::Vertex,
+                    size,
+                    // `plasma.comp` recolours them in place.
+                    vk::BufferUsageFlags::VERTEX_BUFFER | vk::BufferUsageFlags::STORAGE_BUFFER,
+                )
+                .inspect_err(|_| {
+                    for morph in created.drain(..) {
+                        unsafe { context.device.destroy_buffer(morph.buffer, None) };
+                        context.allocator.free(morph.allocation);
+                    }
+                })?;
+            let morph = MorphBuffer {
+                buffer,
+                allocation,
+                // Older than any rewrite, so each frame uploads the first.
+                generation: 0,
+            };
+            created.push(morph);
+            Ok(morph)
+        })?;
+        for morph in buffers.iter() {
+            resources.register(ResourceKind::Buffer, morph.buffer, "morphed vertices", size);
+        }
+        Ok(Self {
+            buffers,
+            morphed: rest.clone(),
+            rest,
+            generation: 0,
+        })
+    }
+
+    /// Has `morph` rewrite the rest pose for `time`, for each frame to
+    /// upload as it comes round.
+    pub fn rewrite(&mut self, time: f32, morph: impl Fn(f32, &mut [Vertex])) {
+        self.morphed.copy_from_slice(&self.rest);
+        morph(time, &mut self.morphed);
+        self.generation += 1;
+    }
+
+    /// Copies the newest vertices into `frame`'s buffer unless it already
+    /// has them. Only once the frame's fence has signalled.
+    pub fn upload(&mut self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>) {
+        let morph = &mut self.buffers[frame];
+        if morph.generation == self.generation {
+            return;
+        }
+        unsafe {
+            (morph.allocation.mapped_ptr() as *mut Vertex)
+                .copy_from_nonoverlapping(self.morphed.as_ptr(), self.morphed.len());
+        }
+        morph.generation = self.generation;
+    }
+
+    /// Every frame's `buffer`, in frame order.
+    pub fn buffers(&self) -> impl Iterator<Item = vk::Buffer> + '_ {
+        self.buffers.iter().map(|morph| morph.buffer)
+    }
+
+    pub fn vertex_count(&self) -> u32 {
+        self.rest.len() as u32
+    }
+
+    /// What `frame` draws pooled meshes from, at the pool's offsets.
+    pub fn buffer(&self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>) -> vk::Buffer {
+        self.buffers[frame].buffer
+    }
+
+    pub fn destroy(
+        &self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        resources: &mut ResourceRegistry,
+    ) {
+        for morph in self.buffers.iter() {
+            resources.unregister(ResourceKind::Buffer, morph.buffer);
+            unsafe { device.destroy_buffer(morph.buffer, None) };
+            allocator.free(morph.allocation);
+        }
+    }
+}
+
+/// The breathing demo: scales positions about the model origin with a sine
+/// of `time`. Normals stay as they are under a uniform scale.
+pub fn breathe(time: f32, vertices: &mut [Vertex]) {
+    let phase = std::f32::consts::TAU * time / BREATHING_PERIOD_SECONDS;
+    let scale = 1.0 + BREATHING_AMPLITUDE * phase.sin();
+    for vertex in vertices {
+        vertex.pos = vertex.pos.map(|coordinate| coordinate * scale);
+    }
+}
@@ -0,0 +1,266 @@
+use ash::vk;
+use log::info;
+use rayon::prelude::*;
+use std::sync::Mutex;
+
+use super::draw_constants::{DrawConstantPacking, DrawConstants};
+use super::draw_list::DrawStats;
+use super::frame::{FrameIndex, FrameResources};
+use super::rendering::PassTarget;
+use super::MAX_FRAMES_IN_FLIGHT;
+
+/// One draw with everything it binds resolved to handles, so any thread can
+/// record it without touching the renderer.
+#[derive(Clone, Copy)]
+pub struct DrawBatch {
+    pub pipeline: vk::Pipeline,
+    pub vertex_buffer: vk::Buffer,
+    pub index_buffer: vk::Buffer,
+    pub index_type: vk::IndexType,
+    /// Sets 0 to 3.
+    pub descriptor_sets: [vk::DescriptorSet; 4],
+    /// The draw's uniform slot, relative to the first, when its constants
+    /// don't all fit the push constant budget.
+    pub slot_offset: Option<u32>,
+    pub index_count: u32,
+    pub first_index: u32,
+    pub vertex_offset: i32,
+    pub instance_count: u32,
+    pub first_instance: u32,
+    pub constants: DrawConstants,
+}
+
+impl DrawBatch {
+    /// Records `batches` in order, binding only what changes from one to the
+    /// next, and counts the binds like `DrawList::record`.
+    pub fn record_all(
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        layout: vk::PipelineLayout,
+        packing: &DrawConstantPacking,
+        batches: &[DrawBatch],
+    ) -> DrawStats {
+        let mut stats = DrawStats::default();
+        let mut pipeline = None;
+        let mut sets = None;
+        let mut mesh = None;
+        for batch in batches {
+            unsafe {
+                if pipeline != Some(batch.pipeline) {
+                    device.cmd_bind_pipeline(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        batch.pipeline,
+                    );
+                    pipeline = Some(batch.pipeline);
+                    sets = None;
+                    stats.pipeline_binds += 1;
+                }
+                if sets != Some(batch.descriptor_sets) {
+                    device.cmd_bind_descriptor_sets(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        layout,
+                        0,
+                        &batch.descriptor_sets,
+                        batch.slot_offset.as_slice(),
+                    );
+                    sets = Some(batch.descriptor_sets);
+                    stats.material_binds += 1;
+                } else if let Some(offset) = batch.slot_offset {
+                    device.cmd_bind_descriptor_sets(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        layout,
+                        0,
+                        &batch.descriptor_sets[..1],
+                        &[offset],
+                    );
+                }
+                let bound = (batch.vertex_buffer, batch.index_buffer, batch.index_type);
+                if mesh != Some(bound) {
+                    device.cmd_bind_vertex_buffers(command_buffer, 0, &[batch.vertex_buffer], &[0]);
+                    device.cmd_bind_index_buffer(
+                        command_buffer,
+                        batch.index_buffer,
+                        0,
+                        batch.index_type,
+                    );
+                    mesh = Some(bound);
+                    stats.mesh_binds += 1;
+                }
+                packing.push(device, command_buffer, layout, &batch.constants);
+                device.cmd_draw_indexed(
+                    command_buffer,
+                    batch.index_count,
+                    batch.instance_count,
+                    batch.first_index,
+                    batch.vertex_offset,
+                    batch.first_instance,
+                );
+            }
+            stats.draws += 1;
+        }
+        stats
+    }
+}
+
+/// The subpass or dynamic rendering a secondary command buffer continues,
+/// and the dynamic state it has to set again, since secondaries inherit
+/// none.
+#[derive(Clone, Copy)]
+pub struct SecondaryTarget {
+    pub pass: PassTarget,
+    pub subpass: u32,
+    /// Null on the dynamic rendering path.
+    pub framebuffer: vk::Framebuffer,
+    /// The primary's active pipeline statistics query, if any.
+    pub pipeline_statistics: vk::QueryPipelineStatisticFlags,
+    pub viewport: vk::Viewport,
+    pub scissor: vk::Rect2D,
+}
+
+/// Secondary buffers from one thread's pool for one frame in flight. They
+/// are reused from frame to frame once the pool has been reset.
+struct ThreadPool {
+    pool: vk::CommandPool,
+    buffers: Vec<vk::CommandBuffer>,
+    // Handed out since the last reset.
+    used: usize,
+}
+
+/// A command pool per frame in flight for each rayon worker, plus one for
+/// the thread recording the primary buffer. A pool may only be used by one
+/// thread at a time, so each thread allocates and records only from its
+/// own; the locks are never contended.
+pub struct ThreadLocalPools {
+    threads: rayon::ThreadPool,
+    // Indexed by the rayon thread index; the last is the recording thread's.
+    pools: FrameResources<Vec<Mutex<ThreadPool>>, MAX_FRAMES_IN_FLIGHT>,
+}
+
+impl ThreadLocalPools {
+    pub fn new(device: &ash::Device, queue_family: u32) -> Self {
+        let threads = rayon::ThreadPoolBuilder::new()
+            .thread_name(|index| format!("record-{}", index))
+            .build()
+            .unwrap();
+        let pool_info = vk::CommandPoolCreateInfo::builder()
+            .queue_family_index(queue_family)
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT);
+        let pools = FrameResources::new(std::array::from_fn(|_| {
+            (0..=threads.current_num_threads())
+                .map(|_| {
+                    Mutex::new(ThreadPool {
+                        pool: unsafe { device.create_command_pool(&pool_info, None).unwrap() },
+                        buffers: Vec::new(),
+                        used: 0,
+                    })
+                })
+                .collect()
+        }));
+        info!(
+            "Recording draws on {} threads",
+            threads.current_num_threads()
+        );
+        Self { threads, pools }
+    }
+
+    /// Frees `frame`'s secondary buffers for reuse. Only once the frame's
+    /// last submission has finished.
+    pub fn reset(&self, device: &ash::Device, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>) {
+        for pool in &self.pools[frame] {
+            let mut pool = pool.lock().unwrap();
+            unsafe {
+                device
+                    .reset_command_pool(pool.pool, vk::CommandPoolResetFlags::empty())
+                    .unwrap();
+            }
+            pool.used = 0;
+        }
+    }
+
+    /// A secondary buffer from the calling thread's pool, begun inside
+    /// `target` with its viewport and scissor set. The caller ends it.
+    pub fn begin(
+        &self,
+        device: &ash::Device,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        target: SecondaryTarget,
+    ) -> vk::CommandBuffer {
+        let index = self
+            .threads
+            .current_thread_index()
+            .unwrap_or(self.threads.current_num_threads());
+        let mut pool = self.pools[frame][index].lock().unwrap();
+        if pool.used == pool.buffers.len() {
+            let alloc_info = vk::CommandBufferAllocateInfo::builder()
+                .command_pool(pool.pool)
+                .level(vk::CommandBufferLevel::SECONDARY)
+                .command_buffer_count(1);
+            let buffer = unsafe { device.allocate_command_buffers(&alloc_info).unwrap()[0] };
+            pool.buffers.push(buffer);
+        }
+        let command_buffer = pool.buffers[pool.used];
+        pool.used += 1;
+
+        let mut rendering = target.pass.inheritance_rendering_info();
+        let mut inheritance = vk::CommandBufferInheritanceInfo::builder()
+            .render_pass(target.pass.render_pass)
+            .subpass(target.subpass)
+            .framebuffer(target.framebuffer)
+            .pipeline_statistics(target.pipeline_statistics);
+        if target.pass.is_dynamic() {
+            inheritance = inheritance.push_next(&mut rendering);
+        }
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(
+                vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT
+                    | vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE,
+            )
+            .inheritance_info(&inheritance);
+        unsafe {
+            device
+                .begin_command_buffer(command_buffer, &begin_info)
+                .unwrap();
+            device.cmd_set_viewport(command_buffer, 0, std::slice::from_ref(&target.viewport));
+            device.cmd_set_scissor(command_buffer, 0, std::slice::from_ref(&target.scissor));
+        }
+        command_buffer
+    }
+
+    /// Splits `batches` into chunks of `chunk_size` and records each into
+    /// its own secondary buffer on the rayon workers. The buffers come back
+    /// in draw order, ended, with the stats of what each recorded.
+    pub fn record_batches(
+        &self,
+        device: &ash::Device,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        target: SecondaryTarget,
+        batches: &[DrawBatch],
+        chunk_size: usize,
+        layout: vk::PipelineLayout,
+        packing: &DrawConstantPacking,
+    ) -> Vec<(vk::CommandBuffer, DrawStats)> {
+        self.threads.install(|| {
+            batches
+                .par_chunks(chunk_size)
+                .map(|chunk| {
+                    let command_buffer = self.begin(device, frame, target);
+                    let stats =
+                        DrawBatch::record_all(device, command_buffer, layout, packing, chunk);
+                    unsafe { device.end_command_buffer(command_buffer).unwrap() };
+                    (command_buffer, stats)
+                })
+                .collect()
+        })
+    }
+
+    pub fn destroy(&self, device: &ash::Device) {
+        for pools in self.pools.iter() {
+            for pool in pools {
+                unsafe { device.destroy_command_pool(pool.lock().unwrap().pool, None) };
+            }
+        }
+    }
+}
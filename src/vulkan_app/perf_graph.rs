@@ -0,0 +1,158 @@
+use ash::vk;
+use std::collections::VecDeque;
+use std::mem::offset_of;
+use std::time::Duration;
+
+use super::allocator::{Allocation, Allocator};
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::FRAME_GRAPH_SAMPLES;
+
+/// Frame budgets drawn as reference lines, in milliseconds.
+const REFERENCE_LINES_MS: [f32; 2] = [1000.0 / 60.0, 1000.0 / 30.0];
+/// Frame time at the top of the graph.
+const GRAPH_MAX_MS: f32 = 50.0;
+const GRAPH_MARGIN: f32 = 16.0;
+const GRAPH_HEIGHT: f32 = 100.0;
+const PIXELS_PER_SAMPLE: f32 = 2.0;
+
+/// Series samples plus two vertices per reference line.
+pub const GRAPH_VERTICES: usize = FRAME_GRAPH_SAMPLES + 2 * REFERENCE_LINES_MS.len();
+
+const WITHIN_BUDGET: [f32; 3] = [0.2, 0.9, 0.3];
+const OVER_BUDGET: [f32; 3] = [1.0, 0.8, 0.1];
+const OVER_HALF_RATE: [f32; 3] = [1.0, 0.2, 0.2];
+const REFERENCE_COLOR: [f32; 3] = [0.5, 0.5, 0.5];
+
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct GraphVertex {
+    /// Normalized device coordinates, y down.
+    pub pos: [f32; 2],
+    pub color: [f32; 3],
+}
+
+impl GraphVertex {
+    pub fn get_binding_description() -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription::builder()
+            .binding(0)
+            .stride(std::mem::size_of::<Self>() as u32)
+            .input_rate(vk::VertexInputRate::VERTEX)
+            .build()
+    }
+
+    pub fn get_attribute_descriptions() -> [vk::VertexInputAttributeDescription; 2] {
+        [
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(0)
+                .format(vk::Format::R32G32_SFLOAT)
+                .offset(offset_of!(Self, pos) as u32)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(1)
+                .format(vk::Format::R32G32B32_SFLOAT)
+                .offset(offset_of!(Self, color) as u32)
+                .build(),
+        ]
+    }
+}
+
+/// The last `FRAME_GRAPH_SAMPLES` frame times, oldest first.
+#[derive(Default)]
+pub struct FrameTimeHistory {
+    samples: VecDeque<f32>,
+}
+
+impl FrameTimeHistory {
+    pub fn push(&mut self, frame_time: Duration) {
+        if self.samples.len() == FRAME_GRAPH_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(frame_time.as_secs_f32() * 1000.0);
+    }
+
+    /// Fills `vertices` with the series, scrolling in from the right edge,
+    /// followed by the reference lines. Returns how many series vertices
+    /// were written.
+    pub fn write_vertices(&self, extent: vk::Extent2D, vertices: &mut [GraphVertex]) -> u32 {
+        let (width, height) = (extent.width as f32, extent.height as f32);
+        let graph_width = (PIXELS_PER_SAMPLE * FRAME_GRAPH_SAMPLES as f32)
+            .min((width - 2.0 * GRAPH_MARGIN).max(0.0));
+        let bottom = height - GRAPH_MARGIN;
+        let right = GRAPH_MARGIN + graph_width;
+        let step = graph_width / FRAME_GRAPH_SAMPLES as f32;
+        let ndc = |x: f32, y: f32| [x / width * 2.0 - 1.0, y / height * 2.0 - 1.0];
+        let y_for = |ms: f32| bottom - ms.min(GRAPH_MAX_MS) / GRAPH_MAX_MS * GRAPH_HEIGHT;
+
+        let count = self.samples.len();
+        for (i, &ms) in self.samples.iter().enumerate() {
+            let x = right - (count - 1 - i) as f32 * step;
+            let color = if ms > REFERENCE_LINES_MS[1] {
+                OVER_HALF_RATE
+            } else if ms > REFERENCE_LINES_MS[0] {
+                OVER_BUDGET
+            } else {
+                WITHIN_BUDGET
+            };
+            vertices[i] = GraphVertex {
+                pos: ndc(x, y_for(ms)),
+                color,
+            };
+        }
+        for (line, &ms) in REFERENCE_LINES_MS.iter().enumerate() {
+            let y = y_for(ms);
+            let first = FRAME_GRAPH_SAMPLES + 2 * line;
+            vertices[first] = GraphVertex {
+                pos: ndc(GRAPH_MARGIN, y),
+                color: REFERENCE_COLOR,
+            };
+            vertices[first + 1] = GraphVertex {
+                pos: ndc(right, y),
+                color: REFERENCE_COLOR,
+            };
+        }
+        count as u32
+    }
+}
+
+/// A persistently mapped vertex buffer holding one frame's graph.
+pub struct GraphBuffer {
+    pub buffer: vk::Buffer,
+    pub allocation: Allocation,
+    pub mapped: *mut GraphVertex,
+    pub series_vertices: u32,
+}
+
+impl GraphBuffer {
+    pub fn update(&mut self, history: &FrameTimeHistory, extent: vk::Extent2D) {
+        let vertices = unsafe { std::slice::from_raw_parts_mut(self.mapped, GRAPH_VERTICES) };
+        self.series_vertices = history.write_vertices(extent, vertices);
+    }
+
+    /// Draws the series as one line strip and each reference line as its own.
+    /// The caller binds the graph pipeline and sets the scissor.
+    pub fn record(&self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        unsafe {
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[self.buffer], &[0]);
+            if self.series_vertices > 1 {
+                device.cmd_draw(command_buffer, self.series_vertices, 1, 0, 0);
+            }
+            for line in 0..REFERENCE_LINES_MS.len() {
+                let first = (FRAME_GRAPH_SAMPLES + 2 * line) as u32;
+                device.cmd_draw(command_buffer, 2, 1, first, 0);
+            }
+        }
+    }
+
+    pub fn destroy(
+        &self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        resources: &mut ResourceRegistry,
+    ) {
+        resources.unregister(ResourceKind::Buffer, self.buffer);
+        unsafe { device.destroy_buffer(self.buffer, None) };
+        allocator.free(self.allocation);
+    }
+}
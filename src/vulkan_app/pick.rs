@@ -0,0 +1,321 @@
+use std::ops::Range;
+
+use ash::vk;
+use cgmath::Matrix4;
+
+use super::allocator::{Allocation, Allocator};
+use super::context::{self, VkContext};
+use super::error::VulkanError;
+use super::memory::ResourceClass;
+use super::mesh_pool::MeshAllocation;
+use super::resources::{ResourceKind, ResourceRegistry};
+
+/// The ID target's format: an object ID per pixel.
+pub const PICK_FORMAT: vk::Format = vk::Format::R32_UINT;
+
+/// What the ID target is cleared to, where no object covers a pixel.
+pub const BACKGROUND_ID: u32 = 0;
+
+/// The ID `pick.frag` writes for object `index`: the animated objects in
+/// order, then the scene objects, one more than the index so that none is
+/// BACKGROUND_ID.
+pub fn object_id(index: u32) -> u32 {
+    index + 1
+}
+
+/// The object index `id` stands for; None for the background.
+pub fn object_index(id: u32) -> Option<u32> {
+    id.checked_sub(1)
+}
+
+/// The pixel of a target of `render` extent under window position `(x, y)`,
+/// in a window of `display` extent, if it is inside `scene`.
+pub fn render_pixel(
+    x: u32,
+    y: u32,
+    display: vk::Extent2D,
+    render: vk::Extent2D,
+    scene: vk::Rect2D,
+) -> Option<vk::Offset2D> {
+    let scale = |position: u32, display: u32, render: u32| {
+        (position as u64 * render as u64 / display.max(1) as u64) as i64
+    };
+    let (x, y) = (
+        scale(x, display.width, render.width),
+        scale(y, display.height, render.height),
+    );
+    let (left, top) = (scene.offset.x as i64, scene.offset.y as i64);
+    let inside = (left..left + scene.extent.width as i64).contains(&x)
+        && (top..top + scene.extent.height as i64).contains(&y);
+    inside.then_some(vk::Offset2D {
+        x: x as i32,
+        y: y as i32,
+    })
+}
+
+/// What `pick.vert` is pushed.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct PickPush {
+    pub model: [[f32; 4]; 4],
+    /// Instance 0's ID; each instance adds its index.
+    pub first_id: u32,
+}
+
+/// One draw of the ID pass, as the frame drew it: `instances` of the
+/// transforms buffer through `model`.
+#[derive(Clone, Debug)]
+pub struct PickDraw {
+    pub mesh: MeshAllocation,
+    pub model: Matrix4<f32>,
+    pub instances: Range<u32>,
+    pub first_id: u32,
+}
+
+/// The ID and depth images the ID pass draws into, at the render extent.
+struct PickTarget {
+    id_image: vk::Image,
+    id_allocation: Allocation,
+    id_view: vk::ImageView,
+    depth_image: vk::Image,
+    depth_allocation: Allocation,
+    depth_view: vk::ImageView,
+    framebuffer: vk::Framebuffer,
+}
+
+/// Object picking: a pass of its own that draws the scene's object IDs
+/// into an R32_UINT target, and a host-visible buffer the pixel under the
+/// cursor is copied into. Only recorded when something is picked, so
+/// having it costs the target's memory and no per-frame bandwidth. The
+/// target follows the render extent like the other attachments.
+pub struct PickPass {
+    pub render_pass: vk::RenderPass,
+    pub layout: vk::PipelineLayout,
+    pub pipeline: vk::Pipeline,
+    /// The frame's draws, by object, rebuilt with the draw list.
+    pub draws: Vec<PickDraw>,
+    buffer: vk::Buffer,
+    allocation: Allocation,
+    // None until `create_target`, and again after `destroy_target`.
+    target: Option<PickTarget>,
+}
+
+impl PickPass {
+    pub fn new(
+        context: &VkContext,
+        render_pass: vk::RenderPass,
+        layout: vk::PipelineLayout,
+        pipeline: vk::Pipeline,
+        resources: &mut ResourceRegistry,
+    ) -> Result<Self, VulkanError> {
+        let size = std::mem::size_of::<u32>() as vk::DeviceSize;
+        let (buffer, allocation) = context.create_buffer(
+            ResourceClass::Staging,
+            size,
+            vk::BufferUsageFlags::TRANSFER_DST,
+        )?;
+        resources.register(ResourceKind::Buffer, buffer, "pick readback buffer", size);
+        Ok(Self {
+            render_pass,
+            layout,
+            pipeline,
+            draws: Vec::new(),
+            buffer,
+            allocation,
+            target: None,
+        })
+    }
+
+    /// The ID target and its depth, of `extent`, and their framebuffer.
+    pub fn create_target(
+        &mut self,
+        context: &VkContext,
+        depth_format: vk::Format,
+        extent: vk::Extent2D,
+        resources: &mut ResourceRegistry,
+    ) -> Result<(), VulkanError> {
+        let (id_image, id_allocation) = context.create_image(
+            extent.width,
+            extent.height,
+            vk::SampleCountFlags::TYPE_1,
+            PICK_FORMAT,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+        )?;
+        let depth = context.create_image(
+            extent.width,
+            extent.height,
+            vk::SampleCountFlags::TYPE_1,
+            depth_format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT
+                | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+        );
+        let (depth_image, depth_allocation) = match depth {
+            Ok(depth) => depth,
+            Err(error) => {
+                unsafe { context.device.destroy_image(id_image, None) };
+                context.allocator.free(id_allocation);
+                return Err(error);
+            }
+        };
+        let id_view = context.create_image_view(id_image, PICK_FORMAT, vk::ImageAspectFlags::COLOR);
+        let depth_view = context.create_image_view(
+            depth_image,
+            depth_format,
+            context::depth_aspect_flags(depth_format),
+        );
+        let attachments = [id_view, depth_view];
+        let framebuffer_info = vk::FramebufferCreateInfo::builder()
+            .render_pass(self.render_pass)
+            .attachments(&attachments)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1);
+        let framebuffer = unsafe {
+            context
+                .device
+                .create_framebuffer(&framebuffer_info, None)
+                .unwrap()
+        };
+        resources.register(
+            ResourceKind::Image,
+            id_image,
+            "pick ID target",
+            id_allocation.size,
+        );
+        resources.register(ResourceKind::ImageView, id_view, "pick ID target view", 0);
+        resources.register(
+            ResourceKind::Image,
+            depth_image,
+            "pick depth target",
+            depth_allocation.size,
+        );
+        resources.register(
+            ResourceKind::ImageView,
+            depth_view,
+            "pick depth target view",
+            0,
+        );
+        self.target = Some(PickTarget {
+            id_image,
+            id_allocation,
+            id_view,
+            depth_image,
+            depth_allocation,
+            depth_view,
+            framebuffer,
+        });
+        Ok(())
+    }
+
+    /// The target's framebuffer; None until `create_target`.
+    pub fn framebuffer(&self) -> Option<vk::Framebuffer> {
+        self.target.as_ref().map(|target| target.framebuffer)
+    }
+
+    /// Copies the ID at `pixel` into the buffer, for `read`. Recorded after
+    /// the pass, which leaves the target in TRANSFER_SRC_OPTIMAL.
+    pub fn record_copy(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        pixel: vk::Offset2D,
+    ) {
+        let Some(target) = &self.target else {
+            return;
+        };
+        let region = vk::BufferImageCopy::builder()
+            .image_subresource(vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            })
+            .image_offset(vk::Offset3D {
+                x: pixel.x,
+                y: pixel.y,
+                z: 0,
+            })
+            .image_extent(vk::Extent3D {
+                width: 1,
+                height: 1,
+                depth: 1,
+            });
+        let buffer_barrier = vk::BufferMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(self.buffer)
+            .offset(0)
+            .size(vk::WHOLE_SIZE);
+        unsafe {
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                target.id_image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self.buffer,
+                std::slice::from_ref(&region),
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST,
+                vk::DependencyFlags::empty(),
+                &[],
+                std::slice::from_ref(&buffer_barrier),
+                &[],
+            );
+        }
+    }
+
+    /// The ID the last copy read. The copy must have completed.
+    pub fn read(&self) -> u32 {
+        unsafe { (self.allocation.mapped_ptr() as *const u32).read_unaligned() }
+    }
+
+    /// The target and its framebuffer. Safe to call again.
+    pub fn destroy_target(
+        &mut self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        resources: &mut ResourceRegistry,
+    ) {
+        let Some(target) = self.target.take() else {
+            return;
+        };
+        resources.unregister(ResourceKind::ImageView, target.id_view);
+        resources.unregister(ResourceKind::Image, target.id_image);
+        resources.unregister(ResourceKind::ImageView, target.depth_view);
+        resources.unregister(ResourceKind::Image, target.depth_image);
+        unsafe {
+            device.destroy_framebuffer(target.framebuffer, None);
+            device.destroy_image_view(target.id_view, None);
+            device.destroy_image(target.id_image, None);
+            device.destroy_image_view(target.depth_view, None);
+            device.destroy_image(target.depth_image, None);
+        }
+        allocator.free(target.id_allocation);
+        allocator.free(target.depth_allocation);
+    }
+
+    /// The pipeline, render pass and readback buffer; the target must
+    /// already be destroyed.
+    pub fn destroy(
+        &self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        resources: &mut ResourceRegistry,
+    ) {
+        resources.unregister(ResourceKind::Pipeline, self.pipeline);
+        resources.unregister(ResourceKind::Buffer, self.buffer);
+        unsafe {
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.layout, None);
+            device.destroy_render_pass(self.render_pass, None);
+            device.destroy_buffer(self.buffer, None);
+        }
+        allocator.free(self.allocation);
+    }
+}
@@ -0,0 +1,187 @@
+use ash::vk;
+use log::info;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use super::interpolation::ColorInterpolation;
+
+/// Which faces a permutation rasterizes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Faces {
+    /// Back faces are culled.
+    Front,
+    /// Nothing is culled, for double-sided objects.
+    Both,
+}
+
+impl Faces {
+    pub fn cull_mode(self) -> vk::CullModeFlags {
+        match self {
+            Faces::Front => vk::CullModeFlags::BACK,
+            Faces::Both => vk::CullModeFlags::NONE,
+        }
+    }
+}
+
+/// How the scene's triangles are rasterized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    Fill,
+    /// Edges only, for debugging geometry. Needs `fillModeNonSolid`.
+    Wireframe,
+}
+
+impl RenderMode {
+    pub fn polygon_mode(self) -> vk::PolygonMode {
+        match self {
+            RenderMode::Fill => vk::PolygonMode::FILL,
+            RenderMode::Wireframe => vk::PolygonMode::LINE,
+        }
+    }
+}
+
+/// One graphics pipeline permutation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PipelineKey {
+    Cube(ColorInterpolation, Faces),
+    DepthPrepass(Faces),
+    /// The cube's edges, with linear interpolation.
+    Wireframe(Faces),
+}
+
+impl PipelineKey {
+    /// Permutation to draw with while this one is still being built. Chains
+    /// end at the back-culled linear permutations, which are always warmed.
+    pub fn fallback(self) -> Option<PipelineKey> {
+        match self {
+            PipelineKey::Cube(ColorInterpolation::NaiveSrgb, faces) => {
+                Some(PipelineKey::Cube(ColorInterpolation::Linear, faces))
+            }
+            PipelineKey::Cube(ColorInterpolation::Linear, Faces::Both) => {
+                Some(PipelineKey::Cube(ColorInterpolation::Linear, Faces::Front))
+            }
+            PipelineKey::DepthPrepass(Faces::Both) => Some(PipelineKey::DepthPrepass(Faces::Front)),
+            PipelineKey::Wireframe(faces) => {
+                Some(PipelineKey::Cube(ColorInterpolation::Linear, faces))
+            }
+            PipelineKey::Cube(ColorInterpolation::Linear, Faces::Front)
+            | PipelineKey::DepthPrepass(Faces::Front) => None,
+        }
+    }
+}
+
+/// Result of a worker-thread `create_graphics_pipelines` call and how long it took.
+pub type BuildResult = (Vec<vk::Pipeline>, Duration);
+
+struct PendingBuild {
+    keys: Vec<PipelineKey>,
+    handle: JoinHandle<BuildResult>,
+}
+
+/// Graphics pipelines by permutation. Everything the scene is expected to need
+/// is created up front by a warmup; anything requested afterwards is built on a
+/// worker thread, one batch at a time, while frames draw with the fallback.
+/// Keys requested while a batch is in flight wait for the next one.
+#[derive(Default)]
+pub struct PipelineVariants {
+    pipelines: Vec<(PipelineKey, vk::Pipeline)>,
+    warmed: bool,
+    created_after_warmup: u32,
+    pending: Option<PendingBuild>,
+    queued: Vec<PipelineKey>,
+}
+
+impl PipelineVariants {
+    pub fn get(&self, key: PipelineKey) -> Option<vk::Pipeline> {
+        self.pipelines
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|&(_, pipeline)| pipeline)
+    }
+
+    pub fn missing(&self, keys: &[PipelineKey]) -> Vec<PipelineKey> {
+        keys.iter()
+            .copied()
+            .filter(|&key| self.get(key).is_none())
+            .collect()
+    }
+
+    /// Keys that are neither created, in flight nor queued.
+    pub fn unscheduled(&self, keys: &[PipelineKey]) -> Vec<PipelineKey> {
+        let in_flight = self.pending.as_ref().map_or(&[][..], |build| &build.keys);
+        self.missing(keys)
+            .into_iter()
+            .filter(|key| !in_flight.contains(key) && !self.queued.contains(key))
+            .collect()
+    }
+
+    pub fn is_building(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    pub fn start_build(&mut self, keys: Vec<PipelineKey>, handle: JoinHandle<BuildResult>) {
+        assert!(
+            self.pending.is_none(),
+            "a pipeline build is already in flight"
+        );
+        self.pending = Some(PendingBuild { keys, handle });
+    }
+
+    pub fn queue(&mut self, keys: &[PipelineKey]) {
+        self.queued.extend_from_slice(keys);
+    }
+
+    /// The in-flight batch, if its worker has finished. Never blocks.
+    pub fn finished_build(&mut self) -> Option<(Vec<PipelineKey>, BuildResult)> {
+        if !self.pending.as_ref()?.handle.is_finished() {
+            return None;
+        }
+        let build = self.pending.take()?;
+        Some((build.keys, build.handle.join().unwrap()))
+    }
+
+    /// Takes the keys requested while the last batch was in flight that still
+    /// need building.
+    pub fn take_queued(&mut self) -> Vec<PipelineKey> {
+        let queued = std::mem::take(&mut self.queued);
+        self.missing(&queued)
+    }
+
+    /// Waits for the in-flight batch and returns its pipelines without adding
+    /// them, for when their render pass or extent is about to go away. Queued
+    /// keys are dropped too; the next warmup covers whatever is still needed.
+    pub fn cancel_build(&mut self) -> Vec<vk::Pipeline> {
+        self.queued.clear();
+        match self.pending.take() {
+            Some(build) => build.handle.join().unwrap().0,
+            None => Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, keys: &[PipelineKey], pipelines: &[vk::Pipeline]) {
+        if self.warmed {
+            self.created_after_warmup += keys.len() as u32;
+            info!(
+                "Created {:?} after warmup ({} pipelines created after warmup)",
+                keys, self.created_after_warmup
+            );
+        }
+        self.pipelines
+            .extend(keys.iter().copied().zip(pipelines.iter().copied()));
+    }
+
+    pub fn finish_warmup(&mut self, elapsed: Duration) {
+        self.warmed = true;
+        info!(
+            "Warmed {} pipelines in {:.1} ms",
+            self.pipelines.len(),
+            elapsed.as_secs_f64() * 1000.0
+        );
+    }
+
+    /// Removes every pipeline for destruction; the next set needs a new warmup.
+    pub fn drain(&mut self) -> Vec<(PipelineKey, vk::Pipeline)> {
+        self.warmed = false;
+        std::mem::take(&mut self.pipelines)
+    }
+}
@@ -0,0 +1,109 @@
+use ash::vk;
+use std::mem::offset_of;
+
+use super::animation::AnimationPushConstants;
+use super::frame::{FrameIndex, FrameResources};
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::vertex::Vertex;
+use super::MAX_FRAMES_IN_FLIGHT;
+
+/// Workgroup size of `plasma.comp`.
+pub const WORKGROUP_SIZE: u32 = 64;
+
+// `plasma.comp` indexes vertices as 11 floats, with the colour after the
+// position.
+const _: () = assert!(std::mem::size_of::<Vertex>() == 11 * 4);
+const _: () = assert!(offset_of!(Vertex, pos) == 0 && offset_of!(Vertex, color) == 3 * 4);
+
+/// `plasma.comp` recolouring the morphed vertices in each frame's own
+/// vertex buffer ahead of the render pass, from wherever `upload` last left
+/// their positions. Takes the time through the same push constants as
+/// `animate.comp`.
+pub struct PlasmaPass {
+    pub set_layout: vk::DescriptorSetLayout,
+    pub descriptor_pool: vk::DescriptorPool,
+    /// Each frame's morphed vertices.
+    pub sets: FrameResources<vk::DescriptorSet, MAX_FRAMES_IN_FLIGHT>,
+    pub layout: vk::PipelineLayout,
+    pub pipeline: vk::Pipeline,
+    pub vertex_count: u32,
+}
+
+impl PlasmaPass {
+    /// Records the dispatch that recolours `frame`'s vertices, in
+    /// `vertex_buffer`, and the barrier that makes them visible to vertex
+    /// input. Must be recorded outside the render pass.
+    pub fn record_dispatch(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        vertex_buffer: vk::Buffer,
+        time: f32,
+    ) {
+        let push_constants = AnimationPushConstants {
+            time,
+            count: self.vertex_count,
+        };
+        // The host's upload before the submit needs no barrier.
+        let barrier = vk::BufferMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(vk::AccessFlags::VERTEX_ATTRIBUTE_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(vertex_buffer)
+            .offset(0)
+            .size(vk::WHOLE_SIZE);
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.pipeline,
+            );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.layout,
+                0,
+                &[self.sets[frame]],
+                &[],
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.layout,
+                vk::ShaderStageFlags::COMPUTE,
+                0,
+                std::slice::from_raw_parts(
+                    &push_constants as *const AnimationPushConstants as *const u8,
+                    std::mem::size_of::<AnimationPushConstants>(),
+                ),
+            );
+            device.cmd_dispatch(
+                command_buffer,
+                self.vertex_count.div_ceil(WORKGROUP_SIZE),
+                1,
+                1,
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::VERTEX_INPUT,
+                vk::DependencyFlags::empty(),
+                &[],
+                std::slice::from_ref(&barrier),
+                &[],
+            );
+        }
+    }
+
+    pub fn destroy(&self, device: &ash::Device, resources: &mut ResourceRegistry) {
+        unsafe {
+            resources.unregister(ResourceKind::Pipeline, self.pipeline);
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.layout, None);
+            resources.unregister(ResourceKind::DescriptorPool, self.descriptor_pool);
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_descriptor_set_layout(self.set_layout, None);
+        }
+    }
+}
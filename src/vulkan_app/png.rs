@@ -0,0 +1,434 @@
+//! Minimal PNG writer: 8-bit RGBA, no filtering, and zlib "stored" blocks
+//! rather than real compression. Larger than a proper encoder's output, but
+//! any decoder reads it and it needs no dependencies.
+//!
+//! Also a reader for textures: 8- and 16-bit images of any colour type,
+//! without interlacing, decoded to 8-bit RGBA.
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+const MAX_STORED_BLOCK: usize = 0xFFFF;
+
+// Deflate's length and distance codes: base value and extra bits, from
+// RFC 1951 section 3.2.5.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Order in which a dynamic block lists its code length code lengths.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+pub fn encode_rgba(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    assert_eq!(rgba.len(), width as usize * height as usize * 4);
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Bit depth 8, color type 6 (RGBA), default compression, filter and no interlace.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // Each scanline is prefixed with its filter type, 0 (none).
+    let mut scanlines = Vec::with_capacity(rgba.len() + height as usize);
+    for row in rgba.chunks_exact(width as usize * 4) {
+        scanlines.push(0);
+        scanlines.extend_from_slice(row);
+    }
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // CMF/FLG for deflate with a 32K window and no preset dictionary.
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// Width, height and tightly packed RGBA8 pixels of a PNG file.
+pub fn decode_rgba(png: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    let mut rest = png.strip_prefix(&SIGNATURE).ok_or("not a PNG file")?;
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = Vec::new();
+    loop {
+        if rest.len() < 12 {
+            return Err("truncated chunk".to_string());
+        }
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        if rest.len() < 12 + length {
+            return Err("truncated chunk".to_string());
+        }
+        let kind = &rest[4..8];
+        let data = &rest[8..8 + length];
+        let crc = u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap());
+        if crc32(&rest[4..8 + length]) != crc {
+            return Err(format!(
+                "bad checksum in {} chunk",
+                String::from_utf8_lossy(kind)
+            ));
+        }
+        match kind {
+            b"IHDR" if data.len() == 13 => header = Some(data),
+            b"PLTE" => palette = data,
+            b"tRNS" => transparency = data,
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+        rest = &rest[12 + length..];
+    }
+
+    let header = header.ok_or("missing IHDR chunk")?;
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap());
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap());
+    let (bit_depth, color_type, interlace) = (header[8], header[9], header[12]);
+    let channels = match color_type {
+        0 | 3 => 1,
+        2 => 3,
+        4 => 2,
+        6 => 4,
+        _ => return Err(format!("unknown colour type {}", color_type)),
+    };
+    if bit_depth != 8 && !(bit_depth == 16 && color_type != 3) {
+        return Err(format!(
+            "unsupported bit depth {} for colour type {}",
+            bit_depth, color_type
+        ));
+    }
+    if interlace != 0 {
+        return Err("interlaced images are not supported".to_string());
+    }
+    if width == 0 || height == 0 {
+        return Err("empty image".to_string());
+    }
+
+    let pixel_bytes = channels * bit_depth as usize / 8;
+    let stride = width as usize * pixel_bytes;
+    let mut scanlines = zlib_inflate(&compressed)?;
+    if scanlines.len() < (stride + 1) * height as usize {
+        return Err("image data is shorter than the image".to_string());
+    }
+    unfilter(&mut scanlines, stride, pixel_bytes, height as usize)?;
+
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for row in scanlines.chunks_exact(stride + 1).take(height as usize) {
+        for pixel in row[1..].chunks_exact(pixel_bytes) {
+            // 16-bit samples keep their most significant byte.
+            let sample = |channel: usize| pixel[channel * pixel_bytes / channels];
+            let texel = match color_type {
+                0 => [sample(0), sample(0), sample(0), 255],
+                2 => [sample(0), sample(1), sample(2), 255],
+                3 => {
+                    let index = pixel[0] as usize;
+                    let color = palette
+                        .get(index * 3..index * 3 + 3)
+                        .ok_or("palette index out of range")?;
+                    let alpha = transparency.get(index).copied().unwrap_or(255);
+                    [color[0], color[1], color[2], alpha]
+                }
+                4 => [sample(0), sample(0), sample(0), sample(1)],
+                _ => [sample(0), sample(1), sample(2), sample(3)],
+            };
+            rgba.extend_from_slice(&texel);
+        }
+    }
+    Ok((width, height, rgba))
+}
+
+/// Undoes each scanline's filter in place. Every line keeps its leading
+/// filter type byte.
+fn unfilter(
+    scanlines: &mut [u8],
+    stride: usize,
+    pixel_bytes: usize,
+    height: usize,
+) -> Result<(), String> {
+    for y in 0..height {
+        let (done, current) = scanlines.split_at_mut(y * (stride + 1));
+        let previous = y.checked_sub(1).map(|_| &done[done.len() - stride..]);
+        let filter = current[0];
+        let line = &mut current[1..=stride];
+        for x in 0..stride {
+            let left = if x >= pixel_bytes {
+                line[x - pixel_bytes]
+            } else {
+                0
+            };
+            let up = previous.map_or(0, |previous| previous[x]);
+            let up_left = match previous {
+                Some(previous) if x >= pixel_bytes => previous[x - pixel_bytes],
+                _ => 0,
+            };
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(format!("unknown filter type {}", filter)),
+            };
+            line[x] = line[x].wrapping_add(predicted);
+        }
+    }
+    Ok(())
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let distance = |value: u8| (estimate - value as i16).abs();
+    if distance(left) <= distance(up) && distance(left) <= distance(up_left) {
+        left
+    } else if distance(up) <= distance(up_left) {
+        up
+    } else {
+        up_left
+    }
+}
+
+fn zlib_inflate(data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 6 || data[0] & 0x0F != 8 || data[1] & 0x20 != 0 {
+        return Err("image data is not a plain zlib stream".to_string());
+    }
+    let mut reader = BitReader {
+        data: &data[2..],
+        position: 0,
+    };
+    let mut out = Vec::new();
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                let start = reader.position.div_ceil(8);
+                let header = reader
+                    .data
+                    .get(start..start + 4)
+                    .ok_or("truncated stored block")?;
+                let length = u16::from_le_bytes([header[0], header[1]]) as usize;
+                let block = reader
+                    .data
+                    .get(start + 4..start + 4 + length)
+                    .ok_or("truncated stored block")?;
+                out.extend_from_slice(block);
+                reader.position = (start + 4 + length) * 8;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths);
+                let distances = Huffman::new(&[5; 30]);
+                inflate_block(&mut reader, &mut out, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut out, &literals, &distances)?;
+            }
+            _ => return Err("invalid deflate block type".to_string()),
+        }
+        if last {
+            break;
+        }
+    }
+    let end = reader.position.div_ceil(8);
+    let checksum = reader
+        .data
+        .get(end..end + 4)
+        .ok_or("missing zlib checksum")?;
+    if u32::from_be_bytes(checksum.try_into().unwrap()) != adler32(&out) {
+        return Err("bad zlib checksum".to_string());
+    }
+    Ok(out)
+}
+
+fn read_dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    let mut code_length_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_length_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_length_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (value, repeat) = match code_lengths.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths.last().ok_or("length repeat with nothing before")?;
+                (previous, 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err("code lengths overrun".to_string());
+    }
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), String> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let code = symbol - 257;
+                if code >= LENGTH_BASE.len() {
+                    return Err("invalid length code".to_string());
+                }
+                let length =
+                    LENGTH_BASE[code] as usize + reader.bits(LENGTH_EXTRA[code] as u32)? as usize;
+                let code = distances.decode(reader)? as usize;
+                if code >= DISTANCE_BASE.len() {
+                    return Err("invalid distance code".to_string());
+                }
+                let distance = DISTANCE_BASE[code] as usize
+                    + reader.bits(DISTANCE_EXTRA[code] as u32)? as usize;
+                if distance > out.len() {
+                    return Err("distance reaches before the start".to_string());
+                }
+                // Byte by byte, as the copy may overlap what it writes.
+                let start = out.len() - distance;
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+/// Reads deflate's least-significant-bit-first bit stream.
+struct BitReader<'a> {
+    data: &'a [u8],
+    /// In bits.
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> Result<u32, String> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = self
+                .data
+                .get(self.position / 8)
+                .ok_or("image data ends early")?;
+            value |= ((byte >> (self.position % 8)) as u32 & 1) << i;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+/// A canonical Huffman code, decoded a bit at a time: `counts[n]` codes of
+/// length n, and the symbols in code order.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16)
+            .filter(|&symbol| lengths[symbol as usize] != 0)
+            .collect();
+        symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, String> {
+        // The first code of each length, and where its symbols start.
+        let (mut code, mut first, mut index) = (0usize, 0usize, 0usize);
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as usize;
+            let count = count as usize;
+            if code < first + count {
+                return Ok(self.symbols[index + code - first]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid Huffman code".to_string())
+    }
+}
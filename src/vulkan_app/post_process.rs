@@ -0,0 +1,258 @@
+use ash::vk;
+
+use super::allocator::{Allocation, Allocator};
+use super::context::VkContext;
+use super::error::VulkanError;
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::{POST_GAMMA_MAX, POST_GAMMA_MIN, POST_GAMMA_STEP, POST_VIGNETTE, POST_VIGNETTE_STEP};
+
+/// What `post.frag` is pushed.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PostParams {
+    /// How far the corners darken: none at 0, to black at 1.
+    pub vignette: f32,
+    /// Colours are raised to `1 / gamma`; 1 leaves them as they are.
+    pub gamma: f32,
+}
+
+impl Default for PostParams {
+    fn default() -> Self {
+        Self {
+            vignette: POST_VIGNETTE,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl PostParams {
+    /// Steps the vignette by POST_VIGNETTE_STEP, back to none after 1.
+    pub fn cycle_vignette(&mut self) {
+        let next = self.vignette + POST_VIGNETTE_STEP;
+        // Steps that don't land on 1 exactly still stop there.
+        self.vignette = if next > 1.0 + POST_VIGNETTE_STEP / 2.0 {
+            0.0
+        } else {
+            next.min(1.0)
+        };
+    }
+
+    /// Brightens for positive `steps`, darkens for negative, within
+    /// POST_GAMMA_MIN and POST_GAMMA_MAX.
+    pub fn step_gamma(&mut self, steps: i32) {
+        self.gamma =
+            (self.gamma + steps as f32 * POST_GAMMA_STEP).clamp(POST_GAMMA_MIN, POST_GAMMA_MAX);
+    }
+}
+
+/// What the scene subpass renders, or resolves, into for one swapchain
+/// image, for the post-process subpass to read back. It never leaves the
+/// render pass, so it is transient and its contents aren't stored.
+struct PostTarget {
+    image: vk::Image,
+    allocation: Allocation,
+    view: vk::ImageView,
+}
+
+/// The render pass's last subpass with `--post-process`: the scene subpass
+/// draws into a target per swapchain image, and this reads it back as an
+/// input attachment and draws it over the swapchain image, or the scaled
+/// target, with a vignette and gamma. The pipeline is built against the
+/// scene render pass; the targets and their descriptor sets follow the
+/// render extent and swapchain image count.
+pub struct PostPass {
+    pub set_layout: vk::DescriptorSetLayout,
+    pub layout: vk::PipelineLayout,
+    pub pipeline: vk::Pipeline,
+    /// The swapchain generation the targets were last created for.
+    pub generation: u64,
+    // Null until `create_targets`, and again after `destroy_targets`.
+    descriptor_pool: vk::DescriptorPool,
+    // One per swapchain image, reading its target.
+    sets: Vec<vk::DescriptorSet>,
+    targets: Vec<PostTarget>,
+}
+
+impl PostPass {
+    pub fn new(
+        set_layout: vk::DescriptorSetLayout,
+        layout: vk::PipelineLayout,
+        pipeline: vk::Pipeline,
+    ) -> Self {
+        Self {
+            set_layout,
+            layout,
+            pipeline,
+            generation: 0,
+            descriptor_pool: vk::DescriptorPool::null(),
+            sets: Vec::new(),
+            targets: Vec::new(),
+        }
+    }
+
+    /// A target of `format` and `extent` for each of `count` swapchain
+    /// images of swapchain `generation`, and the descriptor sets reading
+    /// them.
+    pub fn create_targets(
+        &mut self,
+        context: &VkContext,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        count: usize,
+        generation: u64,
+        resources: &mut ResourceRegistry,
+    ) -> Result<(), VulkanError> {
+        let device = &context.device;
+        self.generation = generation;
+        for _ in 0..count {
+            let (image, allocation) = context.create_image(
+                extent.width,
+                extent.height,
+                vk::SampleCountFlags::TYPE_1,
+                format,
+                vk::ImageTiling::OPTIMAL,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | vk::ImageUsageFlags::INPUT_ATTACHMENT
+                    | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+            )?;
+            let view = context.create_image_view(image, format, vk::ImageAspectFlags::COLOR);
+            resources.register(
+                ResourceKind::Image,
+                image,
+                "post-process target",
+                allocation.size,
+            );
+            resources.register(ResourceKind::ImageView, view, "post-process target view", 0);
+            self.targets.push(PostTarget {
+                image,
+                allocation,
+                view,
+            });
+        }
+
+        let pool_size = vk::DescriptorPoolSize::builder()
+            .ty(vk::DescriptorType::INPUT_ATTACHMENT)
+            .descriptor_count(count as u32)
+            .build();
+        let pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(std::slice::from_ref(&pool_size))
+            .max_sets(count as u32);
+        self.descriptor_pool = unsafe { device.create_descriptor_pool(&pool_info, None).unwrap() };
+        resources.register(
+            ResourceKind::DescriptorPool,
+            self.descriptor_pool,
+            "post-process descriptor pool",
+            0,
+        );
+        let layouts = vec![self.set_layout; count];
+        let alloc_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(self.descriptor_pool)
+            .set_layouts(&layouts);
+        self.sets = unsafe { device.allocate_descriptor_sets(&alloc_info).unwrap() };
+        for (&set, target) in self.sets.iter().zip(&self.targets) {
+            let image_info = vk::DescriptorImageInfo::builder()
+                .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .image_view(target.view)
+                .build();
+            let write = vk::WriteDescriptorSet::builder()
+                .dst_set(set)
+                .dst_binding(0)
+                .descriptor_type(vk::DescriptorType::INPUT_ATTACHMENT)
+                .image_info(std::slice::from_ref(&image_info));
+            unsafe { device.update_descriptor_sets(std::slice::from_ref(&write), &[]) };
+        }
+        Ok(())
+    }
+
+    /// Each swapchain image's target, for its framebuffer.
+    pub fn target_views(&self) -> Vec<vk::ImageView> {
+        self.targets.iter().map(|target| target.view).collect()
+    }
+
+    /// Records the subpass's draw, reading `image_index`'s target, over
+    /// `extent`. The render pass must be in the post-process subpass.
+    pub fn record(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        image_index: usize,
+        extent: vk::Extent2D,
+        params: PostParams,
+    ) {
+        // Drawn in framebuffer coordinates, which never need flipping.
+        let viewport = vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: extent.width as f32,
+            height: extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        };
+        let scissor = vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent,
+        };
+        unsafe {
+            device.cmd_set_viewport(command_buffer, 0, std::slice::from_ref(&viewport));
+            device.cmd_set_scissor(command_buffer, 0, std::slice::from_ref(&scissor));
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline,
+            );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.layout,
+                0,
+                &[self.sets[image_index]],
+                &[],
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.layout,
+                vk::ShaderStageFlags::FRAGMENT,
+                0,
+                std::slice::from_raw_parts(
+                    &params as *const PostParams as *const u8,
+                    std::mem::size_of::<PostParams>(),
+                ),
+            );
+            device.cmd_draw(command_buffer, 3, 1, 0, 0);
+        }
+    }
+
+    /// The targets and their descriptor sets. Safe to call again.
+    pub fn destroy_targets(
+        &mut self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        resources: &mut ResourceRegistry,
+    ) {
+        if self.descriptor_pool != vk::DescriptorPool::null() {
+            resources.unregister(ResourceKind::DescriptorPool, self.descriptor_pool);
+            unsafe { device.destroy_descriptor_pool(self.descriptor_pool, None) };
+            self.descriptor_pool = vk::DescriptorPool::null();
+        }
+        self.sets.clear();
+        for target in self.targets.drain(..) {
+            resources.unregister(ResourceKind::ImageView, target.view);
+            resources.unregister(ResourceKind::Image, target.image);
+            unsafe {
+                device.destroy_image_view(target.view, None);
+                device.destroy_image(target.image, None);
+            }
+            allocator.free(target.allocation);
+        }
+    }
+
+    /// The pipeline and layouts; the targets must already be destroyed.
+    pub fn destroy(&self, device: &ash::Device, resources: &mut ResourceRegistry) {
+        resources.unregister(ResourceKind::Pipeline, self.pipeline);
+        unsafe {
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.layout, None);
+            device.destroy_descriptor_set_layout(self.set_layout, None);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use ash::vk;
+
+/// What presentation should favour. Each asks for one present mode, which
+/// `ConfigResolver` steps down to what the surface offers, FIFO at worst.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresentModePreference {
+    /// FIFO: waits for vertical blank and never tears.
+    Vsync,
+    /// MAILBOX: never tears, and shows the newest frame at each blank.
+    LowLatency,
+    /// IMMEDIATE: presents at once, and may tear.
+    Immediate,
+}
+
+impl PresentModePreference {
+    const ALL: [PresentModePreference; 3] = [
+        PresentModePreference::Vsync,
+        PresentModePreference::LowLatency,
+        PresentModePreference::Immediate,
+    ];
+
+    /// The preference that asks for `mode`. FIFO_RELAXED counts as vsync.
+    pub fn from_mode(mode: vk::PresentModeKHR) -> Self {
+        match mode {
+            vk::PresentModeKHR::MAILBOX => PresentModePreference::LowLatency,
+            vk::PresentModeKHR::IMMEDIATE => PresentModePreference::Immediate,
+            _ => PresentModePreference::Vsync,
+        }
+    }
+
+    pub fn mode(self) -> vk::PresentModeKHR {
+        match self {
+            PresentModePreference::Vsync => vk::PresentModeKHR::FIFO,
+            PresentModePreference::LowLatency => vk::PresentModeKHR::MAILBOX,
+            PresentModePreference::Immediate => vk::PresentModeKHR::IMMEDIATE,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PresentModePreference::Vsync => "vsync",
+            PresentModePreference::LowLatency => "low latency",
+            PresentModePreference::Immediate => "immediate",
+        }
+    }
+
+    /// The one after this, wrapping back to `Vsync`.
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+}
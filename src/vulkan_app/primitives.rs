@@ -0,0 +1,55 @@
+use cgmath::{Matrix4, Vector3};
+
+/// Sub-cubes kept at each subdivision of a Menger sponge.
+const MENGER_CUBES_PER_LEVEL: u64 = 20;
+
+/// Maps the cube mesh, which spans z in [-0.5, 0], onto the unit cube
+/// centred on the origin, so instance transforms can assume a unit cube.
+pub fn cube_mesh_to_unit() -> Matrix4<f32> {
+    Matrix4::from_nonuniform_scale(1.0, 1.0, 2.0)
+        * Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.25))
+}
+
+/// Instances in a level `level` sponge, saturating for absurd levels.
+pub fn menger_instance_count(level: u32) -> u64 {
+    MENGER_CUBES_PER_LEVEL
+        .checked_pow(level)
+        .unwrap_or(u64::MAX)
+}
+
+/// The highest level up to `requested` whose instance count fits `budget`.
+pub fn menger_level_within(requested: u32, budget: u64) -> u32 {
+    (0..=requested)
+        .rev()
+        .find(|&level| menger_instance_count(level) <= budget)
+        .unwrap_or(0)
+}
+
+/// Transforms placing one unit cube per solid cell of a level `level`
+/// Menger sponge that fills the unit cube centred on the origin: 20^level
+/// instances, each scaled to 1/3^level. Level 0 is the cube itself.
+pub fn menger_sponge(level: u32) -> Vec<Matrix4<f32>> {
+    let mut cells = vec![(Vector3::new(0.0f32, 0.0, 0.0), 1.0f32)];
+    for _ in 0..level {
+        cells = cells
+            .iter()
+            .flat_map(|&(center, size)| {
+                let step = size / 3.0;
+                subcube_offsets().map(move |offset| (center + offset * step, step))
+            })
+            .collect();
+    }
+    cells
+        .into_iter()
+        .map(|(center, size)| Matrix4::from_translation(center) * Matrix4::from_scale(size))
+        .collect()
+}
+
+/// Offsets, in sub-cube widths, of the 20 sub-cubes a sponge cell keeps:
+/// all 27 except the centre and the six face centres.
+fn subcube_offsets() -> impl Iterator<Item = Vector3<f32>> {
+    (0..27)
+        .map(|i| [i % 3 - 1, i / 3 % 3 - 1, i / 9 - 1])
+        .filter(|axes| axes.iter().filter(|&&axis| axis == 0).count() < 2)
+        .map(|[x, y, z]| Vector3::new(x as f32, y as f32, z as f32))
+}
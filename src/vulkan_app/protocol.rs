@@ -0,0 +1,120 @@
+//! Wire format of `--serve`.
+//!
+//! Server to client, one message per frame:
+//!   u32 length of the rest of the message, then u8 format, u32 width,
+//!   u32 height and the payload, all big endian.
+//! Client to server, newline-terminated text commands:
+//!   `orbit <yaw> <pitch>` (radians), `pause`, `screenshot`.
+
+use std::io::{self, Read, Write};
+
+/// Bytes of format, width and height ahead of the payload.
+const FRAME_HEADER_LEN: u32 = 9;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameFormat {
+    /// Tightly packed 8-bit RGBA rows, top to bottom.
+    Rgba,
+    Png,
+}
+
+impl FrameFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "rgba" => Some(FrameFormat::Rgba),
+            "png" => Some(FrameFormat::Png),
+            _ => None,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            FrameFormat::Rgba => 0,
+            FrameFormat::Png => 1,
+        }
+    }
+
+    #[allow(dead_code)] // Only clients decode frames.
+    fn from_byte(byte: u8) -> io::Result<Self> {
+        match byte {
+            0 => Ok(FrameFormat::Rgba),
+            1 => Ok(FrameFormat::Png),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown frame format {}", byte),
+            )),
+        }
+    }
+}
+
+pub struct EncodedFrame {
+    pub format: FrameFormat,
+    pub width: u32,
+    pub height: u32,
+    pub payload: Vec<u8>,
+}
+
+impl EncodedFrame {
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let len = FRAME_HEADER_LEN + self.payload.len() as u32;
+        writer.write_all(&len.to_be_bytes())?;
+        writer.write_all(&[self.format.to_byte()])?;
+        writer.write_all(&self.width.to_be_bytes())?;
+        writer.write_all(&self.height.to_be_bytes())?;
+        writer.write_all(&self.payload)?;
+        writer.flush()
+    }
+
+    #[allow(dead_code)] // Only clients decode frames.
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let len = read_u32(reader)?;
+        if len < FRAME_HEADER_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("frame message of {} bytes is shorter than its header", len),
+            ));
+        }
+        let mut format = [0u8];
+        reader.read_exact(&mut format)?;
+        let format = FrameFormat::from_byte(format[0])?;
+        let width = read_u32(reader)?;
+        let height = read_u32(reader)?;
+        let mut payload = vec![0u8; (len - FRAME_HEADER_LEN) as usize];
+        reader.read_exact(&mut payload)?;
+        Ok(Self {
+            format,
+            width,
+            height,
+            payload,
+        })
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Orbit { yaw: f32, pitch: f32 },
+    Pause,
+    Screenshot,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut words = line.split_whitespace();
+        let command = match words.next()? {
+            "orbit" => Command::Orbit {
+                yaw: words.next()?.parse().ok()?,
+                pitch: words.next()?.parse().ok()?,
+            },
+            "pause" => Command::Pause,
+            "screenshot" => Command::Screenshot,
+            _ => return None,
+        };
+        words.next().is_none().then_some(command)
+    }
+}
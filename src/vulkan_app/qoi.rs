@@ -0,0 +1,79 @@
+//! "Quite OK Image" encoder for RGBA8: lossless, and several times faster
+//! to write than PNG, which makes it the cheaper screenshot format.
+//! See https://qoiformat.org/qoi-specification.pdf.
+
+const OP_INDEX: u8 = 0x00;
+const OP_DIFF: u8 = 0x40;
+const OP_LUMA: u8 = 0x80;
+const OP_RUN: u8 = 0xC0;
+const OP_RGB: u8 = 0xFE;
+const OP_RGBA: u8 = 0xFF;
+const MAX_RUN: u8 = 62;
+const END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+pub fn encode_rgba(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    assert_eq!(rgba.len(), width as usize * height as usize * 4);
+
+    let mut out = Vec::with_capacity(14 + rgba.len() / 2 + END_MARKER.len());
+    out.extend_from_slice(b"qoif");
+    out.extend_from_slice(&width.to_be_bytes());
+    out.extend_from_slice(&height.to_be_bytes());
+    // Four channels, sRGB with linear alpha.
+    out.extend_from_slice(&[4, 0]);
+
+    let mut seen = [[0u8; 4]; 64];
+    let mut previous = [0u8, 0, 0, 255];
+    let mut run = 0u8;
+    for pixel in rgba.chunks_exact(4) {
+        let pixel = [pixel[0], pixel[1], pixel[2], pixel[3]];
+        if pixel == previous {
+            run += 1;
+            if run == MAX_RUN {
+                out.push(OP_RUN | (run - 1));
+                run = 0;
+            }
+            continue;
+        }
+        if run > 0 {
+            out.push(OP_RUN | (run - 1));
+            run = 0;
+        }
+
+        let hash = (pixel[0] as usize * 3
+            + pixel[1] as usize * 5
+            + pixel[2] as usize * 7
+            + pixel[3] as usize * 11)
+            % 64;
+        if seen[hash] == pixel {
+            out.push(OP_INDEX | hash as u8);
+        } else if pixel[3] == previous[3] {
+            let dr = pixel[0].wrapping_sub(previous[0]) as i8;
+            let dg = pixel[1].wrapping_sub(previous[1]) as i8;
+            let db = pixel[2].wrapping_sub(previous[2]) as i8;
+            let dr_dg = dr.wrapping_sub(dg);
+            let db_dg = db.wrapping_sub(dg);
+            if (-2..2).contains(&dr) && (-2..2).contains(&dg) && (-2..2).contains(&db) {
+                out.push(
+                    OP_DIFF | (((dr + 2) as u8) << 4) | (((dg + 2) as u8) << 2) | (db + 2) as u8,
+                );
+            } else if (-32..32).contains(&dg)
+                && (-8..8).contains(&dr_dg)
+                && (-8..8).contains(&db_dg)
+            {
+                out.push(OP_LUMA | (dg + 32) as u8);
+                out.push((((dr_dg + 8) as u8) << 4) | (db_dg + 8) as u8);
+            } else {
+                out.extend_from_slice(&[OP_RGB, pixel[0], pixel[1], pixel[2]]);
+            }
+        } else {
+            out.extend_from_slice(&[OP_RGBA, pixel[0], pixel[1], pixel[2], pixel[3]]);
+        }
+        seen[hash] = pixel;
+        previous = pixel;
+    }
+    if run > 0 {
+        out.push(OP_RUN | (run - 1));
+    }
+    out.extend_from_slice(&END_MARKER);
+    out
+}
@@ -1,7 +1,13 @@
+use ash::vk;
+
 #[derive(Clone, Copy)]
 pub struct QueueFamilyIndices {
     pub graphics_family: Option<u32>,
     pub present_family: Option<u32>,
+    /// A family with TRANSFER but not GRAPHICS, for uploads that overlap
+    /// rendering. Optional: without one, uploads go through the graphics
+    /// queue.
+    pub transfer_family: Option<u32>,
 }
 
 impl QueueFamilyIndices {
@@ -9,6 +15,7 @@ impl QueueFamilyIndices {
         Self {
             graphics_family: None,
             present_family: None,
+            transfer_family: None,
         }
     }
 
@@ -16,3 +23,12 @@ impl QueueFamilyIndices {
         self.graphics_family.is_some() && self.present_family.is_some()
     }
 }
+
+/// The dedicated transfer queue and the command pool uploads on it are
+/// recorded from.
+#[derive(Clone, Copy)]
+pub struct TransferQueue {
+    pub family: u32,
+    pub queue: vk::Queue,
+    pub command_pool: vk::CommandPool,
+}
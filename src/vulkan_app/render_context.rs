@@ -0,0 +1,325 @@
+use ash::extensions::khr;
+use ash::vk;
+use log::warn;
+
+use super::context::VkContext;
+use super::error::{Stage, VulkanError};
+use super::extra_window::ExtraWindow;
+use super::frame::{FrameIndex, FrameResources};
+use super::mapped_buffer::MappedBuffer;
+use super::memory::ResourceClass;
+use super::mesh_pool::MeshPool;
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::MAX_FRAMES_IN_FLIGHT;
+
+/// What each frame in flight needs of its own on the device: a command
+/// buffer, the fence its submission signals, and the objects' transforms,
+/// per instance. The buffer is None until the first frame has objects.
+struct DeviceFrame {
+    command_buffer: vk::CommandBuffer,
+    in_flight_fence: vk::Fence,
+    transforms: Option<MappedBuffer>,
+}
+
+/// Another GPU, for the windows `--gpu-for-window` puts on it: a device of
+/// its own, and copies of what the scene is drawn from. Only CPU-side
+/// scene data is shared with the main device; the mesh pool's contents are
+/// copied over whenever they change, and the objects' transforms every
+/// frame. Its windows draw the main frame's draw list in their vertex
+/// colours, unlit, and are submitted on its own queue after the main frame.
+pub struct RenderContext {
+    pub context: VkContext,
+    pub swapchain_loader: khr::Swapchain,
+    pub depth_format: vk::Format,
+    /// The view block, binding 0, as `ExtraWindow` sets are allocated with.
+    pub view_set_layout: vk::DescriptorSetLayout,
+    /// The view set, then the draw's model matrix as a push constant.
+    pub pipeline_layout: vk::PipelineLayout,
+    pub resources: ResourceRegistry,
+    command_pool: vk::CommandPool,
+    frames: FrameResources<DeviceFrame, MAX_FRAMES_IN_FLIGHT>,
+    vertex_buffer: MappedBuffer,
+    index_buffer: MappedBuffer,
+    // The mesh pool revision the buffers hold, None before the first copy.
+    mirrored_revision: Option<u64>,
+}
+
+impl RenderContext {
+    /// Layouts, per-frame objects and mesh buffers as large as `meshes`'
+    /// for `context`'s device. Destroys `context` if they can't be made.
+    pub fn new(context: VkContext, meshes: &MeshPool) -> Result<Self, VulkanError> {
+        let mut resources = ResourceRegistry::default();
+        let (vertex_buffer, index_buffer) =
+            match Self::create_mesh_buffers(&context, meshes, &mut resources) {
+                Ok(buffers) => buffers,
+                Err(error) => {
+                    unsafe { context.destroy() };
+                    return Err(error);
+                }
+            };
+        let device = &context.device;
+        let swapchain_loader = khr::Swapchain::new(&context.instance, device);
+        let depth_format = context.find_depth_format();
+
+        let view_binding = vk::DescriptorSetLayoutBinding::builder()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT);
+        let layout_info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(std::slice::from_ref(&view_binding));
+        let view_set_layout = unsafe {
+            device
+                .create_descriptor_set_layout(&layout_info, None)
+                .unwrap()
+        };
+        let push_constant_range = vk::PushConstantRange::builder()
+            .stage_flags(vk::ShaderStageFlags::VERTEX)
+            .offset(0)
+            .size(std::mem::size_of::<[[f32; 4]; 4]>() as u32);
+        let pipeline_layout_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(std::slice::from_ref(&view_set_layout))
+            .push_constant_ranges(std::slice::from_ref(&push_constant_range));
+        let pipeline_layout = unsafe {
+            device
+                .create_pipeline_layout(&pipeline_layout_info, None)
+                .unwrap()
+        };
+
+        let pool_info = vk::CommandPoolCreateInfo::builder()
+            .queue_family_index(context.queue_family_indices.graphics_family.unwrap())
+            .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER);
+        let command_pool = unsafe { device.create_command_pool(&pool_info, None).unwrap() };
+        let alloc_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(MAX_FRAMES_IN_FLIGHT as u32);
+        let command_buffers = unsafe { device.allocate_command_buffers(&alloc_info).unwrap() };
+        // Signalled, so the first wait on each frame returns at once.
+        let fence_info = vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED);
+        let frames = FrameResources::new(std::array::from_fn(|i| DeviceFrame {
+            command_buffer: command_buffers[i],
+            in_flight_fence: unsafe { device.create_fence(&fence_info, None).unwrap() },
+            transforms: None,
+        }));
+
+        Ok(Self {
+            swapchain_loader,
+            depth_format,
+            view_set_layout,
+            pipeline_layout,
+            resources,
+            command_pool,
+            frames,
+            vertex_buffer,
+            index_buffer,
+            mirrored_revision: None,
+            context,
+        })
+    }
+
+    /// Buffers as large as the pool's, for its vertices and indices.
+    fn create_mesh_buffers(
+        context: &VkContext,
+        meshes: &MeshPool,
+        resources: &mut ResourceRegistry,
+    ) -> Result<(MappedBuffer, MappedBuffer), VulkanError> {
+        let (vertex_bytes, index_bytes) = meshes.contents();
+        let mut create_buffer = |class, size: usize, usage, name| {
+            let size = size as vk::DeviceSize;
+            let (buffer, allocation) = context.create_buffer(class, size, usage)?;
+            resources.register(ResourceKind::Buffer, buffer, name, size);
+            Ok::<_, VulkanError>(MappedBuffer::new(buffer, allocation, size))
+        };
+        let vertex_buffer = create_buffer(
+            ResourceClass::Vertex,
+            vertex_bytes.len(),
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            "device mesh vertex buffer",
+        )?;
+        match create_buffer(
+            ResourceClass::Index,
+            index_bytes.len(),
+            vk::BufferUsageFlags::INDEX_BUFFER,
+            "device mesh index buffer",
+        ) {
+            Ok(index_buffer) => Ok((vertex_buffer, index_buffer)),
+            Err(error) => {
+                resources.unregister(ResourceKind::Buffer, vertex_buffer.buffer);
+                vertex_buffer.destroy(&context.device, &context.allocator);
+                Err(error)
+            }
+        }
+    }
+
+    /// Waits for the last submission `frame` made on the device, so its
+    /// command buffer, transforms and windows' semaphores can be reused.
+    pub fn wait(&self, frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>) -> Result<(), VulkanError> {
+        unsafe {
+            self.context.device.wait_for_fences(
+                std::slice::from_ref(&self.frames[frame].in_flight_fence),
+                true,
+                u64::MAX,
+            )
+        }
+        .map_err(VulkanError::at(Stage::Submit))
+    }
+
+    /// Copies `meshes` over if it changed since the last copy, once the
+    /// device has finished drawing from the old one.
+    pub fn mirror_meshes(&mut self, meshes: &MeshPool) -> Result<(), VulkanError> {
+        if self.mirrored_revision == Some(meshes.revision) {
+            return Ok(());
+        }
+        unsafe { self.context.device.device_wait_idle() }
+            .map_err(VulkanError::at(Stage::Submit))?;
+        let (vertices, indices) = meshes.contents();
+        self.vertex_buffer.write_slice(0, vertices);
+        self.index_buffer.write_slice(0, indices);
+        self.mirrored_revision = Some(meshes.revision);
+        Ok(())
+    }
+
+    /// Writes the transforms `frame` draws with, one per object, growing
+    /// its buffer if they don't fit. `wait` must have returned for `frame`.
+    pub fn write_transforms(
+        &mut self,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        transforms: &[[[f32; 4]; 4]],
+    ) -> Result<(), VulkanError> {
+        let size = std::mem::size_of_val(transforms) as vk::DeviceSize;
+        if size == 0 {
+            return Ok(());
+        }
+        let device_frame = &mut self.frames[frame];
+        if device_frame
+            .transforms
+            .as_ref()
+            .is_none_or(|buffer| buffer.size() < size)
+        {
+            if let Some(old) = device_frame.transforms.take() {
+                self.resources.unregister(ResourceKind::Buffer, old.buffer);
+                old.destroy(&self.context.device, &self.context.allocator);
+            }
+            let (buffer, allocation) = self.context.create_buffer(
+                ResourceClass::Vertex,
+                size,
+                vk::BufferUsageFlags::VERTEX_BUFFER,
+            )?;
+            self.resources.register(
+                ResourceKind::Buffer,
+                buffer,
+                "device instance transforms",
+                size,
+            );
+            device_frame.transforms = Some(MappedBuffer::new(buffer, allocation, size));
+        }
+        if let Some(buffer) = &device_frame.transforms {
+            buffer.write_slice(0, transforms);
+        }
+        Ok(())
+    }
+
+    /// The mesh vertices, then the frame's transforms, for binding 0 and 1.
+    pub fn vertex_buffers(
+        &self,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+    ) -> Option<[vk::Buffer; 2]> {
+        let transforms = self.frames[frame].transforms.as_ref()?;
+        Some([self.vertex_buffer.buffer, transforms.buffer])
+    }
+
+    pub fn index_buffer(&self) -> vk::Buffer {
+        self.index_buffer.buffer
+    }
+
+    /// Records `frame`'s command buffer with `record` and submits it on the
+    /// graphics queue, waiting for each of `windows`' images to be acquired
+    /// and signalling what their presents wait on. Nothing if none of them
+    /// acquired one.
+    pub fn submit(
+        &self,
+        frame: FrameIndex<MAX_FRAMES_IN_FLIGHT>,
+        windows: &[&ExtraWindow],
+        record: impl FnOnce(vk::CommandBuffer),
+    ) -> Result<(), VulkanError> {
+        let mut wait_semaphores = Vec::new();
+        let mut signal_semaphores = Vec::new();
+        for extra in windows.iter().filter(|extra| extra.acquired.is_some()) {
+            let (image_available, render_finished) = extra.semaphores(frame);
+            wait_semaphores.push(image_available);
+            signal_semaphores.push(render_finished);
+        }
+        if wait_semaphores.is_empty() {
+            return Ok(());
+        }
+        let device = &self.context.device;
+        let device_frame = &self.frames[frame];
+        let command_buffer = device_frame.command_buffer;
+        unsafe {
+            device
+                .reset_command_buffer(command_buffer, vk::CommandBufferResetFlags::empty())
+                .map_err(VulkanError::at(Stage::Submit))?;
+            let begin_info = vk::CommandBufferBeginInfo::builder()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+            device
+                .begin_command_buffer(command_buffer, &begin_info)
+                .map_err(VulkanError::at(Stage::Submit))?;
+            record(command_buffer);
+            device
+                .end_command_buffer(command_buffer)
+                .map_err(VulkanError::at(Stage::Submit))?;
+
+            let wait_stages =
+                vec![vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT; wait_semaphores.len()];
+            let submit_info = vk::SubmitInfo::builder()
+                .command_buffers(std::slice::from_ref(&command_buffer))
+                .wait_semaphores(&wait_semaphores)
+                .wait_dst_stage_mask(&wait_stages)
+                .signal_semaphores(&signal_semaphores);
+            device
+                .reset_fences(std::slice::from_ref(&device_frame.in_flight_fence))
+                .map_err(VulkanError::at(Stage::Submit))?;
+            device
+                .queue_submit(
+                    self.context.graphics_queue,
+                    std::slice::from_ref(&submit_info),
+                    device_frame.in_flight_fence,
+                )
+                .map_err(VulkanError::at(Stage::Submit))?;
+        }
+        Ok(())
+    }
+
+    /// Destroys everything, the device last. Its windows must already be
+    /// destroyed and the device idle. Anything its windows or pipelines
+    /// left registered is reported as leaked.
+    pub fn destroy(mut self) {
+        let device = &self.context.device;
+        unsafe {
+            for frame in self.frames.iter_mut() {
+                device.destroy_fence(frame.in_flight_fence, None);
+                if let Some(transforms) = frame.transforms.take() {
+                    self.resources
+                        .unregister(ResourceKind::Buffer, transforms.buffer);
+                    transforms.destroy(device, &self.context.allocator);
+                }
+            }
+            device.destroy_command_pool(self.command_pool, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            device.destroy_descriptor_set_layout(self.view_set_layout, None);
+        }
+        for buffer in [&self.vertex_buffer, &self.index_buffer] {
+            self.resources
+                .unregister(ResourceKind::Buffer, buffer.buffer);
+            buffer.destroy(device, &self.context.allocator);
+        }
+        for leak in self.resources.records() {
+            warn!(
+                "Leaked {:?} '{}' on another GPU ({} bytes, created at frame {})",
+                leak.kind, leak.name, leak.size, leak.created_frame
+            );
+        }
+        unsafe { self.context.destroy() };
+    }
+}
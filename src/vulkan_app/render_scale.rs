@@ -0,0 +1,294 @@
+use ash::vk;
+
+use super::allocator::{Allocation, Allocator};
+use super::context::VkContext;
+use super::error::VulkanError;
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::{
+    RENDER_SCALE_HEADROOM, RENDER_SCALE_MAX_PERCENT, RENDER_SCALE_MIN_PERCENT,
+    RENDER_SCALE_SAMPLES, RENDER_SCALE_STEP_PERCENT,
+};
+
+/// Picks the percentage of the window's size the scene renders at. With a
+/// target frame time it steps down while the frames average over it, and
+/// back up while the next step's predicted time has RENDER_SCALE_HEADROOM
+/// to spare. Each decision averages only frames since the last change, so
+/// one step's effect is measured before the next. Stepping by hand pins the
+/// scale until it's released, and so does jumping to a scale, which can go
+/// past full scale to supersample.
+pub struct RenderScaler {
+    target_ms: Option<f32>,
+    percent: u32,
+    pinned: bool,
+    // Frame times since the last change, or the last decision.
+    sum_ms: f32,
+    samples: u32,
+}
+
+impl RenderScaler {
+    /// At full scale, adapting to `target_ms` if given.
+    pub fn new(target_ms: Option<f32>) -> Self {
+        Self {
+            target_ms,
+            percent: 100,
+            pinned: false,
+            sum_ms: 0.0,
+            samples: 0,
+        }
+    }
+
+    pub fn percent(&self) -> u32 {
+        self.percent
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// Counts a frame that took `frame_ms`. Returns the new percentage when
+    /// the average calls for a step.
+    pub fn push(&mut self, frame_ms: f32) -> Option<u32> {
+        let target_ms = self.target_ms.filter(|_| !self.pinned)?;
+        self.sum_ms += frame_ms;
+        self.samples += 1;
+        if self.samples < RENDER_SCALE_SAMPLES {
+            return None;
+        }
+        let average = self.sum_ms / self.samples as f32;
+        self.sum_ms = 0.0;
+        self.samples = 0;
+        let up = (self.percent + RENDER_SCALE_STEP_PERCENT).min(100);
+        // Frame time is taken to follow the pixel count.
+        let predicted = average * (up as f32 / self.percent as f32).powi(2);
+        let percent = if average > target_ms {
+            self.percent
+                .saturating_sub(RENDER_SCALE_STEP_PERCENT)
+                .max(RENDER_SCALE_MIN_PERCENT)
+        } else if predicted < target_ms * RENDER_SCALE_HEADROOM {
+            up
+        } else {
+            self.percent
+        };
+        self.set(percent)
+    }
+
+    /// Steps up for positive `steps`, down for negative, and pins the
+    /// result. Never steps past full scale, though it may step down from
+    /// above it. Returns the new percentage if it changed.
+    pub fn nudge(&mut self, steps: i32) -> Option<u32> {
+        self.pinned = true;
+        let percent = self.percent as i32 + steps * RENDER_SCALE_STEP_PERCENT as i32;
+        let max = self.percent.max(100) as i32;
+        self.set(percent.clamp(RENDER_SCALE_MIN_PERCENT as i32, max) as u32)
+    }
+
+    /// Pins the scale at `percent`, within RENDER_SCALE_MIN_PERCENT and
+    /// RENDER_SCALE_MAX_PERCENT. Returns the new percentage if it changed.
+    pub fn jump(&mut self, percent: u32) -> Option<u32> {
+        self.pinned = true;
+        self.set(percent.clamp(RENDER_SCALE_MIN_PERCENT, RENDER_SCALE_MAX_PERCENT))
+    }
+
+    /// Lets `--adaptive-resolution` pick the scale again.
+    pub fn unpin(&mut self) {
+        self.pinned = false;
+        self.sum_ms = 0.0;
+        self.samples = 0;
+    }
+
+    fn set(&mut self, percent: u32) -> Option<u32> {
+        if percent == self.percent {
+            return None;
+        }
+        self.percent = percent;
+        self.sum_ms = 0.0;
+        self.samples = 0;
+        Some(percent)
+    }
+}
+
+/// Whether frames rendered below full scale can be blitted onto swapchain
+/// images whose format has `format_features`, created with `usage`.
+pub fn can_upscale(format_features: vk::FormatFeatureFlags, usage: vk::ImageUsageFlags) -> bool {
+    format_features.contains(
+        vk::FormatFeatureFlags::BLIT_SRC
+            | vk::FormatFeatureFlags::BLIT_DST
+            | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+    ) && usage.contains(vk::ImageUsageFlags::TRANSFER_DST)
+}
+
+/// What the scene pass renders into away from full scale, in place of one
+/// swapchain image, which `record_upscale` then stretches or shrinks it
+/// over.
+pub struct ScaledTarget {
+    pub image: vk::Image,
+    pub allocation: Allocation,
+    pub view: vk::ImageView,
+}
+
+impl ScaledTarget {
+    /// `usage` is the swapchain's, so the target can be left in any layout
+    /// the swapchain images could.
+    pub fn new(
+        context: &VkContext,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        usage: vk::ImageUsageFlags,
+        resources: &mut ResourceRegistry,
+    ) -> Result<Self, VulkanError> {
+        let (image, allocation) = context.create_image(
+            extent.width,
+            extent.height,
+            vk::SampleCountFlags::TYPE_1,
+            format,
+            vk::ImageTiling::OPTIMAL,
+            usage | vk::ImageUsageFlags::TRANSFER_SRC,
+        )?;
+        let view = context.create_image_view(image, format, vk::ImageAspectFlags::COLOR);
+        resources.register(
+            ResourceKind::Image,
+            image,
+            "scaled render target",
+            allocation.size,
+        );
+        resources.register(
+            ResourceKind::ImageView,
+            view,
+            "scaled render target view",
+            0,
+        );
+        Ok(Self {
+            image,
+            allocation,
+            view,
+        })
+    }
+
+    /// Records a linear blit of `extent` of the target, which the scene
+    /// pass left in `layout`, over `dst_extent` of `swapchain_image`, which
+    /// is then left in `layout` too, as if the scene pass had drawn it.
+    pub fn record_upscale(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        swapchain_image: vk::Image,
+        extent: vk::Extent2D,
+        dst_extent: vk::Extent2D,
+        layout: vk::ImageLayout,
+    ) {
+        let barrier = |image, old_layout, new_layout, src_access_mask, dst_access_mask| {
+            vk::ImageMemoryBarrier::builder()
+                .src_access_mask(src_access_mask)
+                .dst_access_mask(dst_access_mask)
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .build()
+        };
+        let subresource = vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let corner = |extent: vk::Extent2D| vk::Offset3D {
+            x: extent.width as i32,
+            y: extent.height as i32,
+            z: 1,
+        };
+        let blit = vk::ImageBlit::builder()
+            .src_subresource(subresource)
+            .src_offsets([vk::Offset3D::default(), corner(extent)])
+            .dst_subresource(subresource)
+            .dst_offsets([vk::Offset3D::default(), corner(dst_extent)])
+            .build();
+        unsafe {
+            // The swapchain image's old contents go; the acquire semaphore
+            // is waited on at colour attachment output, which this follows.
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[
+                    barrier(
+                        self.image,
+                        layout,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                        vk::AccessFlags::TRANSFER_READ,
+                    ),
+                    barrier(
+                        swapchain_image,
+                        vk::ImageLayout::UNDEFINED,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::AccessFlags::empty(),
+                        vk::AccessFlags::TRANSFER_WRITE,
+                    ),
+                ],
+            );
+            device.cmd_blit_image(
+                command_buffer,
+                self.image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                swapchain_image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                std::slice::from_ref(&blit),
+                vk::Filter::LINEAR,
+            );
+            // Later copies and transitions of the swapchain image wait on
+            // colour attachment output, as after the scene pass, and so does
+            // the next scene pass into the target, after the blit's read.
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(
+                    swapchain_image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    layout,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                )],
+            );
+        }
+    }
+
+    pub fn destroy(
+        &self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        resources: &mut ResourceRegistry,
+    ) {
+        resources.unregister(ResourceKind::ImageView, self.view);
+        resources.unregister(ResourceKind::Image, self.image);
+        unsafe {
+            device.destroy_image_view(self.view, None);
+            device.destroy_image(self.image, None);
+        }
+        allocator.free(self.allocation);
+    }
+}
+
+/// `extent` scaled to `percent`, at least a pixel each way.
+pub fn scaled_extent(extent: vk::Extent2D, percent: u32) -> vk::Extent2D {
+    let scale = |size: u32| ((size as u64 * percent as u64 + 50) / 100).max(1) as u32;
+    vk::Extent2D {
+        width: scale(extent.width),
+        height: scale(extent.height),
+    }
+}
@@ -0,0 +1,88 @@
+use ash::vk;
+
+use super::capabilities::DynamicRenderingSupport;
+use super::depth::DepthDirection;
+
+/// Begins and ends rendering without a render pass, with Vulkan 1.3's
+/// commands or the extension's.
+pub enum DynamicRenderer {
+    Core,
+    Extension(ash::extensions::khr::DynamicRendering),
+}
+
+impl DynamicRenderer {
+    pub fn new(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        support: DynamicRenderingSupport,
+    ) -> Self {
+        match support {
+            DynamicRenderingSupport::Core => DynamicRenderer::Core,
+            DynamicRenderingSupport::Extension => DynamicRenderer::Extension(
+                ash::extensions::khr::DynamicRendering::new(instance, device),
+            ),
+        }
+    }
+
+    pub fn begin(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        rendering_info: &vk::RenderingInfo,
+    ) {
+        unsafe {
+            match self {
+                DynamicRenderer::Core => device.cmd_begin_rendering(command_buffer, rendering_info),
+                DynamicRenderer::Extension(loader) => {
+                    loader.cmd_begin_rendering(command_buffer, rendering_info)
+                }
+            }
+        }
+    }
+
+    pub fn end(&self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        unsafe {
+            match self {
+                DynamicRenderer::Core => device.cmd_end_rendering(command_buffer),
+                DynamicRenderer::Extension(loader) => loader.cmd_end_rendering(command_buffer),
+            }
+        }
+    }
+}
+
+/// What the scene's pipelines and secondary buffers are built against: the
+/// render pass, or with dynamic rendering (a null `render_pass`) the
+/// attachments' formats and sample count alone.
+#[derive(Clone, Copy)]
+pub struct PassTarget {
+    pub render_pass: vk::RenderPass,
+    pub color_format: vk::Format,
+    pub depth_format: vk::Format,
+    pub samples: vk::SampleCountFlags,
+    /// Which way the scene's depth tests compare.
+    pub depth_direction: DepthDirection,
+}
+
+impl PassTarget {
+    pub fn is_dynamic(&self) -> bool {
+        self.render_pass == vk::RenderPass::null()
+    }
+
+    /// For a pipeline's pNext chain on the dynamic rendering path.
+    pub fn pipeline_rendering_info(&self) -> vk::PipelineRenderingCreateInfoBuilder<'_> {
+        vk::PipelineRenderingCreateInfo::builder()
+            .color_attachment_formats(std::slice::from_ref(&self.color_format))
+            .depth_attachment_format(self.depth_format)
+    }
+
+    /// For a secondary buffer's inheritance info on the dynamic rendering
+    /// path.
+    pub fn inheritance_rendering_info(
+        &self,
+    ) -> vk::CommandBufferInheritanceRenderingInfoBuilder<'_> {
+        vk::CommandBufferInheritanceRenderingInfo::builder()
+            .color_attachment_formats(std::slice::from_ref(&self.color_format))
+            .depth_attachment_format(self.depth_format)
+            .rasterization_samples(self.samples)
+    }
+}
@@ -0,0 +1,86 @@
+use ash::vk;
+use ash::vk::Handle;
+use log::warn;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ResourceKind {
+    Buffer,
+    Image,
+    ImageView,
+    Pipeline,
+    DescriptorPool,
+    Sampler,
+}
+
+#[derive(Clone, Debug)]
+pub struct ResourceRecord {
+    pub name: String,
+    pub kind: ResourceKind,
+    pub size: vk::DeviceSize,
+    pub created_frame: u64,
+}
+
+/// Live GPU objects, registered on creation and removed on destruction so that
+/// anything still present at teardown can be reported as a leak.
+#[derive(Default)]
+pub struct ResourceRegistry {
+    records: Vec<(u64, ResourceRecord)>,
+    frame: u64,
+}
+
+impl ResourceRegistry {
+    pub fn set_frame(&mut self, frame: u64) {
+        self.frame = frame;
+    }
+
+    pub fn register(
+        &mut self,
+        kind: ResourceKind,
+        handle: impl Handle,
+        name: &str,
+        size: vk::DeviceSize,
+    ) {
+        self.records.push((
+            handle.as_raw(),
+            ResourceRecord {
+                name: name.to_string(),
+                kind,
+                size,
+                created_frame: self.frame,
+            },
+        ));
+    }
+
+    pub fn unregister(&mut self, kind: ResourceKind, handle: impl Handle) {
+        let raw = handle.as_raw();
+        match self
+            .records
+            .iter()
+            .position(|(h, record)| *h == raw && record.kind == kind)
+        {
+            Some(pos) => {
+                self.records.remove(pos);
+            }
+            None => warn!("Destroying untracked {:?} {:#x}", kind, raw),
+        }
+    }
+
+    pub fn records(&self) -> Vec<ResourceRecord> {
+        self.records
+            .iter()
+            .map(|(_, record)| record.clone())
+            .collect()
+    }
+
+    pub fn counts_by_kind(&self) -> Vec<(ResourceKind, usize)> {
+        let mut counts: Vec<(ResourceKind, usize)> = Vec::new();
+        for (_, record) in self.records.iter() {
+            match counts.iter_mut().find(|(kind, _)| *kind == record.kind) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((record.kind, 1)),
+            }
+        }
+        counts.sort();
+        counts
+    }
+}
@@ -0,0 +1,356 @@
+use ash::vk;
+use std::collections::VecDeque;
+
+use super::allocator::{Allocation, Allocator};
+use super::capabilities::DeviceLimits;
+use super::context::{self, VkContext};
+use super::error::VulkanError;
+use super::render_scale::scaled_extent;
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::{MAX_FRAMES_IN_FLIGHT, RENDER_SCALE_LADDER, SCALE_TIME_SAMPLES};
+
+/// GPU frame times at each step of RENDER_SCALE_LADDER, for the HUD to
+/// compare the steps by. The frames in flight when the scale changes were
+/// recorded at the old one, so the first MAX_FRAMES_IN_FLIGHT times after a
+/// change are dropped, and so are times at scales off the ladder or with
+/// the split on, which are for two scales at once.
+pub struct ScaleTimes {
+    // The ladder step frames are recorded at, if any.
+    step: Option<usize>,
+    skip: usize,
+    times: [VecDeque<f32>; RENDER_SCALE_LADDER.len()],
+}
+
+impl Default for ScaleTimes {
+    fn default() -> Self {
+        Self {
+            step: None,
+            skip: 0,
+            times: std::array::from_fn(|_| VecDeque::with_capacity(SCALE_TIME_SAMPLES)),
+        }
+    }
+}
+
+impl ScaleTimes {
+    /// Frames from now on are recorded at `percent`, or at no single scale
+    /// with `None`.
+    pub fn set_scale(&mut self, percent: Option<u32>) {
+        let step =
+            percent.and_then(|percent| RENDER_SCALE_LADDER.iter().position(|&p| p == percent));
+        if step != self.step {
+            self.step = step;
+            self.skip = MAX_FRAMES_IN_FLIGHT;
+        }
+    }
+
+    /// Counts a frame's GPU time at the current scale.
+    pub fn push(&mut self, gpu_ms: f32) {
+        if self.skip > 0 {
+            self.skip -= 1;
+            return;
+        }
+        let Some(step) = self.step else {
+            return;
+        };
+        let times = &mut self.times[step];
+        if times.len() == SCALE_TIME_SAMPLES {
+            times.pop_front();
+        }
+        times.push_back(gpu_ms);
+    }
+
+    /// The average GPU time at `percent`, once it has been measured.
+    pub fn average_ms(&self, percent: u32) -> Option<f32> {
+        let step = RENDER_SCALE_LADDER.iter().position(|&p| p == percent)?;
+        let times = &self.times[step];
+        (!times.is_empty()).then(|| times.iter().sum::<f32>() / times.len() as f32)
+    }
+
+    /// A line per step: its key, the pixels it renders of a frame shown at
+    /// `display` on a device with `limits`, and its average GPU time. The
+    /// step at `current` is marked.
+    pub fn lines(&self, display: vk::Extent2D, limits: &DeviceLimits, current: u32) -> Vec<String> {
+        RENDER_SCALE_LADDER
+            .iter()
+            .enumerate()
+            .map(|(index, &percent)| {
+                let extent = limits.render_extent(scaled_extent(display, percent));
+                let megapixels = extent.width as f32 * extent.height as f32 / 1e6;
+                format!(
+                    "{} Ctrl+{} {:>3}% {:>5}x{:<5} {:>5.2} MP  {}",
+                    if percent == current { '>' } else { ' ' },
+                    index + 1,
+                    percent,
+                    extent.width,
+                    extent.height,
+                    megapixels,
+                    match self.average_ms(percent) {
+                        Some(ms) => format!("{:.2} ms", ms),
+                        None => "-".to_string(),
+                    }
+                )
+            })
+            .collect()
+    }
+}
+
+/// The blit that puts the right half of a frame rendered at `source` over
+/// the right half of one shown at `display`. The source's half starts at
+/// the column the display's middle column samples from, so both halves of
+/// the split show the scene at the same place.
+pub fn right_half_blit(source: vk::Extent2D, display: vk::Extent2D) -> vk::ImageBlit {
+    let split = display.width / 2;
+    let source_split = ((split as u64 * source.width as u64 + display.width as u64 / 2)
+        / display.width.max(1) as u64) as u32;
+    let subresource = vk::ImageSubresourceLayers {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        mip_level: 0,
+        base_array_layer: 0,
+        layer_count: 1,
+    };
+    let corner = |x: u32, y: u32, z: i32| vk::Offset3D {
+        x: x as i32,
+        y: y as i32,
+        z,
+    };
+    vk::ImageBlit::builder()
+        .src_subresource(subresource)
+        .src_offsets([
+            corner(source_split, 0, 0),
+            corner(source.width, source.height, 1),
+        ])
+        .dst_subresource(subresource)
+        .dst_offsets([
+            corner(split, 0, 0),
+            corner(display.width, display.height, 1),
+        ])
+        .build()
+}
+
+/// The right half of the A/B split: the scene drawn again at another scale
+/// into images of its own, single-sampled, always filled and without the
+/// background or overlays, like an extra window. `record_composite` puts
+/// its right half over the frame's.
+/// The render pass comes from the app, which also sets the pipelines; both
+/// are destroyed here.
+pub struct ComparisonTarget {
+    pub percent: u32,
+    pub extent: vk::Extent2D,
+    pub render_pass: vk::RenderPass,
+    /// Indexed by CUBE_PIPELINE and DOUBLE_SIDED_PIPELINE.
+    pub pipelines: [vk::Pipeline; 2],
+    pub framebuffer: vk::Framebuffer,
+    color: (vk::Image, Allocation, vk::ImageView),
+    depth: (vk::Image, Allocation, vk::ImageView),
+}
+
+impl ComparisonTarget {
+    /// A colour image of `format` and a depth image of `depth_format` at
+    /// `extent`, and a framebuffer for `render_pass` over them.
+    pub fn new(
+        context: &VkContext,
+        percent: u32,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        depth_format: vk::Format,
+        render_pass: vk::RenderPass,
+        resources: &mut ResourceRegistry,
+    ) -> Result<Self, VulkanError> {
+        let (color_image, color_allocation) = context.create_image(
+            extent.width,
+            extent.height,
+            vk::SampleCountFlags::TYPE_1,
+            format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+        )?;
+        let depth = context.create_image(
+            extent.width,
+            extent.height,
+            vk::SampleCountFlags::TYPE_1,
+            depth_format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT
+                | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+        );
+        let (depth_image, depth_allocation) = match depth {
+            Ok(depth) => depth,
+            Err(error) => {
+                unsafe { context.device.destroy_image(color_image, None) };
+                context.allocator.free(color_allocation);
+                return Err(error);
+            }
+        };
+        let color_view =
+            context.create_image_view(color_image, format, vk::ImageAspectFlags::COLOR);
+        let depth_view = context.create_image_view(
+            depth_image,
+            depth_format,
+            context::depth_aspect_flags(depth_format),
+        );
+        resources.register(
+            ResourceKind::Image,
+            color_image,
+            "comparison colour image",
+            color_allocation.size,
+        );
+        resources.register(
+            ResourceKind::ImageView,
+            color_view,
+            "comparison colour view",
+            0,
+        );
+        resources.register(
+            ResourceKind::Image,
+            depth_image,
+            "comparison depth image",
+            depth_allocation.size,
+        );
+        resources.register(
+            ResourceKind::ImageView,
+            depth_view,
+            "comparison depth view",
+            0,
+        );
+        let attachments = [color_view, depth_view];
+        let framebuffer_info = vk::FramebufferCreateInfo::builder()
+            .render_pass(render_pass)
+            .attachments(&attachments)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1);
+        let framebuffer = unsafe {
+            context
+                .device
+                .create_framebuffer(&framebuffer_info, None)
+                .unwrap()
+        };
+        Ok(Self {
+            percent,
+            extent,
+            render_pass,
+            pipelines: [vk::Pipeline::null(); 2],
+            framebuffer,
+            color: (color_image, color_allocation, color_view),
+            depth: (depth_image, depth_allocation, depth_view),
+        })
+    }
+
+    /// Records the blit of the target's right half, which its render pass
+    /// left ready to copy from, over the right half of `swapchain_image`,
+    /// which holds the frame shown at `display` in `layout` and is left in
+    /// `layout` again.
+    pub fn record_composite(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        swapchain_image: vk::Image,
+        display: vk::Extent2D,
+        layout: vk::ImageLayout,
+    ) {
+        let barrier = |image, old_layout, new_layout, src_access_mask, dst_access_mask| {
+            vk::ImageMemoryBarrier::builder()
+                .src_access_mask(src_access_mask)
+                .dst_access_mask(dst_access_mask)
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .build()
+        };
+        let blit = right_half_blit(self.extent, display);
+        unsafe {
+            // The frame's left half stays, so the swapchain image keeps its
+            // contents; it was last written by the scene pass or the
+            // upscale, which leaves it as colour attachment output.
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[
+                    barrier(
+                        self.color.0,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                        vk::AccessFlags::TRANSFER_READ,
+                    ),
+                    barrier(
+                        swapchain_image,
+                        layout,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                        vk::AccessFlags::TRANSFER_WRITE,
+                    ),
+                ],
+            );
+            device.cmd_blit_image(
+                command_buffer,
+                self.color.0,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                swapchain_image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                std::slice::from_ref(&blit),
+                vk::Filter::LINEAR,
+            );
+            // As after `ScaledTarget::record_upscale`, later work on the
+            // swapchain image waits on colour attachment output, and so does
+            // the next frame's pass into the target, after the blit's read.
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(
+                    swapchain_image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    layout,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                )],
+            );
+        }
+    }
+
+    /// Everything, the render pass and pipelines included. The device must
+    /// be idle.
+    pub fn destroy(
+        &self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        resources: &mut ResourceRegistry,
+    ) {
+        for pipeline in self.pipelines {
+            if pipeline != vk::Pipeline::null() {
+                resources.unregister(ResourceKind::Pipeline, pipeline);
+                unsafe { device.destroy_pipeline(pipeline, None) };
+            }
+        }
+        unsafe {
+            device.destroy_framebuffer(self.framebuffer, None);
+            device.destroy_render_pass(self.render_pass, None);
+        }
+        for (image, allocation, view) in [self.color, self.depth] {
+            resources.unregister(ResourceKind::ImageView, view);
+            resources.unregister(ResourceKind::Image, image);
+            unsafe {
+                device.destroy_image_view(view, None);
+                device.destroy_image(image, None);
+            }
+            allocator.free(allocation);
+        }
+    }
+}
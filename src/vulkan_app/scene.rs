@@ -0,0 +1,40 @@
+use cgmath::{Matrix4, Rad, Vector3};
+
+/// A cube drawn on its own, with its own draw constants: `transform`, then
+/// turned about `axis` through the origin. Off the axis, it orbits it.
+#[derive(Clone, Copy, Debug)]
+pub struct SceneObject {
+    pub transform: Matrix4<f32>,
+    /// Unit length.
+    pub axis: Vector3<f32>,
+    /// Radians per second of animation time.
+    pub angular_velocity: f32,
+}
+
+impl SceneObject {
+    /// The model matrix at `time` seconds of animation.
+    pub fn model(&self, time: f32) -> Matrix4<f32> {
+        Matrix4::from_axis_angle(self.axis, Rad(self.angular_velocity * time)) * self.transform
+    }
+}
+
+/// `count` cubes scaled by `scale`, evenly spaced on a circle of `radius`
+/// about Z, orbiting their common centre at `angular_velocity`.
+pub fn orbiting_cubes(
+    count: usize,
+    radius: f32,
+    scale: f32,
+    angular_velocity: f32,
+) -> Vec<SceneObject> {
+    (0..count)
+        .map(|index| {
+            let angle = std::f32::consts::TAU * index as f32 / count as f32;
+            let offset = Vector3::new(angle.cos(), angle.sin(), 0.0) * radius;
+            SceneObject {
+                transform: Matrix4::from_translation(offset) * Matrix4::from_scale(scale),
+                axis: Vector3::unit_z(),
+                angular_velocity,
+            }
+        })
+        .collect()
+}
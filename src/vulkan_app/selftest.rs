@@ -0,0 +1,149 @@
+use std::fmt;
+
+use super::capture::CapturedFrame;
+use super::SELF_TEST_HASH_TOLERANCE;
+
+pub enum CheckOutcome {
+    Pass(String),
+    Fail(String),
+    Skipped(String),
+}
+
+/// Results of `--self-test`: a handful of checks an end user can run to see
+/// whether their driver renders this scene correctly.
+#[derive(Default)]
+pub struct SelfTestReport {
+    checks: Vec<(&'static str, CheckOutcome)>,
+}
+
+impl SelfTestReport {
+    pub fn add(&mut self, name: &'static str, outcome: CheckOutcome) {
+        self.checks.push((name, outcome));
+    }
+
+    /// Adds `other`'s checks after these.
+    pub fn append(&mut self, other: SelfTestReport) {
+        self.checks.extend(other.checks);
+    }
+
+    /// Skipped checks don't count as failures.
+    pub fn passed(&self) -> bool {
+        !self
+            .checks
+            .iter()
+            .any(|(_, outcome)| matches!(outcome, CheckOutcome::Fail(_)))
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, outcome) in &self.checks {
+            let (label, detail) = match outcome {
+                CheckOutcome::Pass(detail) => ("PASS", detail),
+                CheckOutcome::Fail(detail) => ("FAIL", detail),
+                CheckOutcome::Skipped(detail) => ("SKIP", detail),
+            };
+            writeln!(f, "{} {}: {}", label, name, detail)?;
+        }
+        writeln!(
+            f,
+            "Self-test {}",
+            if self.passed() { "passed" } else { "failed" }
+        )
+    }
+}
+
+/// A frame `--self-test` renders headless at SELF_TEST_SIZE, with the
+/// animation at time 0 and no settings or flags applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CanonicalScene {
+    DefaultCube,
+    Wireframe,
+    /// The cube with its generated face label texture, so no asset files
+    /// are needed.
+    Textured,
+}
+
+impl CanonicalScene {
+    pub const ALL: [Self; 3] = [Self::DefaultCube, Self::Wireframe, Self::Textured];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::DefaultCube => "default cube",
+            Self::Wireframe => "wireframe",
+            Self::Textured => "textured cube",
+        }
+    }
+
+    /// `perceptual_hash` of the scene as a known-good driver renders it,
+    /// copied from the output of a `--self-test` run on one. `None` until
+    /// one is recorded, which skips the check; record it again whenever the
+    /// scene is meant to look different.
+    pub fn reference_hash(self) -> Option<u64> {
+        match self {
+            Self::DefaultCube => None,
+            Self::Wireframe => None,
+            Self::Textured => None,
+        }
+    }
+
+    /// Compares `frame` with `reference_hash`, allowing
+    /// SELF_TEST_HASH_TOLERANCE bits of difference for the drivers' own
+    /// rasterization and filtering.
+    pub fn check(self, frame: &CapturedFrame) -> CheckOutcome {
+        let hash = perceptual_hash(frame);
+        let Some(reference) = self.reference_hash() else {
+            return CheckOutcome::Skipped(format!(
+                "no reference hash recorded; this one is {:#018x}",
+                hash
+            ));
+        };
+        let distance = hash_distance(hash, reference);
+        let detail = format!(
+            "hash {:#018x}, {} bits from {:#018x}",
+            hash, distance, reference
+        );
+        if distance <= SELF_TEST_HASH_TOLERANCE {
+            CheckOutcome::Pass(detail)
+        } else {
+            CheckOutcome::Fail(detail)
+        }
+    }
+}
+
+/// Cells across and down the grid `perceptual_hash` averages the frame
+/// into, one bit each.
+const HASH_GRID: u32 = 8;
+
+/// An average hash of `frame`: its luminance averaged over an 8x8 grid of
+/// cells, one bit per cell, set where the cell is brighter than the mean of
+/// all of them. The first cell is the top bit. Rasterization and filtering
+/// differences between drivers move few cells across the mean, while a
+/// missing, moved or miscoloured cube flips many.
+pub fn perceptual_hash(frame: &CapturedFrame) -> u64 {
+    let mut cells = [0u64; (HASH_GRID * HASH_GRID) as usize];
+    let mut counts = [0u64; (HASH_GRID * HASH_GRID) as usize];
+    for (index, pixel) in frame.rgba.chunks_exact(4).enumerate() {
+        let (x, y) = (index as u32 % frame.width, index as u32 / frame.width);
+        let cell =
+            (y * HASH_GRID / frame.height * HASH_GRID + x * HASH_GRID / frame.width) as usize;
+        // Rec. 601 luma weights, in thousandths.
+        let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(u64::from);
+        cells[cell] += r * 299 + g * 587 + b * 114;
+        counts[cell] += 1;
+    }
+    let means: Vec<u64> = cells
+        .iter()
+        .zip(&counts)
+        .map(|(&sum, &count)| sum / count.max(1))
+        .collect();
+    let mean = means.iter().sum::<u64>() / means.len() as u64;
+    means
+        .iter()
+        .fold(0, |hash, &cell| (hash << 1) | u64::from(cell > mean))
+}
+
+/// How many bits two `perceptual_hash`es differ in.
+pub fn hash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
@@ -0,0 +1,177 @@
+use log::{info, warn};
+use std::io::{self, BufRead, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::config::arg_value;
+use super::protocol::{Command, EncodedFrame, FrameFormat};
+use super::{HEIGHT, SERVE_DEFAULT_FPS, WIDTH};
+
+/// Frames queued between the render loop and the network thread. Anything
+/// offered while the queue is full is dropped rather than stalling rendering.
+const FRAME_QUEUE_DEPTH: usize = 2;
+
+/// Streams frames to every connected TCP client and collects the commands
+/// they send back. Runs an accept thread, one sender thread shared by all
+/// clients, and a reader thread per client.
+pub struct FrameServer {
+    frames: SyncSender<Arc<EncodedFrame>>,
+    commands: Receiver<Command>,
+    clients: Arc<Mutex<Vec<TcpStream>>>,
+    dropped_frames: u64,
+}
+
+impl FrameServer {
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        info!("Serving frames on {}", listener.local_addr()?);
+
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let (frames, frame_receiver) = mpsc::sync_channel(FRAME_QUEUE_DEPTH);
+        let (command_sender, commands) = mpsc::channel();
+
+        let accepted = Arc::clone(&clients);
+        std::thread::spawn(move || accept_clients(listener, accepted, command_sender));
+        let sending = Arc::clone(&clients);
+        std::thread::spawn(move || send_frames(frame_receiver, sending));
+
+        Ok(Self {
+            frames,
+            commands,
+            clients,
+            dropped_frames: 0,
+        })
+    }
+
+    pub fn has_clients(&self) -> bool {
+        !self.clients.lock().unwrap().is_empty()
+    }
+
+    /// Queues `frame` for every client, or drops it if the network thread is
+    /// still busy with earlier ones.
+    pub fn offer(&mut self, frame: EncodedFrame) {
+        match self.frames.try_send(Arc::new(frame)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.dropped_frames += 1;
+                if self.dropped_frames.is_power_of_two() {
+                    warn!("Dropped {} frames for slow clients", self.dropped_frames);
+                }
+            }
+            Err(TrySendError::Disconnected(_)) => panic!("frame sender thread exited"),
+        }
+    }
+
+    /// Commands received since the last call, in arrival order.
+    pub fn commands(&self) -> Vec<Command> {
+        self.commands.try_iter().collect()
+    }
+}
+
+fn accept_clients(
+    listener: TcpListener,
+    clients: Arc<Mutex<Vec<TcpStream>>>,
+    commands: Sender<Command>,
+) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                warn!("Failed to accept client: {}", error);
+                continue;
+            }
+        };
+        let peer = stream
+            .peer_addr()
+            .map_or_else(|_| "unknown".to_string(), |addr| addr.to_string());
+        let reader = match stream.try_clone() {
+            Ok(reader) => reader,
+            Err(error) => {
+                warn!("Failed to set up client {}: {}", peer, error);
+                continue;
+            }
+        };
+        info!("Client {} connected", peer);
+        clients.lock().unwrap().push(stream);
+
+        let commands = commands.clone();
+        std::thread::spawn(move || read_commands(reader, peer, commands));
+    }
+}
+
+fn read_commands(stream: TcpStream, peer: String, commands: Sender<Command>) {
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        match Command::parse(&line) {
+            Some(command) => {
+                if commands.send(command).is_err() {
+                    return;
+                }
+            }
+            None => warn!("Ignoring command from {}: {:?}", peer, line),
+        }
+    }
+    info!("Client {} disconnected", peer);
+}
+
+/// Writes each frame to every client, dropping clients whose writes fail.
+fn send_frames(frames: Receiver<Arc<EncodedFrame>>, clients: Arc<Mutex<Vec<TcpStream>>>) {
+    for frame in frames {
+        clients
+            .lock()
+            .unwrap()
+            .retain_mut(|client| frame.write_to(client).is_ok());
+    }
+}
+
+/// Options of `--serve <port>`: `--serve-size <width>x<height>`,
+/// `--serve-fps <n>` and `--serve-format png|rgba`.
+pub struct ServeConfig {
+    pub port: u16,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub format: FrameFormat,
+}
+
+impl ServeConfig {
+    /// `None` without `--serve`; panics on malformed values.
+    pub fn from_args(args: &[String]) -> Option<Self> {
+        let port = arg_value(args, "--serve")?
+            .parse()
+            .expect("--serve expects a port number");
+        let (width, height) = arg_value(args, "--serve-size")
+            .map(|size| {
+                let (width, height) = size
+                    .split_once('x')
+                    .expect("--serve-size expects <width>x<height>");
+                (
+                    width.parse().expect("invalid --serve-size width"),
+                    height.parse().expect("invalid --serve-size height"),
+                )
+            })
+            .unwrap_or((WIDTH, HEIGHT));
+        let fps = arg_value(args, "--serve-fps")
+            .map(|fps| fps.parse().expect("--serve-fps expects a number"))
+            .unwrap_or(SERVE_DEFAULT_FPS)
+            .max(1);
+        let format = arg_value(args, "--serve-format")
+            .map(|name| FrameFormat::parse(name).expect("--serve-format expects png or rgba"))
+            .unwrap_or(FrameFormat::Png);
+        Some(Self {
+            port,
+            width,
+            height,
+            fps,
+            format,
+        })
+    }
+
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_secs(1) / self.fps
+    }
+}
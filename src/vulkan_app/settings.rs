@@ -0,0 +1,566 @@
+use ash::vk;
+use cgmath::Deg;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::background::Background;
+use super::config::{parse_present_mode, present_mode_name, RenderMode, RendererConfig};
+use super::{
+    HEIGHT, LEGACY_SETTINGS_FILE, SETTINGS_DIR, SETTINGS_FILE, SETTINGS_POLL_MS, SETTINGS_SAVE_MS,
+    SETTINGS_VERSION, WIDTH,
+};
+
+/// The part of `RendererConfig` a settings file can set, and the window's
+/// placement, read from and written to TOML through `SettingsDocument`:
+///
+/// ```toml
+/// version = 3
+/// clear_color = [0.0, 0.0, 0.0]
+/// background = "none"
+/// msaa = 1
+/// present_mode = "mailbox"
+/// render_mode = "on-demand"
+/// animation_speed = 1.0
+///
+/// [camera]
+/// yaw = 0.0
+/// elevation = 0.0
+///
+/// [overlay]
+/// frame_graph = false
+///
+/// [window]
+/// width = 800
+/// height = 600
+/// x = 100
+/// y = 100
+/// fullscreen = false
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    /// Linear, used while there is no sun.
+    pub clear_color: [f32; 3],
+    pub background: Background,
+    pub msaa_samples: u32,
+    pub present_mode: vk::PresentModeKHR,
+    pub render_mode: RenderMode,
+    pub animation_speed: f32,
+    /// Added to the default eye position.
+    pub camera_yaw: Deg<f32>,
+    pub camera_elevation: Deg<f32>,
+    pub show_frame_graph: bool,
+    pub window: WindowSettings,
+}
+
+/// Where the window was last, and whether it was fullscreen. Only read at
+/// startup, where flags win over it; the renderer never sees it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowSettings {
+    /// In logical pixels, of the windowed size even while fullscreen.
+    pub width: u32,
+    pub height: u32,
+    /// The outer position in physical pixels, where the platform has one.
+    pub position: Option<(i32, i32)>,
+    pub fullscreen: bool,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            width: WIDTH,
+            height: HEIGHT,
+            position: None,
+            fullscreen: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SettingsError {
+    /// 1-based; `None` for a value that is well-formed TOML of the right
+    /// type but out of range, which the message names the setting of.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl SettingsError {
+    fn toml(text: &str, error: toml::de::Error) -> Self {
+        Self {
+            line: error
+                .span()
+                .map(|span| text[..span.start].matches('\n').count() + 1),
+            message: error.message().to_string(),
+        }
+    }
+
+    fn setting(message: String) -> Self {
+        Self {
+            line: None,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// The settings file as TOML sees it. Every setting is optional, so a file
+/// can leave any of them to its base; names are checked by
+/// `Settings::apply`.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+struct SettingsDocument {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    clear_color: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    background: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    msaa: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    present_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    render_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    animation_speed: Option<f32>,
+    camera: CameraSection,
+    overlay: OverlaySection,
+    window: WindowSection,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+struct CameraSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    yaw: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elevation: Option<f32>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+struct OverlaySection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frame_graph: Option<bool>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+struct WindowSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    y: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fullscreen: Option<bool>,
+}
+
+fn finite(key: &str, value: Option<f32>) -> Result<Option<f32>, SettingsError> {
+    match value {
+        Some(number) if !number.is_finite() => Err(SettingsError::setting(format!(
+            "`{}` expects a finite number",
+            key
+        ))),
+        _ => Ok(value),
+    }
+}
+
+fn pixels(key: &str, value: Option<u32>) -> Result<Option<u32>, SettingsError> {
+    match value {
+        Some(0) => Err(SettingsError::setting(format!(
+            "`{}` expects a positive number of pixels",
+            key
+        ))),
+        _ => Ok(value),
+    }
+}
+
+impl Settings {
+    pub fn from_config(config: &RendererConfig) -> Self {
+        Self {
+            clear_color: config.clear_color,
+            background: config.background,
+            msaa_samples: config.msaa_samples,
+            present_mode: config.present_mode,
+            render_mode: config.render_mode,
+            animation_speed: config.animation_speed,
+            camera_yaw: config.camera_orbit.0,
+            camera_elevation: config.camera_orbit.1,
+            show_frame_graph: config.show_frame_graph,
+            window: WindowSettings::default(),
+        }
+    }
+
+    pub fn apply_to(&self, config: &mut RendererConfig) {
+        config.clear_color = self.clear_color;
+        config.background = self.background;
+        config.msaa_samples = self.msaa_samples;
+        config.present_mode = self.present_mode;
+        config.render_mode = self.render_mode;
+        config.animation_speed = self.animation_speed;
+        config.camera_orbit = (self.camera_yaw, self.camera_elevation);
+        config.show_frame_graph = self.show_frame_graph;
+    }
+
+    /// Reads `text` on top of `base`: settings the file leaves out keep
+    /// their value from `base`. Fails on the first bad setting, so a file is
+    /// applied whole or not at all. A file without a `version` is version 1;
+    /// once `version` says the file is newer than SETTINGS_VERSION, settings
+    /// this build doesn't know are skipped instead.
+    pub fn parse(text: &str, base: Settings) -> Result<Self, SettingsError> {
+        let mut unknown = Vec::new();
+        let document: SettingsDocument =
+            serde_ignored::deserialize(toml::Deserializer::new(text), |path| {
+                unknown.push(path.to_string())
+            })
+            .map_err(|error| SettingsError::toml(text, error))?;
+        let version = match document.version {
+            Some(0) => {
+                return Err(SettingsError::setting(
+                    "`version` expects a positive whole number".to_string(),
+                ))
+            }
+            version => version.unwrap_or(1),
+        };
+        if let Some(key) = unknown.first().filter(|_| version <= SETTINGS_VERSION) {
+            return Err(SettingsError::setting(format!("unknown setting `{}`", key)));
+        }
+        for key in &unknown {
+            warn!(
+                "Skipping `{}` from a version {} settings file",
+                key, version
+            );
+        }
+        let mut settings = base;
+        settings.apply(document)?;
+        Ok(settings)
+    }
+
+    fn apply(&mut self, document: SettingsDocument) -> Result<(), SettingsError> {
+        if let Some(rgb) = document.clear_color {
+            self.clear_color = match rgb[..] {
+                [r, g, b] if rgb.iter().all(|c| c.is_finite()) => [r, g, b],
+                _ => {
+                    return Err(SettingsError::setting(
+                        "`clear_color` expects [r, g, b]".to_string(),
+                    ))
+                }
+            };
+        }
+        if let Some(name) = document.background {
+            self.background = Background::parse(&name).ok_or_else(|| {
+                SettingsError::setting(
+                    "`background` expects \"none\", \"gradient\", \"starfield\", \"clouds\" \
+                     or \"skybox\""
+                        .to_string(),
+                )
+            })?;
+        }
+        if let Some(samples) = document.msaa {
+            if samples == 0 {
+                return Err(SettingsError::setting(
+                    "`msaa` expects a whole sample count".to_string(),
+                ));
+            }
+            self.msaa_samples = samples;
+        }
+        if let Some(name) = document.present_mode {
+            self.present_mode = parse_present_mode(&name).ok_or_else(|| {
+                SettingsError::setting(
+                    "`present_mode` expects \"immediate\", \"mailbox\", \"fifo\" or \
+                     \"fifo-relaxed\""
+                        .to_string(),
+                )
+            })?;
+        }
+        if let Some(name) = document.render_mode {
+            self.render_mode = RenderMode::parse(&name).ok_or_else(|| {
+                SettingsError::setting(
+                    "`render_mode` expects \"continuous\" or \"on-demand\"".to_string(),
+                )
+            })?;
+        }
+        if let Some(speed) = finite("animation_speed", document.animation_speed)? {
+            self.animation_speed = speed;
+        }
+        if let Some(yaw) = finite("camera.yaw", document.camera.yaw)? {
+            self.camera_yaw = Deg(yaw);
+        }
+        if let Some(elevation) = finite("camera.elevation", document.camera.elevation)? {
+            self.camera_elevation = Deg(elevation);
+        }
+        if let Some(show) = document.overlay.frame_graph {
+            self.show_frame_graph = show;
+        }
+        let window = document.window;
+        if let Some(width) = pixels("window.width", window.width)? {
+            self.window.width = width;
+        }
+        if let Some(height) = pixels("window.height", window.height)? {
+            self.window.height = height;
+        }
+        if window.x.is_some() || window.y.is_some() {
+            let (x, y) = self.window.position.unwrap_or_default();
+            self.window.position = Some((window.x.unwrap_or(x), window.y.unwrap_or(y)));
+        }
+        if let Some(fullscreen) = window.fullscreen {
+            self.window.fullscreen = fullscreen;
+        }
+        Ok(())
+    }
+
+    /// A file `parse` reads back to these settings.
+    pub fn to_toml(&self) -> String {
+        let document = SettingsDocument {
+            version: Some(SETTINGS_VERSION),
+            clear_color: Some(self.clear_color.to_vec()),
+            background: Some(self.background.name().to_string()),
+            msaa: Some(self.msaa_samples),
+            present_mode: Some(present_mode_name(self.present_mode).to_string()),
+            render_mode: Some(self.render_mode.name().to_string()),
+            animation_speed: Some(self.animation_speed),
+            camera: CameraSection {
+                yaw: Some(self.camera_yaw.0),
+                elevation: Some(self.camera_elevation.0),
+            },
+            overlay: OverlaySection {
+                frame_graph: Some(self.show_frame_graph),
+            },
+            window: WindowSection {
+                width: Some(self.window.width),
+                height: Some(self.window.height),
+                x: self.window.position.map(|(x, _)| x),
+                y: self.window.position.map(|(_, y)| y),
+                fullscreen: Some(self.window.fullscreen),
+            },
+        };
+        toml::to_string(&document).expect("settings always serialize")
+    }
+}
+
+/// Watches a settings file by polling its modification time, at most every
+/// `SETTINGS_POLL_MS`. Each change is read in one go and parsed in full, so
+/// a half-written or malformed file leaves the previous settings in place.
+pub struct SettingsFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    next_poll: Instant,
+    // What the file holds as far as this process knows: the last settings
+    // loaded, reloaded or written.
+    saved: Option<Settings>,
+    next_save: Instant,
+    // Read by `load` in place of a missing file, then copied over to it.
+    legacy: Option<PathBuf>,
+}
+
+impl SettingsFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let now = Instant::now();
+        Self {
+            path: path.into(),
+            modified: None,
+            next_poll: now,
+            saved: None,
+            next_save: now,
+            legacy: None,
+        }
+    }
+
+    /// Has `load` fall back to `legacy` while the file doesn't exist, and
+    /// write what it read there to the file, which is read from then on.
+    /// `legacy` itself is left as it is.
+    pub fn migrating_from(mut self, legacy: impl Into<PathBuf>) -> Self {
+        self.legacy = Some(legacy.into());
+        self
+    }
+
+    /// SETTINGS_FILE in the platform's config directory, under
+    /// SETTINGS_DIR, or in the working directory where there's none.
+    /// Until it exists, LEGACY_SETTINGS_FILE in the working directory is
+    /// read and migrated to it.
+    pub fn in_config_dir() -> Self {
+        let file = match dirs::config_dir() {
+            Some(dir) => Self::new(dir.join(SETTINGS_DIR).join(SETTINGS_FILE)),
+            None => Self::new(SETTINGS_FILE),
+        };
+        file.migrating_from(LEGACY_SETTINGS_FILE)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The file's settings on top of `base`, read at startup. A file that
+    /// doesn't parse is moved aside to `<file>.corrupt`, so the next save
+    /// doesn't overwrite what might be a hand edit gone wrong.
+    pub fn load(&mut self, base: Settings) -> Option<Settings> {
+        if let Some(settings) = self.migrate(base) {
+            return Some(settings);
+        }
+        self.modified = self.modified_time();
+        let text = std::fs::read_to_string(&self.path).ok()?;
+        match Settings::parse(&text, base) {
+            Ok(settings) => {
+                self.saved = Some(settings);
+                Some(settings)
+            }
+            Err(error) => {
+                let mut aside = self.path.clone().into_os_string();
+                aside.push(".corrupt");
+                match std::fs::rename(&self.path, &aside) {
+                    Ok(()) => warn!(
+                        "{}: {}; moved to {} and starting from defaults",
+                        self.path.display(),
+                        error,
+                        PathBuf::from(&aside).display()
+                    ),
+                    Err(rename_error) => warn!(
+                        "{}: {}; starting from defaults, and can't move it aside: {}",
+                        self.path.display(),
+                        error,
+                        rename_error
+                    ),
+                }
+                self.modified = None;
+                None
+            }
+        }
+    }
+
+    /// The legacy file's settings on top of `base`, written to the file,
+    /// if there's a legacy file and no file yet. A legacy file that doesn't
+    /// parse is left for the user to fix and the file starts from defaults.
+    fn migrate(&mut self, base: Settings) -> Option<Settings> {
+        let legacy = self.legacy.take()?;
+        if self.path.exists() {
+            return None;
+        }
+        let text = std::fs::read_to_string(&legacy).ok()?;
+        match Settings::parse(&text, base) {
+            Ok(settings) => {
+                self.write(settings);
+                info!(
+                    "Moved settings from {} to {}; {} is no longer read",
+                    legacy.display(),
+                    self.path.display(),
+                    legacy.display()
+                );
+                Some(settings)
+            }
+            Err(error) => {
+                warn!(
+                    "{}: {}; not moved to {}",
+                    legacy.display(),
+                    error,
+                    self.path.display()
+                );
+                None
+            }
+        }
+    }
+
+    /// Deletes the file, for `--reset-settings`, and skips migrating a
+    /// legacy file into it. A missing file is not an error.
+    pub fn reset(&mut self) -> std::io::Result<()> {
+        self.modified = None;
+        self.saved = None;
+        self.legacy = None;
+        match std::fs::remove_file(&self.path) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes `settings` if they differ from what the file holds, at most
+    /// every SETTINGS_SAVE_MS, so a held key or a window drag doesn't write
+    /// on every frame.
+    pub fn save(&mut self, settings: Settings) {
+        let now = Instant::now();
+        if now < self.next_save || self.saved == Some(settings) {
+            return;
+        }
+        self.next_save = now + Duration::from_millis(SETTINGS_SAVE_MS);
+        self.write(settings);
+    }
+
+    /// Writes `settings` now if they differ from what the file holds, for
+    /// the last save before exiting.
+    pub fn flush(&mut self, settings: Settings) {
+        if self.saved != Some(settings) {
+            self.write(settings);
+        }
+    }
+
+    // Into a temporary file renamed over the old one, so a crash mid-write
+    // never leaves half a file to load.
+    fn write(&mut self, settings: Settings) {
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        let written = self
+            .path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(&temporary, settings.to_toml()))
+            .and_then(|()| std::fs::rename(&temporary, &self.path));
+        match written {
+            // Our own write isn't an edit for `poll` to reload.
+            Ok(()) => self.modified = self.modified_time(),
+            Err(error) => warn!("Can't save {}: {}", self.path.display(), error),
+        }
+        // Even on failure, so it's only retried once the settings change.
+        self.saved = Some(settings);
+    }
+
+    fn modified_time(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// The file's settings on top of `current` if it changed since the last
+    /// poll and parses. A missing file is not an error.
+    pub fn poll(&mut self, current: Settings) -> Option<Settings> {
+        let now = Instant::now();
+        if now < self.next_poll {
+            return None;
+        }
+        self.next_poll = now + Duration::from_millis(SETTINGS_POLL_MS);
+        let modified = self.modified_time()?;
+        if self.modified == Some(modified) {
+            return None;
+        }
+        self.modified = Some(modified);
+        let parsed = std::fs::read_to_string(&self.path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| Settings::parse(&text, current).map_err(|error| error.to_string()));
+        match parsed {
+            Ok(settings) => {
+                self.saved = Some(settings);
+                Some(settings)
+            }
+            Err(error) => {
+                warn!(
+                    "{}: {}; keeping the previous settings",
+                    self.path.display(),
+                    error
+                );
+                None
+            }
+        }
+    }
+}
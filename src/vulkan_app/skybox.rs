@@ -0,0 +1,257 @@
+use ash::vk;
+#[cfg(feature = "asset-loaders")]
+use std::f32::consts::{PI, TAU};
+use std::path::Path;
+
+use super::allocator::{Allocation, Allocator};
+#[cfg(feature = "asset-loaders")]
+use super::png;
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::sun::linear_to_srgb;
+use super::texture::Texture;
+
+/// The files a `--skybox` directory holds, in cube map layer order: +X, -X,
+/// +Y, -Y, +Z, -Z, with Y up.
+#[cfg(feature = "asset-loaders")]
+pub const FACE_FILES: [&str; 6] = ["px.png", "nx.png", "py.png", "ny.png", "pz.png", "nz.png"];
+
+// The gradient background's linear colours, as in background.frag.
+const ZENITH: [f32; 3] = [0.05, 0.15, 0.45];
+const HORIZON: [f32; 3] = [0.55, 0.65, 0.8];
+const GROUND: [f32; 3] = [0.04, 0.035, 0.03];
+
+/// The cube map behind `Background::Skybox` and the unit cube it is drawn
+/// on, around the eye.
+pub struct Skybox {
+    /// Six layers, with a CUBE view.
+    pub cube_map: Texture,
+    pub vertex_buffer: vk::Buffer,
+    pub vertex_allocation: Allocation,
+}
+
+impl Skybox {
+    pub fn destroy(
+        &self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        resources: &mut ResourceRegistry,
+    ) {
+        self.cube_map.destroy(device, allocator, resources);
+        resources.unregister(ResourceKind::Buffer, self.vertex_buffer);
+        unsafe { device.destroy_buffer(self.vertex_buffer, None) };
+        allocator.free(self.vertex_allocation);
+    }
+}
+
+/// The cube map direction through `(u, v)`, each from -1 to 1 across
+/// `face`, the way Vulkan addresses cube maps.
+fn face_direction(face: usize, u: f32, v: f32) -> [f32; 3] {
+    match face {
+        0 => [1.0, -v, -u],
+        1 => [-1.0, -v, u],
+        2 => [u, 1.0, v],
+        3 => [u, -1.0, -v],
+        4 => [u, -v, 1.0],
+        _ => [-u, -v, -1.0],
+    }
+}
+
+/// Two triangles per face of a cube from -1 to 1, 36 vertices in all.
+/// Drawn without culling, so the winding doesn't matter.
+pub fn unit_cube() -> Vec<[f32; 3]> {
+    (0..6)
+        .flat_map(|face| {
+            [
+                (-1.0, -1.0),
+                (1.0, -1.0),
+                (-1.0, 1.0),
+                (-1.0, 1.0),
+                (1.0, -1.0),
+                (1.0, 1.0),
+            ]
+            .map(|(u, v)| face_direction(face, u, v))
+        })
+        .collect()
+}
+
+/// Six square `size` faces of RGBA8, one after another, from `texel` at
+/// the unit direction through each texel's centre.
+fn faces(size: u32, texel: impl Fn([f32; 3]) -> [u8; 4]) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(6 * (size * size * 4) as usize);
+    let coordinate = |i: u32| 2.0 * (i as f32 + 0.5) / size as f32 - 1.0;
+    for face in 0..6 {
+        for y in 0..size {
+            for x in 0..size {
+                let [dx, dy, dz] = face_direction(face, coordinate(x), coordinate(y));
+                let length = (dx * dx + dy * dy + dz * dz).sqrt();
+                pixels.extend_from_slice(&texel([dx / length, dy / length, dz / length]));
+            }
+        }
+    }
+    pixels
+}
+
+/// The gradient background as a cube map: sky blue above the horizon
+/// fading to dark ground below, sRGB encoded.
+pub fn gradient(size: u32) -> Vec<u8> {
+    let mix = |a: [f32; 3], b: [f32; 3], t: f32| -> [f32; 3] {
+        std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+    };
+    faces(size, |[_, up, _]| {
+        let color = if up < 0.0 {
+            let t = (-up / 0.15).clamp(0.0, 1.0);
+            mix(HORIZON.map(|c| c * 0.4), GROUND, t * t * (3.0 - 2.0 * t))
+        } else {
+            mix(HORIZON, ZENITH, up.sqrt())
+        };
+        let [r, g, b] = color.map(|c| (linear_to_srgb(c) * 255.0).round() as u8);
+        [r, g, b, 255]
+    })
+}
+
+/// Faces of `size` resampled bilinearly from an equirectangular image:
+/// longitude across, from -Z round through +X, and latitude down from +Y.
+#[cfg(feature = "asset-loaders")]
+fn from_equirectangular(width: u32, height: u32, pixels: &[u8], size: u32) -> Vec<u8> {
+    let texel = |x: i64, y: i64| {
+        let x = x.rem_euclid(width as i64) as usize;
+        let y = y.clamp(0, height as i64 - 1) as usize;
+        let start = (y * width as usize + x) * 4;
+        &pixels[start..start + 4]
+    };
+    faces(size, |[x, y, z]| {
+        let u = (0.5 + x.atan2(-z) / TAU) * width as f32 - 0.5;
+        let v = (0.5 - y.asin() / PI) * height as f32 - 0.5;
+        let (x0, y0) = (u.floor() as i64, v.floor() as i64);
+        let (fx, fy) = (u - u.floor(), v - v.floor());
+        std::array::from_fn(|channel| {
+            let top =
+                texel(x0, y0)[channel] as f32 * (1.0 - fx) + texel(x0 + 1, y0)[channel] as f32 * fx;
+            let bottom = texel(x0, y0 + 1)[channel] as f32 * (1.0 - fx)
+                + texel(x0 + 1, y0 + 1)[channel] as f32 * fx;
+            (top * (1.0 - fy) + bottom * fy).round() as u8
+        })
+    })
+}
+
+#[cfg(feature = "asset-loaders")]
+fn decode(path: &Path) -> Result<(u32, u32, Vec<u8>), String> {
+    std::fs::read(path)
+        .map_err(|error| error.to_string())
+        .and_then(|bytes| png::decode_rgba(&bytes))
+        .map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+/// The faces' size and pixels, one face after another, from `path`: a
+/// directory of `FACE_FILES`, all square and the same size, or a single
+/// equirectangular PNG, converted to faces a quarter of its width.
+#[cfg(feature = "asset-loaders")]
+pub fn load_faces(path: &Path) -> Result<(u32, Vec<u8>), String> {
+    if !path.is_dir() {
+        let (width, height, pixels) = decode(path)?;
+        let size = (width / 4).max(1);
+        return Ok((size, from_equirectangular(width, height, &pixels, size)));
+    }
+    let mut size = None;
+    let mut pixels = Vec::new();
+    for name in FACE_FILES {
+        let file = path.join(name);
+        let (width, height, face) = decode(&file)?;
+        if width != height || size.is_some_and(|size| size != width) {
+            return Err(format!(
+                "{} is {}x{}; faces must be square and all the same size",
+                file.display(),
+                width,
+                height
+            ));
+        }
+        size = Some(width);
+        pixels.extend_from_slice(&face);
+    }
+    Ok((size.unwrap_or(1), pixels))
+}
+
+#[cfg(not(feature = "asset-loaders"))]
+pub fn load_faces(_path: &Path) -> Result<(u32, Vec<u8>), String> {
+    Err("--skybox needs the asset-loaders feature".to_string())
+}
+
+/// Records the copy of `staging`, six `size` faces one after another, into
+/// every layer of `image`, leaving it ready to sample in fragment shaders.
+pub fn record_upload(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    image: vk::Image,
+    staging: vk::Buffer,
+    size: u32,
+) {
+    let range = vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: 0,
+        layer_count: 6,
+    };
+    let barrier = |old_layout, new_layout, src_access_mask, dst_access_mask| {
+        vk::ImageMemoryBarrier::builder()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(range)
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask)
+            .build()
+    };
+    let region = vk::BufferImageCopy::builder()
+        .image_subresource(vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 6,
+        })
+        .image_extent(vk::Extent3D {
+            width: size,
+            height: size,
+            depth: 1,
+        })
+        .build();
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier(
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::AccessFlags::empty(),
+                vk::AccessFlags::TRANSFER_WRITE,
+            )],
+        );
+        device.cmd_copy_buffer_to_image(
+            command_buffer,
+            staging,
+            image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            std::slice::from_ref(&region),
+        );
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier(
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::AccessFlags::SHADER_READ,
+            )],
+        );
+    }
+}
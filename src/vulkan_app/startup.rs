@@ -0,0 +1,44 @@
+use std::time::{Duration, Instant};
+
+/// Wall-clock time of each `VulkanApp::new` phase, measured back to back.
+pub struct StartupProfiler {
+    start: Instant,
+    last: Instant,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl StartupProfiler {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            start: now,
+            last: now,
+            phases: Vec::new(),
+        }
+    }
+
+    /// Ends the current phase, naming it `name`, and starts the next one.
+    pub fn phase(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.phases.push((name, now - self.last));
+        self.last = now;
+    }
+
+    pub fn report(&self, tracked_objects: usize) -> String {
+        let mut report = String::from("Startup phases:\n");
+        for (name, elapsed) in &self.phases {
+            report += &format!("  {:<12} {:>8.1} ms\n", name, millis(*elapsed));
+        }
+        report += &format!(
+            "  {:<12} {:>8.1} ms, {} tracked GPU objects\n",
+            "total",
+            millis(self.last - self.start),
+            tracked_objects
+        );
+        report
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,119 @@
+use cgmath::{Deg, InnerSpace, Rad, Vector3};
+
+/// Clear colour with the sun well below the horizon, linear.
+const NIGHT_SKY: [f32; 3] = [0.005, 0.007, 0.02];
+/// Clear colour with the sun overhead, before the sun's tint, linear.
+const DAY_SKY: [f32; 3] = [0.25, 0.45, 0.85];
+/// Sines of elevation over which the sun fades in at dawn and out at dusk.
+const TWILIGHT: (f32, f32) = (-0.05, 0.1);
+const NIGHT_AMBIENT: f32 = 0.08;
+const DAY_AMBIENT: f32 = 0.3;
+
+/// `--sun`: a directional light circling once per `day_length` seconds,
+/// rising to `max_elevation` at noon.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SunConfig {
+    pub day_length: f32,
+    pub max_elevation: Deg<f32>,
+    /// Colour temperature at the horizon and at `max_elevation`, in kelvin.
+    pub horizon_kelvin: f32,
+    pub zenith_kelvin: f32,
+}
+
+impl Default for SunConfig {
+    fn default() -> Self {
+        Self {
+            day_length: 60.0,
+            max_elevation: Deg(60.0),
+            horizon_kelvin: 2000.0,
+            zenith_kelvin: 6500.0,
+        }
+    }
+}
+
+/// The light at one moment, in linear colour.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SunState {
+    /// Unit vector towards the sun, Z up.
+    pub direction: Vector3<f32>,
+    pub color: [f32; 3],
+    pub ambient: f32,
+    pub sky: [f32; 3],
+}
+
+impl SunConfig {
+    /// Where the sun is `time` seconds into the cycle, which starts at dawn.
+    pub fn at(&self, time: f32) -> SunState {
+        let angle = std::f32::consts::TAU * (time / self.day_length.max(f32::EPSILON)).fract();
+        let elevation = Rad::from(self.max_elevation).0 * angle.sin();
+        let azimuth = angle;
+        let direction = Vector3::new(
+            elevation.cos() * azimuth.cos(),
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+        )
+        .normalize();
+
+        let height = elevation.sin();
+        let daylight = smoothstep(TWILIGHT.0, TWILIGHT.1, height);
+        let noon = Rad::from(self.max_elevation).0.sin().max(f32::EPSILON);
+        let kelvin = self.horizon_kelvin
+            + (self.zenith_kelvin - self.horizon_kelvin) * (height / noon).clamp(0.0, 1.0);
+        let tint = color_temperature(kelvin);
+        SunState {
+            direction,
+            color: tint.map(|c| c * daylight),
+            ambient: NIGHT_AMBIENT + (DAY_AMBIENT - NIGHT_AMBIENT) * daylight,
+            sky: std::array::from_fn(|i| {
+                NIGHT_SKY[i] + (DAY_SKY[i] * tint[i] - NIGHT_SKY[i]) * daylight
+            }),
+        }
+    }
+}
+
+/// Approximate linear RGB of a black body at `kelvin`, normalised so the
+/// brightest channel is 1. Uses Tanner Helland's fit to the CIE data, which
+/// produces sRGB-encoded values, then decodes them. Good from 1000 K to
+/// 40000 K.
+pub fn color_temperature(kelvin: f32) -> [f32; 3] {
+    let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+    let green = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_17 * (t - 60.0).powf(-0.075_514_85)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+    [red, green, blue].map(|c| srgb_to_linear((c / 255.0).clamp(0.0, 1.0)))
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
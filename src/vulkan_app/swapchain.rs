@@ -0,0 +1,501 @@
+use ash::extensions::khr;
+use ash::vk;
+use log::{info, warn};
+
+use super::allocator::Allocation;
+use super::context::{self, SurfaceBundle, VkContext};
+use super::error::{Stage, VulkanError};
+use super::journal::RecreateReason;
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::swapchain_support;
+
+/// Of the image a headless renderer draws into. Mandatory as a colour
+/// attachment, and read back as RGBA without swizzling.
+pub const OFFSCREEN_FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+
+/// The main window's swapchain, or when headless the one offscreen image
+/// standing in for it, with what is made per image or sized with it: the
+/// image views, the fence of the frame last rendered to each image, the
+/// depth attachment and, without dynamic rendering, the scene's
+/// framebuffers.
+pub struct Swapchain {
+    pub loader: khr::Swapchain,
+    // Null when headless.
+    pub handle: vk::SwapchainKHR,
+    pub images: Vec<vk::Image>,
+    pub image_views: Vec<vk::ImageView>,
+    // Per image, the fence of the frame in flight that last rendered to it,
+    // or null. Images can come back from acquire in any order, so an image
+    // may still be rendered to by another frame than the one about to reuse
+    // it.
+    pub images_in_flight: Vec<vk::Fence>,
+    // When headless, the only entry in `images` and its memory.
+    offscreen: Option<(vk::Image, Allocation)>,
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+    // COLOR_ATTACHMENT plus TRANSFER_SRC when supported and whatever
+    // `config.swapchain_usage` asked for.
+    pub usage: vk::ImageUsageFlags,
+    // Bumped by every `recreate`. Descriptor sets written with the
+    // extent-sized attachments note it, and debug builds check them against
+    // it before recording.
+    pub generation: u64,
+    // At the render extent, which may differ from `extent`. Null between
+    // `destroy_depth` and `create_depth`.
+    pub depth_image: vk::Image,
+    pub depth_image_allocation: Option<Allocation>,
+    pub depth_image_view: vk::ImageView,
+    // One per image; empty with dynamic rendering.
+    pub framebuffers: Vec<vk::Framebuffer>,
+}
+
+impl Swapchain {
+    /// A swapchain on the context's surface, for `window`. The depth
+    /// attachment and framebuffers come later, from `create_depth` and
+    /// `create_framebuffers`.
+    pub fn new(
+        context: &VkContext,
+        window: &winit::window::Window,
+        present_mode: vk::PresentModeKHR,
+        requested_usage: vk::ImageUsageFlags,
+        resources: &mut ResourceRegistry,
+    ) -> Result<Self, VulkanError> {
+        let loader = khr::Swapchain::new(&context.instance, &context.device);
+        let (handle, format, extent, usage) = create_swapchain(
+            context,
+            context
+                .surface
+                .as_ref()
+                .expect("a window always has a surface"),
+            None,
+            &loader,
+            present_mode,
+            requested_usage,
+            window,
+            Stage::SwapchainCreation,
+        )?;
+        let images = unsafe { loader.get_swapchain_images(handle) }
+            .map_err(VulkanError::at(Stage::SwapchainCreation))?;
+        Ok(Self::with_images(
+            context, loader, handle, images, None, format, extent, usage, resources,
+        ))
+    }
+
+    /// Stands in for the swapchain when headless: one image, with
+    /// COLOR_ATTACHMENT and TRANSFER_SRC usage plus whatever was requested.
+    pub fn offscreen(
+        context: &VkContext,
+        extent: vk::Extent2D,
+        requested_usage: vk::ImageUsageFlags,
+        resources: &mut ResourceRegistry,
+    ) -> Result<Self, VulkanError> {
+        let loader = khr::Swapchain::new(&context.instance, &context.device);
+        let usage = vk::ImageUsageFlags::COLOR_ATTACHMENT
+            | vk::ImageUsageFlags::TRANSFER_SRC
+            | requested_usage;
+        let (image, allocation) = context.create_image(
+            extent.width,
+            extent.height,
+            vk::SampleCountFlags::TYPE_1,
+            OFFSCREEN_FORMAT,
+            vk::ImageTiling::OPTIMAL,
+            usage,
+        )?;
+        let size = unsafe { context.device.get_image_memory_requirements(image).size };
+        resources.register(ResourceKind::Image, image, "offscreen image", size);
+        Ok(Self::with_images(
+            context,
+            loader,
+            vk::SwapchainKHR::null(),
+            vec![image],
+            Some((image, allocation)),
+            OFFSCREEN_FORMAT,
+            extent,
+            usage,
+            resources,
+        ))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn with_images(
+        context: &VkContext,
+        loader: khr::Swapchain,
+        handle: vk::SwapchainKHR,
+        images: Vec<vk::Image>,
+        offscreen: Option<(vk::Image, Allocation)>,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        usage: vk::ImageUsageFlags,
+        resources: &mut ResourceRegistry,
+    ) -> Self {
+        let image_views = create_image_views(&context.device, &images, format, resources);
+        Self {
+            loader,
+            handle,
+            images_in_flight: vec![vk::Fence::null(); images.len()],
+            images,
+            image_views,
+            offscreen,
+            format,
+            extent,
+            usage,
+            generation: 0,
+            depth_image: vk::Image::null(),
+            depth_image_allocation: None,
+            depth_image_view: vk::ImageView::null(),
+            framebuffers: Vec::new(),
+        }
+    }
+
+    /// Replaces the swapchain with one for the window's current size and
+    /// `present_mode`, with new image views. The old swapchain is handed
+    /// over rather than destroyed first, so images it still has queued for
+    /// presentation are released in order, unless the surface was lost.
+    /// The framebuffers and depth attachment go with the old images; the
+    /// caller makes new ones for the render extent. Only while the device
+    /// is idle.
+    pub fn recreate(
+        &mut self,
+        context: &mut VkContext,
+        window: &winit::window::Window,
+        present_mode: vk::PresentModeKHR,
+        requested_usage: vk::ImageUsageFlags,
+        reason: RecreateReason,
+        resources: &mut ResourceRegistry,
+    ) -> Result<(), VulkanError> {
+        self.generation += 1;
+        self.destroy_extent_resources(context, resources);
+        if reason == RecreateReason::SurfaceLost {
+            // A lost surface's swapchain can't be handed over to another
+            // surface; it goes first, and the new swapchain starts afresh.
+            unsafe { self.loader.destroy_swapchain(self.handle, None) };
+            self.handle = vk::SwapchainKHR::null();
+            context.recreate_surface(window)?;
+            info!("Surface lost; created a new one");
+        }
+        let (handle, format, extent, usage) = create_swapchain(
+            context,
+            context
+                .surface
+                .as_ref()
+                .expect("a window always has a surface"),
+            Some(self.handle),
+            &self.loader,
+            present_mode,
+            requested_usage,
+            window,
+            Stage::SwapchainRecreation,
+        )?;
+        unsafe { self.loader.destroy_swapchain(self.handle, None) };
+        self.handle = handle;
+        self.format = format;
+        self.extent = extent;
+        self.usage = usage;
+        self.images = unsafe { self.loader.get_swapchain_images(handle) }
+            .map_err(VulkanError::at(Stage::SwapchainRecreation))?;
+        // The driver may hand back more images than were asked for. After
+        // the wait for idle no frame is using any of them.
+        self.images_in_flight = vec![vk::Fence::null(); self.images.len()];
+        self.image_views = create_image_views(&context.device, &self.images, format, resources);
+        Ok(())
+    }
+
+    /// The depth attachment at `extent`. `command_pool` is on the graphics
+    /// queue, where the image's layout transition runs.
+    pub fn create_depth(
+        &mut self,
+        context: &VkContext,
+        command_pool: vk::CommandPool,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlags,
+        resources: &mut ResourceRegistry,
+    ) -> Result<(), VulkanError> {
+        let depth_format = context.find_depth_format();
+        let (depth_image, depth_image_allocation) = context.create_image(
+            extent.width,
+            extent.height,
+            samples,
+            depth_format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+        )?;
+        let depth_image_view = context.create_image_view(
+            depth_image,
+            depth_format,
+            context::depth_aspect_flags(depth_format),
+        );
+        context.transition_image_layout(
+            command_pool,
+            depth_image,
+            depth_format,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        );
+        let depth_size = unsafe {
+            context
+                .device
+                .get_image_memory_requirements(depth_image)
+                .size
+        };
+        resources.register(ResourceKind::Image, depth_image, "depth image", depth_size);
+        resources.register(
+            ResourceKind::ImageView,
+            depth_image_view,
+            "depth image view",
+            0,
+        );
+        self.depth_image = depth_image;
+        self.depth_image_allocation = Some(depth_image_allocation);
+        self.depth_image_view = depth_image_view;
+        Ok(())
+    }
+
+    /// Null once destroyed, in case recreation fails before a new one
+    /// exists and teardown comes here again.
+    pub fn destroy_depth(&mut self, context: &VkContext, resources: &mut ResourceRegistry) {
+        let Some(allocation) = self.depth_image_allocation.take() else {
+            return;
+        };
+        resources.unregister(ResourceKind::ImageView, self.depth_image_view);
+        resources.unregister(ResourceKind::Image, self.depth_image);
+        unsafe {
+            context
+                .device
+                .destroy_image_view(self.depth_image_view, None);
+            context.device.destroy_image(self.depth_image, None);
+        }
+        context.allocator.free(allocation);
+        self.depth_image = vk::Image::null();
+        self.depth_image_view = vk::ImageView::null();
+    }
+
+    /// One framebuffer per image, drawing the scene into the matching entry
+    /// of `scene_views` with the depth attachment.
+    /// With `color_view`, the multisampled attachment resolved into each
+    /// scene view.
+    /// With `post_views`, one per image, each image's post-process target
+    /// takes its place in the scene subpass and the image goes last.
+    pub fn create_framebuffers(
+        &mut self,
+        device: &ash::Device,
+        scene_views: &[vk::ImageView],
+        color_view: Option<vk::ImageView>,
+        post_views: &[vk::ImageView],
+        render_pass: vk::RenderPass,
+        extent: vk::Extent2D,
+    ) {
+        self.framebuffers = scene_views
+            .iter()
+            .enumerate()
+            .map(|(i, &view)| {
+                let scene_view = post_views.get(i).copied().unwrap_or(view);
+                let mut attachments = match color_view {
+                    Some(color_view) => vec![color_view, self.depth_image_view, scene_view],
+                    None => vec![scene_view, self.depth_image_view],
+                };
+                if !post_views.is_empty() {
+                    attachments.push(view);
+                }
+                let framebuffer_info = vk::FramebufferCreateInfo::builder()
+                    .render_pass(render_pass)
+                    .attachments(&attachments)
+                    .width(extent.width)
+                    .height(extent.height)
+                    .layers(1);
+                unsafe { device.create_framebuffer(&framebuffer_info, None).unwrap() }
+            })
+            .collect();
+    }
+
+    pub fn destroy_framebuffers(&mut self, device: &ash::Device) {
+        for framebuffer in self.framebuffers.drain(..) {
+            unsafe { device.destroy_framebuffer(framebuffer, None) };
+        }
+    }
+
+    /// What depends on the images and extent: the framebuffers, image views
+    /// and depth attachment. The swapchain itself is left alone.
+    fn destroy_extent_resources(&mut self, context: &VkContext, resources: &mut ResourceRegistry) {
+        self.destroy_framebuffers(&context.device);
+        for image_view in self.image_views.drain(..) {
+            resources.unregister(ResourceKind::ImageView, image_view);
+            unsafe { context.device.destroy_image_view(image_view, None) };
+        }
+        self.destroy_depth(context, resources);
+    }
+
+    /// Everything above, and the swapchain or offscreen image. Only while
+    /// the device is idle.
+    pub fn destroy(&mut self, context: &VkContext, resources: &mut ResourceRegistry) {
+        self.destroy_extent_resources(context, resources);
+        match self.offscreen.take() {
+            Some((image, allocation)) => {
+                resources.unregister(ResourceKind::Image, image);
+                unsafe { context.device.destroy_image(image, None) };
+                context.allocator.free(allocation);
+            }
+            None => unsafe { self.loader.destroy_swapchain(self.handle, None) },
+        }
+        self.handle = vk::SwapchainKHR::null();
+    }
+}
+
+/// A swapchain on `surface` for `window`'s size, with its format, extent
+/// and usage. With `old_swapchain`, the new swapchain takes over from it,
+/// which retires it; the caller destroys it afterwards.
+#[allow(clippy::too_many_arguments)]
+pub fn create_swapchain(
+    context: &VkContext,
+    surface: &SurfaceBundle,
+    old_swapchain: Option<vk::SwapchainKHR>,
+    loader: &khr::Swapchain,
+    present_mode: vk::PresentModeKHR,
+    requested_usage: vk::ImageUsageFlags,
+    window: &winit::window::Window,
+    stage: Stage,
+) -> Result<
+    (
+        vk::SwapchainKHR,
+        vk::Format,
+        vk::Extent2D,
+        vk::ImageUsageFlags,
+    ),
+    VulkanError,
+> {
+    let swapchain_support = context.surface_support(surface);
+    let surface_format = swapchain_support::choose_surface_format(&swapchain_support.formats);
+    let extent = choose_swap_extent(&swapchain_support.capabilities, window);
+    let format_features = unsafe {
+        context
+            .instance
+            .get_physical_device_format_properties(context.physical_device, surface_format.format)
+            .optimal_tiling_features
+    };
+    let usage = swapchain_support::swapchain_usage(
+        requested_usage,
+        swapchain_support.capabilities.supported_usage_flags,
+        surface_format.format,
+        format_features,
+    )
+    .map_err(|message| VulkanError::Config { stage, message })?;
+
+    let mut image_count = swapchain_support.capabilities.min_image_count + 1;
+    if swapchain_support.capabilities.max_image_count > 0
+        && image_count > swapchain_support.capabilities.max_image_count
+    {
+        image_count = swapchain_support.capabilities.max_image_count;
+    }
+
+    let mut create_info = vk::SwapchainCreateInfoKHR::builder()
+        .surface(surface.surface)
+        .min_image_count(image_count)
+        .image_format(surface_format.format)
+        .image_color_space(surface_format.color_space)
+        .image_extent(extent)
+        .image_array_layers(1)
+        .image_usage(usage);
+    if let Some(old_swapchain) = old_swapchain {
+        create_info = create_info.old_swapchain(old_swapchain);
+    }
+
+    let indices = &context.queue_family_indices;
+    let queue_family_indices = [
+        indices.graphics_family.unwrap(),
+        indices.present_family.unwrap(),
+    ];
+
+    if indices.graphics_family != indices.present_family {
+        create_info = create_info
+            .image_sharing_mode(vk::SharingMode::CONCURRENT)
+            .queue_family_indices(&queue_family_indices);
+    } else {
+        create_info = create_info.image_sharing_mode(vk::SharingMode::EXCLUSIVE);
+    }
+
+    let create_info = create_info
+        .pre_transform(swapchain_support.capabilities.current_transform)
+        .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
+        .present_mode(present_mode)
+        .clipped(true);
+
+    let swapchain =
+        unsafe { loader.create_swapchain(&create_info, None) }.map_err(VulkanError::at(stage))?;
+
+    info!(
+        "Surface format: {:?}, {:?}{}",
+        surface_format.format,
+        surface_format.color_space,
+        if swapchain_support::is_unorm_format(surface_format.format) {
+            " (shaders encode sRGB)"
+        } else {
+            ""
+        }
+    );
+    info!("Present mode: {:?}", present_mode);
+    Ok((swapchain, surface_format.format, extent, usage))
+}
+
+fn choose_swap_extent(
+    capabilities: &vk::SurfaceCapabilitiesKHR,
+    window: &winit::window::Window,
+) -> vk::Extent2D {
+    let inner_size = window.inner_size();
+    let window_extent = vk::Extent2D {
+        width: inner_size.width,
+        height: inner_size.height,
+    };
+    let extent = swapchain_support::choose_swap_extent(capabilities, window_extent);
+    if capabilities.current_extent.width == u32::MAX && extent != window_extent {
+        warn!(
+            "Window is {}x{}, outside the surface's {}x{} to {}x{}; using {}x{}",
+            inner_size.width,
+            inner_size.height,
+            capabilities.min_image_extent.width,
+            capabilities.min_image_extent.height,
+            capabilities.max_image_extent.width,
+            capabilities.max_image_extent.height,
+            extent.width,
+            extent.height
+        );
+    }
+    extent
+}
+
+fn create_image_views(
+    device: &ash::Device,
+    images: &[vk::Image],
+    format: vk::Format,
+    resources: &mut ResourceRegistry,
+) -> Vec<vk::ImageView> {
+    images
+        .iter()
+        .enumerate()
+        .map(|(i, &image)| {
+            let create_info = vk::ImageViewCreateInfo::builder()
+                .image(image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(format)
+                .components(vk::ComponentMapping {
+                    r: vk::ComponentSwizzle::IDENTITY,
+                    g: vk::ComponentSwizzle::IDENTITY,
+                    b: vk::ComponentSwizzle::IDENTITY,
+                    a: vk::ComponentSwizzle::IDENTITY,
+                })
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                });
+            let view = unsafe { device.create_image_view(&create_info, None).unwrap() };
+            resources.register(
+                ResourceKind::ImageView,
+                view,
+                &format!("swapchain image view {}", i),
+                0,
+            );
+            view
+        })
+        .collect()
+}
@@ -1,7 +1,181 @@
 use ash::vk;
 
+pub const PREFERRED_SURFACE_FORMAT: vk::SurfaceFormatKHR = vk::SurfaceFormatKHR {
+    format: vk::Format::B8G8R8A8_SRGB,
+    color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+};
+
+/// Picks the swapchain's format from the surface's `available` ones:
+/// PREFERRED_SURFACE_FORMAT, else any other sRGB format in the sRGB colour
+/// space, else a UNORM one in it, which the fragment shaders encode
+/// themselves, else any other in it, else the first. Other colour spaces,
+/// like the extended and HDR ones MoltenVK lists, expect values the shaders
+/// don't write. A single UNDEFINED entry, which older drivers report, means
+/// any format will do.
+pub fn choose_surface_format(available: &[vk::SurfaceFormatKHR]) -> vk::SurfaceFormatKHR {
+    if let [only] = available {
+        if only.format == vk::Format::UNDEFINED {
+            return PREFERRED_SURFACE_FORMAT;
+        }
+    }
+    let nonlinear = || {
+        available
+            .iter()
+            .filter(|format| format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR)
+    };
+    available
+        .iter()
+        .find(|&&format| format == PREFERRED_SURFACE_FORMAT)
+        .or_else(|| nonlinear().find(|format| is_srgb_format(format.format)))
+        .or_else(|| nonlinear().find(|format| is_unorm_format(format.format)))
+        .or_else(|| nonlinear().next())
+        .or(available.first())
+        .copied()
+        .unwrap_or(PREFERRED_SURFACE_FORMAT)
+}
+
+/// Formats the hardware encodes to sRGB on write.
+pub fn is_srgb_format(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::B8G8R8A8_SRGB
+            | vk::Format::R8G8B8A8_SRGB
+            | vk::Format::A8B8G8R8_SRGB_PACK32
+            | vk::Format::B8G8R8_SRGB
+            | vk::Format::R8G8B8_SRGB
+    )
+}
+
+/// Colour formats that store what the shader writes as is. Presented in the
+/// sRGB colour space, the shader has to write sRGB-encoded values.
+pub fn is_unorm_format(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::B8G8R8A8_UNORM
+            | vk::Format::R8G8B8A8_UNORM
+            | vk::Format::A8B8G8R8_UNORM_PACK32
+            | vk::Format::B8G8R8_UNORM
+            | vk::Format::R8G8B8_UNORM
+            | vk::Format::A2B10G10R10_UNORM_PACK32
+            | vk::Format::A2R10G10B10_UNORM_PACK32
+            | vk::Format::R5G6B5_UNORM_PACK16
+            | vk::Format::B5G6R5_UNORM_PACK16
+    )
+}
+
+/// The swapchain's size: the surface's current extent when it has one,
+/// else the `window`'s size clamped to the extents the surface allows.
+pub fn choose_swap_extent(
+    capabilities: &vk::SurfaceCapabilitiesKHR,
+    window: vk::Extent2D,
+) -> vk::Extent2D {
+    if capabilities.current_extent.width != u32::MAX {
+        return capabilities.current_extent;
+    }
+    vk::Extent2D {
+        width: window.width.clamp(
+            capabilities.min_image_extent.width,
+            capabilities.max_image_extent.width,
+        ),
+        height: window.height.clamp(
+            capabilities.min_image_extent.height,
+            capabilities.max_image_extent.height,
+        ),
+    }
+}
+
 pub struct SwapchainSupportDetails {
     pub capabilities: vk::SurfaceCapabilitiesKHR,
     pub formats: Vec<vk::SurfaceFormatKHR>,
     pub present_modes: Vec<vk::PresentModeKHR>,
 }
+
+impl SwapchainSupportDetails {
+    pub fn query(
+        surface_loader: &ash::extensions::khr::Surface,
+        pdevice: vk::PhysicalDevice,
+        surface: vk::SurfaceKHR,
+    ) -> Self {
+        let capabilities = unsafe {
+            surface_loader
+                .get_physical_device_surface_capabilities(pdevice, surface)
+                .unwrap()
+        };
+        let formats = unsafe {
+            surface_loader
+                .get_physical_device_surface_formats(pdevice, surface)
+                .unwrap()
+        };
+        let present_modes = unsafe {
+            surface_loader
+                .get_physical_device_surface_present_modes(pdevice, surface)
+                .unwrap()
+        };
+
+        Self {
+            capabilities,
+            formats,
+            present_modes,
+        }
+    }
+}
+
+/// Usage for the swapchain images: COLOR_ATTACHMENT, TRANSFER_SRC whenever
+/// the surface allows it so frames can be read back, and the `requested`
+/// bits, which must be supported by both the surface and, for STORAGE, the
+/// swapchain format.
+pub fn swapchain_usage(
+    requested: vk::ImageUsageFlags,
+    supported: vk::ImageUsageFlags,
+    format: vk::Format,
+    format_features: vk::FormatFeatureFlags,
+) -> Result<vk::ImageUsageFlags, String> {
+    let missing = requested & !supported;
+    if !missing.is_empty() {
+        return Err(format!(
+            "swapchain usage {:?} was requested but the surface only supports {:?}",
+            missing, supported
+        ));
+    }
+    if requested.contains(vk::ImageUsageFlags::STORAGE)
+        && !format_features.contains(vk::FormatFeatureFlags::STORAGE_IMAGE)
+    {
+        return Err(format!(
+            "swapchain usage STORAGE was requested but the swapchain format {:?} \
+             doesn't support storage images",
+            format
+        ));
+    }
+    Ok(vk::ImageUsageFlags::COLOR_ATTACHMENT
+        | (supported & vk::ImageUsageFlags::TRANSFER_SRC)
+        | requested)
+}
+
+/// Checks that the render pass can leave swapchain images in `layout`, given
+/// their `usage`.
+pub fn check_final_layout(
+    layout: vk::ImageLayout,
+    usage: vk::ImageUsageFlags,
+) -> Result<(), String> {
+    let needed = match layout {
+        vk::ImageLayout::PRESENT_SRC_KHR
+        | vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+        | vk::ImageLayout::GENERAL => return Ok(()),
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL => vk::ImageUsageFlags::TRANSFER_SRC,
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL => vk::ImageUsageFlags::SAMPLED,
+        _ => {
+            return Err(format!(
+                "{:?} is not supported as the swapchain's final layout",
+                layout
+            ))
+        }
+    };
+    if usage.contains(needed) {
+        Ok(())
+    } else {
+        Err(format!(
+            "final layout {:?} needs swapchain usage {:?}, which isn't enabled",
+            layout, needed
+        ))
+    }
+}
@@ -0,0 +1,215 @@
+use super::font::{glyph_rows, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+/// Axis labels in atlas order: face `i` of the labeled cube is cell `i` of
+/// `Pattern::FaceLabels`.
+pub const FACE_LABELS: [&str; 6] = ["+X", "-X", "+Y", "-Y", "+Z", "-Z"];
+/// The face label atlas is a `FACE_LABEL_GRID` x `FACE_LABEL_GRID` grid of cells.
+const FACE_LABEL_GRID: u32 = 4;
+/// Glyph pixels per font pixel at the default 512 texture size.
+const FACE_LABEL_GLYPH_SCALE: u32 = 8;
+const FACE_LABEL_BORDER: u32 = 4;
+const FACE_LABEL_INK: [u8; 4] = [20, 20, 20, 255];
+const FACE_LABEL_PAPER: [u8; 4] = [255, 255, 255, 255];
+const FACE_LABEL_EDGE: [u8; 4] = [90, 90, 90, 255];
+
+/// Procedural RGBA8 patterns, generated straight into a staging buffer so the
+/// repository doesn't need image files for its ground plane and demos.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pattern {
+    Checkerboard {
+        cells: u32,
+        colors: [[u8; 4]; 2],
+    },
+    /// Lines over a fill colour. A transparent fill makes an alpha-tested grid.
+    Grid {
+        cells: u32,
+        line_width: u32,
+        line: [u8; 4],
+        fill: [u8; 4],
+    },
+    /// Red ramps with U and green with V over a coarse checker, which makes
+    /// flipped or swapped texture coordinates obvious.
+    UvTest,
+    /// Tileable value noise: random values on a `cells` x `cells` lattice,
+    /// smoothly interpolated.
+    Noise {
+        seed: u32,
+        cells: u32,
+    },
+    /// One cell per entry of `FACE_LABELS`, the label underlined so a
+    /// flipped or mirrored face reads wrong. Unused cells are plain white,
+    /// for meshes that only want their vertex colour.
+    FaceLabels,
+}
+
+impl Default for Pattern {
+    fn default() -> Self {
+        Pattern::Grid {
+            cells: 16,
+            line_width: 2,
+            line: [230, 230, 230, 255],
+            fill: [40, 40, 40, 0],
+        }
+    }
+}
+
+impl Pattern {
+    /// Parses a `--ground-texture` value, using default parameters for each kind.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "checker" => Some(Pattern::checker()),
+            "grid" => Some(Pattern::default()),
+            "uv" => Some(Pattern::UvTest),
+            "noise" => Some(Pattern::Noise { seed: 1, cells: 8 }),
+            "labels" => Some(Pattern::FaceLabels),
+            _ => None,
+        }
+    }
+
+    pub fn checker() -> Self {
+        Pattern::Checkerboard {
+            cells: 8,
+            colors: [[200, 200, 200, 255], [60, 60, 60, 255]],
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pattern::Checkerboard { .. } => "checker",
+            Pattern::Grid { .. } => "grid",
+            Pattern::UvTest => "uv",
+            Pattern::Noise { .. } => "noise",
+            Pattern::FaceLabels => "labels",
+        }
+    }
+
+    /// Tightly packed RGBA8 pixels for a `size` x `size` image, row by row.
+    pub fn generate(&self, size: u32) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                pixels.extend_from_slice(&self.texel(x, y, size));
+            }
+        }
+        pixels
+    }
+
+    fn texel(&self, x: u32, y: u32, size: u32) -> [u8; 4] {
+        match *self {
+            Pattern::Checkerboard { cells, colors } => {
+                let cell = (size / cells.max(1)).max(1);
+                colors[((x / cell + y / cell) % 2) as usize]
+            }
+            Pattern::Grid {
+                cells,
+                line_width,
+                line,
+                fill,
+            } => {
+                let cell = (size / cells.max(1)).max(1);
+                if x % cell < line_width || y % cell < line_width {
+                    line
+                } else {
+                    fill
+                }
+            }
+            Pattern::UvTest => {
+                let ramp = |i: u32| (i * 255 / (size - 1).max(1)) as u8;
+                let cell = (size / 8).max(1);
+                let shade = if (x / cell + y / cell) % 2 == 0 {
+                    255
+                } else {
+                    160
+                };
+                let scale = |c: u8| (c as u32 * shade / 255) as u8;
+                [scale(ramp(x)), scale(ramp(y)), scale(64), 255]
+            }
+            Pattern::Noise { seed, cells } => {
+                let value = (value_noise(seed, cells.max(1), x, y, size) * 255.0) as u8;
+                [value, value, value, 255]
+            }
+            Pattern::FaceLabels => face_label_texel(x, y, size),
+        }
+    }
+}
+
+/// Where `uv`, in [0, 1] across a face with v = 0 at the top of the label,
+/// lands in the face label atlas.
+pub fn face_label_uv(face: usize, uv: [f32; 2]) -> [f32; 2] {
+    let grid = FACE_LABEL_GRID as usize;
+    let cell = 1.0 / FACE_LABEL_GRID as f32;
+    let (column, row) = ((face % grid) as f32, (face / grid) as f32);
+    [(column + uv[0]) * cell, (row + uv[1]) * cell]
+}
+
+/// The middle of the atlas' blank bottom half, which stays white down to the
+/// smaller mip levels.
+pub const FACE_LABEL_BLANK_UV: [f32; 2] = [0.5, 0.75];
+
+fn face_label_texel(x: u32, y: u32, size: u32) -> [u8; 4] {
+    let cell = (size / FACE_LABEL_GRID).max(1);
+    let index = ((y / cell) * FACE_LABEL_GRID + x / cell) as usize;
+    let Some(label) = FACE_LABELS.get(index) else {
+        return FACE_LABEL_PAPER;
+    };
+    let (lx, ly) = (x % cell, y % cell);
+    let border = FACE_LABEL_BORDER * size / 512;
+    if lx < border || ly < border || lx >= cell - border || ly >= cell - border {
+        return FACE_LABEL_EDGE;
+    }
+
+    // Glyphs are GLYPH_WIDTH wide with a one pixel gap, centred in the cell
+    // and underlined one pixel below the baseline.
+    let scale = (FACE_LABEL_GLYPH_SCALE * size / 512).max(1);
+    let glyphs = label.chars().count() as u32;
+    let text_width = (glyphs * (GLYPH_WIDTH + 1) - 1) * scale;
+    let text_height = (GLYPH_HEIGHT + 2) * scale;
+    let (left, top) = (
+        (cell.saturating_sub(text_width)) / 2,
+        (cell.saturating_sub(text_height)) / 2,
+    );
+    if lx < left || ly < top || lx >= left + text_width || ly >= top + text_height {
+        return FACE_LABEL_PAPER;
+    }
+    let (gx, gy) = ((lx - left) / scale, (ly - top) / scale);
+    if gy == GLYPH_HEIGHT + 1 {
+        return FACE_LABEL_INK;
+    }
+    let column = gx % (GLYPH_WIDTH + 1);
+    let glyph = label.chars().nth((gx / (GLYPH_WIDTH + 1)) as usize);
+    let ink = gy < GLYPH_HEIGHT
+        && column < GLYPH_WIDTH
+        && glyph.is_some_and(|c| glyph_rows(c)[gy as usize] >> (GLYPH_WIDTH - 1 - column) & 1 == 1);
+    if ink {
+        FACE_LABEL_INK
+    } else {
+        FACE_LABEL_PAPER
+    }
+}
+
+fn value_noise(seed: u32, cells: u32, x: u32, y: u32, size: u32) -> f32 {
+    let fx = x as f32 * cells as f32 / size as f32;
+    let fy = y as f32 * cells as f32 / size as f32;
+    let (ix, iy) = (fx as u32, fy as u32);
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, ty) = (smooth(fx - ix as f32), smooth(fy - iy as f32));
+    // Lattice coordinates wrap so the texture tiles.
+    let lattice = |i: u32, j: u32| hash(seed, i % cells, j % cells);
+    let top = lattice(ix, iy) * (1.0 - tx) + lattice(ix + 1, iy) * tx;
+    let bottom = lattice(ix, iy + 1) * (1.0 - tx) + lattice(ix + 1, iy + 1) * tx;
+    top * (1.0 - ty) + bottom * ty
+}
+
+/// A value in [0, 1] that looks random in `seed`, `x` and `y`.
+fn hash(seed: u32, x: u32, y: u32) -> f32 {
+    let mut h = seed
+        .wrapping_mul(0x27d4_eb2d)
+        .wrapping_add(x.wrapping_mul(0x1656_67b1))
+        .wrapping_add(y.wrapping_mul(0x9e37_79b9));
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+    h as f32 / u32::MAX as f32
+}
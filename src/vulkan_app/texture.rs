@@ -0,0 +1,296 @@
+use ash::vk;
+
+use super::allocator::{Allocation, Allocator};
+use super::resources::{ResourceKind, ResourceRegistry};
+
+/// Levels in a full mip chain for an image whose larger side is `size`.
+pub fn mip_levels(size: u32) -> u32 {
+    u32::BITS - size.max(1).leading_zeros()
+}
+
+/// Queue families an upload moves between: the copy runs on `src_family`,
+/// the mip blits and sampling on `dst_family`.
+#[derive(Clone, Copy)]
+pub struct OwnershipTransfer {
+    pub src_family: u32,
+    pub dst_family: u32,
+}
+
+/// A sampled RGBA8 image with its view and sampler.
+pub struct Texture {
+    pub image: vk::Image,
+    pub allocation: Allocation,
+    pub view: vk::ImageView,
+    pub sampler: vk::Sampler,
+    pub width: u32,
+    pub height: u32,
+    pub mip_levels: u32,
+}
+
+impl Texture {
+    /// Records the copy of `staging` into level 0 and a blit down into each
+    /// following level, leaving every level ready to sample in fragment shaders.
+    pub fn record_upload(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        staging: vk::Buffer,
+    ) {
+        self.record_copy(device, command_buffer, staging, None);
+        self.record_mips(device, command_buffer, None);
+    }
+
+    /// Records the copy of `staging` into level 0. With `transfer`, this is
+    /// recorded for the transfer queue and ends by releasing level 0 to the
+    /// graphics family.
+    pub fn record_copy(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        staging: vk::Buffer,
+        transfer: Option<OwnershipTransfer>,
+    ) {
+        let region = vk::BufferImageCopy::builder()
+            .image_subresource(Self::layers(0))
+            .image_extent(vk::Extent3D {
+                width: self.width,
+                height: self.height,
+                depth: 1,
+            })
+            .build();
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[self.barrier(
+                    0,
+                    vk::ImageLayout::UNDEFINED,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::AccessFlags::empty(),
+                    vk::AccessFlags::TRANSFER_WRITE,
+                )],
+            );
+            device.cmd_copy_buffer_to_image(
+                command_buffer,
+                staging,
+                self.image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                std::slice::from_ref(&region),
+            );
+            if let Some(transfer) = transfer {
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[self.ownership_barrier(
+                        transfer,
+                        vk::AccessFlags::TRANSFER_WRITE,
+                        vk::AccessFlags::empty(),
+                    )],
+                );
+            }
+        }
+    }
+
+    /// Records the blits into each level after the first and the transitions
+    /// to SHADER_READ_ONLY_OPTIMAL, on the graphics queue. With `transfer`,
+    /// first acquires the level 0 that `record_copy` released.
+    pub fn record_mips(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        transfer: Option<OwnershipTransfer>,
+    ) {
+        let corner = |level: u32| vk::Offset3D {
+            x: (self.width >> level).max(1) as i32,
+            y: (self.height >> level).max(1) as i32,
+            z: 1,
+        };
+        let to_transfer_dst: Vec<_> = (1..self.mip_levels)
+            .map(|level| {
+                self.barrier(
+                    level,
+                    vk::ImageLayout::UNDEFINED,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::AccessFlags::empty(),
+                    vk::AccessFlags::TRANSFER_WRITE,
+                )
+            })
+            .collect();
+
+        unsafe {
+            if let Some(transfer) = transfer {
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[self.ownership_barrier(
+                        transfer,
+                        vk::AccessFlags::empty(),
+                        vk::AccessFlags::TRANSFER_WRITE,
+                    )],
+                );
+            }
+            if !to_transfer_dst.is_empty() {
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &to_transfer_dst,
+                );
+            }
+
+            for level in 1..self.mip_levels {
+                let source = level - 1;
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[self.barrier(
+                        source,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        vk::AccessFlags::TRANSFER_WRITE,
+                        vk::AccessFlags::TRANSFER_READ,
+                    )],
+                );
+                let blit = vk::ImageBlit::builder()
+                    .src_subresource(Self::layers(source))
+                    .src_offsets([vk::Offset3D::default(), corner(source)])
+                    .dst_subresource(Self::layers(level))
+                    .dst_offsets([vk::Offset3D::default(), corner(level)])
+                    .build();
+                device.cmd_blit_image(
+                    command_buffer,
+                    self.image,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    self.image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    std::slice::from_ref(&blit),
+                    vk::Filter::LINEAR,
+                );
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::FRAGMENT_SHADER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[self.barrier(
+                        source,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                        vk::AccessFlags::TRANSFER_READ,
+                        vk::AccessFlags::SHADER_READ,
+                    )],
+                );
+            }
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[self.barrier(
+                    self.mip_levels - 1,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::AccessFlags::SHADER_READ,
+                )],
+            );
+        }
+    }
+
+    fn barrier(
+        &self,
+        level: u32,
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+        src_access_mask: vk::AccessFlags,
+        dst_access_mask: vk::AccessFlags,
+    ) -> vk::ImageMemoryBarrier {
+        vk::ImageMemoryBarrier::builder()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.image)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: level,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            })
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask)
+            .build()
+    }
+
+    /// The release or acquire half of moving level 0, in
+    /// TRANSFER_DST_OPTIMAL, between the families of `transfer`. Both halves
+    /// must name the same families and layouts.
+    fn ownership_barrier(
+        &self,
+        transfer: OwnershipTransfer,
+        src_access_mask: vk::AccessFlags,
+        dst_access_mask: vk::AccessFlags,
+    ) -> vk::ImageMemoryBarrier {
+        vk::ImageMemoryBarrier {
+            src_queue_family_index: transfer.src_family,
+            dst_queue_family_index: transfer.dst_family,
+            ..self.barrier(
+                0,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                src_access_mask,
+                dst_access_mask,
+            )
+        }
+    }
+
+    fn layers(level: u32) -> vk::ImageSubresourceLayers {
+        vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: level,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    }
+
+    pub fn destroy(
+        &self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        resources: &mut ResourceRegistry,
+    ) {
+        resources.unregister(ResourceKind::Sampler, self.sampler);
+        resources.unregister(ResourceKind::ImageView, self.view);
+        resources.unregister(ResourceKind::Image, self.image);
+        unsafe {
+            device.destroy_sampler(self.sampler, None);
+            device.destroy_image_view(self.view, None);
+            device.destroy_image(self.image, None);
+        }
+        allocator.free(self.allocation);
+    }
+}
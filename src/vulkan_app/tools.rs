@@ -0,0 +1,148 @@
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Transform, Vector3, Vector4};
+
+/// Handle length as a fraction of `GIZMO_SIZE_PIXELS` within which the cursor
+/// ray grabs an axis.
+const PICK_RADIUS: f32 = 0.08;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    /// Unprojects a cursor position in normalized device coordinates
+    /// (`-1..1`, y up) through a conventional `view_proj`.
+    pub fn from_cursor(ndc_x: f32, ndc_y: f32, view_proj: Matrix4<f32>) -> Option<Self> {
+        let inverse = view_proj.invert()?;
+        let unproject = |z: f32| {
+            let p = inverse * Vector4::new(ndc_x, ndc_y, z, 1.0);
+            Point3::new(p.x / p.w, p.y / p.w, p.z / p.w)
+        };
+        let near = unproject(-1.0);
+        let far = unproject(1.0);
+        Some(Self {
+            origin: near,
+            direction: (far - near).normalize(),
+        })
+    }
+
+    pub fn at(&self, t: f32) -> Point3<f32> {
+        self.origin + self.direction * t
+    }
+
+    /// Distance along the ray to the plane through `point` with `normal`, if
+    /// the plane is in front of the origin and not parallel to the ray.
+    pub fn intersect_plane(&self, point: Point3<f32>, normal: Vector3<f32>) -> Option<f32> {
+        let denom = normal.dot(self.direction);
+        if denom.abs() < 1e-6 {
+            return None;
+        }
+        let t = normal.dot(point - self.origin) / denom;
+        (t >= 0.0).then_some(t)
+    }
+
+    /// Closest distance between the ray and the segment `start..start + axis * length`.
+    fn distance_to_segment(&self, start: Point3<f32>, axis: Vector3<f32>, length: f32) -> f32 {
+        let w = self.origin - start;
+        let b = self.direction.dot(axis);
+        let d = self.direction.dot(w);
+        let e = axis.dot(w);
+        let denom = 1.0 - b * b;
+        let s = if denom.abs() < 1e-6 {
+            0.0
+        } else {
+            ((e - b * d) / denom).clamp(0.0, length)
+        };
+        let t = (b * s - d).max(0.0);
+        (self.at(t) - (start + axis * s)).magnitude()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GizmoAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl GizmoAxis {
+    pub const ALL: [GizmoAxis; 3] = [GizmoAxis::X, GizmoAxis::Y, GizmoAxis::Z];
+
+    pub fn direction(self) -> Vector3<f32> {
+        match self {
+            GizmoAxis::X => Vector3::unit_x(),
+            GizmoAxis::Y => Vector3::unit_y(),
+            GizmoAxis::Z => Vector3::unit_z(),
+        }
+    }
+}
+
+/// World-space length that covers `pixels` on screen at `distance` from the
+/// camera, so the gizmo keeps a constant on-screen size.
+pub fn gizmo_scale(distance: f32, fov_y: f32, viewport_height: u32, pixels: f32) -> f32 {
+    2.0 * distance * (fov_y * 0.5).tan() * pixels / viewport_height.max(1) as f32
+}
+
+/// Translate gizmo handle under `ray`, nearest first. Handles are tested before
+/// scene geometry so they win over whatever is behind them.
+pub fn pick_axis(ray: &Ray, origin: Point3<f32>, scale: f32) -> Option<GizmoAxis> {
+    GizmoAxis::ALL
+        .into_iter()
+        .map(|axis| {
+            (
+                axis,
+                ray.distance_to_segment(origin, axis.direction(), scale),
+            )
+        })
+        .filter(|&(_, distance)| distance <= scale * PICK_RADIUS)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(axis, _)| axis)
+}
+
+/// Translation constrained to one axis. The cursor ray is intersected with the
+/// plane containing the axis that faces the camera most directly.
+#[derive(Clone, Copy, Debug)]
+pub struct AxisDrag {
+    pub axis: GizmoAxis,
+    start_position: Point3<f32>,
+    start_hit: Point3<f32>,
+    plane_normal: Vector3<f32>,
+}
+
+impl AxisDrag {
+    pub fn begin(
+        axis: GizmoAxis,
+        position: Point3<f32>,
+        eye: Point3<f32>,
+        ray: &Ray,
+    ) -> Option<Self> {
+        let direction = axis.direction();
+        let to_eye = eye - position;
+        let plane_normal = to_eye - direction * to_eye.dot(direction);
+        if plane_normal.magnitude2() < 1e-8 {
+            // Looking straight down the axis: there is no usable drag plane.
+            return None;
+        }
+        let plane_normal = plane_normal.normalize();
+        let t = ray.intersect_plane(position, plane_normal)?;
+        Some(Self {
+            axis,
+            start_position: position,
+            start_hit: ray.at(t),
+            plane_normal,
+        })
+    }
+
+    /// New position for the dragged object, or `None` if the ray misses the plane.
+    pub fn update(&self, ray: &Ray) -> Option<Point3<f32>> {
+        let t = ray.intersect_plane(self.start_position, self.plane_normal)?;
+        let direction = self.axis.direction();
+        let offset = (ray.at(t) - self.start_hit).dot(direction);
+        Some(self.start_position + direction * offset)
+    }
+}
+
+pub fn translation_of(transform: &Matrix4<f32>) -> Point3<f32> {
+    transform.transform_point(Point3::new(0.0, 0.0, 0.0))
+}
@@ -1,8 +1,94 @@
+use ash::vk;
 use cgmath::Matrix4;
 
+use super::draw_constants::DrawConstants;
+
+/// Vulkan caps `minUniformBufferOffsetAlignment` at 256, so blocks placed on
+/// 256-byte boundaries can be bound at their offsets on any device.
+pub const UNIFORM_BLOCK_ALIGNMENT: vk::DeviceSize = 256;
+
+/// Views rendered each frame; each binds its own `PerViewUbo` at set 1.
+pub const VIEW_COUNT: usize = 2;
+pub const MAIN_VIEW: usize = 0;
+/// Only drawn while the magnifier is held.
+pub const MAGNIFIER_VIEW: usize = 1;
+
+/// Lights the per-frame block has room for; MAX_LIGHTS in common.glsl.
+pub const MAX_LIGHTS: usize = 4;
+
+/// `size` rounded up to a multiple of `alignment`, a power of two as
+/// Vulkan's alignment limits are.
+pub fn align_up(size: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    (size + alignment - 1) & !(alignment - 1)
+}
+
+/// Each swapchain image's uniform buffer holds the frame block, one block
+/// per view, then `draw_slots` per-draw slots, `slot_stride` apart, for
+/// draw constants that didn't fit in push constants.
+pub fn uniform_buffer_size(draw_slots: usize, slot_stride: vk::DeviceSize) -> vk::DeviceSize {
+    draw_slot_offset(draw_slots, slot_stride)
+}
+
+pub const PER_FRAME_OFFSET: vk::DeviceSize = 0;
+
+pub fn per_view_offset(view_index: usize) -> vk::DeviceSize {
+    UNIFORM_BLOCK_ALIGNMENT * (1 + view_index as vk::DeviceSize)
+}
+
+/// The slots start on a UNIFORM_BLOCK_ALIGNMENT boundary, which every
+/// device's `minUniformBufferOffsetAlignment` divides.
+pub fn draw_slot_offset(slot: usize, slot_stride: vk::DeviceSize) -> vk::DeviceSize {
+    per_view_offset(VIEW_COUNT) + slot as vk::DeviceSize * slot_stride
+}
+
+/// A directional light, `Light` in common.glsl.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct Light {
+    /// xyz towards the light, unit length. w is unused.
+    pub direction: [f32; 4],
+    /// rgb in linear colour, w the specular strength.
+    pub color: [f32; 4],
+}
+
+/// Set 0: data every view of a frame shares.
+#[repr(C)]
 #[derive(Copy, Clone)]
-pub struct UniformBufferObject {
-    pub model: Matrix4<f32>,
+pub struct PerFrameUbo {
+    pub camera_position: [f32; 4],
+    pub time: f32,
+    /// How many of `lights`, from the first, light the scene.
+    pub light_count: u32,
+    /// Light every surface gets, whichever way it faces.
+    pub ambient: f32,
+    pub _padding: f32,
+    pub lights: [Light; MAX_LIGHTS],
+}
+
+/// Set 1: one camera.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct PerViewUbo {
     pub view: Matrix4<f32>,
     pub proj: Matrix4<f32>,
+    /// x, y, width, height in pixels.
+    pub viewport: [f32; 4],
 }
+
+// PER_FRAME_UBO_FIELDS and PER_VIEW_UBO_FIELDS in common.glsl. Under std140
+// the light array after `ambient` starts on the next 16-byte boundary, and
+// its elements are 16-byte aligned.
+const _: () = assert!(std::mem::size_of::<Light>() == 32);
+const _: () = assert!(std::mem::size_of::<PerFrameUbo>() == 32 + 32 * MAX_LIGHTS);
+const _: () = assert!(std::mem::offset_of!(PerFrameUbo, time) == 16);
+const _: () = assert!(std::mem::offset_of!(PerFrameUbo, light_count) == 20);
+const _: () = assert!(std::mem::offset_of!(PerFrameUbo, ambient) == 24);
+const _: () = assert!(std::mem::offset_of!(PerFrameUbo, lights) == 32);
+const _: () = assert!(std::mem::size_of::<PerViewUbo>() == 144);
+const _: () = assert!(std::mem::offset_of!(PerViewUbo, viewport) == 128);
+const _: () =
+    assert!(std::mem::size_of::<PerFrameUbo>() as vk::DeviceSize <= UNIFORM_BLOCK_ALIGNMENT);
+const _: () =
+    assert!(std::mem::size_of::<PerViewUbo>() as vk::DeviceSize <= UNIFORM_BLOCK_ALIGNMENT);
+const _: () =
+    assert!(std::mem::size_of::<DrawConstants>() as vk::DeviceSize <= UNIFORM_BLOCK_ALIGNMENT);
@@ -1,11 +1,19 @@
 use ash::vk;
 use std::mem::offset_of;
 
+use super::meshopt::HasPosition;
+use super::texgen::{face_label_uv, FACE_LABEL_BLANK_UV};
+
 #[derive(Clone, Debug, Copy)]
 #[repr(C)]
 pub struct Vertex {
     pub pos: [f32; 3],
     pub color: [f32; 3],
+    /// Into the `Pattern::FaceLabels` atlas, where unlabeled meshes point at
+    /// its blank area, or across the whole cube texture.
+    pub uv: [f32; 2],
+    /// Unit length, in model space.
+    pub normal: [f32; 3],
 }
 
 impl Vertex {
@@ -17,7 +25,7 @@ impl Vertex {
             .build()
     }
 
-    pub fn get_attribute_descriptions() -> [vk::VertexInputAttributeDescription; 2] {
+    pub fn get_attribute_descriptions() -> [vk::VertexInputAttributeDescription; 4] {
         [
             vk::VertexInputAttributeDescription::builder()
                 .binding(0)
@@ -31,50 +39,140 @@ impl Vertex {
                 .format(vk::Format::R32G32B32_SFLOAT)
                 .offset(offset_of!(Self, color) as u32)
                 .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(2)
+                .format(vk::Format::R32G32_SFLOAT)
+                .offset(offset_of!(Self, uv) as u32)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(3)
+                .format(vk::Format::R32G32B32_SFLOAT)
+                .offset(offset_of!(Self, normal) as u32)
+                .build(),
         ]
     }
 }
 
-pub const VERTICES: [Vertex; 8] = [
-    Vertex {
-        pos: [-0.5, -0.5, 0.0],
-        color: [1.0, 0.0, 0.0],
-    },
-    Vertex {
-        pos: [0.5, -0.5, 0.0],
-        color: [0.0, 1.0, 0.0],
-    },
-    Vertex {
-        pos: [0.5, 0.5, 0.0],
-        color: [0.0, 0.0, 1.0],
-    },
-    Vertex {
-        pos: [-0.5, 0.5, 0.0],
-        color: [1.0, 1.0, 1.0],
-    },
-    Vertex {
-        pos: [-0.5, -0.5, -0.5],
-        color: [1.0, 0.0, 0.0],
-    },
-    Vertex {
-        pos: [0.5, -0.5, -0.5],
-        color: [0.0, 1.0, 0.0],
-    },
-    Vertex {
-        pos: [0.5, 0.5, -0.5],
-        color: [0.0, 0.0, 1.0],
-    },
-    Vertex {
-        pos: [-0.5, 0.5, -0.5],
-        color: [1.0, 1.0, 1.0],
-    },
+impl HasPosition for Vertex {
+    fn position_mut(&mut self) -> &mut [f32; 3] {
+        &mut self.pos
+    }
+}
+
+/// The colour cube's corners. Faces don't share them, so each face can
+/// carry its own normal.
+const CORNERS: [([f32; 3], [f32; 3]); 8] = [
+    ([-0.5, -0.5, 0.0], [1.0, 0.0, 0.0]),
+    ([0.5, -0.5, 0.0], [0.0, 1.0, 0.0]),
+    ([0.5, 0.5, 0.0], [0.0, 0.0, 1.0]),
+    ([-0.5, 0.5, 0.0], [1.0, 1.0, 1.0]),
+    ([-0.5, -0.5, -0.5], [1.0, 0.0, 0.0]),
+    ([0.5, -0.5, -0.5], [0.0, 1.0, 0.0]),
+    ([0.5, 0.5, -0.5], [0.0, 0.0, 1.0]),
+    ([-0.5, 0.5, -0.5], [1.0, 1.0, 1.0]),
 ];
 
-pub const INDICES: [u16; 36] = [
-    0, 1, 2, 2, 3, 0, // front
-    4, 6, 5, 4, 7, 6, // back
-    0, 7, 4, 0, 3, 7, // left
-    1, 5, 6, 6, 2, 1, // right
-    3, 2, 6, 6, 7, 3, // top
-    0, 5, 1, 5, 0, 4, // bottom
+/// The cube's faces as quads of `CORNERS` indices, in `FACE_LABELS` order.
+/// Each starts at the bottom-left corner as seen from outside, and all wind
+/// the same way. "Up" is +Y on the side faces, -Z on top and +Z
+/// underneath.
+const FACE_QUADS: [[usize; 4]; 6] = [
+    [1, 5, 6, 2], // +X
+    [4, 0, 3, 7], // -X
+    [3, 2, 6, 7], // +Y
+    [4, 5, 1, 0], // -Y
+    [0, 1, 2, 3], // +Z
+    [5, 4, 7, 6], // -Z
 ];
+
+const FACE_NORMALS: [[f32; 3]; 6] = [
+    [1.0, 0.0, 0.0],
+    [-1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0],
+    [0.0, -1.0, 0.0],
+    [0.0, 0.0, 1.0],
+    [0.0, 0.0, -1.0],
+];
+
+/// Two triangles over a face's four vertices.
+const QUAD_INDICES: [u16; 6] = [0, 1, 2, 2, 3, 0];
+
+/// Four vertices per face, in `FACE_QUADS` order.
+pub const VERTICES: [Vertex; 24] = {
+    let mut vertices = [Vertex {
+        pos: [0.0; 3],
+        color: [0.0; 3],
+        uv: FACE_LABEL_BLANK_UV,
+        normal: [0.0; 3],
+    }; 24];
+    let mut i = 0;
+    while i < vertices.len() {
+        let (pos, color) = CORNERS[FACE_QUADS[i / 4][i % 4]];
+        vertices[i].pos = pos;
+        vertices[i].color = color;
+        vertices[i].normal = FACE_NORMALS[i / 4];
+        i += 1;
+    }
+    vertices
+};
+
+pub const INDICES: [u16; 36] = {
+    let mut indices = [0; 36];
+    let mut i = 0;
+    while i < indices.len() {
+        indices[i] = (i / 6 * 4) as u16 + QUAD_INDICES[i % 6];
+        i += 1;
+    }
+    indices
+};
+
+/// Light tints per axis so X, Y and Z faces tell apart at a glance.
+const FACE_TINTS: [[f32; 3]; 3] = [[1.0, 0.7, 0.7], [0.7, 1.0, 0.7], [0.7, 0.7, 1.0]];
+
+/// The cube with four vertices per face, each face textured with its cell of
+/// the face label atlas.
+pub fn labeled_cube() -> (Vec<Vertex>, Vec<u16>) {
+    face_cube(|face, _, uv| (FACE_TINTS[face / 2], face_label_uv(face, uv)))
+}
+
+/// The cube with four vertices per face, each face showing the whole of a
+/// texture, upright, over the colour cube's vertex colours.
+pub fn textured_cube() -> (Vec<Vertex>, Vec<u16>) {
+    face_cube(|_, corner, uv| (CORNERS[corner].1, uv))
+}
+
+/// The colour cube without its top and bottom, a tube whose inside only
+/// shows when drawn double-sided.
+pub fn open_box() -> (Vec<Vertex>, Vec<u16>) {
+    let (vertices, indices) = face_cube(|_, corner, _| (CORNERS[corner].1, FACE_LABEL_BLANK_UV));
+    // The Z faces come last in `FACE_QUADS`, so dropping them keeps the rest
+    // of the indices as they are.
+    (vertices[..16].to_vec(), indices[..24].to_vec())
+}
+
+/// Builds a cube face by face from `FACE_QUADS`. `attributes` gets the face,
+/// the corner's index into `CORNERS` and its uv across the face, v = 0 at
+/// the top, and returns the vertex's colour and uv.
+fn face_cube(
+    attributes: impl Fn(usize, usize, [f32; 2]) -> ([f32; 3], [f32; 2]),
+) -> (Vec<Vertex>, Vec<u16>) {
+    const CORNER_UVS: [[f32; 2]; 4] = [[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]];
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+    for (face, quad) in FACE_QUADS.iter().enumerate() {
+        let first = vertices.len() as u16;
+        for (&corner, &uv) in quad.iter().zip(&CORNER_UVS) {
+            let (color, uv) = attributes(face, corner, uv);
+            vertices.push(Vertex {
+                pos: CORNERS[corner].0,
+                color,
+                uv,
+                normal: FACE_NORMALS[face],
+            });
+        }
+        indices.extend(QUAD_INDICES.map(|i| first + i));
+    }
+    (vertices, indices)
+}
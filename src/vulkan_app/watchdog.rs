@@ -0,0 +1,217 @@
+use ash::vk;
+use log::warn;
+use std::ffi::{c_void, CStr};
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use super::allocator::{Allocation, Allocator};
+use super::debug::ValidationLog;
+use super::journal::Journal;
+use super::resources::{ResourceKind, ResourceRegistry};
+use super::WATCHDOG_LOG_PATH;
+
+/// Points in `record_command_buffer` that leave a marker in the GPU stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Checkpoint {
+    BeginCommandBuffer,
+    BeginRenderPass,
+    DrawCube,
+    EndRenderPass,
+}
+
+impl Checkpoint {
+    const ALL: [Checkpoint; 4] = [
+        Checkpoint::BeginCommandBuffer,
+        Checkpoint::BeginRenderPass,
+        Checkpoint::DrawCube,
+        Checkpoint::EndRenderPass,
+    ];
+
+    fn label(self) -> &'static CStr {
+        match self {
+            Checkpoint::BeginCommandBuffer => c"begin command buffer",
+            Checkpoint::BeginRenderPass => c"begin render pass",
+            Checkpoint::DrawCube => c"draw cube",
+            Checkpoint::EndRenderPass => c"end render pass",
+        }
+    }
+
+    // Zero is left for "no marker written yet".
+    fn marker(self) -> u32 {
+        self as u32 + 1
+    }
+
+    fn from_marker(marker: u32) -> Option<Self> {
+        Self::ALL.iter().copied().find(|c| c.marker() == marker)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkerExtension {
+    None,
+    NvCheckpoints,
+    AmdBufferMarker,
+}
+
+impl MarkerExtension {
+    pub fn name(self) -> Option<&'static CStr> {
+        match self {
+            MarkerExtension::None => None,
+            MarkerExtension::NvCheckpoints => {
+                Some(ash::extensions::nv::DeviceDiagnosticCheckpoints::name())
+            }
+            MarkerExtension::AmdBufferMarker => Some(vk::AmdBufferMarkerFn::name()),
+        }
+    }
+}
+
+pub enum MarkerBackend {
+    None,
+    NvCheckpoints(ash::extensions::nv::DeviceDiagnosticCheckpoints),
+    AmdBufferMarker {
+        fp: vk::AmdBufferMarkerFn,
+        buffer: vk::Buffer,
+        allocation: Allocation,
+    },
+}
+
+pub struct Watchdog {
+    timeout_ns: u64,
+    device_name: String,
+    backend: MarkerBackend,
+    last_submitted_frame: Option<u64>,
+}
+
+impl Watchdog {
+    pub fn new(timeout_ms: u64, device_name: String, backend: MarkerBackend) -> Self {
+        Self {
+            timeout_ns: timeout_ms * 1_000_000,
+            device_name,
+            backend,
+            last_submitted_frame: None,
+        }
+    }
+
+    pub fn timeout_ns(&self) -> u64 {
+        self.timeout_ns
+    }
+
+    pub fn mark_submitted(&mut self, frame: u64) {
+        self.last_submitted_frame = Some(frame);
+    }
+
+    pub fn checkpoint(&self, command_buffer: vk::CommandBuffer, checkpoint: Checkpoint) {
+        match &self.backend {
+            MarkerBackend::None => {}
+            MarkerBackend::NvCheckpoints(loader) => unsafe {
+                loader.cmd_set_checkpoint(
+                    command_buffer,
+                    checkpoint.label().as_ptr() as *const c_void,
+                );
+            },
+            MarkerBackend::AmdBufferMarker { fp, buffer, .. } => unsafe {
+                (fp.cmd_write_buffer_marker_amd)(
+                    command_buffer,
+                    vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                    *buffer,
+                    0,
+                    checkpoint.marker(),
+                );
+            },
+        }
+    }
+
+    /// Appends a hang report to the watchdog log and returns it for the console.
+    pub fn dump_report(
+        &self,
+        queue: vk::Queue,
+        validation_log: &ValidationLog,
+        journal: &Journal,
+        attempt: u32,
+    ) -> String {
+        let mut report = format!(
+            "=== GPU watchdog: fence not signalled after {} ms (attempt {}) ===\n",
+            self.timeout_ns / 1_000_000,
+            attempt
+        );
+        report += &format!("device: {}\n", self.device_name);
+        match self.last_submitted_frame {
+            Some(frame) => report += &format!("last submitted frame: {}\n", frame),
+            None => report += "last submitted frame: none\n",
+        }
+
+        report += "last checkpoints:\n";
+        for line in self.checkpoint_lines(queue) {
+            report += &format!("  {}\n", line);
+        }
+
+        report += "recent validation messages:\n";
+        let messages = validation_log.recent();
+        if messages.is_empty() {
+            report += "  (none)\n";
+        }
+        for message in messages {
+            report += &format!("  {}\n", message);
+        }
+        report += &journal.dump();
+
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(WATCHDOG_LOG_PATH)
+            .and_then(|mut file| file.write_all(report.as_bytes()));
+        if let Err(error) = written {
+            warn!("Failed to write {}: {}", WATCHDOG_LOG_PATH, error);
+        }
+
+        report
+    }
+
+    fn checkpoint_lines(&self, queue: vk::Queue) -> Vec<String> {
+        match &self.backend {
+            MarkerBackend::None => vec!["(no checkpoint extension available)".to_string()],
+            MarkerBackend::NvCheckpoints(loader) => unsafe {
+                let count = loader.get_queue_checkpoint_data_len(queue);
+                let mut data = vec![vk::CheckpointDataNV::default(); count];
+                loader.get_queue_checkpoint_data(queue, &mut data);
+                data.iter()
+                    .map(|entry| {
+                        let label = if entry.p_checkpoint_marker.is_null() {
+                            "?".into()
+                        } else {
+                            CStr::from_ptr(entry.p_checkpoint_marker as *const _).to_string_lossy()
+                        };
+                        format!("{:?}: {}", entry.stage, label)
+                    })
+                    .collect()
+            },
+            MarkerBackend::AmdBufferMarker { allocation, .. } => unsafe {
+                let marker = *(allocation.mapped_ptr() as *const u32);
+                match Checkpoint::from_marker(marker) {
+                    Some(checkpoint) => vec![format!(
+                        "BOTTOM_OF_PIPE: {}",
+                        checkpoint.label().to_string_lossy()
+                    )],
+                    None => vec!["(no marker reached)".to_string()],
+                }
+            },
+        }
+    }
+
+    pub fn destroy(
+        &mut self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        resources: &mut ResourceRegistry,
+    ) {
+        if let MarkerBackend::AmdBufferMarker {
+            buffer, allocation, ..
+        } = self.backend
+        {
+            resources.unregister(ResourceKind::Buffer, buffer);
+            unsafe { device.destroy_buffer(buffer, None) };
+            allocator.free(allocation);
+        }
+        self.backend = MarkerBackend::None;
+    }
+}
@@ -0,0 +1,145 @@
+//! The device memory allocator's bookkeeping: placing aligned ranges in a
+//! block, reusing and coalescing freed ones, and sizing new blocks.
+
+use rust_vulkan::vulkan_app::{new_block_size, standard_block_size, FreeList, MEMORY_BLOCK_SIZE};
+
+const MIB: u64 = 1 << 20;
+
+/// The free ranges as (start, end) pairs.
+fn free(list: &FreeList) -> Vec<(u64, u64)> {
+    list.free_ranges()
+        .iter()
+        .map(|range| (range.start, range.end))
+        .collect()
+}
+
+#[test]
+fn ranges_start_at_their_alignment_and_padding_stays_free() {
+    let mut list = FreeList::new(1024);
+    assert_eq!(list.allocate(10, 1), Some(0..10));
+    assert_eq!(list.allocate(16, 256), Some(256..272));
+    assert_eq!(free(&list), [(10, 256), (272, 1024)]);
+    // The padding is used by the next allocation that fits it.
+    assert_eq!(list.allocate(100, 4), Some(12..112));
+    assert_eq!(list.used(), 10 + 16 + 100);
+}
+
+#[test]
+fn mixed_allocations_never_overlap() {
+    let mut list = FreeList::new(64 * 1024);
+    let mut allocated = Vec::new();
+    for i in 0..200u64 {
+        let size = 1 + i * 37 % 300;
+        let alignment = [1, 4, 16, 64, 256][i as usize % 5];
+        let range = list.allocate(size, alignment).unwrap();
+        assert_eq!(range.start % alignment, 0);
+        assert_eq!(range.end - range.start, size);
+        allocated.push(range);
+    }
+    allocated.sort_by_key(|range| range.start);
+    for pair in allocated.windows(2) {
+        assert!(pair[0].end <= pair[1].start, "{:?} overlaps", pair);
+    }
+    let total: u64 = allocated.iter().map(|range| range.end - range.start).sum();
+    assert_eq!(list.used(), total);
+}
+
+#[test]
+fn freed_ranges_are_reused_first_fit() {
+    let mut list = FreeList::new(1024);
+    let ranges: Vec<_> = (0..4).map(|_| list.allocate(128, 64).unwrap()).collect();
+    list.release(ranges[1].clone());
+    assert_eq!(list.allocate(128, 64), Some(ranges[1].clone()));
+    // A smaller request goes in the first hole and leaves the rest of it.
+    list.release(ranges[2].clone());
+    assert_eq!(list.allocate(64, 64), Some(256..320));
+    assert_eq!(free(&list), [(320, 384), (512, 1024)]);
+}
+
+#[test]
+fn freed_neighbours_coalesce() {
+    let mut list = FreeList::new(300);
+    let [a, b, c] = [100, 100, 100].map(|size| list.allocate(size, 1).unwrap());
+    assert!(free(&list).is_empty());
+
+    list.release(b.clone());
+    assert_eq!(free(&list), [(100, 200)]);
+    list.release(a.clone());
+    assert_eq!(free(&list), [(0, 200)]);
+    list.release(c.clone());
+    assert_eq!(free(&list), [(0, 300)]);
+    assert_eq!(list.used(), 0);
+
+    // Releasing the middle last joins both sides at once.
+    let [a, b, c] = [100, 100, 100].map(|size| list.allocate(size, 1).unwrap());
+    list.release(a);
+    list.release(c);
+    assert_eq!(free(&list), [(0, 100), (200, 300)]);
+    list.release(b);
+    assert_eq!(free(&list), [(0, 300)]);
+}
+
+#[test]
+fn releasing_nothing_changes_nothing() {
+    let mut list = FreeList::new(256);
+    list.allocate(64, 1).unwrap();
+    list.release(10..10);
+    assert_eq!(free(&list), [(64, 256)]);
+}
+
+#[test]
+fn a_fragmented_block_turns_away_what_only_fits_in_total() {
+    let mut list = FreeList::new(1024);
+    let ranges: Vec<_> = (0..8).map(|_| list.allocate(128, 1).unwrap()).collect();
+    for range in ranges.iter().step_by(2) {
+        list.release(range.clone());
+    }
+    assert_eq!(list.used(), 512);
+    // 512 bytes are free but no 256 of them in a row, so the allocator
+    // needs a new block.
+    assert_eq!(list.allocate(256, 1), None);
+    assert_eq!(list.allocate(128, 1), Some(0..128));
+}
+
+#[test]
+fn blocks_grow_from_a_full_one_into_a_new_one() {
+    // The allocator's search: the first block with room, else a new one.
+    let standard = standard_block_size(8 << 30);
+    let mut blocks = vec![FreeList::new(standard)];
+    let request = 24 * MIB;
+    let mut placed = Vec::new();
+    for _ in 0..5 {
+        let found = blocks
+            .iter_mut()
+            .enumerate()
+            .find_map(|(index, block)| Some((index, block.allocate(request, 256)?)));
+        let (index, range) = found.unwrap_or_else(|| {
+            let mut block = FreeList::new(new_block_size(request, standard));
+            let range = block.allocate(request, 256).unwrap();
+            blocks.push(block);
+            (blocks.len() - 1, range)
+        });
+        placed.push((index, range.start));
+    }
+    // Two 24 MiB ranges fit a 64 MiB block, the third starts another.
+    assert_eq!(placed, [(0, 0), (0, request), (1, 0), (1, request), (2, 0)]);
+}
+
+#[test]
+fn new_blocks_are_standard_unless_the_request_is_over_half_of_one() {
+    let standard = 64 * MIB;
+    assert_eq!(new_block_size(1, standard), standard);
+    assert_eq!(new_block_size(32 * MIB, standard), standard);
+    assert_eq!(new_block_size(32 * MIB + 1, standard), 32 * MIB + 1);
+    assert_eq!(new_block_size(300 * MIB, standard), 300 * MIB);
+}
+
+#[test]
+fn small_heaps_get_smaller_blocks() {
+    assert_eq!(standard_block_size(8 << 30), MEMORY_BLOCK_SIZE);
+    assert_eq!(standard_block_size(256 * MIB), 32 * MIB);
+    assert_eq!(
+        standard_block_size(8 * MEMORY_BLOCK_SIZE),
+        MEMORY_BLOCK_SIZE
+    );
+}
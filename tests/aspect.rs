@@ -0,0 +1,70 @@
+//! Where letterboxing puts the scene.
+
+use ash::vk;
+use rust_vulkan::vulkan_app::{bars, AspectPolicy};
+
+fn extent(width: u32, height: u32) -> vk::Extent2D {
+    vk::Extent2D { width, height }
+}
+
+fn rect(x: i32, y: i32, width: u32, height: u32) -> vk::Rect2D {
+    vk::Rect2D {
+        offset: vk::Offset2D { x, y },
+        extent: extent(width, height),
+    }
+}
+
+#[test]
+fn stretching_fills_the_framebuffer() {
+    assert_eq!(
+        AspectPolicy::Stretch.scene_rect(extent(1600, 300)),
+        rect(0, 0, 1600, 300)
+    );
+}
+
+#[test]
+fn wide_windows_are_pillarboxed_and_tall_ones_letterboxed() {
+    let four_three = AspectPolicy::Letterbox(4.0 / 3.0);
+    assert_eq!(
+        four_three.scene_rect(extent(1600, 600)),
+        rect(400, 0, 800, 600)
+    );
+    assert_eq!(
+        four_three.scene_rect(extent(800, 1200)),
+        rect(0, 300, 800, 600)
+    );
+    assert_eq!(
+        four_three.scene_rect(extent(800, 600)),
+        rect(0, 0, 800, 600)
+    );
+}
+
+#[test]
+fn degenerate_windows_still_get_a_pixel() {
+    let four_three = AspectPolicy::Letterbox(4.0 / 3.0);
+    for size in [extent(1, 1000), extent(1000, 1), extent(0, 0)] {
+        let scene = four_three.scene_rect(size);
+        assert!(scene.extent.width >= 1 && scene.extent.height >= 1);
+    }
+    let scene = AspectPolicy::Letterbox(0.0).scene_rect(extent(640, 480));
+    assert_eq!(scene, rect(0, 0, 640, 480));
+}
+
+#[test]
+fn the_bars_cover_the_rest_of_the_framebuffer() {
+    let size = extent(1600, 600);
+    let scene = AspectPolicy::Letterbox(4.0 / 3.0).scene_rect(size);
+    assert_eq!(
+        bars(size, scene),
+        [rect(0, 0, 400, 600), rect(1200, 0, 400, 600)]
+    );
+    assert!(bars(size, AspectPolicy::Stretch.scene_rect(size)).is_empty());
+}
+
+#[test]
+fn ratios_read_as_width_by_height_or_a_number() {
+    assert_eq!(AspectPolicy::parse_ratio("16:9"), Some(16.0 / 9.0));
+    assert_eq!(AspectPolicy::parse_ratio("1.5"), Some(1.5));
+    assert_eq!(AspectPolicy::parse_ratio("4:0"), None);
+    assert_eq!(AspectPolicy::parse_ratio("wide"), None);
+}
@@ -0,0 +1,80 @@
+//! Shared by the integration tests that need a Vulkan driver.
+
+use rust_vulkan::vulkan_app::{self, decode_png, RendererConfig, VulkanApp};
+use std::path::PathBuf;
+
+/// A golden pixel whose channels all stay within this of the frame's
+/// matches; drivers rasterize and filter edges slightly differently.
+const GOLDEN_CHANNEL_TOLERANCE: u8 = 24;
+/// Pixels in a thousand that may differ from the golden image.
+const GOLDEN_MISMATCH_PER_MILLE: usize = 5;
+
+/// Size of the offscreen image the tests render into.
+pub const WIDTH: u32 = 256;
+pub const HEIGHT: u32 = 256;
+
+/// A renderer with no window, drawing into a `WIDTH` x `HEIGHT` image,
+/// with the default config. Validation is on in debug builds, so the
+/// Khronos layers must be installed as well as a Vulkan driver; a software
+/// one such as lavapipe or SwiftShader will do.
+pub fn headless_app() -> VulkanApp {
+    headless_app_with(RendererConfig::default())
+}
+
+/// `headless_app` with `config` instead of the defaults.
+pub fn headless_app_with(config: RendererConfig) -> VulkanApp {
+    VulkanApp::new_headless(WIDTH, HEIGHT, config).expect("the headless renderer should start")
+}
+
+/// The RGBA pixel at `(x, y)` of a frame read back by `render_to_image`.
+pub fn pixel(frame: &vulkan_app::CapturedFrame, x: u32, y: u32) -> [u8; 4] {
+    let start = ((y * frame.width + x) * 4) as usize;
+    frame.rgba[start..start + 4].try_into().unwrap()
+}
+
+/// Compares `frame` with `tests/golden/<name>.png`. A missing golden image
+/// is recorded from `frame` to be reviewed and committed, except under CI,
+/// where it fails; `UPDATE_GOLDEN=1` records it again. On a mismatch the
+/// frame is written next to the test binaries for comparison.
+pub fn assert_matches_golden(frame: &vulkan_app::CapturedFrame, name: &str) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden"]
+        .iter()
+        .collect::<PathBuf>()
+        .join(format!("{}.png", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() || !path.exists() {
+        assert!(
+            std::env::var_os("CI").is_none() || path.exists(),
+            "{} is missing; record it with UPDATE_GOLDEN=1 on a machine with a driver",
+            path.display()
+        );
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        frame.write_image(&path).unwrap();
+        eprintln!("Recorded {}; review and commit it", path.display());
+        return;
+    }
+    let (width, height, golden) = decode_png(&std::fs::read(&path).unwrap())
+        .unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+    assert_eq!((width, height), (frame.width, frame.height), "{}", name);
+    let mismatched = golden
+        .chunks(4)
+        .zip(frame.rgba.chunks(4))
+        .filter(|(expected, actual)| {
+            expected
+                .iter()
+                .zip(actual.iter())
+                .any(|(e, a)| e.abs_diff(*a) > GOLDEN_CHANNEL_TOLERANCE)
+        })
+        .count();
+    let allowed = (width * height) as usize * GOLDEN_MISMATCH_PER_MILLE / 1000;
+    if mismatched > allowed {
+        let actual = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.png", name));
+        frame.write_image(&actual).unwrap();
+        panic!(
+            "{} pixels differ from {}, more than {}; this frame is at {}",
+            mismatched,
+            path.display(),
+            allowed,
+            actual.display()
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! The downgrade ladder's decisions on the kinds of device the renderer
+//! meets: a desktop discrete GPU, an Intel integrated one on Wayland,
+//! lavapipe and MoltenVK.
+
+use ash::vk;
+use rust_vulkan::vulkan_app::{
+    ConfigResolver, DeviceCapabilities, DeviceLimits, Downgrade, DynamicRenderingSupport,
+    EffectiveConfig, RendererConfig,
+};
+
+struct Profile {
+    name: &'static str,
+    capabilities: DeviceCapabilities,
+    present_modes: &'static [vk::PresentModeKHR],
+}
+
+fn capabilities(max_msaa_samples: u32, max_anisotropy: Option<f32>) -> DeviceCapabilities {
+    DeviceCapabilities {
+        negative_viewport_height: true,
+        pipeline_statistics_query: true,
+        fill_mode_non_solid: true,
+        depth_stencil_resolve: true,
+        max_msaa_samples,
+        sampler_anisotropy: max_anisotropy.is_some(),
+        max_anisotropy: max_anisotropy.unwrap_or(1.0),
+        timestamps: true,
+        timestamp_period: 1.0,
+        dynamic_rendering: Some(DynamicRenderingSupport::Core),
+        portability_subset: false,
+        limits: DeviceLimits {
+            max_push_constants_size: 256,
+            max_per_stage_descriptor_uniform_buffers: 15,
+            max_descriptor_set_uniform_buffers_dynamic: 15,
+            max_bound_descriptor_sets: 32,
+            max_framebuffer_width: 16384,
+            max_framebuffer_height: 16384,
+            max_image_dimension_2d: 16384,
+            min_uniform_buffer_offset_alignment: 64,
+        },
+    }
+}
+
+fn desktop() -> Profile {
+    Profile {
+        name: "desktop",
+        capabilities: capabilities(8, Some(16.0)),
+        present_modes: &[
+            vk::PresentModeKHR::IMMEDIATE,
+            vk::PresentModeKHR::MAILBOX,
+            vk::PresentModeKHR::FIFO,
+            vk::PresentModeKHR::FIFO_RELAXED,
+        ],
+    }
+}
+
+/// Wayland compositors offer no tearing modes.
+fn intel_wayland() -> Profile {
+    Profile {
+        name: "intel",
+        capabilities: capabilities(16, Some(16.0)),
+        present_modes: &[vk::PresentModeKHR::MAILBOX, vk::PresentModeKHR::FIFO],
+    }
+}
+
+/// Software rendering: 4x only and no anisotropic filtering.
+fn lavapipe() -> Profile {
+    let mut capabilities = capabilities(4, None);
+    capabilities.timestamps = false;
+    capabilities.dynamic_rendering = Some(DynamicRenderingSupport::Extension);
+    Profile {
+        name: "lavapipe",
+        capabilities,
+        present_modes: &[vk::PresentModeKHR::IMMEDIATE, vk::PresentModeKHR::FIFO],
+    }
+}
+
+fn moltenvk() -> Profile {
+    let mut capabilities = capabilities(4, Some(16.0));
+    capabilities.portability_subset = true;
+    capabilities.dynamic_rendering = None;
+    Profile {
+        name: "moltenvk",
+        capabilities,
+        present_modes: &[vk::PresentModeKHR::FIFO, vk::PresentModeKHR::IMMEDIATE],
+    }
+}
+
+fn resolve(profile: &Profile, config: &RendererConfig) -> (EffectiveConfig, Vec<Downgrade>) {
+    ConfigResolver::new(&profile.capabilities, profile.present_modes).resolve(config)
+}
+
+fn request(msaa_samples: u32, anisotropy: Option<f32>, mode: vk::PresentModeKHR) -> RendererConfig {
+    RendererConfig {
+        msaa_samples,
+        anisotropy,
+        present_mode: mode,
+        ..RendererConfig::default()
+    }
+}
+
+fn fields(downgrades: &[Downgrade]) -> Vec<&'static str> {
+    downgrades.iter().map(|d| d.field).collect()
+}
+
+#[test]
+fn defaults_resolve_on_every_profile() {
+    // The defaults ask for 4x, 16x anisotropy and MAILBOX.
+    let expected: [(Profile, Option<f32>, vk::PresentModeKHR, &[&str]); 4] = [
+        (desktop(), Some(16.0), vk::PresentModeKHR::MAILBOX, &[]),
+        (
+            intel_wayland(),
+            Some(16.0),
+            vk::PresentModeKHR::MAILBOX,
+            &[],
+        ),
+        (
+            lavapipe(),
+            None,
+            vk::PresentModeKHR::FIFO,
+            &["anisotropy", "present_mode"],
+        ),
+        (
+            moltenvk(),
+            Some(16.0),
+            vk::PresentModeKHR::FIFO,
+            &["present_mode"],
+        ),
+    ];
+    for (profile, anisotropy, present_mode, downgraded) in expected {
+        let (effective, downgrades) = resolve(&profile, &RendererConfig::default());
+        assert_eq!(
+            effective,
+            EffectiveConfig {
+                msaa_samples: 4,
+                anisotropy,
+                present_mode,
+            },
+            "{}",
+            profile.name
+        );
+        assert_eq!(fields(&downgrades), downgraded, "{}", profile.name);
+    }
+}
+
+#[test]
+fn msaa_halves_down_to_the_device_cap() {
+    let eight = request(8, None, vk::PresentModeKHR::FIFO);
+    for (profile, applied) in [
+        (desktop(), 8),
+        (intel_wayland(), 8),
+        (lavapipe(), 4),
+        (moltenvk(), 4),
+    ] {
+        let (effective, downgrades) = resolve(&profile, &eight);
+        assert_eq!(effective.msaa_samples, applied, "{}", profile.name);
+        assert_eq!(downgrades.is_empty(), applied == 8, "{}", profile.name);
+    }
+
+    let (_, downgrades) = resolve(&lavapipe(), &eight);
+    assert_eq!(
+        downgrades,
+        [Downgrade {
+            field: "msaa_samples",
+            requested: "8x".to_string(),
+            applied: "4x".to_string(),
+            reason: "device supports up to 4x".to_string(),
+        }]
+    );
+    assert_eq!(
+        downgrades[0].to_string(),
+        "msaa_samples: 8x -> 4x (device supports up to 4x)"
+    );
+}
+
+#[test]
+fn msaa_counts_round_to_a_power_of_two_and_never_below_one() {
+    let profile = desktop();
+    let resolved = |samples| resolve(&profile, &request(samples, None, vk::PresentModeKHR::FIFO));
+    assert_eq!(resolved(3).0.msaa_samples, 4);
+    assert_eq!(fields(&resolved(3).1), ["msaa_samples"]);
+    assert_eq!(resolved(0).0.msaa_samples, 1);
+    assert!(resolved(0).1.is_empty());
+    assert_eq!(resolved(64).0.msaa_samples, 8);
+}
+
+#[test]
+fn anisotropy_clamps_to_the_limit_or_turns_off() {
+    let mode = vk::PresentModeKHR::FIFO;
+    let (effective, downgrades) = resolve(&desktop(), &request(1, Some(32.0), mode));
+    assert_eq!(effective.anisotropy, Some(16.0));
+    assert_eq!(downgrades[0].reason, "maxSamplerAnisotropy is 16");
+
+    let (effective, downgrades) = resolve(&lavapipe(), &request(1, Some(4.0), mode));
+    assert_eq!(effective.anisotropy, None);
+    assert_eq!(
+        downgrades[0].to_string(),
+        "anisotropy: 4x -> off (samplerAnisotropy is not supported)"
+    );
+
+    // Asking for none is never a downgrade.
+    let (effective, downgrades) = resolve(&lavapipe(), &request(1, None, mode));
+    assert_eq!(effective.anisotropy, None);
+    assert!(downgrades.is_empty());
+}
+
+#[test]
+fn present_modes_step_down_their_ladders() {
+    use vk::PresentModeKHR as Mode;
+    let cases = [
+        (desktop(), Mode::IMMEDIATE, Mode::IMMEDIATE),
+        (desktop(), Mode::FIFO_RELAXED, Mode::FIFO_RELAXED),
+        (intel_wayland(), Mode::IMMEDIATE, Mode::MAILBOX),
+        (intel_wayland(), Mode::FIFO_RELAXED, Mode::FIFO),
+        (lavapipe(), Mode::MAILBOX, Mode::FIFO),
+        (lavapipe(), Mode::IMMEDIATE, Mode::IMMEDIATE),
+        (moltenvk(), Mode::IMMEDIATE, Mode::IMMEDIATE),
+        (moltenvk(), Mode::FIFO_RELAXED, Mode::FIFO),
+    ];
+    for (profile, requested, applied) in cases {
+        let (effective, downgrades) = resolve(&profile, &request(1, None, requested));
+        assert_eq!(
+            effective.present_mode, applied,
+            "{} {:?}",
+            profile.name, requested
+        );
+        assert_eq!(downgrades.len(), usize::from(requested != applied));
+    }
+}
+
+#[test]
+fn fifo_is_used_even_when_the_surface_lists_nothing() {
+    let profile = Profile {
+        present_modes: &[],
+        ..desktop()
+    };
+    for requested in [vk::PresentModeKHR::FIFO, vk::PresentModeKHR::IMMEDIATE] {
+        let (effective, _) = resolve(&profile, &request(1, None, requested));
+        assert_eq!(effective.present_mode, vk::PresentModeKHR::FIFO);
+    }
+    let (_, downgrades) = resolve(&profile, &request(1, None, vk::PresentModeKHR::FIFO));
+    assert!(downgrades.is_empty());
+}
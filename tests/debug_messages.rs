@@ -0,0 +1,180 @@
+//! The debug messenger callback, fed messages by hand: the levels and
+//! targets they are logged at and the validation error count.
+
+use ash::vk;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use rust_vulkan::vulkan_app::{vulkan_debug_callback, Journal, JournalEvent, ValidationLog};
+use std::ffi::{c_void, CString};
+use std::sync::{Arc, Mutex};
+
+type Severity = vk::DebugUtilsMessageSeverityFlagsEXT;
+type MessageType = vk::DebugUtilsMessageTypeFlagsEXT;
+
+/// Every record logged by any test in this file: target, level and text.
+struct Recorder(Mutex<Vec<(String, Level, String)>>);
+
+impl Log for Recorder {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let mut records = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        records.push((
+            record.target().to_string(),
+            record.level(),
+            record.args().to_string(),
+        ));
+    }
+
+    fn flush(&self) {}
+}
+
+static RECORDER: Recorder = Recorder(Mutex::new(Vec::new()));
+
+/// The target and level `text` was logged at. Tests run in parallel, so
+/// each looks for messages only it sends.
+fn logged(text: &str) -> Option<(String, Level, String)> {
+    let records = RECORDER.0.lock().unwrap_or_else(|e| e.into_inner());
+    records
+        .iter()
+        .find(|(_, _, message)| message.ends_with(text))
+        .cloned()
+}
+
+fn send(
+    log: Option<&ValidationLog>,
+    severity: Severity,
+    message_type: MessageType,
+    id: Option<&str>,
+    text: &str,
+) {
+    // The logger can only be set once; later calls fail harmlessly.
+    let _ = log::set_logger(&RECORDER);
+    log::set_max_level(LevelFilter::Trace);
+
+    let text = CString::new(text).unwrap();
+    let id = id.map(|id| CString::new(id).unwrap());
+    let data = vk::DebugUtilsMessengerCallbackDataEXT {
+        p_message: text.as_ptr(),
+        p_message_id_name: id.as_ref().map_or(std::ptr::null(), |id| id.as_ptr()),
+        ..Default::default()
+    };
+    let user_data = log.map_or(std::ptr::null_mut(), |log| {
+        log as *const ValidationLog as *mut c_void
+    });
+    let keep_going = unsafe { vulkan_debug_callback(severity, message_type, &data, user_data) };
+    assert_eq!(keep_going, vk::FALSE, "the call must not be aborted");
+}
+
+#[test]
+fn severities_log_at_their_levels() {
+    let cases = [
+        (Severity::ERROR, Level::Error),
+        (Severity::WARNING, Level::Warn),
+        (Severity::INFO, Level::Info),
+        (Severity::VERBOSE, Level::Trace),
+    ];
+    for (severity, level) in cases {
+        let text = format!("severity test {:?}", severity);
+        send(None, severity, MessageType::VALIDATION, None, &text);
+        assert_eq!(logged(&text).unwrap().1, level, "{:?}", severity);
+    }
+}
+
+#[test]
+fn message_types_log_to_their_own_targets() {
+    let cases = [
+        (MessageType::VALIDATION, "vulkan::validation"),
+        (MessageType::PERFORMANCE, "vulkan::performance"),
+        (MessageType::GENERAL, "vulkan::general"),
+        (
+            MessageType::VALIDATION | MessageType::PERFORMANCE,
+            "vulkan::validation",
+        ),
+    ];
+    for (message_type, target) in cases {
+        let text = format!("target test {:?}", message_type);
+        send(None, Severity::WARNING, message_type, None, &text);
+        assert_eq!(logged(&text).unwrap().0, target, "{:?}", message_type);
+    }
+}
+
+#[test]
+fn messages_are_prefixed_with_their_id() {
+    let id = "VUID-vkCmdDraw-None-02859";
+    send(
+        None,
+        Severity::ERROR,
+        MessageType::VALIDATION,
+        Some(id),
+        "id test numbered",
+    );
+    assert_eq!(
+        logged("id test numbered").unwrap().2,
+        format!("[{}] id test numbered", id)
+    );
+    send(
+        None,
+        Severity::INFO,
+        MessageType::GENERAL,
+        None,
+        "id test loader",
+    );
+    assert_eq!(logged("id test loader").unwrap().2, "[-] id test loader");
+}
+
+#[test]
+fn only_errors_are_counted() {
+    let journal = Arc::new(Journal::default());
+    let log = ValidationLog::new(journal.clone());
+    assert_eq!(log.error_count(), 0, "a clean run has no errors");
+    assert!(!log.has_new_error());
+
+    for severity in [Severity::WARNING, Severity::INFO, Severity::VERBOSE] {
+        send(
+            Some(&log),
+            severity,
+            MessageType::VALIDATION,
+            None,
+            "count test quiet",
+        );
+    }
+    send(
+        Some(&log),
+        Severity::WARNING,
+        MessageType::PERFORMANCE,
+        None,
+        "count test slow",
+    );
+    assert_eq!(log.error_count(), 0);
+    assert!(!log.has_new_error());
+
+    send(
+        Some(&log),
+        Severity::ERROR,
+        MessageType::VALIDATION,
+        None,
+        "count test first",
+    );
+    send(
+        Some(&log),
+        Severity::ERROR,
+        MessageType::VALIDATION,
+        None,
+        "count test second",
+    );
+    assert_eq!(log.error_count(), 2);
+    // Only the newest error is kept for the render thread, once.
+    let latest = log.take_latest_error().unwrap();
+    assert!(latest.ends_with("count test second"), "{}", latest);
+    assert_eq!(log.take_latest_error(), None);
+
+    assert_eq!(log.recent().len(), 6);
+    let validations = journal
+        .entries()
+        .iter()
+        .filter(|entry| matches!(entry.event, JournalEvent::Validation { .. }))
+        .count();
+    assert_eq!(validations, 6);
+}
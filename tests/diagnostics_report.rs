@@ -0,0 +1,165 @@
+//! The layout of the diagnostics report F12 exports and `--print-info`
+//! prints, against a snapshot. Set UPDATE_SNAPSHOTS=1 to rewrite it after a
+//! deliberate change, including to the config constants it lists.
+
+use ash::vk;
+use rust_vulkan::vulkan_app::{
+    DeviceCapabilities, DeviceLimits, DiagnosticsReport, Downgrade, DrawConstantPacking, DrawStats,
+    DynamicRenderingSupport, EffectiveConfig, FragmentationStats, MemoryPlacement, MeshPoolStats,
+    Pattern, ResourceClass, ResourceKind, WindowBackend,
+};
+use std::path::Path;
+
+const SNAPSHOT: &str = "tests/snapshots/diagnostics_report.txt";
+
+fn limits() -> DeviceLimits {
+    DeviceLimits {
+        max_push_constants_size: 256,
+        max_per_stage_descriptor_uniform_buffers: 15,
+        max_descriptor_set_uniform_buffers_dynamic: 8,
+        max_bound_descriptor_sets: 32,
+        max_framebuffer_width: 16384,
+        max_framebuffer_height: 16384,
+        max_image_dimension_2d: 16384,
+        min_uniform_buffer_offset_alignment: 64,
+    }
+}
+
+/// A report with every optional section present.
+fn report() -> DiagnosticsReport {
+    let host = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+    DiagnosticsReport {
+        app_name: "Cube Viewer".to_string(),
+        app_version: vk::make_api_version(0, 1, 2, 0),
+        engine_name: "Snapshot Engine".to_string(),
+        engine_version: vk::make_api_version(0, 0, 3, 1),
+        window_backend: WindowBackend::Wayland,
+        effective: EffectiveConfig {
+            msaa_samples: 4,
+            anisotropy: Some(16.0),
+            present_mode: vk::PresentModeKHR::MAILBOX,
+        },
+        downgrades: vec![Downgrade {
+            field: "present_mode",
+            requested: "IMMEDIATE".to_string(),
+            applied: "MAILBOX".to_string(),
+            reason: "the surface doesn't support it".to_string(),
+        }],
+        surface_extensions: vec![
+            "VK_KHR_surface".to_string(),
+            "VK_KHR_wayland_surface".to_string(),
+        ],
+        device_name: "Test GPU".to_string(),
+        device_type: vk::PhysicalDeviceType::DISCRETE_GPU,
+        api_version: vk::make_api_version(0, 1, 3, 250),
+        driver_version: 0x2180_0000,
+        capabilities: DeviceCapabilities {
+            negative_viewport_height: true,
+            pipeline_statistics_query: true,
+            fill_mode_non_solid: true,
+            depth_stencil_resolve: true,
+            max_msaa_samples: 8,
+            sampler_anisotropy: true,
+            max_anisotropy: 16.0,
+            timestamps: true,
+            timestamp_period: 1.0,
+            dynamic_rendering: Some(DynamicRenderingSupport::Core),
+            portability_subset: false,
+            limits: limits(),
+        },
+        draw_packing: DrawConstantPacking::choose(&limits(), None).unwrap(),
+        memory_placements: vec![
+            MemoryPlacement {
+                class: ResourceClass::Vertex,
+                preferred: vk::MemoryPropertyFlags::DEVICE_LOCAL | host,
+                landed: Some((1, 0, host)),
+            },
+            MemoryPlacement {
+                class: ResourceClass::Image,
+                preferred: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                landed: None,
+            },
+        ],
+        swapchain_extent: vk::Extent2D {
+            width: 800,
+            height: 600,
+        },
+        render_extent: vk::Extent2D {
+            width: 800,
+            height: 450,
+        },
+        swapchain_format: vk::Format::B8G8R8A8_SRGB,
+        swapchain_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+        scene_objects: 1,
+        final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+        frame_count: 120,
+        avg_frame_ms: 16.667,
+        slow_frame_ms: 18.0,
+        avg_gpu_ms: Some(2.5),
+        draw_stats: DrawStats {
+            draws: 3,
+            pipeline_binds: 2,
+            material_binds: 2,
+            mesh_binds: 1,
+        },
+        mesh_pool: MeshPoolStats {
+            meshes: 2,
+            vertices: FragmentationStats {
+                capacity: 65536,
+                free: 65000,
+                largest_free: 65000,
+                free_ranges: 1,
+            },
+            indices: FragmentationStats {
+                capacity: 196608,
+                free: 196000,
+                largest_free: 98000,
+                free_ranges: 2,
+            },
+        },
+        ground_texture: Some((Pattern::UvTest, 512, 10)),
+        live_resources: vec![(ResourceKind::Buffer, 6), (ResourceKind::Image, 3)],
+        validation_messages: vec![
+            "WARNING: vkQueuePresentKHR: the swapchain no longer matches the surface".to_string(),
+        ],
+    }
+}
+
+#[test]
+fn the_report_layout_matches_its_snapshot() {
+    let actual = report().to_string();
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(SNAPSHOT);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap();
+    assert!(
+        actual == expected,
+        "the report differs from {}; rerun with UPDATE_SNAPSHOTS=1 if that's intended:\n{}",
+        SNAPSHOT,
+        actual
+    );
+}
+
+#[test]
+fn optional_sections_say_what_is_missing() {
+    let mut report = report();
+    report.render_extent = report.swapchain_extent;
+    report.capabilities.dynamic_rendering = None;
+    report.effective.anisotropy = None;
+    report.avg_gpu_ms = None;
+    report.ground_texture = None;
+    report.validation_messages.clear();
+    let text = report.to_string();
+    for line in [
+        "  dynamic rendering: false\n",
+        "  anisotropy: off\n",
+        "  gpu time: no timestamps\n",
+        "ground texture: none\n",
+        "recent validation messages:\n  (none)\n",
+    ] {
+        assert!(text.contains(line), "missing {:?} in:\n{}", line, text);
+    }
+    assert!(!text.contains("letterboxed"));
+}
@@ -0,0 +1,132 @@
+//! How the draw constants split between push constants and a dynamic
+//! uniform slot under different device limits.
+
+use ash::vk;
+use rust_vulkan::vulkan_app::{DeviceLimits, DrawConstantPacking, DRAW_UNIFORM_SLOTS};
+
+/// Spec minimums apart from the push constant size and offset alignment.
+fn limits(max_push_constants_size: u32, alignment: vk::DeviceSize) -> DeviceLimits {
+    DeviceLimits {
+        max_push_constants_size,
+        max_per_stage_descriptor_uniform_buffers: 12,
+        max_descriptor_set_uniform_buffers_dynamic: 8,
+        max_bound_descriptor_sets: 4,
+        max_framebuffer_width: 4096,
+        max_framebuffer_height: 4096,
+        max_image_dimension_2d: 4096,
+        min_uniform_buffer_offset_alignment: alignment,
+    }
+}
+
+fn ranges(packing: &DrawConstantPacking) -> Vec<(vk::ShaderStageFlags, u32, u32)> {
+    packing
+        .push_constant_ranges()
+        .iter()
+        .map(|range| (range.stage_flags, range.offset, range.size))
+        .collect()
+}
+
+#[test]
+fn everything_is_pushed_within_the_spec_minimum() {
+    for max in [128, 256] {
+        let packing = DrawConstantPacking::choose(&limits(max, 256), None).unwrap();
+        assert_eq!(packing.push_fields(), 2);
+        assert!(!packing.uses_uniform_slot());
+        assert_eq!(packing.uniform_slots(), 0);
+        assert_eq!(packing.uniform_stages(), vk::ShaderStageFlags::empty());
+        assert_eq!(
+            ranges(&packing),
+            [
+                (vk::ShaderStageFlags::FRAGMENT, 0, 16),
+                (vk::ShaderStageFlags::VERTEX, 16, 64),
+            ]
+        );
+        assert_eq!(
+            packing.to_string(),
+            format!("tint, model pushed (80 of {} bytes)", max)
+        );
+    }
+}
+
+#[test]
+fn fields_past_the_budget_move_to_the_uniform_slot() {
+    let packing = DrawConstantPacking::choose(&limits(256, 64), Some(64)).unwrap();
+    assert_eq!(packing.push_fields(), 1);
+    assert_eq!(packing.uniform_slots(), DRAW_UNIFORM_SLOTS);
+    assert_eq!(packing.uniform_stages(), vk::ShaderStageFlags::VERTEX);
+    assert_eq!(ranges(&packing), [(vk::ShaderStageFlags::FRAGMENT, 0, 16)]);
+    assert_eq!(
+        packing.to_string(),
+        "tint pushed (16 of 64 bytes), model in a dynamic uniform slot (128 byte stride)"
+    );
+
+    let packing = DrawConstantPacking::choose(&limits(256, 256), Some(0)).unwrap();
+    assert_eq!(packing.push_fields(), 0);
+    assert!(ranges(&packing).is_empty());
+    assert_eq!(
+        packing.uniform_stages(),
+        vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT
+    );
+    assert_eq!(
+        packing.to_string(),
+        "nothing pushed (0 byte budget), tint, model in a dynamic uniform slot (256 byte stride)"
+    );
+}
+
+#[test]
+fn a_budget_never_exceeds_the_device_limit() {
+    // A field straddling the limit isn't pushed in part.
+    let packing = DrawConstantPacking::choose(&limits(79, 256), Some(4096)).unwrap();
+    assert_eq!(packing.push_fields(), 1);
+    assert!(packing
+        .to_string()
+        .starts_with("tint pushed (16 of 79 bytes)"));
+    let packing = DrawConstantPacking::choose(&limits(80, 256), Some(4096)).unwrap();
+    assert_eq!(packing.push_fields(), 2);
+}
+
+#[test]
+fn slots_are_spaced_at_the_offset_alignment() {
+    for (alignment, stride) in [(1, 80), (16, 80), (32, 96), (64, 128), (256, 256)] {
+        let packing = DrawConstantPacking::choose(&limits(128, alignment), Some(16)).unwrap();
+        assert_eq!(packing.slot_stride(), stride, "alignment {}", alignment);
+    }
+}
+
+#[test]
+fn overflow_fails_without_a_uniform_buffer_to_put_it_in() {
+    let no_dynamic = DeviceLimits {
+        max_descriptor_set_uniform_buffers_dynamic: 0,
+        ..limits(64, 256)
+    };
+    assert_eq!(
+        DrawConstantPacking::choose(&no_dynamic, None),
+        Err(
+            "model doesn't fit 64 bytes of push constants and the device has no \
+             dynamic uniform buffers"
+                .to_string()
+        )
+    );
+
+    // The vertex stage already reads the frame and view blocks.
+    let two_per_stage = DeviceLimits {
+        max_per_stage_descriptor_uniform_buffers: 2,
+        ..limits(0, 256)
+    };
+    assert_eq!(
+        DrawConstantPacking::choose(&two_per_stage, None),
+        Err(
+            "tint, model doesn't fit 0 bytes of push constants and the vertex stage \
+             can't bind another uniform buffer (limit 2)"
+                .to_string()
+        )
+    );
+
+    // Neither limit matters while everything is pushed.
+    let pushed_only = DeviceLimits {
+        max_descriptor_set_uniform_buffers_dynamic: 0,
+        max_per_stage_descriptor_uniform_buffers: 2,
+        ..limits(128, 256)
+    };
+    assert!(DrawConstantPacking::choose(&pushed_only, None).is_ok());
+}
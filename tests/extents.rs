@@ -0,0 +1,109 @@
+//! Keeping the swapchain, render extent and projection valid at extreme
+//! window sizes.
+
+use ash::vk;
+use rust_vulkan::vulkan_app::{choose_swap_extent, projection_aspect, DeviceLimits};
+
+fn extent(width: u32, height: u32) -> vk::Extent2D {
+    vk::Extent2D { width, height }
+}
+
+fn limits(framebuffer: (u32, u32), image_2d: u32) -> DeviceLimits {
+    DeviceLimits {
+        max_push_constants_size: 128,
+        max_per_stage_descriptor_uniform_buffers: 12,
+        max_descriptor_set_uniform_buffers_dynamic: 8,
+        max_bound_descriptor_sets: 4,
+        max_framebuffer_width: framebuffer.0,
+        max_framebuffer_height: framebuffer.1,
+        max_image_dimension_2d: image_2d,
+        min_uniform_buffer_offset_alignment: 256,
+    }
+}
+
+/// A surface that lets the swapchain pick its size between `min` and `max`.
+fn surface(min: vk::Extent2D, max: vk::Extent2D) -> vk::SurfaceCapabilitiesKHR {
+    vk::SurfaceCapabilitiesKHR {
+        current_extent: extent(u32::MAX, u32::MAX),
+        min_image_extent: min,
+        max_image_extent: max,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn the_render_extent_stays_within_every_limit() {
+    let limits = limits((8192, 4096), 16384);
+    let cases = [
+        (extent(800, 600), extent(800, 600)),
+        (extent(7680, 4320), extent(7680, 4096)),
+        (extent(16384, 16384), extent(8192, 4096)),
+        (extent(0, 0), extent(1, 1)),
+        (extent(1, 600), extent(1, 600)),
+        (extent(800, 0), extent(800, 1)),
+    ];
+    for (surface, render) in cases {
+        assert_eq!(limits.render_extent(surface), render, "{surface:?}");
+    }
+}
+
+#[test]
+fn the_2d_image_limit_caps_the_render_extent_too() {
+    // The depth image is a 2D image, so it can't outgrow the framebuffer's
+    // other limit either.
+    let limits = limits((16384, 16384), 4096);
+    assert_eq!(limits.render_extent(extent(7680, 4320)), extent(4096, 4096));
+}
+
+#[test]
+fn zero_limits_still_leave_a_pixel() {
+    assert_eq!(
+        limits((0, 0), 0).render_extent(extent(800, 600)),
+        extent(1, 1)
+    );
+}
+
+#[test]
+fn the_surface_s_current_extent_wins() {
+    let capabilities = vk::SurfaceCapabilitiesKHR {
+        current_extent: extent(1024, 768),
+        min_image_extent: extent(1, 1),
+        max_image_extent: extent(4096, 4096),
+        ..Default::default()
+    };
+    assert_eq!(
+        choose_swap_extent(&capabilities, extent(800, 600)),
+        extent(1024, 768)
+    );
+}
+
+#[test]
+fn the_window_size_is_clamped_to_the_surface() {
+    let capabilities = surface(extent(64, 64), extent(4096, 2048));
+    let cases = [
+        (extent(800, 600), extent(800, 600)),
+        (extent(1, 1), extent(64, 64)),
+        (extent(0, 600), extent(64, 600)),
+        (extent(7680, 4320), extent(4096, 2048)),
+        (extent(10, 3000), extent(64, 2048)),
+    ];
+    for (window, swapchain) in cases {
+        assert_eq!(
+            choose_swap_extent(&capabilities, window),
+            swapchain,
+            "{window:?}"
+        );
+    }
+}
+
+#[test]
+fn the_projection_aspect_is_finite_at_any_size() {
+    assert_eq!(projection_aspect(extent(800, 600)), 800.0 / 600.0);
+    assert_eq!(projection_aspect(extent(0, 0)), 1.0);
+    assert_eq!(projection_aspect(extent(1, 600)), 1.0 / 600.0);
+    assert_eq!(projection_aspect(extent(800, 0)), 800.0);
+    for (width, height) in [(1, u32::MAX), (u32::MAX, 1), (u32::MAX, u32::MAX)] {
+        let aspect = projection_aspect(extent(width, height));
+        assert!(aspect.is_finite() && aspect > 0.0, "{width}x{height}");
+    }
+}
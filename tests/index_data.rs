@@ -0,0 +1,33 @@
+//! Index type selection for meshes of different sizes.
+
+use ash::vk;
+use rust_vulkan::vulkan_app::{index_type_for, IndexData};
+
+#[test]
+fn sixteen_bits_up_to_65535_vertices() {
+    assert_eq!(index_type_for(0), vk::IndexType::UINT16);
+    assert_eq!(index_type_for(24), vk::IndexType::UINT16);
+    // Indices 0 to 65534, short of the primitive restart index.
+    assert_eq!(index_type_for(65_535), vk::IndexType::UINT16);
+}
+
+#[test]
+fn thirty_two_bits_from_65536_vertices() {
+    assert_eq!(index_type_for(65_536), vk::IndexType::UINT32);
+    assert_eq!(index_type_for(1 << 20), vk::IndexType::UINT32);
+}
+
+#[test]
+fn small_meshes_are_narrowed() {
+    let indices = IndexData::new(vec![0, 1, 65_534], 65_535);
+    assert_eq!(indices, IndexData::U16(vec![0, 1, 65_534]));
+    assert_eq!(indices.index_type(), vk::IndexType::UINT16);
+    assert_eq!(indices.len(), 3);
+}
+
+#[test]
+fn large_meshes_keep_their_indices() {
+    let indices = IndexData::new(vec![0, 65_535, 70_000], 70_001);
+    assert_eq!(indices, IndexData::U32(vec![0, 65_535, 70_000]));
+    assert_eq!(indices.index_type(), vk::IndexType::UINT32);
+}
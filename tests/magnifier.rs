@@ -0,0 +1,123 @@
+//! The magnifier's off-center projection and where its inset goes.
+
+use ash::vk;
+use cgmath::{perspective, Deg, Matrix4, SquareMatrix, Vector2, Vector3, Vector4};
+use rust_vulkan::vulkan_app::{
+    projection_off_center, Inset, MAGNIFIER_BORDER_PIXELS, MAGNIFIER_MARGIN_PIXELS,
+    MAGNIFIER_SIZE_PIXELS,
+};
+
+const EPSILON: f32 = 1e-3;
+
+fn proj() -> Matrix4<f32> {
+    perspective(Deg(60.0), 4.0 / 3.0, 0.1, 100.0)
+}
+
+/// Normalized device coordinates of `point` under `proj`.
+fn ndc(proj: Matrix4<f32>, point: Vector3<f32>) -> Vector3<f32> {
+    let clip = proj * point.extend(1.0);
+    clip.truncate() / clip.w
+}
+
+/// A view space point `proj` puts at `xy` and depth `z` in NDC.
+fn unproject(proj: Matrix4<f32>, xy: Vector2<f32>, z: f32) -> Vector3<f32> {
+    let point = proj.invert().unwrap() * Vector4::new(xy.x, xy.y, z, 1.0);
+    point.truncate() / point.w
+}
+
+fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+    let d = a - b;
+    assert!(
+        d.x.abs() < EPSILON && d.y.abs() < EPSILON && d.z.abs() < EPSILON,
+        "{a:?} != {b:?}"
+    );
+}
+
+fn rect(x: i32, y: i32, width: u32, height: u32) -> vk::Rect2D {
+    vk::Rect2D {
+        offset: vk::Offset2D { x, y },
+        extent: vk::Extent2D { width, height },
+    }
+}
+
+#[test]
+fn no_zoom_and_no_shift_leaves_the_projection_alone() {
+    let center = Vector2::new(0.3, -0.2);
+    assert_eq!(projection_off_center(proj(), center, 1.0, center), proj());
+}
+
+#[test]
+fn the_center_lands_on_the_target_at_every_depth() {
+    let (center, target) = (Vector2::new(0.25, -0.5), Vector2::new(0.6, 0.6));
+    let magnified = projection_off_center(proj(), center, 4.0, target);
+    for z in [0.01, 0.5, 0.99] {
+        let point = unproject(proj(), center, z);
+        // Depth is left as it was, so the depth test still works.
+        assert_close(ndc(magnified, point), target.extend(z));
+    }
+}
+
+#[test]
+fn offsets_around_the_center_grow_by_the_zoom() {
+    let (center, target) = (Vector2::new(-0.4, 0.1), Vector2::new(0.0, 0.0));
+    let zoom = 4.0;
+    let magnified = projection_off_center(proj(), center, zoom, target);
+    for offset in [Vector2::new(0.05, 0.0), Vector2::new(-0.02, 0.08)] {
+        for z in [0.2, 0.9] {
+            let point = unproject(proj(), center + offset, z);
+            assert_close(ndc(magnified, point), (target + offset * zoom).extend(z));
+        }
+    }
+}
+
+#[test]
+fn the_inset_sits_in_the_top_right_corner() {
+    let area = rect(10, 20, 800, 600);
+    let inset = Inset::place(area).unwrap();
+    let outer = MAGNIFIER_SIZE_PIXELS + 2 * MAGNIFIER_BORDER_PIXELS;
+    assert_eq!(
+        inset.border,
+        rect(
+            (10 + 800 - MAGNIFIER_MARGIN_PIXELS - outer) as i32,
+            (20 + MAGNIFIER_MARGIN_PIXELS) as i32,
+            outer,
+            outer,
+        )
+    );
+    let border = MAGNIFIER_BORDER_PIXELS as i32;
+    assert_eq!(
+        inset.inner,
+        rect(
+            inset.border.offset.x + border,
+            inset.border.offset.y + border,
+            MAGNIFIER_SIZE_PIXELS,
+            MAGNIFIER_SIZE_PIXELS,
+        )
+    );
+}
+
+#[test]
+fn there_is_no_inset_on_areas_too_small_for_it() {
+    let fits = MAGNIFIER_SIZE_PIXELS + 2 * MAGNIFIER_BORDER_PIXELS + 2 * MAGNIFIER_MARGIN_PIXELS;
+    assert!(Inset::place(rect(0, 0, fits, fits)).is_some());
+    assert!(Inset::place(rect(0, 0, fits - 1, 600)).is_none());
+    assert!(Inset::place(rect(0, 0, 800, fits - 1)).is_none());
+}
+
+#[test]
+fn the_pixel_under_the_cursor_shows_in_the_middle_of_the_inset() {
+    let area = rect(0, 40, 800, 600);
+    let inset = Inset::place(area).unwrap();
+    let cursor = (200.0, 340.0);
+    let magnified = inset.projection(proj(), area, cursor, 4.0);
+
+    // The cursor's pixel in NDC, y up, and the inset's middle likewise.
+    let to_ndc = |x: f32, y: f32| Vector2::new(x / 400.0 - 1.0, 1.0 - (y - 40.0) / 300.0);
+    let half = MAGNIFIER_SIZE_PIXELS as f32 / 2.0;
+    let middle = to_ndc(
+        inset.inner.offset.x as f32 + half,
+        inset.inner.offset.y as f32 + half,
+    );
+    let point = unproject(proj(), to_ndc(cursor.0, cursor.1), 0.5);
+    assert_close(ndc(magnified, point), middle.extend(0.5));
+}
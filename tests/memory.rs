@@ -0,0 +1,72 @@
+//! Memory type selection against a made-up device.
+
+use ash::vk;
+use rust_vulkan::vulkan_app::{MemoryTypeSelector, ResourceClass};
+
+const DEVICE_LOCAL: vk::MemoryPropertyFlags = vk::MemoryPropertyFlags::DEVICE_LOCAL;
+const HOST: vk::MemoryPropertyFlags = vk::MemoryPropertyFlags::from_raw(
+    vk::MemoryPropertyFlags::HOST_VISIBLE.as_raw()
+        | vk::MemoryPropertyFlags::HOST_COHERENT.as_raw(),
+);
+
+/// A discrete GPU: video memory, system memory, and a 256 MiB window of
+/// video memory the host can map.
+fn discrete_gpu() -> vk::PhysicalDeviceMemoryProperties {
+    let mut properties = vk::PhysicalDeviceMemoryProperties {
+        memory_type_count: 3,
+        memory_heap_count: 3,
+        ..Default::default()
+    };
+    let types = [(DEVICE_LOCAL, 0), (HOST, 1), (DEVICE_LOCAL | HOST, 2)];
+    for (i, (property_flags, heap_index)) in types.into_iter().enumerate() {
+        properties.memory_types[i] = vk::MemoryType {
+            property_flags,
+            heap_index,
+        };
+    }
+    for (i, size) in [8 << 30, 16 << 30, 256 << 20].into_iter().enumerate() {
+        properties.memory_heaps[i] = vk::MemoryHeap {
+            size,
+            flags: if i == 1 {
+                vk::MemoryHeapFlags::empty()
+            } else {
+                vk::MemoryHeapFlags::DEVICE_LOCAL
+            },
+        };
+    }
+    properties
+}
+
+#[test]
+fn images_fall_back_from_device_local() {
+    let memory = MemoryTypeSelector::new(discrete_gpu(), &[]).unwrap();
+    assert_eq!(memory.candidates(ResourceClass::Image, 0b111), [0, 2, 1]);
+    // Only what the image's requirements allow.
+    assert_eq!(memory.candidates(ResourceClass::Image, 0b010), [1]);
+}
+
+#[test]
+fn buffers_need_host_memory() {
+    let memory = MemoryTypeSelector::new(discrete_gpu(), &[]).unwrap();
+    // The mappable video memory is too small to prefer for uniforms.
+    assert_eq!(memory.candidates(ResourceClass::Uniform, 0b111), [1, 2]);
+    assert!(memory.candidates(ResourceClass::Vertex, 0b001).is_empty());
+}
+
+#[test]
+fn placements_show_fallbacks() {
+    let memory = MemoryTypeSelector::new(discrete_gpu(), &[]).unwrap();
+    memory.record(ResourceClass::Image, 1);
+    let image = memory.placements()[ResourceClass::Image as usize].to_string();
+    assert!(image.contains("type 1 in heap 1"), "{}", image);
+    assert!(image.contains("fell back from"), "{}", image);
+}
+
+#[test]
+fn overrides_keep_required_flags() {
+    let overrides = [(ResourceClass::Vertex, DEVICE_LOCAL | HOST)];
+    let memory = MemoryTypeSelector::new(discrete_gpu(), &overrides).unwrap();
+    assert_eq!(memory.candidates(ResourceClass::Vertex, 0b111), [2, 1]);
+    let overrides = [(ResourceClass::Vertex, DEVICE_LOCAL)];
+    assert!(MemoryTypeSelector::new(discrete_gpu(), &overrides).is_err());
+}
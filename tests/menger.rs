@@ -0,0 +1,120 @@
+//! The Menger sponge's instances: how many each level has, where they sit
+//! and how the level is lowered to fit an instance budget.
+
+use cgmath::{Matrix4, Vector3, Vector4};
+use rust_vulkan::vulkan_app::{
+    cube_mesh_to_unit, menger_instance_count, menger_level_within, menger_sponge,
+};
+
+const EPSILON: f32 = 1e-5;
+
+fn close(a: Vector3<f32>, b: Vector3<f32>, epsilon: f32) -> bool {
+    let d = a - b;
+    d.x.abs() < epsilon && d.y.abs() < epsilon && d.z.abs() < epsilon
+}
+
+/// Where `transform` puts the unit cube centred on the origin: its centre
+/// and width, checking it stays an axis-aligned cube.
+fn placement(transform: &Matrix4<f32>) -> (Vector3<f32>, f32) {
+    let center = *transform * Vector4::new(0.0, 0.0, 0.0, 1.0);
+    let size = transform.x.x;
+    assert!((transform.y.y - size).abs() < EPSILON && (transform.z.z - size).abs() < EPSILON);
+    (center.truncate(), size)
+}
+
+#[test]
+fn each_level_keeps_twenty_cubes_of_every_cell() {
+    for level in 0..=4 {
+        let instances = menger_sponge(level);
+        assert_eq!(instances.len() as u64, menger_instance_count(level));
+        assert_eq!(menger_instance_count(level), 20u64.pow(level));
+    }
+    assert_eq!(menger_instance_count(100), u64::MAX);
+}
+
+#[test]
+fn instances_shrink_by_a_third_per_level_and_fill_the_unit_cube() {
+    for level in 0..=3 {
+        let width = 1.0 / 3f32.powi(level as i32);
+        let mut low = Vector3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut high = -low;
+        for transform in menger_sponge(level) {
+            let (center, size) = placement(&transform);
+            assert!((size - width).abs() < EPSILON, "level {level}");
+            for axis in 0..3 {
+                low[axis] = low[axis].min(center[axis] - size / 2.0);
+                high[axis] = high[axis].max(center[axis] + size / 2.0);
+            }
+        }
+        for axis in 0..3 {
+            assert!((low[axis] + 0.5).abs() < EPSILON, "level {level}");
+            assert!((high[axis] - 0.5).abs() < EPSILON, "level {level}");
+        }
+    }
+}
+
+#[test]
+fn instances_sit_on_distinct_cells_of_the_level_grid() {
+    for level in 1..=3 {
+        let cells = 3i32.pow(level);
+        let mut seen = std::collections::HashSet::new();
+        for transform in menger_sponge(level) {
+            let (center, _) = placement(&transform);
+            // Cell centres are at (i + 0.5) / cells - 0.5.
+            let cell = center.map(|c| (c + 0.5) * cells as f32 - 0.5);
+            let rounded = cell.map(f32::round);
+            assert!(close(cell, rounded, 1e-3), "level {level} is off the grid");
+            let index = [rounded.x as i32, rounded.y as i32, rounded.z as i32];
+            assert!(index.iter().all(|&i| (0..cells).contains(&i)));
+            assert!(seen.insert(index), "level {level} repeats {index:?}");
+        }
+    }
+}
+
+#[test]
+fn the_centre_and_face_centres_are_hollow() {
+    for level in 1..=3 {
+        let instances = menger_sponge(level);
+        let third = 1.0 / 3.0;
+        let holes = [
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(third, 0.0, 0.0),
+            Vector3::new(0.0, -third, 0.0),
+            Vector3::new(0.0, 0.0, third),
+        ];
+        for hole in holes {
+            let filled = instances.iter().any(|transform| {
+                let (center, size) = placement(transform);
+                close(center, hole, size / 2.0)
+            });
+            assert!(!filled, "level {level} fills {hole:?}");
+        }
+    }
+}
+
+#[test]
+fn the_level_drops_until_it_fits_the_budget() {
+    assert_eq!(menger_level_within(3, 8000), 3);
+    assert_eq!(menger_level_within(3, 7999), 2);
+    assert_eq!(menger_level_within(2, u64::MAX), 2);
+    assert_eq!(menger_level_within(40, 1_000_000), 4);
+    // Level 0, a single cube, is the floor even with no budget.
+    assert_eq!(menger_level_within(5, 0), 0);
+}
+
+#[test]
+fn the_cube_mesh_maps_onto_the_unit_cube() {
+    let to_unit = cube_mesh_to_unit();
+    let corner = |x: f32, y: f32, z: f32| (to_unit * Vector4::new(x, y, z, 1.0)).truncate();
+    // The mesh's front face at z = 0 and its back at z = -0.5.
+    assert!(close(
+        corner(0.5, -0.5, 0.0),
+        Vector3::new(0.5, -0.5, 0.5),
+        EPSILON
+    ));
+    assert!(close(
+        corner(-0.5, 0.5, -0.5),
+        Vector3::new(-0.5, 0.5, -0.5),
+        EPSILON
+    ));
+}
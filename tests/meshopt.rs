@@ -0,0 +1,163 @@
+//! The vertex cache and fetch optimizations and position quantization on
+//! generated meshes.
+
+use rust_vulkan::vulkan_app::{
+    estimate_acmr, optimize_mesh, optimize_vertex_cache, optimize_vertex_fetch, quantize_positions,
+    HasPosition,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Point([f32; 3]);
+
+impl HasPosition for Point {
+    fn position_mut(&mut self) -> &mut [f32; 3] {
+        &mut self.0
+    }
+}
+
+/// A `side` x `side` grid of quads, two triangles each, with its triangles
+/// shuffled into a cache-hostile order.
+fn shuffled_grid(side: u32) -> (Vec<Point>, Vec<u32>) {
+    let row = side + 1;
+    let vertices = (0..row * row)
+        .map(|i| Point([(i % row) as f32, (i / row) as f32, 0.0]))
+        .collect();
+    let mut triangles = Vec::new();
+    for y in 0..side {
+        for x in 0..side {
+            let corner = y * row + x;
+            triangles.push([corner, corner + 1, corner + row]);
+            triangles.push([corner + 1, corner + row + 1, corner + row]);
+        }
+    }
+    // Fisher-Yates with a fixed LCG, so every run sees the same order.
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    for i in (1..triangles.len()).rev() {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        triangles.swap(i, (state >> 33) as usize % (i + 1));
+    }
+    (vertices, triangles.concat())
+}
+
+fn sorted_triangles(vertices: &[Point], indices: &[u32]) -> Vec<[[u32; 3]; 3]> {
+    // Compared by position, which survives vertex reordering.
+    let mut triangles: Vec<[[u32; 3]; 3]> = indices
+        .chunks(3)
+        .map(|triangle| {
+            [0, 1, 2].map(|corner| vertices[triangle[corner] as usize].0.map(f32::to_bits))
+        })
+        .collect();
+    triangles.sort();
+    triangles
+}
+
+#[test]
+fn cache_reordering_keeps_every_triangle_and_its_winding() {
+    let (vertices, mut indices) = shuffled_grid(16);
+    let before = sorted_triangles(&vertices, &indices);
+    optimize_vertex_cache(&mut indices, vertices.len());
+    assert!(indices
+        .iter()
+        .all(|&index| (index as usize) < vertices.len()));
+    assert_eq!(sorted_triangles(&vertices, &indices), before);
+}
+
+#[test]
+fn cache_reordering_lowers_the_miss_ratio() {
+    let (vertices, mut indices) = shuffled_grid(32);
+    let before = estimate_acmr(&indices, vertices.len());
+    optimize_vertex_cache(&mut indices, vertices.len());
+    let after = estimate_acmr(&indices, vertices.len());
+    assert!(after < before, "ACMR went from {} to {}", before, after);
+    assert!(after < 1.0, "ACMR {} after reordering a grid", after);
+}
+
+#[test]
+fn cache_reordering_never_worsens_an_optimized_mesh() {
+    let (vertices, mut indices) = shuffled_grid(32);
+    optimize_vertex_cache(&mut indices, vertices.len());
+    let once = estimate_acmr(&indices, vertices.len());
+    optimize_vertex_cache(&mut indices, vertices.len());
+    assert!(estimate_acmr(&indices, vertices.len()) <= once);
+}
+
+#[test]
+fn fetch_remap_is_a_permutation_in_first_use_order() {
+    let (mut vertices, mut indices) = shuffled_grid(8);
+    // A vertex no triangle uses stays, at the end.
+    vertices.push(Point([-1.0, -1.0, -1.0]));
+    let (original_vertices, original_indices) = (vertices.clone(), indices.clone());
+    optimize_vertex_fetch(&mut vertices, &mut indices);
+
+    let mut remapped: Vec<_> = vertices.iter().map(|p| p.0.map(f32::to_bits)).collect();
+    let mut original: Vec<_> = original_vertices
+        .iter()
+        .map(|p| p.0.map(f32::to_bits))
+        .collect();
+    remapped.sort();
+    original.sort();
+    assert_eq!(remapped, original);
+    assert_eq!(vertices.last(), original_vertices.last());
+
+    for (&new, &old) in indices.iter().zip(&original_indices) {
+        assert_eq!(vertices[new as usize], original_vertices[old as usize]);
+    }
+    let mut next = 0;
+    for &index in &indices {
+        assert!(
+            index <= next,
+            "vertex {} used before vertex {}",
+            next,
+            index
+        );
+        if index == next {
+            next += 1;
+        }
+    }
+}
+
+#[test]
+fn quantization_error_stays_within_half_a_step() {
+    let mut vertices: Vec<Point> = (0..100)
+        .map(|i| {
+            let t = i as f32 * 0.37;
+            Point([t.sin() * 3.0, t.cos() * 0.5 - 2.0, t * 0.01])
+        })
+        .collect();
+    let original = vertices.clone();
+    let reported = quantize_positions(&mut vertices);
+
+    let mut measured: f32 = 0.0;
+    for axis in 0..3 {
+        let (min, max) = original.iter().fold((f32::MAX, f32::MIN), |(min, max), p| {
+            (min.min(p.0[axis]), max.max(p.0[axis]))
+        });
+        // Half a 16-bit step, plus float rounding.
+        let bound = (max - min) / u16::MAX as f32 * 0.5 + 1e-6;
+        for (quantized, point) in vertices.iter().zip(&original) {
+            let error = (quantized.0[axis] - point.0[axis]).abs();
+            assert!(error <= bound, "axis {} off by {} > {}", axis, error, bound);
+            measured = measured.max(error);
+        }
+    }
+    assert_eq!(reported, measured);
+}
+
+#[test]
+fn flat_axes_are_left_alone_by_quantization() {
+    let mut vertices = vec![Point([0.0, 1.0, 5.0]), Point([2.0, 1.0, 5.0])];
+    quantize_positions(&mut vertices);
+    assert!(vertices.iter().all(|p| p.0[1] == 1.0 && p.0[2] == 5.0));
+}
+
+#[test]
+fn optimizing_u16_meshes_reports_both_miss_ratios() {
+    let (mut vertices, indices) = shuffled_grid(16);
+    let mut indices: Vec<u16> = indices.iter().map(|&index| index as u16).collect();
+    let stats = optimize_mesh(&mut vertices, &mut indices, false);
+    assert!(stats.acmr_after < stats.acmr_before);
+    assert_eq!(stats.quantization_error, None);
+    assert_eq!(stats.acmr_after, estimate_acmr(&indices, vertices.len()));
+}
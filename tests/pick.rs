@@ -0,0 +1,38 @@
+//! Which object ID and target pixel a click reads.
+
+use ash::vk;
+use rust_vulkan::vulkan_app::{object_id, object_index, render_pixel, AspectPolicy};
+
+fn extent(width: u32, height: u32) -> vk::Extent2D {
+    vk::Extent2D { width, height }
+}
+
+#[test]
+fn ids_stand_for_objects_and_zero_for_the_background() {
+    assert_eq!(object_index(0), None);
+    for index in [0, 1, 41] {
+        assert_eq!(object_index(object_id(index)), Some(index));
+    }
+}
+
+#[test]
+fn window_positions_scale_to_the_render_extent() {
+    let (display, render) = (extent(800, 600), extent(400, 300));
+    let scene = AspectPolicy::Stretch.scene_rect(render);
+    assert_eq!(
+        render_pixel(799, 599, display, render, scene),
+        Some(vk::Offset2D { x: 399, y: 299 })
+    );
+    assert_eq!(render_pixel(800, 0, display, render, scene), None);
+}
+
+#[test]
+fn the_letterbox_bars_pick_nothing() {
+    let size = extent(1600, 600);
+    let scene = AspectPolicy::Letterbox(4.0 / 3.0).scene_rect(size);
+    assert_eq!(render_pixel(100, 300, size, size, scene), None);
+    assert_eq!(
+        render_pixel(800, 300, size, size, scene),
+        Some(vk::Offset2D { x: 800, y: 300 })
+    );
+}
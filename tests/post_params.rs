@@ -0,0 +1,29 @@
+//! The post-process vignette and gamma steps.
+
+use rust_vulkan::vulkan_app::PostParams;
+
+#[test]
+fn the_vignette_steps_to_black_corners_and_round_to_none() {
+    let mut params = PostParams {
+        vignette: 0.0,
+        gamma: 1.0,
+    };
+    let mut seen = Vec::new();
+    for _ in 0..5 {
+        params.cycle_vignette();
+        seen.push(params.vignette);
+    }
+    assert_eq!(seen, [0.25, 0.5, 0.75, 1.0, 0.0]);
+}
+
+#[test]
+fn gamma_stays_within_its_range() {
+    let mut params = PostParams::default();
+    assert_eq!(params.gamma, 1.0);
+    params.step_gamma(100);
+    assert_eq!(params.gamma, 2.5);
+    params.step_gamma(-100);
+    assert_eq!(params.gamma, 0.5);
+    params.step_gamma(1);
+    assert!((params.gamma - 0.6).abs() < 1e-6);
+}
@@ -0,0 +1,89 @@
+//! The adaptive resolution scaler against made-up frame times.
+
+use ash::vk;
+use rust_vulkan::vulkan_app::{scaled_extent, RenderScaler, RENDER_SCALE_MAX_PERCENT};
+
+const SAMPLES: u32 = 30;
+
+/// Pushes `SAMPLES` frames of `frame_ms`, returning the last decision.
+fn run(scaler: &mut RenderScaler, frame_ms: f32) -> Option<u32> {
+    (0..SAMPLES).fold(None, |_, _| scaler.push(frame_ms))
+}
+
+#[test]
+fn without_a_target_the_scale_stays_full() {
+    let mut scaler = RenderScaler::new(None);
+    assert_eq!(run(&mut scaler, 100.0), None);
+    assert_eq!(scaler.percent(), 100);
+}
+
+#[test]
+fn slow_frames_step_down_to_the_minimum() {
+    let mut scaler = RenderScaler::new(Some(16.0));
+    assert_eq!(run(&mut scaler, 40.0), Some(90));
+    for _ in 0..10 {
+        run(&mut scaler, 40.0);
+    }
+    assert_eq!(scaler.percent(), 50);
+}
+
+#[test]
+fn steps_back_up_only_with_headroom() {
+    let mut scaler = RenderScaler::new(Some(16.0));
+    run(&mut scaler, 20.0);
+    assert_eq!(scaler.percent(), 90);
+    // 90% to 100% is 1.23 times the pixels: 12 ms would predict 14.8, too
+    // close to 16 to risk stepping straight back down.
+    assert_eq!(run(&mut scaler, 12.0), None);
+    assert_eq!(run(&mut scaler, 8.0), Some(100));
+}
+
+#[test]
+fn nudging_pins_the_scale_until_released() {
+    let mut scaler = RenderScaler::new(Some(16.0));
+    assert_eq!(scaler.nudge(-2), Some(80));
+    assert!(scaler.is_pinned());
+    assert_eq!(run(&mut scaler, 40.0), None);
+    assert_eq!(scaler.nudge(10), Some(100));
+    assert_eq!(scaler.nudge(1), None);
+    scaler.unpin();
+    assert_eq!(run(&mut scaler, 40.0), Some(90));
+}
+
+#[test]
+fn jumping_pins_any_scale_up_to_the_maximum() {
+    let mut scaler = RenderScaler::new(Some(16.0));
+    assert_eq!(scaler.jump(RENDER_SCALE_MAX_PERCENT), Some(200));
+    assert!(scaler.is_pinned());
+    assert_eq!(run(&mut scaler, 40.0), None);
+    assert_eq!(scaler.jump(1000), None);
+    assert_eq!(scaler.jump(10), Some(50));
+    assert_eq!(scaler.jump(150), Some(150));
+    // Nudging steps down from above full scale but never further up.
+    assert_eq!(scaler.nudge(1), None);
+    assert_eq!(scaler.nudge(-1), Some(140));
+}
+
+#[test]
+fn scaled_extents_round_and_keep_a_pixel() {
+    let extent = vk::Extent2D {
+        width: 1366,
+        height: 768,
+    };
+    assert_eq!(
+        scaled_extent(extent, 50),
+        vk::Extent2D {
+            width: 683,
+            height: 384
+        }
+    );
+    assert_eq!(scaled_extent(extent, 100), extent);
+    let tiny = scaled_extent(
+        vk::Extent2D {
+            width: 1,
+            height: 1,
+        },
+        50,
+    );
+    assert_eq!((tiny.width, tiny.height), (1, 1));
+}
@@ -0,0 +1,107 @@
+//! The render scale comparison: GPU times kept per step of the ladder and
+//! where the A/B split's right half is copied from.
+
+use ash::vk;
+use rust_vulkan::vulkan_app::{
+    right_half_blit, scaled_extent, DeviceLimits, ScaleTimes, MAX_FRAMES_IN_FLIGHT,
+    RENDER_SCALE_LADDER,
+};
+
+fn limits() -> DeviceLimits {
+    DeviceLimits {
+        max_push_constants_size: 128,
+        max_per_stage_descriptor_uniform_buffers: 12,
+        max_descriptor_set_uniform_buffers_dynamic: 8,
+        max_bound_descriptor_sets: 4,
+        max_framebuffer_width: 4096,
+        max_framebuffer_height: 4096,
+        max_image_dimension_2d: 4096,
+        min_uniform_buffer_offset_alignment: 256,
+    }
+}
+
+fn extent(width: u32, height: u32) -> vk::Extent2D {
+    vk::Extent2D { width, height }
+}
+
+#[test]
+fn frames_in_flight_at_a_change_are_not_counted() {
+    let mut times = ScaleTimes::default();
+    times.set_scale(Some(50));
+    for _ in 0..MAX_FRAMES_IN_FLIGHT {
+        times.push(100.0);
+    }
+    assert_eq!(times.average_ms(50), None);
+    times.push(2.0);
+    times.push(4.0);
+    assert_eq!(times.average_ms(50), Some(3.0));
+    // Setting the same scale again isn't a change.
+    times.set_scale(Some(50));
+    times.push(6.0);
+    assert_eq!(times.average_ms(50), Some(4.0));
+}
+
+#[test]
+fn times_are_kept_per_step_and_not_off_the_ladder() {
+    let mut times = ScaleTimes::default();
+    for (percent, ms) in [(Some(200), 8.0), (Some(120), 5.0), (None, 9.0)] {
+        times.set_scale(percent);
+        for _ in 0..MAX_FRAMES_IN_FLIGHT + 3 {
+            times.push(ms);
+        }
+    }
+    assert_eq!(times.average_ms(200), Some(8.0));
+    assert_eq!(times.average_ms(120), None);
+    for percent in [50, 75, 100, 150] {
+        assert_eq!(times.average_ms(percent), None, "{percent}%");
+    }
+}
+
+#[test]
+fn lines_show_each_step_with_its_pixels_and_mark_the_current_one() {
+    let mut times = ScaleTimes::default();
+    times.set_scale(Some(100));
+    for _ in 0..MAX_FRAMES_IN_FLIGHT + 1 {
+        times.push(2.5);
+    }
+    let lines = times.lines(extent(3000, 2000), &limits(), 100);
+    assert_eq!(lines.len(), RENDER_SCALE_LADDER.len());
+    assert!(
+        lines[0].starts_with("  Ctrl+1  50%  1500x1000   1.50 MP"),
+        "{}",
+        lines[0]
+    );
+    assert!(lines[0].ends_with('-'), "{}", lines[0]);
+    assert!(lines[2].starts_with("> Ctrl+3 100%"), "{}", lines[2]);
+    assert!(lines[2].ends_with("2.50 ms"), "{}", lines[2]);
+    // 200% is held to what the device can render.
+    assert!(lines[4].contains(" 4096x4000 "), "{}", lines[4]);
+}
+
+#[test]
+fn the_halves_meet_at_the_same_place_in_the_scene() {
+    let display = extent(801, 600);
+    for percent in RENDER_SCALE_LADDER {
+        let source = scaled_extent(display, percent);
+        let blit = right_half_blit(source, display);
+        let [src_start, src_end] = blit.src_offsets;
+        let [dst_start, dst_end] = blit.dst_offsets;
+        assert_eq!((dst_start.x, dst_end.x, dst_end.y), (400, 801, 600));
+        assert_eq!(
+            (src_end.x, src_end.y),
+            (source.width as i32, source.height as i32)
+        );
+        // The split column is at the same fraction of both, to the nearest
+        // source pixel.
+        let at = |x: i32, width: u32| x as f32 / width as f32;
+        assert!(
+            (at(src_start.x, source.width) - at(dst_start.x, display.width)).abs()
+                <= 0.5 / source.width as f32,
+            "{percent}%"
+        );
+    }
+    let full = right_half_blit(display, display);
+    assert_eq!(full.src_offsets, full.dst_offsets);
+    let double = right_half_blit(extent(1602, 1200), display);
+    assert_eq!(double.src_offsets[0].x, 800);
+}
@@ -0,0 +1,58 @@
+//! The perceptual hash `--self-test` compares its frames with.
+
+use rust_vulkan::vulkan_app::{
+    hash_distance, perceptual_hash, CapturedFrame, SELF_TEST_HASH_TOLERANCE,
+};
+
+const SIZE: u32 = 64;
+
+/// A black frame with a grey square, `size` pixels across, its top-left
+/// corner at `(x, y)`.
+fn square(x: u32, y: u32, size: u32) -> CapturedFrame {
+    let mut rgba = vec![0; (SIZE * SIZE * 4) as usize];
+    for row in y..y + size {
+        for column in x..x + size {
+            let start = ((row * SIZE + column) * 4) as usize;
+            rgba[start..start + 4].copy_from_slice(&[180, 180, 180, 255]);
+        }
+    }
+    CapturedFrame {
+        width: SIZE,
+        height: SIZE,
+        rgba,
+    }
+}
+
+#[test]
+fn bits_follow_the_cells_brighter_than_the_mean() {
+    // The top-left 8x8 pixels are the first of the 8x8 cells.
+    assert_eq!(perceptual_hash(&square(0, 0, 8)), 1 << 63);
+    assert_eq!(perceptual_hash(&square(56, 56, 8)), 1);
+    // Nothing is brighter than the mean of a blank frame.
+    assert_eq!(perceptual_hash(&square(0, 0, 0)), 0);
+}
+
+#[test]
+fn small_rasterization_differences_stay_within_the_tolerance() {
+    let reference = perceptual_hash(&square(16, 16, 32));
+    // A pixel more or less along each edge.
+    for (x, y, size) in [(15, 15, 33), (17, 17, 31), (16, 17, 32)] {
+        let distance = hash_distance(perceptual_hash(&square(x, y, size)), reference);
+        assert!(distance <= SELF_TEST_HASH_TOLERANCE, "{} bits", distance);
+    }
+    // Brightness alone doesn't move the bits.
+    let mut dimmer = square(16, 16, 32);
+    for value in dimmer.rgba.iter_mut().filter(|value| **value == 180) {
+        *value = 150;
+    }
+    assert_eq!(perceptual_hash(&dimmer), reference);
+}
+
+#[test]
+fn a_moved_or_missing_square_does_not() {
+    let reference = perceptual_hash(&square(16, 16, 32));
+    for other in [square(0, 0, 32), square(16, 16, 8), square(0, 0, 0)] {
+        let distance = hash_distance(perceptual_hash(&other), reference);
+        assert!(distance > SELF_TEST_HASH_TOLERANCE, "{} bits", distance);
+    }
+}
@@ -0,0 +1,231 @@
+//! Reading and writing the settings file.
+
+use ash::vk;
+use cgmath::Deg;
+use rust_vulkan::vulkan_app::{
+    RenderMode, RendererConfig, Settings, SettingsError, SettingsFile, WindowSettings,
+};
+
+fn defaults() -> Settings {
+    Settings::from_config(&RendererConfig::default())
+}
+
+#[test]
+fn written_settings_read_back_the_same() {
+    let settings = Settings {
+        clear_color: [0.25, 0.5, 1.0],
+        render_mode: RenderMode::Continuous,
+        animation_speed: 2.0,
+        show_frame_graph: true,
+        window: WindowSettings {
+            width: 1024,
+            height: 768,
+            position: Some((-40, 120)),
+            fullscreen: true,
+        },
+        ..defaults()
+    };
+    assert_eq!(
+        Settings::parse(&settings.to_toml(), defaults()),
+        Ok(settings)
+    );
+}
+
+#[test]
+fn every_value_of_every_setting_round_trips() {
+    let modes = [
+        vk::PresentModeKHR::IMMEDIATE,
+        vk::PresentModeKHR::MAILBOX,
+        vk::PresentModeKHR::FIFO,
+        vk::PresentModeKHR::FIFO_RELAXED,
+    ];
+    let backgrounds = ["none", "gradient", "starfield", "clouds", "skybox"];
+    for (i, (&present_mode, background)) in modes.iter().cycle().zip(backgrounds).enumerate() {
+        let background = Settings::parse(&format!("background = \"{}\"", background), defaults())
+            .unwrap()
+            .background;
+        let settings = Settings {
+            clear_color: [0.1, 1e-7, 0.333_333_34],
+            background,
+            msaa_samples: 1 << i,
+            present_mode,
+            render_mode: [RenderMode::Continuous, RenderMode::OnDemand][i % 2],
+            animation_speed: -0.75 * i as f32,
+            camera_yaw: Deg(-179.5),
+            camera_elevation: Deg(89.999),
+            show_frame_graph: i % 2 == 0,
+            window: WindowSettings {
+                width: 1 + i as u32,
+                height: 4096,
+                position: [None, Some((-3840, 0)), Some((0, -1))][i % 3],
+                fullscreen: i % 2 == 1,
+            },
+        };
+        assert_eq!(
+            Settings::parse(&settings.to_toml(), defaults()),
+            Ok(settings)
+        );
+    }
+}
+
+#[test]
+fn comments_and_whitespace_are_ignored() {
+    let text = "# Written by hand\n\
+                \n\
+                \t  msaa   =   8   # the most this GPU has\n\
+                render_mode = \"on-demand\"#trailing\n\
+                [ camera ]\n\
+                yaw=-45\n";
+    let settings = Settings::parse(text, defaults()).unwrap();
+    assert_eq!(settings.msaa_samples, 8);
+    assert_eq!(settings.render_mode, RenderMode::OnDemand);
+    assert_eq!(settings.camera_yaw, Deg(-45.0));
+}
+
+#[test]
+fn malformed_files_report_the_line() {
+    let cases: [(&str, usize); 8] = [
+        ("msaa = 2.5", 1),
+        ("\n\nclear_color = [1, x, 2]", 3),
+        ("clear_color = [1, 2, 3", 1),
+        ("animation_speed = \"fast", 1),
+        ("animation_speed = fast", 1),
+        ("[window]\nx = 1.5", 2),
+        ("[overlay]\nframe_graph = 1", 2),
+        ("msaa = 4\njust some words", 2),
+    ];
+    for (text, line) in cases {
+        let error = Settings::parse(text, defaults()).unwrap_err();
+        assert_eq!(error.line, Some(line), "{:?}: {}", text, error);
+        assert!(
+            error.to_string().starts_with(&format!("line {}: ", line)),
+            "{}",
+            error
+        );
+    }
+}
+
+#[test]
+fn bad_values_name_the_setting() {
+    let cases: [(&str, &str); 10] = [
+        ("msaa = 0", "`msaa` expects a whole sample count"),
+        ("clear_color = [1, 2]", "`clear_color` expects [r, g, b]"),
+        (
+            "animation_speed = inf",
+            "`animation_speed` expects a finite number",
+        ),
+        (
+            "background = \"fog\"",
+            "`background` expects \"none\", \"gradient\", \"starfield\", \"clouds\" or \"skybox\"",
+        ),
+        (
+            "present_mode = \"vsync\"",
+            "`present_mode` expects \"immediate\", \"mailbox\", \"fifo\" or \"fifo-relaxed\"",
+        ),
+        (
+            "render_mode = \"sometimes\"",
+            "`render_mode` expects \"continuous\" or \"on-demand\"",
+        ),
+        (
+            "[window]\nwidth = 0",
+            "`window.width` expects a positive number of pixels",
+        ),
+        (
+            "[camera]\nyaw = nan",
+            "`camera.yaw` expects a finite number",
+        ),
+        ("version = 0", "`version` expects a positive whole number"),
+        ("[camera]\n\nroll = 3", "unknown setting `camera.roll`"),
+    ];
+    for (text, message) in cases {
+        assert_eq!(
+            Settings::parse(text, defaults()),
+            Err(SettingsError {
+                line: None,
+                message: message.to_string(),
+            }),
+            "{:?}",
+            text
+        );
+    }
+    let error = Settings::parse("msaa = 4\n[window]\nheight = 0", defaults()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "`window.height` expects a positive number of pixels"
+    );
+}
+
+#[test]
+fn settings_carry_over_to_the_config_and_back() {
+    let settings = Settings {
+        clear_color: [0.5, 0.25, 0.0],
+        msaa_samples: 8,
+        present_mode: vk::PresentModeKHR::FIFO,
+        render_mode: RenderMode::OnDemand,
+        animation_speed: 3.0,
+        camera_yaw: Deg(30.0),
+        camera_elevation: Deg(-10.0),
+        show_frame_graph: true,
+        ..defaults()
+    };
+    let mut config = RendererConfig::default();
+    settings.apply_to(&mut config);
+    assert_eq!(Settings::from_config(&config), settings);
+}
+
+#[test]
+fn a_file_from_before_versions_keeps_the_defaults_it_leaves_out() {
+    let settings = Settings::parse("animation_speed = 0.5\n", defaults()).unwrap();
+    assert_eq!(settings.animation_speed, 0.5);
+    assert_eq!(settings.window, WindowSettings::default());
+    assert!(Settings::parse("bloom = true\n", defaults()).is_err());
+}
+
+#[test]
+fn a_newer_file_skips_settings_this_build_does_not_know() {
+    let text = "version = 99\nbloom = true\n\n[window]\nwidth = 640\n";
+    let settings = Settings::parse(text, defaults()).unwrap();
+    assert_eq!(settings.window.width, 640);
+}
+
+#[test]
+fn a_file_that_does_not_parse_is_moved_aside() {
+    let dir = std::env::temp_dir().join(format!("vulkan-cube-settings-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("settings.toml");
+    std::fs::write(&path, "animation_speed = fast\n").unwrap();
+
+    let mut file = SettingsFile::new(&path);
+    assert_eq!(file.load(defaults()), None);
+    assert!(!path.exists());
+    assert!(dir.join("settings.toml.corrupt").exists());
+
+    // The next save starts a fresh file.
+    file.flush(defaults());
+    assert_eq!(SettingsFile::new(&path).load(defaults()), Some(defaults()));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_legacy_file_is_read_and_moved_over_until_the_file_exists() {
+    let dir = std::env::temp_dir().join(format!("vulkan-cube-legacy-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config").join("settings.toml");
+    let legacy = dir.join("cube.toml");
+    std::fs::write(&legacy, "animation_speed = 2.0\n").unwrap();
+    let migrated = Settings {
+        animation_speed: 2.0,
+        ..defaults()
+    };
+
+    let mut file = SettingsFile::new(&path).migrating_from(&legacy);
+    assert_eq!(file.load(defaults()), Some(migrated));
+    assert!(path.exists());
+    assert!(legacy.exists());
+
+    // From now on the file wins, even over a changed legacy file.
+    std::fs::write(&legacy, "animation_speed = 3.0\n").unwrap();
+    let mut file = SettingsFile::new(&path).migrating_from(&legacy);
+    assert_eq!(file.load(defaults()), Some(migrated));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
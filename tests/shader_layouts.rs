@@ -0,0 +1,144 @@
+//! Compiles every shader permutation the renderer builds and checks the
+//! blocks and structs they share with Rust against the Rust layouts, as
+//! the SPIR-V reports them.
+
+use rust_vulkan::vulkan_app::{
+    block_offsets, compile_glsl, shader_permutations, AnimationParams, DrawConstants, Light,
+    PerFrameUbo, PerViewUbo, PickPush,
+};
+use std::mem::offset_of;
+use std::path::Path;
+
+/// A GLSL block or struct mirroring a `#[repr(C)]` struct, with the offset
+/// of each member in the Rust struct, by its GLSL name.
+struct Mirror {
+    glsl: &'static str,
+    fields: Vec<(&'static str, usize)>,
+    /// Push constant blocks declare only the fields their stage reads.
+    partial: bool,
+}
+
+fn mirrors() -> Vec<Mirror> {
+    let draw_constants = vec![
+        ("tint", offset_of!(DrawConstants, tint)),
+        ("model", offset_of!(DrawConstants, model)),
+    ];
+    vec![
+        Mirror {
+            glsl: "PerFrame",
+            fields: vec![
+                ("cameraPosition", offset_of!(PerFrameUbo, camera_position)),
+                ("time", offset_of!(PerFrameUbo, time)),
+                ("lightCount", offset_of!(PerFrameUbo, light_count)),
+                ("ambient", offset_of!(PerFrameUbo, ambient)),
+                ("lights", offset_of!(PerFrameUbo, lights)),
+            ],
+            partial: false,
+        },
+        Mirror {
+            glsl: "Light",
+            fields: vec![
+                ("direction", offset_of!(Light, direction)),
+                ("color", offset_of!(Light, color)),
+            ],
+            partial: false,
+        },
+        Mirror {
+            glsl: "PerView",
+            fields: vec![
+                ("view", offset_of!(PerViewUbo, view)),
+                ("proj", offset_of!(PerViewUbo, proj)),
+                ("viewport", offset_of!(PerViewUbo, viewport)),
+            ],
+            partial: false,
+        },
+        Mirror {
+            glsl: "DrawSlot",
+            fields: draw_constants.clone(),
+            partial: false,
+        },
+        Mirror {
+            glsl: "DrawPush",
+            fields: draw_constants,
+            partial: true,
+        },
+        Mirror {
+            glsl: "AnimationParams",
+            fields: vec![
+                ("axis", offset_of!(AnimationParams, axis)),
+                ("speed", offset_of!(AnimationParams, speed)),
+                ("phase", offset_of!(AnimationParams, phase)),
+                ("base", offset_of!(AnimationParams, base)),
+            ],
+            partial: false,
+        },
+        Mirror {
+            glsl: "PickPush",
+            fields: vec![
+                ("model", offset_of!(PickPush, model)),
+                ("firstId", offset_of!(PickPush, first_id)),
+            ],
+            partial: false,
+        },
+    ]
+}
+
+#[test]
+fn every_shader_permutation_compiles_with_the_rust_layouts() {
+    let shader_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/shaders");
+    let compiler = shaderc::Compiler::new().expect("shaderc should load");
+    let mirrors = mirrors();
+    let mut declared = vec![0; mirrors.len()];
+    let mut failures = Vec::new();
+
+    let permutations = shader_permutations(&shader_dir).expect("the shaders should be listed");
+    assert!(permutations
+        .iter()
+        .any(|(path, define)| path.ends_with("shader.frag") && define.is_some()));
+    for (path, define) in permutations {
+        let label = match define {
+            Some((name, value)) => format!("{} with {}={}", path.display(), name, value),
+            None => path.display().to_string(),
+        };
+        let spirv = match compile_glsl(&compiler, &shader_dir, &path, define) {
+            Ok(spirv) => spirv,
+            Err(diagnostics) => {
+                failures.push(format!("{} failed to compile:\n{}", label, diagnostics));
+                continue;
+            }
+        };
+        for (mirror, declared) in mirrors.iter().zip(&mut declared) {
+            let Some(members) = block_offsets(&spirv, mirror.glsl) else {
+                continue;
+            };
+            *declared += 1;
+            for (name, offset) in &members {
+                match mirror.fields.iter().find(|(field, _)| field == name) {
+                    Some(&(_, expected)) if expected == *offset as usize => {}
+                    Some(&(_, expected)) => failures.push(format!(
+                        "{}: {}.{} is at {}, the Rust struct has it at {}",
+                        label, mirror.glsl, name, offset, expected
+                    )),
+                    None => failures.push(format!(
+                        "{}: {}.{} has no Rust field",
+                        label, mirror.glsl, name
+                    )),
+                }
+            }
+            if !mirror.partial && members.len() != mirror.fields.len() {
+                failures.push(format!(
+                    "{}: {} has {} members, the Rust struct {}",
+                    label,
+                    mirror.glsl,
+                    members.len(),
+                    mirror.fields.len()
+                ));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+    for (mirror, declared) in mirrors.iter().zip(declared) {
+        assert!(declared > 0, "no shader declares {}", mirror.glsl);
+    }
+}
@@ -0,0 +1,263 @@
+//! Starts the renderer headless and draws a few frames. Needs a Vulkan
+//! driver, so only built with `--features vulkan-tests`.
+
+mod common;
+
+use ash::vk;
+use common::{assert_matches_golden, headless_app, headless_app_with, pixel, HEIGHT, WIDTH};
+use rust_vulkan::vulkan_app::{
+    CapturedFrame, RendererConfig, VulkanApp, RENDER_SCALE_LADDER, SELF_TEST_SIZE,
+};
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::rc::Rc;
+
+/// Frames rendered before the last one is checked.
+const SMOKE_FRAMES: u32 = 10;
+
+/// Tracked Vulkan objects a default headless start may create. Optional
+/// subsystems are created on first use, so growing past this means something
+/// moved back into startup; raise it only for a feature that has to be there
+/// from the first frame.
+const STARTUP_OBJECT_BUDGET: usize = 80;
+
+/// Animation time the labeled cube's golden image is rendered at.
+const LABELED_CUBE_TIME: f32 = 0.75;
+
+#[test]
+fn renders_the_cube_without_validation_errors() {
+    let mut app = headless_app();
+    let mut frame = None;
+    for index in 0..SMOKE_FRAMES {
+        frame = Some(
+            app.render_to_image(index as f32 / 60.0)
+                .expect("rendering a frame should succeed"),
+        );
+    }
+    let frame = frame.unwrap();
+    assert_eq!(app.validation_error_count(), 0);
+    // The cube sits at the centre of the default view, over a black clear.
+    let [r, g, b, _] = pixel(&frame, WIDTH / 2, HEIGHT / 2);
+    assert_ne!(
+        [r, g, b],
+        [0, 0, 0],
+        "the centre pixel should show the cube"
+    );
+    app.destroy().expect("shutdown should destroy everything");
+}
+
+#[test]
+fn default_startup_stays_within_its_object_budget() {
+    let mut app = headless_app();
+    let live = app.diagnostics_report().live_resources;
+    let total: usize = live.iter().map(|(_, count)| count).sum();
+    assert!(
+        total <= STARTUP_OBJECT_BUDGET,
+        "startup created {} objects, over the budget of {}: {:?}",
+        total,
+        STARTUP_OBJECT_BUDGET,
+        live
+    );
+    app.destroy().expect("shutdown should destroy everything");
+}
+
+#[test]
+fn starts_and_shuts_down_twice() {
+    for _ in 0..2 {
+        let mut app = headless_app();
+        app.render_to_image(0.0)
+            .expect("rendering a frame should succeed");
+        app.destroy().expect("shutdown should destroy everything");
+    }
+}
+
+#[test]
+fn closes_twice_then_drops() {
+    let mut app = headless_app();
+    app.render_to_image(0.0)
+        .expect("rendering a frame should succeed");
+    assert!(!app.resource_report().is_empty());
+    app.close().expect("shutdown should destroy everything");
+    assert!(app.is_closed());
+    let leaks = app.resource_report();
+    assert!(leaks.is_empty(), "left alive: {:?}", leaks);
+    app.close()
+        .expect("closing again should do nothing and report nothing");
+    assert!(app.resource_report().is_empty());
+    assert_eq!(app.validation_error_count(), 0);
+    // Dropping it afterwards must not tear anything down a second time.
+    drop(app);
+}
+
+#[test]
+fn dropping_without_destroy_still_tears_down() {
+    let mut app = headless_app();
+    app.render_to_image(0.0)
+        .expect("rendering a frame should succeed");
+    // The fallback logs instead of reporting; it must neither panic nor
+    // leave the next renderer a device it can't create.
+    drop(app);
+    let mut app = headless_app();
+    app.render_to_image(0.0)
+        .expect("rendering after a dropped renderer should succeed");
+    assert_eq!(app.validation_error_count(), 0);
+    app.destroy().expect("shutdown should destroy everything");
+}
+
+#[test]
+fn picks_the_cube_under_the_cursor() {
+    let mut app = headless_app();
+    app.set_picking(true).expect("picking should start");
+    app.render_to_image(0.0)
+        .expect("rendering a frame should succeed");
+    assert_eq!(app.pick(WIDTH / 2, HEIGHT / 2), Some(0));
+    assert_eq!(app.pick(0, 0), None);
+    assert_eq!(app.validation_error_count(), 0);
+    app.destroy().expect("shutdown should destroy everything");
+}
+
+#[test]
+fn application_identity_reaches_the_driver_and_the_report() {
+    // What vkCreateInstance is handed, read back through the create hook.
+    let seen = Rc::new(RefCell::new(None));
+    let hook_seen = Rc::clone(&seen);
+    let config = RendererConfig {
+        app_name: "Smoke Test".to_string(),
+        app_version: vk::make_api_version(0, 2, 3, 4),
+        engine_name: "Smoke Engine ü".to_string(),
+        engine_version: vk::make_api_version(0, 5, 6, 7),
+        instance_create_hook: Some(Box::new(move |create_info: &mut vk::InstanceCreateInfo| {
+            let app_info = unsafe { &*create_info.p_application_info };
+            let name = |ptr| unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            *hook_seen.borrow_mut() = Some((
+                name(app_info.p_application_name),
+                app_info.application_version,
+                name(app_info.p_engine_name),
+                app_info.engine_version,
+            ));
+        })),
+        ..RendererConfig::default()
+    };
+    let mut app = headless_app_with(config);
+    assert_eq!(
+        seen.borrow_mut().take(),
+        Some((
+            "Smoke Test".to_string(),
+            vk::make_api_version(0, 2, 3, 4),
+            "Smoke Engine ü".to_string(),
+            vk::make_api_version(0, 5, 6, 7),
+        ))
+    );
+
+    let report = app.diagnostics_report();
+    assert_eq!(report.app_name, "Smoke Test");
+    assert_eq!(report.engine_name, "Smoke Engine ü");
+    let text = report.to_string();
+    assert!(text.contains("application: Smoke Test 2.3.4"), "{}", text);
+    assert!(text.contains("engine: Smoke Engine ü 5.6.7"), "{}", text);
+    app.destroy().expect("shutdown should destroy everything");
+}
+
+#[test]
+fn shows_the_inside_of_an_open_box_only_when_double_sided() {
+    let mut app = headless_app_with(RendererConfig {
+        open_box: true,
+        ..RendererConfig::default()
+    });
+    // The box starts double-sided, so its inner walls fill the open top.
+    let double_sided = app
+        .render_to_image(0.0)
+        .expect("rendering a frame should succeed");
+    app.set_double_sided(0, false);
+    let single_sided = app
+        .render_to_image(0.0)
+        .expect("rendering a frame should succeed");
+    assert_eq!(app.validation_error_count(), 0);
+
+    let covered = |frame: &CapturedFrame| {
+        frame
+            .rgba
+            .chunks(4)
+            .filter(|pixel| pixel[..3] != [0, 0, 0])
+            .count()
+    };
+    assert!(
+        covered(&double_sided) > covered(&single_sided),
+        "the inner walls should cover pixels that are background when culled: {} vs {}",
+        covered(&double_sided),
+        covered(&single_sided)
+    );
+    app.destroy().expect("shutdown should destroy everything");
+}
+
+#[test]
+fn the_depth_view_reads_multisampled_depth() {
+    let mut app = headless_app_with(RendererConfig {
+        msaa_samples: 4,
+        ..RendererConfig::default()
+    });
+    app.toggle_depth_view();
+    // Resolved to a single sample where the device can, or read in place.
+    let frame = app
+        .render_to_image(0.0)
+        .expect("rendering a frame should succeed");
+    assert_eq!(app.validation_error_count(), 0);
+    // The cleared background is at the far plane, in white; the cube is
+    // nearer, so darker.
+    let [centre, ..] = pixel(&frame, WIDTH / 2, HEIGHT / 2);
+    let [corner, ..] = pixel(&frame, 0, 0);
+    assert!(
+        centre < corner,
+        "the cube should be nearer than the background: {} vs {}",
+        centre,
+        corner
+    );
+    app.destroy().expect("shutdown should destroy everything");
+}
+
+#[test]
+fn renders_an_a_b_split_of_two_scales() {
+    let mut app = headless_app();
+    app.set_render_scale(RENDER_SCALE_LADDER[0]);
+    app.set_split_scale(Some(RENDER_SCALE_LADDER[4]));
+    for time in [0.0, 0.1] {
+        app.render_to_image(time)
+            .expect("rendering a frame should succeed");
+    }
+    assert_eq!(app.validation_error_count(), 0);
+    let lines = app.render_scale_lines();
+    assert_eq!(lines.len(), RENDER_SCALE_LADDER.len() + 1);
+    // Ending the split frees its target, and frames go on without it.
+    app.set_split_scale(None);
+    app.render_to_image(0.2)
+        .expect("rendering a frame should succeed");
+    app.destroy().expect("shutdown should destroy everything");
+}
+
+#[test]
+fn passes_its_own_self_test() {
+    let mut app =
+        VulkanApp::new_headless(SELF_TEST_SIZE, SELF_TEST_SIZE, RendererConfig::default())
+            .expect("the headless renderer should start");
+    let mut report = app.self_test().expect("the canonical scenes should render");
+    report.append(app.shutdown());
+    assert!(report.passed(), "{}", report);
+}
+
+#[test]
+fn the_labeled_cube_matches_its_golden_image() {
+    let mut app = headless_app();
+    let colour = app
+        .render_to_image(LABELED_CUBE_TIME)
+        .expect("rendering a frame should succeed");
+    app.toggle_face_labels();
+    let labeled = app
+        .render_to_image(LABELED_CUBE_TIME)
+        .expect("rendering a frame should succeed");
+    assert_eq!(app.validation_error_count(), 0);
+    assert_ne!(colour.rgba, labeled.rgba, "the labels should show");
+    // Flipped UVs, mirrored faces or a changed winding or handedness all
+    // move the labels away from where they were recorded.
+    assert_matches_golden(&labeled, "labeled_cube");
+    app.destroy().expect("shutdown should destroy everything");
+}
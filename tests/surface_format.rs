@@ -0,0 +1,121 @@
+//! Swapchain format choice against the lists drivers report.
+
+use ash::vk;
+use rust_vulkan::vulkan_app::{
+    choose_surface_format, is_srgb_format, is_unorm_format, PREFERRED_SURFACE_FORMAT,
+};
+
+fn format(format: vk::Format, color_space: vk::ColorSpaceKHR) -> vk::SurfaceFormatKHR {
+    vk::SurfaceFormatKHR {
+        format,
+        color_space,
+    }
+}
+
+/// What MoltenVK reports with `VK_EXT_swapchain_colorspace` enabled: UNORM
+/// first, then sRGB, then half floats and 10-bit formats in the wide gamut
+/// and HDR colour spaces as well as the sRGB one.
+fn moltenvk() -> Vec<vk::SurfaceFormatKHR> {
+    let mut formats = Vec::new();
+    for color_space in [
+        vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        vk::ColorSpaceKHR::DISPLAY_P3_NONLINEAR_EXT,
+        vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+        vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+    ] {
+        for f in [
+            vk::Format::B8G8R8A8_UNORM,
+            vk::Format::B8G8R8A8_SRGB,
+            vk::Format::R16G16B16A16_SFLOAT,
+            vk::Format::A2B10G10R10_UNORM_PACK32,
+            vk::Format::A2R10G10B10_UNORM_PACK32,
+        ] {
+            formats.push(format(f, color_space));
+        }
+    }
+    formats
+}
+
+#[test]
+fn moltenvk_gets_the_preferred_format() {
+    assert_eq!(choose_surface_format(&moltenvk()), PREFERRED_SURFACE_FORMAT);
+}
+
+#[test]
+fn any_srgb_format_beats_unorm() {
+    // Android drivers list RGBA rather than BGRA.
+    let available = [
+        format(
+            vk::Format::R8G8B8A8_UNORM,
+            vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        ),
+        format(vk::Format::R8G8B8A8_SRGB, vk::ColorSpaceKHR::SRGB_NONLINEAR),
+    ];
+    let chosen = choose_surface_format(&available);
+    assert_eq!(chosen, available[1]);
+    assert!(is_srgb_format(chosen.format));
+    assert!(!is_unorm_format(chosen.format), "the hardware encodes");
+}
+
+#[test]
+fn unorm_in_the_srgb_colour_space_without_srgb_formats() {
+    let available: Vec<_> = moltenvk()
+        .into_iter()
+        .filter(|available| available.format != vk::Format::B8G8R8A8_SRGB)
+        .collect();
+    assert_eq!(
+        choose_surface_format(&available),
+        format(
+            vk::Format::B8G8R8A8_UNORM,
+            vk::ColorSpaceKHR::SRGB_NONLINEAR
+        )
+    );
+    // The shaders encode sRGB themselves on this one.
+    assert!(is_unorm_format(choose_surface_format(&available).format));
+}
+
+#[test]
+fn other_colour_spaces_come_last() {
+    let available = [
+        format(
+            vk::Format::B8G8R8A8_SRGB,
+            vk::ColorSpaceKHR::DISPLAY_P3_NONLINEAR_EXT,
+        ),
+        format(
+            vk::Format::R16G16B16A16_SFLOAT,
+            vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+        ),
+        format(
+            vk::Format::R16G16B16A16_SFLOAT,
+            vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        ),
+    ];
+    assert_eq!(choose_surface_format(&available), available[2]);
+    assert_eq!(choose_surface_format(&available[..2]), available[0]);
+}
+
+#[test]
+fn undefined_means_any_format() {
+    let available = [format(
+        vk::Format::UNDEFINED,
+        vk::ColorSpaceKHR::SRGB_NONLINEAR,
+    )];
+    assert_eq!(choose_surface_format(&available), PREFERRED_SURFACE_FORMAT);
+}
+
+#[test]
+fn undefined_among_others_is_just_unusable() {
+    let available = [
+        format(vk::Format::UNDEFINED, vk::ColorSpaceKHR::SRGB_NONLINEAR),
+        format(
+            vk::Format::A2B10G10R10_UNORM_PACK32,
+            vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        ),
+    ];
+    assert_eq!(choose_surface_format(&available), available[1]);
+}
+
+#[test]
+fn an_empty_list_gets_the_preferred_format() {
+    assert_eq!(choose_surface_format(&[]), PREFERRED_SURFACE_FORMAT);
+}
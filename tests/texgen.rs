@@ -0,0 +1,199 @@
+//! The procedural textures' pixels, checked on the CPU before any upload.
+
+use rust_vulkan::vulkan_app::{face_label_uv, Pattern, FACE_LABELS, FACE_LABEL_BLANK_UV};
+
+const PAPER: [u8; 4] = [255, 255, 255, 255];
+const INK: [u8; 4] = [20, 20, 20, 255];
+const EDGE: [u8; 4] = [90, 90, 90, 255];
+
+/// A generated image with pixel lookup.
+struct Image {
+    size: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    fn new(pattern: Pattern, size: u32) -> Self {
+        let pixels = pattern.generate(size);
+        assert_eq!(pixels.len(), (size * size * 4) as usize);
+        Image { size, pixels }
+    }
+
+    fn at(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * self.size + x) * 4) as usize;
+        self.pixels[i..i + 4].try_into().unwrap()
+    }
+
+    /// The pixel under a texture coordinate, nearest sampled.
+    fn sample(&self, uv: [f32; 2]) -> [u8; 4] {
+        let texel = |t: f32| ((t * self.size as f32) as u32).min(self.size - 1);
+        self.at(texel(uv[0]), texel(uv[1]))
+    }
+}
+
+#[test]
+fn checkerboard_cells_alternate() {
+    let colors = [[200, 0, 0, 255], [0, 0, 200, 255]];
+    let image = Image::new(Pattern::Checkerboard { cells: 4, colors }, 32);
+    for y in 0..32 {
+        for x in 0..32 {
+            let expected = colors[((x / 8 + y / 8) % 2) as usize];
+            assert_eq!(image.at(x, y), expected, "at {x}, {y}");
+        }
+    }
+}
+
+#[test]
+fn grid_lines_have_their_width_and_the_fill_shows_between() {
+    let (line, fill) = ([255, 255, 0, 255], [0, 0, 0, 0]);
+    let grid = Pattern::Grid {
+        cells: 4,
+        line_width: 2,
+        line,
+        fill,
+    };
+    let image = Image::new(grid, 32);
+    for cell in 0..4 {
+        let start = cell * 8;
+        assert_eq!(image.at(start, 5), line);
+        assert_eq!(image.at(start + 1, 5), line);
+        assert_eq!(image.at(start + 2, 5), fill);
+        assert_eq!(image.at(5, start + 1), line);
+        assert_eq!(image.at(start + 7, start + 7), fill);
+    }
+    let lines = image.pixels.chunks(4).filter(|p| *p == line).count();
+    // Two columns and two rows of every 8, less the corners counted twice.
+    assert_eq!(lines, 32 * 32 - 24 * 24);
+}
+
+#[test]
+fn the_default_grid_is_alpha_tested() {
+    let Pattern::Grid { line, fill, .. } = Pattern::default() else {
+        panic!("the default ground texture is a grid");
+    };
+    assert_eq!(line[3], 255);
+    assert_eq!(fill[3], 0);
+}
+
+#[test]
+fn the_uv_test_ramps_red_with_u_and_green_with_v() {
+    let image = Image::new(Pattern::UvTest, 64);
+    assert_eq!(image.at(0, 0), [0, 0, 64, 255]);
+    // The far corner's checker cell is a bright one.
+    assert_eq!(image.at(63, 63), [255, 255, 64, 255]);
+    for y in 0..64 {
+        for x in 0..64 {
+            // Transposing swaps the ramps, so a swapped U and V would show.
+            assert_eq!(image.at(x, y)[0], image.at(y, x)[1], "at {x}, {y}");
+        }
+    }
+    for x in 1..8 {
+        assert!(image.at(x, 0)[0] > image.at(x - 1, 0)[0]);
+        assert!(image.at(0, x)[1] > image.at(0, x - 1)[1]);
+        assert_eq!(image.at(x, 0)[1], 0);
+    }
+}
+
+#[test]
+fn noise_is_grey_varied_and_tiles() {
+    let noise = Pattern::Noise { seed: 7, cells: 8 };
+    let image = Image::new(noise, 256);
+    assert_eq!(image.pixels, noise.generate(256), "the same seed repeats");
+    assert_ne!(
+        image.pixels,
+        Pattern::Noise { seed: 8, cells: 8 }.generate(256)
+    );
+
+    let values: Vec<u8> = image.pixels.chunks(4).map(|p| p[0]).collect();
+    assert!(image
+        .pixels
+        .chunks(4)
+        .all(|p| p[0] == p[1] && p[1] == p[2] && p[3] == 255));
+    let (min, max) = (values.iter().min().unwrap(), values.iter().max().unwrap());
+    assert!(max - min > 100, "values span {min}..{max}");
+
+    // A step is 1/32 of a lattice cell and the smoothstep's slope is at most
+    // 1.5, so wrapping from the last column or row to the first changes a
+    // value no more than a step inside the image does.
+    let step = (1.5 / 32.0 * 255.0) as i32 + 1;
+    for i in 0..256 {
+        let wrap_x = image.at(255, i)[0] as i32 - image.at(0, i)[0] as i32;
+        let wrap_y = image.at(i, 255)[0] as i32 - image.at(i, 0)[0] as i32;
+        assert!(wrap_x.abs() <= step, "row {i} jumps {wrap_x}");
+        assert!(wrap_y.abs() <= step, "column {i} jumps {wrap_y}");
+    }
+}
+
+#[test]
+fn patterns_parse_back_from_their_names() {
+    for name in ["checker", "grid", "uv", "noise", "labels"] {
+        let pattern = Pattern::parse(name).unwrap();
+        assert_eq!(pattern.name(), name);
+    }
+    assert_eq!(Pattern::parse("grid"), Some(Pattern::default()));
+    assert_eq!(Pattern::parse("bricks"), None);
+}
+
+#[test]
+fn each_face_label_cell_is_bordered_inked_and_underlined() {
+    let image = Image::new(Pattern::FaceLabels, 512);
+    for face in 0..FACE_LABELS.len() {
+        let [left, top] = face_label_uv(face, [0.0, 0.0]).map(|t| (t * 512.0) as u32);
+        let cell = 128;
+        assert_eq!(image.at(left, top), EDGE);
+        assert_eq!(image.at(left + cell - 1, top + cell - 1), EDGE);
+        assert_eq!(image.at(left + 10, top + 10), PAPER);
+
+        let rows: Vec<Vec<[u8; 4]>> = (top..top + cell)
+            .map(|y| (left..left + cell).map(|x| image.at(x, y)).collect())
+            .collect();
+        let ink = rows.iter().flatten().filter(|&&p| p == INK).count();
+        assert!(ink > 0, "face {face} has no label");
+        // The underline is the lowest inked row and runs unbroken under
+        // both glyphs.
+        let underline = rows.iter().rposition(|row| row.contains(&INK)).unwrap();
+        let inked = rows[underline].iter().filter(|&&p| p == INK).count();
+        assert_eq!(inked, (2 * 6 - 1) * 8, "face {face}'s underline");
+    }
+}
+
+#[test]
+fn face_labels_read_differently_when_mirrored() {
+    let image = Image::new(Pattern::FaceLabels, 512);
+    let cell = |face: usize| -> Vec<[u8; 4]> {
+        let [left, top] = face_label_uv(face, [0.0, 0.0]).map(|t| (t * 512.0) as u32);
+        (top..top + 128)
+            .flat_map(|y| (left..left + 128).map(move |x| (x, y)))
+            .map(|(x, y)| image.at(x, y))
+            .collect()
+    };
+    let first = cell(0);
+    let mirrored: Vec<[u8; 4]> = first
+        .chunks(128)
+        .flat_map(|row| row.iter().rev().copied())
+        .collect();
+    assert_ne!(first, mirrored);
+    for face in 1..FACE_LABELS.len() {
+        assert_ne!(cell(face), first, "face {face} looks like face 0");
+    }
+}
+
+#[test]
+fn face_label_uvs_stay_inside_their_cells() {
+    for face in 0..FACE_LABELS.len() {
+        let [u0, v0] = face_label_uv(face, [0.0, 0.0]);
+        let [u1, v1] = face_label_uv(face, [1.0, 1.0]);
+        assert!((u1 - u0 - 0.25).abs() < 1e-6 && (v1 - v0 - 0.25).abs() < 1e-6);
+        assert!(u1 <= 1.0 && v1 <= 0.5, "face {face} leaves the label rows");
+    }
+}
+
+#[test]
+fn the_blank_area_stays_white_at_small_sizes() {
+    for size in [512, 64, 16, 4] {
+        let image = Image::new(Pattern::FaceLabels, size);
+        assert_eq!(image.sample(FACE_LABEL_BLANK_UV), PAPER, "at {size}");
+        let bottom_half = &image.pixels[image.pixels.len() / 2..];
+        assert!(bottom_half.chunks(4).all(|p| p == PAPER), "at {size}");
+    }
+}
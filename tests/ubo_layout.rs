@@ -0,0 +1,151 @@
+//! The uniform blocks' Rust mirrors against std140 offsets worked out from
+//! their GLSL declarations in common.glsl, without a shader compiler.
+
+use rust_vulkan::vulkan_app::{DrawConstants, Light, PerFrameUbo, PerViewUbo, MAX_LIGHTS};
+use std::mem::{offset_of, size_of};
+
+/// A std140 member type, as far as these blocks need.
+#[derive(Clone, Copy)]
+enum Std140 {
+    Scalar,
+    Vec4,
+    Mat4,
+    Struct(&'static [Std140]),
+    Array(&'static Std140, usize),
+}
+
+fn round_up(value: usize, alignment: usize) -> usize {
+    value.div_ceil(alignment) * alignment
+}
+
+impl Std140 {
+    fn alignment(self) -> usize {
+        match self {
+            Std140::Scalar => 4,
+            Std140::Vec4 | Std140::Mat4 => 16,
+            // Rules 9 and 4: structs and array elements round up to a vec4's.
+            Std140::Struct(members) => struct_alignment(members),
+            Std140::Array(element, _) => round_up(element.alignment(), 16),
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Std140::Scalar => 4,
+            Std140::Vec4 => 16,
+            Std140::Mat4 => 64,
+            Std140::Struct(members) => struct_size(members),
+            Std140::Array(element, count) => round_up(element.size(), self.alignment()) * count,
+        }
+    }
+}
+
+/// The last member's end, rounded up to the struct's alignment.
+fn struct_size(members: &[Std140]) -> usize {
+    let end = offsets(members).last().unwrap() + members.last().unwrap().size();
+    round_up(end, struct_alignment(members))
+}
+
+fn struct_alignment(members: &[Std140]) -> usize {
+    round_up(members.iter().map(|m| m.alignment()).max().unwrap_or(1), 16)
+}
+
+/// Each member's offset, placed one after another at its alignment.
+fn offsets(members: &[Std140]) -> Vec<usize> {
+    let mut next = 0;
+    members
+        .iter()
+        .map(|member| {
+            let offset = round_up(next, member.alignment());
+            next = offset + member.size();
+            offset
+        })
+        .collect()
+}
+
+const LIGHT: Std140 = Std140::Struct(&[Std140::Vec4, Std140::Vec4]);
+
+#[test]
+fn the_light_struct_matches_std140() {
+    assert_eq!(
+        [offset_of!(Light, direction), offset_of!(Light, color)].to_vec(),
+        offsets(&[Std140::Vec4, Std140::Vec4]),
+    );
+    assert_eq!(size_of::<Light>(), LIGHT.size());
+}
+
+#[test]
+fn the_per_frame_block_matches_std140() {
+    // cameraPosition, time, lightCount, ambient, lights[MAX_LIGHTS].
+    let members = [
+        Std140::Vec4,
+        Std140::Scalar,
+        Std140::Scalar,
+        Std140::Scalar,
+        Std140::Array(&LIGHT, MAX_LIGHTS),
+    ];
+    let expected = offsets(&members);
+    assert_eq!(
+        [
+            offset_of!(PerFrameUbo, camera_position),
+            offset_of!(PerFrameUbo, time),
+            offset_of!(PerFrameUbo, light_count),
+            offset_of!(PerFrameUbo, ambient),
+            offset_of!(PerFrameUbo, lights),
+        ]
+        .to_vec(),
+        expected,
+    );
+    assert_eq!(expected[4], 32, "the array skips to the next vec4 boundary");
+    assert_eq!(size_of::<PerFrameUbo>(), struct_size(&members));
+}
+
+#[test]
+fn light_array_elements_sit_at_the_std140_stride() {
+    let stride = LIGHT.size();
+    assert_eq!(size_of::<[Light; MAX_LIGHTS]>(), stride * MAX_LIGHTS);
+    let block = PerFrameUbo {
+        camera_position: [0.0; 4],
+        time: 0.0,
+        light_count: 0,
+        ambient: 0.0,
+        _padding: 0.0,
+        lights: [Light::default(); MAX_LIGHTS],
+    };
+    let base = &block as *const PerFrameUbo as usize;
+    for (i, light) in block.lights.iter().enumerate() {
+        let offset = light as *const Light as usize - base;
+        assert_eq!(offset, offset_of!(PerFrameUbo, lights) + i * stride);
+    }
+}
+
+#[test]
+fn the_per_view_block_matches_std140() {
+    // view, proj, viewport.
+    let members = [Std140::Mat4, Std140::Mat4, Std140::Vec4];
+    assert_eq!(
+        [
+            offset_of!(PerViewUbo, view),
+            offset_of!(PerViewUbo, proj),
+            offset_of!(PerViewUbo, viewport),
+        ]
+        .to_vec(),
+        offsets(&members),
+    );
+    assert_eq!(size_of::<PerViewUbo>(), struct_size(&members));
+}
+
+#[test]
+fn draw_constants_match_std140() {
+    // tint, model.
+    let members = [Std140::Vec4, Std140::Mat4];
+    assert_eq!(
+        [
+            offset_of!(DrawConstants, tint),
+            offset_of!(DrawConstants, model)
+        ]
+        .to_vec(),
+        offsets(&members),
+    );
+    assert_eq!(size_of::<DrawConstants>(), struct_size(&members));
+}
@@ -0,0 +1,72 @@
+//! Where the frame block, view blocks and per-draw slots sit in each
+//! swapchain image's uniform buffer.
+
+use ash::vk;
+use rust_vulkan::vulkan_app::{
+    align_up, draw_slot_offset, per_view_offset, uniform_buffer_size, DrawConstants, PerFrameUbo,
+    PerViewUbo, PER_FRAME_OFFSET, UNIFORM_BLOCK_ALIGNMENT, VIEW_COUNT,
+};
+use std::mem::size_of;
+
+const DRAW_CONSTANTS: vk::DeviceSize = size_of::<DrawConstants>() as vk::DeviceSize;
+
+#[test]
+fn sizes_round_up_to_the_alignment() {
+    let cases = [
+        (0, 256, 0),
+        (1, 1, 1),
+        (80, 1, 80),
+        (80, 64, 128),
+        (80, 256, 256),
+        (128, 64, 128),
+        (256, 256, 256),
+        (257, 256, 512),
+    ];
+    for (size, alignment, aligned) in cases {
+        assert_eq!(align_up(size, alignment), aligned, "{size} to {alignment}");
+    }
+}
+
+#[test]
+fn the_frame_and_view_blocks_do_not_overlap() {
+    let mut blocks = vec![(PER_FRAME_OFFSET, size_of::<PerFrameUbo>())];
+    for view in 0..VIEW_COUNT {
+        blocks.push((per_view_offset(view), size_of::<PerViewUbo>()));
+    }
+    for pair in blocks.windows(2) {
+        let ((offset, size), (next, _)) = (pair[0], pair[1]);
+        assert!(offset + size as vk::DeviceSize <= next);
+    }
+    for (offset, _) in blocks {
+        assert_eq!(offset % UNIFORM_BLOCK_ALIGNMENT, 0);
+    }
+}
+
+#[test]
+fn draw_slots_follow_the_device_alignment() {
+    // A 64-byte alignment packs two 80-byte slots where 256 fits one.
+    for (alignment, stride) in [(64, 128), (256, 256)] {
+        let slot_stride = align_up(DRAW_CONSTANTS, alignment);
+        assert_eq!(slot_stride, stride);
+        let first = draw_slot_offset(0, slot_stride);
+        assert_eq!(first, per_view_offset(VIEW_COUNT));
+        for slot in 0..8 {
+            let offset = draw_slot_offset(slot, slot_stride);
+            assert_eq!(offset % alignment, 0, "slot {slot} at {alignment}");
+            assert_eq!(offset, first + slot as vk::DeviceSize * stride);
+        }
+        assert!(slot_stride >= DRAW_CONSTANTS);
+    }
+}
+
+#[test]
+fn the_buffer_ends_after_its_last_slot() {
+    let no_slots = uniform_buffer_size(0, 256);
+    assert_eq!(no_slots, per_view_offset(VIEW_COUNT));
+    for (slots, stride) in [(1, 128), (64, 128), (64, 256), (1000, 256)] {
+        let size = uniform_buffer_size(slots, stride);
+        assert_eq!(size, no_slots + slots as vk::DeviceSize * stride);
+        let last = draw_slot_offset(slots - 1, stride);
+        assert!(last + DRAW_CONSTANTS <= size, "{slots} slots of {stride}");
+    }
+}
@@ -0,0 +1,19 @@
+//! Parsing `--gpu-for-window` mappings.
+
+use rust_vulkan::vulkan_app::{GpuSelector, WindowGpus};
+
+#[test]
+fn windows_map_to_gpus_by_index_or_name() {
+    let gpus = WindowGpus::parse("0:1,1:0,2:nvidia").unwrap();
+    assert_eq!(gpus.for_window(0), Some(&GpuSelector::Index(1)));
+    assert_eq!(gpus.for_window(1), Some(&GpuSelector::Index(0)));
+    assert_eq!(gpus.for_window(2), Some(&GpuSelector::parse("nvidia")));
+    assert_eq!(gpus.for_window(3), None);
+}
+
+#[test]
+fn malformed_mappings_are_refused() {
+    for text in ["", "1", "1:", "one:0", "0:1,0:2"] {
+        assert!(WindowGpus::parse(text).is_err(), "{:?} parsed", text);
+    }
+}